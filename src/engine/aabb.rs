@@ -1,4 +1,6 @@
-#[derive(Debug)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct AABB {
     pub min: nalgebra_glm::Vec3,
     pub max: nalgebra_glm::Vec3,
@@ -45,22 +47,4 @@ impl AABB {
         self.min.z = self.min.z.min(other.min.z);
         self.max.z = self.max.z.max(other.max.z);
     }
-
-    pub fn intersects(&self, other: &AABB) -> bool {
-        // Check for separation in the x-axis
-        if self.max.x < other.min.x || self.min.x > other.max.x {
-            return false;
-        }
-        // Check for separation in the y-axis
-        if self.max.y < other.min.y || self.min.y > other.max.y {
-            return false;
-        }
-        // Check for separation in the z-axis
-        if self.max.z < other.min.z || self.min.z > other.max.z {
-            return false;
-        }
-
-        // No separation found, the AABBs intersect
-        true
-    }
 }
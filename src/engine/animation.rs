@@ -0,0 +1,145 @@
+// Keyframe animation. A clip moves, turns and stretches a whole mesh over time, on top of where its entity is. Clips
+// are named, so that game code can ask for "walk" or "attack" without caring what the keyframes are.
+
+use specs::{Component, DenseVecStorage, Entities, Join, Read, ReadStorage, System, WriteStorage};
+
+use crate::App;
+
+use super::{
+    physics::PositionComponent, render3d::OpenGlResource, time::TimeResource, update_lod::lod_dt,
+};
+
+/// Where a mesh is moved, turned and stretched to, relative to its entity
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pose {
+    pub offset: nalgebra_glm::Vec3,
+    pub rotation: nalgebra_glm::Vec3, //< Radians about x, then y, then z
+    pub scale: nalgebra_glm::Vec3,
+}
+
+impl Pose {
+    pub const IDENTITY: Pose = Pose {
+        offset: nalgebra_glm::Vec3::new(0.0, 0.0, 0.0),
+        rotation: nalgebra_glm::Vec3::new(0.0, 0.0, 0.0),
+        scale: nalgebra_glm::Vec3::new(1.0, 1.0, 1.0),
+    };
+
+    pub fn lerp(&self, other: &Pose, t: f32) -> Pose {
+        Pose {
+            offset: nalgebra_glm::lerp(&self.offset, &other.offset, t),
+            rotation: nalgebra_glm::lerp(&self.rotation, &other.rotation, t),
+            scale: nalgebra_glm::lerp(&self.scale, &other.scale, t),
+        }
+    }
+
+    pub fn matrix(&self) -> nalgebra_glm::Mat4 {
+        let mut matrix = nalgebra_glm::translation(&self.offset);
+        matrix = nalgebra_glm::rotate_z(&matrix, self.rotation.z);
+        matrix = nalgebra_glm::rotate_y(&matrix, self.rotation.y);
+        matrix = nalgebra_glm::rotate_x(&matrix, self.rotation.x);
+        nalgebra_glm::scale(&matrix, &self.scale)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Keyframe {
+    pub time: f32, //< Seconds from the start of the clip
+    pub pose: Pose,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Clip {
+    pub name: &'static str,
+    pub keyframes: &'static [Keyframe], //< In order of time
+    pub looping: bool,                  //< Clips that don't loop hold their last pose
+}
+
+impl Clip {
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |keyframe| keyframe.time)
+    }
+
+    /// The pose `time` seconds into the clip, blended between the keyframes either side
+    pub fn sample(&self, time: f32) -> Pose {
+        let duration = self.duration();
+        let time = if self.looping && duration > 0.0 {
+            time.rem_euclid(duration)
+        } else {
+            time.min(duration)
+        };
+        let Some(next) = self.keyframes.iter().position(|k| k.time > time) else {
+            return self.keyframes.last().map_or(Pose::IDENTITY, |k| k.pose);
+        };
+        if next == 0 {
+            return self.keyframes[0].pose;
+        }
+        let (a, b) = (&self.keyframes[next - 1], &self.keyframes[next]);
+        a.pose.lerp(&b.pose, (time - a.time) / (b.time - a.time))
+    }
+}
+
+#[derive(Component)]
+#[storage(DenseVecStorage)]
+pub struct AnimationComponent {
+    pub clips: &'static [Clip],
+    pub playing: Option<&'static str>, //< Name of the clip being played, None holds still
+    pub time: f32,                     //< Seconds into the clip being played
+    pub pose: Pose, //< Where the clip has got to, which renderers draw the mesh at
+}
+
+impl AnimationComponent {
+    pub fn new(clips: &'static [Clip]) -> Self {
+        Self {
+            clips,
+            playing: None,
+            time: 0.0,
+            pose: Pose::IDENTITY,
+        }
+    }
+
+    /// Starts a clip from the beginning, unless it's already playing. Clips the entity doesn't have stop animation.
+    pub fn play(&mut self, name: &'static str) {
+        if self.playing == Some(name) {
+            return;
+        }
+        self.playing = self
+            .clips
+            .iter()
+            .any(|clip| clip.name == name)
+            .then_some(name);
+        self.time = 0.0;
+    }
+
+    fn clip(&self) -> Option<&Clip> {
+        let name = self.playing?;
+        self.clips.iter().find(|clip| clip.name == name)
+    }
+}
+
+/// Moves every animation along, and works out the pose it's in. Should run before rendering.
+pub struct AnimationSystem;
+impl<'a> System<'a> for AnimationSystem {
+    type SystemData = (
+        WriteStorage<'a, AnimationComponent>,
+        ReadStorage<'a, PositionComponent>,
+        Read<'a, TimeResource>,
+        Read<'a, OpenGlResource>,
+        Read<'a, App>,
+        Entities<'a>,
+    );
+
+    fn run(&mut self, (mut animations, positions, time, open_gl, app, entities): Self::SystemData) {
+        for (animation, position, entity) in (&mut animations, positions.maybe(), &entities).join()
+        {
+            let pos = position.map(|position| position.pos);
+            let Some(dt) = lod_dt(entity, pos, open_gl.camera.position, app.ticks, time.dt) else {
+                continue;
+            };
+            animation.time += dt;
+            // With nothing playing, whatever set the pose last keeps it
+            if let Some(clip) = animation.clip() {
+                animation.pose = clip.sample(animation.time);
+            }
+        }
+    }
+}
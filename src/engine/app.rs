@@ -1,11 +1,24 @@
 use std::cell::RefCell;
-use std::time::Instant;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use sdl2::controller::{Axis, Button, GameController};
 use sdl2::event::{Event, WindowEvent};
-use sdl2::keyboard::Scancode;
-use sdl2::sys::{SDL_GetPerformanceCounter, SDL_GetPerformanceFrequency};
-use sdl2::video::SwapInterval;
-use sdl2::Sdl;
+use sdl2::keyboard::{Mod, Scancode};
+use sdl2::video::{FullscreenType, SwapInterval};
+use sdl2::{GameControllerSubsystem, Sdl};
+
+use super::benchmark::detect_quality_preset;
+use super::error::EngineError;
+use super::render3d::take_draw_calls;
+use super::settings::{DisplaySettings, GraphicsSettings, Settings};
+use super::time::TICK_SECONDS;
+
+const TICK_MICROS: u128 = (TICK_SECONDS as f64 * 1_000_000.0) as u128;
+/// Every scancode SDL has, so that any key, even media keys past the usual 256, can be held and bound
+pub const NUM_SCANCODES: usize = sdl2::sys::SDL_Scancode::SDL_NUM_SCANCODES as usize;
+const SPIN_MARGIN: Duration = Duration::from_millis(2); //< The frame limiter spins instead of sleeping this close to the next frame
 
 #[derive(Clone)]
 pub struct App {
@@ -15,11 +28,15 @@ pub struct App {
 
     // Main loop stuff
     pub running: bool,
-    pub seconds: f32, //< How many seconds the program has been up
-    pub ticks: usize, //< How many ticks the program has been up
+    pub seconds: f32,      //< How many seconds the program has been up
+    pub ticks: usize,      //< How many ticks the program has been up
+    pub tick_alpha: f32, //< How far the frame being rendered is from the last tick to the next, in [0, 1)
+    pub fps: f32,        //< Frames rendered in the last second
+    pub tick_rate: f32,  //< Ticks run in the last second
+    pub draw_calls: usize, //< Made while rendering the last frame
 
     // User input state
-    pub keys: [bool; 256],
+    pub keys: [bool; NUM_SCANCODES],
     pub mouse_x: i32,
     pub mouse_y: i32,
     pub mouse_rel_x: i32,
@@ -27,23 +44,80 @@ pub struct App {
     pub mouse_left_down: bool,
     pub mouse_right_down: bool,
     pub mouse_wheel: f32,
+    pub buttons: [bool; 32], //< Gamepad buttons, indexed by sdl2::controller::Button
+    pub axes: [f32; 6],      //< Gamepad axes in [-1, 1], indexed by sdl2::controller::Axis
+
+    // Debug cursor mode, the cursor is free and the camera only turns while right mouse is held. Toggled with F2.
+    pub debug_cursor: bool,
+
+    // Whether the mouse is held in the middle of the window to look around with, or free to point at things. A scene
+    // starts with what frees_cursor says, and can ask for the other with capture_mouse and release_mouse. The request
+    // is queued, and applied on the next tick. The mouse is only held while the window has focus.
+    pub mouse_wanted: bool,
+    pub(crate) mouse_request: MouseRequest,
+    pub focused: bool,
+    pub mouse_captured: bool, //< Whether SDL has been told to hold the mouse, as of the last sync
+
+    pub fullscreen: bool, //< Toggled with Alt+Enter
+}
+
+/// A capture or release asked for by a scene or one of its systems during an update. Clones of App share it, so that
+/// a request made through the copy an island hands its systems still reaches the main loop.
+#[derive(Default, Clone)]
+pub(crate) struct MouseRequest(Arc<AtomicU8>);
+
+impl MouseRequest {
+    const NONE: u8 = 0;
+    const CAPTURE: u8 = 1;
+    const RELEASE: u8 = 2;
+
+    fn set(&self, wanted: bool) {
+        let request = if wanted { Self::CAPTURE } else { Self::RELEASE };
+        self.0.store(request, Ordering::Relaxed);
+    }
+
+    /// The last request, if there's been one since the last take
+    fn take(&self) -> Option<bool> {
+        match self.0.swap(Self::NONE, Ordering::Relaxed) {
+            Self::CAPTURE => Some(true),
+            Self::RELEASE => Some(false),
+            _ => None,
+        }
+    }
 }
 
+/// Makes the first scene, once the window and GL context are up
+pub type SceneInit<'a> = dyn Fn(&App) -> Result<Box<dyn Scene>, EngineError> + 'a;
+
 pub fn run(
     screen_width: i32,
     screen_height: i32,
     window_title: &'static str,
-    init: &dyn Fn(&App) -> RefCell<Box<dyn Scene>>,
+    debug_cursor: bool,
+    init: &SceneInit<'_>,
 ) -> Result<(), String> {
     let sdl_context = sdl2::init()?;
     let video_subsystem = sdl_context.video()?;
     let _audio_subsystem = sdl_context.audio()?;
+    let controller_subsystem = sdl_context.game_controller()?;
+    let mut controllers: Vec<GameController> = vec![]; //< Kept open, SDL stops sending events for closed pads
 
     let gl_attr = video_subsystem.gl_attr();
     gl_attr.set_context_profile(sdl2::video::GLProfile::Core);
     gl_attr.set_context_version(3, 3);
 
-    let window = video_subsystem
+    // MSAA has to be requested before the window is made, so a freshly detected preset only takes effect next launch
+    let settings = Settings::load();
+    if let Some(samples) = settings
+        .as_ref()
+        .map(|s| s.graphics.msaa_samples)
+        .filter(|&n| n > 0)
+    {
+        gl_attr.set_multisample_buffers(1);
+        gl_attr.set_multisample_samples(samples);
+    }
+
+    let mut window = video_subsystem
         .window(window_title, screen_width as u32, screen_height as u32)
         .resizable()
         .opengl()
@@ -55,26 +129,40 @@ pub fn run(
     let _gl =
         gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
 
-    window
-        .subsystem()
-        .gl_set_swap_interval(SwapInterval::VSync)
-        .unwrap();
+    let display_settings = settings
+        .as_ref()
+        .map(|s| s.display.clone())
+        .unwrap_or_default();
+    let swap_interval = if display_settings.vsync {
+        SwapInterval::VSync
+    } else {
+        SwapInterval::Immediate
+    };
+    if let Err(err) = window.subsystem().gl_set_swap_interval(swap_interval) {
+        println!("Couldn't set swap interval: {}", err);
+    }
 
-    unsafe {
-        gl::Enable(gl::DEPTH_TEST);
-        gl::DepthFunc(gl::LESS);
-        gl::Enable(gl::CULL_FACE);
-        gl::Enable(gl::MULTISAMPLE);
-        gl::Enable(gl::BLEND);
-        gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+    if settings.is_none() {
+        println!("No settings found, detecting graphics quality...");
+        let settings = Settings {
+            graphics: GraphicsSettings::from_preset(detect_quality_preset()),
+            display: display_settings.clone(),
+            ..Default::default()
+        };
+        if let Err(err) = settings.save() {
+            println!("Couldn't save settings: {}", err);
+        }
     }
 
+    init_gl_state();
+
+    let fullscreen = display_settings.fullscreen;
     let mut app = App {
         screen_width,
         screen_height,
         // sdl_context,
         running: true,
-        keys: [false; 256],
+        keys: [false; NUM_SCANCODES],
         mouse_x: 0,
         mouse_y: 0,
         mouse_rel_x: 0,
@@ -82,75 +170,177 @@ pub fn run(
         mouse_left_down: false,
         mouse_right_down: false,
         mouse_wheel: 0.0,
+        buttons: [false; 32],
+        axes: [0.0; 6],
+        debug_cursor,
+        mouse_wanted: false,
+        mouse_request: MouseRequest::default(),
+        focused: true,
+        mouse_captured: false,
+        fullscreen,
         seconds: 0.0,
         ticks: 0,
+        tick_alpha: 0.0,
+        fps: 0.0,
+        tick_rate: 0.0,
+        draw_calls: 0,
     };
 
-    let initial_scene = init(&app);
+    let initial_scene = init(&app)?;
     let mut scene_stack: Vec<RefCell<Box<dyn Scene>>> = vec![];
-    scene_stack.push(initial_scene);
+    scene_stack.push(RefCell::new(initial_scene));
 
     let time = Instant::now();
-    let mut start = time.elapsed().as_millis();
+    let mut fps_start = Instant::now();
     let mut current;
     let mut previous = 0;
     let mut lag = 0;
     let mut elapsed;
     let mut frames = 0;
-    const DELTA_T: u128 = 16;
+    let mut stats_ticks = 0;
+    let mut cursor_stale = true; //< Whether the scene on top is new, and its cursor needs setting up
     while app.running {
+        let frame_start = Instant::now();
         app.seconds = time.elapsed().as_secs_f32();
-        current = time.elapsed().as_millis();
+        current = time.elapsed().as_micros();
         elapsed = current - previous;
 
         previous = current;
         lag += elapsed;
 
-        let scene_stale = false;
-        while lag >= DELTA_T {
+        let mut scene_stale = false;
+        while lag >= TICK_MICROS {
             app.reset_input();
-            app.poll_input(&sdl_context);
-            sdl_context.mouse().warp_mouse_in_window(
-                &window,
-                app.screen_width / 2,
-                app.screen_height / 2,
-            );
-            sdl_context.mouse().set_relative_mouse_mode(true);
+            app.poll_input(&sdl_context, &controller_subsystem, &mut controllers);
+            apply_fullscreen(&mut window, &mut app);
+            // Menus free the cursor, to be clicked around with
+            if cursor_stale {
+                if scene_stack
+                    .last()
+                    .is_some_and(|scene_ref| scene_ref.borrow().frees_cursor())
+                {
+                    app.release_mouse();
+                } else {
+                    app.capture_mouse();
+                }
+                cursor_stale = false;
+            }
+            if let Some(wanted) = app.mouse_request.take() {
+                app.mouse_wanted = wanted;
+            }
+            app.sync_mouse(&sdl_context, &window);
 
-            if let Some(scene_ref) = scene_stack.last() {
-                scene_ref.borrow_mut().update(&app);
-                app.ticks += 1;
+            let command = match scene_stack.last() {
+                Some(scene_ref) => {
+                    let command = scene_ref.borrow_mut().update(&app);
+                    app.ticks += 1;
+                    command
+                }
+                None => SceneCommand::None,
+            };
+            let changed = !matches!(command, SceneCommand::None);
+            match command {
+                SceneCommand::None => {}
+                SceneCommand::Replace(scene) => {
+                    scene_stack.pop();
+                    scene_stack.push(RefCell::new(scene));
+                }
+                SceneCommand::Push(scene) => scene_stack.push(RefCell::new(scene)),
+                SceneCommand::Reset(scene) => {
+                    scene_stack.clear();
+                    scene_stack.push(RefCell::new(scene));
+                }
+                SceneCommand::Pop => {
+                    scene_stack.pop();
+                }
+                SceneCommand::Fail(err) => return Err(err.into()),
+            }
+            if changed {
+                // The new scene shouldn't have to catch up on time spent setting it up
+                scene_stale = true;
+                cursor_stale = true;
+                lag = 0;
+                previous = time.elapsed().as_micros();
             }
 
             if !scene_stale {
                 // if scene isn't stale, purge the scene
-                lag -= DELTA_T;
+                lag -= TICK_MICROS;
             } else {
                 break;
             }
         }
 
         if !scene_stale {
+            app.tick_alpha = lag as f32 / TICK_MICROS as f32;
             if let Some(scene_ref) = scene_stack.last() {
                 scene_ref.borrow_mut().render(&app);
                 frames += 1;
             }
+            app.draw_calls = take_draw_calls();
             window.gl_swap_window();
         }
 
-        let end = unsafe { SDL_GetPerformanceCounter() };
-        let freq = unsafe { SDL_GetPerformanceFrequency() };
-        let seconds = (end as f64 - (start as f64)) / (freq as f64);
-        if seconds > 5.0 {
-            println!("5 seconds;  fps: {}", frames / 5);
-            start = end as u128;
+        wait_for_next_frame(frame_start, &display_settings);
+
+        let stats_seconds = fps_start.elapsed().as_secs_f32();
+        if stats_seconds >= 1.0 {
+            app.fps = frames as f32 / stats_seconds;
+            app.tick_rate = (app.ticks - stats_ticks) as f32 / stats_seconds;
+            fps_start = Instant::now();
             frames = 0;
+            stats_ticks = app.ticks;
         }
     }
 
     Ok(())
 }
 
+/// Switches the window in or out of fullscreen, if the app has been toggled since. The window's size changing comes
+/// back as an event, which updates the screen size.
+fn apply_fullscreen(window: &mut sdl2::video::Window, app: &mut App) {
+    let wanted = if app.fullscreen {
+        FullscreenType::Desktop
+    } else {
+        FullscreenType::Off
+    };
+    if window.fullscreen_state() == wanted {
+        return;
+    }
+    if let Err(err) = window.set_fullscreen(wanted) {
+        println!("Couldn't change fullscreen: {}", err);
+        app.fullscreen = !app.fullscreen;
+    }
+}
+
+/// The GL state every scene expects to start from
+pub fn init_gl_state() {
+    unsafe {
+        gl::Enable(gl::DEPTH_TEST);
+        gl::DepthFunc(gl::LESS);
+        gl::Enable(gl::CULL_FACE);
+        gl::Enable(gl::MULTISAMPLE);
+        gl::Enable(gl::BLEND);
+        gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+    }
+}
+
+/// Holds the frame rate down to the cap, if there is one. Sleeping alone tends to oversleep by a millisecond or more,
+/// so it sleeps most of the way and spins for the rest.
+fn wait_for_next_frame(frame_start: Instant, display_settings: &DisplaySettings) {
+    let Some(fps_cap) = display_settings.fps_cap.filter(|&cap| cap > 0) else {
+        return;
+    };
+    let frame_end = frame_start + Duration::from_secs_f64(1.0 / fps_cap as f64);
+    let now = Instant::now();
+    if frame_end > now + SPIN_MARGIN {
+        std::thread::sleep(frame_end - now - SPIN_MARGIN);
+    }
+    while Instant::now() < frame_end {
+        std::hint::spin_loop();
+    }
+}
+
 impl App {
     fn reset_input(&mut self) {
         self.mouse_rel_x = 0;
@@ -158,7 +348,71 @@ impl App {
         self.mouse_wheel = 0.0;
     }
 
-    fn poll_input(&mut self, sdl_context: &Sdl) {
+    /// A copy with nothing held or moved, for when the player shouldn't be in control
+    pub fn without_input(&self) -> App {
+        App {
+            keys: [false; NUM_SCANCODES],
+            mouse_rel_x: 0,
+            mouse_rel_y: 0,
+            mouse_left_down: false,
+            mouse_right_down: false,
+            mouse_wheel: 0.0,
+            buttons: [false; 32],
+            axes: [0.0; 6],
+            ..self.clone()
+        }
+    }
+
+    /// Holds the mouse in the window to look around with, once the window has focus. Takes effect next tick.
+    pub fn capture_mouse(&self) {
+        self.mouse_request.set(true);
+    }
+
+    /// Frees the mouse, to point at things with. Takes effect next tick.
+    pub fn release_mouse(&self) {
+        self.mouse_request.set(false);
+    }
+
+    /// Tells SDL whether to hold the mouse, if that's changed. A held mouse is kept in the middle of the window.
+    fn sync_mouse(&mut self, sdl_context: &Sdl, window: &sdl2::video::Window) {
+        let capture = self.mouse_wanted && self.focused && !self.debug_cursor;
+        let mouse = sdl_context.mouse();
+        if capture != self.mouse_captured {
+            mouse.set_relative_mouse_mode(capture);
+            mouse.show_cursor(!capture);
+            self.mouse_captured = capture;
+        }
+        if capture {
+            mouse.warp_mouse_in_window(window, self.screen_width / 2, self.screen_height / 2);
+        }
+    }
+
+    /// Width over height of the window
+    pub fn aspect(&self) -> f32 {
+        self.screen_width as f32 / self.screen_height.max(1) as f32
+    }
+
+    /// Whether a gamepad button is held on any connected gamepad
+    pub fn button(&self, button: Button) -> bool {
+        self.buttons[button as usize]
+    }
+
+    /// A gamepad axis in [-1, 1], with a small dead zone so that resting sticks read as 0
+    pub fn axis(&self, axis: Axis) -> f32 {
+        let value = self.axes[axis as usize];
+        if value.abs() < 0.2 {
+            0.0
+        } else {
+            value
+        }
+    }
+
+    fn poll_input(
+        &mut self,
+        sdl_context: &Sdl,
+        controller_subsystem: &GameControllerSubsystem,
+        controllers: &mut Vec<GameController>,
+    ) {
         let mut event_queue = sdl_context.event_pump().unwrap();
         for event in event_queue.poll_iter() {
             match event {
@@ -171,8 +425,11 @@ impl App {
                 } => {
                     self.mouse_x = x;
                     self.mouse_y = y;
-                    self.mouse_rel_x = xrel;
-                    self.mouse_rel_y = yrel;
+                    // A free mouse doesn't look around, unless right mouse is held in debug cursor mode
+                    if self.mouse_captured || (self.debug_cursor && self.mouse_right_down) {
+                        self.mouse_rel_x = xrel;
+                        self.mouse_rel_y = yrel;
+                    }
                 }
 
                 Event::MouseButtonDown { mouse_btn, .. } => match mouse_btn {
@@ -191,19 +448,36 @@ impl App {
                     self.mouse_wheel = y as f32;
                 }
 
-                Event::Window { win_event, .. } => {
-                    if let WindowEvent::Resized(new_width, new_height) = win_event {
+                Event::Window { win_event, .. } => match win_event {
+                    // Size changes come from the window being resized, and from going in and out of fullscreen
+                    WindowEvent::SizeChanged(new_width, new_height) => {
                         self.screen_width = new_width;
                         self.screen_height = new_height;
                     }
-                }
+                    // The mouse is let go while another window has focus, and held again on coming back
+                    WindowEvent::FocusLost => self.focused = false,
+                    WindowEvent::FocusGained => self.focused = true,
+                    _ => {}
+                },
 
-                Event::KeyDown { scancode, .. } => match scancode {
+                Event::KeyDown {
+                    scancode,
+                    keymod,
+                    repeat,
+                    ..
+                } => match scancode {
                     Some(sc) => {
                         self.keys[sc as usize] = true;
                         if self.keys[Scancode::Escape as usize] {
                             self.running = false
                         }
+                        if sc == Scancode::F2 && !repeat {
+                            self.debug_cursor = !self.debug_cursor;
+                        }
+                        let alt = keymod.intersects(Mod::LALTMOD | Mod::RALTMOD);
+                        if sc == Scancode::Return && alt && !repeat {
+                            self.fullscreen = !self.fullscreen;
+                        }
                     }
                     None => {}
                 },
@@ -213,6 +487,30 @@ impl App {
                     None => {}
                 },
 
+                Event::ControllerDeviceAdded { which, .. } => {
+                    match controller_subsystem.open(which) {
+                        Ok(controller) => {
+                            println!("Gamepad connected: {}", controller.name());
+                            controllers.push(controller);
+                        }
+                        Err(err) => println!("Couldn't open gamepad: {}", err),
+                    }
+                }
+
+                Event::ControllerDeviceRemoved { which, .. } => {
+                    controllers.retain(|c| c.instance_id() != which);
+                    self.buttons = [false; 32];
+                    self.axes = [0.0; 6];
+                }
+
+                Event::ControllerButtonDown { button, .. } => self.buttons[button as usize] = true,
+
+                Event::ControllerButtonUp { button, .. } => self.buttons[button as usize] = false,
+
+                Event::ControllerAxisMotion { axis, value, .. } => {
+                    self.axes[axis as usize] = (value as f32 / i16::MAX as f32).max(-1.0);
+                }
+
                 _ => {}
             }
         }
@@ -227,7 +525,11 @@ impl Default for App {
             running: Default::default(),
             seconds: Default::default(),
             ticks: Default::default(),
-            keys: [false; 256],
+            tick_alpha: Default::default(),
+            fps: Default::default(),
+            tick_rate: Default::default(),
+            draw_calls: Default::default(),
+            keys: [false; NUM_SCANCODES],
             mouse_x: Default::default(),
             mouse_y: Default::default(),
             mouse_rel_x: Default::default(),
@@ -235,12 +537,70 @@ impl Default for App {
             mouse_left_down: Default::default(),
             mouse_right_down: Default::default(),
             mouse_wheel: Default::default(),
+            buttons: [false; 32],
+            axes: [0.0; 6],
+            debug_cursor: Default::default(),
+            mouse_wanted: Default::default(),
+            mouse_request: Default::default(),
+            focused: Default::default(),
+            mouse_captured: Default::default(),
+            fullscreen: Default::default(),
         }
     }
 }
 
+/// What a scene wants the app to do after it updates
+pub enum SceneCommand {
+    None,
+    Replace(Box<dyn Scene>), //< Swaps the current scene out for another one
+    Push(Box<dyn Scene>), //< Puts a scene on top of the current one, which stays underneath but stops updating
+    Reset(Box<dyn Scene>), //< Throws away every scene and starts over with this one
+    Pop,                  //< Throws away the current scene, going back to the one underneath
+    Fail(EngineError),    //< Quits, because a scene couldn't be set up
+}
+
+impl SceneCommand {
+    /// Pushes a scene, or quits if it couldn't be set up
+    pub fn push<S: Scene + 'static>(scene: Result<S, EngineError>) -> Self {
+        scene.map_or_else(SceneCommand::Fail, |scene| {
+            SceneCommand::Push(Box::new(scene))
+        })
+    }
+
+    /// Swaps to a scene, or quits if it couldn't be set up
+    pub fn replace<S: Scene + 'static>(scene: Result<S, EngineError>) -> Self {
+        scene.map_or_else(SceneCommand::Fail, |scene| {
+            SceneCommand::Replace(Box::new(scene))
+        })
+    }
+
+    /// Starts over with a scene, or quits if it couldn't be set up
+    pub fn reset<S: Scene + 'static>(scene: Result<S, EngineError>) -> Self {
+        scene.map_or_else(SceneCommand::Fail, |scene| {
+            SceneCommand::Reset(Box::new(scene))
+        })
+    }
+}
+
 pub trait Scene {
-    // TODO: Return a "command" enum so that scene's can affect App state
-    fn update(&mut self, app: &App);
+    fn update(&mut self, app: &App) -> SceneCommand;
     fn render(&mut self, app: &App);
+
+    /// Whether the cursor is shown and free to move while the scene is on top, rather than captured for looking around
+    fn frees_cursor(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mouse_requests_made_through_a_copy_reach_the_app() {
+        let app = App::default();
+        app.without_input().release_mouse();
+        assert_eq!(app.mouse_request.take(), Some(false));
+        assert_eq!(app.mouse_request.take(), None);
+    }
 }
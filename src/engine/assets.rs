@@ -0,0 +1,124 @@
+// Where the game's files are. Asset paths in the code are relative, like "res/pop.ogg", and are looked up under an
+// asset root found once at startup, so that the game runs from any working directory and once it's packaged.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+/// Names a folder to use as the asset root, over anywhere else
+const ASSET_ROOT_VAR: &str = "TREASURE_HUNT_ASSETS";
+/// An asset root is any folder with this in it
+pub const ASSET_DIR: &str = "res";
+/// Lists every asset in a packaged build with its size in bytes, so that a build with files missing or cut short is
+/// caught at startup rather than partway through a game. Running from the repo, there isn't one.
+pub const MANIFEST_PATH: &str = "res/manifest.txt";
+
+static ASSET_ROOT: OnceLock<PathBuf> = OnceLock::new();
+
+/// Places the asset root might be, best first
+fn candidate_roots() -> Vec<PathBuf> {
+    let mut candidates = vec![];
+    // Next to the executable, or in the Resources folder of a macOS app bundle
+    if let Some(exe_dir) = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+    {
+        candidates.push(exe_dir.join("../Resources"));
+        candidates.push(exe_dir);
+    }
+    // The crate, when run with cargo
+    if let Some(manifest_dir) = std::env::var_os("CARGO_MANIFEST_DIR") {
+        candidates.push(PathBuf::from(manifest_dir));
+    }
+    if let Ok(working_dir) = std::env::current_dir() {
+        candidates.push(working_dir);
+    }
+    candidates
+}
+
+/// Finds the asset root, for `asset_path` to look files up under. An asset root set with `TREASURE_HUNT_ASSETS` has
+/// to be right, rather than falling back to the usual places, so that a typo doesn't quietly load other files.
+pub fn init_asset_root() -> Result<&'static Path, String> {
+    if let Some(root) = ASSET_ROOT.get() {
+        return Ok(root);
+    }
+    let root = match std::env::var_os(ASSET_ROOT_VAR) {
+        Some(root) => {
+            let root = PathBuf::from(root);
+            if !root.join(ASSET_DIR).is_dir() {
+                return Err(format!(
+                    "{} is set to {}, but there's no {} folder in it",
+                    ASSET_ROOT_VAR,
+                    root.display(),
+                    ASSET_DIR
+                ));
+            }
+            root
+        }
+        None => {
+            let candidates = candidate_roots();
+            let found = candidates
+                .iter()
+                .find(|candidate| candidate.join(ASSET_DIR).is_dir());
+            let Some(found) = found else {
+                let searched: Vec<String> = candidates
+                    .iter()
+                    .map(|candidate| format!("  {}", candidate.join(ASSET_DIR).display()))
+                    .collect();
+                return Err(format!(
+                    "Couldn't find the game's {} folder. Looked in:\n{}\nSet {} to the folder it's in.",
+                    ASSET_DIR,
+                    searched.join("\n"),
+                    ASSET_ROOT_VAR
+                ));
+            };
+            found.clone()
+        }
+    };
+    verify_manifest(&root)?;
+    Ok(ASSET_ROOT.get_or_init(|| root))
+}
+
+/// Checks that every asset in the manifest is there and the right size, if there's a manifest
+fn verify_manifest(root: &Path) -> Result<(), String> {
+    let Ok(manifest) = std::fs::read_to_string(root.join(MANIFEST_PATH)) else {
+        return Ok(());
+    };
+    let mut problems = vec![];
+    for line in manifest.lines().filter(|line| !line.is_empty()) {
+        let Some((size, path)) = line.split_once('\t') else {
+            return Err(format!("Couldn't read {} line {:?}", MANIFEST_PATH, line));
+        };
+        let size: u64 = size
+            .parse()
+            .map_err(|_| format!("Couldn't read {} line {:?}", MANIFEST_PATH, line))?;
+        match std::fs::metadata(root.join(path)) {
+            Ok(metadata) if metadata.len() == size => {}
+            Ok(metadata) => problems.push(format!(
+                "  {} is {} bytes, should be {}",
+                path,
+                metadata.len(),
+                size
+            )),
+            Err(_) => problems.push(format!("  {} is missing", path)),
+        }
+    }
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "Some of the game's files in {} are missing or damaged, try getting the game again:\n{}",
+            root.display(),
+            problems.join("\n")
+        ))
+    }
+}
+
+/// Where a file given relative to the asset root really is. Paths are left as they are until the root has been found.
+pub fn asset_path(path: impl AsRef<Path>) -> PathBuf {
+    match ASSET_ROOT.get() {
+        Some(root) => root.join(path),
+        None => path.as_ref().to_path_buf(),
+    }
+}
@@ -1,14 +1,31 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
 
+use rand::Rng;
 use sdl2::mixer::{self, Chunk};
 
+use super::assets::asset_path;
+
 enum SoundCommand {
-    Play(String, i32),
+    Play(String, i32, f32, f32), //< File path, volume, pitch, muffle
     Quit,
 }
 
+/// How much a sound is allowed to randomly deviate each time it's played
+#[derive(Clone, Copy, Default)]
+pub struct SoundVariation {
+    pub pitch_jitter: f32, //< Pitch is scaled by a random factor in [1 - jitter, 1 + jitter]
+    pub volume_jitter: f32, //< Volume is scaled by a random factor in [1 - jitter, 1]
+}
+
 pub struct AudioManager {
     sender: std::sync::mpsc::Sender<SoundCommand>,
+    variations: HashMap<String, SoundVariation>,
+    muffle: f32,        //< [0, 1], how muffled sounds are, like when there's fog
+    underwater: bool, //< Whether the listener is underwater, which muffles everything as much as it can be
+    master_volume: i32, //< [0, 128], from the settings
 }
 
 impl AudioManager {
@@ -28,17 +45,27 @@ impl AudioManager {
             )
             .unwrap();
             sdl2::mixer::allocate_channels(4);
+            let (_, _, num_channels) = sdl2::mixer::query_spec().unwrap();
 
             // Create a thread-safe shared vector of 16 Chunks. `None` means they are not playing, `Some` means they are
             let chunks: Arc<Mutex<Vec<Option<Chunk>>>> =
                 Arc::new(Mutex::new((0..16).map(|_| None).collect()));
 
+            // Decoded samples for each file that has been played, so that files are only read once
+            let mut samples_cache: HashMap<String, Vec<i16>> = HashMap::new();
+
             // Pend on commands from the receiver
             for command in receiver {
                 AudioManager::clear_unused_channels(&chunks);
                 match command {
-                    SoundCommand::Play(file_path, volume) => {
-                        let sound_file = mixer::Chunk::from_file(&file_path).unwrap();
+                    SoundCommand::Play(file_path, volume, pitch, muffle) => {
+                        let samples = samples_cache
+                            .entry(file_path.clone())
+                            .or_insert_with(|| AudioManager::decode_file(&file_path));
+                        let mut samples = resample(samples, num_channels as usize, pitch);
+                        low_pass(&mut samples, num_channels as usize, muffle);
+                        let sound_file =
+                            Chunk::from_raw_buffer(samples.into_boxed_slice()).unwrap();
                         // Lock the `channels` mutex to get exclusive access to the channels vector
                         let mut chunks = chunks.lock().unwrap();
                         // Find the first available (non-None) channel
@@ -64,7 +91,30 @@ impl AudioManager {
             sdl2::mixer::close_audio();
         });
 
-        Self { sender }
+        Self::from_sender(sender)
+    }
+
+    /// An audio manager that plays nothing, for when there's no sound device, like when running headless
+    pub fn silent() -> Self {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            for command in receiver {
+                if let SoundCommand::Quit = command {
+                    break;
+                }
+            }
+        });
+        Self::from_sender(sender)
+    }
+
+    fn from_sender(sender: std::sync::mpsc::Sender<SoundCommand>) -> Self {
+        Self {
+            sender,
+            variations: HashMap::new(),
+            muffle: 0.0,
+            underwater: false,
+            master_volume: 128,
+        }
     }
 
     fn clear_unused_channels(chunks: &Arc<Mutex<Vec<Option<Chunk>>>>) {
@@ -76,12 +126,82 @@ impl AudioManager {
         }
     }
 
-    /// Plays a sound.
-    /// - file_path: relative to the crate directory
+    /// Loads a sound file and copies out its samples, already converted to the mixer's format
+    fn decode_file(file_path: &str) -> Vec<i16> {
+        let path = asset_path(file_path);
+        let chunk = mixer::Chunk::from_file(&path)
+            .unwrap_or_else(|err| panic!("Couldn't load {}: {}", path.display(), err));
+        unsafe {
+            let raw = &*chunk.raw;
+            std::slice::from_raw_parts(raw.abuf as *const i16, raw.alen as usize / 2).to_vec()
+        }
+    }
+
+    /// Sets the default random variation used whenever the given sound is played
+    pub fn set_variation(&mut self, file_path: &str, variation: SoundVariation) {
+        self.variations.insert(file_path.to_string(), variation);
+    }
+
+    /// Sets how loud every sound is, in [0, 128]. 128 plays sounds at the volume they're asked for.
+    pub fn set_master_volume(&mut self, volume: i32) {
+        self.master_volume = volume.clamp(0, 128);
+    }
+
+    /// Sets how muffled sounds played from now on are. 0 is clear, 1 is heavily muffled.
+    pub fn set_muffle(&mut self, muffle: f32) {
+        self.muffle = muffle.clamp(0.0, 1.0);
+    }
+
+    /// Sets whether the listener is underwater. Sounds played while they are are fully muffled, whatever the muffle.
+    pub fn set_underwater(&mut self, underwater: bool) {
+        self.underwater = underwater;
+    }
+
+    /// Plays a sound, with that sound's default variation applied.
+    /// - file_path: relative to the asset root
     /// - volume: [0, 128], anything above 128 is clipped to 128.
     pub fn play_sound(&self, file_path: String, volume: i32) {
+        let variation = self.variations.get(&file_path).copied().unwrap_or_default();
+        self.play_sound_varied(file_path, volume, variation);
+    }
+
+    /// Plays a sound with a random pitch and volume, ignoring the sound's default variation.
+    /// - file_path: relative to the asset root
+    /// - volume: [0, 128], anything above 128 is clipped to 128.
+    pub fn play_sound_varied(&self, file_path: String, volume: i32, variation: SoundVariation) {
+        let mut rng = rand::thread_rng();
+        let pitch = 1.0 + variation.pitch_jitter * rng.gen_range(-1.0..=1.0);
+        let volume = (volume as f32 * (1.0 - variation.volume_jitter * rng.gen::<f32>())) as i32;
+        self.send_play(file_path, volume, pitch, self.current_muffle());
+    }
+
+    /// Plays a sound at a set pitch, without variation. A pitch above 1.0 makes the sound higher and shorter.
+    /// - file_path: relative to the asset root
+    /// - volume: [0, 128], anything above 128 is clipped to 128.
+    pub fn play_sound_pitched(&self, file_path: String, volume: i32, pitch: f32) {
+        self.send_play(file_path, volume, pitch, self.current_muffle());
+    }
+
+    /// Plays a sound without variation or muffling, for sounds that should always cut through clearly.
+    /// - file_path: relative to the asset root
+    /// - volume: [0, 128], anything above 128 is clipped to 128.
+    pub fn play_sound_unmuffled(&self, file_path: String, volume: i32) {
+        self.send_play(file_path, volume, 1.0, 0.0);
+    }
+
+    fn current_muffle(&self) -> f32 {
+        if self.underwater {
+            1.0
+        } else {
+            self.muffle
+        }
+    }
+
+    fn send_play(&self, file_path: String, volume: i32, pitch: f32, muffle: f32) {
+        // Muffled sounds are quieter too, not just duller
+        let volume = (volume as f32 * (1.0 - 0.4 * muffle)) as i32 * self.master_volume / 128;
         self.sender
-            .send(SoundCommand::Play(file_path, volume))
+            .send(SoundCommand::Play(file_path, volume, pitch, muffle))
             .unwrap();
     }
 }
@@ -93,6 +213,44 @@ impl Drop for AudioManager {
     }
 }
 
+/// Resamples interleaved audio with linear interpolation. A pitch above 1.0 makes the sound higher and shorter.
+fn resample(samples: &[i16], num_channels: usize, pitch: f32) -> Vec<i16> {
+    if (pitch - 1.0).abs() < 0.001 || samples.len() < 2 * num_channels {
+        return samples.to_vec();
+    }
+
+    let num_frames = samples.len() / num_channels;
+    let new_num_frames = (num_frames as f32 / pitch) as usize;
+    let mut retval = Vec::with_capacity(new_num_frames * num_channels);
+    for frame in 0..new_num_frames {
+        let src = frame as f32 * pitch;
+        let src_frame = (src as usize).min(num_frames - 2);
+        let t = (src - src_frame as f32).min(1.0);
+        for channel in 0..num_channels {
+            let a = samples[src_frame * num_channels + channel] as f32;
+            let b = samples[(src_frame + 1) * num_channels + channel] as f32;
+            retval.push((a + t * (b - a)) as i16);
+        }
+    }
+    retval
+}
+
+/// Filters out high frequencies from interleaved audio, with a one-pole filter per channel. A muffle of 0 leaves the
+/// samples as they are.
+fn low_pass(samples: &mut [i16], num_channels: usize, muffle: f32) {
+    if muffle < 0.001 {
+        return;
+    }
+    let alpha = 1.0 - 0.85 * muffle;
+    let mut prev = vec![0.0; num_channels];
+    for frame in samples.chunks_mut(num_channels) {
+        for (sample, prev) in frame.iter_mut().zip(prev.iter_mut()) {
+            *prev += alpha * (*sample as f32 - *prev);
+            *sample = *prev as i16;
+        }
+    }
+}
+
 pub struct AudioResource {
     pub audio_mgr: AudioManager,
 }
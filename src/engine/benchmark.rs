@@ -0,0 +1,79 @@
+use std::time::Instant;
+
+use super::{
+    camera::{Camera, ProjectionKind},
+    objects::{create_program, Fbo, Texture},
+    render3d::MESH_UNIFORMS,
+    settings::QualityPreset,
+    water::create_water_mesh,
+};
+
+const BENCHMARK_WIDTH: i32 = 1280;
+const BENCHMARK_HEIGHT: i32 = 720;
+const BENCHMARK_SECONDS: f32 = 1.0;
+
+/// Renders a heavy scene to an offscreen target for about a second, and picks a quality preset based on how many
+/// frames got rendered. Needs a current OpenGL context.
+pub fn detect_quality_preset() -> QualityPreset {
+    let program = match create_program(
+        include_str!("../shaders/benchmark.vert"),
+        include_str!("../shaders/benchmark.frag"),
+        MESH_UNIFORMS,
+    ) {
+        Ok(program) => program,
+        Err(err) => {
+            println!("Couldn't run the quality benchmark, {}", err);
+            return QualityPreset::Medium;
+        }
+    };
+    // The water grid is the densest mesh around, it's a good stand-in for the terrain
+    let mesh = create_water_mesh();
+    let camera = Camera::new(
+        nalgebra_glm::vec3(0.0, -1.5, 0.8),
+        nalgebra_glm::vec3(0.0, 0.0, 0.0),
+        nalgebra_glm::vec3(0.0, 0.0, 1.0),
+        ProjectionKind::Perspective { fov: 0.9 },
+    );
+
+    let depth_buffer = Texture::new();
+    depth_buffer.load_depth_buffer(BENCHMARK_WIDTH, BENCHMARK_HEIGHT);
+    let color_buffer = Texture::new();
+    color_buffer.load_color_buffer(BENCHMARK_WIDTH, BENCHMARK_HEIGHT);
+    let fbo = Fbo::new();
+    fbo.bind();
+    depth_buffer.post_bind();
+    color_buffer.post_bind_color();
+
+    program.set();
+    let start = Instant::now();
+    let mut frames = 0;
+    while start.elapsed().as_secs_f32() < BENCHMARK_SECONDS {
+        unsafe {
+            gl::Viewport(0, 0, BENCHMARK_WIDTH, BENCHMARK_HEIGHT);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        for layer in 0..6 {
+            mesh.draw(
+                &program,
+                &camera,
+                nalgebra_glm::vec3(0.0, 0.0, -0.1 * layer as f32),
+                nalgebra_glm::vec3(2.0, 2.0, 1.0),
+            );
+        }
+        // Wait for the GPU to actually finish, otherwise we'd only be timing how fast commands get queued
+        unsafe { gl::Finish() }
+        frames += 1;
+    }
+    fbo.unbind();
+
+    let fps = frames as f32 / start.elapsed().as_secs_f32();
+    let preset = if fps >= 120.0 {
+        QualityPreset::High
+    } else if fps >= 45.0 {
+        QualityPreset::Medium
+    } else {
+        QualityPreset::Low
+    };
+    println!("Benchmark: {:.1} fps, picked {:?} quality", fps, preset);
+    preset
+}
@@ -18,12 +18,24 @@ impl Default for ProjectionKind {
     }
 }
 
-#[derive(Default)]
 pub struct Camera {
     pub position: nalgebra_glm::Vec3,
     pub lookat: nalgebra_glm::Vec3,
     pub up: nalgebra_glm::Vec3,
     pub projection_kind: ProjectionKind,
+    pub aspect: f32, //< Width over height of what's rendered to, for perspective projections
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            position: Default::default(),
+            lookat: Default::default(),
+            up: Default::default(),
+            projection_kind: Default::default(),
+            aspect: 1.0,
+        }
+    }
 }
 
 impl Camera {
@@ -38,6 +50,7 @@ impl Camera {
             lookat,
             up,
             projection_kind,
+            aspect: 1.0,
         }
     }
 
@@ -45,7 +58,7 @@ impl Camera {
         let view_matrix = nalgebra_glm::look_at(&self.position, &self.lookat, &self.up);
         let proj_matrix = match self.projection_kind {
             ProjectionKind::Perspective { fov } => {
-                nalgebra_glm::perspective(1.0, fov, 0.01, 9.296e+9)
+                nalgebra_glm::perspective(self.aspect, fov, 0.01, 9.296e+9)
             }
             ProjectionKind::Orthographic {
                 left,
@@ -59,6 +72,16 @@ impl Camera {
         (view_matrix, proj_matrix)
     }
 
+    /// Where a point in the world ends up on screen, in [-1, 1] on both axes. None if it's behind the camera.
+    pub fn world_to_screen(&self, pos: nalgebra_glm::Vec3) -> Option<nalgebra_glm::Vec2> {
+        let (view, proj) = self.gen_view_proj_matrices();
+        let clip = proj * view * nalgebra_glm::vec4(pos.x, pos.y, pos.z, 1.0);
+        if clip.w <= 0.0 {
+            return None;
+        }
+        Some(clip.xy() / clip.w)
+    }
+
     pub fn inv_proj_view(&self) -> nalgebra_glm::Mat4 {
         let (view, proj) = self.gen_view_proj_matrices();
         let proj_view = proj * view;
@@ -0,0 +1,86 @@
+// Camera paths for cutscenes. A path is a list of keyframes saying where the camera is and what it's looking at, and
+// the camera glides through them along a Catmull-Rom spline, so that it doesn't turn sharply at each keyframe.
+
+/// How the camera speeds up and slows down between two keyframes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Easing {
+    Linear,
+    EaseIn,    //< Starts slow
+    EaseOut,   //< Ends slow
+    EaseInOut, //< Starts and ends slow
+}
+
+impl Easing {
+    /// Remaps how far between two keyframes the camera is in time to how far it is along the path, both in [0, 1]
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => t * (2.0 - t),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CameraKeyframe {
+    pub time: f32, //< Seconds from the start of the path
+    pub position: nalgebra_glm::Vec3,
+    pub lookat: nalgebra_glm::Vec3,
+    pub easing: Easing, //< How the camera gets here from the keyframe before
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CameraPath {
+    keyframes: Vec<CameraKeyframe>, //< In order of time
+}
+
+impl CameraPath {
+    pub fn new(keyframes: Vec<CameraKeyframe>) -> Self {
+        Self { keyframes }
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |keyframe| keyframe.time)
+    }
+
+    /// Where the camera is and what it's looking at `time` seconds into the path. Holds the last keyframe once the
+    /// path is over.
+    pub fn sample(&self, time: f32) -> (nalgebra_glm::Vec3, nalgebra_glm::Vec3) {
+        let Some(next) = self.keyframes.iter().position(|k| k.time > time) else {
+            return self.keyframes.last().map_or(
+                (nalgebra_glm::Vec3::zeros(), nalgebra_glm::Vec3::zeros()),
+                |k| (k.position, k.lookat),
+            );
+        };
+        if next == 0 {
+            return (self.keyframes[0].position, self.keyframes[0].lookat);
+        }
+        // The keyframes either side of the segment shape the curve, the ends of the path are repeated
+        let (a, b) = (&self.keyframes[next - 1], &self.keyframes[next]);
+        let before = &self.keyframes[next.saturating_sub(2)];
+        let after = &self.keyframes[(next + 1).min(self.keyframes.len() - 1)];
+        let t = b.easing.apply((time - a.time) / (b.time - a.time));
+        (
+            catmull_rom(before.position, a.position, b.position, after.position, t),
+            catmull_rom(before.lookat, a.lookat, b.lookat, after.lookat, t),
+        )
+    }
+}
+
+/// The point `t` of the way from `p1` to `p2`, on a curve that also passes through `p0` and `p3`
+fn catmull_rom(
+    p0: nalgebra_glm::Vec3,
+    p1: nalgebra_glm::Vec3,
+    p2: nalgebra_glm::Vec3,
+    p3: nalgebra_glm::Vec3,
+    t: f32,
+) -> nalgebra_glm::Vec3 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    0.5 * (2.0 * p1
+        + (p2 - p0) * t
+        + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+        + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+}
@@ -0,0 +1,347 @@
+// Collision shapes, and how any two of them overlap. Shapes are placed relative to an entity's position, and every
+// pair of shapes can be tested and pushed apart, so that systems don't need to care what shapes they're given. A
+// spatial grid narrows down which pairs are worth testing.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::aabb::AABB;
+
+const EPSILON: f32 = 0.00001;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Shape {
+    Aabb(AABB),                            //< Offset from the entity's position
+    Cylinder { radius: f32, height: f32 }, //< Upright, standing on the entity's position
+    Sphere { radius: f32 },                //< Centered on the entity's position
+}
+
+impl Shape {
+    /// The box around the shape, when it's placed at `pos`
+    pub fn bounds(&self, pos: nalgebra_glm::Vec3) -> AABB {
+        match self {
+            Shape::Aabb(aabb) => aabb.translate(pos),
+            Shape::Cylinder { radius, height } => AABB::from_min_max(
+                pos - nalgebra_glm::vec3(*radius, *radius, 0.0),
+                pos + nalgebra_glm::vec3(*radius, *radius, *height),
+            ),
+            Shape::Sphere { radius } => {
+                AABB::from_min_max(pos.add_scalar(-radius), pos.add_scalar(*radius))
+            }
+        }
+    }
+
+    /// The point in the shape placed at `pos` that's closest to `point`. Points inside the shape are their own
+    /// closest point.
+    pub fn closest_point(
+        &self,
+        pos: nalgebra_glm::Vec3,
+        point: nalgebra_glm::Vec3,
+    ) -> nalgebra_glm::Vec3 {
+        match self {
+            Shape::Aabb(aabb) => {
+                let aabb = aabb.translate(pos);
+                nalgebra_glm::clamp_vec(&point, &aabb.min, &aabb.max)
+            }
+            Shape::Cylinder { radius, height } => {
+                let from_axis = (point - pos).xy();
+                let dist = nalgebra_glm::length(&from_axis);
+                let xy = if dist > *radius {
+                    pos.xy() + from_axis * (radius / dist)
+                } else {
+                    point.xy()
+                };
+                let z = point.z.clamp(pos.z, pos.z + height);
+                nalgebra_glm::vec3(xy.x, xy.y, z)
+            }
+            Shape::Sphere { radius } => {
+                let from_center = point - pos;
+                let dist = nalgebra_glm::length(&from_center);
+                if dist > *radius {
+                    pos + from_center * (radius / dist)
+                } else {
+                    point
+                }
+            }
+        }
+    }
+}
+
+/// Buckets things into square cells across the ground by their bounds, so that only things sharing a cell need testing
+/// against each other. Things are referred to by index, into whatever list the caller keeps.
+pub struct SpatialGrid {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+}
+
+impl SpatialGrid {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+        }
+    }
+
+    /// The cells a box covers, ignoring height
+    fn cells_covered(&self, bounds: &AABB) -> impl Iterator<Item = (i32, i32)> {
+        let min = (bounds.min.xy() / self.cell_size).map(|x| x.floor() as i32);
+        let max = (bounds.max.xy() / self.cell_size).map(|x| x.floor() as i32);
+        (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| (x, y)))
+    }
+
+    pub fn insert(&mut self, index: usize, bounds: &AABB) {
+        for cell in self.cells_covered(bounds).collect::<Vec<_>>() {
+            self.cells.entry(cell).or_default().push(index);
+        }
+    }
+
+    /// Everything sharing a cell with a box, each only once, in order of index
+    pub fn query(&self, bounds: &AABB) -> Vec<usize> {
+        let mut found: Vec<usize> = self
+            .cells_covered(bounds)
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+            .collect();
+        found.sort_unstable();
+        found.dedup();
+        found
+    }
+}
+
+/// The shortest move that takes `a` out of `b`, or None if they don't overlap
+pub fn penetration(
+    a: &Shape,
+    a_pos: nalgebra_glm::Vec3,
+    b: &Shape,
+    b_pos: nalgebra_glm::Vec3,
+) -> Option<nalgebra_glm::Vec3> {
+    // Nothing overlaps unless the boxes around them do, which is also a cheap way to skip far away pairs
+    let bounds_push = aabb_penetration(&a.bounds(a_pos), &b.bounds(b_pos))?;
+    match (a, b) {
+        (Shape::Aabb(_), Shape::Aabb(_)) => Some(bounds_push),
+        (Shape::Sphere { radius }, _) => sphere_penetration(a_pos, *radius, b, b_pos, bounds_push),
+        (_, Shape::Sphere { radius }) => {
+            sphere_penetration(b_pos, *radius, a, a_pos, -bounds_push).map(|push| -push)
+        }
+        (Shape::Cylinder { radius, .. }, _) => {
+            cylinder_penetration(a_pos, *radius, b, b_pos, bounds_push)
+        }
+        (_, Shape::Cylinder { radius, .. }) => {
+            cylinder_penetration(b_pos, *radius, a, a_pos, -bounds_push).map(|push| -push)
+        }
+    }
+}
+
+/// How far `a` has to move along one axis to leave `b`
+fn aabb_penetration(a: &AABB, b: &AABB) -> Option<nalgebra_glm::Vec3> {
+    let mut push = nalgebra_glm::Vec3::zeros();
+    let mut shortest = f32::MAX;
+    for axis in 0..3 {
+        let up = b.max[axis] - a.min[axis]; //< Moving a toward +axis
+        let down = a.max[axis] - b.min[axis]; //< Moving a toward -axis
+        if up <= 0.0 || down <= 0.0 {
+            return None;
+        }
+        let (dist, sign) = if up < down { (up, 1.0) } else { (down, -1.0) };
+        if dist < shortest {
+            shortest = dist;
+            push = nalgebra_glm::Vec3::zeros();
+            push[axis] = dist * sign;
+        }
+    }
+    Some(push)
+}
+
+/// A sphere against any shape
+fn sphere_penetration(
+    center: nalgebra_glm::Vec3,
+    radius: f32,
+    other: &Shape,
+    other_pos: nalgebra_glm::Vec3,
+    bounds_push: nalgebra_glm::Vec3,
+) -> Option<nalgebra_glm::Vec3> {
+    let from_other = center - other.closest_point(other_pos, center);
+    let dist = nalgebra_glm::length(&from_other);
+    if dist >= radius {
+        None
+    } else if dist > EPSILON {
+        Some(from_other * ((radius - dist) / dist))
+    } else {
+        // The center is inside the other shape, so there's no direction to go by. Leave the way the boxes would.
+        Some(bounds_push)
+    }
+}
+
+/// An upright cylinder against a box or another cylinder. Cylinders are always pushed out sideways, since they're
+/// mostly people and trees standing on uneven ground, where pushing up or down would fight with the terrain.
+fn cylinder_penetration(
+    pos: nalgebra_glm::Vec3,
+    radius: f32,
+    other: &Shape,
+    other_pos: nalgebra_glm::Vec3,
+    bounds_push: nalgebra_glm::Vec3,
+) -> Option<nalgebra_glm::Vec3> {
+    let axis_point = nalgebra_glm::vec3(pos.x, pos.y, other_pos.z);
+    let (closest, other_radius) = match other {
+        Shape::Cylinder {
+            radius: other_radius,
+            ..
+        } => (other_pos.xy(), *other_radius),
+        _ => (other.closest_point(other_pos, axis_point).xy(), 0.0),
+    };
+    let from_other = pos.xy() - closest;
+    let dist = nalgebra_glm::length(&from_other);
+    let overlap = radius + other_radius - dist;
+    if overlap <= 0.0 {
+        return None;
+    }
+    let push = if dist > EPSILON {
+        from_other * (overlap / dist)
+    } else {
+        // The axis is inside the other shape, so there's no direction to go by. Leave the way the boxes would, or
+        // any way at all if the boxes would go up or down.
+        let bounds_sideways = bounds_push.xy();
+        if bounds_sideways == nalgebra_glm::Vec2::zeros() {
+            nalgebra_glm::vec2(overlap, 0.0)
+        } else {
+            bounds_sideways
+        }
+    };
+    Some(nalgebra_glm::vec3(push.x, push.y, 0.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A unit box standing on its position
+    fn unit_box() -> Shape {
+        Shape::Aabb(AABB::from_min_max(
+            nalgebra_glm::vec3(-0.5, -0.5, 0.0),
+            nalgebra_glm::vec3(0.5, 0.5, 1.0),
+        ))
+    }
+
+    fn ball() -> Shape {
+        Shape::Sphere { radius: 0.5 }
+    }
+
+    fn post() -> Shape {
+        Shape::Cylinder {
+            radius: 0.5,
+            height: 1.0,
+        }
+    }
+
+    fn bounds(min: [f32; 2], max: [f32; 2]) -> AABB {
+        AABB::from_min_max(
+            nalgebra_glm::vec3(min[0], min[1], 0.0),
+            nalgebra_glm::vec3(max[0], max[1], 1.0),
+        )
+    }
+
+    /// How far `a` is pushed out of `b` when it's `x` along from it, with their middles level at `z`
+    fn push_at(a: &Shape, b: &Shape, x: f32, z: f32) -> Option<nalgebra_glm::Vec3> {
+        let a_z = if matches!(a, Shape::Sphere { .. }) {
+            z
+        } else {
+            0.0
+        };
+        let b_z = if matches!(b, Shape::Sphere { .. }) {
+            z
+        } else {
+            0.0
+        };
+        penetration(
+            a,
+            nalgebra_glm::vec3(x, 0.0, a_z),
+            b,
+            nalgebra_glm::vec3(0.0, 0.0, b_z),
+        )
+    }
+
+    /// Checks every pair of shapes a quarter overlapping, just touching, and apart along x
+    fn check_pair(a: Shape, b: Shape) {
+        let push = push_at(&a, &b, 0.75, 0.5).expect("overlapping shapes should be pushed apart");
+        assert!(
+            nalgebra_glm::distance(&push, &nalgebra_glm::vec3(0.25, 0.0, 0.0)) < 1e-5,
+            "{:?} against {:?} pushed by {:?}",
+            a,
+            b,
+            push
+        );
+        assert_eq!(push_at(&a, &b, 1.0, 0.5), None, "{:?} touching {:?}", a, b);
+        assert_eq!(
+            push_at(&a, &b, 2.0, 0.5),
+            None,
+            "{:?} apart from {:?}",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn boxes_overlap() {
+        check_pair(unit_box(), unit_box());
+    }
+
+    #[test]
+    fn spheres_overlap() {
+        check_pair(ball(), ball());
+        check_pair(ball(), unit_box());
+        check_pair(unit_box(), ball());
+    }
+
+    #[test]
+    fn cylinders_overlap() {
+        check_pair(post(), post());
+        check_pair(post(), unit_box());
+        check_pair(unit_box(), post());
+        check_pair(post(), ball());
+        check_pair(ball(), post());
+    }
+
+    #[test]
+    fn cylinders_are_only_pushed_sideways() {
+        // Sunk a little into the top of the box, with its axis inside it, which the boxes alone would push up out of
+        let push = cylinder_penetration(
+            nalgebra_glm::vec3(0.0, 0.0, 0.9),
+            0.5,
+            &unit_box(),
+            nalgebra_glm::zero(),
+            nalgebra_glm::vec3(0.0, 0.0, 0.1),
+        )
+        .unwrap();
+        assert_eq!(push.z, 0.0);
+        assert!(nalgebra_glm::length(&push) > 0.0);
+
+        // Off to one side of another cylinder's axis, it's pushed straight away from it
+        let push = cylinder_penetration(
+            nalgebra_glm::vec3(0.0, 0.25, 0.0),
+            0.5,
+            &post(),
+            nalgebra_glm::zero(),
+            nalgebra_glm::vec3(0.0, 0.75, 0.0),
+        )
+        .unwrap();
+        assert_eq!(push, nalgebra_glm::vec3(0.0, 0.75, 0.0));
+    }
+
+    #[test]
+    fn grid_finds_things_sharing_a_cell() {
+        let mut grid = SpatialGrid::new(1.0);
+        grid.insert(0, &bounds([0.2, 0.2], [0.8, 0.8]));
+        grid.insert(1, &bounds([1.5, 0.2], [2.5, 0.8])); //< Across two cells
+        grid.insert(2, &bounds([-3.0, -3.0], [-2.5, -2.5]));
+
+        assert_eq!(grid.query(&bounds([0.5, 0.5], [1.6, 0.6])), vec![0, 1]);
+        // Each thing only once, however many of its cells the box covers
+        assert_eq!(grid.query(&bounds([1.2, 0.5], [2.8, 0.6])), vec![1]);
+        // Cells below zero round down, not toward zero
+        assert_eq!(grid.query(&bounds([-2.9, -2.9], [-2.1, -2.1])), vec![2]);
+        assert_eq!(grid.query(&bounds([-0.5, -0.5], [-0.1, -0.1])), vec![]);
+        assert_eq!(grid.query(&bounds([5.0, 5.0], [6.0, 6.0])), vec![]);
+    }
+}
@@ -0,0 +1,44 @@
+// What can go wrong loading the game's assets and setting up OpenGL. Each error says which asset it was about, so that
+// a missing or broken file is reported by name rather than as a panic somewhere in the renderer.
+
+use std::fmt;
+
+#[derive(Debug)]
+pub enum EngineError {
+    Texture { path: String, reason: String }, //< A picture that's missing or couldn't be decoded
+    Mesh(String),                             //< A model that couldn't be parsed
+    Shader { stage: &'static str, log: String }, //< A shader that didn't compile, with the driver's log
+    Link(String),    //< Shaders that didn't link into a program, with the driver's log
+    Uniform(String), //< A uniform the program doesn't have, or that was optimized out
+    Font { path: String, reason: String },
+    Sdl(String), //< A part of SDL that couldn't be started
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EngineError::Texture { path, reason } => {
+                write!(f, "couldn't load texture {}: {}", path, reason)
+            }
+            EngineError::Mesh(reason) => write!(f, "couldn't load mesh: {}", reason),
+            EngineError::Shader { stage, log } => {
+                write!(f, "couldn't compile {} shader: {}", stage, log.trim())
+            }
+            EngineError::Link(log) => write!(f, "couldn't link shader program: {}", log.trim()),
+            EngineError::Uniform(name) => write!(f, "no uniform named {}", name),
+            EngineError::Font { path, reason } => {
+                write!(f, "couldn't load font {}: {}", path, reason)
+            }
+            EngineError::Sdl(reason) => write!(f, "couldn't start SDL: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for EngineError {}
+
+/// Most of the game reports errors as strings
+impl From<EngineError> for String {
+    fn from(err: EngineError) -> Self {
+        err.to_string()
+    }
+}
@@ -0,0 +1,246 @@
+// Golden image testing. Scenes are rendered offscreen and compared with images stored in the repo, so that a change
+// to terrain generation, lighting or shadows that changes what the game looks like doesn't go unnoticed. Drivers
+// don't all render exactly alike, so small differences are let through.
+
+use std::path::PathBuf;
+
+use image::RgbaImage;
+
+use super::{
+    app::init_gl_state,
+    assets::asset_path,
+    objects::{Fbo, Texture},
+};
+
+pub const GOLDEN_DIR: &str = "res/golden";
+const CHANNEL_TOLERANCE: u8 = 12; //< Channel differences up to this are put down to driver noise
+const MAX_BAD_PIXELS: f32 = 0.01; //< Fraction of pixels allowed to differ by more than the tolerance
+
+/// How far apart two images are
+#[derive(Debug, PartialEq)]
+pub struct ImageDiff {
+    pub bad_pixels: f32, //< Fraction of pixels with a channel differing by more than the tolerance
+    pub max_channel_diff: u8,
+}
+
+impl ImageDiff {
+    pub fn passes(&self) -> bool {
+        self.bad_pixels <= MAX_BAD_PIXELS
+    }
+}
+
+/// Compares two images pixel by pixel. They have to be the same size.
+pub fn compare_images(expected: &RgbaImage, actual: &RgbaImage) -> Result<ImageDiff, String> {
+    if expected.dimensions() != actual.dimensions() {
+        return Err(format!(
+            "expected a {:?} image, got {:?}",
+            expected.dimensions(),
+            actual.dimensions()
+        ));
+    }
+    let mut bad_pixels = 0;
+    let mut max_channel_diff = 0;
+    for (a, b) in expected.pixels().zip(actual.pixels()) {
+        let diff =
+            a.0.iter()
+                .zip(b.0.iter())
+                .map(|(a, b)| a.abs_diff(*b))
+                .max()
+                .unwrap();
+        max_channel_diff = max_channel_diff.max(diff);
+        if diff > CHANNEL_TOLERANCE {
+            bad_pixels += 1;
+        }
+    }
+    Ok(ImageDiff {
+        bad_pixels: bad_pixels as f32 / expected.pixels().len() as f32,
+        max_channel_diff,
+    })
+}
+
+/// What happened when a render was checked against its golden image
+#[derive(Debug)]
+pub enum GoldenResult {
+    Matched(ImageDiff),
+    Mismatched(ImageDiff), //< The render is saved next to the golden image, as `<name>.actual.png`
+    Blessed,               //< The render was saved as the new golden image
+}
+
+/// Where the golden image called `name` is kept
+pub fn golden_path(name: &str) -> PathBuf {
+    asset_path(GOLDEN_DIR).join(format!("{}.png", name))
+}
+
+/// Checks a render against the golden image called `name`, or replaces the golden image with it when blessing. A
+/// missing golden image is an error, so that a gallery that was never blessed can't pass by checking nothing.
+pub fn check_golden(name: &str, actual: &RgbaImage, bless: bool) -> Result<GoldenResult, String> {
+    let golden_dir = asset_path(GOLDEN_DIR);
+    let path = golden_path(name);
+    if bless {
+        std::fs::create_dir_all(&golden_dir).map_err(|e| e.to_string())?;
+        actual.save(&path).map_err(|e| e.to_string())?;
+        return Ok(GoldenResult::Blessed);
+    }
+    if !path.exists() {
+        return Err(format!(
+            "no golden image at {}, bless with --bless-golden",
+            path.display()
+        ));
+    }
+
+    let expected = image::open(&path).map_err(|e| e.to_string())?.to_rgba8();
+    let diff = compare_images(&expected, actual)?;
+    if diff.passes() {
+        Ok(GoldenResult::Matched(diff))
+    } else {
+        let actual_path = golden_dir.join(format!("{}.actual.png", name));
+        actual.save(actual_path).map_err(|e| e.to_string())?;
+        Ok(GoldenResult::Mismatched(diff))
+    }
+}
+
+/// A color and depth buffer to render into instead of the screen
+pub struct OffscreenTarget {
+    fbo: Fbo,
+    _depth_buffer: Texture,
+    _color_buffer: Texture,
+    width: i32,
+    height: i32,
+}
+
+impl OffscreenTarget {
+    pub fn new(width: i32, height: i32) -> Self {
+        let depth_buffer = Texture::new();
+        depth_buffer.load_depth_buffer(width, height);
+        let color_buffer = Texture::new();
+        color_buffer.load_color_buffer(width, height);
+        let fbo = Fbo::new();
+        fbo.bind();
+        depth_buffer.post_bind();
+        color_buffer.post_bind_color();
+        fbo.unbind();
+        Self {
+            fbo,
+            _depth_buffer: depth_buffer,
+            _color_buffer: color_buffer,
+            width,
+            height,
+        }
+    }
+
+    pub fn bind(&self) {
+        self.fbo.bind();
+    }
+
+    /// Reads back what was rendered, top row first
+    pub fn read_pixels(&self) -> RgbaImage {
+        self.fbo.bind();
+        let image = read_pixels(self.width, self.height);
+        self.fbo.unbind();
+        image
+    }
+}
+
+/// Reads back the bottom left of whatever framebuffer is bound, top row first. With none bound, that's the frame
+/// that's about to be shown.
+pub fn read_pixels(width: i32, height: i32) -> RgbaImage {
+    let mut pixels = vec![0u8; (width * height * 4) as usize];
+    unsafe {
+        gl::Finish();
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl::ReadPixels(
+            0,
+            0,
+            width,
+            height,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            pixels.as_mut_ptr() as *mut gl::types::GLvoid,
+        );
+    }
+    // GL's first row is the bottom of the image
+    let image = RgbaImage::from_raw(width as u32, height as u32, pixels).unwrap();
+    image::imageops::flip_vertical(&image)
+}
+
+/// Runs `f` with a current OpenGL context, in a hidden window, for rendering without showing anything
+pub fn with_offscreen_context<T>(f: impl FnOnce() -> T) -> Result<T, String> {
+    let sdl_context = sdl2::init()?;
+    let video_subsystem = sdl_context.video()?;
+    // Island scenes play sounds
+    let _audio_subsystem = sdl_context.audio()?;
+
+    let gl_attr = video_subsystem.gl_attr();
+    gl_attr.set_context_profile(sdl2::video::GLProfile::Core);
+    gl_attr.set_context_version(3, 3);
+
+    let window = video_subsystem
+        .window("Treasure Hunt golden images", 64, 64)
+        .hidden()
+        .opengl()
+        .build()
+        .map_err(|e| e.to_string())?;
+    let _gl_context = window.gl_create_context()?;
+    gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
+    init_gl_state();
+
+    Ok(f())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid(width: u32, height: u32, color: [u8; 4]) -> RgbaImage {
+        RgbaImage::from_pixel(width, height, image::Rgba(color))
+    }
+
+    #[test]
+    fn identical_images_match() {
+        let image = solid(8, 8, [10, 20, 30, 255]);
+        let diff = compare_images(&image, &image).unwrap();
+        assert_eq!(
+            diff,
+            ImageDiff {
+                bad_pixels: 0.0,
+                max_channel_diff: 0,
+            }
+        );
+        assert!(diff.passes());
+    }
+
+    #[test]
+    fn small_differences_are_tolerated() {
+        let expected = solid(8, 8, [100, 100, 100, 255]);
+        let actual = solid(8, 8, [100 + CHANNEL_TOLERANCE, 100, 100, 255]);
+        let diff = compare_images(&expected, &actual).unwrap();
+        assert_eq!(diff.max_channel_diff, CHANNEL_TOLERANCE);
+        assert!(diff.passes());
+    }
+
+    #[test]
+    fn a_changed_region_fails() {
+        let expected = solid(10, 10, [0, 0, 0, 255]);
+        let mut actual = expected.clone();
+        for x in 0..10 {
+            actual.put_pixel(x, 0, image::Rgba([255, 0, 0, 255]));
+        }
+        let diff = compare_images(&expected, &actual).unwrap();
+        assert_eq!(diff.bad_pixels, 0.1);
+        assert_eq!(diff.max_channel_diff, 255);
+        assert!(!diff.passes());
+    }
+
+    #[test]
+    fn a_missing_golden_image_is_an_error() {
+        let actual = solid(8, 8, [0, 0, 0, 255]);
+        assert!(check_golden("not_a_golden_image", &actual, false).is_err());
+    }
+
+    #[test]
+    fn different_sizes_are_an_error() {
+        let expected = solid(8, 8, [0, 0, 0, 255]);
+        let actual = solid(4, 8, [0, 0, 0, 255]);
+        assert!(compare_images(&expected, &actual).is_err());
+    }
+}
@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+
+use specs::{Read, System};
+
+use crate::App;
+
+use super::{
+    objects::{Program, Vao, Vbo},
+    render3d::{count_draw_call, OpenGlResource},
+    settings::GraphicsSettings,
+    sky::SkyResource,
+};
+
+/// Floats per tuft in the instance buffer: position, height, color, and whether it's a flower
+pub const GRASS_INSTANCE_STRIDE: usize = 8;
+/// Set on the grass program each frame
+pub const GRASS_UNIFORMS: &[&str] = &[
+    "u_resolution",
+    "u_view_matrix",
+    "u_proj_matrix",
+    "u_camera_pos",
+    "u_fade_distance",
+    "u_time",
+    "u_sun_dir",
+    "u_sun_color",
+    "u_moon_color",
+    "u_fog_color",
+    "u_fog_density",
+];
+
+/// The tufts of grass and flowers scattered over one patch of ground, in their own instance buffer. Tufts are in a
+/// random order, so that drawing only the first few of them thins the grass out evenly.
+pub struct GrassPatch {
+    vao: Vao,
+    _instance_vbo: Vbo, //< Only kept so the buffer lives as long as the vao
+    count: usize,
+}
+
+impl GrassPatch {
+    fn new(corner_vbo: &Vbo, instances: &[f32]) -> Self {
+        let vao = Vao::gen();
+        let instance_vbo = Vbo::gen();
+        unsafe {
+            gl::BindVertexArray(vao.id);
+
+            corner_vbo.bind();
+            gl::EnableVertexAttribArray(0);
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, 0, std::ptr::null());
+
+            // Per tuft position and height, then color and whether it's a flower
+            instance_vbo.set_dynamic(instances);
+            let stride = (GRASS_INSTANCE_STRIDE * std::mem::size_of::<f32>()) as i32;
+            gl::EnableVertexAttribArray(1);
+            gl::VertexAttribPointer(1, 4, gl::FLOAT, gl::FALSE, stride, std::ptr::null());
+            gl::VertexAttribDivisor(1, 1);
+            gl::EnableVertexAttribArray(2);
+            gl::VertexAttribPointer(
+                2,
+                4,
+                gl::FLOAT,
+                gl::FALSE,
+                stride,
+                (4 * std::mem::size_of::<f32>()) as *const _,
+            );
+            gl::VertexAttribDivisor(2, 1);
+
+            gl::BindVertexArray(0);
+        }
+        Self {
+            vao,
+            _instance_vbo: instance_vbo,
+            count: instances.len() / GRASS_INSTANCE_STRIDE,
+        }
+    }
+}
+
+/// The grass patches near the camera, by whatever cell the scene scatters them over
+#[derive(Default)]
+pub struct GrassResource {
+    pub program: Program,
+    corner_vbo: Vbo,
+    pub patches: HashMap<(i32, i32), GrassPatch>,
+    pub fade_distance: f32, //< Tufts shrink away to nothing by this far from the camera
+}
+
+impl GrassResource {
+    pub fn new(program: Program, fade_distance: f32) -> Self {
+        let corner_vbo = Vbo::gen();
+        // One quad standing on its bottom edge, turned to face the camera in the vertex shader
+        corner_vbo.set(&vec![-0.5, 0.0, 0.5, 0.0, -0.5, 1.0, 0.5, 1.0]);
+        Self {
+            program,
+            corner_vbo,
+            patches: HashMap::new(),
+            fade_distance,
+        }
+    }
+
+    pub fn add_patch(&mut self, cell: (i32, i32), instances: &[f32]) {
+        let patch = GrassPatch::new(&self.corner_vbo, instances);
+        self.patches.insert(cell, patch);
+    }
+}
+
+/// Draws the grass patches, with one instanced draw call each. Should run after the opaque 3D pass.
+pub struct GrassRenderSystem;
+impl<'a> System<'a> for GrassRenderSystem {
+    type SystemData = (
+        Read<'a, App>,
+        Read<'a, OpenGlResource>,
+        Read<'a, GrassResource>,
+        Read<'a, SkyResource>,
+        Read<'a, GraphicsSettings>,
+    );
+
+    fn run(&mut self, (app, open_gl, grass, sky, settings): Self::SystemData) {
+        if grass.patches.is_empty() || settings.grass_density <= 0.0 {
+            return;
+        }
+
+        let program = &grass.program;
+        program.set();
+        let (view_matrix, proj_matrix) = open_gl.camera.gen_view_proj_matrices();
+        let camera = open_gl.camera.position;
+        let uniform = |name: &str| program.uniform(name).id;
+        unsafe {
+            gl::Uniform2f(
+                uniform("u_resolution"),
+                app.screen_width as f32,
+                app.screen_height as f32,
+            );
+            gl::UniformMatrix4fv(
+                uniform("u_view_matrix"),
+                1,
+                gl::FALSE,
+                &view_matrix.columns(0, 4)[0],
+            );
+            gl::UniformMatrix4fv(
+                uniform("u_proj_matrix"),
+                1,
+                gl::FALSE,
+                &proj_matrix.columns(0, 4)[0],
+            );
+            gl::Uniform3f(uniform("u_camera_pos"), camera.x, camera.y, camera.z);
+            gl::Uniform1f(uniform("u_fade_distance"), grass.fade_distance);
+            gl::Uniform1f(uniform("u_time"), app.seconds);
+            gl::Uniform3f(
+                uniform("u_sun_dir"),
+                sky.sun_dir.x,
+                sky.sun_dir.y,
+                sky.sun_dir.z,
+            );
+            gl::Uniform3f(
+                uniform("u_sun_color"),
+                sky.sun_color.x,
+                sky.sun_color.y,
+                sky.sun_color.z,
+            );
+            gl::Uniform3f(
+                uniform("u_moon_color"),
+                sky.moon_color.x,
+                sky.moon_color.y,
+                sky.moon_color.z,
+            );
+            gl::Uniform3f(
+                uniform("u_fog_color"),
+                sky.horizon_color.x,
+                sky.horizon_color.y,
+                sky.horizon_color.z,
+            );
+            gl::Uniform1f(uniform("u_fog_density"), sky.fog_density);
+
+            // Both sides of a tuft can be seen as it sways
+            gl::Disable(gl::CULL_FACE);
+            for patch in grass.patches.values() {
+                let count = (patch.count as f32 * settings.grass_density.min(1.0)) as i32;
+                if count == 0 {
+                    continue;
+                }
+                gl::BindVertexArray(patch.vao.id);
+                count_draw_call();
+                gl::DrawArraysInstanced(gl::TRIANGLE_STRIP, 0, 4, count);
+            }
+            gl::Enable(gl::CULL_FACE);
+            gl::BindVertexArray(0);
+        }
+    }
+}
@@ -0,0 +1,189 @@
+// Running the game without a window or a GPU, for simulating it in tests. GL is loaded with functions that do
+// nothing, so that everything that makes meshes, textures and programs still runs, and nothing is drawn.
+
+use std::{
+    ffi::c_void,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use gl::types::{
+    GLbitfield, GLboolean, GLchar, GLenum, GLfloat, GLint, GLsizei, GLsizeiptr, GLuint, GLuint64,
+};
+
+/// Ids handed out by the functions that create GL objects. Starts at 1, since 0 is "no object" to GL.
+static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+
+fn next_id() -> GLuint {
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Declares GL functions that do nothing, each with the signature the `gl` crate calls it with, and lists them by
+/// their GL name for the loader
+macro_rules! null_functions {
+    ($($name:ident($($ty:ty),*) $(-> $ret:ty = $val:expr)?;)*) => {
+        #[allow(non_snake_case)]
+        mod null {
+            use super::*;
+            $(
+                pub extern "system" fn $name($(_: $ty),*) $(-> $ret)? {
+                    $($val)?
+                }
+            )*
+        }
+
+        const NULL_FUNCTIONS: &[(&str, *const c_void)] = &[
+            $((concat!("gl", stringify!($name)), null::$name as *const c_void),)*
+        ];
+    };
+}
+
+null_functions! {
+    ActiveTexture(GLenum);
+    AttachShader(GLuint, GLuint);
+    BeginQuery(GLenum, GLuint);
+    BindBuffer(GLenum, GLuint);
+    BindFramebuffer(GLenum, GLuint);
+    BindTexture(GLenum, GLuint);
+    BindVertexArray(GLuint);
+    BlendFunc(GLenum, GLenum);
+    BufferData(GLenum, GLsizeiptr, *const c_void, GLenum);
+    CheckFramebufferStatus(GLenum) -> GLenum = gl::FRAMEBUFFER_COMPLETE;
+    Clear(GLbitfield);
+    ClearColor(GLfloat, GLfloat, GLfloat, GLfloat);
+    CompileShader(GLuint);
+    CullFace(GLenum);
+    DeleteBuffers(GLsizei, *const GLuint);
+    DeleteProgram(GLuint);
+    DeleteQueries(GLsizei, *const GLuint);
+    DeleteShader(GLuint);
+    DeleteTextures(GLsizei, *const GLuint);
+    DeleteVertexArrays(GLsizei, *const GLuint);
+    DepthFunc(GLenum);
+    DepthMask(GLboolean);
+    DetachShader(GLuint, GLuint);
+    Disable(GLenum);
+    DrawArraysInstanced(GLenum, GLint, GLsizei, GLsizei);
+    DrawBuffer(GLenum);
+    DrawElements(GLenum, GLsizei, GLenum, *const c_void);
+    Enable(GLenum);
+    EnableVertexAttribArray(GLuint);
+    EndQuery(GLenum);
+    Finish();
+    FramebufferTexture2D(GLenum, GLenum, GLenum, GLuint, GLint);
+    GenerateMipmap(GLenum);
+    GetUniformLocation(GLuint, *const GLchar) -> GLint = 0;
+    LinkProgram(GLuint);
+    PixelStorei(GLenum, GLint);
+    ReadBuffer(GLenum);
+    ReadPixels(GLint, GLint, GLsizei, GLsizei, GLenum, GLenum, *mut c_void);
+    ShaderSource(GLuint, GLsizei, *const *const GLchar, *const GLint);
+    TexImage2D(GLenum, GLint, GLint, GLsizei, GLsizei, GLint, GLenum, GLenum, *const c_void);
+    TexParameterfv(GLenum, GLenum, *const GLfloat);
+    TexParameteri(GLenum, GLenum, GLint);
+    Uniform1f(GLint, GLfloat);
+    Uniform1fv(GLint, GLsizei, *const GLfloat);
+    Uniform1i(GLint, GLint);
+    Uniform2f(GLint, GLfloat, GLfloat);
+    Uniform3f(GLint, GLfloat, GLfloat, GLfloat);
+    Uniform3fv(GLint, GLsizei, *const GLfloat);
+    UniformMatrix4fv(GLint, GLsizei, GLboolean, *const GLfloat);
+    VertexAttribDivisor(GLuint, GLuint);
+    VertexAttribPointer(GLuint, GLint, GLenum, GLboolean, GLsizei, *const c_void);
+    Viewport(GLint, GLint, GLsizei, GLsizei);
+}
+
+// The functions that hand things back through pointers fill them in, the way a driver that can't fail would
+
+extern "system" fn create_object() -> GLuint {
+    next_id()
+}
+
+extern "system" fn create_shader(_kind: GLenum) -> GLuint {
+    next_id()
+}
+
+extern "system" fn gen_objects(n: GLsizei, ids: *mut GLuint) {
+    for i in 0..n.max(0) as usize {
+        unsafe { *ids.add(i) = next_id() };
+    }
+}
+
+/// Every shader compiles and every program links, with nothing in their logs
+extern "system" fn get_object_iv(_object: GLuint, pname: GLenum, params: *mut GLint) {
+    let value = match pname {
+        gl::COMPILE_STATUS | gl::LINK_STATUS => gl::TRUE as GLint,
+        _ => 0,
+    };
+    unsafe { *params = value };
+}
+
+extern "system" fn get_info_log(
+    _object: GLuint,
+    size: GLsizei,
+    length: *mut GLsizei,
+    log: *mut GLchar,
+) {
+    unsafe {
+        if !length.is_null() {
+            *length = 0;
+        }
+        if size > 0 {
+            *log = 0;
+        }
+    }
+}
+
+extern "system" fn get_integer_v(_pname: GLenum, data: *mut GLint) {
+    unsafe { *data = 0 };
+}
+
+/// Queries are always ready, and always took no time
+extern "system" fn get_query_object_uiv(_id: GLuint, pname: GLenum, params: *mut GLuint) {
+    let value = match pname {
+        gl::QUERY_RESULT_AVAILABLE => gl::TRUE as GLuint,
+        _ => 0,
+    };
+    unsafe { *params = value };
+}
+
+extern "system" fn get_query_object_ui64v(_id: GLuint, _pname: GLenum, params: *mut GLuint64) {
+    unsafe { *params = 0 };
+}
+
+/// Points every GL function the engine uses at one that does nothing, with the same signature. Functions that create
+/// objects hand out fresh ids, and shaders always compile. Functions the engine doesn't use are left unloaded, so that
+/// calling one panics rather than doing something undefined.
+///
+/// Must not be called once a real GL context is in use, since it replaces the functions for the whole program.
+pub fn load_null_gl() {
+    gl::load_with(|name| match name {
+        "glCreateProgram" => create_object as *const c_void,
+        "glCreateShader" => create_shader as *const c_void,
+        "glGenBuffers" | "glGenFramebuffers" | "glGenQueries" | "glGenTextures"
+        | "glGenVertexArrays" => gen_objects as *const c_void,
+        "glGetShaderiv" | "glGetProgramiv" => get_object_iv as *const c_void,
+        "glGetShaderInfoLog" | "glGetProgramInfoLog" => get_info_log as *const c_void,
+        "glGetIntegerv" => get_integer_v as *const c_void,
+        "glGetQueryObjectuiv" => get_query_object_uiv as *const c_void,
+        "glGetQueryObjectui64v" => get_query_object_ui64v as *const c_void,
+        _ => NULL_FUNCTIONS
+            .iter()
+            .find(|(null_name, _)| *null_name == name)
+            .map_or(std::ptr::null(), |(_, function)| *function),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::objects::create_program;
+
+    #[test]
+    fn null_gl_compiles_shaders_and_hands_out_ids() {
+        load_null_gl();
+        assert!(create_program("void main() {}", "void main() {}", &["u_tint"]).is_ok());
+        let mut ids = [0; 3];
+        unsafe { gl::GenBuffers(3, ids.as_mut_ptr()) };
+        assert!(ids[0] != 0 && ids[0] != ids[1] && ids[1] != ids[2]);
+    }
+}
@@ -0,0 +1,228 @@
+// Named actions, and the keys and buttons bound to them. Systems ask whether an action is held rather than checking
+// keys themselves, so that the controls can be rebound in the settings file.
+
+use std::collections::{BTreeMap, HashMap};
+
+use sdl2::{
+    controller::{Axis, Button},
+    keyboard::Scancode,
+};
+use serde::{Deserialize, Serialize};
+
+use super::app::App;
+
+const TRIGGER_THRESHOLD: f32 = 0.5; //< How far a gamepad trigger has to be pulled to count as held
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+pub enum Action {
+    MoveForward,
+    MoveBack,
+    MoveLeft,
+    MoveRight,
+    Jump,
+    Sprint,
+    Crouch,
+    Fire,
+    Interact,
+    Reload,
+    Torch,
+    BuyAmmo,
+    Options,
+    Journal,
+    Map,
+    RerollTreasure, //< At the trader, trades the nearest far off chest for one closer by
+    BuyParrot,      //< At the trader
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum MouseButton {
+    Left,
+    Right,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Trigger {
+    Left,
+    Right,
+}
+
+/// Something that can be pressed. Keys and gamepad buttons go by their SDL names, like "Left Shift" or "leftstick".
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Binding {
+    Key(String),
+    Mouse(MouseButton),
+    Pad(String),
+    Trigger(Trigger),
+}
+
+/// The bindings for each action, as written in the settings file. Actions left out keep their default bindings.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ControlSettings {
+    pub bindings: BTreeMap<Action, Vec<Binding>>,
+}
+
+impl Default for ControlSettings {
+    fn default() -> Self {
+        let key = |name: &str| Binding::Key(name.to_string());
+        let pad = |name: &str| Binding::Pad(name.to_string());
+        Self {
+            bindings: BTreeMap::from([
+                (Action::MoveForward, vec![key("W")]),
+                (Action::MoveBack, vec![key("S")]),
+                (Action::MoveLeft, vec![key("A")]),
+                (Action::MoveRight, vec![key("D")]),
+                (Action::Jump, vec![key("Space"), pad("a")]),
+                (Action::Sprint, vec![key("Left Shift"), pad("leftstick")]),
+                (Action::Crouch, vec![key("Left Ctrl"), pad("rightstick")]),
+                (
+                    Action::Fire,
+                    vec![
+                        Binding::Mouse(MouseButton::Left),
+                        Binding::Trigger(Trigger::Right),
+                    ],
+                ),
+                (Action::Interact, vec![key("E"), pad("x")]),
+                (Action::Reload, vec![key("R"), pad("b")]),
+                (Action::Torch, vec![key("F"), pad("dpup")]),
+                (Action::BuyAmmo, vec![key("B"), pad("dpdown")]),
+                (Action::Options, vec![key("Tab"), pad("back")]),
+                (Action::Journal, vec![key("J"), pad("dpleft")]),
+                (Action::Map, vec![key("M"), pad("y")]),
+                // Every gamepad button is taken already, so rerolling is only on the keyboard unless it's rebound
+                (Action::RerollTreasure, vec![key("T")]),
+                (Action::BuyParrot, vec![key("P"), pad("dpright")]),
+            ]),
+        }
+    }
+}
+
+/// A binding looked up in SDL's tables
+#[derive(Clone, Copy, Debug)]
+enum Input {
+    Key(Scancode),
+    Mouse(MouseButton),
+    Pad(Button),
+    Trigger(Axis),
+}
+
+impl Input {
+    fn resolve(binding: &Binding) -> Result<Self, String> {
+        match binding {
+            Binding::Key(name) => Scancode::from_name(name)
+                .map(Input::Key)
+                .ok_or_else(|| format!("no key named {:?}", name)),
+            Binding::Mouse(button) => Ok(Input::Mouse(*button)),
+            Binding::Pad(name) => Button::from_string(name)
+                .map(Input::Pad)
+                .ok_or_else(|| format!("no gamepad button named {:?}", name)),
+            Binding::Trigger(Trigger::Left) => Ok(Input::Trigger(Axis::TriggerLeft)),
+            Binding::Trigger(Trigger::Right) => Ok(Input::Trigger(Axis::TriggerRight)),
+        }
+    }
+
+    fn held(&self, app: &App) -> bool {
+        match self {
+            Input::Key(scancode) => app.keys[*scancode as usize],
+            Input::Mouse(MouseButton::Left) => app.mouse_left_down,
+            Input::Mouse(MouseButton::Right) => app.mouse_right_down,
+            Input::Pad(button) => app.button(*button),
+            Input::Trigger(axis) => app.axis(*axis) > TRIGGER_THRESHOLD,
+        }
+    }
+
+    fn name(&self) -> String {
+        match self {
+            Input::Key(scancode) => scancode.name().to_string(),
+            Input::Mouse(MouseButton::Left) => "Left Click".to_string(),
+            Input::Mouse(MouseButton::Right) => "Right Click".to_string(),
+            Input::Pad(button) => button.string(),
+            Input::Trigger(Axis::TriggerLeft) => "Left Trigger".to_string(),
+            Input::Trigger(_) => "Right Trigger".to_string(),
+        }
+    }
+}
+
+/// The controls, ready to check against the input state
+#[derive(Clone)]
+pub struct InputMap {
+    bindings: HashMap<Action, Vec<Input>>,
+}
+
+impl InputMap {
+    /// Looks up the bindings in the settings over the defaults. Bindings that don't name a real key or button are
+    /// reported and left out.
+    pub fn new(controls: &ControlSettings) -> Self {
+        let mut merged = ControlSettings::default().bindings;
+        merged.extend(controls.bindings.clone());
+        let bindings = merged
+            .into_iter()
+            .map(|(action, bindings)| {
+                let inputs = bindings
+                    .iter()
+                    .filter_map(|binding| match Input::resolve(binding) {
+                        Ok(input) => Some(input),
+                        Err(err) => {
+                            println!("Couldn't bind {:?}: {}", action, err);
+                            None
+                        }
+                    })
+                    .collect();
+                (action, inputs)
+            })
+            .collect();
+        Self { bindings }
+    }
+
+    /// Whether anything bound to the action is held
+    pub fn held(&self, app: &App, action: Action) -> bool {
+        self.bindings
+            .get(&action)
+            .is_some_and(|inputs| inputs.iter().any(|input| input.held(app)))
+    }
+
+    /// What to tell the player to press for an action, like "E". Only the first binding is named.
+    pub fn name(&self, action: Action) -> String {
+        self.bindings
+            .get(&action)
+            .and_then(|inputs| inputs.first())
+            .map_or_else(|| format!("{:?}", action), |input| input.name())
+    }
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        Self::new(&ControlSettings::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_bindings_dont_clash() {
+        let mut bound = HashMap::new();
+        for (action, bindings) in ControlSettings::default().bindings {
+            for binding in bindings {
+                if let Some(other) = bound.insert(format!("{:?}", binding), action) {
+                    panic!(
+                        "{:?} is bound to both {:?} and {:?}",
+                        binding, other, action
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn keys_past_the_first_256_can_be_bound() {
+        let input = InputMap {
+            bindings: HashMap::from([(Action::Torch, vec![Input::Key(Scancode::Sleep)])]),
+        };
+        let mut app = App::default();
+        assert!(!input.held(&app, Action::Torch));
+        app.keys[Scancode::Sleep as usize] = true;
+        assert!(input.held(&app, Action::Torch));
+    }
+}
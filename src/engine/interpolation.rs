@@ -0,0 +1,117 @@
+// Smooths out motion between fixed updates. Ticks come every 16ms, but frames come whenever the display wants them, so
+// things are drawn partway between where they were last tick and where they are now, by `App::tick_alpha`.
+
+use specs::{prelude::*, Component};
+
+use super::{
+    physics::{PositionComponent, VelocityComponent},
+    render3d::OpenGlResource,
+};
+
+/// Anything that moves further than this in a tick was put there rather than moved there, and isn't interpolated
+const SNAP_DISTANCE: f32 = 1.0;
+
+/// Where a moving entity was at the start of the tick
+#[derive(Component)]
+#[storage(DenseVecStorage)]
+pub struct PreviousPositionComponent {
+    pub pos: nalgebra_glm::Vec3,
+}
+
+/// Where the camera was at the start of the tick. None until the first tick.
+#[derive(Default)]
+pub struct PreviousCameraResource {
+    pub position_lookat: Option<(nalgebra_glm::Vec3, nalgebra_glm::Vec3)>,
+}
+
+/// Between `previous` and `current`, or `current` if the two are too far apart
+pub fn interpolate(
+    previous: nalgebra_glm::Vec3,
+    current: nalgebra_glm::Vec3,
+    alpha: f32,
+) -> nalgebra_glm::Vec3 {
+    if nalgebra_glm::distance(&previous, &current) > SNAP_DISTANCE {
+        current
+    } else {
+        nalgebra_glm::lerp(&previous, &current, alpha)
+    }
+}
+
+/// Remembers where moving entities and the camera are before the tick moves them. Add before anything that moves
+/// things.
+pub struct RecordPreviousSystem;
+impl<'a> System<'a> for RecordPreviousSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, PositionComponent>,
+        ReadStorage<'a, VelocityComponent>,
+        WriteStorage<'a, PreviousPositionComponent>,
+        Read<'a, OpenGlResource>,
+        Write<'a, PreviousCameraResource>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, positions, velocities, mut previous, open_gl, mut previous_camera): Self::SystemData,
+    ) {
+        // Things that have stopped moving for good are drawn where they are
+        let stopped: Vec<Entity> = (&entities, &previous, !&velocities)
+            .join()
+            .map(|(entity, _, _)| entity)
+            .collect();
+        for entity in stopped {
+            previous.remove(entity);
+        }
+        for (entity, position, _) in (&entities, &positions, &velocities).join() {
+            previous
+                .insert(entity, PreviousPositionComponent { pos: position.pos })
+                .unwrap();
+        }
+        previous_camera.position_lookat = Some((open_gl.camera.position, open_gl.camera.lookat));
+    }
+}
+
+/// Moves the camera to where it is `alpha` of the way through the tick, for drawing a frame. Returns where the tick
+/// left it, for `restore_camera` to put it back after.
+pub fn interpolate_camera(world: &World, alpha: f32) -> (nalgebra_glm::Vec3, nalgebra_glm::Vec3) {
+    let mut open_gl = world.write_resource::<OpenGlResource>();
+    let camera = &mut open_gl.camera;
+    let current = (camera.position, camera.lookat);
+    if let Some((position, lookat)) = world
+        .read_resource::<PreviousCameraResource>()
+        .position_lookat
+    {
+        camera.position = interpolate(position, current.0, alpha);
+        camera.lookat = interpolate(lookat, current.1, alpha);
+    }
+    current
+}
+
+/// Puts the camera back where the tick left it, so that the next tick doesn't start from an in-between position
+pub fn restore_camera(world: &World, (position, lookat): (nalgebra_glm::Vec3, nalgebra_glm::Vec3)) {
+    let mut open_gl = world.write_resource::<OpenGlResource>();
+    open_gl.camera.position = position;
+    open_gl.camera.lookat = lookat;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interpolates_between_ticks() {
+        let previous = nalgebra_glm::vec3(0.0, 0.0, 0.0);
+        let current = nalgebra_glm::vec3(0.1, 0.0, 0.0);
+        assert_eq!(
+            interpolate(previous, current, 0.5),
+            nalgebra_glm::vec3(0.05, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn teleports_snap() {
+        let previous = nalgebra_glm::vec3(0.0, 0.0, 0.0);
+        let current = nalgebra_glm::vec3(5.0, 0.0, 0.0);
+        assert_eq!(interpolate(previous, current, 0.5), current);
+    }
+}
@@ -1,11 +1,35 @@
 pub(crate) mod aabb;
+pub(crate) mod animation;
 pub(crate) mod app;
+pub(crate) mod assets;
 pub(crate) mod audio;
+pub(crate) mod benchmark;
 pub(crate) mod camera;
+pub(crate) mod cinematic;
+pub(crate) mod collision;
+pub(crate) mod error;
 pub(crate) mod frustrum;
+pub(crate) mod golden;
+pub(crate) mod grass;
+pub(crate) mod headless;
+pub(crate) mod input;
+pub(crate) mod interpolation;
 pub(crate) mod objects;
+pub(crate) mod package;
+pub(crate) mod particles;
 pub(crate) mod perlin;
 pub(crate) mod physics;
+pub(crate) mod profiling;
+pub(crate) mod raycast;
+pub(crate) mod registry;
 pub(crate) mod render3d;
+pub(crate) mod settings;
 pub(crate) mod shadow_map;
+pub(crate) mod sky;
+pub(crate) mod snapshot;
 pub(crate) mod text;
+pub(crate) mod time;
+pub(crate) mod ui_button;
+pub(crate) mod ui_nav;
+pub(crate) mod update_lod;
+pub(crate) mod water;
@@ -1,9 +1,11 @@
 // Put OpenGL Objects here
 
 use std::{
+    collections::HashMap,
     ffi::{CStr, CString},
     path::Path,
     ptr::{null, null_mut},
+    sync::Arc,
 };
 
 use gl::{
@@ -13,13 +15,15 @@ use gl::{
 
 use image::{EncodableLayout, ImageError};
 
+use super::{assets::asset_path, error::EngineError};
+
 // An OpenGL Shader
 pub struct Shader {
     id: GLuint,
 }
 
 impl Shader {
-    pub fn from_source(source: &CStr, kind: GLenum) -> Result<Self, String> {
+    pub fn from_source(source: &CStr, kind: GLenum) -> Result<Self, EngineError> {
         let id = unsafe { gl::CreateShader(kind) };
 
         unsafe {
@@ -43,7 +47,13 @@ impl Shader {
                 gl::GetShaderInfoLog(id, len, null_mut(), error.as_ptr() as *mut GLchar);
             }
 
-            return Err(error.to_string_lossy().into_owned());
+            unsafe {
+                gl::DeleteShader(id);
+            }
+            return Err(EngineError::Shader {
+                stage: stage_name(kind),
+                log: error.to_string_lossy().into_owned(),
+            });
         }
 
         Ok(Shader { id })
@@ -65,10 +75,11 @@ impl Drop for Shader {
 #[derive(Default)]
 pub struct Program {
     id: GLuint,
+    uniforms: HashMap<&'static str, GLint>, //< Looked up once, when the program is built
 }
 
 impl Program {
-    fn from_shaders(shaders: &[Shader]) -> Result<Self, String> {
+    fn from_shaders(shaders: &[Shader]) -> Result<Self, EngineError> {
         let id = unsafe { gl::CreateProgram() };
 
         for shader in shaders {
@@ -99,7 +110,10 @@ impl Program {
                 gl::GetProgramInfoLog(id, len, null_mut(), error.as_ptr() as *mut GLchar);
             }
 
-            return Err(error.to_string_lossy().into_owned());
+            unsafe {
+                gl::DeleteProgram(id);
+            }
+            return Err(EngineError::Link(error.to_string_lossy().into_owned()));
         }
 
         for shader in shaders {
@@ -108,7 +122,23 @@ impl Program {
             }
         }
 
-        Ok(Program { id })
+        Ok(Program {
+            id,
+            uniforms: HashMap::new(),
+        })
+    }
+
+    /// Where a uniform listed when the program was built is. Setting an unlisted one does nothing.
+    pub fn uniform(&self, name: &str) -> Uniform {
+        let id = self.uniforms.get(name).copied();
+        debug_assert!(
+            id.is_some(),
+            "{} wasn't listed when its program was built",
+            name
+        );
+        Uniform {
+            id: id.unwrap_or(-1),
+        }
     }
 
     pub fn set(&self) {
@@ -136,28 +166,43 @@ fn create_whitespace_cstring_with_len(len: usize) -> CString {
     unsafe { CString::from_vec_unchecked(buffer) }
 }
 
+fn stage_name(kind: GLenum) -> &'static str {
+    match kind {
+        gl::VERTEX_SHADER => "vertex",
+        gl::FRAGMENT_SHADER => "fragment",
+        _ => "unknown",
+    }
+}
+
+/// Compiles a shader's source, which can't have any nul bytes in it
+fn compile(source: &str, kind: GLenum) -> Result<Shader, EngineError> {
+    let source = CString::new(source).map_err(|err| EngineError::Shader {
+        stage: stage_name(kind),
+        log: err.to_string(),
+    })?;
+    Shader::from_source(&source, kind)
+}
+
+/// Builds a program, and looks up the uniforms it's going to be given, so that a missing one is reported here rather
+/// than when it's first set
 pub fn create_program(
     vert_data: &'static str,
     frag_data: &'static str,
-) -> Result<Program, &'static str> {
-    let vert_shader = Shader::from_source(
-        &CString::new(vert_data).unwrap(), // TODO: Load this at runtime
-        gl::VERTEX_SHADER,
-    )
-    .unwrap();
-    let frag_shader = Shader::from_source(
-        &CString::new(frag_data).unwrap(), // TODO: Load this at runtime
-        gl::FRAGMENT_SHADER,
-    )
-    .unwrap();
-
-    let shader_program = Program::from_shaders(&[vert_shader, frag_shader]).unwrap();
-
-    Ok(shader_program)
+    uniforms: &[&'static str],
+) -> Result<Program, EngineError> {
+    let vert_shader = compile(vert_data, gl::VERTEX_SHADER)?; // TODO: Load this at runtime
+    let frag_shader = compile(frag_data, gl::FRAGMENT_SHADER)?;
+    let mut program = Program::from_shaders(&[vert_shader, frag_shader])?;
+    for name in uniforms {
+        let uniform = Uniform::new(program.id, name)?;
+        program.uniforms.insert(name, uniform.id);
+    }
+    Ok(program)
 }
 
 // OpenGL Vertex Buffer Object
 // Contains vertex data given as input to the vertex shader
+#[derive(Default)]
 pub struct Vbo {
     pub id: GLuint,
 }
@@ -176,6 +221,19 @@ impl Vbo {
         self.data(data);
     }
 
+    /// Like `set`, but hints that the data will be replaced every frame
+    pub fn set_dynamic(&self, data: &[f32]) {
+        self.bind();
+        unsafe {
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                std::mem::size_of_val(data) as gl::types::GLsizeiptr,
+                data.as_ptr() as *const gl::types::GLvoid,
+                gl::DYNAMIC_DRAW,
+            );
+        }
+    }
+
     pub fn bind(&self) {
         unsafe {
             gl::BindBuffer(gl::ARRAY_BUFFER, self.id);
@@ -270,6 +328,7 @@ impl Drop for Ibo {
 }
 
 /// OpenGL Vertex Array Object
+#[derive(Default)]
 pub struct Vao {
     pub id: GLuint,
 }
@@ -335,37 +394,60 @@ impl Drop for Vao {
     }
 }
 
+#[derive(Clone, Copy)]
 pub struct Uniform {
     pub id: GLint,
 }
 
 impl Uniform {
-    pub fn new(program: u32, name: &str) -> Result<Self, &'static str> {
-        let cname: CString = CString::new(name).expect("CString::new failed");
+    /// Finds a uniform in a program. Uniforms the shader doesn't use can be optimized out, and won't be found.
+    fn new(program: u32, name: &str) -> Result<Self, EngineError> {
+        let cname = CString::new(name).map_err(|_| EngineError::Uniform(name.to_string()))?;
         let location: GLint = unsafe { gl::GetUniformLocation(program, cname.as_ptr()) };
         if location == -1 {
-            return Err("Couldn't get a uniform location");
+            return Err(EngineError::Uniform(name.to_string()));
         }
         Ok(Uniform { id: location })
     }
 }
 
+/// A texture's GL object, deleted once nothing uses it anymore
+struct TextureObject(GLuint);
+
+impl Drop for TextureObject {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, [self.0].as_ptr());
+        }
+    }
+}
+
+/// A texture, which clones share, so that one loaded picture can be put on many things
 #[derive(Clone)]
 pub struct Texture {
     pub id: GLuint,
+    _object: Option<Arc<TextureObject>>, //< Keeps the GL object around while any clone is. None for no texture.
 }
 
 impl Texture {
     pub fn new() -> Self {
         let mut id: GLuint = 0;
         unsafe { gl::GenTextures(1, &mut id) }
-        Self { id }
+        Self {
+            id,
+            _object: Some(Arc::new(TextureObject(id))),
+        }
     }
 
-    pub fn from_png(texture_filename: &'static str) -> Self {
+    /// Loads a texture from a png, given relative to the asset root
+    pub fn from_png(texture_filename: &'static str) -> Result<Self, EngineError> {
         let texture = Texture::new();
-        texture.load(&Path::new(texture_filename)).unwrap();
-        texture
+        let path = asset_path(texture_filename);
+        texture.load(&path).map_err(|err| EngineError::Texture {
+            path: path.display().to_string(),
+            reason: err.to_string(),
+        })?;
+        Ok(texture)
     }
 
     pub fn from_surface(surface: sdl2::surface::Surface) -> Self {
@@ -410,6 +492,55 @@ impl Texture {
         texture
     }
 
+    /// Makes a texture from tightly packed RGBA pixels, where the first row is the top of the image
+    pub fn from_rgba(width: i32, height: i32, pixels: &[u8]) -> Self {
+        assert_eq!(pixels.len(), (width * height * 4) as usize);
+        let texture = Texture::new();
+        unsafe {
+            texture.bind();
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGBA as i32,
+                width,
+                height,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                pixels.as_ptr() as *const std::ffi::c_void,
+            );
+            gl::TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_WRAP_S,
+                gl::CLAMP_TO_EDGE as GLint,
+            );
+            gl::TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_WRAP_T,
+                gl::CLAMP_TO_EDGE as GLint,
+            );
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
+        }
+        texture
+    }
+
+    /// Makes the texture repeat past its edges, and mipmaps it so that it stays smooth far away
+    pub fn repeating(self) -> Self {
+        unsafe {
+            self.bind();
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as GLint);
+            gl::GenerateMipmap(gl::TEXTURE_2D);
+            gl::TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_MIN_FILTER,
+                gl::LINEAR_MIPMAP_LINEAR as GLint,
+            );
+        }
+        self
+    }
+
     pub fn bind(&self) {
         unsafe { gl::BindTexture(gl::TEXTURE_2D, self.id) }
     }
@@ -484,6 +615,55 @@ impl Texture {
         }
     }
 
+    pub fn load_color_buffer(&self, width: i32, height: i32) {
+        self.bind();
+
+        unsafe {
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGBA as GLint,
+                width,
+                height,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                std::ptr::null(),
+            );
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
+            gl::TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_WRAP_S,
+                gl::CLAMP_TO_EDGE as GLint,
+            );
+            gl::TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_WRAP_T,
+                gl::CLAMP_TO_EDGE as GLint,
+            );
+        }
+    }
+
+    /// Attaches this texture as the bound framebuffer's color buffer. Any depth buffer should be attached first.
+    pub fn post_bind_color(&self) {
+        unsafe {
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::TEXTURE_2D,
+                self.id,
+                0,
+            );
+            gl::DrawBuffer(gl::COLOR_ATTACHMENT0);
+            gl::ReadBuffer(gl::COLOR_ATTACHMENT0);
+
+            if gl::CheckFramebufferStatus(gl::FRAMEBUFFER) != gl::FRAMEBUFFER_COMPLETE {
+                panic!("Framebuffer is not complete!");
+            }
+        };
+    }
+
     pub fn post_bind(&self) {
         unsafe {
             gl::FramebufferTexture2D(
@@ -517,16 +697,12 @@ impl Texture {
     }
 }
 
-impl Drop for Texture {
-    fn drop(&mut self) {
-        unsafe {
-            gl::DeleteTextures(1, [self.id].as_ptr());
-        }
-    }
-}
 impl Default for Texture {
     fn default() -> Self {
-        Self { id: 0 }
+        Self {
+            id: 0,
+            _object: None,
+        }
     }
 }
 
@@ -0,0 +1,113 @@
+// Release packaging. `--package` puts together a folder that testers can run as it is: the game itself, its assets, the
+// SDL libraries it needs on Windows, every setting at its default for reference, and any licenses. The asset manifest
+// goes in too, for the game to check at startup.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    assets::{ASSET_DIR, MANIFEST_PATH},
+    settings::Settings,
+};
+
+const DEFAULT_OUT_DIR: &str = "dist";
+const DEFAULT_SETTINGS_FILE: &str = "settings.default.ron";
+const SKIPPED_ASSET_DIRS: &[&str] = &["golden"]; //< Only the tests use these
+const LICENSE_PREFIXES: &[&str] = &["LICENSE", "LICENCE", "COPYING", "NOTICE"];
+
+/// Packages the running executable with the assets under `asset_root`, into a folder named for this platform under
+/// `out_dir`. Anything already in that folder is replaced. Returns the folder.
+pub fn package(asset_root: &Path, out_dir: Option<&str>) -> Result<PathBuf, String> {
+    let dir = Path::new(out_dir.unwrap_or(DEFAULT_OUT_DIR)).join(format!(
+        "treasure-hunt-{}-{}",
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    ));
+    if dir.exists() {
+        fs::remove_dir_all(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    }
+    fs::create_dir_all(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    copy_file(&exe, &dir.join(exe.file_name().unwrap()))?;
+
+    let mut manifest = vec![];
+    copy_assets(
+        &asset_root.join(ASSET_DIR),
+        &dir.join(ASSET_DIR),
+        Path::new(ASSET_DIR),
+        &mut manifest,
+    )?;
+    let manifest: Vec<String> = manifest
+        .iter()
+        .map(|(path, size)| format!("{}\t{}", size, path))
+        .collect();
+    fs::write(dir.join(MANIFEST_PATH), manifest.join("\n") + "\n").map_err(|e| e.to_string())?;
+
+    let mut licenses = 0;
+    for entry in fs::read_dir(asset_root).map_err(|e| e.to_string())? {
+        let path = entry.map_err(|e| e.to_string())?.path();
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        let is_license = LICENSE_PREFIXES
+            .iter()
+            .any(|prefix| name.to_uppercase().starts_with(prefix));
+        // The SDL libraries are only checked in for Windows, other platforms link them in or have them installed
+        let is_library = cfg!(windows) && name.ends_with(".dll");
+        if path.is_file() && (is_license || is_library) {
+            copy_file(&path, &dir.join(&name))?;
+            licenses += is_license as usize;
+        }
+    }
+    if licenses == 0 {
+        println!(
+            "Warning: no license files found in {}",
+            asset_root.display()
+        );
+    }
+
+    fs::write(
+        dir.join(DEFAULT_SETTINGS_FILE),
+        Settings::default().to_ron()?,
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(dir)
+}
+
+/// Copies the assets in `from` to `to`, adding each one to the manifest by its path from the asset root
+fn copy_assets(
+    from: &Path,
+    to: &Path,
+    relative: &Path,
+    manifest: &mut Vec<(String, u64)>,
+) -> Result<(), String> {
+    fs::create_dir_all(to).map_err(|e| format!("{}: {}", to.display(), e))?;
+    let mut entries: Vec<PathBuf> = fs::read_dir(from)
+        .map_err(|e| format!("{}: {}", from.display(), e))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()
+        .map_err(|e| e.to_string())?;
+    // Sorted, so that the manifest comes out the same every time
+    entries.sort();
+    for path in entries {
+        let name = path.file_name().unwrap();
+        let relative = relative.join(name);
+        if path.is_dir() {
+            if !SKIPPED_ASSET_DIRS.iter().any(|skipped| name == *skipped) {
+                copy_assets(&path, &to.join(name), &relative, manifest)?;
+            }
+        } else if relative != Path::new(MANIFEST_PATH) {
+            let size = copy_file(&path, &to.join(name))?;
+            // Forward slashes, so that a manifest made on Windows still works elsewhere
+            let relative = relative.to_string_lossy().replace('\\', "/");
+            manifest.push((relative, size));
+        }
+    }
+    Ok(())
+}
+
+fn copy_file(from: &Path, to: &Path) -> Result<u64, String> {
+    fs::copy(from, to).map_err(|e| format!("Couldn't copy {}: {}", from.display(), e))
+}
@@ -0,0 +1,277 @@
+use rand::Rng;
+use specs::{
+    Component, DenseVecStorage, Entities, Join, LazyUpdate, Read, ReadStorage, System, WriteStorage,
+};
+
+use crate::App;
+
+use super::{
+    objects::{Program, Vao, Vbo},
+    physics::PositionComponent,
+    render3d::{count_draw_call, OpenGlResource},
+    time::TimeResource,
+    update_lod::UpdateLod,
+};
+
+/// Floats per particle in the instance buffer: position, size, color
+const INSTANCE_STRIDE: usize = 8;
+/// Set on the particle program each frame
+pub const PARTICLE_UNIFORMS: &[&str] = &["u_resolution", "u_view_matrix", "u_proj_matrix"];
+
+/// Describes how an emitter spawns particles, and how they look over their lifetime. Times are in seconds, distances
+/// in world units.
+#[derive(Clone, Copy)]
+pub struct EmitterPreset {
+    pub burst: usize,        //< Particles spawned all at once when the emitter starts
+    pub rate: f32,           //< Particles spawned per second afterwards
+    pub emit_seconds: f32, //< How long the emitter spawns for, infinite emitters are never removed
+    pub lifetime: f32,     //< How long each particle lives
+    pub speed: f32,        //< Initial speed of each particle, per second
+    pub direction: [f32; 3], //< Main direction particles are thrown
+    pub spread: f32,       //< 0 throws along `direction`, 1 throws in any direction
+    pub gravity: f32,      //< Downward acceleration, per second squared
+    pub drag: f32, //< How quickly particles slow down, speed falls off by a factor of e every 1/drag seconds
+    pub start_size: f32, //< Width of a particle when spawned
+    pub end_size: f32, //< Width of a particle when it dies
+    pub start_color: [f32; 4],
+    pub end_color: [f32; 4],
+}
+
+struct Particle {
+    pos: nalgebra_glm::Vec3,
+    vel: nalgebra_glm::Vec3,
+    age: f32,
+}
+
+#[derive(Component)]
+#[storage(DenseVecStorage)]
+pub struct ParticleEmitterComponent {
+    pub preset: EmitterPreset,
+    pub age: f32, //< Seconds since the emitter started
+    particles: Vec<Particle>,
+    spawn_debt: f32, //< Fractional particles owed from previous ticks
+    burst_done: bool,
+}
+
+impl ParticleEmitterComponent {
+    pub fn new(preset: EmitterPreset) -> Self {
+        Self {
+            preset,
+            age: 0.0,
+            particles: vec![],
+            spawn_debt: 0.0,
+            burst_done: false,
+        }
+    }
+
+    /// Whether the emitter has stopped spawning, and all of its particles have died
+    pub fn finished(&self) -> bool {
+        self.age >= self.preset.emit_seconds && self.particles.is_empty()
+    }
+
+    fn spawn(&mut self, origin: nalgebra_glm::Vec3, rng: &mut impl Rng) {
+        let preset = &self.preset;
+        let direction = nalgebra_glm::make_vec3(&preset.direction);
+        let random_dir = nalgebra_glm::vec3(
+            rng.gen_range(-1.0..=1.0),
+            rng.gen_range(-1.0..=1.0),
+            rng.gen_range(-1.0..=1.0),
+        );
+        let dir = nalgebra_glm::lerp(&direction, &random_dir, preset.spread);
+        let dir = if nalgebra_glm::length(&dir) > 0.0001 {
+            dir.normalize()
+        } else {
+            direction
+        };
+        self.particles.push(Particle {
+            pos: origin,
+            vel: dir * preset.speed * rng.gen_range(0.5..=1.0),
+            age: 0.0,
+        });
+    }
+}
+
+/// Spawns a stand-alone emitter at a position, which deletes itself once it's done
+pub fn spawn_emitter(
+    entities: &Entities,
+    lazy: &LazyUpdate,
+    position: nalgebra_glm::Vec3,
+    preset: EmitterPreset,
+) {
+    let entity = entities.create();
+    lazy.insert(entity, PositionComponent { pos: position });
+    lazy.insert(entity, ParticleEmitterComponent::new(preset));
+}
+
+/// Spawns, moves, and ages particles
+pub struct ParticleSystem;
+impl<'a> System<'a> for ParticleSystem {
+    type SystemData = (
+        WriteStorage<'a, ParticleEmitterComponent>,
+        ReadStorage<'a, PositionComponent>,
+        Read<'a, TimeResource>,
+        Read<'a, OpenGlResource>,
+        Read<'a, App>,
+        Entities<'a>,
+    );
+
+    fn run(&mut self, (mut emitters, positions, time, open_gl, app, entities): Self::SystemData) {
+        let mut rng = rand::thread_rng();
+        let mut removed_entities = Vec::new();
+        for (emitter, position, entity) in (&mut emitters, &positions, &entities).join() {
+            let lod = UpdateLod::new(position.pos, open_gl.camera.position);
+            let Some(dt) = lod.dt(entity, app.ticks, time.dt) else {
+                continue;
+            };
+            let preset = emitter.preset;
+            for particle in &mut emitter.particles {
+                particle.vel.z -= preset.gravity * dt;
+                particle.vel *= (-preset.drag * dt).exp();
+                particle.pos += particle.vel * dt;
+                particle.age += dt;
+            }
+            emitter.particles.retain(|p| p.age < preset.lifetime);
+
+            if !emitter.burst_done {
+                for _ in 0..preset.burst {
+                    emitter.spawn(position.pos, &mut rng);
+                }
+                emitter.burst_done = true;
+            }
+            if emitter.age < preset.emit_seconds {
+                emitter.spawn_debt += preset.rate * dt;
+                while emitter.spawn_debt >= 1.0 {
+                    emitter.spawn(position.pos, &mut rng);
+                    emitter.spawn_debt -= 1.0;
+                }
+            }
+            emitter.age += dt;
+
+            if emitter.finished() {
+                removed_entities.push(entity);
+            }
+        }
+        for removed_entity in removed_entities {
+            entities.delete(removed_entity).unwrap();
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ParticleResource {
+    pub program: Program,
+    vao: Vao,
+    _corner_vbo: Vbo, //< Only kept so the buffer lives as long as the vao
+    instance_vbo: Vbo,
+}
+
+impl ParticleResource {
+    pub fn new(program: Program) -> Self {
+        let vao = Vao::gen();
+        let corner_vbo = Vbo::gen();
+        let instance_vbo = Vbo::gen();
+        unsafe {
+            gl::BindVertexArray(vao.id);
+
+            // One quad, drawn as a triangle strip and stretched to face the camera in the vertex shader
+            corner_vbo.set(&vec![-0.5, -0.5, 0.5, -0.5, -0.5, 0.5, 0.5, 0.5]);
+            gl::EnableVertexAttribArray(0);
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, 0, std::ptr::null());
+
+            // Per particle position and size, then color
+            instance_vbo.bind();
+            let stride = (INSTANCE_STRIDE * std::mem::size_of::<f32>()) as i32;
+            gl::EnableVertexAttribArray(1);
+            gl::VertexAttribPointer(1, 4, gl::FLOAT, gl::FALSE, stride, std::ptr::null());
+            gl::VertexAttribDivisor(1, 1);
+            gl::EnableVertexAttribArray(2);
+            gl::VertexAttribPointer(
+                2,
+                4,
+                gl::FLOAT,
+                gl::FALSE,
+                stride,
+                (4 * std::mem::size_of::<f32>()) as *const _,
+            );
+            gl::VertexAttribDivisor(2, 1);
+
+            gl::BindVertexArray(0);
+        }
+        Self {
+            program,
+            vao,
+            _corner_vbo: corner_vbo,
+            instance_vbo,
+        }
+    }
+}
+
+/// Draws every particle as a camera facing quad, in one instanced draw call. Should run after the opaque 3D pass.
+pub struct ParticleRenderSystem;
+impl<'a> System<'a> for ParticleRenderSystem {
+    type SystemData = (
+        ReadStorage<'a, ParticleEmitterComponent>,
+        Read<'a, App>,
+        Read<'a, OpenGlResource>,
+        Read<'a, ParticleResource>,
+    );
+
+    fn run(&mut self, (emitters, app, open_gl, particles): Self::SystemData) {
+        let mut instances = Vec::<f32>::new();
+        for emitter in (&emitters).join() {
+            let preset = &emitter.preset;
+            let start_color = nalgebra_glm::make_vec4(&preset.start_color);
+            let end_color = nalgebra_glm::make_vec4(&preset.end_color);
+            for particle in &emitter.particles {
+                let t = particle.age / preset.lifetime;
+                let size = preset.start_size + t * (preset.end_size - preset.start_size);
+                let color = nalgebra_glm::lerp(&start_color, &end_color, t);
+                instances.extend([particle.pos.x, particle.pos.y, particle.pos.z, size]);
+                instances.extend([color.x, color.y, color.z, color.w]);
+            }
+        }
+        if instances.is_empty() {
+            return;
+        }
+
+        let program = &particles.program;
+        program.set();
+        let (view_matrix, proj_matrix) = open_gl.camera.gen_view_proj_matrices();
+        unsafe {
+            gl::Uniform2f(
+                program.uniform("u_resolution").id,
+                app.screen_width as f32,
+                app.screen_height as f32,
+            );
+            gl::UniformMatrix4fv(
+                program.uniform("u_view_matrix").id,
+                1,
+                gl::FALSE,
+                &view_matrix.columns(0, 4)[0],
+            );
+            gl::UniformMatrix4fv(
+                program.uniform("u_proj_matrix").id,
+                1,
+                gl::FALSE,
+                &proj_matrix.columns(0, 4)[0],
+            );
+
+            gl::BindVertexArray(particles.vao.id);
+            particles.instance_vbo.set_dynamic(&instances);
+
+            // Particles are see-through, so they shouldn't hide each other
+            gl::Disable(gl::CULL_FACE);
+            gl::DepthMask(gl::FALSE);
+            count_draw_call();
+            gl::DrawArraysInstanced(
+                gl::TRIANGLE_STRIP,
+                0,
+                4,
+                (instances.len() / INSTANCE_STRIDE) as i32,
+            );
+            gl::DepthMask(gl::TRUE);
+            gl::Enable(gl::CULL_FACE);
+            gl::BindVertexArray(0);
+        }
+    }
+}
@@ -1,6 +1,16 @@
-use std::cmp::Ordering;
+use std::{
+    cmp::Ordering,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use rand::{Rng, SeedableRng};
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use super::water::SEA_LEVEL;
+
+const OCCLUSION_DIRECTIONS: usize = 8; //< Around each point, for finding how much of the sky the terrain hides
+const OCCLUSION_DISTANCES: [f32; 4] = [1.0, 2.0, 4.0, 8.0]; //< Tiles out from the point, along each direction
 
 static HASH: [i32; 256] = [
     208, 34, 231, 213, 32, 248, 233, 56, 161, 78, 24, 140, 71, 48, 140, 254, 245, 255, 247, 247,
@@ -18,21 +28,162 @@ static HASH: [i32; 256] = [
     250, 1, 8, 198, 250, 209, 92, 222, 173, 21, 88, 102, 219,
 ];
 
+/// The unit gradients gradient noise picks from at each grid point. Eight directions are plenty in 2D.
+const GRADIENTS: [(f32, f32); 8] = [
+    (1.0, 0.0),
+    (-1.0, 0.0),
+    (0.0, 1.0),
+    (0.0, -1.0),
+    (
+        std::f32::consts::FRAC_1_SQRT_2,
+        std::f32::consts::FRAC_1_SQRT_2,
+    ),
+    (
+        -std::f32::consts::FRAC_1_SQRT_2,
+        std::f32::consts::FRAC_1_SQRT_2,
+    ),
+    (
+        std::f32::consts::FRAC_1_SQRT_2,
+        -std::f32::consts::FRAC_1_SQRT_2,
+    ),
+    (
+        -std::f32::consts::FRAC_1_SQRT_2,
+        -std::f32::consts::FRAC_1_SQRT_2,
+    ),
+];
+
+/// Which kind of noise a map's heights start out as
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum NoiseKind {
+    /// Smoothed random values at each grid point, from a fixed hash table. What islands have always been made of,
+    /// but it shows the grid on large maps, and only 256 seeds are different from each other.
+    #[default]
+    Value,
+    /// Perlin's gradient noise, from a permutation table shuffled by the seed. Smooth, with no grid showing through.
+    Gradient,
+}
+
+/// How a map's starting heights are made. The defaults are what `PerlinMap::new` uses.
+#[derive(Clone, Copy, Debug)]
+pub struct NoiseParams {
+    pub kind: NoiseKind,
+    pub frequency: f32,   //< Of the first octave, in cycles per tile
+    pub octaves: i32,     //< Layers of noise, each finer than the last
+    pub persistence: f32, //< How much each octave counts, compared to the one before
+    pub lacunarity: f32,  //< How much finer each octave is than the one before
+    pub amplitude: f32,   //< Heights are in [0, amplitude]
+}
+
+impl Default for NoiseParams {
+    fn default() -> Self {
+        Self {
+            kind: NoiseKind::Value,
+            frequency: 0.03,
+            octaves: 10,
+            persistence: 0.5,
+            lacunarity: 2.0,
+            amplitude: 1.0,
+        }
+    }
+}
+
+/// A round island raised out of the noise, in tiles
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bulge {
+    pub center: nalgebra_glm::Vec2,
+    pub radius: f32, //< Where the shore would be, if the noise were flat
+}
+
+impl Bulge {
+    /// How much a spot is raised: 1 in the middle, 0 at the radius, and less than 0 out to sea
+    pub fn rise(&self, p: nalgebra_glm::Vec2) -> f32 {
+        let xo = p.x - self.center.x;
+        let yo = p.y - self.center.y;
+        let d = (xo * xo + yo * yo).sqrt();
+        -(d - self.radius) / self.radius
+    }
+
+    /// Which bulge a spot belongs to, the one that raises it the most
+    pub fn owner(bulges: &[Bulge], p: nalgebra_glm::Vec2) -> usize {
+        (0..bulges.len())
+            .max_by(|&a, &b| bulges[a].rise(p).total_cmp(&bulges[b].rise(p)))
+            .unwrap_or(0)
+    }
+}
+
 #[derive(Default)]
 pub struct PerlinMap {
     cells: Vec<Cell>,
     map_width: usize,
 }
 
-#[derive(Default)]
+/// The island's terrain, which doesn't change once generated. Cheap to clone, to share with other threads.
+#[derive(Default, Clone)]
 pub struct PerlinMapResource {
-    pub map: PerlinMap,
+    pub map: Arc<PerlinMap>,
+    pub moisture: Arc<MoistureMap>,
 }
 
 #[derive(Default, Copy, Clone)]
 struct Cell {
     pub height: f32,
-    pub flow: f32,
+}
+
+/// Knobs for how erosion carves the island. The defaults give the usual island.
+#[derive(Clone, Copy)]
+pub struct ErosionParams {
+    pub droplets: usize, //< How many droplets are rolled down the map, more carves deeper valleys
+    pub sediment_capacity: f32, //< How much sediment a droplet can carry, higher erodes faster slopes harder
+    pub erode_speed: f32,       //< Fraction of missing sediment a droplet picks up each step
+    pub deposit_speed: f32,     //< Fraction of excess sediment a droplet drops each step
+    pub gravity: f32,           //< How strongly slopes accelerate droplets
+    pub evaporation: f32,       //< Fraction of a droplet's water lost each step
+    pub max_age: usize,         //< Steps a droplet lives for at most
+    pub talus: f32, //< Steepest height difference between cells before material slides, low is sandy
+    pub settling: f32, //< Fraction of the excess that slides each time
+}
+
+impl Default for ErosionParams {
+    fn default() -> Self {
+        Self {
+            droplets: 20_000,
+            sediment_capacity: 1.0,
+            erode_speed: 0.1,
+            deposit_speed: 0.1,
+            gravity: 1.0,
+            evaporation: 0.001,
+            max_age: 500,
+            talus: 0.9,
+            settling: 0.8,
+        }
+    }
+}
+
+/// How much water flowed over each cell during erosion. High in valleys and riverbeds, low on ridges.
+#[derive(Default)]
+pub struct MoistureMap {
+    values: Vec<f32>,
+    map_width: usize,
+}
+
+impl MoistureMap {
+    fn new(map_width: usize) -> Self {
+        Self {
+            values: vec![0.0; map_width * map_width],
+            map_width,
+        }
+    }
+
+    pub fn get(&self, p: nalgebra_glm::Vec2) -> f32 {
+        if p.x < 0.0 || p.y < 0.0 || p.x >= self.map_width as f32 || p.y >= self.map_width as f32 {
+            return 0.0;
+        }
+        self.values[p.x as usize + p.y as usize * self.map_width]
+    }
+
+    fn incr(&mut self, p: nalgebra_glm::Vec2, val: f32) {
+        self.values[p.x as usize + p.y as usize * self.map_width] += val
+    }
 }
 
 struct Particle {
@@ -56,14 +207,15 @@ impl Particle {
         }
     }
 
-    fn descend(&mut self, map: &mut PerlinMap) -> bool {
+    fn descend(
+        &mut self,
+        map: &mut PerlinMap,
+        moisture: &mut MoistureMap,
+        params: &ErosionParams,
+    ) -> bool {
         const MIN_VOLUME: f32 = 0.01;
-        const DEPOSITION_RATE: f32 = 0.1;
-        const EVAPORATION_RATE: f32 = 0.001;
-        const MAX_AGE: usize = 500;
-        const ENTRAINMENT: f32 = 1.0;
 
-        if self.age > MAX_AGE {
+        if self.age > params.max_age {
             map.incr_height(self.pos, self.sediment);
             return false;
         }
@@ -79,7 +231,7 @@ impl Particle {
 
         // Accelerate particle using classical mechanics
         let old_pos = self.pos;
-        self.vel += grad.xy() / self.volume;
+        self.vel += params.gravity * grad.xy() / self.volume;
         if nalgebra_glm::length(&self.vel) > 0.0 {
             self.vel = (2.0 as f32).sqrt() * nalgebra_glm::normalize(&self.vel);
         }
@@ -92,27 +244,33 @@ impl Particle {
         }
 
         // Update flow, momentum
-        map.incr_flow(old_pos, self.volume);
+        moisture.incr(old_pos, self.volume);
 
         // Compute Equilibrium Sediment Content
         let c_eq = (self.volume
             // * (1.0 + 0.01 * map.flow(old_pos))
             * nalgebra_glm::length(&self.vel)
-            * (map.height(old_pos) - map.height(self.pos)))
-        .max(0.0);
+            * (map.height(old_pos) - map.height(self.pos))
+            * params.sediment_capacity)
+            .max(0.0);
 
         // Compute Capacity Difference ("Driving Force")
         let cdiff = c_eq - self.sediment;
 
         // Perform the Mass Transfer!
-        let mass_transfered = DEPOSITION_RATE * cdiff;
+        let rate = if cdiff > 0.0 {
+            params.erode_speed
+        } else {
+            params.deposit_speed
+        };
+        let mass_transfered = rate * cdiff;
         self.sediment += mass_transfered;
         map.incr_height(old_pos, -mass_transfered);
 
-        self.sediment /= 1.0 - EVAPORATION_RATE;
-        self.volume *= 1.0 - EVAPORATION_RATE;
+        self.sediment /= 1.0 - params.evaporation;
+        self.volume *= 1.0 - params.evaporation;
 
-        map.cascade(self.pos);
+        map.cascade(self.pos, params);
 
         self.age += 1;
         true
@@ -121,14 +279,32 @@ impl Particle {
 
 impl PerlinMap {
     pub fn new(map_width: usize, level_of_detail: f32, seed: i32, amplitude: f32) -> Self {
+        let params = NoiseParams {
+            frequency: level_of_detail,
+            amplitude,
+            ..Default::default()
+        };
+        Self::with_noise(map_width, seed, &params)
+    }
+
+    /// A map of noise, of whichever kind and octaves are asked for
+    pub fn with_noise(map_width: usize, seed: i32, params: &NoiseParams) -> Self {
         let mut retval = Self::default();
 
         retval.map_width = map_width;
+        let permutation = match params.kind {
+            NoiseKind::Value => None,
+            NoiseKind::Gradient => Some(Permutation::new(seed as u64)),
+        };
         for y in 0..map_width {
             for x in 0..map_width {
+                let (x, y) = (x as f32, y as f32);
+                let noise = match &permutation {
+                    None => perlin2d(x, y, params, seed),
+                    Some(permutation) => gradient2d(x, y, params, permutation),
+                };
                 retval.cells.push(Cell {
-                    height: perlin2d(x as f32, y as f32, level_of_detail, 10, seed) * amplitude,
-                    flow: 0.0,
+                    height: noise * params.amplitude,
                 });
             }
         }
@@ -136,34 +312,40 @@ impl PerlinMap {
         retval
     }
 
-    pub fn erode(&mut self, total_particles: usize, seed: u64) {
+    /// Erodes the map by rolling droplets down it, and returns where the water flowed. `on_progress` is called with the
+    /// percent done every time it changes.
+    pub fn erode(
+        &mut self,
+        params: &ErosionParams,
+        seed: u64,
+        mut on_progress: impl FnMut(usize),
+    ) -> MoistureMap {
         let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+        let mut moisture = MoistureMap::new(self.map_width);
+        let total_particles = params.droplets;
 
-        let mut checkpoint = total_particles / 10;
+        let mut percent = 0;
         for i in 0..total_particles {
-            if i > checkpoint {
-                checkpoint += total_particles / 10;
-                println!(
-                    " - {}%",
-                    (i as f32 / total_particles as f32 * 100.0) as usize
-                );
+            let curr_percent = i * 100 / total_particles;
+            if curr_percent != percent {
+                percent = curr_percent;
+                on_progress(percent);
             }
 
             let mut drop = Particle::new(nalgebra_glm::vec2(
                 rng.gen_range(0.0..self.map_width as f32),
                 rng.gen_range(0.0..self.map_width as f32),
             ));
-            if self.height(drop.pos) < 0.5 {
+            if self.height(drop.pos) < SEA_LEVEL {
                 continue;
             }
-            while drop.descend(self) {}
+            while drop.descend(self, &mut moisture, params) {}
         }
-    }
 
-    pub fn cascade(&mut self, pos: nalgebra_glm::Vec2) {
-        const MAX_DIFF: f32 = 0.9;
-        const SETTLING: f32 = 0.8;
+        moisture
+    }
 
+    pub fn cascade(&mut self, pos: nalgebra_glm::Vec2, params: &ErosionParams) {
         let neighbors = [
             nalgebra_glm::vec2(-1.0, -1.0),
             nalgebra_glm::vec2(-1.0, 0.0),
@@ -199,7 +381,7 @@ impl PerlinMap {
 
             // The amount of excess difference
             let excess = if in_bound_neighbors[i].z > 0.1 {
-                diff.abs() - MAX_DIFF
+                diff.abs() - params.talus
             } else {
                 diff.abs()
             };
@@ -208,7 +390,7 @@ impl PerlinMap {
             }
 
             // Actual amount transferred
-            let transfer = SETTLING * excess / 2.0;
+            let transfer = params.settling * excess / 2.0;
 
             // Cap by maximum transferrable amount
             if diff > 0.0 {
@@ -235,89 +417,108 @@ impl PerlinMap {
         self.cells[p.x as usize + p.y as usize * self.map_width].height += val
     }
 
-    pub fn flow(&self, p: nalgebra_glm::Vec2) -> f32 {
-        self.cells[p.x as usize + p.y as usize * self.map_width].flow
-    }
-
-    pub fn incr_flow(&mut self, p: nalgebra_glm::Vec2, val: f32) {
-        self.cells[p.x as usize + p.y as usize * self.map_width].flow += val
+    /// The height of a corner of the terrain's triangles. Corners off the map take the height of the nearest one on it,
+    /// so that the terrain carries on flat past its edges rather than dropping away.
+    pub(super) fn vertex_height(&self, corner: nalgebra_glm::Vec2) -> f32 {
+        let last = self.map_width.saturating_sub(1) as f32;
+        self.height(nalgebra_glm::clamp(&corner, 0.0, last))
     }
 
-    pub fn get_z_interpolated(&self, p: nalgebra_glm::Vec2) -> f32 {
-        assert!(!p.x.is_nan());
+    /// The corners of the triangle under `p`, with their heights. Each tile is split in two along the diagonal from
+    /// its bottom right corner to its top left.
+    fn triangle(&self, p: nalgebra_glm::Vec2) -> [nalgebra_glm::Vec3; 3] {
         // The coordinates of the tile's origin (bottom left corner)
         let origin = nalgebra_glm::floor(&p);
 
         // Coordinates inside the tile. [0,1]
         let offset = p - origin;
 
-        let ray_origin = nalgebra_glm::vec3(p.x, p.y, 10000.0);
-        let ray_direction = nalgebra_glm::vec3(0.0, 0.0, -1.0);
-
         let offsets = if offset.y <= 1.0 - offset.x {
             // In bottom triangle
-            vec![
+            [
                 nalgebra_glm::vec2(0.0, 0.0), // Contains the origin
                 nalgebra_glm::vec2(1.0, 0.0),
                 nalgebra_glm::vec2(0.0, 1.0),
             ]
         } else {
             // In top triangle
-            vec![
+            [
                 nalgebra_glm::vec2(1.0, 0.0),
                 nalgebra_glm::vec2(1.0, 1.0), // Contains the anti-origin
                 nalgebra_glm::vec2(0.0, 1.0),
             ]
         };
-        let offsets: Vec<nalgebra_glm::Vec3> = offsets
-            .iter()
-            .map(|o| nalgebra_glm::vec3(origin.x + o.x, origin.y + o.y, self.height(origin + o)))
-            .collect();
+        offsets.map(|o| {
+            let corner = origin + o;
+            nalgebra_glm::vec3(corner.x, corner.y, self.vertex_height(corner))
+        })
+    }
 
-        let (retval, _t) = intersect(
-            offsets[0],
-            offsets[1],
-            offsets[2],
-            ray_origin,
-            ray_direction,
-        )
-        .unwrap();
-        retval.z
+    /// The height of the terrain at `p`, on the triangle under it. Past the map's edges the terrain carries on flat.
+    /// None if `p` isn't a finite point.
+    pub fn checked_z_interpolated(&self, p: nalgebra_glm::Vec2) -> Option<f32> {
+        if !p.x.is_finite() || !p.y.is_finite() {
+            return None;
+        }
+        // Solved from the triangle's plane directly, since a ray cast down can slip between triangles on the diagonal
+        let [a, b, c] = self.triangle(p);
+        let normal = tri_normal(a, b, c);
+        Some(a.z - (normal.x * (p.x - a.x) + normal.y * (p.y - a.y)) / normal.z)
     }
 
-    pub fn oob(&self, p: nalgebra_glm::Vec2) -> bool {
-        p.x < 0.0 || p.y < 0.0 || p.x >= self.map_width as f32 || p.y >= self.map_width as f32
+    /// Like `checked_z_interpolated`, for points known to be finite. A NaN is a bug wherever it came from, so panics.
+    pub fn get_z_interpolated(&self, p: nalgebra_glm::Vec2) -> f32 {
+        self.checked_z_interpolated(p)
+            .unwrap_or_else(|| panic!("no terrain height at {:?}", p))
     }
 
-    pub fn get_normal(&self, p: nalgebra_glm::Vec2) -> nalgebra_glm::Vec3 {
-        assert!(!p.x.is_nan());
-        // The coordinates of the tile's origin (bottom left corner)
-        let origin = nalgebra_glm::floor(&p);
+    /// How much of the sky the terrain around `p` hides, from 0 out in the open to 1 at the bottom of a well. Found
+    /// from how high the horizon is in a few directions, which is cheap enough to bake into the terrain's vertices.
+    pub fn occlusion(&self, p: nalgebra_glm::Vec2) -> f32 {
+        let z = self.height(p);
+        let total: f32 = (0..OCCLUSION_DIRECTIONS)
+            .map(|i| {
+                let angle = i as f32 / OCCLUSION_DIRECTIONS as f32 * std::f32::consts::TAU;
+                let dir = nalgebra_glm::vec2(angle.cos(), angle.sin());
+                // Sine of the angle up to the highest point seen that way
+                OCCLUSION_DISTANCES
+                    .iter()
+                    .map(|distance| {
+                        let sample = p + dir * *distance;
+                        if self.oob(sample) {
+                            return 0.0;
+                        }
+                        let rise = self.height(sample) - z;
+                        rise / (rise * rise + distance * distance).sqrt()
+                    })
+                    .fold(0.0, f32::max)
+            })
+            .sum();
+        total / OCCLUSION_DIRECTIONS as f32
+    }
 
-        // Coordinates inside the tile. [0,1]
-        let offset = p - origin;
+    /// How many tiles wide and tall the map is
+    pub fn width(&self) -> usize {
+        self.map_width
+    }
 
-        let offsets = if offset.y <= 1.0 - offset.x {
-            // In bottom triangle
-            [
-                nalgebra_glm::vec2(0.0, 0.0), // Contains the origin
-                nalgebra_glm::vec2(1.0, 0.0),
-                nalgebra_glm::vec2(0.0, 1.0),
-            ]
-        } else {
-            // In top triangle
-            [
-                nalgebra_glm::vec2(1.0, 0.0),
-                nalgebra_glm::vec2(1.0, 1.0), // Contains the anti-origin
-                nalgebra_glm::vec2(0.0, 1.0),
-            ]
-        };
-        let offsets: Vec<nalgebra_glm::Vec3> = offsets
-            .iter()
-            .map(|o| nalgebra_glm::vec3(origin.x + o.x, origin.y + o.y, self.height(origin + o)))
-            .collect();
+    pub fn oob(&self, p: nalgebra_glm::Vec2) -> bool {
+        p.x < 0.0 || p.y < 0.0 || p.x >= self.map_width as f32 || p.y >= self.map_width as f32
+    }
 
-        tri_normal(offsets[0], offsets[1], offsets[2])
+    /// Which way the terrain faces at `p`, pointing up out of it. None if `p` isn't a finite point.
+    pub fn checked_normal(&self, p: nalgebra_glm::Vec2) -> Option<nalgebra_glm::Vec3> {
+        if !p.x.is_finite() || !p.y.is_finite() {
+            return None;
+        }
+        let [a, b, c] = self.triangle(p);
+        Some(tri_normal(a, b, c))
+    }
+
+    /// Like `checked_normal`, for points known to be finite
+    pub fn get_normal(&self, p: nalgebra_glm::Vec2) -> nalgebra_glm::Vec3 {
+        self.checked_normal(p)
+            .unwrap_or_else(|| panic!("no terrain normal at {:?}", p))
     }
 
     pub fn get_dot_prod(&self, p: nalgebra_glm::Vec2) -> f32 {
@@ -326,18 +527,29 @@ impl PerlinMap {
         nalgebra_glm::dot(&self.get_normal(p), &nalgebra_glm::vec3(0.0, 0.0, 1.0))
     }
 
-    pub fn create_bulge(&mut self) {
+    /// The one bulge a lone island is raised with, in the middle of the map
+    pub fn center_bulge(&self) -> Bulge {
+        Bulge {
+            center: nalgebra_glm::vec2(self.map_width as f32 / 2.0, self.map_width as f32 / 2.0),
+            radius: 0.8 * 0.25 * (2.0 as f32).sqrt() * self.map_width as f32,
+        }
+    }
+
+    /// Raises an island out of the noise around each bulge. Where bulges are far enough apart, the sea between them
+    /// stays as channels.
+    pub fn create_bulges(&mut self, bulges: &[Bulge]) {
         for y in 0..self.map_width {
             for x in 0..self.map_width {
                 let z = self.cells[x + y * self.map_width].height;
-                let xo = (x as f32) - (self.map_width as f32) / 2.0;
-                let yo = (y as f32) - (self.map_width as f32) / 2.0;
-                let d = ((xo * xo + yo * yo) as f32).sqrt();
-                let shoreline = 0.8 * 0.25 * (2.0 as f32).sqrt() * self.map_width as f32;
-                let bulge = -(d - shoreline) / shoreline;
+                // Each spot belongs to whichever island rises highest there
+                let p = nalgebra_glm::vec2(x as f32, y as f32);
+                let bulge = bulges
+                    .iter()
+                    .map(|bulge| bulge.rise(p))
+                    .fold(f32::MIN, f32::max);
                 self.cells[x + y * self.map_width].height =
                     self.map_width as f32 / 200.0 * (z + bulge);
-                if self.cells[x + y * self.map_width].height > 0.5 {
+                if self.cells[x + y * self.map_width].height > SEA_LEVEL {
                     self.cells[x + y * self.map_width].height =
                         (self.cells[x + y * self.map_width].height - 0.4).powf(2.0) + 0.4;
                 }
@@ -361,24 +573,102 @@ impl PerlinMap {
     }
 }
 
-fn perlin2d(x: f32, y: f32, freq: f32, depth: i32, seed: i32) -> f32 {
-    let mut xa = x * freq;
-    let mut ya = y * freq;
+/// Octaves of value noise, in [0, 1)
+fn perlin2d(x: f32, y: f32, params: &NoiseParams, seed: i32) -> f32 {
+    let mut xa = x * params.frequency;
+    let mut ya = y * params.frequency;
     let mut amp: f32 = 1.0;
     let mut fin: f32 = 0.0;
     let mut div: f32 = 0.0;
 
-    for _ in 0..depth {
+    for _ in 0..params.octaves {
         div += 256.0 * amp;
         fin += noise2d(xa, ya, seed) * amp;
-        amp /= 2.0;
-        xa *= 2.0;
-        ya *= 2.0;
+        amp *= params.persistence;
+        xa *= params.lacunarity;
+        ya *= params.lacunarity;
     }
 
     fin / div
 }
 
+/// A shuffle of 0..256, repeated twice over so that hashing a pair of coordinates doesn't have to wrap
+struct Permutation([u8; 512]);
+
+impl Permutation {
+    fn new(seed: u64) -> Self {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+        let mut shuffled: Vec<u8> = (0..=255).collect();
+        shuffled.shuffle(&mut rng);
+        let mut table = [0; 512];
+        for (i, entry) in table.iter_mut().enumerate() {
+            *entry = shuffled[i % 256];
+        }
+        Self(table)
+    }
+
+    fn hash(&self, x: i32, y: i32) -> u8 {
+        self.0[self.0[(x & 255) as usize] as usize + (y & 255) as usize]
+    }
+}
+
+/// Octaves of gradient noise, in [0, 1]
+fn gradient2d(x: f32, y: f32, params: &NoiseParams, permutation: &Permutation) -> f32 {
+    let mut freq = params.frequency;
+    let mut amp: f32 = 1.0;
+    let mut fin: f32 = 0.0;
+    let mut div: f32 = 0.0;
+
+    for _ in 0..params.octaves {
+        div += amp;
+        fin += gradient_noise(x * freq, y * freq, permutation) * amp;
+        amp *= params.persistence;
+        freq *= params.lacunarity;
+    }
+
+    // A single octave is at most half the diagonal of a cell away from zero
+    0.5 + fin / div * std::f32::consts::FRAC_1_SQRT_2
+}
+
+/// Gradient noise at a point, in [-1/sqrt(2), 1/sqrt(2)]. Zero at every grid point.
+fn gradient_noise(x: f32, y: f32, permutation: &Permutation) -> f32 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (x_frac, y_frac) = (x - x0, y - y0);
+    let (x_int, y_int) = (x0 as i32, y0 as i32);
+    // How far the point is along the gradient picked for a corner of its cell
+    let corner = |dx: i32, dy: i32| {
+        let (gx, gy) = GRADIENTS[(permutation.hash(x_int + dx, y_int + dy) & 7) as usize];
+        gx * (x_frac - dx as f32) + gy * (y_frac - dy as f32)
+    };
+    let u = fade(x_frac);
+    let v = fade(y_frac);
+    let low = lin_inter(corner(0, 0), corner(1, 0), u);
+    let high = lin_inter(corner(0, 1), corner(1, 1), u);
+    lin_inter(low, high, v)
+}
+
+/// Perlin's quintic curve, which eases in and out of each cell so that the noise has no creases at cell edges
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+/// How long it takes to fill a map of each kind of noise, with the default octaves
+pub fn benchmark_noise(map_width: usize) -> Vec<(NoiseKind, Duration)> {
+    [NoiseKind::Value, NoiseKind::Gradient]
+        .into_iter()
+        .map(|kind| {
+            let params = NoiseParams {
+                kind,
+                ..Default::default()
+            };
+            let start = Instant::now();
+            let map = PerlinMap::with_noise(map_width, 1, &params);
+            std::hint::black_box(map);
+            (kind, start.elapsed())
+        })
+        .collect()
+}
+
 fn noise2d(x: f32, y: f32, seed: i32) -> f32 {
     let x_int = x as i32;
     let y_int = y as i32;
@@ -406,7 +696,7 @@ fn lin_inter(x: f32, y: f32, s: f32) -> f32 {
     x + s * (y - x)
 }
 
-fn intersect(
+pub(super) fn intersect(
     v0: nalgebra_glm::Vec3,
     v1: nalgebra_glm::Vec3,
     v2: nalgebra_glm::Vec3,
@@ -444,7 +734,7 @@ fn intersect(
     Some((intersection_point, t))
 }
 
-fn tri_normal(
+pub(super) fn tri_normal(
     v0: nalgebra_glm::Vec3,
     v1: nalgebra_glm::Vec3,
     v2: nalgebra_glm::Vec3,
@@ -454,3 +744,216 @@ fn tri_normal(
     let normal = nalgebra_glm::cross(&edge1, &edge2).normalize();
     normal
 }
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn flat_map(width: usize) -> PerlinMap {
+        PerlinMap {
+            cells: vec![Cell::default(); width * width],
+            map_width: width,
+        }
+    }
+
+    #[test]
+    fn open_ground_is_unoccluded() {
+        let map = flat_map(32);
+        assert_eq!(map.occlusion(nalgebra_glm::vec2(16.0, 16.0)), 0.0);
+    }
+
+    #[test]
+    fn valleys_are_occluded() {
+        let mut map = flat_map(32);
+        for y in 0..32 {
+            for x in 0..32 {
+                let p = nalgebra_glm::vec2(x as f32, y as f32);
+                map.incr_height(p, (x as f32 - 16.0).abs() * 0.5);
+            }
+        }
+        let valley = map.occlusion(nalgebra_glm::vec2(16.0, 16.0));
+        let slope = map.occlusion(nalgebra_glm::vec2(24.0, 16.0));
+        assert!(valley > slope && slope > 0.0);
+    }
+
+    /// A map with a height at each vertex, in rows from the bottom
+    fn map_from_heights(width: usize, heights: &[f32]) -> PerlinMap {
+        PerlinMap {
+            cells: heights.iter().map(|&height| Cell { height }).collect(),
+            map_width: width,
+        }
+    }
+
+    /// A map that rises half a unit for every tile in x
+    fn ramp(width: usize) -> PerlinMap {
+        let heights: Vec<f32> = (0..width * width)
+            .map(|i| (i % width) as f32 * 0.5)
+            .collect();
+        map_from_heights(width, &heights)
+    }
+
+    #[test]
+    fn vertices_keep_their_heights() {
+        let map = ramp(8);
+        for (x, y) in [(0.0, 0.0), (3.0, 5.0), (7.0, 7.0)] {
+            let p = nalgebra_glm::vec2(x, y);
+            assert_eq!(map.get_z_interpolated(p), map.height(p));
+        }
+    }
+
+    #[test]
+    fn slopes_are_interpolated_linearly() {
+        let map = ramp(8);
+        // Either side of the diagonal, so both triangles are covered
+        assert!((map.get_z_interpolated(nalgebra_glm::vec2(2.25, 4.5)) - 1.125).abs() < 1e-5);
+        assert!((map.get_z_interpolated(nalgebra_glm::vec2(2.75, 4.5)) - 1.375).abs() < 1e-5);
+        let normal = map.get_normal(nalgebra_glm::vec2(2.5, 4.5));
+        let expected = nalgebra_glm::vec3(-0.5, 0.0, 1.0).normalize();
+        assert!(nalgebra_glm::distance(&normal, &expected) < 1e-5);
+    }
+
+    #[test]
+    fn terrain_carries_on_flat_past_the_edges() {
+        let map = ramp(8);
+        let edge = map.get_z_interpolated(nalgebra_glm::vec2(7.0, 3.0));
+        assert_eq!(map.get_z_interpolated(nalgebra_glm::vec2(7.5, 3.0)), edge);
+        assert_eq!(map.get_z_interpolated(nalgebra_glm::vec2(20.0, 3.0)), edge);
+        assert_eq!(map.get_z_interpolated(nalgebra_glm::vec2(-3.0, -3.0)), 0.0);
+        assert_eq!(
+            map.get_normal(nalgebra_glm::vec2(7.5, 7.5)),
+            nalgebra_glm::vec3(0.0, 0.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn non_finite_points_have_no_height() {
+        let map = ramp(8);
+        for p in [
+            nalgebra_glm::vec2(f32::NAN, 1.0),
+            nalgebra_glm::vec2(1.0, f32::INFINITY),
+        ] {
+            assert_eq!(map.checked_z_interpolated(p), None);
+            assert_eq!(map.checked_normal(p), None);
+        }
+    }
+
+    fn gradient(octaves: i32) -> NoiseParams {
+        NoiseParams {
+            kind: NoiseKind::Gradient,
+            octaves,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn value_noise_matches_the_original_islands() {
+        let map = PerlinMap::new(16, 0.03, 7, 2.0);
+        for y in 0..16 {
+            for x in 0..16 {
+                let expected = perlin2d_original(x as f32, y as f32, 0.03, 10, 7) * 2.0;
+                assert_eq!(map.cells[y * 16 + x].height, expected);
+            }
+        }
+    }
+
+    /// Value noise as it was before it took octave parameters
+    fn perlin2d_original(x: f32, y: f32, freq: f32, depth: i32, seed: i32) -> f32 {
+        let (mut xa, mut ya) = (x * freq, y * freq);
+        let (mut amp, mut fin, mut div) = (1.0, 0.0, 0.0);
+        for _ in 0..depth {
+            div += 256.0 * amp;
+            fin += noise2d(xa, ya, seed) * amp;
+            amp /= 2.0;
+            xa *= 2.0;
+            ya *= 2.0;
+        }
+        fin / div
+    }
+
+    #[test]
+    fn gradient_noise_is_the_same_for_a_seed() {
+        let a = PerlinMap::with_noise(32, 5, &gradient(4));
+        let b = PerlinMap::with_noise(32, 5, &gradient(4));
+        let c = PerlinMap::with_noise(32, 6, &gradient(4));
+        let heights =
+            |map: &PerlinMap| map.cells.iter().map(|cell| cell.height).collect::<Vec<_>>();
+        assert_eq!(heights(&a), heights(&b));
+        assert_ne!(heights(&a), heights(&c));
+    }
+
+    #[test]
+    fn gradient_noise_is_flat_at_grid_points() {
+        let params = NoiseParams {
+            frequency: 1.0,
+            ..gradient(1)
+        };
+        let map = PerlinMap::with_noise(8, 3, &params);
+        assert!(map.cells.iter().all(|cell| cell.height == 0.5));
+    }
+
+    #[test]
+    fn bulges_leave_channels_between_them() {
+        let mut map = map_from_heights(200, &[0.5; 200 * 200]);
+        let bulges = [
+            Bulge {
+                center: nalgebra_glm::vec2(50.0, 100.0),
+                radius: 30.0,
+            },
+            Bulge {
+                center: nalgebra_glm::vec2(150.0, 100.0),
+                radius: 30.0,
+            },
+        ];
+        map.create_bulges(&bulges);
+        assert!(map.height(nalgebra_glm::vec2(50.0, 100.0)) > SEA_LEVEL);
+        assert!(map.height(nalgebra_glm::vec2(150.0, 100.0)) > SEA_LEVEL);
+        assert!(map.height(nalgebra_glm::vec2(100.0, 100.0)) < SEA_LEVEL);
+        assert_eq!(Bulge::owner(&bulges, nalgebra_glm::vec2(90.0, 120.0)), 0);
+        assert_eq!(Bulge::owner(&bulges, nalgebra_glm::vec2(110.0, 80.0)), 1);
+    }
+
+    proptest! {
+        #[test]
+        fn gradient_noise_stays_in_range(
+            seed in any::<u64>(),
+            x in -1000.0f32..1000.0,
+            y in -1000.0f32..1000.0,
+            octaves in 1..10,
+        ) {
+            let permutation = Permutation::new(seed);
+            let noise = gradient2d(x, y, &gradient(octaves), &permutation);
+            prop_assert!((0.0..=1.0).contains(&noise), "{} is out of range", noise);
+        }
+
+        #[test]
+        fn interpolated_heights_lie_between_the_tiles_corners(
+            heights in prop::collection::vec(-10.0f32..10.0, 6 * 6),
+            x in -2.0f32..8.0,
+            y in -2.0f32..8.0,
+        ) {
+            let map = map_from_heights(6, &heights);
+            let p = nalgebra_glm::vec2(x, y);
+            let origin = nalgebra_glm::floor(&p);
+            let corners = [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)]
+                .map(|(dx, dy)| map.vertex_height(origin + nalgebra_glm::vec2(dx, dy)));
+            let low = corners.iter().copied().fold(f32::MAX, f32::min);
+            let high = corners.iter().copied().fold(f32::MIN, f32::max);
+            let z = map.get_z_interpolated(p);
+            prop_assert!(z >= low - 1e-4 && z <= high + 1e-4, "{} isn't in [{}, {}]", z, low, high);
+        }
+
+        #[test]
+        fn normals_are_unit_length_and_point_up(
+            heights in prop::collection::vec(-10.0f32..10.0, 6 * 6),
+            x in -2.0f32..8.0,
+            y in -2.0f32..8.0,
+        ) {
+            let map = map_from_heights(6, &heights);
+            let normal = map.get_normal(nalgebra_glm::vec2(x, y));
+            prop_assert!((nalgebra_glm::length(&normal) - 1.0).abs() < 1e-4);
+            prop_assert!(normal.z > 0.0);
+        }
+    }
+}
@@ -1,13 +1,44 @@
-use specs::{Component, DenseVecStorage};
+use serde::{Deserialize, Serialize};
+use specs::{prelude::*, Component};
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize)]
 #[storage(DenseVecStorage)]
 pub struct PositionComponent {
     pub pos: nalgebra_glm::Vec3,
 }
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize)]
 #[storage(DenseVecStorage)]
 pub struct VelocityComponent {
     pub vel: nalgebra_glm::Vec3,
 }
+
+/// Keeps an entity at an offset from another one, like something carried. The offset doesn't turn with the parent, so
+/// whatever owns the child turns the offset itself.
+#[derive(Component)]
+#[storage(HashMapStorage)]
+pub struct ParentComponent {
+    pub parent: Entity,
+    pub offset: nalgebra_glm::Vec3,
+}
+
+/// Moves children along with their parents. Children whose parent is gone stay where they were left.
+pub struct ParentSystem;
+impl<'a> System<'a> for ParentSystem {
+    type SystemData = (
+        ReadStorage<'a, ParentComponent>,
+        WriteStorage<'a, PositionComponent>,
+        Entities<'a>,
+    );
+
+    fn run(&mut self, (parents, mut positions, entities): Self::SystemData) {
+        for (parent, child) in (&parents, &entities).join() {
+            let Some(parent_pos) = positions.get(parent.parent).map(|p| p.pos) else {
+                continue;
+            };
+            if let Some(position) = positions.get_mut(child) {
+                position.pos = parent_pos + parent.offset;
+            }
+        }
+    }
+}
@@ -0,0 +1,231 @@
+// Timing where a frame goes. Systems added with `add_timed` have how long they take to run recorded, and the GPU ones
+// added with `add_gpu_timed` also have how long the GPU spends on what they draw, from timer queries. The times are
+// averaged over recent frames, so that the numbers are steady enough to read.
+
+use std::time::Instant;
+
+use gl::types::{GLuint, GLuint64};
+use specs::{DispatcherBuilder, System, SystemData, Write};
+
+const SMOOTHING: f32 = 0.05; //< How much of each new time goes into the average
+
+/// How long something took, recently
+struct Timing {
+    name: String,
+    last_ms: f32,
+    average_ms: f32,
+}
+
+/// How long each timed system takes, in the order they were first timed
+#[derive(Default)]
+pub struct ProfileResource {
+    cpu: Vec<Timing>,
+    gpu: Vec<Timing>,
+}
+
+impl ProfileResource {
+    fn record(timings: &mut Vec<Timing>, name: &str, ms: f32) {
+        match timings.iter_mut().find(|timing| timing.name == name) {
+            Some(timing) => {
+                timing.last_ms = ms;
+                timing.average_ms += (ms - timing.average_ms) * SMOOTHING;
+            }
+            None => timings.push(Timing {
+                name: name.to_string(),
+                last_ms: ms,
+                average_ms: ms,
+            }),
+        }
+    }
+
+    pub fn record_cpu(&mut self, name: &str, ms: f32) {
+        Self::record(&mut self.cpu, name, ms);
+    }
+
+    pub fn record_gpu(&mut self, name: &str, ms: f32) {
+        Self::record(&mut self.gpu, name, ms);
+    }
+
+    /// The systems that take longest on average, with their average times in milliseconds
+    pub fn slowest_systems(&self, count: usize) -> Vec<(&str, f32)> {
+        let mut slowest: Vec<(&str, f32)> = self
+            .cpu
+            .iter()
+            .map(|timing| (timing.name.as_str(), timing.average_ms))
+            .collect();
+        slowest.sort_by(|a, b| b.1.total_cmp(&a.1));
+        slowest.truncate(count);
+        slowest
+    }
+
+    /// Average GPU times of the timed passes in milliseconds, in the order they're drawn
+    pub fn gpu_passes(&self) -> Vec<(&str, f32)> {
+        self.gpu
+            .iter()
+            .map(|timing| (timing.name.as_str(), timing.average_ms))
+            .collect()
+    }
+
+    /// Every timing, as a table for dumping to a file
+    pub fn report(&self) -> String {
+        let mut report = format!("{:<32} {:>10} {:>10}\n", "", "last ms", "average ms");
+        for (heading, timings) in [("cpu", &self.cpu), ("gpu", &self.gpu)] {
+            report.push_str(&format!("{}\n", heading));
+            for timing in timings {
+                report.push_str(&format!(
+                    "  {:<30} {:>10.3} {:>10.3}\n",
+                    timing.name, timing.last_ms, timing.average_ms
+                ));
+            }
+            let total: f32 = timings.iter().map(|timing| timing.average_ms).sum();
+            report.push_str(&format!("  {:<30} {:>10} {:>10.3}\n", "total", "", total));
+        }
+        report
+    }
+}
+
+/// Runs a system, and records how long it took
+pub struct Timed<S> {
+    name: String,
+    system: S,
+}
+
+impl<'a, S: System<'a>> System<'a> for Timed<S>
+where
+    S::SystemData: SystemData<'a>,
+{
+    type SystemData = (S::SystemData, Write<'a, ProfileResource>);
+
+    fn run(&mut self, (data, mut profile): Self::SystemData) {
+        let start = Instant::now();
+        self.system.run(data);
+        profile.record_cpu(&self.name, start.elapsed().as_secs_f32() * 1000.0);
+    }
+}
+
+/// Runs a system that draws, and records how long the GPU spent on what it drew as well. Queries take a frame or so to
+/// come back, and a new one isn't started until the last one has, so not every frame is timed.
+pub struct GpuTimed<S> {
+    timed: Timed<S>,
+    query: Option<GLuint>, //< Made the first time the system runs, since it needs the GL context
+    pending: bool,         //< Whether the query is still waiting on its result
+}
+
+impl<'a, S: System<'a>> System<'a> for GpuTimed<S>
+where
+    S::SystemData: SystemData<'a>,
+{
+    type SystemData = <Timed<S> as System<'a>>::SystemData;
+
+    fn run(&mut self, (data, mut profile): Self::SystemData) {
+        let query = *self.query.get_or_insert_with(|| {
+            let mut id: GLuint = 0;
+            unsafe {
+                gl::GenQueries(1, &mut id);
+            }
+            id
+        });
+        if self.pending {
+            let mut available: GLuint = 0;
+            unsafe {
+                gl::GetQueryObjectuiv(query, gl::QUERY_RESULT_AVAILABLE, &mut available);
+            }
+            if available != 0 {
+                let mut nanoseconds: GLuint64 = 0;
+                unsafe {
+                    gl::GetQueryObjectui64v(query, gl::QUERY_RESULT, &mut nanoseconds);
+                }
+                profile.record_gpu(&self.timed.name, nanoseconds as f32 / 1_000_000.0);
+                self.pending = false;
+            }
+        }
+
+        let start_query = !self.pending;
+        if start_query {
+            unsafe {
+                gl::BeginQuery(gl::TIME_ELAPSED, query);
+            }
+        }
+        self.timed.run((data, profile));
+        if start_query {
+            unsafe {
+                gl::EndQuery(gl::TIME_ELAPSED);
+            }
+            self.pending = true;
+        }
+    }
+}
+
+impl<S> Drop for GpuTimed<S> {
+    fn drop(&mut self) {
+        if let Some(query) = self.query {
+            unsafe {
+                gl::DeleteQueries(1, &query);
+            }
+        }
+    }
+}
+
+/// Adding systems to a dispatcher with their times recorded
+pub trait TimedDispatch {
+    fn add_timed<S>(&mut self, system: S, name: &str, dep: &[&str])
+    where
+        S: for<'c> System<'c> + Send + 'static,
+        for<'c> <S as System<'c>>::SystemData: SystemData<'c>;
+
+    /// For systems that draw. Only one can be drawing at a time, since GPU timer queries can't overlap.
+    fn add_gpu_timed<S>(&mut self, system: S, name: &str, dep: &[&str])
+    where
+        S: for<'c> System<'c> + Send + 'static,
+        for<'c> <S as System<'c>>::SystemData: SystemData<'c>;
+}
+
+impl TimedDispatch for DispatcherBuilder<'_, '_> {
+    fn add_timed<S>(&mut self, system: S, name: &str, dep: &[&str])
+    where
+        S: for<'c> System<'c> + Send + 'static,
+        for<'c> <S as System<'c>>::SystemData: SystemData<'c>,
+    {
+        let timed = Timed {
+            name: name.to_string(),
+            system,
+        };
+        self.add(timed, name, dep);
+    }
+
+    fn add_gpu_timed<S>(&mut self, system: S, name: &str, dep: &[&str])
+    where
+        S: for<'c> System<'c> + Send + 'static,
+        for<'c> <S as System<'c>>::SystemData: SystemData<'c>,
+    {
+        let timed = GpuTimed {
+            timed: Timed {
+                name: name.to_string(),
+                system,
+            },
+            query: None,
+            pending: false,
+        };
+        self.add(timed, name, dep);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slowest_systems_come_first() {
+        let mut profile = ProfileResource::default();
+        profile.record_cpu("physics", 1.0);
+        profile.record_cpu("ai", 3.0);
+        profile.record_cpu("sky", 0.5);
+        profile.record_cpu("physics", 2.0);
+        let slowest = profile.slowest_systems(2);
+        assert_eq!(slowest.len(), 2);
+        assert_eq!(slowest[0], ("ai", 3.0));
+        // Averaged toward the new time, rather than jumping to it
+        assert_eq!(slowest[1].0, "physics");
+        assert!(slowest[1].1 > 1.0 && slowest[1].1 < 2.0);
+    }
+}
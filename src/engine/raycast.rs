@@ -0,0 +1,242 @@
+// Casting rays against the terrain and against boxes. The terrain is walked tile by tile along the ray, so a ray only
+// tests the triangles it passes over. Directions don't need to be normalized, distances are in multiples of the
+// direction given.
+
+use super::{
+    aabb::AABB,
+    perlin::{intersect, tri_normal, PerlinMap},
+};
+
+/// Where a ray hit something
+#[derive(Clone, Copy, Debug)]
+pub struct RayHit {
+    pub pos: nalgebra_glm::Vec3,
+    pub normal: nalgebra_glm::Vec3, //< Points back out of whatever was hit
+    pub t: f32,                     //< How far along the ray, pos = origin + t * dir
+}
+
+impl PerlinMap {
+    /// The first point the ray hits the terrain, if it hits it at all. Only the map itself can be hit, rays leaving
+    /// the map's edge hit nothing.
+    pub fn raycast(&self, origin: nalgebra_glm::Vec3, dir: nalgebra_glm::Vec3) -> Option<RayHit> {
+        let width = self.width() as f32;
+        let map_bounds = AABB::from_min_max(
+            nalgebra_glm::vec3(0.0, 0.0, f32::MIN),
+            nalgebra_glm::vec3(width, width, f32::MAX),
+        );
+        let (t_enter, t_exit) = ray_aabb_span(origin, dir, &map_bounds)?;
+        let t_enter = t_enter.max(0.0);
+
+        // Digital differential analyzer, visiting every tile the ray passes over in order
+        let start = origin + t_enter * dir;
+        let mut tile = nalgebra_glm::vec2(
+            start.x.floor().clamp(0.0, width - 1.0),
+            start.y.floor().clamp(0.0, width - 1.0),
+        );
+        let step = nalgebra_glm::vec2(dir.x.signum(), dir.y.signum());
+        let t_delta = nalgebra_glm::vec2((1.0 / dir.x).abs(), (1.0 / dir.y).abs());
+        let mut t_next = nalgebra_glm::vec2(
+            next_boundary(origin.x, dir.x, tile.x),
+            next_boundary(origin.y, dir.y, tile.y),
+        );
+
+        let mut t = t_enter;
+        while t <= t_exit {
+            let t_leave = t_next.x.min(t_next.y).min(t_exit);
+            if let Some(hit) = self.raycast_tile(tile, origin, dir, t, t_leave) {
+                return Some(hit);
+            }
+            t = t_leave;
+            if t_next.x < t_next.y {
+                tile.x += step.x;
+                t_next.x += t_delta.x;
+            } else {
+                tile.y += step.y;
+                t_next.y += t_delta.y;
+            }
+            if self.oob(tile) || t_leave >= t_exit {
+                break;
+            }
+        }
+        None
+    }
+
+    /// Tests the two triangles of one tile, only counting hits while the ray is over the tile
+    fn raycast_tile(
+        &self,
+        tile: nalgebra_glm::Vec2,
+        origin: nalgebra_glm::Vec3,
+        dir: nalgebra_glm::Vec3,
+        t_min: f32,
+        t_max: f32,
+    ) -> Option<RayHit> {
+        const TRIANGLES: [[(f32, f32); 3]; 2] = [
+            [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)], // Bottom triangle, contains the origin
+            [(1.0, 0.0), (1.0, 1.0), (0.0, 1.0)], // Top triangle, contains the anti-origin
+        ];
+        TRIANGLES
+            .iter()
+            .filter_map(|offsets| {
+                let corners = offsets.map(|(x, y)| {
+                    let corner = tile + nalgebra_glm::vec2(x, y);
+                    nalgebra_glm::vec3(corner.x, corner.y, self.vertex_height(corner))
+                });
+                let (pos, t) = intersect(corners[0], corners[1], corners[2], origin, dir)?;
+                if t < t_min || t > t_max {
+                    return None;
+                }
+                let normal = tri_normal(corners[0], corners[1], corners[2]);
+                Some(RayHit { pos, normal, t })
+            })
+            .min_by(|a, b| a.t.total_cmp(&b.t))
+    }
+}
+
+/// The first point a ray hits a box from outside, if it hits it at all. Rays starting inside the box hit it straight
+/// away, at their origin.
+pub fn raycast_aabb(
+    origin: nalgebra_glm::Vec3,
+    dir: nalgebra_glm::Vec3,
+    aabb: &AABB,
+) -> Option<RayHit> {
+    let (t_enter, _) = ray_aabb_span(origin, dir, aabb)?;
+    if t_enter <= 0.0 {
+        return Some(RayHit {
+            pos: origin,
+            normal: -dir,
+            t: 0.0,
+        });
+    }
+    let pos = origin + t_enter * dir;
+    // The ray came in through whichever face it's closest to
+    let mut normal = nalgebra_glm::Vec3::zeros();
+    let mut closest = f32::MAX;
+    for axis in 0..3 {
+        for (face, sign) in [(aabb.min[axis], -1.0), (aabb.max[axis], 1.0)] {
+            let dist = (pos[axis] - face).abs();
+            if dist < closest {
+                closest = dist;
+                normal = nalgebra_glm::Vec3::zeros();
+                normal[axis] = sign;
+            }
+        }
+    }
+    Some(RayHit {
+        pos,
+        normal,
+        t: t_enter,
+    })
+}
+
+/// The span of the ray inside a box, by the slab method. None if the ray misses the box, or the box is behind it.
+fn ray_aabb_span(
+    origin: nalgebra_glm::Vec3,
+    dir: nalgebra_glm::Vec3,
+    aabb: &AABB,
+) -> Option<(f32, f32)> {
+    let mut t_enter = f32::MIN;
+    let mut t_exit = f32::MAX;
+    for axis in 0..3 {
+        if dir[axis] == 0.0 {
+            // Parallel to this pair of faces, so it's either always between them or never
+            if origin[axis] < aabb.min[axis] || origin[axis] > aabb.max[axis] {
+                return None;
+            }
+            continue;
+        }
+        let t0 = (aabb.min[axis] - origin[axis]) / dir[axis];
+        let t1 = (aabb.max[axis] - origin[axis]) / dir[axis];
+        t_enter = t_enter.max(t0.min(t1));
+        t_exit = t_exit.min(t0.max(t1));
+    }
+    if t_enter > t_exit || t_exit < 0.0 {
+        None
+    } else {
+        Some((t_enter, t_exit))
+    }
+}
+
+/// How far along the ray it next crosses a tile edge, along one axis
+fn next_boundary(origin: f32, dir: f32, tile: f32) -> f32 {
+    if dir > 0.0 {
+        (tile + 1.0 - origin) / dir
+    } else if dir < 0.0 {
+        (tile - origin) / dir
+    } else {
+        f32::MAX
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map() -> PerlinMap {
+        PerlinMap::new(32, 0.1, 7, 4.0)
+    }
+
+    #[test]
+    fn rays_straight_down_hit_the_terrain_height() {
+        let map = map();
+        for p in [
+            nalgebra_glm::vec2(3.2, 4.7),
+            nalgebra_glm::vec2(10.9, 20.1),
+            nalgebra_glm::vec2(25.5, 8.5),
+        ] {
+            let hit = map
+                .raycast(
+                    nalgebra_glm::vec3(p.x, p.y, 100.0),
+                    nalgebra_glm::vec3(0.0, 0.0, -1.0),
+                )
+                .unwrap();
+            assert!((hit.pos.z - map.get_z_interpolated(p)).abs() < 0.001);
+            assert!(hit.normal.z > 0.0);
+        }
+    }
+
+    #[test]
+    fn slanted_rays_stop_at_the_first_hit() {
+        let map = map();
+        let origin = nalgebra_glm::vec3(-5.0, 2.5, 10.0);
+        let dir = nalgebra_glm::vec3(1.0, 0.5, -0.3);
+        let hit = map.raycast(origin, dir).unwrap();
+        assert!((hit.pos.z - map.get_z_interpolated(hit.pos.xy())).abs() < 0.001);
+        // Everything before the hit is above the terrain
+        for i in 0..100 {
+            let p = origin + dir * (hit.t * i as f32 / 100.0);
+            assert!(map.oob(p.xy()) || p.z >= map.get_z_interpolated(p.xy()) - 0.001);
+        }
+    }
+
+    #[test]
+    fn rays_leaving_the_map_hit_nothing() {
+        let map = map();
+        let hit = map.raycast(
+            nalgebra_glm::vec3(16.0, 16.0, 100.0),
+            nalgebra_glm::vec3(1.0, 0.0, 0.0),
+        );
+        assert!(hit.is_none());
+    }
+
+    #[test]
+    fn rays_hit_the_facing_side_of_boxes() {
+        let aabb = AABB::from_min_max(
+            nalgebra_glm::vec3(1.0, -1.0, -1.0),
+            nalgebra_glm::vec3(3.0, 1.0, 1.0),
+        );
+        let hit = raycast_aabb(
+            nalgebra_glm::vec3(0.0, 0.0, 0.0),
+            nalgebra_glm::vec3(2.0, 0.0, 0.0),
+            &aabb,
+        )
+        .unwrap();
+        assert_eq!(hit.t, 0.5);
+        assert_eq!(hit.normal, nalgebra_glm::vec3(-1.0, 0.0, 0.0));
+        assert!(raycast_aabb(
+            nalgebra_glm::vec3(0.0, 0.0, 0.0),
+            nalgebra_glm::vec3(-1.0, 0.0, 0.0),
+            &aabb,
+        )
+        .is_none());
+    }
+}
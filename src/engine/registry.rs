@@ -0,0 +1,157 @@
+// The component registry. Gameplay components are listed once, each under a stable name that stays the same if the
+// type is renamed or moved. Snapshots and saves go through the registry by name, so they don't need to know the types.
+
+use serde::{de::DeserializeOwned, Serialize};
+use specs::{Component, Entity, World, WorldExt};
+
+type WriteFn = fn(&World, Entity) -> Result<Option<String>, String>;
+type ReadFn = fn(&World, Entity, &str) -> Result<(), String>;
+
+struct Registration {
+    name: &'static str,
+    register: fn(&mut World),
+    write: WriteFn,
+    read: Option<ReadFn>, //< None for components that can only be written
+}
+
+/// Every registered component type, by name
+#[derive(Default)]
+pub struct ComponentRegistry {
+    registrations: Vec<Registration>,
+}
+
+impl ComponentRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a component that can be written out and read back in
+    pub fn with<T>(mut self, name: &'static str) -> Self
+    where
+        T: Component + Serialize + DeserializeOwned + Send + Sync,
+        T::Storage: Default,
+    {
+        self.registrations.push(Registration {
+            name,
+            register: |world| world.register::<T>(),
+            write: write_component::<T>,
+            read: Some(read_component::<T>),
+        });
+        self
+    }
+
+    /// Adds a component that can only be written out, like one that points to other entities, whose ids won't mean
+    /// anything in another world
+    pub fn with_write_only<T>(mut self, name: &'static str) -> Self
+    where
+        T: Component + Serialize + Send + Sync,
+        T::Storage: Default,
+    {
+        self.registrations.push(Registration {
+            name,
+            register: |world| world.register::<T>(),
+            write: write_component::<T>,
+            read: None,
+        });
+        self
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.registrations
+            .iter()
+            .map(|registration| registration.name)
+    }
+
+    /// Registers every component with the world
+    pub fn register_all(&self, world: &mut World) {
+        for registration in &self.registrations {
+            (registration.register)(world);
+        }
+    }
+
+    /// The entity's component with the given name as RON, or None if it doesn't have one
+    pub fn write(
+        &self,
+        world: &World,
+        entity: Entity,
+        name: &str,
+    ) -> Result<Option<String>, String> {
+        let registration = self.get(name)?;
+        (registration.write)(world, entity)
+    }
+
+    /// Gives the entity the component with the given name, read from RON, replacing any it already has
+    pub fn read(&self, world: &World, entity: Entity, name: &str, ron: &str) -> Result<(), String> {
+        let registration = self.get(name)?;
+        let read = registration
+            .read
+            .ok_or_else(|| format!("{} components can't be read back in", name))?;
+        read(world, entity, ron)
+    }
+
+    fn get(&self, name: &str) -> Result<&Registration, String> {
+        self.registrations
+            .iter()
+            .find(|registration| registration.name == name)
+            .ok_or_else(|| format!("no component is registered as {}", name))
+    }
+}
+
+fn write_component<T: Component + Serialize>(
+    world: &World,
+    entity: Entity,
+) -> Result<Option<String>, String> {
+    let storage = world.read_storage::<T>();
+    storage
+        .get(entity)
+        .map(|component| ron::to_string(component).map_err(|e| e.to_string()))
+        .transpose()
+}
+
+fn read_component<T: Component + DeserializeOwned>(
+    world: &World,
+    entity: Entity,
+    ron: &str,
+) -> Result<(), String> {
+    let component: T = ron::from_str(ron).map_err(|e| e.to_string())?;
+    world
+        .write_storage::<T>()
+        .insert(entity, component)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use specs::{Builder, VecStorage};
+
+    use super::*;
+
+    #[derive(Component, Serialize, Deserialize, PartialEq, Debug)]
+    #[storage(VecStorage)]
+    struct CounterComponent {
+        count: u32,
+    }
+
+    #[test]
+    fn components_round_trip_by_name() {
+        let registry = ComponentRegistry::new().with::<CounterComponent>("Counter");
+        let mut world = World::new();
+        registry.register_all(&mut world);
+        let a = world
+            .create_entity()
+            .with(CounterComponent { count: 3 })
+            .build();
+        let b = world.create_entity().build();
+
+        let ron = registry.write(&world, a, "Counter").unwrap().unwrap();
+        assert_eq!(registry.write(&world, b, "Counter"), Ok(None));
+        registry.read(&world, b, "Counter", &ron).unwrap();
+        assert_eq!(
+            world.read_storage::<CounterComponent>().get(b),
+            Some(&CounterComponent { count: 3 })
+        );
+        assert!(registry.read(&world, b, "Missing", &ron).is_err());
+    }
+}
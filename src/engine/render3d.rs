@@ -1,9 +1,54 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
 use crate::App;
 
-use super::{camera::Camera, objects::*, physics::PositionComponent, shadow_map::SunResource};
+use super::{
+    animation::{AnimationComponent, Pose},
+    camera::Camera,
+    error::EngineError,
+    interpolation::{interpolate, PreviousPositionComponent},
+    objects::*,
+    physics::PositionComponent,
+    settings::GraphicsSettings,
+    shadow_map::SunResource,
+};
 
 use obj::{load_obj, Obj, TexturedVertex};
-use specs::{Component, DenseVecStorage, Join, Read, ReadStorage, System, Write};
+use specs::{
+    Component, DenseVecStorage, HashMapStorage, Join, NullStorage, Read, ReadStorage, System, Write,
+};
+
+const WHITE: nalgebra_glm::Vec3 = nalgebra_glm::Vec3::new(1.0, 1.0, 1.0);
+pub const MAX_POINT_LIGHTS: usize = 8; //< Has to match the size of the light arrays in 3d.frag
+/// Set on any program a mesh is drawn with
+pub const MESH_UNIFORMS: &[&str] = &["u_model_matrix", "u_view_matrix", "u_proj_matrix"];
+/// Set on the lit 3D program each frame, along with the mesh's
+pub const LIT_UNIFORMS: &[&str] = &[
+    "u_resolution",
+    "u_sun_dir",
+    "light_mvp",
+    "u_tint",
+    "u_light_count",
+    "u_light_pos",
+    "u_light_color",
+    "u_light_radius",
+    "u_pcf_radius",
+    "u_overlay",
+    "u_cull_dist",
+    "u_splat",
+    "u_splat_repeats",
+    "u_map_width",
+    "u_chunk_size",
+    "u_fog_color",
+    "u_fog_density",
+    "u_fog_start",
+    "u_fog_end",
+    "u_water_level",
+    "u_caustics_strength",
+    "u_time",
+    "u_moon_dir",
+    "u_moon_color",
+];
 
 pub struct Input {
     ibo: Ibo,
@@ -45,8 +90,9 @@ impl Mesh {
         }
     }
 
-    pub fn from_obj(obj_file_data: &[u8], color: nalgebra_glm::Vec3) -> Self {
-        let obj: Obj<TexturedVertex> = load_obj(&obj_file_data[..]).unwrap();
+    pub fn from_obj(obj_file_data: &[u8], color: nalgebra_glm::Vec3) -> Result<Self, EngineError> {
+        let obj: Obj<TexturedVertex> =
+            load_obj(obj_file_data).map_err(|err| EngineError::Mesh(err.to_string()))?;
         let vb: Vec<TexturedVertex> = obj.vertices;
 
         let indices = vec_u32_from_vec_u16(&obj.indices);
@@ -62,13 +108,13 @@ impl Mesh {
 
         let data = vec![vertices, normals, uv, colors];
 
-        Self::new(indices, data)
+        Ok(Self::new(indices, data))
     }
 
     pub fn set_3d(program: &Program, sun_dir: nalgebra_glm::Vec3, resolution: nalgebra_glm::Vec2) {
         program.set();
-        let u_resolution = Uniform::new(program.id(), "u_resolution").unwrap();
-        let u_sun_dir = Uniform::new(program.id(), "u_sun_dir").unwrap();
+        let u_resolution = program.uniform("u_resolution");
+        let u_sun_dir = program.uniform("u_sun_dir");
         unsafe {
             gl::Uniform2f(u_resolution.id, resolution.x, resolution.y);
             gl::Uniform3f(u_sun_dir.id, sun_dir.x, sun_dir.y, sun_dir.z);
@@ -85,6 +131,16 @@ impl Mesh {
         model_matrix
     }
 
+    /// The model matrix of a mesh in an animation's pose. The pose's offset isn't scaled along with the mesh.
+    pub fn get_posed_model_matrix(
+        position: nalgebra_glm::Vec3,
+        scale: nalgebra_glm::Vec3,
+        pose: &Pose,
+    ) -> nalgebra_glm::Mat4 {
+        let model_matrix = nalgebra_glm::translation(&position) * pose.matrix();
+        nalgebra_glm::scale(&model_matrix, &scale)
+    }
+
     pub fn draw(
         &self,
         program: &Program,
@@ -92,10 +148,18 @@ impl Mesh {
         position: nalgebra_glm::Vec3,
         scale: nalgebra_glm::Vec3,
     ) {
-        let u_model_matrix = Uniform::new(program.id(), "u_model_matrix").unwrap();
-        let u_view_matrix = Uniform::new(program.id(), "u_view_matrix").unwrap();
-        let u_proj_matrix = Uniform::new(program.id(), "u_proj_matrix").unwrap();
-        let model_matrix = Mesh::get_model_matrix(position, scale);
+        self.draw_with_model_matrix(program, camera, Mesh::get_model_matrix(position, scale));
+    }
+
+    pub fn draw_with_model_matrix(
+        &self,
+        program: &Program,
+        camera: &Camera,
+        model_matrix: nalgebra_glm::Mat4,
+    ) {
+        let u_model_matrix = program.uniform("u_model_matrix");
+        let u_view_matrix = program.uniform("u_view_matrix");
+        let u_proj_matrix = program.uniform("u_proj_matrix");
         let (view_matrix, proj_matrix) = camera.gen_view_proj_matrices();
         unsafe {
             gl::UniformMatrix4fv(
@@ -117,6 +181,7 @@ impl Mesh {
                 &proj_matrix.columns(0, 4)[0],
             );
             self.set();
+            count_draw_call();
             gl::DrawElements(
                 gl::TRIANGLES,
                 self.indices_len(),
@@ -139,6 +204,64 @@ impl Mesh {
     }
 }
 
+/// An upright capsule standing on the origin: a cylinder with half a sphere on each end. Stands in for a body that's
+/// only there to cast a shadow.
+pub fn create_capsule_mesh(radius: f32, height: f32) -> Mesh {
+    const SEGMENTS: usize = 12; //< Around the capsule
+    const RINGS: usize = 4; //< From each pole to the cylinder
+
+    // Rings from the bottom pole up to the top pole. The bottom half sphere's last ring and the top half sphere's first
+    // ring are both on the equator, with the cylinder between them.
+    let mut rings = vec![];
+    for k in 0..=RINGS {
+        let angle = (k as f32 / RINGS as f32 - 1.0) * std::f32::consts::FRAC_PI_2;
+        rings.push((angle, radius));
+    }
+    for k in 0..=RINGS {
+        let angle = k as f32 / RINGS as f32 * std::f32::consts::FRAC_PI_2;
+        rings.push((angle, height - radius));
+    }
+
+    let mut vertices = vec![];
+    let mut normals = vec![];
+    let mut uv = vec![];
+    let mut colors = vec![];
+    for (i, (angle, center_z)) in rings.iter().enumerate() {
+        for j in 0..=SEGMENTS {
+            let around = j as f32 / SEGMENTS as f32 * std::f32::consts::TAU;
+            let normal = nalgebra_glm::vec3(
+                angle.cos() * around.cos(),
+                angle.cos() * around.sin(),
+                angle.sin(),
+            );
+            vertices.extend([
+                normal.x * radius,
+                normal.y * radius,
+                center_z + normal.z * radius,
+            ]);
+            normals.extend([normal.x, normal.y, normal.z]);
+            uv.extend([
+                j as f32 / SEGMENTS as f32,
+                i as f32 / (rings.len() - 1) as f32,
+                0.0,
+            ]);
+            colors.extend([1.0, 1.0, 1.0]);
+        }
+    }
+
+    let mut indices = vec![];
+    let row = (SEGMENTS + 1) as u32;
+    for i in 0..(rings.len() - 1) as u32 {
+        for j in 0..SEGMENTS as u32 {
+            let below = i * row + j;
+            let above = below + row;
+            indices.extend([below, below + 1, above + 1, below, above + 1, above]);
+        }
+    }
+
+    Mesh::new(indices, vec![vertices, normals, uv, colors])
+}
+
 fn flatten_positions(vertices: &Vec<TexturedVertex>) -> Vec<f32> {
     let mut retval = vec![];
     for vertex in vertices {
@@ -179,7 +302,7 @@ fn vec_u32_from_vec_u16(input: &Vec<u16>) -> Vec<u32> {
 
 #[derive(Default)]
 pub struct MeshMgr {
-    meshes: Vec<Mesh>,
+    meshes: Vec<Option<Mesh>>, //< None where a mesh was removed, the slot gets reused
 }
 
 impl MeshMgr {
@@ -188,16 +311,38 @@ impl MeshMgr {
     }
 
     pub fn add_mesh(&mut self, mesh: Mesh) -> usize {
+        if let Some(id) = self.meshes.iter().position(|slot| slot.is_none()) {
+            self.meshes[id] = Some(mesh);
+            return id;
+        }
         let id = self.meshes.len();
-        self.meshes.push(mesh);
+        self.meshes.push(Some(mesh));
         id
     }
 
+    /// Removes a mesh, freeing its GL buffers. Its id may be handed out again by `add_mesh`.
+    pub fn remove_mesh(&mut self, id: usize) {
+        self.meshes[id] = None;
+    }
+
     pub fn get_mesh(&self, id: usize) -> &Mesh {
-        self.meshes.get(id).unwrap()
+        self.meshes.get(id).unwrap().as_ref().unwrap()
     }
 }
 
+/// Draw calls made since `take_draw_calls` was last called
+static DRAW_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts a draw call towards the diagnostics. Everything drawn through a `Mesh` is counted already.
+pub fn count_draw_call() {
+    DRAW_CALLS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// The draw calls made since the last call, which is once a frame
+pub fn take_draw_calls() -> usize {
+    DRAW_CALLS.swap(0, Ordering::Relaxed)
+}
+
 #[derive(Default)]
 pub struct MeshMgrResource {
     pub data: MeshMgr,
@@ -209,6 +354,15 @@ pub struct OpenGlResource {
     pub program: Program,
 }
 
+/// Which pass a mesh is drawn in. Opaque meshes go first, nearest first, then see-through ones, furthest first and
+/// without writing depth, so that they blend over everything behind them. The water goes over both.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
+pub enum RenderLayer {
+    #[default]
+    Opaque,
+    Transparent,
+}
+
 #[derive(Component)]
 #[storage(DenseVecStorage)]
 pub struct MeshComponent {
@@ -216,6 +370,126 @@ pub struct MeshComponent {
     pub scale: nalgebra_glm::Vec3,
     pub texture: Texture,
     pub render_dist: Option<f32>, //< When Some, only render when the position is this close to the camera
+    pub shadow_only: bool, //< Only drawn into the shadow map, for bodies the camera is inside of
+    pub layer: RenderLayer,
+    pub order: i32, //< Within a layer, lower orders are drawn first, before distance is considered
+}
+
+/// Multiplies the color of a mesh, for telling apart things that share a texture
+#[derive(Component)]
+#[storage(HashMapStorage)]
+pub struct TintComponent {
+    pub color: nalgebra_glm::Vec3,
+}
+
+/// Marks a mesh as terrain, which the debug overlays are drawn over
+#[derive(Component, Default)]
+#[storage(NullStorage)]
+pub struct TerrainComponent;
+
+/// Debug views drawn over the terrain, for tuning how the island is generated
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum TerrainOverlay {
+    #[default]
+    Off,
+    Grid,     //< Tile edges, with chunk borders picked out
+    Slope,    //< Flat ground is blue, cliffs are red
+    Moisture, //< How much water flowed over the ground while it was eroded
+}
+
+impl TerrainOverlay {
+    pub fn next(&self) -> Self {
+        match self {
+            TerrainOverlay::Off => TerrainOverlay::Grid,
+            TerrainOverlay::Grid => TerrainOverlay::Slope,
+            TerrainOverlay::Slope => TerrainOverlay::Moisture,
+            TerrainOverlay::Moisture => TerrainOverlay::Off,
+        }
+    }
+}
+
+/// Which debug view is drawn over the terrain, and what it needs
+#[derive(Default)]
+pub struct TerrainOverlayResource {
+    pub overlay: TerrainOverlay,
+    pub moisture: Option<Texture>, //< Moisture in the red channel, with row y holding the tiles along y
+    pub map_width: f32,            //< Tiles
+    pub chunk_size: f32,           //< Tiles
+}
+
+/// Light given off from a point, like a torch flame. Fades out to nothing `radius` away.
+#[derive(Component)]
+#[storage(HashMapStorage)]
+pub struct LightComponent {
+    pub color: nalgebra_glm::Vec3,
+    pub radius: f32,
+}
+
+/// Uploads the lights nearest the camera to the 3D program, for everything drawn with it this frame
+pub struct PointLightSystem;
+impl<'a> System<'a> for PointLightSystem {
+    type SystemData = (
+        ReadStorage<'a, LightComponent>,
+        ReadStorage<'a, PositionComponent>,
+        ReadStorage<'a, PreviousPositionComponent>,
+        Read<'a, App>,
+        Read<'a, OpenGlResource>,
+    );
+
+    fn run(&mut self, (lights, positions, previous, app, open_gl): Self::SystemData) {
+        let camera = open_gl.camera.position;
+        let mut nearest: Vec<(nalgebra_glm::Vec3, &LightComponent)> =
+            (&lights, &positions, previous.maybe())
+                .join()
+                .map(|(light, position, previous)| {
+                    let pos = previous.map_or(position.pos, |previous| {
+                        interpolate(previous.pos, position.pos, app.tick_alpha)
+                    });
+                    (pos, light)
+                })
+                .collect();
+        nearest.sort_by(|a, b| {
+            let a = nalgebra_glm::distance(&a.0, &camera);
+            let b = nalgebra_glm::distance(&b.0, &camera);
+            a.total_cmp(&b)
+        });
+        nearest.truncate(MAX_POINT_LIGHTS);
+
+        let light_positions: Vec<f32> = nearest
+            .iter()
+            .flat_map(|(pos, _)| pos.iter().copied())
+            .collect();
+        let colors: Vec<f32> = nearest
+            .iter()
+            .flat_map(|(_, light)| light.color.iter().copied())
+            .collect();
+        let radii: Vec<f32> = nearest.iter().map(|(_, light)| light.radius).collect();
+        open_gl.program.set();
+        let u_light_count = open_gl.program.uniform("u_light_count");
+        unsafe { gl::Uniform1i(u_light_count.id, nearest.len() as i32) }
+        if nearest.is_empty() {
+            return;
+        }
+        let u_light_pos = open_gl.program.uniform("u_light_pos");
+        let u_light_color = open_gl.program.uniform("u_light_color");
+        let u_light_radius = open_gl.program.uniform("u_light_radius");
+        let count = nearest.len() as i32;
+        unsafe {
+            gl::Uniform3fv(u_light_pos.id, count, light_positions.as_ptr());
+            gl::Uniform3fv(u_light_color.id, count, colors.as_ptr());
+            gl::Uniform1fv(u_light_radius.id, count, radii.as_ptr());
+        }
+    }
+}
+
+/// Textures blended over the terrain, by weights looked up by tile
+#[derive(Default)]
+pub struct TerrainTexturesResource {
+    pub weights: Option<Texture>, //< Sand, grass and rock in red, green and blue, row y holding the tiles along y
+    pub sand: Texture,
+    pub grass: Texture, //< Tinted by the biome's color, so it's grey around 0.5
+    pub rock: Texture,
+    pub repeats: f32, //< Times the textures repeat across a tile
 }
 
 pub struct Render3dSystem;
@@ -223,27 +497,108 @@ impl<'a> System<'a> for Render3dSystem {
     type SystemData = (
         ReadStorage<'a, MeshComponent>,
         ReadStorage<'a, PositionComponent>,
+        ReadStorage<'a, PreviousPositionComponent>,
+        ReadStorage<'a, AnimationComponent>,
+        ReadStorage<'a, TintComponent>,
+        ReadStorage<'a, TerrainComponent>,
         Read<'a, App>,
         Read<'a, MeshMgrResource>,
         Read<'a, OpenGlResource>,
+        Read<'a, GraphicsSettings>,
+        Read<'a, TerrainOverlayResource>,
+        Read<'a, TerrainTexturesResource>,
         Write<'a, SunResource>,
     );
 
-    fn run(&mut self, (render_comps, positions, app, mesh_mgr, open_gl, sun): Self::SystemData) {
+    fn run(
+        &mut self,
+        (
+            render_comps,
+            positions,
+            previous,
+            animations,
+            tints,
+            terrain,
+            app,
+            mesh_mgr,
+            open_gl,
+            settings,
+            overlay,
+            terrain_textures,
+            sun,
+        ): Self::SystemData,
+    ) {
         unsafe {
             gl::Viewport(0, 0, app.screen_width, app.screen_height);
             gl::Enable(gl::CULL_FACE);
             gl::CullFace(gl::BACK);
-            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+            // The color buffer was already covered by the sky
+            gl::Clear(gl::DEPTH_BUFFER_BIT);
         }
 
         open_gl.program.set();
+        let u_pcf_radius = open_gl.program.uniform("u_pcf_radius");
+        unsafe { gl::Uniform1i(u_pcf_radius.id, settings.pcf_quality.kernel_radius()) }
+        let u_overlay = open_gl.program.uniform("u_overlay");
+        let u_cull_dist = open_gl.program.uniform("u_cull_dist");
+        let u_splat = open_gl.program.uniform("u_splat");
+        let splat = terrain_textures.weights.is_some();
+        if let Some(weights) = &terrain_textures.weights {
+            let program_id = open_gl.program.id();
+            weights.activate(gl::TEXTURE3);
+            weights.associate_uniform(program_id, 3, "u_splat_weights");
+            terrain_textures.sand.activate(gl::TEXTURE4);
+            terrain_textures
+                .sand
+                .associate_uniform(program_id, 4, "u_sand");
+            terrain_textures.grass.activate(gl::TEXTURE5);
+            terrain_textures
+                .grass
+                .associate_uniform(program_id, 5, "u_grass");
+            terrain_textures.rock.activate(gl::TEXTURE6);
+            terrain_textures
+                .rock
+                .associate_uniform(program_id, 6, "u_rock");
+            let u_splat_repeats = open_gl.program.uniform("u_splat_repeats");
+            unsafe { gl::Uniform1f(u_splat_repeats.id, terrain_textures.repeats) }
+        }
+        // Both the overlays and the terrain textures look things up by tile
+        let u_map_width = open_gl.program.uniform("u_map_width");
+        let u_chunk_size = open_gl.program.uniform("u_chunk_size");
+        unsafe {
+            gl::Uniform1f(u_map_width.id, overlay.map_width);
+            gl::Uniform1f(u_chunk_size.id, overlay.chunk_size);
+        }
+        if overlay.overlay != TerrainOverlay::Off {
+            if let Some(moisture) = &overlay.moisture {
+                moisture.activate(gl::TEXTURE2);
+                moisture.associate_uniform(open_gl.program.id(), 2, "u_moisture");
+            }
+        }
 
-        for (renderable, position) in (&render_comps, &positions).join() {
+        let mut draws = vec![];
+        for (renderable, position, previous, animation, tint, terrain) in (
+            &render_comps,
+            &positions,
+            previous.maybe(),
+            animations.maybe(),
+            tints.maybe(),
+            terrain.maybe(),
+        )
+            .join()
+        {
+            if renderable.shadow_only {
+                continue;
+            }
+            // Moving things are drawn partway between their last two ticks
+            let pos = previous.map_or(position.pos, |previous| {
+                interpolate(previous.pos, position.pos, app.tick_alpha)
+            });
             // Cull models that are too far away
+            let distance = nalgebra_glm::length(&(pos - open_gl.camera.position));
             match renderable.render_dist {
                 Some(d) => {
-                    if nalgebra_glm::length(&(position.pos - open_gl.camera.position)) > d {
+                    if distance > d {
                         continue;
                     }
                 }
@@ -256,33 +611,170 @@ impl<'a> System<'a> for Render3dSystem {
             // if nalgebra_glm::dot(&view_ray, &model_to_player_ray) < 0.0 {
             //     continue;
             // }
-
-            let mesh = mesh_mgr.data.get_mesh(renderable.mesh_id);
-            renderable.texture.activate(gl::TEXTURE0);
-            renderable
-                .texture
-                .associate_uniform(open_gl.program.id(), 0, "texture0");
-            sun.depth_map.activate(gl::TEXTURE1);
-            sun.depth_map
-                .associate_uniform(open_gl.program.id(), 1, "shadow_map");
-
-            let u_light_matrix = Uniform::new(open_gl.program.id(), "light_mvp").unwrap();
-            let model_matrix = Mesh::get_model_matrix(position.pos, renderable.scale);
-            let (light_view_matrix, light_proj_matrix) = sun.shadow_camera.gen_view_proj_matrices();
-            let light_space_mvp = light_proj_matrix * light_view_matrix * model_matrix;
+            draws.push((
+                renderable,
+                pos,
+                animation,
+                tint,
+                terrain.is_some(),
+                distance,
+            ));
+        }
+        draws.sort_by(|a, b| {
+            let by_distance = match a.0.layer {
+                RenderLayer::Opaque => a.5.total_cmp(&b.5),
+                RenderLayer::Transparent => b.5.total_cmp(&a.5),
+            };
+            (a.0.layer, a.0.order)
+                .cmp(&(b.0.layer, b.0.order))
+                .then(by_distance)
+        });
+
+        for (renderable, pos, animation, tint, is_terrain, _) in draws {
+            if renderable.layer == RenderLayer::Transparent {
+                unsafe { gl::DepthMask(gl::FALSE) }
+            }
+            let shown_overlay = if is_terrain {
+                overlay.overlay
+            } else {
+                TerrainOverlay::Off
+            };
             unsafe {
-                gl::UniformMatrix4fv(
-                    u_light_matrix.id,
-                    1,
-                    gl::FALSE,
-                    &light_space_mvp.columns(0, 4)[0],
-                );
+                gl::Uniform1i(u_overlay.id, shown_overlay as i32);
+                gl::Uniform1f(u_cull_dist.id, renderable.render_dist.unwrap_or(0.0));
+                gl::Uniform1i(u_splat.id, (splat && is_terrain) as i32);
             }
-            mesh.draw(
-                &open_gl.program,
-                &open_gl.camera,
-                position.pos,
-                renderable.scale,
+            let pose = animation.map_or(&Pose::IDENTITY, |animation| &animation.pose);
+            let model_matrix = Mesh::get_posed_model_matrix(pos, renderable.scale, pose);
+            draw_lit(
+                mesh_mgr.data.get_mesh(renderable.mesh_id),
+                &renderable.texture,
+                tint.map_or(WHITE, |tint| tint.color),
+                model_matrix,
+                &open_gl,
+                &sun,
+            );
+        }
+        unsafe {
+            gl::DepthMask(gl::TRUE);
+            // The view models are drawn with the same program, and aren't terrain or culled
+            gl::Uniform1i(u_overlay.id, TerrainOverlay::Off as i32);
+            gl::Uniform1f(u_cull_dist.id, 0.0);
+            gl::Uniform1i(u_splat.id, 0);
+        }
+    }
+}
+
+/// Draws a mesh with the 3D program, lit by the sun and shaded by the shadow map
+fn draw_lit(
+    mesh: &Mesh,
+    texture: &Texture,
+    tint: nalgebra_glm::Vec3,
+    model_matrix: nalgebra_glm::Mat4,
+    open_gl: &OpenGlResource,
+    sun: &SunResource,
+) {
+    texture.activate(gl::TEXTURE0);
+    texture.associate_uniform(open_gl.program.id(), 0, "texture0");
+    sun.depth_map.activate(gl::TEXTURE1);
+    sun.depth_map
+        .associate_uniform(open_gl.program.id(), 1, "shadow_map");
+
+    let u_light_matrix = open_gl.program.uniform("light_mvp");
+    let u_tint = open_gl.program.uniform("u_tint");
+    let (light_view_matrix, light_proj_matrix) = sun.shadow_camera.gen_view_proj_matrices();
+    let light_space_mvp = light_proj_matrix * light_view_matrix * model_matrix;
+    unsafe {
+        gl::UniformMatrix4fv(
+            u_light_matrix.id,
+            1,
+            gl::FALSE,
+            &light_space_mvp.columns(0, 4)[0],
+        );
+        gl::Uniform3f(u_tint.id, tint.x, tint.y, tint.z);
+    }
+    mesh.draw_with_model_matrix(&open_gl.program, &open_gl.camera, model_matrix);
+}
+
+/// Something held in front of the camera, like a gun or an arm. Placed relative to the camera rather than the world,
+/// with +x pointing the way the camera looks, +y to its left, and +z up.
+#[derive(Component)]
+#[storage(HashMapStorage)]
+pub struct ViewModelComponent {
+    pub mesh_id: usize,
+    pub scale: nalgebra_glm::Vec3,
+    pub texture: Texture,
+    pub offset: nalgebra_glm::Vec3, //< Where it's held, forward, left, and up from the camera
+    pub sway: nalgebra_glm::Vec3,   //< Added to the offset, for bobbing along while walking
+    pub kick: f32,                  //< Radians tipped up about the back end, for recoil
+    pub visible: bool,
+}
+
+impl ViewModelComponent {
+    /// Turns the model from camera space into world space
+    pub fn model_matrix(&self, camera: &Camera) -> nalgebra_glm::Mat4 {
+        let forward = nalgebra_glm::normalize(&(camera.lookat - camera.position));
+        let left = nalgebra_glm::normalize(&nalgebra_glm::cross(&camera.up, &forward));
+        let up = nalgebra_glm::cross(&forward, &left);
+        let camera_basis = nalgebra_glm::mat4(
+            forward.x,
+            left.x,
+            up.x,
+            camera.position.x, //
+            forward.y,
+            left.y,
+            up.y,
+            camera.position.y, //
+            forward.z,
+            left.z,
+            up.z,
+            camera.position.z, //
+            0.0,
+            0.0,
+            0.0,
+            1.0,
+        );
+        // Tipped up about the back end, so the muzzle rises and the grip stays in hand
+        let back = nalgebra_glm::vec3(-self.scale.x, 0.0, 0.0);
+        let kick = nalgebra_glm::translation(&back)
+            * nalgebra_glm::rotation(-self.kick, &nalgebra_glm::vec3(0.0, 1.0, 0.0))
+            * nalgebra_glm::translation(&-back);
+        camera_basis
+            * nalgebra_glm::translation(&(self.offset + self.sway))
+            * kick
+            * nalgebra_glm::scaling(&self.scale)
+    }
+}
+
+/// Draws view models over the rest of the world. The depth buffer is cleared first, so they never clip into the
+/// ground or a wall the player is up against.
+pub struct ViewModelRenderSystem;
+impl<'a> System<'a> for ViewModelRenderSystem {
+    type SystemData = (
+        ReadStorage<'a, ViewModelComponent>,
+        Read<'a, MeshMgrResource>,
+        Read<'a, OpenGlResource>,
+        Read<'a, SunResource>,
+    );
+
+    fn run(&mut self, (view_models, mesh_mgr, open_gl, sun): Self::SystemData) {
+        if !view_models.join().any(|view_model| view_model.visible) {
+            return;
+        }
+        unsafe {
+            gl::Enable(gl::CULL_FACE);
+            gl::CullFace(gl::BACK);
+            gl::Clear(gl::DEPTH_BUFFER_BIT);
+        }
+        open_gl.program.set();
+        for view_model in view_models.join().filter(|view_model| view_model.visible) {
+            draw_lit(
+                mesh_mgr.data.get_mesh(view_model.mesh_id),
+                &view_model.texture,
+                WHITE,
+                view_model.model_matrix(&open_gl.camera),
+                &open_gl,
+                &sun,
             );
         }
     }
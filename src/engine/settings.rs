@@ -0,0 +1,187 @@
+use serde::{Deserialize, Serialize};
+
+use super::input::ControlSettings;
+
+const SETTINGS_PATH: &str = "settings.ron";
+
+/// Everything the player can configure, persisted between runs
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub graphics: GraphicsSettings,
+    pub display: DisplaySettings,
+    pub controls: ControlSettings,
+    pub view: ViewSettings,
+    pub audio: AudioSettings,
+    pub gameplay: GameplaySettings,
+}
+
+impl Settings {
+    /// Loads the settings file. Returns None if there isn't one yet, or it couldn't be read.
+    pub fn load() -> Option<Self> {
+        let contents = std::fs::read_to_string(SETTINGS_PATH).ok()?;
+        match ron::from_str(&contents) {
+            Ok(settings) => Some(settings),
+            Err(err) => {
+                println!("Couldn't parse {}: {}", SETTINGS_PATH, err);
+                None
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        std::fs::write(SETTINGS_PATH, self.to_ron()?).map_err(|e| e.to_string())
+    }
+
+    /// The settings as they're written to the settings file
+    pub fn to_ron(&self) -> Result<String, String> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Broad graphics quality levels. Picked automatically on first run, but every value can be changed afterwards.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum QualityPreset {
+    Low,
+    Medium,
+    High,
+}
+
+/// How soft shadow edges are. Each step up samples a wider ring of shadow map texels.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum PcfQuality {
+    Off,  //< Single sample, hard edges
+    Low,  //< 3x3 kernel
+    High, //< 5x5 kernel
+}
+
+impl PcfQuality {
+    /// Number of texels sampled on each side of the center texel
+    pub fn kernel_radius(&self) -> i32 {
+        match self {
+            PcfQuality::Off => 0,
+            PcfQuality::Low => 1,
+            PcfQuality::High => 2,
+        }
+    }
+}
+
+/// Graphics options that trade image quality for performance
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GraphicsSettings {
+    pub preset: QualityPreset, //< The preset these values started from
+    pub shadow_size: i32,      //< Width and height of the shadow map, in texels
+    pub pcf_quality: PcfQuality,
+    pub view_distance: f32, //< How far away terrain and trees are drawn. Smaller props are drawn half as far.
+    pub chunk_load_radius: f32, //< Terrain chunks are built within this distance, and unloaded past it
+    pub msaa_samples: u8,       //< 0 disables multisampling. Takes effect on the next launch.
+    pub grass_density: f32,     //< [0, 1], how much of the grass near the camera is drawn
+}
+
+impl GraphicsSettings {
+    pub fn from_preset(preset: QualityPreset) -> Self {
+        match preset {
+            QualityPreset::Low => Self {
+                preset,
+                shadow_size: 512,
+                pcf_quality: PcfQuality::Off,
+                view_distance: 160.0,
+                chunk_load_radius: 224.0,
+                msaa_samples: 0,
+                grass_density: 0.25,
+            },
+            QualityPreset::Medium => Self {
+                preset,
+                shadow_size: 1024,
+                pcf_quality: PcfQuality::Low,
+                view_distance: 256.0,
+                chunk_load_radius: 320.0,
+                msaa_samples: 2,
+                grass_density: 0.6,
+            },
+            QualityPreset::High => Self {
+                preset,
+                shadow_size: 2048,
+                pcf_quality: PcfQuality::High,
+                view_distance: 384.0,
+                chunk_load_radius: 448.0,
+                msaa_samples: 4,
+                grass_density: 1.0,
+            },
+        }
+    }
+}
+
+impl Default for GraphicsSettings {
+    fn default() -> Self {
+        Self::from_preset(QualityPreset::Medium)
+    }
+}
+
+/// How the player sees the world and looks around
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ViewSettings {
+    pub fov: f32,               //< Vertical field of view, in radians
+    pub mouse_sensitivity: f32, //< Radians turned per pixel the mouse moves
+}
+
+impl Default for ViewSettings {
+    fn default() -> Self {
+        Self {
+            fov: 0.9,
+            mouse_sensitivity: 0.01,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub volume: i32, //< [0, 128], every sound is scaled by this over 128
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self { volume: 128 }
+    }
+}
+
+/// Optional helpers that change how the game plays
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameplaySettings {
+    pub parrot: bool, //< Whether a parrot bought from the trader comes along, and shows the way to treasure
+    pub corpse_seconds: f32, //< How long defeated mobs lie there before sinking away
+}
+
+impl Default for GameplaySettings {
+    fn default() -> Self {
+        Self {
+            parrot: true,
+            corpse_seconds: 5.0,
+        }
+    }
+}
+
+/// How often frames are shown, and whether the game starts fullscreen. These take effect on the next launch, though
+/// Alt+Enter switches in and out of fullscreen any time.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplaySettings {
+    pub vsync: bool,
+    pub fps_cap: Option<u32>, //< Frames per second to limit rendering to, None renders as fast as possible
+    pub fullscreen: bool,     //< Fills the desktop, at its resolution, rather than a window
+}
+
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        Self {
+            vsync: true,
+            fps_cap: None,
+            fullscreen: false,
+        }
+    }
+}
@@ -2,21 +2,22 @@ use specs::{Component, Join, NullStorage, Read, ReadStorage, System, Write};
 
 use super::{
     aabb::AABB,
+    animation::{AnimationComponent, Pose},
     camera::{Camera, ProjectionKind},
     frustrum::Frustrum,
     objects::{Fbo, Program, Texture},
     physics::PositionComponent,
-    render3d::{MeshComponent, MeshMgrResource, OpenGlResource},
+    render3d::{Mesh, MeshComponent, MeshMgrResource, OpenGlResource},
+    settings::GraphicsSettings,
 };
 
-const SHADOW_SIZE: i32 = 1024;
-
 #[derive(Default)]
 pub struct SunResource {
     pub shadow_camera: Camera,
     pub shadow_program: Program,
     pub fbo: Fbo,
     pub depth_map: Texture,
+    pub shadow_size: i32,
     pub light_dir: nalgebra_glm::Vec3,
 }
 
@@ -24,21 +25,33 @@ impl SunResource {
     pub fn new(
         shadow_camera: Camera,
         shadow_program: Program,
+        shadow_size: i32,
         light_dir: nalgebra_glm::Vec3,
     ) -> Self {
         let depth_map = Texture::new();
-        depth_map.load_depth_buffer(SHADOW_SIZE, SHADOW_SIZE);
+        depth_map.load_depth_buffer(shadow_size, shadow_size);
         let fbo = Fbo::new();
         fbo.bind();
         depth_map.post_bind();
+        fbo.unbind();
         Self {
             shadow_camera,
             shadow_program,
             fbo,
             depth_map,
+            shadow_size,
             light_dir,
         }
     }
+
+    /// Reallocates the depth map, for when the shadow resolution setting changes
+    pub fn resize(&mut self, shadow_size: i32) {
+        self.depth_map.load_depth_buffer(shadow_size, shadow_size);
+        self.fbo.bind();
+        self.depth_map.post_bind();
+        self.fbo.unbind();
+        self.shadow_size = shadow_size;
+    }
 }
 
 #[derive(Default)]
@@ -53,18 +66,28 @@ impl<'a> System<'a> for ShadowSystem {
         ReadStorage<'a, MeshComponent>,
         ReadStorage<'a, PositionComponent>,
         ReadStorage<'a, CastsShadowComponent>,
+        ReadStorage<'a, AnimationComponent>,
         Read<'a, MeshMgrResource>,
         Read<'a, OpenGlResource>,
+        Read<'a, GraphicsSettings>,
         Write<'a, SunResource>,
     );
 
     fn run(
         &mut self,
-        (render_comps, positions, shadow, mesh_mgr, open_gl, mut sun): Self::SystemData,
+        (render_comps, positions, shadow, animations, mesh_mgr, open_gl, settings, mut sun): Self::SystemData,
     ) {
+        // Whatever was being rendered to before, which isn't always the screen
+        let mut previous_fbo = 0;
+        unsafe { gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut previous_fbo) }
+
+        if sun.shadow_size != settings.shadow_size {
+            sun.resize(settings.shadow_size);
+        }
+
         sun.fbo.bind();
         unsafe {
-            gl::Viewport(0, 0, SHADOW_SIZE, SHADOW_SIZE);
+            gl::Viewport(0, 0, sun.shadow_size, sun.shadow_size);
             gl::Enable(gl::CULL_FACE);
             gl::CullFace(gl::FRONT);
             gl::Clear(gl::DEPTH_BUFFER_BIT)
@@ -127,7 +150,9 @@ impl<'a> System<'a> for ShadowSystem {
         };
 
         // Render the stuff that casts shadows
-        for (renderable, position, _) in (&render_comps, &positions, &shadow).join() {
+        for (renderable, position, _, animation) in
+            (&render_comps, &positions, &shadow, animations.maybe()).join()
+        {
             match renderable.render_dist {
                 Some(d) => {
                     if nalgebra_glm::length(&(position.pos - open_gl.camera.position)) > d {
@@ -138,14 +163,14 @@ impl<'a> System<'a> for ShadowSystem {
             }
 
             let mesh = mesh_mgr.data.get_mesh(renderable.mesh_id);
-            mesh.draw(
+            let pose = animation.map_or(&Pose::IDENTITY, |animation| &animation.pose);
+            mesh.draw_with_model_matrix(
                 &sun.shadow_program,
                 &sun.shadow_camera,
-                position.pos,
-                renderable.scale,
+                Mesh::get_posed_model_matrix(position.pos, renderable.scale, pose),
             );
         }
 
-        sun.fbo.unbind();
+        unsafe { gl::BindFramebuffer(gl::FRAMEBUFFER, previous_fbo as u32) }
     }
 }
@@ -0,0 +1,167 @@
+use specs::{Read, System};
+
+use crate::App;
+
+use super::{
+    objects::Program,
+    render3d::{Mesh, MeshMgrResource, OpenGlResource},
+};
+
+/// Set on the sky's program each frame, along with the mesh's
+pub const SKY_UNIFORMS: &[&str] = &[
+    "u_resolution",
+    "u_camera_pos",
+    "u_zenith_color",
+    "u_horizon_color",
+    "u_sun_dir",
+    "u_sun_color",
+    "u_moon_dir",
+    "u_moon_color",
+    "u_star_brightness",
+    "u_star_rotation",
+];
+
+#[derive(Default)]
+pub struct SkyResource {
+    pub program: Program,
+    pub mesh_id: usize,
+    pub zenith_color: nalgebra_glm::Vec3,  //< Color straight up
+    pub horizon_color: nalgebra_glm::Vec3, //< Color at the horizon, also used for fog
+    pub sun_dir: nalgebra_glm::Vec3,
+    pub sun_color: nalgebra_glm::Vec3, //< Black hides the sun disc
+    pub moon_dir: nalgebra_glm::Vec3,
+    pub moon_color: nalgebra_glm::Vec3, //< Black hides the moon disc, dimmer away from full moon
+    pub star_brightness: f32,           //< 0 during the day, 1 at night
+    pub star_rotation: f32,             //< Angle the stars have turned, about the x axis
+    pub fog_density: f32, //< Per unit of distance, for things drawn outside the 3D program
+}
+
+impl SkyResource {
+    pub fn new(program: Program, mesh_id: usize) -> Self {
+        Self {
+            program,
+            mesh_id,
+            zenith_color: nalgebra_glm::vec3(0.31, 0.51, 0.82),
+            horizon_color: nalgebra_glm::vec3(0.67, 0.8, 0.97),
+            sun_dir: nalgebra_glm::vec3(0.0, 0.0, 1.0),
+            sun_color: nalgebra_glm::vec3(1.0, 0.95, 0.85),
+            moon_dir: nalgebra_glm::vec3(0.0, 0.0, -1.0),
+            moon_color: nalgebra_glm::vec3(0.0, 0.0, 0.0),
+            star_brightness: 0.0,
+            star_rotation: 0.0,
+            fog_density: 0.0,
+        }
+    }
+}
+
+/// Creates a cube spanning [-1, 1], with its faces pointing inwards. The shader only cares about the direction to each
+/// fragment, so a cube works just as well as a sphere.
+pub fn create_sky_mesh() -> Mesh {
+    let mut vertices = Vec::<f32>::new();
+    for i in 0..8 {
+        vertices.push(if i & 1 == 0 { -1.0 } else { 1.0 });
+        vertices.push(if i & 2 == 0 { -1.0 } else { 1.0 });
+        vertices.push(if i & 4 == 0 { -1.0 } else { 1.0 });
+    }
+    let indices = vec![
+        0, 1, 3, 0, 3, 2, // bottom
+        4, 6, 7, 4, 7, 5, // top
+        0, 4, 5, 0, 5, 1, // front
+        2, 3, 7, 2, 7, 6, // back
+        0, 2, 6, 0, 6, 4, // left
+        1, 5, 7, 1, 7, 3, // right
+    ];
+    Mesh::new(indices, vec![vertices])
+}
+
+/// Clears the screen and draws the sky behind everything. Should run before the opaque 3D pass.
+pub struct SkyRenderSystem;
+impl<'a> System<'a> for SkyRenderSystem {
+    type SystemData = (
+        Read<'a, App>,
+        Read<'a, MeshMgrResource>,
+        Read<'a, OpenGlResource>,
+        Read<'a, SkyResource>,
+    );
+
+    fn run(&mut self, (app, mesh_mgr, open_gl, sky): Self::SystemData) {
+        const SKY_EXTENT: f32 = 1000.0;
+
+        unsafe {
+            gl::Viewport(0, 0, app.screen_width, app.screen_height);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+
+        let camera = &open_gl.camera;
+        let program = &sky.program;
+        program.set();
+        unsafe {
+            gl::Uniform2f(
+                program.uniform("u_resolution").id,
+                app.screen_width as f32,
+                app.screen_height as f32,
+            );
+            gl::Uniform3f(
+                program.uniform("u_camera_pos").id,
+                camera.position.x,
+                camera.position.y,
+                camera.position.z,
+            );
+            gl::Uniform3f(
+                program.uniform("u_zenith_color").id,
+                sky.zenith_color.x,
+                sky.zenith_color.y,
+                sky.zenith_color.z,
+            );
+            gl::Uniform3f(
+                program.uniform("u_horizon_color").id,
+                sky.horizon_color.x,
+                sky.horizon_color.y,
+                sky.horizon_color.z,
+            );
+            gl::Uniform3f(
+                program.uniform("u_sun_dir").id,
+                sky.sun_dir.x,
+                sky.sun_dir.y,
+                sky.sun_dir.z,
+            );
+            gl::Uniform3f(
+                program.uniform("u_sun_color").id,
+                sky.sun_color.x,
+                sky.sun_color.y,
+                sky.sun_color.z,
+            );
+            gl::Uniform3f(
+                program.uniform("u_moon_dir").id,
+                sky.moon_dir.x,
+                sky.moon_dir.y,
+                sky.moon_dir.z,
+            );
+            gl::Uniform3f(
+                program.uniform("u_moon_color").id,
+                sky.moon_color.x,
+                sky.moon_color.y,
+                sky.moon_color.z,
+            );
+            gl::Uniform1f(program.uniform("u_star_brightness").id, sky.star_brightness);
+            gl::Uniform1f(program.uniform("u_star_rotation").id, sky.star_rotation);
+
+            // Seen from the inside, and never in front of anything
+            gl::Disable(gl::CULL_FACE);
+            gl::DepthMask(gl::FALSE);
+        }
+
+        let mesh = mesh_mgr.data.get_mesh(sky.mesh_id);
+        mesh.draw(
+            program,
+            camera,
+            camera.position,
+            nalgebra_glm::vec3(SKY_EXTENT, SKY_EXTENT, SKY_EXTENT),
+        );
+
+        unsafe {
+            gl::DepthMask(gl::TRUE);
+            gl::Enable(gl::CULL_FACE);
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize, Serializer};
+use specs::{Entity, Join, World, WorldExt};
+
+use super::registry::ComponentRegistry;
+
+/// Every serializable component of every entity at one tick, for comparing world states while debugging
+#[derive(Default, Serialize, Deserialize)]
+pub struct WorldSnapshot {
+    pub tick: usize,
+    pub entities: BTreeMap<u32, BTreeMap<String, String>>, //< Entity id to component name to RON
+}
+
+impl WorldSnapshot {
+    pub fn new(tick: usize) -> Self {
+        Self {
+            tick,
+            entities: BTreeMap::new(),
+        }
+    }
+
+    /// Every registered component of every entity in the world
+    pub fn capture(tick: usize, world: &World, registry: &ComponentRegistry) -> Self {
+        let mut retval = Self::new(tick);
+        for entity in world.entities().join() {
+            for name in registry.names() {
+                if let Some(value) = registry.write(world, entity, name).unwrap() {
+                    retval
+                        .entities
+                        .entry(entity.id())
+                        .or_default()
+                        .insert(name.to_string(), value);
+                }
+            }
+        }
+        retval
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| e.to_string())?;
+        std::fs::write(path, contents).map_err(|e| e.to_string())
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        ron::from_str(&contents).map_err(|e| e.to_string())
+    }
+
+    /// Lists how `other` differs from this snapshot, one line per added or removed entity or changed component
+    pub fn diff(&self, other: &WorldSnapshot) -> Vec<String> {
+        let mut retval = vec![format!("tick {} -> {}", self.tick, other.tick)];
+        for (id, components) in &self.entities {
+            let Some(other_components) = other.entities.get(id) else {
+                retval.push(format!("- entity {}", id));
+                continue;
+            };
+            for (name, value) in components {
+                match other_components.get(name) {
+                    Some(other_value) if other_value == value => {}
+                    Some(other_value) => retval.push(format!(
+                        "~ entity {} {}: {} -> {}",
+                        id, name, value, other_value
+                    )),
+                    None => retval.push(format!("~ entity {} lost {}: {}", id, name, value)),
+                }
+            }
+            for (name, other_value) in other_components {
+                if !components.contains_key(name) {
+                    retval.push(format!("~ entity {} gained {}: {}", id, name, other_value));
+                }
+            }
+        }
+        for id in other.entities.keys() {
+            if !self.entities.contains_key(id) {
+                retval.push(format!("+ entity {}", id));
+            }
+        }
+        retval
+    }
+}
+
+/// Serializes an entity reference as its id, so components that point to other entities can be snapshotted.
+/// Use with `#[serde(serialize_with = "serialize_entity")]`.
+pub fn serialize_entity<S: Serializer>(entity: &Entity, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u32(entity.id())
+}
@@ -1,5 +1,6 @@
+use std::{collections::HashMap, sync::OnceLock};
+
 use specs::prelude::*;
-use std::path::Path;
 
 use sdl2::{
     pixels::Color,
@@ -10,26 +11,45 @@ use specs::{Component, DispatcherBuilder, VecStorage, World};
 use crate::App;
 
 use super::{
-    camera::Camera,
-    objects::{Program, Texture, Uniform},
+    assets::asset_path,
+    camera::{Camera, ProjectionKind},
+    error::EngineError,
+    objects::{create_program, Program, Texture},
     physics::PositionComponent,
-    render3d::MeshMgrResource,
+    render3d::{Mesh, MeshMgrResource, OpenGlResource, MESH_UNIFORMS},
 };
 
+/// Set on the UI program for each quad, along with the mesh's
+const UI_UNIFORMS: &[&str] = &["u_opacity", "u_tint", "u_rotation"];
+
+/// Started by the first font manager, and kept for the rest of the program, so that fonts can outlive the manager and
+/// be kept around by systems that re-render text
+static TTF_CONTEXT: OnceLock<Sdl2TtfContext> = OnceLock::new();
+
 pub struct FontMgr {
-    ttf_context: Sdl2TtfContext,
+    ttf_context: &'static Sdl2TtfContext,
 }
 
 impl FontMgr {
-    pub fn new() -> Self {
-        let ttf_context = sdl2::ttf::init().unwrap();
-        Self { ttf_context }
+    pub fn new() -> Result<Self, EngineError> {
+        if let Some(ttf_context) = TTF_CONTEXT.get() {
+            return Ok(Self { ttf_context });
+        }
+        let ttf_context = sdl2::ttf::init().map_err(|err| EngineError::Sdl(err.to_string()))?;
+        Ok(Self {
+            ttf_context: TTF_CONTEXT.get_or_init(|| ttf_context),
+        })
     }
 
-    pub fn load_font(&self, path: &str, size: u16) -> Result<Font, String> {
+    /// Loads a font, given relative to the asset root
+    pub fn load_font(&self, path: &str, size: u16) -> Result<Font<'static, 'static>, EngineError> {
+        let path = asset_path(path);
         self.ttf_context
-            .load_font(Path::new(path), size)
-            .map_err(|e| e.to_string())
+            .load_font(&path, size)
+            .map_err(|reason| EngineError::Font {
+                path: path.display().to_string(),
+                reason,
+            })
     }
 }
 
@@ -39,6 +59,42 @@ pub struct UIResource {
     pub program: Program,
 }
 
+impl UIResource {
+    /// Sets up the 2D program, with a camera where the screen spans [-1, 1] on both axes
+    pub fn new() -> Result<Self, EngineError> {
+        Ok(Self {
+            camera: Camera::new(
+                nalgebra_glm::vec3(0.0, 0.0, 1.0),
+                nalgebra_glm::zero(),
+                nalgebra_glm::vec3(0.0, 1.0, 0.0),
+                ProjectionKind::Orthographic {
+                    left: -1.0,
+                    right: 1.0,
+                    bottom: -1.0,
+                    top: 1.0,
+                    near: 0.01,
+                    far: 10.0,
+                },
+            ),
+            program: create_program(
+                include_str!("../shaders/2d.vert"),
+                include_str!("../shaders/2d.frag"),
+                &[MESH_UNIFORMS, UI_UNIFORMS].concat(),
+            )?,
+        })
+    }
+}
+
+/// Which group a quad is drawn with. Layers are drawn in this order, each over the ones before.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
+pub enum UiLayer {
+    World, //< Pinned to things in the world, like health bars over mobs
+    Flash, //< Full screen tints, over the world but under the HUD
+    #[default]
+    Hud,
+    Cinematic, //< Over everything, like the letterbox bars
+}
+
 #[derive(Component)]
 #[storage(VecStorage)]
 
@@ -47,7 +103,11 @@ pub struct QuadComponent {
     pub width: i32,
     pub height: i32,
     pub opacity: f32,
+    pub tint: nalgebra_glm::Vec3, //< Multiplied with the texture's color
+    pub rotation: f32, //< Counter-clockwise, in radians. Corners are clipped, so keep a clear border.
     pub texture: Texture,
+    pub layer: UiLayer,
+    pub order: i32, //< Within a layer, higher orders are drawn over lower ones
 }
 
 impl QuadComponent {
@@ -57,30 +117,283 @@ impl QuadComponent {
             width,
             height,
             opacity: 1.0,
+            tint: nalgebra_glm::vec3(1.0, 1.0, 1.0),
+            rotation: 0.0,
             texture,
+            layer: UiLayer::Hud,
+            order: 0,
         }
     }
 
-    pub fn from_text(text: &'static str, font: &Font, color: Color, quad_mesh_id: usize) -> Self {
-        let surface = font
-            .render(text)
-            .blended(color)
-            .unwrap()
-            .convert_format(sdl2::pixels::PixelFormatEnum::RGBA32)
-            .unwrap();
-
-        let width = surface.width();
-        let height = surface.height();
-
-        let texture = Texture::from_surface(surface);
+    pub fn from_text(text: &str, font: &Font, color: Color, quad_mesh_id: usize) -> Self {
+        let (texture, width, height) = render_text(text, font, color);
         Self {
             mesh_id: quad_mesh_id,
-            width: width as i32,
-            height: height as i32,
+            width,
+            height,
             opacity: 1.0,
+            tint: nalgebra_glm::vec3(1.0, 1.0, 1.0),
+            rotation: 0.0,
             texture,
+            layer: UiLayer::Hud,
+            order: 0,
         }
     }
+
+    /// Moves the quad into a layer, and to an order within it
+    pub fn in_layer(mut self, layer: UiLayer, order: i32) -> Self {
+        self.layer = layer;
+        self.order = order;
+        self
+    }
+
+    /// Re-renders the quad's texture with new text. The quad is resized to fit the text.
+    pub fn set_text(&mut self, text: &str, font: &Font, color: Color) {
+        let (texture, width, height) = render_text(text, font, color);
+        self.texture = texture;
+        self.width = width;
+        self.height = height;
+    }
+}
+
+/// Pins a quad to a spot in the 3D world instead of the screen. The quad stays facing the screen and keeps its size in
+/// pixels, and isn't drawn when the spot is behind the camera.
+#[derive(Component)]
+#[storage(VecStorage)]
+pub struct BillboardComponent {
+    pub anchor: nalgebra_glm::Vec3,
+}
+
+/// Where text is kept along one axis of the screen, as it changes size
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Align {
+    Start, //< Left, or top
+    Center,
+    End, //< Right, or bottom
+}
+
+impl Align {
+    /// Where the center of something `size` pixels long goes along a screen `screen` pixels long, to be `offset`
+    /// pixels in from the edge. Offsets from the center move right or down.
+    fn place(&self, size: i32, offset: i32, screen: i32) -> f32 {
+        let (size, offset, screen) = (size as f32, offset as f32, screen as f32);
+        match self {
+            Align::Start => offset + size / 2.0,
+            Align::Center => screen / 2.0 + offset,
+            Align::End => screen - offset - size / 2.0,
+        }
+    }
+}
+
+/// A corner or edge of the screen, or its middle
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Anchor {
+    pub horizontal: Align,
+    pub vertical: Align,
+}
+
+impl Anchor {
+    pub const fn new(horizontal: Align, vertical: Align) -> Self {
+        Self {
+            horizontal,
+            vertical,
+        }
+    }
+
+    /// Where the center of a quad `size` pixels big goes, to be `offset` pixels in from the anchor
+    pub fn place(
+        &self,
+        size: (i32, i32),
+        offset: (i32, i32),
+        screen: (i32, i32),
+    ) -> nalgebra_glm::Vec3 {
+        let x_px = self.horizontal.place(size.0, offset.0, screen.0);
+        let y_px = self.vertical.place(size.1, offset.1, screen.1);
+        nalgebra_glm::vec3(
+            -1.0 + 2.0 * x_px / screen.0 as f32,
+            1.0 - 2.0 * y_px / screen.1 as f32,
+            0.0,
+        )
+    }
+}
+
+/// Keeps a quad a number of pixels in from a corner or edge of the screen, whatever size the quad and the window are.
+/// The layout system moves the quad's position every frame, so nothing else should.
+#[derive(Component)]
+#[storage(VecStorage)]
+pub struct LayoutComponent {
+    pub anchor: Anchor,
+    pub offset: (i32, i32), //< Pixels in from the anchor
+}
+
+impl LayoutComponent {
+    pub fn new(anchor: Anchor, offset: (i32, i32)) -> Self {
+        Self { anchor, offset }
+    }
+}
+
+/// Text that can be changed while the game runs, shown by the quad on the same entity. The text system only renders
+/// the text again when it changes. Give it a layout to keep it in place as it changes size.
+#[derive(Component)]
+#[storage(VecStorage)]
+pub struct TextComponent {
+    text: String,
+    rendered: Option<String>, //< The text the quad shows, None until it's first rendered
+    pub font: usize,          //< Which of the text system's fonts to use
+    pub color: Color,
+}
+
+impl TextComponent {
+    pub fn new(text: &str, font: usize) -> Self {
+        Self {
+            text: text.to_string(),
+            rendered: None,
+            font,
+            color: Color::RGBA(255, 255, 255, 255),
+        }
+    }
+
+    /// Changes the text. Cheap to call every tick, the quad is only rendered again if the text is different.
+    pub fn set_text(&mut self, text: &str) {
+        if self.text != text {
+            self.text = text.to_string();
+        }
+    }
+}
+
+/// Renders changed text into its quad. Holds the fonts, so has to be added as a thread local system.
+pub struct TextSystem {
+    pub fonts: Vec<Font<'static, 'static>>,
+}
+impl<'a> System<'a> for TextSystem {
+    type SystemData = (
+        WriteStorage<'a, TextComponent>,
+        WriteStorage<'a, QuadComponent>,
+    );
+
+    fn run(&mut self, (mut texts, mut quads): Self::SystemData) {
+        for (text, quad) in (&mut texts, &mut quads).join() {
+            if text.rendered.as_deref() != Some(text.text.as_str()) {
+                quad.set_text(&text.text, &self.fonts[text.font], text.color);
+                text.rendered = Some(text.text.clone());
+            }
+        }
+    }
+}
+
+/// Places laid out quads for the current window size, just before they're drawn
+struct LayoutSystem;
+impl<'a> System<'a> for LayoutSystem {
+    type SystemData = (
+        ReadStorage<'a, LayoutComponent>,
+        ReadStorage<'a, QuadComponent>,
+        WriteStorage<'a, PositionComponent>,
+        Read<'a, App>,
+    );
+
+    fn run(&mut self, (layouts, quads, mut positions, app): Self::SystemData) {
+        for (layout, quad, position) in (&layouts, &quads, &mut positions).join() {
+            position.pos = layout.anchor.place(
+                (quad.width, quad.height),
+                layout.offset,
+                (app.screen_width, app.screen_height),
+            );
+        }
+    }
+}
+
+/// A font's characters, each rendered once, for text that changes every frame, like a frame counter. Drawn a
+/// character at a time, since re-rendering a whole quad's text every frame is slow. Fine for monospace fonts, other
+/// fonts lose their kerning.
+pub struct GlyphCache {
+    font: Font<'static, 'static>,
+    glyphs: HashMap<char, (Texture, i32, i32)>,
+}
+
+impl GlyphCache {
+    pub fn new(font: Font<'static, 'static>) -> Self {
+        Self {
+            font,
+            glyphs: HashMap::new(),
+        }
+    }
+
+    fn glyph(&mut self, c: char) -> &(Texture, i32, i32) {
+        let font = &self.font;
+        self.glyphs
+            .entry(c)
+            .or_insert_with(|| render_text(&c.to_string(), font, Color::RGBA(255, 255, 255, 255)))
+    }
+
+    /// Width of a line in pixels
+    pub fn width(&mut self, line: &str) -> i32 {
+        line.chars().map(|c| self.glyph(c).1).sum()
+    }
+
+    /// Draws lines of text with the 2D program, starting at `(x, y)` pixels from the top left of the screen
+    pub fn draw(
+        &mut self,
+        lines: &[String],
+        (x, y): (i32, i32),
+        color: nalgebra_glm::Vec3,
+        quad_mesh: &Mesh,
+        ui: &UIResource,
+        app: &App,
+    ) {
+        let line_height = self.font.height();
+        ui.program.set();
+        let u_opacity = ui.program.uniform("u_opacity");
+        let u_tint = ui.program.uniform("u_tint");
+        let u_rotation = ui.program.uniform("u_rotation");
+        unsafe {
+            gl::DepthMask(gl::FALSE);
+            gl::Uniform1f(u_opacity.id, 1.0);
+            gl::Uniform3f(u_tint.id, color.x, color.y, color.z);
+            gl::Uniform1f(u_rotation.id, 0.0);
+        }
+        let (screen_width, screen_height) = (app.screen_width as f32, app.screen_height as f32);
+        for (i, line) in lines.iter().enumerate() {
+            let top = (y + i as i32 * line_height) as f32;
+            let mut left = x as f32;
+            for c in line.chars() {
+                let (texture, width, height) = self.glyph(c);
+                let (width, height) = (*width as f32, *height as f32);
+                texture.activate(gl::TEXTURE0);
+                texture.associate_uniform(ui.program.id(), 0, "texture0");
+                // The quad mesh spans [-1, 1], so it's placed by its center and scaled by half its size
+                quad_mesh.draw(
+                    &ui.program,
+                    &ui.camera,
+                    nalgebra_glm::vec3(
+                        -1.0 + (2.0 * left + width) / screen_width,
+                        1.0 - (2.0 * top + height) / screen_height,
+                        0.0,
+                    ),
+                    nalgebra_glm::vec3(width / screen_width, height / screen_height, 1.0),
+                );
+                left += width;
+            }
+        }
+        unsafe {
+            gl::DepthMask(gl::TRUE);
+        }
+    }
+}
+
+fn render_text(text: &str, font: &Font, color: Color) -> (Texture, i32, i32) {
+    // SDL_ttf refuses to render zero-width strings
+    let text = if text.is_empty() { " " } else { text };
+    let surface = font
+        .render(text)
+        .blended(color)
+        .unwrap()
+        .convert_format(sdl2::pixels::PixelFormatEnum::RGBA32)
+        .unwrap();
+
+    let width = surface.width() as i32;
+    let height = surface.height() as i32;
+
+    (Texture::from_surface(surface), width, height)
 }
 
 struct QuadSystem;
@@ -88,24 +401,52 @@ impl<'a> System<'a> for QuadSystem {
     type SystemData = (
         ReadStorage<'a, QuadComponent>,
         ReadStorage<'a, PositionComponent>,
+        ReadStorage<'a, BillboardComponent>,
         Read<'a, MeshMgrResource>,
         Read<'a, App>,
         Read<'a, UIResource>,
+        Option<Read<'a, OpenGlResource>>, //< The 3D camera, for billboards. Scenes without a 3D world don't have one.
     );
 
-    fn run(&mut self, (quads, positions, mesh_mgr, app, open_gl): Self::SystemData) {
-        for (quad, position) in (&quads, &positions).join() {
+    fn run(
+        &mut self,
+        (quads, positions, billboards, mesh_mgr, app, open_gl, world_gl): Self::SystemData,
+    ) {
+        // Quads are all drawn at the same depth, so later quads go over earlier ones rather than being hidden by them
+        unsafe {
+            gl::DepthMask(gl::FALSE);
+        }
+        let mut draws: Vec<_> = (&quads, &positions, billboards.maybe()).join().collect();
+        // A stable sort, so that quads with the same layer and order keep being drawn in the same order
+        draws.sort_by_key(|(quad, _, _)| (quad.layer, quad.order));
+        for (quad, position, billboard) in draws {
+            let pos = match (billboard, &world_gl) {
+                (None, _) => position.pos,
+                (Some(billboard), Some(world_gl)) => {
+                    match world_gl.camera.world_to_screen(billboard.anchor) {
+                        Some(on_screen) => nalgebra_glm::vec3(on_screen.x, on_screen.y, 0.0),
+                        None => continue,
+                    }
+                }
+                (Some(_), None) => continue,
+            };
             let mesh = mesh_mgr.data.get_mesh(quad.mesh_id);
             open_gl.program.set();
             quad.texture.activate(gl::TEXTURE0);
             quad.texture
                 .associate_uniform(open_gl.program.id(), 0, "texture0");
-            let u_opacity = Uniform::new(open_gl.program.id(), "u_opacity").unwrap();
-            unsafe { gl::Uniform1f(u_opacity.id, quad.opacity) }
+            let u_opacity = open_gl.program.uniform("u_opacity");
+            let u_tint = open_gl.program.uniform("u_tint");
+            let u_rotation = open_gl.program.uniform("u_rotation");
+            unsafe {
+                gl::Uniform1f(u_opacity.id, quad.opacity);
+                gl::Uniform3f(u_tint.id, quad.tint.x, quad.tint.y, quad.tint.z);
+                gl::Uniform1f(u_rotation.id, quad.rotation);
+            }
             mesh.draw(
                 &open_gl.program,
                 &open_gl.camera,
-                position.pos,
+                pos,
                 nalgebra_glm::vec3(
                     (quad.width as f32) / (app.screen_width as f32),
                     (quad.height as f32) / (app.screen_height as f32),
@@ -113,6 +454,9 @@ impl<'a> System<'a> for QuadSystem {
                 ),
             );
         }
+        unsafe {
+            gl::DepthMask(gl::TRUE);
+        }
     }
 }
 
@@ -120,7 +464,36 @@ pub fn initialize_gui(world: &mut World, dispatcher_builder: &mut DispatcherBuil
     // TODO: We will need an update and a render dispatch
     // Register GUI components
     world.register::<QuadComponent>();
+    world.register::<BillboardComponent>();
+    world.register::<TextComponent>();
+    world.register::<LayoutComponent>();
 
     // Add GUI systems to the dispatcher
-    dispatcher_builder.add(QuadSystem, "quad system", &[]);
+    dispatcher_builder.add(LayoutSystem, "layout system", &[]);
+    dispatcher_builder.add(QuadSystem, "quad system", &["layout system"]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: nalgebra_glm::Vec3, b: nalgebra_glm::Vec3) -> bool {
+        nalgebra_glm::distance(&a, &b) < 1e-5
+    }
+
+    #[test]
+    fn anchored_quads_stay_in_from_their_corner() {
+        let screen = (800, 600);
+        let top_left = Anchor::new(Align::Start, Align::Start).place((100, 20), (8, 8), screen);
+        assert!(close(
+            top_left,
+            nalgebra_glm::vec3(-1.0 + 116.0 / 800.0, 1.0 - 36.0 / 600.0, 0.0)
+        ));
+        let bottom_right = Anchor::new(Align::End, Align::End).place((100, 20), (8, 8), screen);
+        assert!(close(bottom_right, -top_left));
+        assert!(close(
+            Anchor::new(Align::Center, Align::Center).place((100, 20), (0, 0), screen),
+            nalgebra_glm::zero()
+        ));
+    }
 }
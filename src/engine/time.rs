@@ -0,0 +1,129 @@
+// Game time. Animations and timers step by `dt` rather than counting ticks, so that they keep their speed if the tick
+// rate changes, and slow down along with the game in slow motion. UI and audio go by `real_dt` instead, so that
+// hit-stops and slow motion never hold up a dialog or a sound.
+
+/// Seconds of real time per fixed update tick. The tick rate is set here, and everything else works from it.
+pub const TICK_SECONDS: f32 = 0.016;
+
+/// Converts a speed per second to a velocity, which move things by so much each tick
+pub const fn per_tick(per_second: f32) -> f32 {
+    per_second * TICK_SECONDS
+}
+
+/// Converts a velocity back to a speed per second
+pub const fn per_second(per_tick: f32) -> f32 {
+    per_tick / TICK_SECONDS
+}
+
+/// Converts an acceleration per second squared to how much it changes a velocity by each tick
+pub const fn accel_per_tick(per_second_squared: f32) -> f32 {
+    per_second_squared * TICK_SECONDS * TICK_SECONDS
+}
+
+/// A stretch of slowed down game time
+#[derive(Clone, Copy)]
+struct SlowMotion {
+    scale: f32,
+    seconds_left: f32, //< Real seconds
+}
+
+/// How much game time passes each tick
+pub struct TimeResource {
+    pub dt: f32,        //< Seconds of game time in the current tick
+    pub elapsed: f32,   //< Seconds of game time since the scene started
+    pub real_dt: f32, //< Seconds of real time in the current tick, whatever the game time is doing
+    pub scale: f32, //< How fast game time runs compared to real time, 1.0 is normal, lower is slow motion
+    hit_stop_left: f32, //< Real seconds until game time starts again after a hit-stop
+    slow_motion: Option<SlowMotion>,
+}
+
+impl Default for TimeResource {
+    fn default() -> Self {
+        Self {
+            dt: 0.0,
+            elapsed: 0.0,
+            real_dt: 0.0,
+            scale: 1.0,
+            hit_stop_left: 0.0,
+            slow_motion: None,
+        }
+    }
+}
+
+impl TimeResource {
+    /// Moves game time along by one tick, which took `real_dt` seconds of real time
+    pub fn tick(&mut self, real_dt: f32) {
+        // Hit-stops are rounded to the nearest tick
+        let scale = if self.hit_stop_left > real_dt / 2.0 {
+            0.0
+        } else {
+            self.slow_motion
+                .map_or(self.scale, |slow_motion| self.scale * slow_motion.scale)
+        };
+        self.hit_stop_left = (self.hit_stop_left - real_dt).max(0.0);
+        if let Some(slow_motion) = &mut self.slow_motion {
+            slow_motion.seconds_left -= real_dt;
+            if slow_motion.seconds_left <= 0.0 {
+                self.slow_motion = None;
+            }
+        }
+
+        self.real_dt = real_dt;
+        self.dt = real_dt * scale;
+        self.elapsed += self.dt;
+    }
+
+    /// How fast game time is running this tick compared to real time. Things that move by so much each tick, rather
+    /// than by `dt`, move by this much of it.
+    pub fn rate(&self) -> f32 {
+        if self.real_dt > 0.0 {
+            self.dt / self.real_dt
+        } else {
+            self.scale
+        }
+    }
+
+    /// Stops game time for `seconds` of real time, starting next tick. Doesn't cut short a longer hit-stop.
+    pub fn hit_stop(&mut self, seconds: f32) {
+        self.hit_stop_left = self.hit_stop_left.max(seconds);
+    }
+
+    /// Runs game time at `scale` of its usual speed for `seconds` of real time, starting next tick
+    pub fn slow_motion(&mut self, scale: f32, seconds: f32) {
+        self.slow_motion = Some(SlowMotion {
+            scale,
+            seconds_left: seconds,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TICK: f32 = 0.016;
+
+    #[test]
+    fn hit_stop_freezes_game_time_but_not_real_time() {
+        let mut time = TimeResource::default();
+        time.hit_stop(0.05);
+        for _ in 0..3 {
+            time.tick(TICK);
+            assert_eq!(time.dt, 0.0);
+            assert_eq!(time.real_dt, TICK);
+        }
+        time.tick(TICK);
+        assert_eq!(time.dt, TICK);
+    }
+
+    #[test]
+    fn slow_motion_wears_off() {
+        let mut time = TimeResource::default();
+        time.slow_motion(0.25, 2.0 * TICK);
+        time.tick(TICK);
+        assert_eq!(time.rate(), 0.25);
+        time.tick(TICK);
+        time.tick(TICK);
+        assert_eq!(time.rate(), 1.0);
+    }
+}
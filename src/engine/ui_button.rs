@@ -0,0 +1,150 @@
+// Mouse driven menu buttons. A button is any quad that can be hovered and clicked with the cursor, which only shows in
+// scenes that free it. Buttons that are also focusable take focus when hovered, and are selected when clicked, so menus
+// built for focus navigation work with the mouse too.
+
+use specs::{prelude::*, Component};
+
+use crate::App;
+
+use super::{
+    physics::PositionComponent,
+    text::QuadComponent,
+    ui_nav::{FocusableComponent, UiFocusResource},
+};
+
+const HOVER_TINT: (f32, f32, f32) = (1.0, 0.8, 0.2); //< The same as focus, for buttons that can't be focused
+const PRESSED_TINT: (f32, f32, f32) = (0.7, 0.5, 0.1);
+
+/// A quad that can be clicked
+#[derive(Component, Default)]
+#[storage(VecStorage)]
+pub struct ButtonComponent {
+    pub hovered: bool,
+    pub pressed: bool, //< The mouse went down on the button, and hasn't come up yet
+    pub clicked: bool, //< Only set for the tick the mouse came up on the button it went down on
+}
+
+/// Whether the mouse, in pixels from the top left of the screen, is over a quad `size` pixels big centered at `pos`
+fn contains(
+    pos: nalgebra_glm::Vec3,
+    size: (i32, i32),
+    mouse: (i32, i32),
+    screen: (i32, i32),
+) -> bool {
+    let (screen_width, screen_height) = (screen.0 as f32, screen.1 as f32);
+    let x = -1.0 + 2.0 * mouse.0 as f32 / screen_width;
+    let y = 1.0 - 2.0 * mouse.1 as f32 / screen_height;
+    (x - pos.x).abs() <= size.0 as f32 / screen_width
+        && (y - pos.y).abs() <= size.1 as f32 / screen_height
+}
+
+/// Finds the button under the cursor, and clicks it when the mouse goes down and up on it. Add after the navigation
+/// system, so that hovering and clicking win over it.
+#[derive(Default)]
+pub struct UiInteractionSystem {
+    mouse_was_down: bool,
+    last_mouse: (i32, i32),
+    pressed: Option<Entity>, //< The button the mouse went down on
+}
+
+impl<'a> System<'a> for UiInteractionSystem {
+    type SystemData = (
+        WriteStorage<'a, ButtonComponent>,
+        WriteStorage<'a, FocusableComponent>,
+        WriteStorage<'a, QuadComponent>,
+        ReadStorage<'a, PositionComponent>,
+        Write<'a, UiFocusResource>,
+        Read<'a, App>,
+        Entities<'a>,
+    );
+
+    fn run(
+        &mut self,
+        (mut buttons, mut focusables, mut quads, positions, mut focus, app, entities): Self::SystemData,
+    ) {
+        let mouse = (app.mouse_x, app.mouse_y);
+        let screen = (app.screen_width, app.screen_height);
+        let hovered = (&buttons, &quads, &positions, &entities)
+            .join()
+            .find(|(_, quad, position, _)| {
+                contains(position.pos, (quad.width, quad.height), mouse, screen)
+            })
+            .map(|(_, _, _, entity)| entity);
+
+        let mouse_down = app.mouse_left_down;
+        if mouse_down && !self.mouse_was_down {
+            self.pressed = hovered;
+        }
+        let released = !mouse_down && self.mouse_was_down;
+        let clicked = if released {
+            self.pressed
+                .take()
+                .filter(|pressed| Some(*pressed) == hovered)
+        } else {
+            None
+        };
+        self.mouse_was_down = mouse_down;
+
+        // Only moving the mouse takes focus, so that a resting cursor doesn't fight the keys
+        let mouse_moved = mouse != self.last_mouse;
+        self.last_mouse = mouse;
+        if mouse_moved && hovered.is_some_and(|hovered| focusables.contains(hovered)) {
+            focus.focused = hovered;
+        }
+
+        for (button, quad, entity) in (&mut buttons, &mut quads, &entities).join() {
+            button.hovered = hovered == Some(entity);
+            button.pressed = self.pressed == Some(entity);
+            button.clicked = clicked == Some(entity);
+            let focusable = focusables.get_mut(entity);
+            let tint = if button.pressed && button.hovered {
+                Some(PRESSED_TINT)
+            } else if focusable.is_some() {
+                None // Left tinted by focus
+            } else if button.hovered {
+                Some(HOVER_TINT)
+            } else {
+                Some((1.0, 1.0, 1.0))
+            };
+            if let Some(tint) = tint {
+                quad.tint = nalgebra_glm::vec3(tint.0, tint.1, tint.2);
+            }
+            if let (Some(focusable), true) = (focusable, button.clicked) {
+                focusable.selected = true;
+            }
+        }
+    }
+}
+
+/// Registers buttons. The system belongs in the update dispatcher. Call after `initialize_ui_navigation`, if the scene
+/// has focus navigation too, so that the mouse goes after the keys.
+pub fn initialize_ui_buttons(world: &mut World, dispatcher_builder: &mut DispatcherBuilder) {
+    world.register::<ButtonComponent>();
+    world.register::<FocusableComponent>();
+    let after: &[&str] = if world.has_value::<UiFocusResource>() {
+        &["ui navigation system"]
+    } else {
+        world.insert(UiFocusResource::default());
+        &[]
+    };
+    dispatcher_builder.add(
+        UiInteractionSystem::default(),
+        "ui interaction system",
+        after,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_cursor_is_over_a_quad_inside_its_edges() {
+        let (size, screen) = ((200, 40), (800, 600));
+        let center = nalgebra_glm::zero();
+        assert!(contains(center, size, (400, 300), screen));
+        assert!(contains(center, size, (499, 319), screen));
+        assert!(!contains(center, size, (510, 300), screen));
+        assert!(!contains(center, size, (400, 330), screen));
+    }
+}
@@ -0,0 +1,125 @@
+// Focus based menu navigation, so that every menu can be driven by a gamepad (or just the arrow keys)
+
+use sdl2::{
+    controller::{Axis, Button},
+    keyboard::Scancode,
+};
+use specs::{prelude::*, Component};
+
+use crate::App;
+
+use super::text::QuadComponent;
+
+const STICK_THRESHOLD: f32 = 0.5; //< How far the stick has to be pushed to move focus
+const FOCUS_TINT: (f32, f32, f32) = (1.0, 0.8, 0.2);
+
+/// Something in a menu that can be focused and selected
+#[derive(Component)]
+#[storage(VecStorage)]
+pub struct FocusableComponent {
+    pub order: i32,     //< Focus moves through focusables from lowest to highest order
+    pub selected: bool, //< Only set for the tick the focusable was selected
+}
+
+impl FocusableComponent {
+    pub fn new(order: i32) -> Self {
+        Self {
+            order,
+            selected: false,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct UiFocusResource {
+    pub focused: Option<Entity>,
+    pub back: bool, //< Only set for the tick back was pressed
+}
+
+/// Moves focus with the d-pad, left stick, or arrow keys. A/Enter selects the focused entity, B/Backspace backs out.
+/// The focused entity's quad is tinted so that it stands out.
+#[derive(Default)]
+pub struct UiNavigationSystem {
+    prev_was_down: bool,
+    next_was_down: bool,
+    select_was_down: bool,
+    back_was_down: bool,
+}
+
+impl<'a> System<'a> for UiNavigationSystem {
+    type SystemData = (
+        WriteStorage<'a, FocusableComponent>,
+        WriteStorage<'a, QuadComponent>,
+        Write<'a, UiFocusResource>,
+        Read<'a, App>,
+        Entities<'a>,
+    );
+
+    fn run(&mut self, (mut focusables, mut quads, mut focus, app, entities): Self::SystemData) {
+        let prev_down = app.button(Button::DPadUp)
+            || app.button(Button::DPadLeft)
+            || app.keys[Scancode::Up as usize]
+            || app.keys[Scancode::Left as usize]
+            || app.axis(Axis::LeftY) < -STICK_THRESHOLD
+            || app.axis(Axis::LeftX) < -STICK_THRESHOLD;
+        let next_down = app.button(Button::DPadDown)
+            || app.button(Button::DPadRight)
+            || app.keys[Scancode::Down as usize]
+            || app.keys[Scancode::Right as usize]
+            || app.axis(Axis::LeftY) > STICK_THRESHOLD
+            || app.axis(Axis::LeftX) > STICK_THRESHOLD;
+        let select_down = app.button(Button::A) || app.keys[Scancode::Return as usize];
+        let back_down = app.button(Button::B) || app.keys[Scancode::Backspace as usize];
+
+        let prev_pressed = prev_down && !self.prev_was_down;
+        let next_pressed = next_down && !self.next_was_down;
+        let select_pressed = select_down && !self.select_was_down;
+        focus.back = back_down && !self.back_was_down;
+        self.prev_was_down = prev_down;
+        self.next_was_down = next_down;
+        self.select_was_down = select_down;
+        self.back_was_down = back_down;
+
+        let mut order: Vec<(i32, Entity)> = (&focusables, &entities)
+            .join()
+            .map(|(focusable, entity)| (focusable.order, entity))
+            .collect();
+        order.sort();
+        if order.is_empty() {
+            focus.focused = None;
+            return;
+        }
+
+        // Focus the first entity if nothing is focused, or the focused entity went away
+        let mut index = focus
+            .focused
+            .and_then(|focused| order.iter().position(|(_, e)| *e == focused))
+            .unwrap_or(0);
+        if prev_pressed {
+            index = (index + order.len() - 1) % order.len();
+        }
+        if next_pressed {
+            index = (index + 1) % order.len();
+        }
+        let focused = order[index].1;
+        focus.focused = Some(focused);
+
+        for (focusable, entity) in (&mut focusables, &entities).join() {
+            focusable.selected = select_pressed && entity == focused;
+            if let Some(quad) = quads.get_mut(entity) {
+                quad.tint = if entity == focused {
+                    nalgebra_glm::vec3(FOCUS_TINT.0, FOCUS_TINT.1, FOCUS_TINT.2)
+                } else {
+                    nalgebra_glm::vec3(1.0, 1.0, 1.0)
+                };
+            }
+        }
+    }
+}
+
+/// Registers focus navigation. The system belongs in the update dispatcher, since it reads input edges.
+pub fn initialize_ui_navigation(world: &mut World, dispatcher_builder: &mut DispatcherBuilder) {
+    world.register::<FocusableComponent>();
+    world.insert(UiFocusResource::default());
+    dispatcher_builder.add(UiNavigationSystem::default(), "ui navigation system", &[]);
+}
@@ -0,0 +1,75 @@
+// Update level of detail. Things far from the camera are only updated every few ticks, stepping by all the time since
+// their last update, so that a big, lively island doesn't cost as much as if everything on it were up close.
+
+use specs::Entity;
+
+/// Beyond each distance from the camera, in world units, things are updated once every so many ticks. Furthest last.
+const LOD_BANDS: [(f32, usize); 2] = [(2.0, 2), (5.0, 4)];
+
+/// How often something is updated, from how far it is from the camera
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct UpdateLod {
+    period: usize, //< Ticks between updates
+}
+
+impl UpdateLod {
+    pub fn new(pos: nalgebra_glm::Vec3, camera_pos: nalgebra_glm::Vec3) -> Self {
+        let distance = nalgebra_glm::distance(&pos, &camera_pos);
+        let period = LOD_BANDS
+            .iter()
+            .rev()
+            .find(|(band_distance, _)| distance > *band_distance)
+            .map_or(1, |(_, period)| *period);
+        Self { period }
+    }
+
+    /// The time to step by on tick `ticks`, or None if it's skipped this tick. Entities are spread over the ticks by
+    /// id, so that the ones far away don't all update on the same tick.
+    pub fn dt(&self, entity: Entity, ticks: usize, dt: f32) -> Option<f32> {
+        if (ticks + entity.id() as usize).is_multiple_of(self.period) {
+            Some(dt * self.period as f32)
+        } else {
+            None
+        }
+    }
+}
+
+/// The time to step something at `pos` by this tick, if anything. Things without a position are always updated.
+pub fn lod_dt(
+    entity: Entity,
+    pos: Option<nalgebra_glm::Vec3>,
+    camera_pos: nalgebra_glm::Vec3,
+    ticks: usize,
+    dt: f32,
+) -> Option<f32> {
+    match pos {
+        Some(pos) => UpdateLod::new(pos, camera_pos).dt(entity, ticks, dt),
+        None => Some(dt),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::{Builder, World, WorldExt};
+
+    use super::*;
+
+    #[test]
+    fn far_things_update_less_often_but_keep_up() {
+        let mut world = World::new();
+        let entity = world.create_entity().build();
+        let lod = UpdateLod::new(nalgebra_glm::vec3(10.0, 0.0, 0.0), nalgebra_glm::zero());
+        let steps: Vec<f32> = (0..8)
+            .filter_map(|tick| lod.dt(entity, tick, 0.5))
+            .collect();
+        assert_eq!(steps, vec![2.0, 2.0]);
+    }
+
+    #[test]
+    fn close_things_update_every_tick() {
+        let mut world = World::new();
+        let entity = world.create_entity().build();
+        let lod = UpdateLod::new(nalgebra_glm::vec3(1.0, 0.0, 0.0), nalgebra_glm::zero());
+        assert!((0..8).all(|tick| lod.dt(entity, tick, 0.5) == Some(0.5)));
+    }
+}
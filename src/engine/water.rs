@@ -0,0 +1,178 @@
+use specs::{Read, System};
+
+use crate::App;
+
+use super::{
+    objects::Program,
+    render3d::{Mesh, MeshMgrResource, OpenGlResource},
+};
+
+/// Number of grid cells along each side of the water mesh
+const WATER_GRID_CELLS: usize = 256;
+
+/// The calm water level islands are generated around. Worldgen runs before there's a `WaterResource`, so it uses this
+/// directly, everything after should ask the resource.
+pub const SEA_LEVEL: f32 = 0.5;
+
+/// Set on the water's program each frame, along with the mesh's
+pub const WATER_UNIFORMS: &[&str] = &[
+    "u_resolution",
+    "u_time",
+    "u_camera_pos",
+    "u_wave_amplitude",
+    "u_wave_length",
+    "u_wave_speed",
+    "u_water_color",
+    "u_sky_color",
+    "u_sun_dir",
+    "u_underwater",
+];
+
+#[derive(Default)]
+pub struct WaterResource {
+    pub program: Program,
+    pub mesh_id: usize,
+    pub level: f32,                    //< Height of the calm water surface
+    pub color: nalgebra_glm::Vec3,     //< Deep water color
+    pub fog_color: nalgebra_glm::Vec3, //< What things fade into underwater
+    pub fog_density: f32,              //< Underwater fog thickness, per unit of distance
+    pub wave_amplitude: f32,           //< Height of the wave crests
+    pub wave_length: f32,              //< Distance between wave crests
+    pub wave_speed: f32,               //< How fast the wave crests move
+    pub sky_color: nalgebra_glm::Vec3, //< Reflected sky, set by the sky system
+    pub sun_dir: nalgebra_glm::Vec3,   //< For the sun glint, set by the sky system
+}
+
+impl WaterResource {
+    pub fn new(program: Program, mesh_id: usize, level: f32) -> Self {
+        Self {
+            program,
+            mesh_id,
+            level,
+            color: nalgebra_glm::vec3(0.05, 0.22, 0.32),
+            fog_color: nalgebra_glm::vec3(0.04, 0.18, 0.25),
+            fog_density: 12.0,
+            wave_amplitude: 0.012,
+            wave_length: 0.6,
+            wave_speed: 0.15,
+            sky_color: nalgebra_glm::vec3(0.67, 0.8, 0.97),
+            sun_dir: nalgebra_glm::vec3(0.0, 0.0, 1.0),
+        }
+    }
+
+    /// Whether a point is below the calm water surface
+    pub fn is_underwater(&self, pos: nalgebra_glm::Vec3) -> bool {
+        self.depth(pos) > 0.0
+    }
+
+    /// How far below the calm water surface a point is, negative when it's above the water
+    pub fn depth(&self, pos: nalgebra_glm::Vec3) -> f32 {
+        self.level - pos.z
+    }
+}
+
+/// Creates a flat square grid spanning [-1, 1]. Vertices are packed tightly around the center, and spread out towards
+/// the edges, so that waves near the camera are detailed while the water still reaches the horizon.
+pub fn create_water_mesh() -> Mesh {
+    let mut vertices = Vec::<f32>::new();
+    let mut indices = Vec::<u32>::new();
+
+    let warp = |i: usize| {
+        let t = 2.0 * (i as f32) / (WATER_GRID_CELLS as f32) - 1.0;
+        t.signum() * t * t
+    };
+    for y in 0..=WATER_GRID_CELLS {
+        for x in 0..=WATER_GRID_CELLS {
+            vertices.push(warp(x));
+            vertices.push(warp(y));
+            vertices.push(0.0);
+        }
+    }
+
+    let row = (WATER_GRID_CELLS + 1) as u32;
+    for y in 0..WATER_GRID_CELLS as u32 {
+        for x in 0..WATER_GRID_CELLS as u32 {
+            let i = x + y * row;
+            indices.extend([i, i + 1, i + row]);
+            indices.extend([i + 1, i + row + 1, i + row]);
+        }
+    }
+
+    Mesh::new(indices, vec![vertices])
+}
+
+/// Draws the water surface. Should run after the opaque 3D pass, since water is see-through.
+pub struct WaterRenderSystem;
+impl<'a> System<'a> for WaterRenderSystem {
+    type SystemData = (
+        Read<'a, App>,
+        Read<'a, MeshMgrResource>,
+        Read<'a, OpenGlResource>,
+        Read<'a, WaterResource>,
+    );
+
+    fn run(&mut self, (app, mesh_mgr, open_gl, water): Self::SystemData) {
+        const WATER_EXTENT: f32 = 1000.0;
+
+        let camera = &open_gl.camera;
+        let program = &water.program;
+        program.set();
+        unsafe {
+            gl::Uniform2f(
+                program.uniform("u_resolution").id,
+                app.screen_width as f32,
+                app.screen_height as f32,
+            );
+            gl::Uniform1f(program.uniform("u_time").id, app.seconds);
+            gl::Uniform3f(
+                program.uniform("u_camera_pos").id,
+                camera.position.x,
+                camera.position.y,
+                camera.position.z,
+            );
+            gl::Uniform1f(program.uniform("u_wave_amplitude").id, water.wave_amplitude);
+            gl::Uniform1f(program.uniform("u_wave_length").id, water.wave_length);
+            gl::Uniform1f(program.uniform("u_wave_speed").id, water.wave_speed);
+            gl::Uniform3f(
+                program.uniform("u_water_color").id,
+                water.color.x,
+                water.color.y,
+                water.color.z,
+            );
+            gl::Uniform3f(
+                program.uniform("u_sky_color").id,
+                water.sky_color.x,
+                water.sky_color.y,
+                water.sky_color.z,
+            );
+            gl::Uniform3f(
+                program.uniform("u_sun_dir").id,
+                water.sun_dir.x,
+                water.sun_dir.y,
+                water.sun_dir.z,
+            );
+            gl::Uniform1i(
+                program.uniform("u_underwater").id,
+                water.is_underwater(camera.position) as i32,
+            );
+
+            // The surface is seen from both above and below, and shouldn't hide other see-through things
+            gl::Disable(gl::CULL_FACE);
+            gl::DepthMask(gl::FALSE);
+        }
+
+        // The grid follows the camera around, the waves are computed in world space so they stay put
+        let mesh = mesh_mgr.data.get_mesh(water.mesh_id);
+        mesh.draw(
+            program,
+            camera,
+            nalgebra_glm::vec3(camera.position.x, camera.position.y, water.level),
+            nalgebra_glm::vec3(WATER_EXTENT, WATER_EXTENT, 1.0),
+        );
+
+        unsafe {
+            gl::DepthMask(gl::TRUE);
+            gl::Enable(gl::CULL_FACE);
+        }
+    }
+}
@@ -1,10 +1,11 @@
 mod engine;
 mod scenes;
 
-use std::cell::RefCell;
-
-use engine::app::*;
-use scenes::island::Island;
+use engine::{app::*, perlin::NoiseKind, snapshot::WorldSnapshot};
+use scenes::{
+    island::{run_golden_tests, run_simulation_tests, IslandLayout},
+    loading::LoadingScene,
+};
 
 // TODO:
 // x Island generation
@@ -22,7 +23,72 @@ use scenes::island::Island;
 // - Sound
 
 fn main() -> Result<(), String> {
-    run(800, 600, "Treasure Hunt", &|_app| {
-        RefCell::new(Box::new(Island::new()))
+    // `--diff-snapshots <a> <b>` prints how two world snapshots (dumped with F9) differ, without starting the game
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(i) = args.iter().position(|arg| arg == "--diff-snapshots") {
+        let (Some(a), Some(b)) = (args.get(i + 1), args.get(i + 2)) else {
+            return Err("--diff-snapshots expects two snapshot files".to_string());
+        };
+        let a = WorldSnapshot::load(a)?;
+        let b = WorldSnapshot::load(b)?;
+        for line in a.diff(&b) {
+            println!("{}", line);
+        }
+        return Ok(());
+    }
+
+    // `--bench-noise` times how long each kind of noise takes to fill an island-sized map
+    if args.iter().any(|arg| arg == "--bench-noise") {
+        for (kind, time) in engine::perlin::benchmark_noise(scenes::island::MAP_WIDTH) {
+            println!("{:?}: {:?}", kind, time);
+        }
+        return Ok(());
+    }
+
+    let asset_root = engine::assets::init_asset_root()?;
+    println!("Loading assets from {}", asset_root.display());
+
+    // `--package [dir]` puts together a folder to hand out, with this build and its assets, under `dist` by default
+    if let Some(i) = args.iter().position(|arg| arg == "--package") {
+        let out_dir = args.get(i + 1).filter(|arg| !arg.starts_with("--"));
+        let dir = engine::package::package(asset_root, out_dir.map(String::as_str))?;
+        println!("Packaged the game into {}", dir.display());
+        return Ok(());
+    }
+
+    // `--golden` renders the golden image gallery offscreen and checks it against `res/golden`. `--bless-golden`
+    // replaces the stored images instead.
+    let bless = args.iter().any(|arg| arg == "--bless-golden");
+    if bless || args.iter().any(|arg| arg == "--golden") {
+        return run_golden_tests(bless);
+    }
+
+    // `--simulate` plays through a few scripted scenarios without a window or GPU, and checks how the game responds
+    if args.iter().any(|arg| arg == "--simulate") {
+        return run_simulation_tests();
+    }
+
+    // `--seed <n>` replays a specific island, like one copied from the debug HUD
+    let seed = std::env::args()
+        .skip_while(|arg| arg != "--seed")
+        .nth(1)
+        .map(|arg| arg.parse::<u64>().expect("--seed expects a number"));
+    // `--debug-cursor` starts with a free cursor, for when a debugger or second monitor is involved. F2 toggles it.
+    let debug_cursor = std::env::args().any(|arg| arg == "--debug-cursor");
+    // `--archipelago` makes a few smaller islands to swim between, rather than one big one
+    let layout = if std::env::args().any(|arg| arg == "--archipelago") {
+        IslandLayout::Archipelago
+    } else {
+        IslandLayout::Single
+    };
+    // `--gradient-noise` starts islands from gradient noise rather than value noise, which doesn't show a grid
+    let noise = if std::env::args().any(|arg| arg == "--gradient-noise") {
+        NoiseKind::Gradient
+    } else {
+        NoiseKind::Value
+    };
+
+    run(800, 600, "Treasure Hunt", debug_cursor, &|_app| {
+        Ok(Box::new(LoadingScene::new(seed, layout, noise)?))
     })
 }
@@ -0,0 +1,448 @@
+// What mobs do. Each mob is in one state at a time: standing around, wandering, chasing the player once it has
+// noticed them, lunging at them up close, or fleeing when badly hurt. Mobs won't walk into deep water on their own,
+// and ghosts won't walk into torchlight at night.
+// Different kinds of mobs can tune how they behave through their `AiParams`, down to circling in the air instead of
+// walking. Walking mobs that can't chase the player in a straight line go around whatever's in the way.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use specs::{prelude::*, Component};
+
+use crate::engine::{
+    perlin::PerlinMapResource,
+    physics::{PositionComponent, VelocityComponent},
+    time::{accel_per_tick, per_tick, TimeResource},
+    water::WaterResource,
+};
+
+use super::{
+    is_night,
+    navigation::{NavGridResource, CELL_SIZE},
+    perception::PerceptionComponent,
+    torch::LightResource,
+    HealthComponent, PlayerComponent, GRAVITY, PERSON_HEIGHT, UNIT_PER_METER,
+};
+
+const REPATH_SECONDS: f32 = 1.0; //< How often a chasing mob looks for a new way round to the player
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub(super) enum AiState {
+    Idle,
+    Wander, //< Walking along `wander_heading`, or over to a noise
+    Chase,
+    Attack, //< Lunging at the player, once close enough
+    Flee,
+}
+
+/// How a kind of mob behaves. Speeds are in meters per second.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub(super) struct AiParams {
+    pub wander_speed: f32,
+    pub chase_speed: f32,
+    pub attack_speed: f32,
+    pub flee_speed: f32,
+    pub idle_seconds: f32, //< How long the mob stands around before wandering off
+    pub wander_seconds: f32, //< How long the mob wanders before standing around again
+    pub attack_range: f32, //< How close the player has to be for the mob to lunge
+    pub flee_health: f32,  //< Below this much health, the mob runs from the player
+    pub flee_distance: f32, //< How far from the player a fleeing mob feels safe
+    pub max_water_depth: f32, //< The mob won't walk anywhere deeper than this
+    pub turn_rate: f32,    //< Radians per second the mob turns while wandering, 0 wanders straight
+    pub fly_height: f32,   //< How high above the ground or water the mob flies, 0 walks
+    pub fears_light: bool, //< Keeps out of lights at night
+}
+
+impl Default for AiParams {
+    /// Ghosts, which other kinds of mobs tweak
+    fn default() -> Self {
+        Self {
+            wander_speed: 4.0,
+            chase_speed: 12.5,
+            attack_speed: 16.0,
+            flee_speed: 14.0,
+            idle_seconds: 3.0,
+            wander_seconds: 4.0,
+            attack_range: 0.5,
+            flee_health: 0.3,
+            flee_distance: 6.0,
+            max_water_depth: 0.02,
+            turn_rate: 0.0,
+            fly_height: 0.0,
+            fears_light: true,
+        }
+    }
+}
+
+#[derive(Component, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub(super) struct AiComponent {
+    pub state: AiState,
+    pub seconds_in_state: f32,
+    pub wander_heading: f32, //< Radians, 0 is east
+    pub params: AiParams,
+    #[serde(skip)]
+    pub path: Vec<nalgebra_glm::Vec2>, //< Spots still to go through on the way round to the player
+    #[serde(skip)]
+    pub repath_seconds: f32, //< Until the path is found again, as the player moves
+}
+
+impl AiComponent {
+    pub fn new(params: AiParams) -> Self {
+        Self {
+            state: AiState::Idle,
+            seconds_in_state: 0.0,
+            wander_heading: 0.0,
+            params,
+            path: vec![],
+            repath_seconds: 0.0,
+        }
+    }
+}
+
+/// Where a chasing mob heads for: straight at the player when nothing's in the way, or the next spot along a path
+/// around whatever is. Straight at the player if there's no way round.
+fn chase_target(
+    ai: &mut AiComponent,
+    from: nalgebra_glm::Vec2,
+    to: nalgebra_glm::Vec2,
+    nav: &NavGridResource,
+    dt: f32,
+) -> nalgebra_glm::Vec2 {
+    if nav.line_clear(from, to) {
+        ai.path.clear();
+        ai.repath_seconds = 0.0;
+        return to;
+    }
+    ai.repath_seconds -= dt;
+    if ai.repath_seconds <= 0.0 {
+        ai.path = nav.find_path(from, to).unwrap_or_default();
+        ai.repath_seconds = REPATH_SECONDS;
+    }
+    // Spots are passed once the mob is near enough, except the last, which is where the player was
+    while ai.path.len() > 1 && nalgebra_glm::distance(&from, &ai.path[0]) < CELL_SIZE * 0.5 {
+        ai.path.remove(0);
+    }
+    ai.path.first().copied().unwrap_or(to)
+}
+
+/// What a mob knows when deciding what to do next
+#[derive(Clone, Copy, Debug)]
+pub(super) struct AiInputs {
+    pub aggro: bool,         //< The mob has noticed the player
+    pub investigating: bool, //< The mob heard something and is going to check it out
+    pub player_distance: f32,
+    pub health: f32,
+}
+
+/// The state a mob moves into, given how long it's been in its current one and what it knows
+pub(super) fn next_state(
+    state: AiState,
+    seconds_in_state: f32,
+    inputs: AiInputs,
+    params: &AiParams,
+) -> AiState {
+    // Badly hurt mobs run until they're far enough away, and stop chasing
+    let hurt = inputs.health < params.flee_health;
+    if hurt && inputs.player_distance < params.flee_distance {
+        return AiState::Flee;
+    }
+
+    if inputs.aggro && !hurt {
+        // A little further to give up on a lunge than to start one, so mobs don't flicker between the two
+        let attack_range = if state == AiState::Attack {
+            params.attack_range * 1.5
+        } else {
+            params.attack_range
+        };
+        return if inputs.player_distance < attack_range {
+            AiState::Attack
+        } else {
+            AiState::Chase
+        };
+    }
+
+    if inputs.investigating {
+        return AiState::Wander;
+    }
+    match state {
+        AiState::Idle if seconds_in_state >= params.idle_seconds => AiState::Wander,
+        AiState::Wander if seconds_in_state >= params.wander_seconds => AiState::Idle,
+        AiState::Idle | AiState::Wander => state,
+        // Lost track of the player
+        AiState::Chase | AiState::Attack | AiState::Flee => AiState::Idle,
+    }
+}
+
+/// Runs each mob's state machine, and moves it accordingly
+pub(super) struct AiSystem {
+    pub rng: StdRng, //< Picks wander headings
+}
+
+impl AiSystem {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed ^ 0x0a1_5eed),
+        }
+    }
+}
+
+impl<'a> System<'a> for AiSystem {
+    type SystemData = (
+        WriteStorage<'a, AiComponent>,
+        WriteStorage<'a, PerceptionComponent>,
+        ReadStorage<'a, HealthComponent>,
+        ReadStorage<'a, PlayerComponent>,
+        ReadStorage<'a, PositionComponent>,
+        WriteStorage<'a, VelocityComponent>,
+        Read<'a, PerlinMapResource>,
+        Read<'a, NavGridResource>,
+        Read<'a, WaterResource>,
+        Read<'a, LightResource>,
+        Read<'a, TimeResource>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            mut ais,
+            mut perceptions,
+            healths,
+            players,
+            positions,
+            mut velocities,
+            tiles,
+            nav,
+            water,
+            lights,
+            time,
+        ): Self::SystemData,
+    ) {
+        let Some((_, player_position)) = (&players, &positions).join().next() else {
+            return;
+        };
+        let player_pos = player_position.pos;
+        let night = is_night(time.elapsed);
+
+        for (ai, perception, health, position, velocity) in (
+            &mut ais,
+            &mut perceptions,
+            &healths,
+            &positions,
+            &mut velocities,
+        )
+            .join()
+        {
+            let to_player = (player_pos - position.pos).xy();
+            let inputs = AiInputs {
+                aggro: perception.aggro,
+                investigating: perception.investigating.is_some(),
+                player_distance: nalgebra_glm::length(&to_player),
+                health: health.health,
+            };
+            let state = next_state(ai.state, ai.seconds_in_state, inputs, &ai.params);
+            if state != ai.state {
+                ai.state = state;
+                ai.seconds_in_state = 0.0;
+                if state == AiState::Wander {
+                    ai.wander_heading = self
+                        .rng
+                        .gen_range(-std::f32::consts::PI..std::f32::consts::PI);
+                }
+            }
+            ai.seconds_in_state += time.dt;
+
+            // Flying mobs hold their height against gravity, and swoop down to the player's chest to attack
+            if ai.params.fly_height > 0.0 {
+                let below = tiles
+                    .map
+                    .get_z_interpolated(position.pos.xy())
+                    .max(water.level);
+                let height = if ai.state == AiState::Attack {
+                    PERSON_HEIGHT * 0.5
+                } else {
+                    ai.params.fly_height * UNIT_PER_METER
+                };
+                // Enough to cancel out gravity, and then some towards the height it wants to fly at
+                velocity.vel.z = accel_per_tick(GRAVITY) + (below + height - position.pos.z) * 0.05;
+            }
+
+            if ai.state == AiState::Wander {
+                ai.wander_heading += ai.params.turn_rate * time.dt;
+            }
+            let (heading, speed) = match ai.state {
+                AiState::Idle => continue,
+                AiState::Wander => match perception.investigating {
+                    Some(spot) => {
+                        let to_spot = (spot - position.pos).xy();
+                        (to_spot.y.atan2(to_spot.x), ai.params.wander_speed)
+                    }
+                    None => (ai.wander_heading, ai.params.wander_speed),
+                },
+                AiState::Chase if ai.params.fly_height > 0.0 => {
+                    (to_player.y.atan2(to_player.x), ai.params.chase_speed)
+                }
+                AiState::Chase => {
+                    let from = position.pos.xy();
+                    let to_target = chase_target(ai, from, player_pos.xy(), &nav, time.dt) - from;
+                    (to_target.y.atan2(to_target.x), ai.params.chase_speed)
+                }
+                AiState::Attack => (to_player.y.atan2(to_player.x), ai.params.attack_speed),
+                AiState::Flee => ((-to_player.y).atan2(-to_player.x), ai.params.flee_speed),
+            };
+
+            // Look a step ahead, and stay out of deep water, and light if afraid of it. Wandering mobs turn around
+            // instead.
+            let step =
+                nalgebra_glm::vec2(heading.cos(), heading.sin()) * per_tick(speed * UNIT_PER_METER);
+            let ahead = position.pos.xy() + step * 10.0;
+            let ground = nalgebra_glm::vec3(ahead.x, ahead.y, tiles.map.get_z_interpolated(ahead));
+            let lit = night && ai.params.fears_light && lights.lit_by(ahead).is_some();
+            if tiles.map.oob(ahead) || water.depth(ground) > ai.params.max_water_depth || lit {
+                if ai.state == AiState::Wander {
+                    ai.wander_heading += std::f32::consts::PI;
+                }
+                continue;
+            }
+
+            velocity.vel.x = step.x;
+            velocity.vel.y = step.y;
+            if matches!(ai.state, AiState::Wander | AiState::Flee) {
+                perception.facing = heading;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inputs() -> AiInputs {
+        AiInputs {
+            aggro: false,
+            investigating: false,
+            player_distance: 10.0,
+            health: 1.0,
+        }
+    }
+
+    #[test]
+    fn idle_mobs_wander_off_after_a_while() {
+        let params = AiParams::default();
+        assert_eq!(
+            next_state(AiState::Idle, 0.0, inputs(), &params),
+            AiState::Idle
+        );
+        assert_eq!(
+            next_state(AiState::Idle, params.idle_seconds, inputs(), &params),
+            AiState::Wander
+        );
+        assert_eq!(
+            next_state(AiState::Wander, params.wander_seconds, inputs(), &params),
+            AiState::Idle
+        );
+    }
+
+    #[test]
+    fn noises_get_investigated() {
+        let params = AiParams::default();
+        let heard = AiInputs {
+            investigating: true,
+            ..inputs()
+        };
+        assert_eq!(
+            next_state(AiState::Idle, 0.0, heard, &params),
+            AiState::Wander
+        );
+        // Even after the usual wandering time is up
+        assert_eq!(
+            next_state(AiState::Wander, params.wander_seconds, heard, &params),
+            AiState::Wander
+        );
+    }
+
+    #[test]
+    fn aggro_mobs_chase_then_attack() {
+        let params = AiParams::default();
+        let far = AiInputs {
+            aggro: true,
+            ..inputs()
+        };
+        assert_eq!(next_state(AiState::Idle, 0.0, far, &params), AiState::Chase);
+        let close = AiInputs {
+            player_distance: params.attack_range * 0.5,
+            ..far
+        };
+        assert_eq!(
+            next_state(AiState::Chase, 0.0, close, &params),
+            AiState::Attack
+        );
+    }
+
+    #[test]
+    fn attacks_keep_going_a_little_past_the_attack_range() {
+        let params = AiParams::default();
+        let just_outside = AiInputs {
+            aggro: true,
+            player_distance: params.attack_range * 1.2,
+            ..inputs()
+        };
+        assert_eq!(
+            next_state(AiState::Attack, 0.0, just_outside, &params),
+            AiState::Attack
+        );
+        assert_eq!(
+            next_state(AiState::Chase, 0.0, just_outside, &params),
+            AiState::Chase
+        );
+    }
+
+    #[test]
+    fn losing_the_player_goes_back_to_idle() {
+        let params = AiParams::default();
+        for state in [AiState::Chase, AiState::Attack] {
+            assert_eq!(next_state(state, 0.0, inputs(), &params), AiState::Idle);
+        }
+    }
+
+    #[test]
+    fn badly_hurt_mobs_flee_until_safe() {
+        let params = AiParams::default();
+        let hurt = AiInputs {
+            aggro: true,
+            player_distance: 1.0,
+            health: params.flee_health * 0.5,
+            ..inputs()
+        };
+        assert_eq!(
+            next_state(AiState::Chase, 0.0, hurt, &params),
+            AiState::Flee
+        );
+        let safe = AiInputs {
+            player_distance: params.flee_distance,
+            ..hurt
+        };
+        assert_eq!(next_state(AiState::Flee, 0.0, safe, &params), AiState::Idle);
+    }
+
+    #[test]
+    fn mob_types_can_override_parameters() {
+        let skittish = AiParams {
+            flee_health: 1.1,
+            ..AiParams::default()
+        };
+        let aggro = AiInputs {
+            aggro: true,
+            player_distance: 1.0,
+            ..inputs()
+        };
+        assert_eq!(
+            next_state(AiState::Idle, 0.0, aggro, &skittish),
+            AiState::Flee
+        );
+        assert_eq!(
+            next_state(AiState::Idle, 0.0, aggro, &AiParams::default()),
+            AiState::Chase
+        );
+    }
+}
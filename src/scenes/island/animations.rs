@@ -0,0 +1,134 @@
+// The island's animation clips, and picking which one each mob plays from what it's doing
+
+use specs::prelude::*;
+
+use crate::engine::animation::{AnimationComponent, Clip, Keyframe, Pose};
+
+use super::ai::{AiComponent, AiState};
+
+const fn key(time: f32, offset: [f32; 3], rotation: [f32; 3], scale: [f32; 3]) -> Keyframe {
+    Keyframe {
+        time,
+        pose: Pose {
+            offset: nalgebra_glm::Vec3::new(offset[0], offset[1], offset[2]),
+            rotation: nalgebra_glm::Vec3::new(rotation[0], rotation[1], rotation[2]),
+            scale: nalgebra_glm::Vec3::new(scale[0], scale[1], scale[2]),
+        },
+    }
+}
+
+const REST: Keyframe = key(0.0, [0.0; 3], [0.0; 3], [1.0; 3]);
+
+// Breathing in and out
+const MOB_IDLE: [Keyframe; 3] = [
+    REST,
+    key(1.0, [0.0, 0.0, 0.003], [0.0; 3], [1.03, 1.03, 0.97]),
+    key(2.0, [0.0; 3], [0.0; 3], [1.0; 3]),
+];
+// Bobbing up with each step, and rocking side to side
+const MOB_WALK: [Keyframe; 5] = [
+    REST,
+    key(0.15, [0.0, 0.0, 0.01], [0.12, 0.0, 0.0], [1.0; 3]),
+    key(0.3, [0.0; 3], [0.0; 3], [1.0; 3]),
+    key(0.45, [0.0, 0.0, 0.01], [-0.12, 0.0, 0.0], [1.0; 3]),
+    key(0.6, [0.0; 3], [0.0; 3], [1.0; 3]),
+];
+// Crouching down, then springing up and forward
+const MOB_ATTACK: [Keyframe; 4] = [
+    REST,
+    key(0.12, [0.0; 3], [0.0; 3], [1.15, 1.15, 0.75]),
+    key(0.25, [0.0, 0.0, 0.02], [0.0, 0.3, 0.0], [0.9, 0.9, 1.2]),
+    key(0.5, [0.0; 3], [0.0; 3], [1.0; 3]),
+];
+pub(super) const MOB_CLIPS: [Clip; 3] = [
+    Clip {
+        name: "idle",
+        keyframes: &MOB_IDLE,
+        looping: true,
+    },
+    Clip {
+        name: "walk",
+        keyframes: &MOB_WALK,
+        looping: true,
+    },
+    Clip {
+        name: "attack",
+        keyframes: &MOB_ATTACK,
+        looping: true,
+    },
+];
+
+// Jolting as the lid is thrown open, then settling
+const CHEST_OPEN: [Keyframe; 4] = [
+    REST,
+    key(0.1, [0.0; 3], [0.0; 3], [1.1, 1.1, 0.8]),
+    key(0.3, [0.0, 0.0, 0.03], [0.0; 3], [0.95, 0.95, 1.1]),
+    key(0.5, [0.0; 3], [0.0; 3], [1.0; 3]),
+];
+pub(super) const CHEST_CLIPS: [Clip; 1] = [Clip {
+    name: "chest-open",
+    keyframes: &CHEST_OPEN,
+    looping: false,
+}];
+
+// The lid of an opened chest swinging back on its hinge, overshooting a little and settling. The lid turns about its
+// middle, so it's moved back and up as well, to keep its back edge on the hinge.
+const LID_OPEN: [Keyframe; 4] = [
+    REST,
+    key(0.15, [-0.0275, 0.0, 0.0476], [0.0, -1.047, 0.0], [1.0; 3]),
+    key(0.3, [-0.0782, 0.0, 0.0498], [0.0, -2.007, 0.0], [1.0; 3]),
+    key(0.45, [-0.0645, 0.0, 0.0542], [0.0, -1.745, 0.0], [1.0; 3]),
+];
+pub(super) const LID_CLIPS: [Clip; 1] = [Clip {
+    name: "lid-open",
+    keyframes: &LID_OPEN,
+    looping: false,
+}];
+
+// Coins spinning as they fly
+const COIN_SPIN: [Keyframe; 3] = [
+    REST,
+    key(0.3, [0.0; 3], [0.0, 0.0, std::f32::consts::PI], [1.0; 3]),
+    key(0.6, [0.0; 3], [0.0, 0.0, std::f32::consts::TAU], [1.0; 3]),
+];
+pub(super) const COIN_CLIPS: [Clip; 1] = [Clip {
+    name: "spin",
+    keyframes: &COIN_SPIN,
+    looping: true,
+}];
+
+// Pickups bobbing up and down as they turn, waiting to be picked up
+const LOOT_BOB: [Keyframe; 3] = [
+    REST,
+    key(
+        0.8,
+        [0.0, 0.0, 0.004],
+        [0.0, 0.0, std::f32::consts::PI],
+        [1.0; 3],
+    ),
+    key(1.6, [0.0; 3], [0.0, 0.0, std::f32::consts::TAU], [1.0; 3]),
+];
+pub(super) const LOOT_CLIPS: [Clip; 1] = [Clip {
+    name: "bob",
+    keyframes: &LOOT_BOB,
+    looping: true,
+}];
+
+/// Plays the clip that goes with what each mob is doing
+pub(super) struct MobAnimationSystem;
+impl<'a> System<'a> for MobAnimationSystem {
+    type SystemData = (
+        ReadStorage<'a, AiComponent>,
+        WriteStorage<'a, AnimationComponent>,
+    );
+
+    fn run(&mut self, (ais, mut animations): Self::SystemData) {
+        for (ai, animation) in (&ais, &mut animations).join() {
+            animation.play(match ai.state {
+                AiState::Idle => "idle",
+                AiState::Wander | AiState::Chase | AiState::Flee => "walk",
+                AiState::Attack => "attack",
+            });
+        }
+    }
+}
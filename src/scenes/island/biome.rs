@@ -0,0 +1,132 @@
+// Biomes, which decide how the ground is colored, what grows on it, and what roams it
+
+use super::mobs::MobKind;
+
+const FOREST_MOISTURE: f32 = 20.0; //< Wetter than this grows forest
+const SWAMP_MOISTURE: f32 = 60.0; //< Wetter than this, low down, is swamp
+const SWAMP_MAX_HEIGHT: f32 = 1.5;
+const PEAK_HEIGHT: f32 = 4.0; //< Everything above this is bare rock
+
+#[derive(Clone, Copy, PartialEq)]
+pub(super) enum Biome {
+    Seabed, //< Anywhere under the water, where nothing grows or roams
+    Beach,
+    Grassland,
+    Forest,
+    RockyPeak,
+    Swamp,
+}
+
+/// How a biome looks, and what grows there. Chances are relative, a spot is tried until something fits somewhere.
+pub(super) struct BiomeConfig {
+    pub name: &'static str,
+    pub color: [f32; 3],
+    pub tree_chance: f32, //< Chance a tree is planted on a spot picked in this biome
+    pub bush_chance: f32, //< Chance a bush is planted on a spot picked in this biome
+    pub mob: Option<MobKind>, //< What the spawner spawns here
+    pub grass_density: f32, //< Tufts of grass per square meter
+    pub flower_chance: f32, //< Chance a tuft is a flower instead
+}
+
+const SEABED: BiomeConfig = BiomeConfig {
+    name: "shallows",
+    color: [0.86, 0.74, 0.62],
+    tree_chance: 0.0,
+    bush_chance: 0.0,
+    mob: None,
+    grass_density: 0.0,
+    flower_chance: 0.0,
+};
+const BEACH: BiomeConfig = BiomeConfig {
+    name: "beach",
+    color: [0.86, 0.74, 0.62],
+    tree_chance: 0.0,
+    bush_chance: 0.02,
+    mob: Some(MobKind::Crab),
+    grass_density: 0.01,
+    flower_chance: 0.0,
+};
+const GRASSLAND: BiomeConfig = BiomeConfig {
+    name: "grassland",
+    color: [0.27, 0.36, 0.19],
+    tree_chance: 0.1,
+    bush_chance: 0.3,
+    mob: Some(MobKind::Ghost),
+    grass_density: 2.0,
+    flower_chance: 0.04,
+};
+const FOREST: BiomeConfig = BiomeConfig {
+    name: "forest",
+    color: [0.2, 0.3, 0.14],
+    tree_chance: 1.0,
+    bush_chance: 0.4,
+    mob: Some(MobKind::Ghost),
+    grass_density: 0.8,
+    flower_chance: 0.01,
+};
+const ROCKY_PEAK: BiomeConfig = BiomeConfig {
+    name: "peaks",
+    color: [0.5, 0.45, 0.4],
+    tree_chance: 0.0,
+    bush_chance: 0.05, //< Only the odd bush clings on, so that cliffs stay bare rock
+    mob: Some(MobKind::Bird),
+    grass_density: 0.15,
+    flower_chance: 0.02,
+};
+const SWAMP: BiomeConfig = BiomeConfig {
+    name: "swamp",
+    color: [0.24, 0.27, 0.16],
+    tree_chance: 0.3,
+    bush_chance: 0.8,
+    mob: Some(MobKind::Ghost),
+    grass_density: 1.2,
+    flower_chance: 0.0,
+};
+
+impl Biome {
+    /// Picks the biome for a spot on the island.
+    /// - slope: dot product of the ground's normal with up, 1 is flat and 0 is a cliff
+    /// - moisture: from the erosion moisture map
+    /// - underwater: whether the spot is below the water, see `WaterResource::is_underwater`
+    pub fn classify(height: f32, slope: f32, moisture: f32, underwater: bool) -> Self {
+        if underwater {
+            Biome::Seabed
+        } else if height < 0.9 * slope && 0.9 < slope {
+            Biome::Beach
+        } else if slope < 0.9 || height > PEAK_HEIGHT {
+            Biome::RockyPeak
+        } else if moisture > SWAMP_MOISTURE && height < SWAMP_MAX_HEIGHT {
+            Biome::Swamp
+        } else if moisture > FOREST_MOISTURE {
+            Biome::Forest
+        } else {
+            Biome::Grassland
+        }
+    }
+
+    pub fn config(&self) -> &'static BiomeConfig {
+        match self {
+            Biome::Seabed => &SEABED,
+            Biome::Beach => &BEACH,
+            Biome::Grassland => &GRASSLAND,
+            Biome::Forest => &FOREST,
+            Biome::RockyPeak => &ROCKY_PEAK,
+            Biome::Swamp => &SWAMP,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nothing_grows_or_roams_under_the_water() {
+        let seabed = Biome::classify(0.2, 1.0, 0.0, true);
+        assert!(seabed == Biome::Seabed);
+        assert_eq!(seabed.config().tree_chance, 0.0);
+        assert_eq!(seabed.config().bush_chance, 0.0);
+        assert!(seabed.config().mob.is_none());
+        assert!(Biome::classify(0.6, 1.0, 0.0, false) == Biome::Beach);
+    }
+}
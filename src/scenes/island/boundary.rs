@@ -0,0 +1,132 @@
+// The edge of the world. Past the island, a current pushes back harder the further out the player swims. Fighting it
+// wears the player out, and if they keep at it, or get right to the edge of the map, they wash up back on the shore.
+
+use specs::prelude::*;
+
+use crate::engine::{
+    perlin::PerlinMapResource,
+    physics::{PositionComponent, VelocityComponent},
+    time::{per_tick, TimeResource},
+    water::WaterResource,
+};
+
+use super::{PlayerComponent, ToastResource, MAP_WIDTH, UNIT_PER_METER};
+
+const EDGE_LIMIT: f32 = 2.0; //< Tiles in from the edge of the map that the player can't get past
+const CURRENT_WIDTH: f32 = 10.0; //< Tiles the current runs over, out to the limit
+const CURRENT_SPEED: f32 = 8.0 * UNIT_PER_METER; //< Meters per second at the limit, faster than the player swims
+const FATIGUE_SECONDS: f32 = 15.0; //< How long the player can fight the current before being washed up
+const WARN_SECONDS: f32 = 5.0; //< How long into the current the player is warned
+const SHORE_STEP: f32 = 0.5; //< Tiles between the spots checked for dry land, on the way back in
+
+/// How far out into the current a point is, from 0 where it starts to 1 at the limit. Points inside the current's
+/// inner edge are 0, and points past the limit are more than 1.
+fn current_strength(pos: nalgebra_glm::Vec2) -> f32 {
+    let size = MAP_WIDTH as f32 - 1.0;
+    let to_edge = pos.x.min(pos.y).min(size - pos.x).min(size - pos.y);
+    ((EDGE_LIMIT + CURRENT_WIDTH - to_edge) / CURRENT_WIDTH).max(0.0)
+}
+
+/// Which way is back towards the middle of the island
+fn inland(pos: nalgebra_glm::Vec2) -> nalgebra_glm::Vec2 {
+    let middle = nalgebra_glm::vec2(MAP_WIDTH as f32, MAP_WIDTH as f32) / 2.0;
+    let to_middle = middle - pos;
+    if nalgebra_glm::length(&to_middle) < 0.001 {
+        return nalgebra_glm::zero();
+    }
+    to_middle.normalize()
+}
+
+/// Pushes the player back towards the island, and washes them up on the shore once they've fought it too long
+#[derive(Default)]
+pub(super) struct BoundarySystem {
+    fighting_for: f32, //< Seconds the player has spent in the current
+}
+
+impl<'a> System<'a> for BoundarySystem {
+    type SystemData = (
+        ReadStorage<'a, PlayerComponent>,
+        WriteStorage<'a, PositionComponent>,
+        WriteStorage<'a, VelocityComponent>,
+        Read<'a, PerlinMapResource>,
+        Read<'a, WaterResource>,
+        Read<'a, TimeResource>,
+        Write<'a, ToastResource>,
+    );
+
+    fn run(
+        &mut self,
+        (players, mut positions, mut velocities, tiles, water, time, mut toast): Self::SystemData,
+    ) {
+        for (_, position, velocity) in (&players, &mut positions, &mut velocities).join() {
+            let strength = current_strength(position.pos.xy());
+            if strength <= 0.0 {
+                self.fighting_for = 0.0;
+                continue;
+            }
+            let was_fighting_for = self.fighting_for;
+            self.fighting_for += time.dt;
+            if was_fighting_for < WARN_SECONDS && self.fighting_for >= WARN_SECONDS {
+                toast.show("The current is wearing you out, head back to the island");
+            }
+
+            if strength < 1.0 && self.fighting_for < FATIGUE_SECONDS {
+                let push = inland(position.pos.xy()) * per_tick(CURRENT_SPEED * strength);
+                velocity.vel.x += push.x;
+                velocity.vel.y += push.y;
+                continue;
+            }
+
+            // Washed back along the way in, until there's dry land
+            let size = MAP_WIDTH as f32 - 1.0;
+            let mut shore = if position.pos.x.is_finite() && position.pos.y.is_finite() {
+                position
+                    .pos
+                    .xy()
+                    .map(|c| c.clamp(EDGE_LIMIT, size - EDGE_LIMIT))
+            } else {
+                nalgebra_glm::vec2(size, size) / 2.0
+            };
+            let dir = inland(shore);
+            let steps = (MAP_WIDTH as f32 / SHORE_STEP) as usize;
+            for _ in 0..steps {
+                if tiles.map.get_z_interpolated(shore) > water.level {
+                    break;
+                }
+                shore += dir * SHORE_STEP;
+            }
+            position.pos =
+                nalgebra_glm::vec3(shore.x, shore.y, tiles.map.get_z_interpolated(shore));
+            velocity.vel = nalgebra_glm::zero();
+            self.fighting_for = 0.0;
+            toast.show("You washed up on the shore, exhausted");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_island_has_no_current() {
+        let middle = nalgebra_glm::vec2(MAP_WIDTH as f32, MAP_WIDTH as f32) / 2.0;
+        assert_eq!(current_strength(middle), 0.0);
+    }
+
+    #[test]
+    fn the_current_grows_towards_the_edge() {
+        let y = MAP_WIDTH as f32 / 2.0;
+        let inner = current_strength(nalgebra_glm::vec2(EDGE_LIMIT + CURRENT_WIDTH * 0.75, y));
+        let outer = current_strength(nalgebra_glm::vec2(EDGE_LIMIT + CURRENT_WIDTH * 0.25, y));
+        assert!(0.0 < inner && inner < outer && outer < 1.0);
+        assert!(current_strength(nalgebra_glm::vec2(EDGE_LIMIT, y)) >= 1.0);
+        assert!(current_strength(nalgebra_glm::vec2(-5.0, y)) > 1.0);
+    }
+
+    #[test]
+    fn inland_points_to_the_middle() {
+        let dir = inland(nalgebra_glm::vec2(0.0, MAP_WIDTH as f32 / 2.0));
+        assert!((dir - nalgebra_glm::vec2(1.0, 0.0)).norm() < 1e-5);
+    }
+}
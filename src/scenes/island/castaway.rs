@@ -0,0 +1,196 @@
+// The castaway side objective: a villager stuck by a mob camp, who has to be walked back to where the player started
+
+use serde::{Deserialize, Serialize};
+use specs::{
+    prelude::*,
+    shrev::{EventChannel, ReaderId},
+    Component,
+};
+
+use crate::engine::{
+    audio::AudioResource,
+    perlin::PerlinMapResource,
+    physics::{PositionComponent, VelocityComponent},
+    time::{per_tick, TimeResource},
+};
+
+use super::{
+    compass::compass_direction,
+    interaction::{interact_reader, InteractAction, InteractEvent, InteractableComponent},
+    stats::StatsResource,
+    DialogResource, GoldResource, MobComponent, PlayerComponent, TreasureMapComponent,
+    UNIT_PER_METER,
+};
+
+pub(super) const TALK_DIST: f32 = 3.0 * UNIT_PER_METER; //< How close the player has to be to talk
+const CAMP_RADIUS: f32 = 12.0 * UNIT_PER_METER; //< No mobs may be this close when freeing the castaway
+const FOLLOW_DIST: f32 = 1.5 * UNIT_PER_METER; //< The castaway stops walking this close to the player
+const WAIT_DIST: f32 = 20.0 * UNIT_PER_METER; //< Further than this, the castaway stops and waits
+const HOME_RADIUS: f32 = 4.0 * UNIT_PER_METER; //< How close to home counts as rescued
+const WAIT_SECONDS: f32 = 5.0; //< How long the castaway waits before catching up
+const WALK_SPEED: f32 = per_tick(3.5 * UNIT_PER_METER);
+const REWARD_GOLD: u32 = 50;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub(super) enum CastawayState {
+    Trapped,                //< Waiting by the mob camp to be talked to
+    Following,              //< Walking after the player
+    Waiting { since: f32 }, //< The player got too far away, `since` is in seconds of game time
+    Rescued,                //< Made it home, the reward has been given out
+}
+
+#[derive(Component, Serialize, Deserialize)]
+#[storage(HashMapStorage)]
+pub(super) struct CastawayComponent {
+    pub state: CastawayState,
+    pub home: nalgebra_glm::Vec3, //< Where the player started, the castaway has to be brought back here
+}
+
+pub(super) struct CastawaySystem {
+    reader: ReaderId<InteractEvent>,
+}
+
+impl CastawaySystem {
+    pub fn new(world: &mut World) -> Self {
+        Self {
+            reader: interact_reader(world),
+        }
+    }
+}
+
+impl<'a> System<'a> for CastawaySystem {
+    type SystemData = (
+        WriteStorage<'a, CastawayComponent>,
+        WriteStorage<'a, PositionComponent>,
+        WriteStorage<'a, VelocityComponent>,
+        ReadStorage<'a, PlayerComponent>,
+        ReadStorage<'a, MobComponent>,
+        ReadStorage<'a, TreasureMapComponent>,
+        WriteStorage<'a, InteractableComponent>,
+        Read<'a, EventChannel<InteractEvent>>,
+        Read<'a, TimeResource>,
+        Read<'a, PerlinMapResource>,
+        Read<'a, AudioResource>,
+        Write<'a, DialogResource>,
+        Write<'a, GoldResource>,
+        Write<'a, StatsResource>,
+        Entities<'a>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            mut castaways,
+            mut positions,
+            mut velocities,
+            players,
+            mobs,
+            treasure_maps,
+            mut interactables,
+            events,
+            time,
+            tiles,
+            audio,
+            mut dialog,
+            mut gold,
+            mut stats,
+            entities,
+        ): Self::SystemData,
+    ) {
+        let talked_to: Vec<Entity> = events
+            .read(&mut self.reader)
+            .filter(|event| event.action == InteractAction::Talk)
+            .map(|event| event.entity)
+            .collect();
+
+        let player_pos = (&players, &positions).join().next().unwrap().1.pos;
+        let mob_positions: Vec<nalgebra_glm::Vec3> =
+            (&mobs, &positions).join().map(|(_, p)| p.pos).collect();
+        let unfound_treasures: Vec<nalgebra_glm::Vec3> = (&treasure_maps)
+            .join()
+            .filter(|map| !map.found)
+            .filter_map(|map| positions.get(map.treasure_entity))
+            .map(|p| p.pos)
+            .collect();
+
+        for (castaway, position, velocity, entity) in
+            (&mut castaways, &mut positions, &mut velocities, &entities).join()
+        {
+            let to_player = (player_pos - position.pos).xy();
+            let player_dist = nalgebra_glm::length(&to_player);
+            // Only a trapped castaway has anything to say
+            if castaway.state != CastawayState::Trapped {
+                interactables.remove(entity);
+            }
+
+            match castaway.state {
+                CastawayState::Trapped => {
+                    if !talked_to.contains(&entity) {
+                        continue;
+                    }
+                    let camp_cleared = mob_positions
+                        .iter()
+                        .all(|mob| nalgebra_glm::distance(mob, &position.pos) > CAMP_RADIUS);
+                    if camp_cleared {
+                        dialog.say("Thank you! Please, take me back to your camp.");
+                        castaway.state = CastawayState::Following;
+                    } else {
+                        dialog.say("Help! I can't get past these ghosts!");
+                    }
+                }
+
+                CastawayState::Following => {
+                    if player_dist > WAIT_DIST {
+                        dialog.say("Wait for me!");
+                        castaway.state = CastawayState::Waiting {
+                            since: time.elapsed,
+                        };
+                    } else if player_dist > FOLLOW_DIST {
+                        let walk = to_player.normalize().scale(WALK_SPEED);
+                        velocity.vel.x = walk.x;
+                        velocity.vel.y = walk.y;
+                    }
+                }
+
+                CastawayState::Waiting { since } => {
+                    if player_dist <= WAIT_DIST {
+                        castaway.state = CastawayState::Following;
+                    } else if time.elapsed - since > WAIT_SECONDS {
+                        // Catch up, out of sight just behind the player
+                        let behind = player_pos.xy() - to_player.normalize().scale(FOLLOW_DIST);
+                        let height = tiles.map.get_z_interpolated(behind);
+                        position.pos = nalgebra_glm::vec3(behind.x, behind.y, height);
+                        velocity.vel = nalgebra_glm::zero();
+                        castaway.state = CastawayState::Following;
+                    }
+                }
+
+                CastawayState::Rescued => continue,
+            }
+
+            if castaway.state != CastawayState::Trapped
+                && nalgebra_glm::distance(&position.pos.xy(), &castaway.home.xy()) < HOME_RADIUS
+            {
+                gold.gold += REWARD_GOLD;
+                stats.gold_earned += REWARD_GOLD;
+                let hint = match unfound_treasures.iter().min_by(|a, b| {
+                    let da = nalgebra_glm::distance(a, &castaway.home);
+                    let db = nalgebra_glm::distance(b, &castaway.home);
+                    da.total_cmp(&db)
+                }) {
+                    Some(treasure) => format!(
+                        " I saw a chest buried to the {} of here.",
+                        compass_direction((treasure - castaway.home).xy())
+                    ),
+                    None => String::new(),
+                };
+                dialog.say(&format!(
+                    "Home at last! Here's {} gold.{}",
+                    REWARD_GOLD, hint
+                ));
+                audio.audio_mgr.play_sound("res/win.ogg".to_string(), 128);
+                castaway.state = CastawayState::Rescued;
+            }
+        }
+    }
+}
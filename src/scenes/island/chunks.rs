@@ -0,0 +1,238 @@
+// Terrain chunks are built as the player gets close to them, and thrown away once the player leaves. Chunk vertex data
+// is built on worker threads, and only a few finished chunks are handed to GL each tick, so exploring doesn't hitch.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
+};
+
+use specs::{prelude::*, Entity};
+
+use crate::engine::{
+    perlin::PerlinMapResource,
+    render3d::{Mesh, MeshComponent, MeshMgrResource, OpenGlResource},
+    water::WaterResource,
+};
+
+use super::{
+    create_mesh,
+    prefabs::{spawn_terrain_chunk, PrefabResource},
+    CHUNK_SIZE, MAP_WIDTH,
+};
+
+/// How many built chunks may be uploaded to GL in one tick, so that moving quickly doesn't hitch the game
+const MAX_CHUNK_UPLOADS_PER_TICK: usize = 2;
+/// How many chunks may be waiting on the workers at once. Kept small so that chunks the player has since walked
+/// away from don't hold up closer ones.
+const MAX_CHUNKS_BUILDING: usize = 8;
+const MAX_CHUNK_WORKERS: usize = 4;
+
+#[derive(Default)]
+pub(super) struct ChunkResource {
+    pub load_radius: f32,
+    pub synchronous: bool, //< Build and upload every missing chunk within the tick, when hitches don't matter
+    loaded: HashMap<(usize, usize), Entity>, //< Chunk corner to the chunk's entity
+}
+
+impl ChunkResource {
+    pub fn new(load_radius: f32) -> Self {
+        Self {
+            load_radius,
+            synchronous: false,
+            loaded: HashMap::new(),
+        }
+    }
+
+    pub fn loaded_count(&self) -> usize {
+        self.loaded.len()
+    }
+}
+
+/// The corner of the chunk a spot on the map is in
+pub(super) fn chunk_at(pos: nalgebra_glm::Vec2) -> (usize, usize) {
+    let corner = |v: f32| (v.max(0.0) as usize / CHUNK_SIZE) * CHUNK_SIZE;
+    (corner(pos.x), corner(pos.y))
+}
+
+/// Distance from the camera to the middle of a chunk, ignoring height
+fn chunk_distance(camera_pos: nalgebra_glm::Vec2, chunk: (usize, usize)) -> f32 {
+    let half = CHUNK_SIZE as f32 / 2.0;
+    let center = nalgebra_glm::vec2(chunk.0 as f32 + half, chunk.1 as f32 + half);
+    nalgebra_glm::distance(&camera_pos, &center)
+}
+
+/// A chunk's vertex data, ready to upload
+struct BuiltChunk {
+    chunk: (usize, usize),
+    indices: Vec<u32>,
+    datas: Vec<Vec<f32>>, //< Vertices, normals, uvs and colors
+}
+
+/// Worker threads that build chunk vertex data from the terrain
+struct ChunkBuilder {
+    jobs: Sender<(usize, usize)>,
+    built: Receiver<BuiltChunk>,
+    _workers: Vec<JoinHandle<()>>, //< Finish once `jobs` is dropped
+}
+
+impl ChunkBuilder {
+    fn new(tiles: PerlinMapResource, water_level: f32) -> Self {
+        let (jobs, job_receiver) = channel::<(usize, usize)>();
+        let (built_sender, built) = channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        let worker_count = std::thread::available_parallelism()
+            .map_or(1, |n| n.get().saturating_sub(1))
+            .clamp(1, MAX_CHUNK_WORKERS);
+        let workers = (0..worker_count)
+            .map(|i| {
+                let tiles = tiles.clone();
+                let job_receiver = job_receiver.clone();
+                let built_sender = built_sender.clone();
+                std::thread::Builder::new()
+                    .name(format!("chunk builder {}", i))
+                    .spawn(move || loop {
+                        let job = job_receiver.lock().unwrap().recv();
+                        let Ok(chunk) = job else {
+                            return;
+                        };
+                        let (indices, v, n, u, c) =
+                            create_mesh(&tiles, water_level, chunk.0, chunk.1);
+                        let built = BuiltChunk {
+                            chunk,
+                            indices,
+                            datas: vec![v, n, u, c],
+                        };
+                        if built_sender.send(built).is_err() {
+                            return;
+                        }
+                    })
+                    .unwrap()
+            })
+            .collect();
+        Self {
+            jobs,
+            built,
+            _workers: workers,
+        }
+    }
+}
+
+/// Loads and unloads chunks around the camera
+#[derive(Default)]
+pub(super) struct ChunkStreamingSystem {
+    builder: Option<ChunkBuilder>, //< Started the first tick, once the terrain is in the world
+    building: HashSet<(usize, usize)>, //< Sent to the workers, and not back yet
+    ready: Vec<BuiltChunk>,        //< Built, waiting for their turn to be uploaded
+}
+impl<'a> System<'a> for ChunkStreamingSystem {
+    type SystemData = (
+        ReadStorage<'a, MeshComponent>,
+        Write<'a, ChunkResource>,
+        Write<'a, MeshMgrResource>,
+        Read<'a, PerlinMapResource>,
+        Read<'a, WaterResource>,
+        Read<'a, OpenGlResource>,
+        Read<'a, PrefabResource>,
+        Read<'a, LazyUpdate>,
+        Entities<'a>,
+    );
+
+    fn run(
+        &mut self,
+        (meshes, mut chunks, mut mesh_mgr, tiles, water, opengl, prefabs, lazy, entities): Self::SystemData,
+    ) {
+        let builder = self
+            .builder
+            .get_or_insert_with(|| ChunkBuilder::new(tiles.clone(), water.level));
+        let camera_pos = opengl.camera.position.xy();
+        let load_radius = chunks.load_radius;
+
+        // Unload chunks that are too far away. Chunks are kept a little past the load radius, so that walking back and
+        // forth over the edge doesn't keep rebuilding the same chunk.
+        let unload_radius = load_radius + CHUNK_SIZE as f32;
+        let far_chunks: Vec<(usize, usize)> = chunks
+            .loaded
+            .keys()
+            .filter(|chunk| chunk_distance(camera_pos, **chunk) > unload_radius)
+            .copied()
+            .collect();
+        for chunk in far_chunks {
+            let entity = chunks.loaded.remove(&chunk).unwrap();
+            if let Some(mesh) = meshes.get(entity) {
+                mesh_mgr.data.remove_mesh(mesh.mesh_id);
+            }
+            entities.delete(entity).unwrap();
+        }
+
+        // Have the closest missing chunks built
+        let queued = self.building.len() + self.ready.len();
+        let mut missing_chunks: Vec<(usize, usize)> = (0..MAP_WIDTH)
+            .step_by(CHUNK_SIZE)
+            .flat_map(|y| (0..MAP_WIDTH).step_by(CHUNK_SIZE).map(move |x| (x, y)))
+            .filter(|chunk| !chunks.loaded.contains_key(chunk))
+            .filter(|chunk| !self.building.contains(chunk))
+            .filter(|chunk| !self.ready.iter().any(|built| built.chunk == *chunk))
+            .filter(|chunk| chunk_distance(camera_pos, *chunk) <= load_radius)
+            .collect();
+        missing_chunks.sort_by(|a, b| {
+            chunk_distance(camera_pos, *a).total_cmp(&chunk_distance(camera_pos, *b))
+        });
+        let max_building = if chunks.synchronous {
+            usize::MAX
+        } else {
+            MAX_CHUNKS_BUILDING
+        };
+        for chunk in missing_chunks
+            .into_iter()
+            .take(max_building.saturating_sub(queued))
+        {
+            builder.jobs.send(chunk).unwrap();
+            self.building.insert(chunk);
+        }
+
+        // Pick up whatever the workers have finished. Chunks the player has left behind in the meantime are dropped.
+        if chunks.synchronous {
+            while !self.building.is_empty() {
+                let built = builder.built.recv().unwrap();
+                self.building.remove(&built.chunk);
+                self.ready.push(built);
+            }
+        }
+        for built in builder.built.try_iter() {
+            self.building.remove(&built.chunk);
+            self.ready.push(built);
+        }
+        self.ready
+            .retain(|built| chunk_distance(camera_pos, built.chunk) <= unload_radius);
+
+        // Upload the closest built chunks
+        self.ready.sort_by(|a, b| {
+            chunk_distance(camera_pos, b.chunk).total_cmp(&chunk_distance(camera_pos, a.chunk))
+        });
+        let max_uploads = if chunks.synchronous {
+            usize::MAX
+        } else {
+            MAX_CHUNK_UPLOADS_PER_TICK
+        };
+        for _ in 0..max_uploads {
+            let Some(built) = self.ready.pop() else {
+                break;
+            };
+            let mesh_id = mesh_mgr
+                .data
+                .add_mesh(Mesh::new(built.indices, built.datas));
+            let entity = spawn_terrain_chunk(
+                &entities,
+                &lazy,
+                &prefabs,
+                mesh_id,
+                nalgebra_glm::vec3(built.chunk.0 as f32, built.chunk.1 as f32, 0.0),
+            );
+            chunks.loaded.insert(built.chunk, entity);
+        }
+    }
+}
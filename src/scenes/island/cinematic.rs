@@ -0,0 +1,221 @@
+// Cutscenes. While a camera path is playing it takes over the camera, black bars slide in at the top and bottom of the
+// screen, and the player's controls are taken away. The island opens with a flyover past the treasure, and winning
+// circles up and away from the player.
+
+use std::f32::consts::PI;
+
+use specs::{prelude::*, Component};
+
+use crate::{
+    engine::{
+        cinematic::{CameraKeyframe, CameraPath, Easing},
+        physics::PositionComponent,
+        render3d::OpenGlResource,
+        text::QuadComponent,
+        time::TimeResource,
+    },
+    App,
+};
+
+use super::{MAP_WIDTH, UNIT_PER_METER};
+
+const LETTERBOX_HEIGHT: f32 = 0.12; //< Height of each bar, as a fraction of the screen
+const LETTERBOX_SLIDE_SECONDS: f32 = 0.5;
+
+// The intro flyover
+const OVERVIEW_HEIGHT: f32 = 2000.0 * UNIT_PER_METER;
+const OVERVIEW_BACK: f32 = 3000.0 * UNIT_PER_METER; //< South of the middle of the island
+const TREASURE_VIEW_DIST: f32 = 400.0 * UNIT_PER_METER; //< Far enough to show the area, not the spot
+const TREASURE_VIEW_HEIGHT: f32 = 250.0 * UNIT_PER_METER;
+const SECONDS_PER_TREASURE: f32 = 3.0;
+const LANDING_SECONDS: f32 = 3.0;
+
+// The victory sequence
+const VICTORY_SECONDS: f32 = 5.0;
+const VICTORY_DIST: f32 = 60.0 * UNIT_PER_METER;
+const VICTORY_HEIGHT: f32 = 40.0 * UNIT_PER_METER;
+
+#[derive(Default)]
+pub(super) struct CinematicResource {
+    path: Option<CameraPath>,
+    time: f32,      //< Seconds into the path
+    letterbox: f32, //< How far the bars have slid in, in [0, 1]
+}
+
+impl CinematicResource {
+    pub fn play(&mut self, path: CameraPath) {
+        self.path = Some(path);
+        self.time = 0.0;
+    }
+
+    /// Ends the path early, handing the camera straight back
+    pub fn skip(&mut self) {
+        self.path = None;
+    }
+
+    pub fn playing(&self) -> bool {
+        self.path.is_some()
+    }
+}
+
+/// A black bar across the top or bottom of the screen
+#[derive(Component)]
+#[storage(HashMapStorage)]
+pub(super) struct LetterboxComponent {
+    pub top: bool,
+}
+
+/// Moves the camera along the playing path. Runs after the player system, so that it has the last say on the camera.
+pub(super) struct CinematicSystem;
+impl<'a> System<'a> for CinematicSystem {
+    type SystemData = (
+        Write<'a, CinematicResource>,
+        Write<'a, OpenGlResource>,
+        Read<'a, TimeResource>,
+    );
+
+    fn run(&mut self, (mut cinematic, mut opengl, time): Self::SystemData) {
+        let letterbox_target = if cinematic.playing() { 1.0 } else { 0.0 };
+        let slide = time.real_dt / LETTERBOX_SLIDE_SECONDS;
+        cinematic.letterbox += (letterbox_target - cinematic.letterbox).clamp(-slide, slide);
+
+        let Some(path) = &cinematic.path else {
+            return;
+        };
+        let (position, lookat) = path.sample(cinematic.time);
+        let finished = cinematic.time >= path.duration();
+        opengl.camera.position = position;
+        opengl.camera.lookat = lookat;
+        cinematic.time += time.dt;
+        if finished {
+            cinematic.path = None;
+        }
+    }
+}
+
+/// Slides the letterbox bars in and out
+pub(super) struct LetterboxSystem;
+impl<'a> System<'a> for LetterboxSystem {
+    type SystemData = (
+        ReadStorage<'a, LetterboxComponent>,
+        WriteStorage<'a, QuadComponent>,
+        WriteStorage<'a, PositionComponent>,
+        Read<'a, CinematicResource>,
+        Read<'a, App>,
+    );
+
+    fn run(&mut self, (letterboxes, mut quads, mut positions, cinematic, app): Self::SystemData) {
+        let amount = Easing::EaseInOut.apply(cinematic.letterbox);
+        let height = LETTERBOX_HEIGHT * amount * app.screen_height as f32;
+        for (letterbox, quad, position) in (&letterboxes, &mut quads, &mut positions).join() {
+            quad.width = app.screen_width;
+            quad.height = height as i32;
+            quad.opacity = if height >= 1.0 { 1.0 } else { 0.0 };
+            // Quads are placed by their middle, with the screen spanning [-1, 1]
+            let y = 1.0 - height / app.screen_height as f32;
+            position.pos = nalgebra_glm::vec3(0.0, if letterbox.top { y } else { -y }, 0.0);
+        }
+    }
+}
+
+/// Starts high above the island, sweeps around past the area each treasure is in, and comes down to the player's eyes
+pub(super) fn intro_path(
+    treasures: &[nalgebra_glm::Vec3],
+    eye: nalgebra_glm::Vec3,
+    lookat: nalgebra_glm::Vec3,
+) -> CameraPath {
+    let middle = nalgebra_glm::vec3(MAP_WIDTH as f32 / 2.0, MAP_WIDTH as f32 / 2.0, 0.0);
+    let mut keyframes = vec![CameraKeyframe {
+        time: 0.0,
+        position: middle + nalgebra_glm::vec3(0.0, -OVERVIEW_BACK, OVERVIEW_HEIGHT),
+        lookat: middle,
+        easing: Easing::Linear,
+    }];
+
+    // Visiting the treasure in order around the island keeps the camera from doubling back
+    let angle_around = |pos: &nalgebra_glm::Vec3| {
+        let from_middle = pos - middle;
+        (from_middle.y.atan2(from_middle.x) + PI / 2.0).rem_euclid(2.0 * PI)
+    };
+    let mut treasures = treasures.to_vec();
+    treasures.sort_by(|a, b| angle_around(a).total_cmp(&angle_around(b)));
+    let mut time = 0.0;
+    for treasure in treasures {
+        let outward = treasure.xy() - middle.xy();
+        let outward = if nalgebra_glm::length(&outward) > 0.0 {
+            nalgebra_glm::normalize(&outward)
+        } else {
+            nalgebra_glm::vec2(0.0, -1.0)
+        };
+        time += SECONDS_PER_TREASURE;
+        keyframes.push(CameraKeyframe {
+            time,
+            position: treasure
+                + nalgebra_glm::vec3(
+                    outward.x * TREASURE_VIEW_DIST,
+                    outward.y * TREASURE_VIEW_DIST,
+                    TREASURE_VIEW_HEIGHT,
+                ),
+            lookat: treasure,
+            easing: Easing::EaseInOut,
+        });
+    }
+
+    // Comes in from above and behind the player, so the last stretch ends up looking the way they are
+    let facing = lookat - eye;
+    time += LANDING_SECONDS;
+    keyframes.push(CameraKeyframe {
+        time,
+        position: eye - facing * TREASURE_VIEW_DIST
+            + nalgebra_glm::vec3(0.0, 0.0, TREASURE_VIEW_HEIGHT * 0.5),
+        lookat: eye + facing,
+        easing: Easing::EaseInOut,
+    });
+    time += LANDING_SECONDS;
+    keyframes.push(CameraKeyframe {
+        time,
+        position: eye,
+        lookat,
+        easing: Easing::EaseOut,
+    });
+    CameraPath::new(keyframes)
+}
+
+/// Pulls back from the player in a rising half circle, keeping them in view
+pub(super) fn victory_path(
+    eye: nalgebra_glm::Vec3,
+    lookat: nalgebra_glm::Vec3,
+    player_pos: nalgebra_glm::Vec3,
+) -> CameraPath {
+    const STEPS: usize = 4;
+    let facing = (lookat - eye).xy();
+    let start_angle = facing.y.atan2(facing.x) + PI;
+    let mut keyframes = vec![CameraKeyframe {
+        time: 0.0,
+        position: eye,
+        lookat,
+        easing: Easing::Linear,
+    }];
+    for i in 1..=STEPS {
+        let t = i as f32 / STEPS as f32;
+        let angle = start_angle + t * PI;
+        keyframes.push(CameraKeyframe {
+            time: t * VICTORY_SECONDS,
+            position: player_pos
+                + nalgebra_glm::vec3(
+                    angle.cos() * VICTORY_DIST * t,
+                    angle.sin() * VICTORY_DIST * t,
+                    VICTORY_HEIGHT * t,
+                ),
+            lookat: player_pos,
+            easing: if i == 1 {
+                Easing::EaseIn
+            } else if i == STEPS {
+                Easing::EaseOut
+            } else {
+                Easing::Linear
+            },
+        });
+    }
+    CameraPath::new(keyframes)
+}
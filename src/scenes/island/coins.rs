@@ -0,0 +1,117 @@
+// Gold coins thrown out of a chest when it's opened. They spill up and out, then fly into the player's pockets, adding
+// to their gold as they land.
+
+use specs::{prelude::*, Component};
+
+use crate::engine::{audio::AudioResource, physics::PositionComponent, time::TimeResource};
+
+use super::{stats::StatsResource, GoldResource, PlayerComponent, PERSON_HEIGHT, UNIT_PER_METER};
+
+pub(super) const CHEST_GOLD: u32 = 25; //< Gold in every chest, split between its coins
+pub(super) const CHEST_COINS: u32 = 5;
+const SPILL_SPEED: f32 = 1.2 * UNIT_PER_METER; //< Per second, out and up from the chest
+const SPILL_GRAVITY: f32 = 4.0 * UNIT_PER_METER; //< Per second squared, while spilling
+const SPILL_SECONDS: f32 = 0.5; //< How long coins spill before flying to the player
+pub(super) const FLY_SPEED: f32 = 2.0 * UNIT_PER_METER; //< Per second, when things start flying to the player
+pub(super) const FLY_ACCELERATION: f32 = 20.0 * UNIT_PER_METER; //< Per second squared
+pub(super) const PICKUP_DIST: f32 = 0.3 * UNIT_PER_METER;
+
+#[derive(Component)]
+#[storage(VecStorage)]
+pub(super) struct CoinComponent {
+    pub value: u32,
+    pub vel: nalgebra_glm::Vec3, //< Per second
+    pub age: f32,                //< Seconds since the coin came out of the chest
+}
+
+impl CoinComponent {
+    /// The `i`th coin out of a chest, each thrown a different way around
+    pub fn spilled(i: u32) -> Self {
+        let angle = i as f32 / CHEST_COINS as f32 * std::f32::consts::TAU;
+        Self {
+            value: CHEST_GOLD / CHEST_COINS,
+            vel: nalgebra_glm::vec3(angle.cos() * 0.5, angle.sin() * 0.5, 1.0) * SPILL_SPEED,
+            age: 0.0,
+        }
+    }
+}
+
+/// The velocity that flies something at `pos` straight at `target` at `speed`, slowed so that it doesn't overshoot in a
+/// tick of `dt` seconds
+pub(super) fn fly_toward(
+    pos: nalgebra_glm::Vec3,
+    target: nalgebra_glm::Vec3,
+    speed: f32,
+    dt: f32,
+) -> nalgebra_glm::Vec3 {
+    let to_target = target - pos;
+    let distance = nalgebra_glm::length(&to_target);
+    if distance == 0.0 {
+        return nalgebra_glm::zero();
+    }
+    to_target / distance * speed.min(distance / dt)
+}
+
+/// Spills coins out of chests, flies them to the player, and pays them in
+pub(super) struct CoinSystem;
+impl<'a> System<'a> for CoinSystem {
+    type SystemData = (
+        WriteStorage<'a, CoinComponent>,
+        WriteStorage<'a, PositionComponent>,
+        ReadStorage<'a, PlayerComponent>,
+        Write<'a, GoldResource>,
+        Write<'a, StatsResource>,
+        Read<'a, AudioResource>,
+        Read<'a, TimeResource>,
+        Entities<'a>,
+    );
+
+    fn run(
+        &mut self,
+        (mut coins, mut positions, players, mut gold, mut stats, audio, time, entities): Self::SystemData,
+    ) {
+        let Some((_, player_position)) = (&players, &positions).join().next() else {
+            return;
+        };
+        // Into the player's hands, rather than their feet
+        let pocket = player_position.pos + nalgebra_glm::vec3(0.0, 0.0, PERSON_HEIGHT * 0.5);
+
+        for (coin, position, entity) in (&mut coins, &mut positions, &entities).join() {
+            coin.age += time.dt;
+            if coin.age < SPILL_SECONDS {
+                coin.vel.z -= SPILL_GRAVITY * time.dt;
+            } else {
+                if nalgebra_glm::distance(&pocket, &position.pos) < PICKUP_DIST {
+                    gold.gold += coin.value;
+                    stats.gold_earned += coin.value;
+                    audio.audio_mgr.play_sound("res/pop.ogg".to_string(), 64);
+                    entities.delete(entity).unwrap();
+                    continue;
+                }
+                let speed = FLY_SPEED + FLY_ACCELERATION * (coin.age - SPILL_SECONDS);
+                coin.vel = fly_toward(position.pos, pocket, speed, time.dt);
+            }
+            position.pos += coin.vel * time.dt;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flying_things_stop_at_their_target() {
+        let target = nalgebra_glm::vec3(1.0, 0.0, 0.0);
+        // Far off, they go at full speed
+        let vel = fly_toward(nalgebra_glm::Vec3::zeros(), target, 0.5, 0.1);
+        assert_eq!(vel, nalgebra_glm::vec3(0.5, 0.0, 0.0));
+        // Close by, they only go as far as the target in one tick
+        let vel = fly_toward(nalgebra_glm::vec3(0.9, 0.0, 0.0), target, 5.0, 0.1);
+        assert!(nalgebra_glm::distance(&vel, &nalgebra_glm::vec3(1.0, 0.0, 0.0)) < 1e-5);
+        assert_eq!(
+            fly_toward(target, target, 5.0, 0.1),
+            nalgebra_glm::Vec3::zeros()
+        );
+    }
+}
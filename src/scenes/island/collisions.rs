@@ -0,0 +1,190 @@
+// Working out what's touching what. Each tick every collider is put into a spatial grid, and every overlapping pair
+// with at least one side moving is written to the collision event channel. Systems that care about things touching,
+// like pushing bodies apart, bullets hitting, or mobs hurting the player, read the channel instead of testing shapes
+// themselves.
+
+use specs::{
+    prelude::*,
+    shrev::{EventChannel, ReaderId},
+};
+
+use crate::engine::{
+    collision::{penetration, SpatialGrid},
+    physics::{PositionComponent, VelocityComponent},
+    raycast::{raycast_aabb, RayHit},
+};
+
+use super::{ColliderComponent, ProjectileComponent, UNIT_PER_METER};
+
+const GRID_CELL_SIZE: f32 = 20.0 * UNIT_PER_METER; //< A little bigger than the biggest colliders, the bush walls
+const SOLVER_ITERATIONS: usize = 4; //< How many times overlapping bodies are pushed apart each tick
+
+/// Two colliders overlapping
+#[derive(Clone, Copy, Debug)]
+pub(super) struct CollisionEvent {
+    pub a: Entity, //< Always moving
+    pub b: Entity,
+}
+
+impl CollisionEvent {
+    /// What an entity collided with, if it's in this collision
+    pub fn other(&self, entity: Entity) -> Option<Entity> {
+        if self.a == entity {
+            Some(self.b)
+        } else if self.b == entity {
+            Some(self.a)
+        } else {
+            None
+        }
+    }
+}
+
+/// Starts reading collision events. Readers only see events written after they start, and have to read every tick.
+pub(super) fn collision_reader(world: &mut World) -> ReaderId<CollisionEvent> {
+    world
+        .write_resource::<EventChannel<CollisionEvent>>()
+        .register_reader()
+}
+
+/// The first collider a ray hits, out of those `filter` lets through. Colliders are treated as the boxes around them.
+pub(super) fn raycast_colliders(
+    origin: nalgebra_glm::Vec3,
+    dir: nalgebra_glm::Vec3,
+    colliders: &ReadStorage<ColliderComponent>,
+    positions: &ReadStorage<PositionComponent>,
+    entities: &Entities,
+    filter: impl Fn(Entity) -> bool,
+) -> Option<(Entity, RayHit)> {
+    (colliders, positions, entities)
+        .join()
+        .filter(|(_, _, entity)| filter(*entity))
+        .filter_map(|(collider, position, entity)| {
+            let hit = raycast_aabb(origin, dir, &collider.shape.bounds(position.pos))?;
+            Some((entity, hit))
+        })
+        .min_by(|(_, a), (_, b)| a.t.total_cmp(&b.t))
+}
+
+/// Finds every overlapping pair of colliders. Things that don't move can't run into each other, so pairs of still
+/// things are skipped.
+pub(super) struct CollisionDetectionSystem;
+impl<'a> System<'a> for CollisionDetectionSystem {
+    type SystemData = (
+        ReadStorage<'a, ColliderComponent>,
+        ReadStorage<'a, PositionComponent>,
+        ReadStorage<'a, VelocityComponent>,
+        Write<'a, EventChannel<CollisionEvent>>,
+        Entities<'a>,
+    );
+
+    fn run(&mut self, (colliders, positions, velocities, mut channel, entities): Self::SystemData) {
+        let mut events = vec![];
+        let bodies: Vec<_> = (&colliders, &positions, &entities)
+            .join()
+            .map(|(collider, position, entity)| {
+                let moves = velocities.contains(entity);
+                (&collider.shape, position.pos, moves, entity)
+            })
+            .collect();
+
+        let mut grid = SpatialGrid::new(GRID_CELL_SIZE);
+        for (i, (shape, pos, _, _)) in bodies.iter().enumerate() {
+            grid.insert(i, &shape.bounds(*pos));
+        }
+
+        for (i, (shape, pos, moves, entity)) in bodies.iter().enumerate() {
+            if !moves {
+                continue;
+            }
+            for j in grid.query(&shape.bounds(*pos)) {
+                let (other_shape, other_pos, other_moves, other_entity) = bodies[j];
+                // Pairs of moving things are found from both sides, only keep one
+                if j == i || (other_moves && j < i) {
+                    continue;
+                }
+                if penetration(shape, *pos, other_shape, other_pos).is_some() {
+                    events.push(CollisionEvent {
+                        a: *entity,
+                        b: other_entity,
+                    });
+                }
+            }
+        }
+        channel.iter_write(events);
+    }
+}
+
+/// Pushes bodies out of each other. When two moving bodies bump, they're each pushed half the way. Pushing one pair
+/// apart can push a body into something else, like the player being shoved into a tree by a mob, so the pairs are
+/// pushed apart a few times over. Projectiles pass through, and are left to the hit reactions.
+pub(super) struct CollisionResponseSystem {
+    reader: ReaderId<CollisionEvent>,
+}
+
+impl CollisionResponseSystem {
+    pub fn new(world: &mut World) -> Self {
+        Self {
+            reader: collision_reader(world),
+        }
+    }
+}
+
+impl<'a> System<'a> for CollisionResponseSystem {
+    type SystemData = (
+        ReadStorage<'a, ColliderComponent>,
+        ReadStorage<'a, ProjectileComponent>,
+        WriteStorage<'a, PositionComponent>,
+        WriteStorage<'a, VelocityComponent>,
+        Read<'a, EventChannel<CollisionEvent>>,
+    );
+
+    fn run(
+        &mut self,
+        (colliders, projectiles, mut positions, mut velocities, channel): Self::SystemData,
+    ) {
+        let pairs: Vec<_> = channel
+            .read(&mut self.reader)
+            .filter(|event| !projectiles.contains(event.a) && !projectiles.contains(event.b))
+            .collect();
+
+        for _ in 0..SOLVER_ITERATIONS {
+            let mut separated = true;
+            for event in &pairs {
+                // Earlier pushes may have moved either body, so see how far they overlap now
+                let (a_pos, b_pos) = (positions.get(event.a), positions.get(event.b));
+                let (a_collider, b_collider) = (colliders.get(event.a), colliders.get(event.b));
+                let (Some(a_pos), Some(b_pos), Some(a_collider), Some(b_collider)) =
+                    (a_pos, b_pos, a_collider, b_collider)
+                else {
+                    continue;
+                };
+                let Some(push) =
+                    penetration(&a_collider.shape, a_pos.pos, &b_collider.shape, b_pos.pos)
+                else {
+                    continue;
+                };
+                separated = false;
+
+                let share = if velocities.contains(event.b) {
+                    0.5
+                } else {
+                    1.0
+                };
+                for (entity, push) in [(event.a, push), (event.b, -push)] {
+                    let Some(velocity) = velocities.get_mut(entity) else {
+                        continue;
+                    };
+                    positions.get_mut(entity).unwrap().pos += push * share;
+
+                    // Stop moving into what was bumped, but keep sliding along it
+                    let normal = push.normalize();
+                    let into = velocity.vel.dot(&normal).min(0.0);
+                    velocity.vel -= normal * into;
+                }
+            }
+            if separated {
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,136 @@
+// The compass strip across the top of the screen. It shows the half of the compass the player is facing, with the
+// cardinal directions sliding past as they turn, and a mark for each chest that has been found.
+
+use std::f32::consts::PI;
+
+use specs::{prelude::*, Component};
+
+use crate::engine::{
+    physics::PositionComponent,
+    text::{LayoutComponent, QuadComponent},
+};
+
+use super::{PlayerComponent, TreasureMapComponent};
+
+pub(super) const STRIP_WIDTH: i32 = 360; //< Pixels
+pub(super) const STRIP_HEIGHT: i32 = 28;
+pub(super) const STRIP_TOP: i32 = 6; //< Pixels down from the top of the screen
+const STRIP_SPAN: f32 = PI; //< How many radians of heading fit across the strip
+pub(super) const CARDINALS: [(&str, f32); 4] =
+    [("E", 0.0), ("N", PI / 2.0), ("W", PI), ("S", -PI / 2.0)];
+const DIRECTIONS: [&str; 8] = [
+    "east",
+    "north-east",
+    "north",
+    "north-west",
+    "west",
+    "south-west",
+    "south",
+    "south-east",
+];
+
+/// What a mark on the strip points to
+#[derive(Clone, Copy)]
+pub(super) enum CompassTarget {
+    Bearing(f32),     //< Radians counter-clockwise from east, like the player's facing
+    Treasure(Entity), //< A treasure map, whose chest is marked once it's been found
+}
+
+/// A mark on the compass strip
+#[derive(Component)]
+#[storage(HashMapStorage)]
+pub(super) struct CompassMarkComponent {
+    pub target: CompassTarget,
+}
+
+/// How many pixels right of the middle of the strip a bearing goes, for a player facing `facing`. None if it's off
+/// the strip.
+fn strip_offset(bearing: f32, facing: f32) -> Option<i32> {
+    // Turning clockwise, to the right, makes the angle go down
+    let angle = (bearing - facing + PI).rem_euclid(2.0 * PI) - PI;
+    if angle.abs() > STRIP_SPAN / 2.0 {
+        return None;
+    }
+    Some((-angle / STRIP_SPAN * STRIP_WIDTH as f32).round() as i32)
+}
+
+/// Which way `offset` points, as one of eight compass directions, for dialog and the journal. North is +y.
+pub(super) fn compass_direction(offset: nalgebra_glm::Vec2) -> &'static str {
+    let eighths = (offset.y.atan2(offset.x) / (PI / 4.0)).round() as i32;
+    DIRECTIONS[eighths.rem_euclid(8) as usize]
+}
+
+/// Slides the marks along the strip as the player turns
+pub(super) struct CompassSystem;
+impl<'a> System<'a> for CompassSystem {
+    type SystemData = (
+        ReadStorage<'a, CompassMarkComponent>,
+        ReadStorage<'a, PlayerComponent>,
+        ReadStorage<'a, PositionComponent>,
+        ReadStorage<'a, TreasureMapComponent>,
+        WriteStorage<'a, LayoutComponent>,
+        WriteStorage<'a, QuadComponent>,
+    );
+
+    fn run(
+        &mut self,
+        (marks, players, positions, treasure_maps, mut layouts, mut quads): Self::SystemData,
+    ) {
+        let Some((player, player_position)) = (&players, &positions).join().next() else {
+            return;
+        };
+        for (mark, layout, quad) in (&marks, &mut layouts, &mut quads).join() {
+            let bearing = match mark.target {
+                CompassTarget::Bearing(bearing) => Some(bearing),
+                CompassTarget::Treasure(treasure_map) => {
+                    let treasure_map = treasure_maps.get(treasure_map).unwrap();
+                    let chest = positions.get(treasure_map.treasure_entity).unwrap();
+                    let to_chest = chest.pos - player_position.pos;
+                    treasure_map.found.then(|| to_chest.y.atan2(to_chest.x))
+                }
+            };
+            match bearing.and_then(|bearing| strip_offset(bearing, player.facing)) {
+                Some(offset) => {
+                    quad.opacity = 1.0;
+                    layout.offset.0 = offset;
+                }
+                None => quad.opacity = 0.0,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_heading_is_in_the_middle() {
+        assert_eq!(strip_offset(PI / 2.0, PI / 2.0), Some(0));
+    }
+
+    #[test]
+    fn bearings_to_the_right_are_right_of_the_middle() {
+        // Facing north, east is a quarter turn to the right, at the right end of the strip
+        assert_eq!(strip_offset(0.0, PI / 2.0), Some(STRIP_WIDTH / 2));
+        assert_eq!(strip_offset(PI, PI / 2.0), Some(-STRIP_WIDTH / 2));
+    }
+
+    #[test]
+    fn bearings_behind_are_off_the_strip() {
+        assert_eq!(strip_offset(-PI / 2.0, PI / 2.0), None);
+        // Wrapping around from just west of south to just east of it
+        assert!(strip_offset(-PI / 2.0 + 0.1, -PI / 2.0 - 0.1 + 2.0 * PI).is_some());
+    }
+
+    #[test]
+    fn compass_directions_go_by_the_nearest_eighth() {
+        assert_eq!(compass_direction(nalgebra_glm::vec2(1.0, 0.1)), "east");
+        assert_eq!(
+            compass_direction(nalgebra_glm::vec2(1.0, 1.0)),
+            "north-east"
+        );
+        assert_eq!(compass_direction(nalgebra_glm::vec2(-0.1, -1.0)), "south");
+        assert_eq!(compass_direction(nalgebra_glm::vec2(1.0, -0.1)), "east");
+    }
+}
@@ -0,0 +1,226 @@
+// Mobs hurting the player. Touching a mob takes a chunk of health, bigger for nastier kinds of mobs, then the player
+// gets a moment to get away before they can be hurt again. The screen flashes red whenever it happens, and an arrow at
+// the edge of the screen points to the mob if it's out of sight.
+
+use std::f32::consts::PI;
+
+use specs::{
+    prelude::*,
+    shrev::{EventChannel, ReaderId},
+    Component,
+};
+
+use crate::{
+    engine::{
+        audio::AudioResource, physics::PositionComponent, text::QuadComponent, time::TimeResource,
+    },
+    App,
+};
+
+use super::{
+    collisions::{collision_reader, CollisionEvent},
+    DeathSplishAnimComponent, HealthComponent, MobComponent, PlayerComponent,
+};
+
+const INVULNERABLE_SECONDS: f32 = 1.0; //< How long after being hurt the player can't be hurt again
+const FLASH_SECONDS: f32 = 0.3;
+const FLASH_OPACITY: f32 = 0.5; //< How red the screen gets right as the player is hurt
+const INDICATOR_SECONDS: f32 = 1.0;
+const INDICATOR_MARGIN: f32 = 48.0; //< Gap between the arrow and the edge of the screen, in pixels
+const IN_SIGHT_ANGLE: f32 = PI / 4.0; //< Mobs within this many radians of straight ahead don't get an arrow
+
+/// A red quad covering the whole screen, faded in when the player is hurt
+#[derive(Component)]
+#[storage(HashMapStorage)]
+pub(super) struct DamageFlashComponent {
+    pub seconds_left: f32,
+}
+
+/// An arrow at the edge of the screen, pointing to where the player was last hurt from
+#[derive(Component)]
+#[storage(HashMapStorage)]
+pub(super) struct DamageIndicatorComponent {
+    pub from: nalgebra_glm::Vec3,
+    pub seconds_left: f32,
+}
+
+/// Hurts the player when they collide with a mob
+pub(super) struct ContactDamageSystem {
+    reader: ReaderId<CollisionEvent>,
+}
+
+impl ContactDamageSystem {
+    pub fn new(world: &mut World) -> Self {
+        Self {
+            reader: collision_reader(world),
+        }
+    }
+}
+
+impl<'a> System<'a> for ContactDamageSystem {
+    type SystemData = (
+        WriteStorage<'a, PlayerComponent>,
+        WriteStorage<'a, HealthComponent>,
+        ReadStorage<'a, MobComponent>,
+        ReadStorage<'a, DeathSplishAnimComponent>,
+        ReadStorage<'a, PositionComponent>,
+        WriteStorage<'a, DamageFlashComponent>,
+        WriteStorage<'a, DamageIndicatorComponent>,
+        Read<'a, EventChannel<CollisionEvent>>,
+        Read<'a, TimeResource>,
+        Read<'a, AudioResource>,
+        Entities<'a>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            mut players,
+            mut healths,
+            mobs,
+            dying,
+            positions,
+            mut flashes,
+            mut indicators,
+            channel,
+            time,
+            audio,
+            entities,
+        ): Self::SystemData,
+    ) {
+        let events: Vec<_> = channel.read(&mut self.reader).collect();
+        for (player, health, player_entity) in (&mut players, &mut healths, &entities).join() {
+            player.invulnerable_for = (player.invulnerable_for - time.dt).max(0.0);
+            if player.invulnerable_for > 0.0 {
+                continue;
+            }
+            // Only the worst of the mobs touching the player counts
+            let worst = events
+                .iter()
+                .filter_map(|event| event.other(player_entity))
+                .filter(|other| !dying.contains(*other))
+                .filter_map(|other| Some((other, mobs.get(other)?.kind.stats().contact_damage)))
+                .max_by(|a, b| a.1.total_cmp(&b.1));
+            let Some((mob_entity, damage)) = worst else {
+                continue;
+            };
+            health.health -= damage;
+            player.invulnerable_for = INVULNERABLE_SECONDS;
+            audio.audio_mgr.play_sound("res/hit.ogg".to_string(), 128);
+            for flash in (&mut flashes).join() {
+                flash.seconds_left = FLASH_SECONDS;
+            }
+            if let Some(mob_position) = positions.get(mob_entity) {
+                for indicator in (&mut indicators).join() {
+                    indicator.from = mob_position.pos;
+                    indicator.seconds_left = INDICATOR_SECONDS;
+                }
+            }
+        }
+    }
+}
+
+/// Stretches the flash over the screen and fades it out
+pub(super) struct DamageFlashSystem;
+impl<'a> System<'a> for DamageFlashSystem {
+    type SystemData = (
+        WriteStorage<'a, DamageFlashComponent>,
+        WriteStorage<'a, QuadComponent>,
+        WriteStorage<'a, PositionComponent>,
+        Read<'a, TimeResource>,
+        Read<'a, App>,
+    );
+
+    fn run(&mut self, (mut flashes, mut quads, mut positions, time, app): Self::SystemData) {
+        for (flash, quad, position) in (&mut flashes, &mut quads, &mut positions).join() {
+            flash.seconds_left = (flash.seconds_left - time.real_dt).max(0.0);
+            quad.opacity = FLASH_OPACITY * flash.seconds_left / FLASH_SECONDS;
+            quad.width = app.screen_width;
+            quad.height = app.screen_height;
+            position.pos = nalgebra_glm::zero();
+        }
+    }
+}
+
+/// Where the damage arrow goes for a mob `angle` radians counter-clockwise from where the player is facing, and how
+/// far to turn it from pointing up. None if the mob is in sight.
+fn indicator_placement(
+    angle: f32,
+    screen: nalgebra_glm::Vec2,
+) -> Option<(nalgebra_glm::Vec2, f32)> {
+    let angle = (angle + PI).rem_euclid(2.0 * PI) - PI;
+    if angle.abs() < IN_SIGHT_ANGLE {
+        return None;
+    }
+    // Straight ahead is up the screen, and counter-clockwise is to the left
+    let dir = nalgebra_glm::vec2(-angle.sin(), angle.cos());
+    let half = screen / 2.0 - nalgebra_glm::vec2(INDICATOR_MARGIN, INDICATOR_MARGIN);
+    let scale = (half.x / dir.x.abs()).min(half.y / dir.y.abs());
+    let on_edge = (dir * scale).component_div(&screen) * 2.0;
+    Some((on_edge, angle))
+}
+
+/// Points the damage arrow at where the player was hurt from as they turn, and fades it out
+pub(super) struct DamageIndicatorSystem;
+impl<'a> System<'a> for DamageIndicatorSystem {
+    type SystemData = (
+        WriteStorage<'a, DamageIndicatorComponent>,
+        WriteStorage<'a, QuadComponent>,
+        WriteStorage<'a, PositionComponent>,
+        ReadStorage<'a, PlayerComponent>,
+        Read<'a, TimeResource>,
+        Read<'a, App>,
+    );
+
+    fn run(
+        &mut self,
+        (mut indicators, mut quads, mut positions, players, time, app): Self::SystemData,
+    ) {
+        let Some((player, player_position)) = (&players, &positions).join().next() else {
+            return;
+        };
+        let (facing, player_pos) = (player.facing, player_position.pos);
+        let screen = nalgebra_glm::vec2(app.screen_width as f32, app.screen_height as f32);
+        for (indicator, quad, position) in (&mut indicators, &mut quads, &mut positions).join() {
+            indicator.seconds_left = (indicator.seconds_left - time.real_dt).max(0.0);
+            let offset = indicator.from - player_pos;
+            let placement = indicator_placement(offset.y.atan2(offset.x) - facing, screen);
+            let Some((on_edge, rotation)) = placement.filter(|_| indicator.seconds_left > 0.0)
+            else {
+                quad.opacity = 0.0;
+                continue;
+            };
+            quad.opacity = indicator.seconds_left / INDICATOR_SECONDS;
+            quad.rotation = rotation;
+            position.pos = nalgebra_glm::vec3(on_edge.x, on_edge.y, 0.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN: nalgebra_glm::Vec2 = nalgebra_glm::Vec2::new(800.0, 600.0);
+
+    #[test]
+    fn mobs_in_sight_have_no_arrow() {
+        assert!(indicator_placement(0.3, SCREEN).is_none());
+        assert!(indicator_placement(2.0 * PI - 0.3, SCREEN).is_none());
+    }
+
+    #[test]
+    fn mobs_behind_point_down_from_the_bottom_edge() {
+        let (on_edge, rotation) = indicator_placement(PI, SCREEN).unwrap();
+        assert!((on_edge.y - (-1.0 + 2.0 * INDICATOR_MARGIN / SCREEN.y)).abs() < 1e-5);
+        assert!(on_edge.x.abs() < 1e-5);
+        assert!((rotation.abs() - PI).abs() < 1e-5);
+    }
+
+    #[test]
+    fn mobs_to_the_left_point_left() {
+        let (on_edge, _) = indicator_placement(PI / 2.0, SCREEN).unwrap();
+        assert!(on_edge.x < 0.0);
+        assert!(on_edge.y.abs() < 1e-5);
+    }
+}
@@ -0,0 +1,126 @@
+// The diagnostics overlay, toggled with F3. Shows how fast the game is running, where the frame time goes, and where
+// the player is. Its numbers change every frame, so it's drawn a character at a time from a glyph cache rather than
+// with a quad.
+
+use sdl2::keyboard::Scancode;
+use specs::prelude::*;
+
+use crate::{
+    engine::{
+        physics::PositionComponent,
+        profiling::ProfileResource,
+        render3d::MeshMgrResource,
+        text::{GlyphCache, UIResource},
+    },
+    App,
+};
+
+use super::{
+    chunks::{chunk_at, ChunkResource},
+    prefabs::PrefabResource,
+    PlayerComponent,
+};
+
+const MARGIN: i32 = 8; //< Pixels from the top right corner of the screen
+const SLOWEST_SHOWN: usize = 5; //< How many of the slowest systems are listed
+
+/// Whether the overlay is shown, and what it says
+#[derive(Default)]
+pub(super) struct DiagnosticsResource {
+    visible: bool,
+    lines: Vec<String>,
+}
+
+/// Toggles the overlay, and gathers what it shows
+#[derive(Default)]
+pub(super) struct DiagnosticsSystem {
+    toggle_was_down: bool,
+}
+impl<'a> System<'a> for DiagnosticsSystem {
+    type SystemData = (
+        ReadStorage<'a, PlayerComponent>,
+        ReadStorage<'a, PositionComponent>,
+        ReadExpect<'a, ChunkResource>,
+        Read<'a, ProfileResource>,
+        Read<'a, App>,
+        Write<'a, DiagnosticsResource>,
+        Entities<'a>,
+    );
+
+    fn run(
+        &mut self,
+        (players, positions, chunks, profile, app, mut diagnostics, entities): Self::SystemData,
+    ) {
+        let toggle_down = app.keys[Scancode::F3 as usize];
+        if toggle_down && !self.toggle_was_down {
+            diagnostics.visible = !diagnostics.visible;
+        }
+        self.toggle_was_down = toggle_down;
+        if !diagnostics.visible {
+            return;
+        }
+
+        let Some((_, position)) = (&players, &positions).join().next() else {
+            return;
+        };
+        let pos = position.pos;
+        let (chunk_x, chunk_y) = chunk_at(pos.xy());
+        diagnostics.lines = vec![
+            format!("fps {:.0}  ticks/s {:.0}", app.fps, app.tick_rate),
+            format!("draw calls {}", app.draw_calls),
+            format!("entities {}", entities.join().count()),
+            format!("pos {:.2} {:.2} {:.2}", pos.x, pos.y, pos.z),
+            format!(
+                "chunk ({}, {})  {} loaded",
+                chunk_x,
+                chunk_y,
+                chunks.loaded_count()
+            ),
+        ];
+        let gpu_passes: Vec<String> = profile
+            .gpu_passes()
+            .into_iter()
+            .map(|(name, ms)| format!("{} {:.2}ms", name.trim_end_matches(" system"), ms))
+            .collect();
+        diagnostics
+            .lines
+            .push(format!("gpu {}", gpu_passes.join("  ")));
+        for (name, ms) in profile.slowest_systems(SLOWEST_SHOWN) {
+            diagnostics.lines.push(format!("{} {:.2}ms", name, ms));
+        }
+    }
+}
+
+/// Draws the overlay over the rest of the UI
+pub(super) struct DiagnosticsRenderSystem {
+    pub glyphs: GlyphCache,
+}
+impl<'a> System<'a> for DiagnosticsRenderSystem {
+    type SystemData = (
+        Read<'a, DiagnosticsResource>,
+        Read<'a, MeshMgrResource>,
+        Read<'a, PrefabResource>,
+        Read<'a, UIResource>,
+        Read<'a, App>,
+    );
+
+    fn run(&mut self, (diagnostics, mesh_mgr, prefabs, ui, app): Self::SystemData) {
+        if !diagnostics.visible {
+            return;
+        }
+        let width = diagnostics
+            .lines
+            .iter()
+            .map(|line| self.glyphs.width(line))
+            .max()
+            .unwrap_or(0);
+        self.glyphs.draw(
+            &diagnostics.lines,
+            (app.screen_width - width - MARGIN, MARGIN),
+            nalgebra_glm::vec3(1.0, 1.0, 0.4),
+            mesh_mgr.data.get_mesh(prefabs.quad_mesh),
+            &ui,
+            &app,
+        );
+    }
+}
@@ -0,0 +1,92 @@
+// Winning. The game is won once every treasure map has been found, and then after a last look at the island it gives
+// way to a summary of how the hunt went.
+
+use specs::{prelude::*, Component};
+
+use crate::engine::{
+    physics::PositionComponent, render3d::OpenGlResource, text::TextComponent, time::TimeResource,
+};
+
+use super::{
+    cinematic::{victory_path, CinematicResource},
+    stats::{GameSummary, StatsResource},
+    GoldResource, PlayerComponent, TreasureMapComponent,
+};
+
+const VICTORY_DELAY_SECONDS: f32 = 2.0; //< Time to hear the last chest open before the summary comes up
+const FINAL_SLOW_MOTION_SCALE: f32 = 0.35;
+const FINAL_SLOW_MOTION_SECONDS: f32 = 1.2; //< Real seconds the last chest opening is slowed down for
+
+#[derive(Default)]
+pub(super) struct GoalResource {
+    pub won_at: Option<f32>,          //< Game time the last map was found
+    pub summary: Option<GameSummary>, //< Set once the island should give way to the summary screen
+}
+
+/// The count of treasure found so far, under the map icons
+#[derive(Component, Default)]
+#[storage(NullStorage)]
+pub(super) struct TreasureCounterComponent;
+
+/// Keeps the treasure counter up to date
+pub(super) struct TreasureCounterSystem;
+impl<'a> System<'a> for TreasureCounterSystem {
+    type SystemData = (
+        ReadStorage<'a, TreasureMapComponent>,
+        ReadStorage<'a, TreasureCounterComponent>,
+        WriteStorage<'a, TextComponent>,
+        Read<'a, GoldResource>,
+    );
+
+    fn run(&mut self, (treasure_maps, counters, mut texts, gold): Self::SystemData) {
+        let maps_found = (&treasure_maps).join().filter(|map| map.found).count();
+        let maps = (&treasure_maps).join().count();
+        let text = format!("{} / {} treasure   {} gold", maps_found, maps, gold.gold);
+        for (_, counter_text) in (&counters, &mut texts).join() {
+            counter_text.set_text(&text);
+        }
+    }
+}
+
+/// Notices when every treasure map has been found, and plays the victory sequence
+pub(super) struct GoalSystem;
+impl<'a> System<'a> for GoalSystem {
+    type SystemData = (
+        ReadStorage<'a, TreasureMapComponent>,
+        WriteStorage<'a, PlayerComponent>,
+        ReadStorage<'a, PositionComponent>,
+        Write<'a, GoalResource>,
+        Write<'a, CinematicResource>,
+        Read<'a, OpenGlResource>,
+        Read<'a, StatsResource>,
+        Write<'a, TimeResource>,
+    );
+
+    fn run(
+        &mut self,
+        (treasure_maps, mut players, positions, mut goal, mut cinematic, opengl, stats, mut time): Self::SystemData,
+    ) {
+        let maps_found = (&treasure_maps).join().filter(|map| map.found).count();
+        let all_found = maps_found > 0 && maps_found == (&treasure_maps).join().count();
+        if !all_found {
+            return;
+        }
+
+        if goal.won_at.is_none() {
+            if let Some((player, position)) = (&mut players, &positions).join().next() {
+                let path = victory_path(opengl.camera.position, opengl.camera.lookat, position.pos);
+                // Nothing can spoil the win while the player isn't in control
+                player.invulnerable_for = f32::MAX;
+                cinematic.play(path);
+            }
+            time.slow_motion(FINAL_SLOW_MOTION_SCALE, FINAL_SLOW_MOTION_SECONDS);
+        }
+        let won_at = *goal.won_at.get_or_insert(time.elapsed);
+        if goal.summary.is_none()
+            && time.elapsed - won_at >= VICTORY_DELAY_SECONDS
+            && !cinematic.playing()
+        {
+            goal.summary = Some(GameSummary::new(true, won_at, maps_found, &stats));
+        }
+    }
+}
@@ -0,0 +1,147 @@
+// The golden image gallery: a few fixed seeds, each looked at from the same few spots every time. Run with
+// `--golden` to check the renders against `res/golden`, or `--bless-golden` to replace the stored images after an
+// intended change to how the island looks.
+
+use std::sync::{atomic::AtomicUsize, Arc};
+
+use specs::prelude::*;
+
+use crate::{
+    engine::{
+        golden::{
+            check_golden, golden_path, with_offscreen_context, GoldenResult, OffscreenTarget,
+        },
+        perlin::NoiseParams,
+        physics::{PositionComponent, VelocityComponent},
+        settings::{GraphicsSettings, QualityPreset},
+    },
+    App, Scene,
+};
+
+use super::{chunks::ChunkResource, generate_terrain, Island, IslandLayout, PlayerComponent};
+
+const GOLDEN_SEEDS: [u64; 3] = [1, 42, 20240601];
+const GOLDEN_WIDTH: i32 = 640;
+const GOLDEN_HEIGHT: i32 = 480;
+const SETTLE_TICKS: usize = 60; //< Ticks to run before rendering, so that things can settle into place
+
+/// Where the camera is put for a render, relative to the island's spawn point
+struct GoldenView {
+    name: &'static str,
+    offset: nalgebra_glm::Vec3,
+    facing: f32,
+    pitch: f32, //< Positive looks down
+}
+
+const GOLDEN_VIEWS: [GoldenView; 3] = [
+    GoldenView {
+        name: "spawn_east",
+        offset: nalgebra_glm::Vec3::new(0.0, 0.0, 0.0),
+        facing: 0.0,
+        pitch: 0.0,
+    },
+    GoldenView {
+        name: "spawn_west",
+        offset: nalgebra_glm::Vec3::new(0.0, 0.0, 0.0),
+        facing: std::f32::consts::PI,
+        pitch: 0.1,
+    },
+    // High up and looking down, to catch the shadows
+    GoldenView {
+        name: "overhead",
+        offset: nalgebra_glm::Vec3::new(-4.0, -4.0, 3.0),
+        facing: std::f32::consts::FRAC_PI_4,
+        pitch: 0.7,
+    },
+];
+
+/// The name of each render in the gallery
+fn golden_names() -> impl Iterator<Item = (u64, &'static GoldenView, String)> {
+    GOLDEN_SEEDS.into_iter().flat_map(|seed| {
+        GOLDEN_VIEWS
+            .iter()
+            .map(move |view| (seed, view, format!("seed{}_{}", seed, view.name)))
+    })
+}
+
+/// Renders every seed from every view, and checks the renders against the stored golden images. Returns an error
+/// listing the renders that didn't match. Golden images that haven't been blessed yet are an error before anything is
+/// rendered.
+pub(crate) fn run_golden_tests(bless: bool) -> Result<(), String> {
+    if !bless {
+        let missing: Vec<String> = golden_names()
+            .map(|(_, _, name)| name)
+            .filter(|name| !golden_path(name).exists())
+            .collect();
+        if !missing.is_empty() {
+            return Err(format!(
+                "No golden images for {}. Bless them with --bless-golden on a build that looks right, and commit \
+                 them.",
+                missing.join(", ")
+            ));
+        }
+    }
+    with_offscreen_context(|| {
+        let target = OffscreenTarget::new(GOLDEN_WIDTH, GOLDEN_HEIGHT);
+        let app = App {
+            screen_width: GOLDEN_WIDTH,
+            screen_height: GOLDEN_HEIGHT,
+            running: true,
+            tick_alpha: 1.0, //< Drawn right where the last tick left things
+            ..Default::default()
+        };
+        let mut failures = vec![];
+        for (seed, view, name) in golden_names() {
+            // A fresh island for each view, so that views don't depend on each other
+            let terrain = generate_terrain(
+                Some(seed),
+                IslandLayout::Single,
+                &NoiseParams::default(),
+                Arc::new(AtomicUsize::new(0)),
+            );
+            let spawn_point = terrain.spawn_point;
+            let mut island = Island::with_graphics_settings(
+                terrain,
+                GraphicsSettings::from_preset(QualityPreset::Medium),
+            )?;
+            // All the terrain in view has to be there for the render, however long it takes to build
+            island.world.write_resource::<ChunkResource>().synchronous = true;
+            for _ in 0..SETTLE_TICKS {
+                pose_player(&mut island, spawn_point + view.offset, view);
+                island.update(&app);
+            }
+
+            target.bind();
+            island.render(&app);
+            match check_golden(&name, &target.read_pixels(), bless)? {
+                GoldenResult::Matched(diff) => println!("{}: ok ({:?})", name, diff),
+                GoldenResult::Blessed => println!("{}: blessed", name),
+                GoldenResult::Mismatched(diff) => {
+                    failures.push(format!("{}: doesn't match ({:?})", name, diff))
+                }
+            }
+        }
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(failures.join("\n"))
+        }
+    })?
+}
+
+/// Holds the player still at a spot, looking a certain way
+fn pose_player(island: &mut Island, pos: nalgebra_glm::Vec3, view: &GoldenView) {
+    let world = &mut island.world;
+    for (player, position, velocity) in (
+        &mut world.write_storage::<PlayerComponent>(),
+        &mut world.write_storage::<PositionComponent>(),
+        &mut world.write_storage::<VelocityComponent>(),
+    )
+        .join()
+    {
+        player.facing = view.facing;
+        player.pitch = view.pitch;
+        position.pos = pos;
+        velocity.vel = nalgebra_glm::zero();
+    }
+}
@@ -0,0 +1,154 @@
+// Health bars. The player's is always in the top left corner of the screen. Everything else with health, like mobs
+// and practice targets, gets one floating over its head when it's hurt, which fades away if it isn't hurt again for a
+// while.
+
+use specs::{prelude::*, Component};
+
+use crate::engine::{
+    objects::Texture,
+    physics::PositionComponent,
+    text::{BillboardComponent, QuadComponent, UiLayer},
+    time::TimeResource,
+};
+
+use super::{HealthComponent, PlayerComponent, PrefabResource};
+
+pub(super) const PLAYER_BAR_WIDTH: i32 = 200; //< Pixels, at full health
+pub(super) const PLAYER_BAR_HEIGHT: i32 = 12;
+pub(super) const PLAYER_BAR_MARGIN: i32 = 16; //< Gap between the player's health bar and the top left corner, in pixels
+const MOB_BAR_WIDTH: i32 = 48;
+const MOB_BAR_HEIGHT: i32 = 5;
+const MOB_BAR_HEIGHT_ABOVE: f32 = 0.25; //< How far above a mob's feet its bar floats
+const MOB_BAR_SECONDS: f32 = 4.0; //< How long a mob's bar stays up after it was last hurt
+const MOB_BAR_FADE_SECONDS: f32 = 0.5;
+
+/// The player's health bar, or the dark track behind it
+#[derive(Component)]
+#[storage(HashMapStorage)]
+pub(super) struct PlayerHealthBarComponent {
+    pub track: bool, //< The track stays full width, behind the bar
+}
+
+/// A bar floating over a hurt mob
+#[derive(Component)]
+#[storage(HashMapStorage)]
+pub(super) struct MobHealthBarComponent {
+    pub mob: Entity,
+    pub shown_health: f32, //< The health the bar was last updated for, to notice new damage
+    pub seconds_left: f32,
+}
+
+/// Green at full health, red when nearly dead
+fn health_color(health: f32) -> nalgebra_glm::Vec3 {
+    nalgebra_glm::lerp(
+        &nalgebra_glm::vec3(0.85, 0.1, 0.1),
+        &nalgebra_glm::vec3(0.2, 0.8, 0.2),
+        health,
+    )
+}
+
+/// A plain white texture, tinted to whatever color the bar should be
+pub(super) fn bar_texture() -> Texture {
+    Texture::from_rgba(1, 1, &[255, 255, 255, 255])
+}
+
+/// Keeps the player's bar in sync with their health, and puts bars over anything else when it gets hurt
+pub(super) struct HealthBarSystem;
+impl<'a> System<'a> for HealthBarSystem {
+    type SystemData = (
+        ReadStorage<'a, PlayerHealthBarComponent>,
+        WriteStorage<'a, MobHealthBarComponent>,
+        ReadStorage<'a, PlayerComponent>,
+        ReadStorage<'a, HealthComponent>,
+        WriteStorage<'a, BillboardComponent>,
+        WriteStorage<'a, QuadComponent>,
+        ReadStorage<'a, PositionComponent>,
+        Read<'a, PrefabResource>,
+        Read<'a, TimeResource>,
+        Read<'a, LazyUpdate>,
+        Entities<'a>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            player_bars,
+            mut mob_bars,
+            players,
+            healths,
+            mut billboards,
+            mut quads,
+            positions,
+            prefabs,
+            time,
+            lazy,
+            entities,
+        ): Self::SystemData,
+    ) {
+        let player_health = (&players, &healths).join().next().unwrap().1.health;
+
+        // Laid out from the left, so that the bar shrinks toward the corner
+        for (bar, quad) in (&player_bars, &mut quads).join() {
+            if bar.track {
+                quad.width = PLAYER_BAR_WIDTH;
+                quad.tint = nalgebra_glm::vec3(0.1, 0.1, 0.1);
+                quad.opacity = 0.6;
+            } else {
+                quad.width = (PLAYER_BAR_WIDTH as f32 * player_health).round() as i32;
+                quad.tint = health_color(player_health);
+            }
+        }
+
+        for (bar, quad, billboard, entity) in
+            (&mut mob_bars, &mut quads, &mut billboards, &entities).join()
+        {
+            // Mobs lose their health when they die
+            let (Some(health), Some(position)) = (healths.get(bar.mob), positions.get(bar.mob))
+            else {
+                entities.delete(entity).unwrap();
+                continue;
+            };
+            if health.health != bar.shown_health {
+                bar.shown_health = health.health;
+                bar.seconds_left = MOB_BAR_SECONDS;
+            }
+            bar.seconds_left = (bar.seconds_left - time.real_dt).max(0.0);
+
+            billboard.anchor = position.pos + nalgebra_glm::vec3(0.0, 0.0, MOB_BAR_HEIGHT_ABOVE);
+            quad.width = ((MOB_BAR_WIDTH as f32 * health.health).round() as i32).max(1);
+            quad.tint = health_color(health.health);
+            quad.opacity = (bar.seconds_left / MOB_BAR_FADE_SECONDS).min(1.0);
+        }
+
+        // Hurt mobs and targets without a bar get one
+        let mut with_bars: Vec<Entity> = (&mob_bars).join().map(|bar| bar.mob).collect();
+        for (_, health, position, mob) in (!&players, &healths, &positions, &entities).join() {
+            if health.health >= 1.0 || with_bars.contains(&mob) {
+                continue;
+            }
+            with_bars.push(mob);
+            let anchor = position.pos + nalgebra_glm::vec3(0.0, 0.0, MOB_BAR_HEIGHT_ABOVE);
+            lazy.create_entity(&entities)
+                .with(
+                    QuadComponent::from_texture(
+                        bar_texture(),
+                        MOB_BAR_WIDTH,
+                        MOB_BAR_HEIGHT,
+                        prefabs.quad_mesh,
+                    )
+                    .in_layer(UiLayer::World, 0),
+                )
+                .with(PositionComponent {
+                    pos: nalgebra_glm::zero(),
+                })
+                .with(BillboardComponent { anchor })
+                .with(MobHealthBarComponent {
+                    mob,
+                    // Different from the mob's health, so the bar starts out fully shown
+                    shown_health: -1.0,
+                    seconds_left: 0.0,
+                })
+                .build();
+        }
+    }
+}
@@ -0,0 +1,384 @@
+// What happens when a bullet hits a mob or a practice target, or something in the way like a tree. Each reaction is its
+// own system reading the collision event channel, so that new reactions can be added without touching the collision
+// code or each other. Hits on a mob's weak point are critical, and do extra damage, sound sharper and mark yellow.
+
+use std::{collections::HashSet, ops::Deref};
+
+use specs::{
+    prelude::*,
+    shrev::{EventChannel, ReaderId},
+    storage::MaskedStorage,
+    Component,
+};
+
+use crate::engine::{
+    audio::AudioResource,
+    particles::{spawn_emitter, EmitterPreset},
+    perlin::PerlinMapResource,
+    physics::{PositionComponent, VelocityComponent},
+    render3d::OpenGlResource,
+    text::QuadComponent,
+    time::{per_tick, TimeResource},
+};
+
+use super::{
+    collisions::{collision_reader, CollisionEvent},
+    stats::StatsResource,
+    status::StatusComponent,
+    HealthComponent, MobComponent, PlayerComponent, ProjectileComponent, UNIT_PER_METER,
+};
+
+const KNOCKBACK_LIFT: f32 = 6.25 * UNIT_PER_METER; //< Meters per second up off the ground, for things that are hit
+const HIT_MARKER_SECONDS: f32 = 0.25;
+const HIT_MARKER_COLOR: nalgebra_glm::Vec3 = nalgebra_glm::Vec3::new(1.0, 0.3, 0.2);
+const CRITICAL_MARKER_COLOR: nalgebra_glm::Vec3 = nalgebra_glm::Vec3::new(1.0, 0.85, 0.1);
+const CRITICAL_PITCH: f32 = 1.4; //< Critical hits sound higher than other hits
+
+// Chips of bark knocked off where a bullet hits a tree
+const OBSTACLE_HIT_PARTICLES: EmitterPreset = EmitterPreset {
+    burst: 8,
+    rate: 0.0,
+    emit_seconds: 0.0,
+    lifetime: 0.4,
+    speed: 2.0 * UNIT_PER_METER,
+    direction: [0.0, 0.0, 1.0],
+    spread: 0.9,
+    gravity: 19.5 * UNIT_PER_METER,
+    drag: 3.0,
+    start_size: 0.1 * UNIT_PER_METER,
+    end_size: 0.04 * UNIT_PER_METER,
+    start_color: [0.36, 0.25, 0.15, 1.0],
+    end_color: [0.36, 0.25, 0.15, 0.0],
+};
+
+/// A projectile hitting something that can be hurt
+struct Hit {
+    projectile: Entity,
+    target: Entity,
+}
+
+/// The hits among some collisions. Each projectile only hits the first thing it touches, so that every reaction agrees
+/// on what was hit.
+fn hits<'e>(
+    events: impl Iterator<Item = &'e CollisionEvent>,
+    is_projectile: impl Fn(Entity) -> bool,
+    can_be_hit: impl Fn(Entity) -> bool,
+) -> Vec<Hit> {
+    let mut spent = HashSet::new();
+    events
+        .filter_map(|event| {
+            let (projectile, target) = if is_projectile(event.a) {
+                (event.a, event.b)
+            } else if is_projectile(event.b) {
+                (event.b, event.a)
+            } else {
+                return None;
+            };
+            let hit = !is_projectile(target) && can_be_hit(target) && spent.insert(projectile);
+            hit.then_some(Hit { projectile, target })
+        })
+        .collect()
+}
+
+/// Anything with health can be shot, except the player
+fn can_be_shot<H>(
+    entity: Entity,
+    players: &ReadStorage<PlayerComponent>,
+    healths: &Storage<HealthComponent, H>,
+) -> bool
+where
+    H: Deref<Target = MaskedStorage<HealthComponent>>,
+{
+    !players.contains(entity) && healths.contains(entity)
+}
+
+/// How many times the usual damage a hit does, if it landed on a mob's weak point. Goes by how high up the mob the
+/// projectile was when it hit.
+fn critical_multiplier(
+    hit: &Hit,
+    mobs: &ReadStorage<MobComponent>,
+    positions: &ReadStorage<PositionComponent>,
+) -> Option<f32> {
+    let stats = mobs.get(hit.target)?.kind.stats();
+    let height = positions.get(hit.projectile)?.pos.z - positions.get(hit.target)?.pos.z;
+    stats.critical_multiplier(height)
+}
+
+/// A mark over the crosshair, flashed when a shot lands
+#[derive(Component)]
+#[storage(HashMapStorage)]
+pub(super) struct HitMarkerComponent {
+    pub seconds_left: f32,
+}
+
+/// Takes health from what's hit, and uses up the projectile
+pub(super) struct DamageSystem {
+    reader: ReaderId<CollisionEvent>,
+}
+
+impl DamageSystem {
+    pub fn new(world: &mut World) -> Self {
+        Self {
+            reader: collision_reader(world),
+        }
+    }
+}
+
+impl<'a> System<'a> for DamageSystem {
+    type SystemData = (
+        WriteStorage<'a, HealthComponent>,
+        ReadStorage<'a, ProjectileComponent>,
+        ReadStorage<'a, PlayerComponent>,
+        ReadStorage<'a, MobComponent>,
+        ReadStorage<'a, StatusComponent>,
+        ReadStorage<'a, PositionComponent>,
+        Read<'a, EventChannel<CollisionEvent>>,
+        Write<'a, StatsResource>,
+        Entities<'a>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            mut healths,
+            projectiles,
+            players,
+            mobs,
+            statuses,
+            positions,
+            channel,
+            mut stats,
+            entities,
+        ): Self::SystemData,
+    ) {
+        let hits = hits(
+            channel.read(&mut self.reader),
+            |e| projectiles.contains(e),
+            |e| can_be_shot(e, &players, &healths),
+        );
+        for hit in hits {
+            let toughness = mobs
+                .get(hit.target)
+                .map_or(1.0, |mob| mob.kind.stats().toughness);
+            let damage_taken = statuses
+                .get(hit.target)
+                .map_or(1.0, |status| status.damage_taken_multiplier());
+            let critical = critical_multiplier(&hit, &mobs, &positions).unwrap_or(1.0);
+            let damage = projectiles.get(hit.projectile).unwrap().damage * critical;
+            healths.get_mut(hit.target).unwrap().health -= damage / toughness * damage_taken;
+            stats.attacks_landed += 1;
+            entities.delete(hit.projectile).unwrap();
+        }
+    }
+}
+
+/// Knocks what's hit along with the projectile, and up off the ground. Things without a velocity, like practice
+/// targets, are fixed in place.
+pub(super) struct KnockbackSystem {
+    reader: ReaderId<CollisionEvent>,
+}
+
+impl KnockbackSystem {
+    pub fn new(world: &mut World) -> Self {
+        Self {
+            reader: collision_reader(world),
+        }
+    }
+}
+
+impl<'a> System<'a> for KnockbackSystem {
+    type SystemData = (
+        ReadStorage<'a, PositionComponent>,
+        WriteStorage<'a, VelocityComponent>,
+        ReadStorage<'a, HealthComponent>,
+        ReadStorage<'a, ProjectileComponent>,
+        ReadStorage<'a, PlayerComponent>,
+        Read<'a, EventChannel<CollisionEvent>>,
+        Read<'a, PerlinMapResource>,
+    );
+
+    fn run(
+        &mut self,
+        (positions, mut velocities, healths, projectiles, players, channel, tiles): Self::SystemData,
+    ) {
+        let hits = hits(
+            channel.read(&mut self.reader),
+            |e| projectiles.contains(e),
+            |e| can_be_shot(e, &players, &healths),
+        );
+        for hit in hits {
+            let Some(projectile_vel) = velocities.get(hit.projectile).map(|v| v.vel) else {
+                continue;
+            };
+            let Some(target_velocity) = velocities.get_mut(hit.target) else {
+                continue;
+            };
+            target_velocity.vel.x += projectile_vel.x;
+            target_velocity.vel.y += projectile_vel.y;
+            let target_pos = positions.get(hit.target).unwrap().pos;
+            let tile_z: f32 = tiles.map.get_z_interpolated(target_pos.xy());
+            if target_pos.z + 0.01 <= tile_z {
+                target_velocity.vel.z += per_tick(KNOCKBACK_LIFT);
+            }
+        }
+    }
+}
+
+/// Plays the sound of things being hit
+pub(super) struct SfxSystem {
+    reader: ReaderId<CollisionEvent>,
+}
+
+impl SfxSystem {
+    pub fn new(world: &mut World) -> Self {
+        Self {
+            reader: collision_reader(world),
+        }
+    }
+}
+
+impl<'a> System<'a> for SfxSystem {
+    type SystemData = (
+        ReadStorage<'a, HealthComponent>,
+        ReadStorage<'a, ProjectileComponent>,
+        ReadStorage<'a, PlayerComponent>,
+        ReadStorage<'a, MobComponent>,
+        ReadStorage<'a, PositionComponent>,
+        Read<'a, EventChannel<CollisionEvent>>,
+        Read<'a, AudioResource>,
+    );
+
+    fn run(
+        &mut self,
+        (healths, projectiles, players, mobs, positions, channel, audio): Self::SystemData,
+    ) {
+        let hits = hits(
+            channel.read(&mut self.reader),
+            |e| projectiles.contains(e),
+            |e| can_be_shot(e, &players, &healths),
+        );
+        for hit in hits {
+            if critical_multiplier(&hit, &mobs, &positions).is_some() {
+                audio
+                    .audio_mgr
+                    .play_sound_pitched("res/hit.ogg".to_string(), 128, CRITICAL_PITCH);
+            } else {
+                audio.audio_mgr.play_sound("res/hit.ogg".to_string(), 128);
+            }
+        }
+    }
+}
+
+/// Stops projectiles at things in the way that can't be hurt and don't move, like trees and bush walls
+pub(super) struct ObstacleSystem {
+    reader: ReaderId<CollisionEvent>,
+}
+
+impl ObstacleSystem {
+    pub fn new(world: &mut World) -> Self {
+        Self {
+            reader: collision_reader(world),
+        }
+    }
+}
+
+impl<'a> System<'a> for ObstacleSystem {
+    type SystemData = (
+        ReadStorage<'a, PositionComponent>,
+        ReadStorage<'a, VelocityComponent>,
+        ReadStorage<'a, HealthComponent>,
+        ReadStorage<'a, ProjectileComponent>,
+        Read<'a, EventChannel<CollisionEvent>>,
+        Read<'a, AudioResource>,
+        Read<'a, OpenGlResource>,
+        Read<'a, LazyUpdate>,
+        Entities<'a>,
+    );
+
+    fn run(
+        &mut self,
+        (positions, velocities, healths, projectiles, channel, audio, opengl, lazy, entities): Self::SystemData,
+    ) {
+        let hits = hits(
+            channel.read(&mut self.reader),
+            |e| projectiles.contains(e),
+            |e| !healths.contains(e) && !velocities.contains(e),
+        );
+        for hit in hits {
+            let Some(pos) = positions.get(hit.projectile).map(|p| p.pos) else {
+                continue;
+            };
+            entities.delete(hit.projectile).unwrap();
+            spawn_emitter(&entities, &lazy, pos, OBSTACLE_HIT_PARTICLES);
+            let distance = nalgebra_glm::length(&(opengl.camera.position - pos));
+            audio.audio_mgr.play_sound(
+                "res/ground.ogg".to_string(),
+                (50.0 * 128.0 / distance.powf(2.0)) as i32,
+            );
+        }
+    }
+}
+
+/// Flashes the hit marker when a shot hurts something, and fades it out again
+pub(super) struct HitMarkerSystem {
+    reader: ReaderId<CollisionEvent>,
+}
+
+impl HitMarkerSystem {
+    pub fn new(world: &mut World) -> Self {
+        Self {
+            reader: collision_reader(world),
+        }
+    }
+}
+
+impl<'a> System<'a> for HitMarkerSystem {
+    type SystemData = (
+        WriteStorage<'a, HitMarkerComponent>,
+        WriteStorage<'a, QuadComponent>,
+        ReadStorage<'a, HealthComponent>,
+        ReadStorage<'a, ProjectileComponent>,
+        ReadStorage<'a, PlayerComponent>,
+        ReadStorage<'a, MobComponent>,
+        ReadStorage<'a, PositionComponent>,
+        Read<'a, EventChannel<CollisionEvent>>,
+        Read<'a, TimeResource>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            mut markers,
+            mut quads,
+            healths,
+            projectiles,
+            players,
+            mobs,
+            positions,
+            channel,
+            time,
+        ): Self::SystemData,
+    ) {
+        let hits = hits(
+            channel.read(&mut self.reader),
+            |e| projectiles.contains(e),
+            |e| can_be_shot(e, &players, &healths),
+        );
+        let critical = hits
+            .iter()
+            .any(|hit| critical_multiplier(hit, &mobs, &positions).is_some());
+        for (marker, quad) in (&mut markers, &mut quads).join() {
+            if hits.is_empty() {
+                marker.seconds_left = (marker.seconds_left - time.real_dt).max(0.0);
+            } else {
+                marker.seconds_left = HIT_MARKER_SECONDS;
+                quad.tint = if critical {
+                    CRITICAL_MARKER_COLOR
+                } else {
+                    HIT_MARKER_COLOR
+                };
+            }
+            quad.opacity = marker.seconds_left / HIT_MARKER_SECONDS;
+        }
+    }
+}
@@ -0,0 +1,123 @@
+// Arrows around the edge of the screen, pointing towards the chests the player is tracking while they're out of view.
+// A chest is tracked once the trader has given a hint for it, and the arrow points at the middle of the hint circle
+// rather than the chest itself, so it only gets the player to the right area.
+
+use std::f32::consts::PI;
+
+use specs::{prelude::*, Component};
+
+use crate::{
+    engine::{physics::PositionComponent, render3d::OpenGlResource, text::QuadComponent},
+    App,
+};
+
+use super::TreasureMapComponent;
+
+const EDGE_MARGIN: f32 = 40.0; //< Gap between an arrow and the edge of the screen, in pixels
+
+/// An arrow pointing towards the hint for one treasure map's chest
+#[derive(Component)]
+#[storage(HashMapStorage)]
+pub(super) struct ChestIndicatorComponent {
+    pub treasure_map: Entity,
+}
+
+/// Where a point off the screen should be shown on its edge, and which way it is from the middle of the screen, in
+/// pixels. None if the point is already on screen.
+fn edge_point(
+    clip: nalgebra_glm::Vec4,
+    screen: nalgebra_glm::Vec2,
+) -> Option<(nalgebra_glm::Vec2, nalgebra_glm::Vec2)> {
+    // Dividing by w would mirror points behind the camera through the middle of the screen, so divide by its size
+    let ndc = clip.xy() / clip.w.abs().max(0.0001);
+    if clip.w > 0.0 && ndc.x.abs() <= 1.0 && ndc.y.abs() <= 1.0 {
+        return None;
+    }
+    let dir_px = ndc.component_mul(&screen) / 2.0;
+    if nalgebra_glm::length(&dir_px) < 0.0001 {
+        return None;
+    }
+    // Pushed out along the direction until it meets the inset edge
+    let half = screen / 2.0 - nalgebra_glm::vec2(EDGE_MARGIN, EDGE_MARGIN);
+    let scale = (half.x / dir_px.x.abs()).min(half.y / dir_px.y.abs());
+    let on_edge = (dir_px * scale).component_div(&screen) * 2.0;
+    Some((on_edge, dir_px))
+}
+
+/// Moves each tracked chest's arrow to the edge of the screen nearest to it, and hides the rest
+pub(super) struct ChestIndicatorSystem;
+impl<'a> System<'a> for ChestIndicatorSystem {
+    type SystemData = (
+        ReadStorage<'a, ChestIndicatorComponent>,
+        ReadStorage<'a, TreasureMapComponent>,
+        WriteStorage<'a, PositionComponent>,
+        WriteStorage<'a, QuadComponent>,
+        Read<'a, OpenGlResource>,
+        Read<'a, App>,
+        Entities<'a>,
+    );
+
+    fn run(
+        &mut self,
+        (indicators, treasure_maps, mut positions, mut quads, opengl, app, entities): Self::SystemData,
+    ) {
+        let screen = nalgebra_glm::vec2(app.screen_width as f32, app.screen_height as f32);
+        let (view, proj) = opengl.camera.gen_view_proj_matrices();
+        let proj_view = proj * view;
+
+        let mut updates = vec![];
+        for (indicator, entity) in (&indicators, &entities).join() {
+            let treasure_map = treasure_maps.get(indicator.treasure_map).unwrap();
+            let target = match treasure_map.hint {
+                Some(hint) if !treasure_map.found => {
+                    // The hint is only a spot on the map, so it's put at the height of the chest
+                    let chest = positions.get(treasure_map.treasure_entity).unwrap();
+                    Some(nalgebra_glm::vec4(hint.x, hint.y, chest.pos.z, 1.0))
+                }
+                _ => None,
+            };
+            let edge = target.and_then(|target| edge_point(proj_view * target, screen));
+            updates.push((entity, edge));
+        }
+        for (entity, edge) in updates {
+            let quad = quads.get_mut(entity).unwrap();
+            let Some((on_edge, dir_px)) = edge else {
+                quad.opacity = 0.0;
+                continue;
+            };
+            quad.opacity = 1.0;
+            // The arrow points up to begin with
+            quad.rotation = dir_px.y.atan2(dir_px.x) - PI / 2.0;
+            positions.get_mut(entity).unwrap().pos = nalgebra_glm::vec3(on_edge.x, on_edge.y, 0.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN: nalgebra_glm::Vec2 = nalgebra_glm::Vec2::new(800.0, 600.0);
+
+    #[test]
+    fn on_screen_points_have_no_arrow() {
+        assert!(edge_point(nalgebra_glm::vec4(0.5, -0.5, 0.0, 1.0), SCREEN).is_none());
+    }
+
+    #[test]
+    fn off_screen_points_go_to_the_nearest_edge() {
+        let (on_edge, dir) = edge_point(nalgebra_glm::vec4(3.0, 0.0, 0.0, 1.0), SCREEN).unwrap();
+        assert_eq!(
+            on_edge,
+            nalgebra_glm::vec2(1.0 - 2.0 * EDGE_MARGIN / SCREEN.x, 0.0)
+        );
+        assert!(dir.x > 0.0);
+    }
+
+    #[test]
+    fn points_behind_the_camera_stay_on_their_side() {
+        // Behind and to the left, which dividing by w would put on the right
+        let (on_edge, _) = edge_point(nalgebra_glm::vec4(-0.5, 0.1, 0.0, -1.0), SCREEN).unwrap();
+        assert!(on_edge.x < 0.0);
+    }
+}
@@ -0,0 +1,183 @@
+// Doing things with what's nearby, like opening chests and talking to people. Whatever the player is closest to and
+// facing gets a prompt floating over it, and pressing E sends an event for it. Each kind of thing handles its own
+// events, so the systems for chests and people don't have to check the keys or distances themselves.
+
+use specs::{
+    prelude::*,
+    shrev::{EventChannel, ReaderId},
+    Component,
+};
+
+use crate::{
+    engine::{
+        input::{Action, InputMap},
+        physics::PositionComponent,
+        render3d::OpenGlResource,
+        text::{BillboardComponent, QuadComponent, TextComponent},
+    },
+    App,
+};
+
+use super::{PlayerComponent, UNIT_PER_METER};
+
+const MIN_FACING: f32 = 0.5; //< Cosine of how far off to the side something can be and still be picked
+const PROMPT_HEIGHT: f32 = 1.2 * UNIT_PER_METER; //< How far over the thing its prompt floats
+
+/// What happens when something is interacted with
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(super) enum InteractAction {
+    OpenChest, //< Also digs up buried chests
+    Talk,
+}
+
+/// Something the player can walk up to and press E at
+#[derive(Component)]
+#[storage(HashMapStorage)]
+pub(super) struct InteractableComponent {
+    pub radius: f32,          //< How close the player has to be
+    pub prompt: &'static str, //< What pressing E does, finishing "Press E to"
+    pub action: InteractAction,
+}
+
+/// The player pressing E at something
+pub(super) struct InteractEvent {
+    pub entity: Entity,
+    pub action: InteractAction,
+}
+
+/// Starts reading interact events. Readers only see events written after they start, and have to read every tick.
+pub(super) fn interact_reader(world: &mut World) -> ReaderId<InteractEvent> {
+    world
+        .write_resource::<EventChannel<InteractEvent>>()
+        .register_reader()
+}
+
+/// The prompt floating over whatever would be interacted with
+#[derive(Component)]
+#[storage(HashMapStorage)]
+pub(super) struct InteractPromptComponent;
+
+/// How good a pick something is, higher being better. Things ahead of the player and close by beat things off to the
+/// side or further away. None if it's out of reach, or not in front of the player.
+fn interact_score(
+    forward: nalgebra_glm::Vec2,
+    to_thing: nalgebra_glm::Vec3,
+    radius: f32,
+) -> Option<f32> {
+    let distance = nalgebra_glm::length(&to_thing);
+    if distance > radius {
+        return None;
+    }
+    // Standing right on top of something, any way the player faces is toward it
+    let flat = to_thing.xy();
+    let facing = if nalgebra_glm::length(&flat) < f32::EPSILON {
+        1.0
+    } else {
+        nalgebra_glm::dot(&forward, &flat.normalize())
+    };
+    (facing >= MIN_FACING).then_some(facing - distance / radius)
+}
+
+/// Picks the best thing to interact with, puts the prompt over it, and sends an event when E is pressed
+#[derive(Default)]
+pub(super) struct InteractionSystem {
+    was_down: bool,
+}
+impl<'a> System<'a> for InteractionSystem {
+    type SystemData = (
+        ReadStorage<'a, InteractableComponent>,
+        ReadStorage<'a, InteractPromptComponent>,
+        ReadStorage<'a, PlayerComponent>,
+        ReadStorage<'a, PositionComponent>,
+        WriteStorage<'a, TextComponent>,
+        WriteStorage<'a, QuadComponent>,
+        WriteStorage<'a, BillboardComponent>,
+        Read<'a, OpenGlResource>,
+        Read<'a, App>,
+        Read<'a, InputMap>,
+        Write<'a, EventChannel<InteractEvent>>,
+        Entities<'a>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            interactables,
+            prompts,
+            players,
+            positions,
+            mut texts,
+            mut quads,
+            mut billboards,
+            opengl,
+            app,
+            input,
+            mut events,
+            entities,
+        ): Self::SystemData,
+    ) {
+        let down = input.held(&app, Action::Interact);
+        let pressed = down && !self.was_down;
+        self.was_down = down;
+
+        let Some((_, player_position)) = (&players, &positions).join().next() else {
+            return;
+        };
+        let look = (opengl.camera.lookat - opengl.camera.position).xy();
+        let forward = if nalgebra_glm::length(&look) < f32::EPSILON {
+            nalgebra_glm::vec2(1.0, 0.0)
+        } else {
+            look.normalize()
+        };
+        let best = (&interactables, &positions, &entities)
+            .join()
+            .filter_map(|(interactable, position, entity)| {
+                let to_thing = position.pos - player_position.pos;
+                let score = interact_score(forward, to_thing, interactable.radius)?;
+                Some((score, interactable, position.pos, entity))
+            })
+            .max_by(|a, b| a.0.total_cmp(&b.0));
+
+        for (_, text, quad, billboard) in (&prompts, &mut texts, &mut quads, &mut billboards).join()
+        {
+            match best {
+                Some((_, interactable, pos, _)) => {
+                    text.set_text(&format!(
+                        "Press {} to {}",
+                        input.name(Action::Interact),
+                        interactable.prompt
+                    ));
+                    billboard.anchor = pos + nalgebra_glm::vec3(0.0, 0.0, PROMPT_HEIGHT);
+                    quad.opacity = 1.0;
+                }
+                None => quad.opacity = 0.0,
+            }
+        }
+
+        if let Some((_, interactable, _, entity)) = best.filter(|_| pressed) {
+            events.single_write(InteractEvent {
+                entity,
+                action: interactable.action,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn closer_and_straighter_ahead_is_better() {
+        let forward = nalgebra_glm::vec2(1.0, 0.0);
+        let radius = 3.0;
+        let ahead = interact_score(forward, nalgebra_glm::vec3(1.0, 0.0, 0.0), radius).unwrap();
+        let further = interact_score(forward, nalgebra_glm::vec3(2.0, 0.0, 0.0), radius).unwrap();
+        let aside = interact_score(forward, nalgebra_glm::vec3(1.0, 0.5, 0.0), radius).unwrap();
+        assert!(ahead > further);
+        assert!(ahead > aside);
+        // Behind the player, or out of reach
+        assert!(interact_score(forward, nalgebra_glm::vec3(-1.0, 0.0, 0.0), radius).is_none());
+        assert!(interact_score(forward, nalgebra_glm::vec3(4.0, 0.0, 0.0), radius).is_none());
+    }
+}
@@ -0,0 +1,187 @@
+// The player's inventory, and the hotbar along the bottom of the screen. Whatever is in the active slot decides what
+// the player can do: weapons shoot, the machete cuts, the shovel digs.
+
+use sdl2::{controller::Button, keyboard::Scancode, pixels::Color, ttf::Font};
+use serde::{Deserialize, Serialize};
+use specs::{prelude::*, Component};
+
+use crate::{
+    engine::text::{Align, Anchor, LayoutComponent, QuadComponent},
+    App,
+};
+
+use super::{tools::Tool, weapons::Weapon, GoldResource, PlayerComponent};
+
+pub(super) const HOTBAR_SLOTS: usize = 6; //< The weapons, both tools, and gold
+const SLOT_WIDTH: i32 = 120; //< Pixels between the left edges of slots
+const MARGIN: i32 = 12; //< Gap between the hotbar and the bottom left corner of the screen, in pixels
+const ACTIVE_TINT: (f32, f32, f32) = (1.0, 0.8, 0.2);
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub(super) enum Item {
+    Weapon(Weapon),
+    Tool(Tool),
+    Gold,
+}
+
+impl Item {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Item::Weapon(weapon) => weapon.stats().name,
+            Item::Tool(tool) => tool.name(),
+            Item::Gold => "gold",
+        }
+    }
+}
+
+#[derive(Component, Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[storage(HashMapStorage)]
+pub(super) struct InventoryComponent {
+    pub items: Vec<Item>, //< In hotbar order
+    pub active: usize,    //< Index into `items` of the item in hand
+}
+
+impl InventoryComponent {
+    /// The player starts out with just their weapons
+    pub fn new() -> Self {
+        Self {
+            items: Weapon::ALL.map(Item::Weapon).to_vec(),
+            active: 0,
+        }
+    }
+
+    pub fn has(&self, item: Item) -> bool {
+        self.items.contains(&item)
+    }
+
+    /// Whether the item is in the active slot
+    pub fn holding(&self, item: Item) -> bool {
+        self.items.get(self.active) == Some(&item)
+    }
+
+    /// The weapon in the active slot, if it is one
+    pub fn held_weapon(&self) -> Option<Weapon> {
+        match self.items.get(self.active) {
+            Some(Item::Weapon(weapon)) => Some(*weapon),
+            _ => None,
+        }
+    }
+
+    /// Adds the item to the end of the hotbar, if it isn't there already
+    pub fn give(&mut self, item: Item) {
+        if !self.has(item) && self.items.len() < HOTBAR_SLOTS {
+            self.items.push(item);
+        }
+    }
+}
+
+/// One slot in the hotbar
+#[derive(Component)]
+#[storage(HashMapStorage)]
+pub(super) struct HotbarSlotComponent {
+    pub index: usize,
+}
+
+/// Switches the active item with the number keys, the mouse wheel, or the shoulder buttons. Also puts gold in the
+/// hotbar once the player has some.
+#[derive(Default)]
+pub(super) struct InventorySystem {
+    prev_was_down: bool,
+    next_was_down: bool,
+}
+impl<'a> System<'a> for InventorySystem {
+    type SystemData = (
+        ReadStorage<'a, PlayerComponent>,
+        WriteStorage<'a, InventoryComponent>,
+        Read<'a, App>,
+        Read<'a, GoldResource>,
+    );
+
+    fn run(&mut self, (players, mut inventories, app, gold): Self::SystemData) {
+        let prev_down = app.button(Button::LeftShoulder);
+        let next_down = app.button(Button::RightShoulder);
+        let prev_pressed = (prev_down && !self.prev_was_down) || app.mouse_wheel > 0.0;
+        let next_pressed = (next_down && !self.next_was_down) || app.mouse_wheel < 0.0;
+        self.prev_was_down = prev_down;
+        self.next_was_down = next_down;
+
+        const NUMBER_KEYS: [Scancode; HOTBAR_SLOTS] = [
+            Scancode::Num1,
+            Scancode::Num2,
+            Scancode::Num3,
+            Scancode::Num4,
+            Scancode::Num5,
+            Scancode::Num6,
+        ];
+        for (_, inventory) in (&players, &mut inventories).join() {
+            if gold.gold > 0 {
+                inventory.give(Item::Gold);
+            }
+
+            let len = inventory.items.len();
+            if prev_pressed {
+                inventory.active = (inventory.active + len - 1) % len;
+            }
+            if next_pressed {
+                inventory.active = (inventory.active + 1) % len;
+            }
+            for (i, key) in NUMBER_KEYS.iter().enumerate() {
+                if app.keys[*key as usize] && i < len {
+                    inventory.active = i;
+                }
+            }
+        }
+    }
+}
+
+/// Where a hotbar slot's label goes: left aligned in the slot, with the slots in a row along the bottom left
+pub(super) fn slot_layout(index: usize) -> LayoutComponent {
+    LayoutComponent::new(
+        Anchor::new(Align::Start, Align::End),
+        (MARGIN + index as i32 * SLOT_WIDTH, MARGIN),
+    )
+}
+
+/// Labels each hotbar slot with its number key and item
+pub(super) struct HotbarSystem {
+    pub font: Font<'static, 'static>,
+    pub labels: [String; HOTBAR_SLOTS], //< What each slot currently says, so text is only rendered when it changes
+}
+impl<'a> System<'a> for HotbarSystem {
+    type SystemData = (
+        ReadStorage<'a, HotbarSlotComponent>,
+        ReadStorage<'a, PlayerComponent>,
+        ReadStorage<'a, InventoryComponent>,
+        WriteStorage<'a, QuadComponent>,
+        Read<'a, GoldResource>,
+    );
+
+    fn run(&mut self, (slots, players, inventories, mut quads, gold): Self::SystemData) {
+        let inventory = (&players, &inventories).join().next().unwrap().1;
+
+        for (slot, quad) in (&slots, &mut quads).join() {
+            let item = inventory.items.get(slot.index);
+            let label = match item {
+                Some(Item::Gold) => format!("{} {} gold", slot.index + 1, gold.gold),
+                Some(item) => format!("{} {}", slot.index + 1, item.name()),
+                None => String::new(),
+            };
+            if label != self.labels[slot.index] {
+                quad.set_text(&label, &self.font, Color::RGBA(255, 255, 255, 255));
+                self.labels[slot.index] = label;
+            }
+
+            let active = slot.index == inventory.active;
+            quad.opacity = match (item, active) {
+                (None, _) => 0.0,
+                (Some(_), true) => 1.0,
+                (Some(_), false) => 0.6,
+            };
+            quad.tint = if active {
+                nalgebra_glm::vec3(ACTIVE_TINT.0, ACTIVE_TINT.1, ACTIVE_TINT.2)
+            } else {
+                nalgebra_glm::vec3(1.0, 1.0, 1.0)
+            };
+        }
+    }
+}
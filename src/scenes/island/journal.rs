@@ -0,0 +1,124 @@
+// The photo journal. Every chest found is photographed and written up with when and where it was, in a journal kept
+// apart from the save, so that it outlasts the island it was found on.
+
+use image::{imageops::FilterType, RgbaImage};
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+
+use crate::{
+    engine::{
+        golden::read_pixels, perlin::PerlinMapResource, time::TimeResource, water::WaterResource,
+    },
+    App,
+};
+
+use super::{
+    biome::Biome, clock_day, clock_time, compass::compass_direction, model_time, ToastResource,
+    MAP_WIDTH,
+};
+
+pub(crate) const JOURNAL_PATH: &str = "journal.ron";
+const PHOTO_DIR: &str = "journal"; //< Photos are kept as PNGs in here, named by their entry's number
+const PHOTO_WIDTH: u32 = 240;
+const PHOTO_HEIGHT: u32 = 135;
+
+/// One found treasure
+#[derive(Serialize, Deserialize)]
+pub(crate) struct JournalEntry {
+    pub photo: String, //< Path to the photo
+    pub day: u32,      //< Counting from 1, the day the island was landed on
+    pub hours: u32,
+    pub minutes: u32,
+    pub location: String, //< Like "the north-east forest"
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub(crate) struct Journal {
+    pub entries: Vec<JournalEntry>,
+}
+
+impl Journal {
+    /// The journal so far, or an empty one if nothing has been found yet
+    pub(crate) fn load(path: &str) -> Result<Self, String> {
+        if !std::path::Path::new(path).exists() {
+            return Ok(Self::default());
+        }
+        let contents = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        ron::from_str(&contents).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    fn save(&self, path: &str) -> Result<(), String> {
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| e.to_string())?;
+        std::fs::write(path, contents).map_err(|e| e.to_string())
+    }
+}
+
+/// Chests found this tick, waiting for the next frame to be photographed
+#[derive(Default)]
+pub(super) struct JournalResource {
+    pending: Vec<nalgebra_glm::Vec3>,
+}
+
+impl JournalResource {
+    pub fn photograph(&mut self, chest_pos: nalgebra_glm::Vec3) {
+        self.pending.push(chest_pos);
+    }
+}
+
+/// Where on the island `pos` is, like "the north-east forest"
+fn location_name(world: &World, pos: nalgebra_glm::Vec3) -> String {
+    let tiles = world.read_resource::<PerlinMapResource>();
+    let biome = Biome::classify(
+        pos.z,
+        tiles.map.get_dot_prod(pos.xy()),
+        tiles.moisture.get(pos.xy()),
+        world.read_resource::<WaterResource>().is_underwater(pos),
+    );
+    let center = nalgebra_glm::vec2(MAP_WIDTH as f32, MAP_WIDTH as f32) / 2.0;
+    format!(
+        "the {} {}",
+        compass_direction(pos.xy() - center),
+        biome.config().name
+    )
+}
+
+/// Photographs any chests found since the last frame, and writes them into the journal. Call once the world has been
+/// drawn, before the HUD goes over it.
+pub(super) fn take_photos(world: &World, app: &App) {
+    let pending = std::mem::take(&mut world.write_resource::<JournalResource>().pending);
+    if pending.is_empty() {
+        return;
+    }
+    let frame = read_pixels(app.screen_width, app.screen_height);
+    let photo = image::imageops::resize(&frame, PHOTO_WIDTH, PHOTO_HEIGHT, FilterType::Triangle);
+    match add_entries(world, &photo, &pending) {
+        Ok(()) => world
+            .write_resource::<ToastResource>()
+            .show("Added to the journal"),
+        Err(err) => println!("Couldn't add to the journal: {}", err),
+    }
+}
+
+fn add_entries(
+    world: &World,
+    photo: &RgbaImage,
+    chests: &[nalgebra_glm::Vec3],
+) -> Result<(), String> {
+    let mut journal = Journal::load(JOURNAL_PATH)?;
+    std::fs::create_dir_all(PHOTO_DIR).map_err(|e| e.to_string())?;
+    let model_t = model_time(world.read_resource::<TimeResource>().elapsed);
+    let (hours, minutes) = clock_time(model_t);
+    for chest_pos in chests {
+        let path = format!("{}/{}.png", PHOTO_DIR, journal.entries.len());
+        photo.save(&path).map_err(|e| format!("{}: {}", path, e))?;
+        journal.entries.push(JournalEntry {
+            photo: path,
+            day: clock_day(model_t),
+            hours,
+            minutes,
+            location: location_name(world, *chest_pos),
+        });
+    }
+    journal.save(JOURNAL_PATH)
+}
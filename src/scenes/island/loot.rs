@@ -0,0 +1,225 @@
+// What mobs drop when they're defeated. Each kind of mob has a loot table of things it might drop, which are thrown out
+// of it as pickups that bob and spin on the ground. Once the player comes close, pickups fly into their pockets like
+// coins out of a chest, and are paid into their gold or ammo. Pickups left lying around for long enough are gone.
+
+use rand::Rng;
+use specs::{prelude::*, Component};
+
+use crate::engine::{
+    audio::AudioResource, perlin::PerlinMapResource, physics::PositionComponent, time::TimeResource,
+};
+
+use super::{
+    coins::{fly_toward, FLY_ACCELERATION, FLY_SPEED, PICKUP_DIST},
+    mobs::MobKind,
+    stats::StatsResource,
+    weapons::{Weapon, WeaponComponent},
+    GoldResource, PlayerComponent, PERSON_HEIGHT, UNIT_PER_METER,
+};
+
+const THROW_SPEED: f32 = 1.5 * UNIT_PER_METER; //< Per second, out and up from the mob
+const THROW_GRAVITY: f32 = 6.0 * UNIT_PER_METER; //< Per second squared, until the pickup lands
+const REST_HEIGHT: f32 = 0.3 * UNIT_PER_METER; //< How high over the ground pickups bob
+const MAGNET_DIST: f32 = 4.0 * UNIT_PER_METER; //< Pickups fly to the player from this close
+const LOOT_SECONDS: f32 = 90.0; //< Pickups nobody comes for are gone after this long
+
+/// Something a mob can drop
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(super) enum Loot {
+    Gold(u32),
+    Rounds(Weapon, u32), //< Spare rounds for a weapon
+}
+
+/// A chance of a mob dropping something. Every entry in a table is rolled for on its own.
+struct LootEntry {
+    loot: Loot,
+    chance: f32,
+}
+
+const GHOST_LOOT: [LootEntry; 2] = [
+    LootEntry {
+        loot: Loot::Gold(3),
+        chance: 0.6,
+    },
+    LootEntry {
+        loot: Loot::Rounds(Weapon::Musket, 3),
+        chance: 0.3,
+    },
+];
+const CRAB_LOOT: [LootEntry; 2] = [
+    LootEntry {
+        loot: Loot::Gold(2),
+        chance: 0.5,
+    },
+    LootEntry {
+        loot: Loot::Rounds(Weapon::Blunderbuss, 2),
+        chance: 0.25,
+    },
+];
+const SKELETON_LOOT: [LootEntry; 3] = [
+    LootEntry {
+        loot: Loot::Gold(8),
+        chance: 0.9,
+    },
+    LootEntry {
+        loot: Loot::Rounds(Weapon::Musket, 4),
+        chance: 0.5,
+    },
+    LootEntry {
+        loot: Loot::Rounds(Weapon::ThrowingKnife, 1),
+        chance: 0.3,
+    },
+];
+const BIRD_LOOT: [LootEntry; 1] = [LootEntry {
+    loot: Loot::Gold(1),
+    chance: 0.4,
+}];
+
+fn loot_table(kind: MobKind) -> &'static [LootEntry] {
+    match kind {
+        MobKind::Ghost => &GHOST_LOOT,
+        MobKind::Crab => &CRAB_LOOT,
+        MobKind::Skeleton => &SKELETON_LOOT,
+        MobKind::Bird => &BIRD_LOOT,
+    }
+}
+
+/// What a defeated mob of a kind drops
+pub(super) fn roll_loot(kind: MobKind, rng: &mut impl Rng) -> Vec<Loot> {
+    loot_table(kind)
+        .iter()
+        .filter(|entry| rng.gen::<f32>() < entry.chance)
+        .map(|entry| entry.loot)
+        .collect()
+}
+
+#[derive(Component)]
+#[storage(VecStorage)]
+pub(super) struct LootComponent {
+    pub loot: Loot,
+    pub vel: nalgebra_glm::Vec3, //< Per second
+    pub age: f32,                //< Seconds since the pickup was dropped
+    pub flying: Option<f32>, //< Seconds the pickup has been flying to the player, once it's started
+}
+
+impl LootComponent {
+    /// The `i`th of `count` pickups dropped at once, each thrown a different way around
+    pub fn thrown(loot: Loot, i: usize, count: usize) -> Self {
+        let angle = i as f32 / count as f32 * std::f32::consts::TAU;
+        Self {
+            loot,
+            vel: nalgebra_glm::vec3(angle.cos() * 0.5, angle.sin() * 0.5, 1.0) * THROW_SPEED,
+            age: 0.0,
+            flying: None,
+        }
+    }
+}
+
+/// Throws pickups out of defeated mobs, flies them to the player when they come close, and pays them in
+pub(super) struct LootSystem;
+impl<'a> System<'a> for LootSystem {
+    type SystemData = (
+        WriteStorage<'a, LootComponent>,
+        WriteStorage<'a, PositionComponent>,
+        ReadStorage<'a, PlayerComponent>,
+        WriteStorage<'a, WeaponComponent>,
+        Write<'a, GoldResource>,
+        Write<'a, StatsResource>,
+        Read<'a, PerlinMapResource>,
+        Read<'a, AudioResource>,
+        Read<'a, TimeResource>,
+        Entities<'a>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            mut loots,
+            mut positions,
+            players,
+            mut weapons,
+            mut gold,
+            mut stats,
+            tiles,
+            audio,
+            time,
+            entities,
+        ): Self::SystemData,
+    ) {
+        let Some((_, player_position, weapon)) = (&players, &positions, &mut weapons).join().next()
+        else {
+            return;
+        };
+        // Into the player's hands, rather than their feet
+        let pocket = player_position.pos + nalgebra_glm::vec3(0.0, 0.0, PERSON_HEIGHT * 0.5);
+
+        for (loot, position, entity) in (&mut loots, &mut positions, &entities).join() {
+            loot.age += time.dt;
+            let distance = nalgebra_glm::distance(&pocket, &position.pos);
+            if loot.flying.is_none() && distance < MAGNET_DIST {
+                loot.flying = Some(0.0);
+            }
+
+            let Some(flying) = loot.flying.as_mut() else {
+                if loot.age > LOOT_SECONDS {
+                    entities.delete(entity).unwrap();
+                    continue;
+                }
+                // Thrown out until it lands, then bobbing in place
+                let rest = tiles.map.get_z_interpolated(position.pos.xy()) + REST_HEIGHT;
+                loot.vel.z -= THROW_GRAVITY * time.dt;
+                position.pos += loot.vel * time.dt;
+                if position.pos.z <= rest {
+                    position.pos.z = rest;
+                    loot.vel = nalgebra_glm::zero();
+                }
+                continue;
+            };
+
+            if distance < PICKUP_DIST {
+                match loot.loot {
+                    Loot::Gold(amount) => {
+                        gold.gold += amount;
+                        stats.gold_earned += amount;
+                    }
+                    Loot::Rounds(kind, rounds) => {
+                        weapon.give_rounds(kind, rounds);
+                    }
+                }
+                audio.audio_mgr.play_sound("res/pop.ogg".to_string(), 64);
+                entities.delete(entity).unwrap();
+                continue;
+            }
+            *flying += time.dt;
+            let speed = FLY_SPEED + FLY_ACCELERATION * *flying;
+            loot.vel = fly_toward(position.pos, pocket, speed, time.dt);
+            position.pos += loot.vel * time.dt;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn drops_come_from_the_mobs_table() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut gold_drops = 0;
+        for _ in 0..100 {
+            let drops = roll_loot(MobKind::Skeleton, &mut rng);
+            assert!(drops.len() <= SKELETON_LOOT.len());
+            assert!(drops
+                .iter()
+                .all(|drop| SKELETON_LOOT.iter().any(|entry| entry.loot == *drop)));
+            gold_drops += drops
+                .iter()
+                .filter(|drop| matches!(drop, Loot::Gold(_)))
+                .count();
+        }
+        // Skeletons nearly always have gold on them
+        assert!(gold_drops > 75);
+    }
+}
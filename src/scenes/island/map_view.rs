@@ -0,0 +1,252 @@
+// The treasure maps themselves, held up with M. Each one is a sketch of the ground around its chest, inked onto
+// parchment from the heightmap when the island is made, with an X where the chest is. The player has to find the
+// stretch of coast or hills it shows to know where to dig.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use specs::{prelude::*, Component};
+
+use crate::{
+    engine::{
+        input::{Action, InputMap},
+        objects::Texture,
+        perlin::{PerlinMap, PerlinMapResource},
+        physics::PositionComponent,
+        text::QuadComponent,
+        time::TimeResource,
+        water::WaterResource,
+    },
+    App,
+};
+
+use super::TreasureMapComponent;
+
+const TEXTURE_SIZE: usize = 256;
+pub(super) const MAP_VIEW_SIZE: i32 = 448; //< Width and height on screen, in pixels
+const VIEW_WIDTH: f32 = 32.0; //< Tiles across a map, about 640 meters
+const CONTOUR_STEP: f32 = 0.5; //< Height between the contour lines on land
+const X_SIZE: f32 = 7.0; //< Half the width of the X, in pixels
+const X_THICKNESS: f32 = 1.5;
+const RAISE_SECONDS: f32 = 0.2;
+const PARCHMENT: nalgebra_glm::Vec3 = nalgebra_glm::Vec3::new(0.87, 0.78, 0.6);
+const SEA: nalgebra_glm::Vec3 = nalgebra_glm::Vec3::new(0.55, 0.68, 0.7);
+const INK: nalgebra_glm::Vec3 = nalgebra_glm::Vec3::new(0.25, 0.17, 0.1);
+const RED_INK: nalgebra_glm::Vec3 = nalgebra_glm::Vec3::new(0.65, 0.1, 0.08);
+
+/// The large map for one treasure map, below the bottom of the screen until it's raised
+#[derive(Component)]
+#[storage(HashMapStorage)]
+pub(super) struct MapViewComponent {
+    pub treasure_map: Entity,
+}
+
+/// Bottom left corner of the part of the island a chest's map shows. The chest is somewhere in the middle half rather
+/// than dead center, so that the map can't be read by walking to the middle of what it shows.
+fn map_window(chest: nalgebra_glm::Vec2) -> nalgebra_glm::Vec2 {
+    let seed = (chest.x.to_bits() as u64) << 32 | chest.y.to_bits() as u64;
+    let mut rng = StdRng::seed_from_u64(seed);
+    let offset = nalgebra_glm::vec2(rng.gen_range(-0.25..0.25), rng.gen_range(-0.25..0.25));
+    chest + (offset.add_scalar(-0.5)) * VIEW_WIDTH
+}
+
+/// Inks a map from the height under each pixel, with the first row at the top. The X goes over `chest_px`.
+fn map_view_pixels(heights: &[f32], water_level: f32, chest_px: nalgebra_glm::Vec2) -> Vec<u8> {
+    let size = TEXTURE_SIZE;
+    let at = |x: usize, row: usize| heights[x.min(size - 1) + row.min(size - 1) * size];
+    let step = |height: f32| (height / CONTOUR_STEP).floor();
+    let mut rng = StdRng::seed_from_u64(chest_px.x.to_bits() as u64);
+    let mut pixels = Vec::with_capacity(size * size * 4);
+    for row in 0..size {
+        for x in 0..size {
+            let grain: f32 = rng.gen();
+            let (height, right, below) = (at(x, row), at(x + 1, row), at(x, row + 1));
+            let land = height >= water_level;
+            let coast = (right >= water_level) != land || (below >= water_level) != land;
+            let contour = land && (step(height) != step(right) || step(height) != step(below));
+
+            let mut color = PARCHMENT * (0.95 + 0.08 * grain);
+            if !land {
+                color = nalgebra_glm::lerp(&color, &SEA, 0.35);
+                // Wavy lines across the sea
+                let wave = row as f32 + 2.0 * (x as f32 * 0.25).sin();
+                if (wave.round() as i32).rem_euclid(9) == 0 {
+                    color = nalgebra_glm::lerp(&color, &INK, 0.3);
+                }
+            }
+            if contour {
+                color = nalgebra_glm::lerp(&color, &INK, 0.35);
+            }
+            if coast {
+                color = INK;
+            }
+
+            let from_chest = nalgebra_glm::vec2(x as f32 + 0.5, row as f32 + 0.5) - chest_px;
+            let on_stroke = (from_chest.x - from_chest.y).abs() < X_THICKNESS
+                || (from_chest.x + from_chest.y).abs() < X_THICKNESS;
+            if from_chest.x.abs() <= X_SIZE && from_chest.y.abs() <= X_SIZE && on_stroke {
+                color = RED_INK;
+            }
+
+            // Darker toward the edges, which are ragged like old paper
+            let edge = x.min(row).min(size - 1 - x).min(size - 1 - row) as f32;
+            color *= 0.75 + 0.25 * (edge / 24.0).min(1.0);
+            let alpha = if edge < 3.0 * grain { 0 } else { 255 };
+
+            pixels.extend(color.iter().map(|c| (c.clamp(0.0, 1.0) * 255.0) as u8));
+            pixels.push(alpha);
+        }
+    }
+    pixels
+}
+
+/// Draws the map for the chest at `chest`
+pub(super) fn render_map_view_texture(
+    map: &PerlinMap,
+    water_level: f32,
+    chest: nalgebra_glm::Vec2,
+) -> Texture {
+    let corner = map_window(chest);
+    let tiles_per_pixel = VIEW_WIDTH / TEXTURE_SIZE as f32;
+    // The first row is the top of the texture, which is north, like the minimap
+    let heights: Vec<f32> = (0..TEXTURE_SIZE)
+        .flat_map(|row| (0..TEXTURE_SIZE).map(move |x| (x, row)))
+        .map(|(x, row)| {
+            let offset = nalgebra_glm::vec2(x as f32 + 0.5, (TEXTURE_SIZE - 1 - row) as f32 + 0.5);
+            let pos = corner + offset * tiles_per_pixel;
+            // Off the edge of the island is open sea
+            if map.oob(pos) {
+                water_level - 1.0
+            } else {
+                map.get_z_interpolated(pos)
+            }
+        })
+        .collect();
+    let chest_px = nalgebra_glm::vec2(
+        (chest.x - corner.x) / tiles_per_pixel,
+        TEXTURE_SIZE as f32 - (chest.y - corner.y) / tiles_per_pixel,
+    );
+    let pixels = map_view_pixels(&heights, water_level, chest_px);
+    Texture::from_rgba(TEXTURE_SIZE as i32, TEXTURE_SIZE as i32, &pixels)
+}
+
+/// Draws a treasure map again, once its chest has been moved to `chest`
+pub(super) fn redraw_map_view(world: &World, treasure_map: Entity, chest: nalgebra_glm::Vec2) {
+    let tiles = world.read_resource::<PerlinMapResource>();
+    let water = world.read_resource::<WaterResource>();
+    let texture = render_map_view_texture(&tiles.map, water.level, chest);
+    let map_views = world.read_storage::<MapViewComponent>();
+    let mut quads = world.write_storage::<QuadComponent>();
+    for (map_view, quad) in (&map_views, &mut quads).join() {
+        if map_view.treasure_map == treasure_map {
+            quad.texture = texture.clone();
+        }
+    }
+}
+
+/// Raises a map while M is held. Each time it's raised, the next map whose chest hasn't been found yet is on top. The
+/// mouse is freed while a map is up, so that looking at it doesn't turn the camera.
+#[derive(Default)]
+pub(super) struct MapViewSystem {
+    shown: Option<Entity>, //< The treasure map last raised
+    was_down: bool,
+    raised: f32, //< 0 when put away, 1 when held up
+}
+impl<'a> System<'a> for MapViewSystem {
+    type SystemData = (
+        ReadStorage<'a, MapViewComponent>,
+        ReadStorage<'a, TreasureMapComponent>,
+        WriteStorage<'a, QuadComponent>,
+        WriteStorage<'a, PositionComponent>,
+        Read<'a, TimeResource>,
+        Read<'a, App>,
+        Read<'a, InputMap>,
+        Entities<'a>,
+    );
+
+    fn run(
+        &mut self,
+        (map_views, treasure_maps, mut quads, mut positions, time, app, input, entities): Self::SystemData,
+    ) {
+        let down = input.held(&app, Action::Map);
+        if down && !self.was_down {
+            // Once every chest is found, any map can still be looked at
+            let all: Vec<Entity> = (&treasure_maps, &entities).join().map(|(_, e)| e).collect();
+            let unfound: Vec<Entity> = (&treasure_maps, &entities)
+                .join()
+                .filter(|(map, _)| !map.found)
+                .map(|(_, e)| e)
+                .collect();
+            let choices = if unfound.is_empty() { all } else { unfound };
+            let next = self
+                .shown
+                .and_then(|shown| choices.iter().position(|e| *e == shown))
+                .map_or(0, |i| i + 1);
+            self.shown = choices.get(next % choices.len().max(1)).copied();
+        }
+        self.was_down = down;
+
+        let target = if down && self.shown.is_some() {
+            1.0
+        } else {
+            0.0
+        };
+        let step = time.real_dt / RAISE_SECONDS;
+        let was_raised = self.raised > 0.0;
+        self.raised = (self.raised + (target - self.raised).clamp(-step, step)).clamp(0.0, 1.0);
+        if !was_raised && self.raised > 0.0 {
+            app.release_mouse();
+        } else if was_raised && self.raised == 0.0 {
+            app.capture_mouse();
+        }
+
+        // Slides up from below the bottom of the screen to the middle
+        let eased = self.raised * self.raised * (3.0 - 2.0 * self.raised);
+        let lowered_y = -1.0 - MAP_VIEW_SIZE as f32 / app.screen_height as f32;
+        for (map_view, quad, position) in (&map_views, &mut quads, &mut positions).join() {
+            let shown = Some(map_view.treasure_map) == self.shown && self.raised > 0.0;
+            quad.opacity = if shown { 1.0 } else { 0.0 };
+            position.pos = nalgebra_glm::vec3(0.0, lowered_y * (1.0 - eased), 0.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chests_are_in_the_middle_half_of_their_map() {
+        for chest in [
+            nalgebra_glm::vec2(12.3, 200.7),
+            nalgebra_glm::vec2(350.0, 41.5),
+        ] {
+            let from_corner = (chest - map_window(chest)) / VIEW_WIDTH;
+            assert!((0.25..=0.75).contains(&from_corner.x));
+            assert!((0.25..=0.75).contains(&from_corner.y));
+        }
+    }
+
+    #[test]
+    fn coast_is_inked_and_chest_is_marked() {
+        // Sea on the left half, land on the right
+        let heights: Vec<f32> = (0..TEXTURE_SIZE * TEXTURE_SIZE)
+            .map(|i| {
+                if i % TEXTURE_SIZE < TEXTURE_SIZE / 2 {
+                    -1.0
+                } else {
+                    1.0
+                }
+            })
+            .collect();
+        let chest_px = nalgebra_glm::vec2(192.0, 128.0);
+        let pixels = map_view_pixels(&heights, 0.0, chest_px);
+        let rgb = |x: usize, row: usize| {
+            let i = (x + row * TEXTURE_SIZE) * 4;
+            [pixels[i], pixels[i + 1], pixels[i + 2]]
+        };
+        let coast = rgb(TEXTURE_SIZE / 2 - 1, 64);
+        let land = rgb(TEXTURE_SIZE / 2 + 20, 64);
+        assert!(coast[0] < land[0] / 2);
+        let x = rgb(192, 128);
+        assert!(x[0] > 2 * x[1]);
+    }
+}
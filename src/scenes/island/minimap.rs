@@ -0,0 +1,193 @@
+// The minimap in the corner of the screen. The island is drawn into a texture once, and markers are moved over it.
+
+use std::f32::consts::PI;
+
+use specs::{prelude::*, Component};
+
+use crate::{
+    engine::{
+        objects::Texture,
+        perlin::{MoistureMap, PerlinMap},
+        physics::PositionComponent,
+        text::QuadComponent,
+        water::WaterResource,
+    },
+    App,
+};
+
+use super::{biome::Biome, PlayerComponent, TreasureMapComponent, MAP_WIDTH, UNIT_PER_METER};
+
+pub(super) const MINIMAP_SIZE: i32 = 192; //< Width and height on screen, in pixels
+pub(super) const ARROW_SIZE: i32 = 16;
+const MARGIN: f32 = 16.0; //< Gap between the minimap and the corner of the screen, in pixels
+pub(super) const HINT_RADIUS: f32 = 300.0 * UNIT_PER_METER; //< How rough the trader's hints are
+
+#[derive(Component)]
+#[storage(HashMapStorage)]
+pub(super) struct MinimapComponent {}
+
+#[derive(Clone, Copy)]
+pub(super) enum MinimapMarker {
+    Player,
+    Treasure(Entity), //< The treasure map entity. Only shown once the map has been found.
+    Hint(Entity), //< The treasure map entity. Only shown once a hint has been bought, until the map is found.
+}
+
+#[derive(Component)]
+#[storage(HashMapStorage)]
+pub(super) struct MinimapMarkerComponent {
+    pub marker: MinimapMarker,
+}
+
+/// Draws the island from above: biome colors, shaded by slope, with water around it
+pub(super) fn render_minimap_texture(
+    map: &PerlinMap,
+    moisture: &MoistureMap,
+    water: &WaterResource,
+) -> Texture {
+    let light = nalgebra_glm::vec3(-1.0, 1.0, 2.0).normalize();
+    let mut pixels = Vec::with_capacity(MAP_WIDTH * MAP_WIDTH * 4);
+    // The first row is the top of the texture, which is north
+    for row in 0..MAP_WIDTH {
+        for x in 0..MAP_WIDTH {
+            let pos = nalgebra_glm::vec2(x as f32, (MAP_WIDTH - 1 - row) as f32);
+            let height = map.height(pos);
+            let ground = nalgebra_glm::vec3(pos.x, pos.y, height);
+            let color = if water.is_underwater(ground) {
+                // Deeper water is darker
+                let depth = water.depth(ground).clamp(0.0, 0.5) * 2.0;
+                nalgebra_glm::lerp(
+                    &nalgebra_glm::vec3(0.3, 0.6, 0.75),
+                    &nalgebra_glm::vec3(0.05, 0.2, 0.35),
+                    depth,
+                )
+            } else {
+                let normal = map.get_normal(pos);
+                let biome = Biome::classify(height, normal.z.abs(), moisture.get(pos), false);
+                let shade = 0.6 + 0.4 * normal.dot(&light).max(0.0);
+                nalgebra_glm::make_vec3(&biome.config().color) * shade
+            };
+            pixels.extend([
+                (color.x * 255.0) as u8,
+                (color.y * 255.0) as u8,
+                (color.z * 255.0) as u8,
+                220,
+            ]);
+        }
+    }
+    Texture::from_rgba(MAP_WIDTH as i32, MAP_WIDTH as i32, &pixels)
+}
+
+/// A small white arrow pointing up, with a clear border so that it can be rotated
+pub(super) fn render_arrow_texture() -> Texture {
+    let mut pixels = Vec::with_capacity((ARROW_SIZE * ARROW_SIZE * 4) as usize);
+    for row in 0..ARROW_SIZE {
+        for x in 0..ARROW_SIZE {
+            // [-1, 1], with +y up
+            let px = (x as f32 + 0.5) / ARROW_SIZE as f32 * 2.0 - 1.0;
+            let py = 1.0 - (row as f32 + 0.5) / ARROW_SIZE as f32 * 2.0;
+            // Tip at the top, widening to the bottom, all inside the circle that stays visible when rotated
+            let t = (0.6 - py) / 1.2;
+            let inside = (0.0..=1.0).contains(&t) && px.abs() <= 0.45 * t;
+            let alpha = if inside { 255 } else { 0 };
+            pixels.extend([255, 255, 255, alpha]);
+        }
+    }
+    Texture::from_rgba(ARROW_SIZE, ARROW_SIZE, &pixels)
+}
+
+/// How wide a hint circle is on the minimap, in pixels
+pub(super) fn hint_size() -> i32 {
+    (2.0 * HINT_RADIUS / MAP_WIDTH as f32 * MINIMAP_SIZE as f32) as i32
+}
+
+/// A thin white ring, touching the edges of the texture
+pub(super) fn render_ring_texture(size: i32) -> Texture {
+    const THICKNESS: f32 = 2.0; //< In pixels
+    let mut pixels = Vec::with_capacity((size * size * 4) as usize);
+    let radius = size as f32 / 2.0;
+    for row in 0..size {
+        for x in 0..size {
+            let from_center =
+                nalgebra_glm::vec2(x as f32 + 0.5 - radius, row as f32 + 0.5 - radius);
+            let dist = nalgebra_glm::length(&from_center);
+            let alpha = if (radius - THICKNESS..=radius).contains(&dist) {
+                255
+            } else {
+                0
+            };
+            pixels.extend([255, 255, 255, alpha]);
+        }
+    }
+    Texture::from_rgba(size, size, &pixels)
+}
+
+/// Keeps the minimap in the bottom right corner, and moves the markers over it
+pub(super) struct MinimapSystem;
+impl<'a> System<'a> for MinimapSystem {
+    type SystemData = (
+        ReadStorage<'a, MinimapComponent>,
+        ReadStorage<'a, MinimapMarkerComponent>,
+        ReadStorage<'a, PlayerComponent>,
+        ReadStorage<'a, TreasureMapComponent>,
+        WriteStorage<'a, PositionComponent>,
+        WriteStorage<'a, QuadComponent>,
+        Read<'a, App>,
+        Entities<'a>,
+    );
+
+    fn run(
+        &mut self,
+        (minimaps, markers, players, treasure_maps, mut positions, mut quads, app, entities): Self::SystemData,
+    ) {
+        let screen = nalgebra_glm::vec2(app.screen_width as f32, app.screen_height as f32);
+        let center_px = (MINIMAP_SIZE as f32 / 2.0 + MARGIN) * 2.0;
+        let center = nalgebra_glm::vec2(1.0 - center_px / screen.x, -1.0 + center_px / screen.y);
+        for (_, position) in (&minimaps, &mut positions).join() {
+            position.pos = nalgebra_glm::vec3(center.x, center.y, 0.0);
+        }
+
+        // Where a spot on the island ends up on screen. Markers are drawn a little closer, to stay on top.
+        let to_screen = |pos: nalgebra_glm::Vec3| {
+            let offset = (pos.xy() / MAP_WIDTH as f32).add_scalar(-0.5) * MINIMAP_SIZE as f32;
+            let on_screen = center + offset.component_div(&screen) * 2.0;
+            nalgebra_glm::vec3(on_screen.x, on_screen.y, 0.1)
+        };
+
+        let (facing, player_pos) = (&players, &positions)
+            .join()
+            .map(|(player, position)| (player.facing, position.pos))
+            .next()
+            .unwrap();
+        let mut updates = vec![];
+        for (marker, entity) in (&markers, &entities).join() {
+            match marker.marker {
+                MinimapMarker::Player => {
+                    // The arrow points north, and facing 0 is east
+                    updates.push((entity, to_screen(player_pos), 1.0, facing - PI / 2.0));
+                }
+                MinimapMarker::Treasure(map_entity) => {
+                    let treasure_map = treasure_maps.get(map_entity).unwrap();
+                    let treasure_pos = positions.get(treasure_map.treasure_entity).unwrap().pos;
+                    let opacity = if treasure_map.found { 1.0 } else { 0.0 };
+                    updates.push((entity, to_screen(treasure_pos), opacity, 0.0));
+                }
+                MinimapMarker::Hint(map_entity) => {
+                    let treasure_map = treasure_maps.get(map_entity).unwrap();
+                    let (center, opacity) = match treasure_map.hint {
+                        Some(center) if !treasure_map.found => (center, 0.8),
+                        _ => (nalgebra_glm::zero(), 0.0),
+                    };
+                    let pos = nalgebra_glm::vec3(center.x, center.y, 0.0);
+                    updates.push((entity, to_screen(pos), opacity, 0.0));
+                }
+            }
+        }
+        for (entity, pos, opacity, rotation) in updates {
+            positions.get_mut(entity).unwrap().pos = pos;
+            let quad = quads.get_mut(entity).unwrap();
+            quad.opacity = opacity;
+            quad.rotation = rotation;
+        }
+    }
+}
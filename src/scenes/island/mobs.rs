@@ -0,0 +1,162 @@
+// The kinds of mobs. Each kind looks different, takes a different number of hits, hurts the player by a different
+// amount, and moves in its own way through its `AiParams`. Some have a weak point, where shots land critical hits.
+
+use serde::{Deserialize, Serialize};
+
+use crate::engine::render3d::RenderLayer;
+
+use super::{ai::AiParams, prefabs::PrefabTexture};
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub(super) enum MobKind {
+    Ghost,
+    Crab,     //< Scuttles along beaches, and into the shallows
+    Skeleton, //< Guards the treasure camps, and never runs away
+    Bird,     //< Circles high up, and swoops down to attack
+}
+
+/// The part of a mob, from some way up its body to the top, where shots do extra damage
+pub(super) struct WeakPoint {
+    pub above: f32,      //< Fraction of the mob's height the weak point starts at
+    pub multiplier: f32, //< How many times the usual damage a critical hit does
+}
+
+/// How a kind of mob looks, and how it fares in a fight
+pub(super) struct MobStats {
+    pub texture: PrefabTexture,
+    pub layer: RenderLayer, //< Transparent for see-through textures
+    pub scale: [f32; 3],    //< Of the mob mesh
+    pub radius: f32,
+    pub height: f32,
+    pub toughness: f32, //< How many times longer than a ghost the mob takes to shoot down
+    pub contact_damage: f32, //< Health the player loses when touched
+    pub weak_to_light: bool, //< Weakened when close to the player's torch
+    pub weak_point: Option<WeakPoint>,
+}
+
+const GHOST: MobStats = MobStats {
+    texture: PrefabTexture::Ghost,
+    layer: RenderLayer::Transparent,
+    scale: [1.0, 1.0, 1.0],
+    radius: 0.05,
+    height: 0.2,
+    toughness: 1.0,
+    contact_damage: 0.2,
+    weak_to_light: true,
+    weak_point: Some(WeakPoint {
+        above: 0.7,
+        multiplier: 2.0,
+    }),
+};
+const CRAB: MobStats = MobStats {
+    texture: PrefabTexture::Chest,
+    layer: RenderLayer::Opaque,
+    scale: [1.6, 1.6, 0.35],
+    radius: 0.05,
+    height: 0.07,
+    toughness: 1.5,
+    contact_damage: 0.15,
+    weak_to_light: false,
+    weak_point: None, //< All shell
+};
+const SKELETON: MobStats = MobStats {
+    texture: PrefabTexture::Bullet,
+    layer: RenderLayer::Opaque,
+    scale: [0.9, 0.9, 1.2],
+    radius: 0.04,
+    height: 0.24,
+    toughness: 2.0,
+    contact_damage: 0.3,
+    weak_to_light: false,
+    weak_point: Some(WeakPoint {
+        above: 0.8,
+        multiplier: 2.5,
+    }),
+};
+const BIRD: MobStats = MobStats {
+    texture: PrefabTexture::Earth,
+    layer: RenderLayer::Opaque,
+    scale: [1.0, 1.0, 0.3],
+    radius: 0.04,
+    height: 0.06,
+    toughness: 0.5,
+    contact_damage: 0.1,
+    weak_to_light: false,
+    weak_point: None, //< Too small to aim for any one part
+};
+
+impl MobStats {
+    /// How many times the usual damage a shot does, landing `height` above the mob's feet
+    pub fn critical_multiplier(&self, height: f32) -> Option<f32> {
+        let weak_point = self.weak_point.as_ref()?;
+        (height >= weak_point.above * self.height).then_some(weak_point.multiplier)
+    }
+}
+
+impl MobKind {
+    pub fn stats(&self) -> &'static MobStats {
+        match self {
+            MobKind::Ghost => &GHOST,
+            MobKind::Crab => &CRAB,
+            MobKind::Skeleton => &SKELETON,
+            MobKind::Bird => &BIRD,
+        }
+    }
+
+    pub fn ai_params(&self) -> AiParams {
+        match self {
+            MobKind::Ghost => AiParams::default(),
+            MobKind::Crab => AiParams {
+                wander_speed: 2.0,
+                chase_speed: 9.0,
+                attack_speed: 14.0,
+                idle_seconds: 4.0,
+                wander_seconds: 2.0,
+                flee_health: 0.0,
+                max_water_depth: 0.1,
+                fears_light: false,
+                ..AiParams::default()
+            },
+            MobKind::Skeleton => AiParams {
+                wander_speed: 3.0,
+                chase_speed: 10.0,
+                attack_speed: 14.0,
+                idle_seconds: 6.0,
+                wander_seconds: 2.0,
+                attack_range: 0.6,
+                flee_health: 0.0,
+                fears_light: false,
+                ..AiParams::default()
+            },
+            MobKind::Bird => AiParams {
+                wander_speed: 8.0,
+                chase_speed: 14.0,
+                attack_speed: 18.0,
+                idle_seconds: 0.0,
+                wander_seconds: 20.0,
+                flee_health: 0.5,
+                max_water_depth: f32::INFINITY,
+                turn_rate: 0.6,
+                fly_height: 4.0,
+                fears_light: false,
+                ..AiParams::default()
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_shots_to_the_weak_point_are_critical() {
+        let skeleton = MobKind::Skeleton.stats();
+        assert_eq!(skeleton.critical_multiplier(0.1 * skeleton.height), None);
+        assert_eq!(
+            skeleton.critical_multiplier(0.9 * skeleton.height),
+            Some(2.5)
+        );
+        assert_eq!(MobKind::Crab.stats().critical_multiplier(1.0), None);
+    }
+}
@@ -0,0 +1,2801 @@
+mod ai;
+mod animations;
+mod biome;
+mod boundary;
+mod castaway;
+mod chunks;
+mod cinematic;
+mod coins;
+mod collisions;
+mod compass;
+mod damage;
+mod diagnostics;
+mod goal;
+mod golden;
+mod health_bars;
+mod hits;
+mod indicators;
+mod interaction;
+mod inventory;
+mod journal;
+mod loot;
+mod map_view;
+mod minimap;
+mod mobs;
+mod navigation;
+mod parrot;
+mod perception;
+mod persistence;
+mod prefabs;
+mod range;
+mod scatter;
+mod simulation;
+mod sites;
+mod sonar;
+mod spawner;
+mod splat;
+mod stamina;
+mod stats;
+mod status;
+mod summary;
+mod swimming;
+mod terrain_overlay;
+mod tools;
+mod torch;
+mod view_model;
+mod weapons;
+mod weather;
+mod worldgen;
+
+use std::{f32::consts::PI, ffi::CString, sync::Arc};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use sdl2::{
+    controller::{Axis, Button},
+    keyboard::Scancode,
+    pixels::Color,
+    ttf::Font,
+};
+use serde::{Deserialize, Serialize};
+use specs::{
+    prelude::*,
+    shrev::{EventChannel, ReaderId},
+    Component, Join, ReadStorage,
+};
+
+use crate::{
+    engine::{
+        animation::{AnimationComponent, AnimationSystem},
+        audio::{AudioManager, AudioResource, SoundVariation},
+        camera::{Camera, ProjectionKind},
+        collision::Shape,
+        error::EngineError,
+        grass::{GrassRenderSystem, GrassResource, GRASS_UNIFORMS},
+        input::{Action, InputMap},
+        interpolation::{
+            interpolate_camera, restore_camera, PreviousPositionComponent, RecordPreviousSystem,
+        },
+        objects::{create_program, Texture},
+        particles::{
+            spawn_emitter, EmitterPreset, ParticleEmitterComponent, ParticleRenderSystem,
+            ParticleResource, ParticleSystem, PARTICLE_UNIFORMS,
+        },
+        perlin::{Bulge, MoistureMap, NoiseKind, PerlinMap, PerlinMapResource},
+        physics::{ParentComponent, ParentSystem, PositionComponent, VelocityComponent},
+        profiling::{ProfileResource, TimedDispatch},
+        registry::ComponentRegistry,
+        render3d::{
+            LightComponent, Mesh, MeshComponent, MeshMgr, MeshMgrResource, OpenGlResource,
+            PointLightSystem, Render3dSystem, RenderLayer, TerrainComponent, TintComponent,
+            ViewModelComponent, ViewModelRenderSystem, LIT_UNIFORMS, MESH_UNIFORMS,
+        },
+        settings::{GameplaySettings, GraphicsSettings, QualityPreset, Settings, ViewSettings},
+        shadow_map::{CastsShadowComponent, ShadowSystem, SunResource},
+        sky::{create_sky_mesh, SkyRenderSystem, SkyResource, SKY_UNIFORMS},
+        snapshot::{serialize_entity, WorldSnapshot},
+        text::{
+            initialize_gui, Align, Anchor, FontMgr, GlyphCache, LayoutComponent, QuadComponent,
+            TextComponent, TextSystem, UIResource, UiLayer,
+        },
+        time::{accel_per_tick, per_tick, TimeResource, TICK_SECONDS},
+        ui_nav::initialize_ui_navigation,
+        update_lod::lod_dt,
+        water::{create_water_mesh, WaterRenderSystem, WaterResource, SEA_LEVEL, WATER_UNIFORMS},
+    },
+    scenes::{
+        journal::JournalScene, loading::LoadingScene, options::OptionsScene, summary::SummaryScene,
+    },
+    App, Scene, SceneCommand,
+};
+use ai::{AiComponent, AiSystem};
+use animations::MobAnimationSystem;
+use biome::Biome;
+use boundary::BoundarySystem;
+use castaway::{CastawayComponent, CastawaySystem};
+use chunks::{ChunkResource, ChunkStreamingSystem};
+use cinematic::{
+    intro_path, CinematicResource, CinematicSystem, LetterboxComponent, LetterboxSystem,
+};
+use coins::{CoinComponent, CoinSystem, CHEST_COINS};
+use collisions::{CollisionDetectionSystem, CollisionEvent, CollisionResponseSystem};
+use compass::{CompassMarkComponent, CompassSystem};
+use damage::{
+    ContactDamageSystem, DamageFlashComponent, DamageFlashSystem, DamageIndicatorComponent,
+    DamageIndicatorSystem,
+};
+use diagnostics::{DiagnosticsRenderSystem, DiagnosticsResource, DiagnosticsSystem};
+use goal::{GoalResource, GoalSystem, TreasureCounterComponent, TreasureCounterSystem};
+pub(crate) use golden::run_golden_tests;
+use health_bars::{
+    bar_texture, HealthBarSystem, MobHealthBarComponent, PlayerHealthBarComponent,
+    PLAYER_BAR_HEIGHT, PLAYER_BAR_MARGIN, PLAYER_BAR_WIDTH,
+};
+use hits::{
+    DamageSystem, HitMarkerComponent, HitMarkerSystem, KnockbackSystem, ObstacleSystem, SfxSystem,
+};
+use indicators::{ChestIndicatorComponent, ChestIndicatorSystem};
+use interaction::{
+    interact_reader, InteractAction, InteractEvent, InteractPromptComponent, InteractableComponent,
+    InteractionSystem,
+};
+use inventory::{
+    slot_layout, HotbarSlotComponent, HotbarSystem, InventoryComponent, InventorySystem, Item,
+    HOTBAR_SLOTS,
+};
+use journal::{take_photos, JournalResource};
+pub(crate) use journal::{Journal, JOURNAL_PATH};
+use loot::{roll_loot, LootComponent, LootSystem};
+use map_view::{render_map_view_texture, MapViewComponent, MapViewSystem};
+use minimap::{
+    render_minimap_texture, MinimapComponent, MinimapMarker, MinimapMarkerComponent, MinimapSystem,
+};
+use mobs::MobKind;
+use navigation::NavGridResource;
+use parrot::{ParrotComponent, ParrotResource, ParrotSystem};
+use perception::{NoiseResource, PerceptionComponent, PerceptionSystem, GUNSHOT_NOISE_RADIUS};
+pub(crate) use persistence::SaveGame;
+use persistence::{PersistentIdComponent, PersistentIdResource, SAVE_PATH};
+use prefabs::{
+    spawn_bush, spawn_castaway, spawn_chest_indicator, spawn_chest_lid, spawn_coin, spawn_compass,
+    spawn_damage_indicator, spawn_interact_prompt, spawn_loot, spawn_map_view, spawn_minimap,
+    spawn_minimap_marker, spawn_mob, spawn_player, spawn_stamina_bar, spawn_swimming_hud,
+    spawn_target, spawn_trader, spawn_treasure, spawn_treasure_counter, spawn_treasure_map,
+    spawn_tree, spawn_view_models, spawn_wall_bush, PrefabResource, PrefabTexture,
+};
+use range::{TargetComponent, TargetSystem, TARGET_SCALE};
+use scatter::{GrassScatterSystem, GRASS_DISTANCE};
+pub(crate) use simulation::run_simulation_tests;
+use sites::{dress_treasure_sites, AmbientSoundComponent, AmbientSoundSystem, SitePropComponent};
+use sonar::SonarSystem;
+use spawner::SpawnerSystem;
+use splat::terrain_textures_resource;
+use stamina::{
+    StaminaBarComponent, StaminaBarSystem, StaminaComponent, StaminaSystem, JUMP_COST,
+    WINDED_SWIM_SPEED,
+};
+pub(crate) use stats::GameSummary;
+use stats::{StatsResource, StatsSystem};
+use status::{StatusComponent, StatusSystem};
+pub(crate) use summary::WorldSummaryResource;
+use swimming::{
+    OxygenMeterComponent, SwimmerComponent, SwimmingHudSystem, SwimmingSystem,
+    UnderwaterTintComponent,
+};
+use terrain_overlay::{terrain_overlay_resource, TerrainOverlaySystem};
+use tools::{
+    BlockingComponent, ChestComponent, MacheteSystem, Tool, TraderComponent, TraderSystem,
+};
+use torch::{LightResource, TorchFlameComponent, TorchSystem, TORCH_SHOT_PERIOD_SCALE};
+use view_model::{HeldWeaponComponent, ViewModelSystem};
+use weapons::{
+    AmmoReadoutComponent, AmmoReadoutSystem, ReloadSystem, WeaponComponent, MELEE_DAMAGE,
+    MELEE_RADIUS, MELEE_RANGE, MELEE_SECONDS, MELEE_SOUND, MELEE_VOLUME, READOUT_MARGIN,
+};
+use weather::{WeatherResource, WeatherSystem};
+pub(crate) use worldgen::{generate_terrain, GeneratedTerrain, IslandLayout};
+
+pub const MAP_WIDTH: usize = 400;
+const CHUNK_SIZE: usize = 64;
+const HUD_FONT: usize = 0; //< Index of the HUD font in the text system's fonts
+const PROFILE_PATH: &str = "profile.txt"; //< Where F6 dumps how long systems have been taking
+const UNIT_PER_METER: f32 = 0.05;
+const PERSON_HEIGHT: f32 = 1.6764 * UNIT_PER_METER;
+const CROUCH_HEIGHT: f32 = 1.0 * UNIT_PER_METER;
+const GRAVITY: f32 = 19.53125 * UNIT_PER_METER; //< Meters per second squared
+const JUMP_SPEED: f32 = 6.25 * UNIT_PER_METER; //< Meters per second, straight up
+const SWIM_UP_ACCEL: f32 = 3.90625 * UNIT_PER_METER; //< Meters per second squared, while holding jump underwater
+const SWIM_UP_MAX_SPEED: f32 = 125.0 * UNIT_PER_METER; //< Meters per second
+const PITCH_MARGIN: f32 = 0.01; //< Radians the camera is kept from looking straight up or down
+const FOG_START: f32 = 0.6; //< Of the view distance, where the haze over the edge of the world starts
+
+pub const QUAD_DATA: &[u8] = include_bytes!("../../../res/quad.obj");
+pub const CONE_DATA: &[u8] = include_bytes!("../../../res/cone.obj");
+pub const BUSH_DATA: &[u8] = include_bytes!("../../../res/bush.obj");
+pub const CUBE_DATA: &[u8] = include_bytes!("../../../res/cube.obj");
+pub const MOB_DATA: &[u8] = include_bytes!("../../../res/mob.obj");
+pub const CHEST_DATA: &[u8] = include_bytes!("../../../res/chest.obj");
+
+// Default pitch/volume jitter for sounds that get played over and over
+const SOUND_VARIATIONS: &[(&str, SoundVariation)] = &[
+    (
+        "res/walk.ogg",
+        SoundVariation {
+            pitch_jitter: 0.12,
+            volume_jitter: 0.3,
+        },
+    ),
+    (
+        "res/pop.ogg",
+        SoundVariation {
+            pitch_jitter: 0.06,
+            volume_jitter: 0.1,
+        },
+    ),
+    (
+        "res/hit.ogg",
+        SoundVariation {
+            pitch_jitter: 0.1,
+            volume_jitter: 0.15,
+        },
+    ),
+    (
+        "res/ground.ogg",
+        SoundVariation {
+            pitch_jitter: 0.15,
+            volume_jitter: 0.2,
+        },
+    ),
+    (
+        "res/jump.ogg",
+        SoundVariation {
+            pitch_jitter: 0.05,
+            volume_jitter: 0.1,
+        },
+    ),
+];
+
+// Dirt kicked up where a bullet hits the ground
+const BULLET_IMPACT_PARTICLES: EmitterPreset = EmitterPreset {
+    burst: 10,
+    rate: 0.0,
+    emit_seconds: 0.0,
+    lifetime: 0.48,
+    speed: 2.5 * UNIT_PER_METER,
+    direction: [0.0, 0.0, 1.0],
+    spread: 0.6,
+    gravity: 19.5 * UNIT_PER_METER,
+    drag: 3.2,
+    start_size: 0.15 * UNIT_PER_METER,
+    end_size: 0.05 * UNIT_PER_METER,
+    start_color: [0.45, 0.36, 0.25, 1.0],
+    end_color: [0.45, 0.36, 0.25, 0.0],
+};
+
+// Spray thrown up where a bullet hits the water
+const WATER_SPLASH_PARTICLES: EmitterPreset = EmitterPreset {
+    burst: 14,
+    rate: 0.0,
+    emit_seconds: 0.0,
+    lifetime: 0.6,
+    speed: 3.5 * UNIT_PER_METER,
+    direction: [0.0, 0.0, 1.0],
+    spread: 0.3,
+    gravity: 19.5 * UNIT_PER_METER,
+    drag: 1.5,
+    start_size: 0.12 * UNIT_PER_METER,
+    end_size: 0.2 * UNIT_PER_METER,
+    start_color: [0.85, 0.92, 1.0, 0.9],
+    end_color: [0.85, 0.92, 1.0, 0.0],
+};
+
+// A short glowing streak left behind a bullet, for as long as it flies
+const BULLET_TRACER_PARTICLES: EmitterPreset = EmitterPreset {
+    burst: 0,
+    rate: 250.0,
+    emit_seconds: f32::MAX,
+    lifetime: 0.05,
+    speed: 0.0,
+    direction: [0.0, 0.0, 1.0],
+    spread: 0.0,
+    gravity: 0.0,
+    drag: 0.0,
+    start_size: 0.08 * UNIT_PER_METER,
+    end_size: 0.02 * UNIT_PER_METER,
+    start_color: [1.0, 0.85, 0.5, 0.9],
+    end_color: [1.0, 0.6, 0.3, 0.0],
+};
+
+// Bubbles streaming up behind a bullet underwater
+const BUBBLE_TRAIL_PARTICLES: EmitterPreset = EmitterPreset {
+    burst: 0,
+    rate: 40.0,
+    emit_seconds: f32::MAX,
+    lifetime: 0.9,
+    speed: 0.3 * UNIT_PER_METER,
+    direction: [0.0, 0.0, 1.0],
+    spread: 0.5,
+    gravity: -1.5 * UNIT_PER_METER,
+    drag: 2.0,
+    start_size: 0.04 * UNIT_PER_METER,
+    end_size: 0.08 * UNIT_PER_METER,
+    start_color: [0.85, 0.95, 1.0, 0.7],
+    end_color: [0.85, 0.95, 1.0, 0.0],
+};
+
+const PROJECTILE_SECONDS: f32 = 2.0; //< Bullets that haven't hit anything by now are gone, about 150m out
+const WATER_REFRACTIVE_INDEX: f32 = 1.33; //< Bullets going into the water bend towards straight down by this much
+const UNDERWATER_DRAG: f32 = 12.0; //< Per second, how quickly bullets slow down underwater
+const UNDERWATER_RANGE: f32 = 3.0 * UNIT_PER_METER; //< Bullets are spent after going this far underwater
+const KILL_HIT_STOP_SECONDS: f32 = 0.05; //< The game holds still for a moment when a mob dies
+const DEATH_KNOCKBACK: f32 = 5.0 * UNIT_PER_METER; //< Meters per second a defeated mob is thrown away from the player
+const DEATH_LIFT: f32 = 4.0 * UNIT_PER_METER; //< Meters per second a defeated mob is thrown up
+const TUMBLE_SECONDS: f32 = 1.0; //< How long a defeated mob tumbles over before lying still
+const TUMBLE_SPIN: f32 = std::f32::consts::PI; //< Radians per second a defeated mob starts flopping over at
+
+// Puff of smoke when a mob dies
+const MOB_DEATH_PARTICLES: EmitterPreset = EmitterPreset {
+    burst: 24,
+    rate: 0.0,
+    emit_seconds: 0.0,
+    lifetime: 0.72,
+    speed: 1.9 * UNIT_PER_METER,
+    direction: [0.0, 0.0, 1.0],
+    spread: 1.0,
+    gravity: 0.0,
+    drag: 6.6,
+    start_size: 0.3 * UNIT_PER_METER,
+    end_size: 0.8 * UNIT_PER_METER,
+    start_color: [0.9, 0.9, 0.9, 0.8],
+    end_color: [0.7, 0.7, 0.7, 0.0],
+};
+
+// Sand kicked up by each step on the beach
+const SAND_FOOTSTEP_PARTICLES: EmitterPreset = EmitterPreset {
+    burst: 5,
+    rate: 0.0,
+    emit_seconds: 0.0,
+    lifetime: 0.32,
+    speed: 1.25 * UNIT_PER_METER,
+    direction: [0.0, 0.0, 1.0],
+    spread: 0.8,
+    gravity: 19.5 * UNIT_PER_METER,
+    drag: 6.6,
+    start_size: 0.08 * UNIT_PER_METER,
+    end_size: 0.04 * UNIT_PER_METER,
+    start_color: [0.86, 0.74, 0.62, 1.0],
+    end_color: [0.86, 0.74, 0.62, 0.0],
+};
+
+/*
+ * COMPONENTS
+ */
+#[derive(Component, Serialize, Deserialize)]
+#[storage(HashMapStorage)]
+struct PlayerComponent {
+    // Status
+    feet_on_ground: bool,
+    crouching: bool, //< Slower, but harder for mobs to see and hear
+    sprinting: bool,
+    torch_raised: bool, //< Held up in the off hand, which slows the gun down
+
+    // View variables
+    facing: f32,
+    pitch: f32,
+
+    // Animations and timing
+    t_last_shot: f32, //< Seconds of game time
+    t_last_walk_played: f32,
+    invulnerable_for: f32, //< Seconds until mobs can hurt the player again
+}
+
+#[derive(Component, Serialize)]
+#[storage(VecStorage)]
+struct TreasureMapComponent {
+    #[serde(serialize_with = "serialize_entity")]
+    treasure_entity: Entity,
+    found: bool,
+    hint: Option<nalgebra_glm::Vec2>, //< Center of a circle on the minimap that the treasure is somewhere in
+}
+
+#[derive(Component, Serialize, Deserialize)]
+#[storage(VecStorage)]
+struct MobComponent {
+    kind: MobKind,
+}
+
+#[derive(Component, Serialize, Deserialize)]
+#[storage(VecStorage)]
+struct ProjectileComponent {
+    age: f32,               //< Seconds since it was fired
+    lifetime: f32,          //< Seconds before it's gone, if it hasn't hit anything
+    damage: f32,            //< Health it takes from a ghost, tougher things lose less
+    underwater_travel: f32, //< World units it has gone underwater
+}
+
+#[derive(Component, Serialize, Deserialize)]
+#[storage(VecStorage)]
+struct ColliderComponent {
+    shape: Shape,
+}
+
+#[derive(Component, Serialize, Deserialize)]
+#[storage(VecStorage)]
+struct HealthComponent {
+    health: f32, // 1.0 is full health, 0.0 is dead
+}
+
+/// A defeated mob, tumbling over, then lying there for a while, then squashing away
+#[derive(Component, Default, Serialize, Deserialize)]
+#[storage(VecStorage)]
+struct DeathSplishAnimComponent {
+    timeline: f32, // 0.0 is just starting 1.0 is end
+    #[serde(default)]
+    seconds_dead: f32, //< The splish starts once the mob is done tumbling and has lain there long enough
+    #[serde(default)]
+    spin: nalgebra_glm::Vec3, //< Radians per second about x, y and z the mob flops over at, slowing as it lands
+}
+
+#[derive(Component)]
+#[storage(HashMapStorage)]
+struct DebugHudComponent {}
+
+#[derive(Component)]
+#[storage(HashMapStorage)]
+struct DialogComponent {}
+
+#[derive(Component)]
+#[storage(HashMapStorage)]
+struct ToastComponent {}
+
+/// The gameplay components, under the names snapshots and saves know them by. Don't rename them, or old saves and
+/// snapshots won't line up.
+fn component_registry() -> ComponentRegistry {
+    ComponentRegistry::new()
+        .with::<PositionComponent>("Position")
+        .with::<VelocityComponent>("Velocity")
+        .with::<PlayerComponent>("Player")
+        .with_write_only::<TreasureMapComponent>("TreasureMap")
+        .with::<MobComponent>("Mob")
+        .with::<AiComponent>("Ai")
+        .with::<ProjectileComponent>("Projectile")
+        .with::<ColliderComponent>("Collider")
+        .with::<TargetComponent>("Target")
+        .with::<HealthComponent>("Health")
+        .with::<DeathSplishAnimComponent>("DeathSplishAnim")
+        .with::<CastawayComponent>("Castaway")
+        .with::<ChestComponent>("Chest")
+        .with::<BlockingComponent>("Blocking")
+        .with::<SitePropComponent>("SiteProp")
+        .with_write_only::<AmbientSoundComponent>("AmbientSound")
+        .with::<TraderComponent>("Trader")
+        .with::<PersistentIdComponent>("PersistentId")
+        .with::<InventoryComponent>("Inventory")
+        .with::<WeaponComponent>("Weapon")
+        .with::<PerceptionComponent>("Perception")
+        .with::<StatusComponent>("Status")
+        .with::<SwimmerComponent>("Swimmer")
+        .with::<StaminaComponent>("Stamina")
+}
+
+/*
+ * RESOURCES
+ */
+#[derive(Default, Clone, Copy)]
+struct SeedResource {
+    seed: u64,
+    layout: IslandLayout,
+    noise: NoiseKind,
+}
+
+#[derive(Default)]
+struct GoldResource {
+    gold: u32,
+}
+
+/// A line of dialog shown at the bottom of the screen
+#[derive(Default)]
+struct DialogResource {
+    text: String,
+    seconds_left: f32, //< The line is hidden once this runs out
+}
+
+impl DialogResource {
+    fn say(&mut self, text: &str) {
+        const SECONDS_PER_LINE: f32 = 4.0;
+        self.text = text.to_string();
+        self.seconds_left = SECONDS_PER_LINE;
+    }
+}
+
+/// A short announcement shown near the top of the screen, that fades in and out
+#[derive(Default)]
+struct ToastResource {
+    text: String,
+    seconds_left: f32,
+}
+
+impl ToastResource {
+    const SECONDS_PER_TOAST: f32 = 3.0;
+
+    fn show(&mut self, text: &str) {
+        self.text = text.to_string();
+        self.seconds_left = Self::SECONDS_PER_TOAST;
+    }
+}
+
+/*
+ * SYSTEMS
+ */
+struct SkySystem;
+impl<'a> System<'a> for SkySystem {
+    type SystemData = (
+        Read<'a, App>,
+        Read<'a, OpenGlResource>,
+        Write<'a, SunResource>,
+        Write<'a, SkyResource>,
+        Write<'a, WaterResource>,
+        Read<'a, WeatherResource>,
+        Read<'a, TimeResource>,
+        Read<'a, GraphicsSettings>,
+    );
+    fn run(
+        &mut self,
+        (app, open_gl, mut sun, mut sky, mut water, weather, time, settings): Self::SystemData,
+    ) {
+        let model_t = model_time(time.elapsed);
+        let day_color = nalgebra_glm::vec3(172.0, 205.0, 248.0);
+        let night_color = nalgebra_glm::vec3(5.0, 6.0, 7.0);
+        let red_color = nalgebra_glm::vec3(124.0, 102.0, 86.0);
+        let day_zenith_color = nalgebra_glm::vec3(80.0, 130.0, 210.0);
+        let night_zenith_color = nalgebra_glm::vec3(1.0, 2.0, 4.0);
+        let (do_color, zenith_color) = if model_t.cos() > 0.0 {
+            (day_color, day_zenith_color)
+        } else {
+            (night_color, night_zenith_color)
+        };
+        // Sunsets only redden the horizon
+        let dnf = model_t.sin().powf(100.0);
+        let sky_color = (dnf * red_color + (1.0 - dnf) * do_color) / 255.0;
+        let sun_dir = nalgebra_glm::vec3(0.0, model_t.sin(), model_t.cos());
+        // The moon rises opposite the sun, on a slightly tilted path
+        let moon_dir =
+            nalgebra_glm::normalize(&nalgebra_glm::vec3(0.3, -model_t.sin(), -model_t.cos()));
+        let moon_light = nalgebra_glm::vec3(0.35, 0.4, 0.55)
+            * moon_phase(model_t)
+            * (4.0 * moon_dir.z).clamp(0.0, 1.0);
+
+        // Underwater, everything fades into murky water instead of sky
+        let underwater = water.is_underwater(open_gl.camera.position);
+        if underwater {
+            let daylight = (4.0 * sun_dir.z).clamp(0.1, 1.0);
+            sky.horizon_color = water.fog_color * daylight;
+            sky.zenith_color = sky.horizon_color;
+            sky.sun_color = nalgebra_glm::vec3(0.0, 0.0, 0.0);
+            sky.moon_color = nalgebra_glm::vec3(0.0, 0.0, 0.0);
+            sky.star_brightness = 0.0;
+        } else {
+            sky.horizon_color = sky_color;
+            sky.zenith_color = (1.0 - dnf) * zenith_color / 255.0 + dnf * sky_color;
+            sky.sun_color = nalgebra_glm::vec3(1.0, 0.95, 0.85) * (8.0 * sun_dir.z).clamp(0.0, 1.0);
+            sky.moon_color = moon_light;
+            sky.star_brightness = (-4.0 * sun_dir.z).clamp(0.0, 1.0);
+
+            // Fog washes the whole sky out to grey, and hides the sun and stars
+            let daylight = (4.0 * sun_dir.z).clamp(0.1, 1.0);
+            let fog_grey = nalgebra_glm::vec3(0.7, 0.72, 0.75) * daylight;
+            sky.horizon_color = nalgebra_glm::lerp(&sky.horizon_color, &fog_grey, weather.fog);
+            sky.zenith_color = nalgebra_glm::lerp(&sky.zenith_color, &fog_grey, weather.fog);
+            sky.sun_color *= 1.0 - 0.8 * weather.fog;
+            sky.moon_color *= 1.0 - weather.fog;
+            sky.star_brightness *= 1.0 - weather.fog;
+        }
+        sky.sun_dir = sun_dir;
+        sky.moon_dir = moon_dir;
+        sky.star_rotation = model_t;
+        let fog_color = sky.horizon_color;
+        let fog_density = if underwater {
+            water.fog_density
+        } else {
+            weather.fog_density()
+        };
+        sky.fog_density = fog_density;
+
+        Mesh::set_3d(
+            &open_gl.program,
+            sun_dir,
+            nalgebra_glm::vec2(app.screen_width as f32, app.screen_height as f32),
+        );
+        let u_fog_color = open_gl.program.uniform("u_fog_color");
+        let u_fog_density = open_gl.program.uniform("u_fog_density");
+        let u_fog_start = open_gl.program.uniform("u_fog_start");
+        let u_fog_end = open_gl.program.uniform("u_fog_end");
+        let u_water_level = open_gl.program.uniform("u_water_level");
+        let u_caustics_strength = open_gl.program.uniform("u_caustics_strength");
+        let u_time = open_gl.program.uniform("u_time");
+        let u_moon_dir = open_gl.program.uniform("u_moon_dir");
+        let u_moon_color = open_gl.program.uniform("u_moon_color");
+        // Fog dims the moonlight too, but it still lights the ground underwater
+        let moon_light = moon_light * (1.0 - 0.5 * weather.fog);
+        // Caustics need direct sunlight, so they fade out as the sun sets
+        let caustics_strength = (4.0 * sun_dir.z).clamp(0.0, 1.0);
+        unsafe {
+            gl::Uniform3f(u_fog_color.id, fog_color.x, fog_color.y, fog_color.z);
+            gl::Uniform1f(u_fog_density.id, fog_density);
+            gl::Uniform1f(u_fog_start.id, FOG_START * settings.view_distance);
+            gl::Uniform1f(u_fog_end.id, settings.view_distance);
+            gl::Uniform1f(u_water_level.id, water.level);
+            gl::Uniform1f(u_caustics_strength.id, caustics_strength);
+            gl::Uniform1f(u_time.id, app.seconds);
+            gl::Uniform3f(u_moon_dir.id, moon_dir.x, moon_dir.y, moon_dir.z);
+            gl::Uniform3f(u_moon_color.id, moon_light.x, moon_light.y, moon_light.z);
+        }
+
+        // Shadows are cast by whichever is up
+        sun.light_dir = if sun_dir.z > 0.0 { sun_dir } else { moon_dir };
+        water.sky_color = sky_color;
+        water.sun_dir = sun_dir;
+    }
+}
+
+/// The time of day as an angle, based on how many seconds of game time have passed
+fn model_time(seconds: f32) -> f32 {
+    const MIN_PER_DAY: f32 = 60.0;
+    // Noon:     0.0
+    // Evening:  1.57
+    // Midnight: 3.14
+    // Morning:  4.71
+    // Noon2:    6.28
+    seconds / (MIN_PER_DAY * 60.0) + 5.5
+}
+
+/// How full the moon is, from 0 at new moon to 1 at full moon. Starts full, and goes through a cycle every 8 days.
+fn moon_phase(model_t: f32) -> f32 {
+    const DAYS_PER_CYCLE: f32 = 8.0;
+    let day = (model_t / (2.0 * PI)).floor();
+    0.5 + 0.5 * (2.0 * PI * day / DAYS_PER_CYCLE).cos()
+}
+
+/// Whether it's night, whenever the sky says it is
+fn is_night(seconds: f32) -> bool {
+    model_time(seconds).cos() <= 0.0
+}
+
+/// Which day it is, counting from 1 for the day the island was landed on
+fn clock_day(model_t: f32) -> u32 {
+    ((12.0 + model_t / (2.0 * PI) * 24.0) / 24.0).floor() as u32
+}
+
+/// The time of day as a 24 hour clock reading
+fn clock_time(model_t: f32) -> (u32, u32) {
+    let hours = (12.0 + model_t / (2.0 * PI) * 24.0).rem_euclid(24.0);
+    (hours as u32, (hours.fract() * 60.0) as u32)
+}
+
+struct PhysicsSystem;
+impl<'a> System<'a> for PhysicsSystem {
+    type SystemData = (
+        WriteStorage<'a, PositionComponent>,
+        WriteStorage<'a, VelocityComponent>,
+        Read<'a, PerlinMapResource>,
+        Read<'a, TimeResource>,
+    );
+    fn run(&mut self, (mut positions, mut velocities, tile, time): Self::SystemData) {
+        // Velocities are per tick, so slow motion covers less of each one
+        let rate = time.rate();
+        for (position, velocity) in (&mut positions, &mut velocities).join() {
+            velocity.vel.z -= accel_per_tick(GRAVITY) * rate;
+            position.pos += velocity.vel * rate;
+
+            let feet_height = tile.map.get_z_interpolated(position.pos.xy());
+            if position.pos.z <= feet_height {
+                let normal = tile.map.get_normal(position.pos.xy());
+                let d = feet_height - position.pos.z;
+                velocity.vel += normal * 0.1 * d; // normal from slopes
+                if nalgebra_glm::length(&velocity.vel.xy()) < 0.05 {
+                    let feet_normal = -nalgebra_glm::vec3(normal.x, normal.y, 0.0);
+                    velocity.vel += feet_normal * 0.1 * d; // if standing still, remove the side-to-side component from the slope normal, so there's no slipping
+                }
+                // If the player is a meter deep into the earth, hard bump them
+                let bump_limit = UNIT_PER_METER * 0.01;
+                if feet_height - position.pos.z >= bump_limit {
+                    position.pos.z = feet_height - bump_limit;
+                }
+
+                velocity.vel *= 0.8; // friction
+            }
+        }
+    }
+}
+
+struct PlayerSystem;
+impl<'a> System<'a> for PlayerSystem {
+    type SystemData = (
+        WriteStorage<'a, PositionComponent>,
+        WriteStorage<'a, VelocityComponent>,
+        WriteStorage<'a, PlayerComponent>,
+        ReadStorage<'a, InventoryComponent>,
+        WriteStorage<'a, WeaponComponent>,
+        WriteStorage<'a, MeshComponent>,
+        WriteStorage<'a, StaminaComponent>,
+        Read<'a, App>,
+        Read<'a, InputMap>,
+        Read<'a, ViewSettings>,
+        Read<'a, TimeResource>,
+        Write<'a, OpenGlResource>,
+        Read<'a, AudioResource>,
+        Read<'a, PerlinMapResource>,
+        Read<'a, WaterResource>,
+        Read<'a, PrefabResource>,
+        Write<'a, NoiseResource>,
+        Write<'a, StatsResource>,
+        Read<'a, LazyUpdate>,
+        Entities<'a>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            mut positions,
+            mut velocities,
+            mut players,
+            inventories,
+            mut weapons,
+            mut meshes,
+            mut staminas,
+            app,
+            input,
+            view,
+            time,
+            mut opengl,
+            audio,
+            tiles,
+            water,
+            prefabs,
+            mut noises,
+            mut run_stats,
+            lazy,
+            entities,
+        ): Self::SystemData,
+    ) {
+        for (player, inventory, weapon, mesh, stamina, position, velocity) in (
+            &mut players,
+            &inventories,
+            &mut weapons,
+            &mut meshes,
+            &mut staminas,
+            &mut positions,
+            &mut velocities,
+        )
+            .join()
+        {
+            // TODO: This is a lot. Can it be cleaned up somehow?
+            let curr_w_state = input.held(&app, Action::MoveForward);
+            let curr_s_state = input.held(&app, Action::MoveBack);
+            let curr_a_state = input.held(&app, Action::MoveLeft);
+            let curr_d_state = input.held(&app, Action::MoveRight);
+            let curr_space_state = input.held(&app, Action::Jump);
+            let curr_shift_state = input.held(&app, Action::Sprint);
+            let curr_ctrl_state = input.held(&app, Action::Crouch);
+            let stick = nalgebra_glm::vec2(app.axis(Axis::LeftX), app.axis(Axis::LeftY));
+            let walking = curr_w_state
+                || curr_s_state
+                || curr_a_state
+                || curr_d_state
+                || stick != nalgebra_glm::Vec2::zeros();
+            let swimming = water.is_underwater(position.pos);
+            player.crouching = curr_ctrl_state && !swimming;
+            player.sprinting =
+                walking && curr_shift_state && !player.crouching && !swimming && stamina.fresh();
+            let walk_speed: f32 = if swimming && !stamina.fresh() {
+                WINDED_SWIM_SPEED
+            } else if swimming {
+                1.0
+            } else if player.crouching {
+                0.5
+            } else if player.sprinting {
+                1.3
+            } else {
+                1.0
+            };
+            let view_speed = view.mouse_sensitivity;
+            let facing_vec = nalgebra_glm::vec3(
+                player.facing.cos(),
+                player.facing.sin(),
+                if swimming { -player.pitch.sin() } else { 0.0 },
+            );
+            let sideways_vec = nalgebra_glm::cross(&opengl.camera.up, &facing_vec);
+            let mut player_vel_vec: nalgebra_glm::Vec3 = nalgebra_glm::zero();
+            if curr_w_state {
+                player_vel_vec += facing_vec;
+            }
+            if curr_s_state {
+                player_vel_vec += -facing_vec;
+            }
+            if curr_a_state {
+                player_vel_vec += sideways_vec;
+            }
+            if curr_d_state {
+                player_vel_vec += -sideways_vec;
+            }
+            player_vel_vec += -stick.y * facing_vec - stick.x * sideways_vec;
+            if curr_space_state && swimming {
+                velocity.vel.z += accel_per_tick(SWIM_UP_ACCEL);
+                velocity.vel.z = velocity.vel.z.min(per_tick(SWIM_UP_MAX_SPEED));
+            } else if curr_space_state && player.feet_on_ground && stamina.fresh() {
+                velocity.vel.z += per_tick(JUMP_SPEED);
+                stamina.spend(JUMP_COST);
+                audio.audio_mgr.play_sound("res/jump.ogg".to_string(), 128);
+                println!("{}", opengl.camera.position);
+            } else if walking {
+                // Move the player, this way moving diagonal isn't faster
+                velocity.vel +=
+                    player_vel_vec.normalize() * per_tick(walk_speed * 4.317 * UNIT_PER_METER);
+            }
+            // The right stick turns as fast as moving the mouse this many pixels per tick
+            const STICK_LOOK_SPEED: f32 = 12.0;
+            let look_x = app.mouse_rel_x as f32 + STICK_LOOK_SPEED * app.axis(Axis::RightX);
+            let look_y = app.mouse_rel_y as f32 + STICK_LOOK_SPEED * app.axis(Axis::RightY);
+            player.facing -= view_speed * look_x;
+            player.pitch = (player.pitch + view_speed * look_y)
+                .clamp(PITCH_MARGIN - PI / 2.0, PI / 2.0 - PITCH_MARGIN);
+
+            let eye_height = if player.crouching {
+                CROUCH_HEIGHT
+            } else {
+                PERSON_HEIGHT
+            };
+            opengl.camera.position = position.pos + nalgebra_glm::vec3(0.0, 0.0, eye_height);
+            mesh.scale.z = eye_height / PERSON_HEIGHT;
+
+            let feet_height = tiles.map.get_z_interpolated(position.pos.xy());
+            player.feet_on_ground = position.pos.z <= feet_height;
+            if !player.feet_on_ground {
+                velocity.vel.x *= 0.8;
+                velocity.vel.y *= 0.8;
+            }
+
+            let rot_matrix = nalgebra_glm::rotate_y(
+                &nalgebra_glm::rotate_z(&nalgebra_glm::one(), player.facing),
+                player.pitch,
+            );
+            let facing_vec = (rot_matrix * nalgebra_glm::vec4(1.0, 0.0, 0.0, 0.0)).xyz();
+            opengl.camera.lookat = opengl.camera.position + facing_vec;
+
+            if let Some(held) = inventory.held_weapon() {
+                let stats = held.stats();
+                let shot_period = if player.torch_raised {
+                    stats.period * TORCH_SHOT_PERIOD_SCALE
+                } else {
+                    stats.period
+                };
+                let fire_down = input.held(&app, Action::Fire);
+                if weapon.out_of_ammo(held) {
+                    if fire_down && weapon.ready_to_swing(time.elapsed) {
+                        // A swing is an unseen projectile that only reaches as far as an arm and a weapon
+                        weapon.swing(time.elapsed);
+                        run_stats.attacks += 1;
+                        let swing_entity = entities.create();
+                        lazy.insert(
+                            swing_entity,
+                            PositionComponent {
+                                pos: opengl.camera.position,
+                            },
+                        );
+                        lazy.insert(
+                            swing_entity,
+                            VelocityComponent {
+                                vel: facing_vec
+                                    * per_tick(MELEE_RANGE / MELEE_SECONDS * UNIT_PER_METER),
+                            },
+                        );
+                        lazy.insert(
+                            swing_entity,
+                            ProjectileComponent {
+                                age: 0.0,
+                                lifetime: MELEE_SECONDS,
+                                damage: MELEE_DAMAGE,
+                                underwater_travel: 0.0,
+                            },
+                        );
+                        lazy.insert(
+                            swing_entity,
+                            ColliderComponent {
+                                shape: Shape::Sphere {
+                                    radius: MELEE_RADIUS * UNIT_PER_METER,
+                                },
+                            },
+                        );
+                        audio
+                            .audio_mgr
+                            .play_sound(MELEE_SOUND.to_string(), MELEE_VOLUME);
+                    }
+                } else if weapon.ready(held, shot_period, time.elapsed) && fire_down {
+                    weapon.fire(held, time.elapsed);
+                    if stats.loud {
+                        player.t_last_shot = time.elapsed;
+                        noises.make(position.pos, GUNSHOT_NOISE_RADIUS);
+                    }
+                    run_stats.shots_fired += 1;
+                    let gun_pos = opengl.camera.position
+                        + nalgebra_glm::vec3(0.0, 0.0, -0.5 * UNIT_PER_METER);
+                    let convergence =
+                        ((opengl.camera.position + facing_vec * 1.0) - gun_pos).normalize();
+                    let right = nalgebra_glm::normalize(&nalgebra_glm::cross(
+                        &convergence,
+                        &nalgebra_glm::vec3(0.0, 0.0, 1.0),
+                    ));
+                    let up = nalgebra_glm::cross(&right, &convergence);
+                    for (right_offset, up_offset) in held.pellet_offsets() {
+                        run_stats.attacks += 1;
+                        let dir = (convergence + right * right_offset + up * up_offset).normalize();
+                        let bullet_entity = entities.create();
+                        lazy.insert(
+                            bullet_entity,
+                            MeshComponent {
+                                mesh_id: prefabs.cube_mesh,
+                                scale: nalgebra_glm::vec3(stats.size, stats.size, stats.size),
+                                texture: prefabs.texture(PrefabTexture::Bullet),
+                                render_dist: Some(128.0),
+                                shadow_only: false,
+                                layer: RenderLayer::Opaque,
+                                order: 0,
+                            },
+                        );
+                        lazy.insert(bullet_entity, PositionComponent { pos: gun_pos });
+                        lazy.insert(
+                            bullet_entity,
+                            VelocityComponent {
+                                vel: dir.scale(per_tick(stats.speed * UNIT_PER_METER)),
+                            },
+                        );
+                        lazy.insert(
+                            bullet_entity,
+                            ProjectileComponent {
+                                age: 0.0,
+                                lifetime: PROJECTILE_SECONDS,
+                                damage: stats.damage,
+                                underwater_travel: 0.0,
+                            },
+                        );
+                        if stats.tracer {
+                            lazy.insert(
+                                bullet_entity,
+                                ParticleEmitterComponent::new(BULLET_TRACER_PARTICLES),
+                            );
+                        }
+                        lazy.insert(
+                            bullet_entity,
+                            ColliderComponent {
+                                shape: Shape::Sphere {
+                                    radius: stats.size / 2.0,
+                                },
+                            },
+                        );
+                    }
+                    audio
+                        .audio_mgr
+                        .play_sound(stats.sound.to_string(), stats.volume);
+                }
+            }
+            // 107 steps per minute
+            // 60 seconds per 107 steps
+            // 0.56 seconds per step
+            if walking
+                && player.feet_on_ground
+                && time.elapsed - player.t_last_walk_played > 0.56 / walk_speed
+            {
+                player.t_last_walk_played = time.elapsed;
+                audio.audio_mgr.play_sound("res/walk.ogg".to_string(), 35);
+
+                // Kick up some sand, same test the terrain mesh uses to color sand
+                let normal = tiles.map.get_normal(position.pos.xy());
+                if normal.z > 0.9 && position.pos.z < 0.9 * normal.z {
+                    spawn_emitter(&entities, &lazy, position.pos, SAND_FOOTSTEP_PARTICLES);
+                }
+            }
+        }
+    }
+}
+
+/// Opens chests the player interacts with. Buried chests need the shovel to dig up.
+struct TreasureSystem {
+    reader: ReaderId<InteractEvent>,
+}
+
+impl TreasureSystem {
+    fn new(world: &mut World) -> Self {
+        Self {
+            reader: interact_reader(world),
+        }
+    }
+}
+
+impl<'a> System<'a> for TreasureSystem {
+    type SystemData = (
+        WriteStorage<'a, TreasureMapComponent>,
+        WriteStorage<'a, QuadComponent>,
+        ReadStorage<'a, PositionComponent>,
+        ReadStorage<'a, PlayerComponent>,
+        WriteStorage<'a, InventoryComponent>,
+        WriteStorage<'a, WeaponComponent>,
+        ReadStorage<'a, ChestComponent>,
+        WriteStorage<'a, AnimationComponent>,
+        WriteStorage<'a, InteractableComponent>,
+        Read<'a, EventChannel<InteractEvent>>,
+        Read<'a, AudioResource>,
+        Write<'a, DialogResource>,
+        Write<'a, JournalResource>,
+        Read<'a, PrefabResource>,
+        Read<'a, LazyUpdate>,
+        Entities<'a>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            mut treasure_maps,
+            mut quads,
+            positions,
+            player,
+            mut inventories,
+            mut weapons,
+            chests,
+            mut animations,
+            mut interactables,
+            events,
+            audio,
+            mut dialog,
+            mut journal,
+            prefabs,
+            lazy,
+            entities,
+        ): Self::SystemData,
+    ) {
+        let opened: Vec<Entity> = events
+            .read(&mut self.reader)
+            .filter(|event| event.action == InteractAction::OpenChest)
+            .map(|event| event.entity)
+            .collect();
+        let (_, player_entity) = (&player, &entities).join().next().unwrap();
+        let inventory = inventories.get_mut(player_entity).unwrap();
+        let weapon = weapons.get_mut(player_entity).unwrap();
+        let shovel = Item::Tool(Tool::Shovel);
+        for (treasure_map, quad) in (&mut treasure_maps, &mut quads).join() {
+            // Get the corresponding treasure entity
+            let treasure_entity = treasure_map.treasure_entity;
+
+            // Found chests, including ones opened before a save was loaded, have nothing left to open
+            if treasure_map.found {
+                interactables.remove(treasure_entity);
+            }
+
+            // Access components of the treasure entity
+            if let Some(treasure_position) = positions.get(treasure_entity) {
+                if opened.contains(&treasure_entity) && !treasure_map.found {
+                    let chest = chests.get(treasure_entity).unwrap();
+                    if chest.buried && !inventory.holding(shovel) {
+                        if inventory.has(shovel) {
+                            dialog.say("Something's buried here. I should get my shovel out.");
+                        } else {
+                            dialog.say("Something's buried here. I need a shovel to dig it up.");
+                        }
+                    } else {
+                        quad.texture = prefabs.texture(PrefabTexture::Gold);
+                        if let Some(animation) = animations.get_mut(treasure_entity) {
+                            animation.play("chest-open");
+                        }
+                        audio.audio_mgr.play_sound("res/win.ogg".to_string(), 128);
+                        let chest_pos = treasure_position.pos;
+                        lazy.exec_mut(move |world| {
+                            spawn_chest_lid(world, chest_pos, false);
+                            for i in 0..CHEST_COINS {
+                                spawn_coin(world, chest_pos, i);
+                            }
+                        });
+                        // Every chest has a small stash of powder and shot in it too
+                        weapon.find_stash();
+                        if let Some(tool) = chest.contents {
+                            inventory.give(Item::Tool(tool));
+                            dialog.say(&format!("There's a {} in this chest!", tool.name()));
+                        }
+                        treasure_map.found = true;
+                        interactables.remove(treasure_entity);
+                        journal.photograph(chest_pos);
+                    }
+                }
+
+                // Maps still to follow are faded, the map itself has to be held up to see where to go
+                quad.opacity = if treasure_map.found { 1.0 } else { 0.4 };
+            }
+        }
+    }
+}
+
+struct ProjectileSystem;
+impl<'a> System<'a> for ProjectileSystem {
+    type SystemData = (
+        WriteStorage<'a, PositionComponent>,
+        WriteStorage<'a, ProjectileComponent>,
+        WriteStorage<'a, VelocityComponent>,
+        Read<'a, PerlinMapResource>,
+        Read<'a, WaterResource>,
+        Read<'a, AudioResource>,
+        Read<'a, OpenGlResource>,
+        Read<'a, TimeResource>,
+        Read<'a, LazyUpdate>,
+        Entities<'a>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            mut positions,
+            mut projectiles,
+            mut velocities,
+            tile,
+            water,
+            audio,
+            opengl,
+            time,
+            lazy,
+            entities,
+        ): Self::SystemData,
+    ) {
+        for (position, projectile, velocity, entity) in
+            (&mut positions, &mut projectiles, &mut velocities, &entities).join()
+        {
+            projectile.age += time.dt;
+            if projectile.age >= projectile.lifetime {
+                entities.delete(entity).unwrap();
+                continue;
+            }
+
+            // Bullets are fast enough to pass through a ridge between ticks, so look for the ground along the way
+            let travel = velocity.vel * time.rate();
+            let start = position.pos - travel;
+            let ground = match tile.map.raycast(start, travel) {
+                Some(hit) if hit.t <= 1.0 => Some((hit.t, hit.pos + 0.001 * hit.normal)),
+                _ => {
+                    let tile_z: f32 = tile.map.get_z_interpolated(position.pos.xy());
+                    (position.pos.z < tile_z).then(|| {
+                        (
+                            1.0,
+                            nalgebra_glm::vec3(position.pos.x, position.pos.y, tile_z),
+                        )
+                    })
+                }
+            };
+            // Bullets going into the sea splash at the surface and carry on under it
+            let surface =
+                (!water.is_underwater(start) && water.is_underwater(position.pos)).then(|| {
+                    let (above, below) = (water.depth(start), water.depth(position.pos));
+                    let t = above / (above - below);
+                    (t, nalgebra_glm::lerp(&start, &position.pos, t))
+                });
+            let play_impact_sound = |at: nalgebra_glm::Vec3, volume: f32| {
+                let distance = nalgebra_glm::length(&(opengl.camera.position - at));
+                audio.audio_mgr.play_sound(
+                    "res/ground.ogg".to_string(),
+                    (volume * 128.0 / distance.powf(2.0)) as i32,
+                );
+            };
+
+            let entry = match (ground, surface) {
+                (Some((ground_t, _)), Some((surface_t, splash))) if surface_t < ground_t => {
+                    Some(splash)
+                }
+                (None, Some((_, splash))) => Some(splash),
+                (Some((_, impact)), _) => {
+                    entities.delete(entity).unwrap();
+                    spawn_emitter(&entities, &lazy, impact, BULLET_IMPACT_PARTICLES);
+                    play_impact_sound(impact, 50.0);
+                    continue;
+                }
+                (None, None) => None,
+            };
+            if let Some(splash) = entry {
+                spawn_emitter(&entities, &lazy, splash, WATER_SPLASH_PARTICLES);
+                play_impact_sound(splash, 30.0);
+                // The rest of the tick's travel is spent bending through the surface
+                let speed = nalgebra_glm::length(&velocity.vel);
+                let refracted = nalgebra_glm::refract_vec(
+                    &velocity.vel.normalize(),
+                    &nalgebra_glm::vec3(0.0, 0.0, 1.0),
+                    1.0 / WATER_REFRACTIVE_INDEX,
+                );
+                velocity.vel = refracted * speed;
+                position.pos = splash + nalgebra_glm::vec3(0.0, 0.0, -0.001);
+            }
+
+            if water.is_underwater(position.pos) {
+                // Bubbles replace the tracer once it's under
+                if projectile.underwater_travel == 0.0 {
+                    lazy.insert(
+                        entity,
+                        ParticleEmitterComponent::new(BUBBLE_TRAIL_PARTICLES),
+                    );
+                }
+                projectile.underwater_travel += nalgebra_glm::length(&travel);
+                velocity.vel *= (-UNDERWATER_DRAG * time.dt).exp();
+                if projectile.underwater_travel >= UNDERWATER_RANGE {
+                    entities.delete(entity).unwrap();
+                }
+            }
+        }
+    }
+}
+
+struct HealthSystem;
+impl<'a> System<'a> for HealthSystem {
+    type SystemData = WriteStorage<'a, HealthComponent>;
+
+    fn run(&mut self, mut healths: Self::SystemData) {
+        for health in (&mut healths).join() {
+            health.health = health.health.clamp(0.0, 1.0);
+        }
+    }
+}
+
+/// Knocks defeated mobs away from the player, tumbling, stops them fighting, and throws out what they drop
+struct MobDeathSystem {
+    rng: StdRng, //< Rolls loot drops
+}
+
+impl MobDeathSystem {
+    fn new(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed ^ 0x1007_d20b),
+        }
+    }
+}
+
+impl<'a> System<'a> for MobDeathSystem {
+    type SystemData = (
+        WriteStorage<'a, HealthComponent>,
+        ReadStorage<'a, MobComponent>,
+        WriteStorage<'a, DeathSplishAnimComponent>,
+        WriteStorage<'a, ColliderComponent>,
+        WriteStorage<'a, CastsShadowComponent>,
+        WriteStorage<'a, AiComponent>,
+        WriteStorage<'a, AnimationComponent>,
+        ReadStorage<'a, PlayerComponent>,
+        ReadStorage<'a, PositionComponent>,
+        WriteStorage<'a, VelocityComponent>,
+        Read<'a, AudioResource>,
+        Write<'a, TimeResource>,
+        Write<'a, StatsResource>,
+        Read<'a, LazyUpdate>,
+        Entities<'a>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            mut healths,
+            mobs,
+            mut death_splish_anims,
+            mut colliders,
+            mut casts_shadows,
+            mut ais,
+            mut animations,
+            players,
+            positions,
+            mut velocities,
+            audio,
+            mut time,
+            mut stats,
+            lazy,
+            entities,
+        ): Self::SystemData,
+    ) {
+        let player_pos = (&players, &positions)
+            .join()
+            .next()
+            .map_or(nalgebra_glm::zero(), |(_, position)| position.pos);
+        let mut removed_entities = Vec::new();
+        for (health, mob, position, entity) in (&healths, &mobs, &positions, &entities).join() {
+            if health.health <= 0.0 {
+                let drops = roll_loot(mob.kind, &mut self.rng);
+                let mob_pos = position.pos;
+                lazy.exec_mut(move |world| {
+                    for (i, loot) in drops.iter().enumerate() {
+                        spawn_loot(world, mob_pos, *loot, i, drops.len());
+                    }
+                });
+                let away = (position.pos - player_pos).xy();
+                let away = if nalgebra_glm::length(&away) > 0.0 {
+                    away.normalize()
+                } else {
+                    nalgebra_glm::vec2(1.0, 0.0)
+                };
+                if let Some(velocity) = velocities.get_mut(entity) {
+                    velocity.vel.x += away.x * per_tick(DEATH_KNOCKBACK);
+                    velocity.vel.y += away.y * per_tick(DEATH_KNOCKBACK);
+                    velocity.vel.z += per_tick(DEATH_LIFT);
+                }
+                // Flopping over backwards, away from the player
+                let spin = nalgebra_glm::vec3(-away.y, away.x, 0.0) * TUMBLE_SPIN;
+                death_splish_anims
+                    .insert(
+                        entity,
+                        DeathSplishAnimComponent {
+                            spin,
+                            ..Default::default()
+                        },
+                    )
+                    .unwrap();
+                spawn_emitter(&entities, &lazy, position.pos, MOB_DEATH_PARTICLES);
+                removed_entities.push(entity);
+            }
+        }
+        for removed_entity in removed_entities {
+            healths.remove(removed_entity);
+            colliders.remove(removed_entity);
+            casts_shadows.remove(removed_entity);
+            // The tumble takes over the mob's pose from its walk and attack clips
+            ais.remove(removed_entity);
+            if let Some(animation) = animations.get_mut(removed_entity) {
+                animation.playing = None;
+            }
+            audio.audio_mgr.play_sound("res/dead.ogg".to_string(), 128);
+            time.hit_stop(KILL_HIT_STOP_SECONDS);
+            stats.mobs_defeated += 1;
+        }
+    }
+}
+
+/// Tumbles defeated mobs over, leaves them lying for a while, then squashes them away
+struct DeathSplishAnimSystem;
+impl<'a> System<'a> for DeathSplishAnimSystem {
+    type SystemData = (
+        WriteStorage<'a, MeshComponent>,
+        WriteStorage<'a, DeathSplishAnimComponent>,
+        WriteStorage<'a, AnimationComponent>,
+        ReadStorage<'a, PositionComponent>,
+        Read<'a, TimeResource>,
+        Read<'a, OpenGlResource>,
+        Read<'a, GameplaySettings>,
+        Read<'a, App>,
+        Entities<'a>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            mut renderables,
+            mut death_splish_anims,
+            mut animations,
+            positions,
+            time,
+            open_gl,
+            settings,
+            app,
+            entities,
+        ): Self::SystemData,
+    ) {
+        const DURATION: f32 = 1.0; // s
+        let mut removed_entities = Vec::new();
+        for (renderable, death_splish_anim, animation, position, entity) in (
+            &mut renderables,
+            &mut death_splish_anims,
+            (&mut animations).maybe(),
+            positions.maybe(),
+            &entities,
+        )
+            .join()
+        {
+            let pos = position.map(|position| position.pos);
+            let Some(dt) = lod_dt(entity, pos, open_gl.camera.position, app.ticks, time.dt) else {
+                continue;
+            };
+            death_splish_anim.seconds_dead += dt;
+            if death_splish_anim.seconds_dead < TUMBLE_SECONDS {
+                let slowing = 1.0 - death_splish_anim.seconds_dead / TUMBLE_SECONDS;
+                if let Some(animation) = animation {
+                    animation.pose.rotation += death_splish_anim.spin * slowing * dt;
+                }
+                continue;
+            }
+            if death_splish_anim.seconds_dead < TUMBLE_SECONDS + settings.corpse_seconds {
+                continue;
+            }
+            death_splish_anim.timeline += dt / DURATION;
+            let z = 1.0 - death_splish_anim.timeline.powf(2.0);
+            let xy = (3.33 / (z + 0.833)).sqrt();
+            renderable.scale = nalgebra_glm::vec3(xy, xy, z);
+            if death_splish_anim.timeline >= 1.0 {
+                removed_entities.push(entity);
+            }
+        }
+        for removed_entity in removed_entities {
+            entities.delete(removed_entity).unwrap();
+        }
+    }
+}
+
+/// Shows the seed, player position, facing, and time of day. Toggled with F1, Ctrl+C copies the seed and position.
+struct DebugHudSystem {
+    font: Font<'static, 'static>,
+    visible: bool,
+    toggle_was_down: bool,
+    copy_was_down: bool,
+    text: String,
+}
+impl<'a> System<'a> for DebugHudSystem {
+    type SystemData = (
+        ReadStorage<'a, PlayerComponent>,
+        ReadStorage<'a, DebugHudComponent>,
+        ReadStorage<'a, PositionComponent>,
+        WriteStorage<'a, QuadComponent>,
+        Read<'a, SeedResource>,
+        Read<'a, App>,
+        Read<'a, TimeResource>,
+    );
+
+    fn run(&mut self, (players, huds, positions, mut quads, seed, app, time): Self::SystemData) {
+        let toggle_down = app.keys[Scancode::F1 as usize];
+        if toggle_down && !self.toggle_was_down {
+            self.visible = !self.visible;
+        }
+        self.toggle_was_down = toggle_down;
+
+        let (player, player_position) = (&players, &positions).join().next().unwrap();
+        let tile_x = player_position.pos.x.floor() as i32;
+        let tile_y = player_position.pos.y.floor() as i32;
+
+        let copy_down = app.keys[Scancode::C as usize]
+            && (app.keys[Scancode::LCtrl as usize] || app.keys[Scancode::RCtrl as usize]);
+        if copy_down && !self.copy_was_down {
+            let clipboard_text = format!("--seed {} at ({}, {})", seed.seed, tile_x, tile_y);
+            let clipboard_cstr = CString::new(clipboard_text.clone()).unwrap();
+            if unsafe { sdl2::sys::SDL_SetClipboardText(clipboard_cstr.as_ptr()) } == 0 {
+                println!("Copied to clipboard: {}", clipboard_text);
+            } else {
+                println!("Couldn't copy to clipboard: {}", sdl2::get_error());
+            }
+        }
+        self.copy_was_down = copy_down;
+
+        let (hours, minutes) = clock_time(model_time(time.elapsed));
+        let text = format!(
+            "seed {}  tile ({}, {})  facing {:.0}°  {:02}:{:02}",
+            seed.seed,
+            tile_x,
+            tile_y,
+            player.facing.to_degrees().rem_euclid(360.0),
+            hours,
+            minutes
+        );
+
+        for (quad, _) in (&mut quads, &huds).join() {
+            // Only re-render the text when it has actually changed
+            if self.visible && text != self.text {
+                quad.set_text(&text, &self.font, Color::RGBA(255, 255, 255, 255));
+                self.text = text.clone();
+            }
+            quad.opacity = if self.visible { 1.0 } else { 0.0 };
+        }
+    }
+}
+
+struct DialogSystem {
+    font: Font<'static, 'static>,
+    text: String,
+}
+impl<'a> System<'a> for DialogSystem {
+    type SystemData = (
+        ReadStorage<'a, DialogComponent>,
+        WriteStorage<'a, QuadComponent>,
+        Write<'a, DialogResource>,
+        Read<'a, TimeResource>,
+    );
+
+    fn run(&mut self, (dialogs, mut quads, mut dialog, time): Self::SystemData) {
+        dialog.seconds_left = (dialog.seconds_left - time.real_dt).max(0.0);
+        let visible = dialog.seconds_left > 0.0;
+
+        for (quad, _) in (&mut quads, &dialogs).join() {
+            if visible && dialog.text != self.text {
+                quad.set_text(&dialog.text, &self.font, Color::RGBA(255, 255, 255, 255));
+                self.text = dialog.text.clone();
+            }
+            quad.opacity = if visible { 1.0 } else { 0.0 };
+        }
+    }
+}
+
+struct ToastSystem {
+    font: Font<'static, 'static>,
+    text: String,
+}
+impl<'a> System<'a> for ToastSystem {
+    type SystemData = (
+        ReadStorage<'a, ToastComponent>,
+        WriteStorage<'a, QuadComponent>,
+        Write<'a, ToastResource>,
+        Read<'a, TimeResource>,
+    );
+
+    fn run(&mut self, (toasts, mut quads, mut toast, time): Self::SystemData) {
+        toast.seconds_left = (toast.seconds_left - time.real_dt).max(0.0);
+
+        for (quad, _) in (&mut quads, &toasts).join() {
+            if toast.seconds_left > 0.0 && toast.text != self.text {
+                quad.set_text(&toast.text, &self.font, Color::RGBA(255, 255, 255, 255));
+                self.text = toast.text.clone();
+            }
+            // Fade in and out over half a second
+            const FADE_SECONDS: f32 = 0.5;
+            let shown = ToastResource::SECONDS_PER_TOAST - toast.seconds_left;
+            quad.opacity = (shown / FADE_SECONDS)
+                .min(toast.seconds_left / FADE_SECONDS)
+                .clamp(0.0, 1.0);
+        }
+    }
+}
+
+/*
+ * SCENE STUFF
+ */
+pub struct Island {
+    world: World,
+    update_dispatcher: Dispatcher<'static, 'static>,
+    render_dispatcher: Dispatcher<'static, 'static>,
+    ui_render_dispatcher: Dispatcher<'static, 'static>,
+    snapshot_key_was_down: bool,
+    save_key_was_down: bool,
+    load_key_was_down: bool,
+    profile_key_was_down: bool,
+    skip_key_was_down: bool,
+    options_key_was_down: bool,
+    journal_key_was_down: bool,
+    settings_stale: bool, //< Set while the options menu is open, so the settings are read again afterwards
+}
+
+impl Scene for Island {
+    fn update(&mut self, app: &App) -> SceneCommand {
+        if self.settings_stale {
+            self.settings_stale = false;
+            self.apply_settings(&Settings::load().unwrap_or_default());
+        }
+
+        // Cutscenes take the controls away, apart from Enter or Start to skip them
+        let skip_key_down = app.keys[Scancode::Return as usize] || app.button(Button::Start);
+        let skip_pressed = skip_key_down && !self.skip_key_was_down;
+        self.skip_key_was_down = skip_key_down;
+        let mut cinematic = self.world.write_resource::<CinematicResource>();
+        if skip_pressed {
+            cinematic.skip();
+        }
+        let playing = cinematic.playing();
+        drop(cinematic);
+
+        let options_key_down = self
+            .world
+            .read_resource::<InputMap>()
+            .held(app, Action::Options);
+        let options_pressed = options_key_down && !self.options_key_was_down;
+        self.options_key_was_down = options_key_down;
+        if options_pressed && !playing {
+            self.settings_stale = true;
+            return SceneCommand::push(OptionsScene::new());
+        }
+        let journal_key_down = self
+            .world
+            .read_resource::<InputMap>()
+            .held(app, Action::Journal);
+        let journal_pressed = journal_key_down && !self.journal_key_was_down;
+        self.journal_key_was_down = journal_key_down;
+        if journal_pressed && !playing {
+            return SceneCommand::push(JournalScene::new());
+        }
+
+        if playing {
+            self.world.insert(app.without_input());
+        } else {
+            self.world.insert((*app).clone());
+        }
+        self.world
+            .write_resource::<TimeResource>()
+            .tick(TICK_SECONDS);
+        self.update_dispatcher.dispatch_seq(&mut self.world);
+        self.world.maintain();
+
+        let player_dead = (
+            &self.world.read_storage::<PlayerComponent>(),
+            &self.world.read_storage::<HealthComponent>(),
+        )
+            .join()
+            .any(|(_, health)| health.health <= 0.0);
+        if player_dead {
+            let maps_found = (&self.world.read_storage::<TreasureMapComponent>())
+                .join()
+                .filter(|map| map.found)
+                .count();
+            let summary = GameSummary::new(
+                false,
+                self.world.read_resource::<TimeResource>().elapsed,
+                maps_found,
+                &self.world.read_resource::<StatsResource>(),
+            );
+            let SeedResource {
+                seed,
+                layout,
+                noise,
+            } = *self.world.read_resource::<SeedResource>();
+            return SceneCommand::replace(SummaryScene::new(summary, Some(seed), layout, noise));
+        }
+        if let Some(summary) = self.world.write_resource::<GoalResource>().summary.take() {
+            let SeedResource { layout, noise, .. } = *self.world.read_resource::<SeedResource>();
+            return SceneCommand::replace(SummaryScene::new(summary, None, layout, noise));
+        }
+
+        // F6 dumps how long everything has been taking
+        let profile_key_down = app.keys[Scancode::F6 as usize];
+        if profile_key_down && !self.profile_key_was_down {
+            let report = self.world.read_resource::<ProfileResource>().report();
+            match std::fs::write(PROFILE_PATH, report) {
+                Ok(()) => self
+                    .world
+                    .write_resource::<ToastResource>()
+                    .show("Profile saved"),
+                Err(err) => println!("Couldn't save profile: {}", err),
+            }
+        }
+        self.profile_key_was_down = profile_key_down;
+
+        // F9 dumps the world, for comparing with `--diff-snapshots`
+        let snapshot_key_down = app.keys[Scancode::F9 as usize];
+        if snapshot_key_down && !self.snapshot_key_was_down {
+            self.dump_snapshot(app.ticks);
+        }
+        self.snapshot_key_was_down = snapshot_key_down;
+
+        // F5 saves, F8 loads the last save. Loading generates the island from the save's seed again.
+        let save_key_down = app.keys[Scancode::F5 as usize];
+        if save_key_down && !self.save_key_was_down {
+            match SaveGame::capture(&self.world).save(SAVE_PATH) {
+                Ok(()) => self
+                    .world
+                    .write_resource::<ToastResource>()
+                    .show("Game saved"),
+                Err(err) => println!("Couldn't save: {}", err),
+            }
+        }
+        self.save_key_was_down = save_key_down;
+        let load_key_down = app.keys[Scancode::F8 as usize];
+        let load_pressed = load_key_down && !self.load_key_was_down;
+        self.load_key_was_down = load_key_down;
+        if load_pressed {
+            match SaveGame::load(SAVE_PATH) {
+                Ok(save) => return SceneCommand::replace(LoadingScene::from_save(save)),
+                Err(err) => println!("Couldn't load save: {}", err),
+            }
+        }
+
+        SceneCommand::None
+    }
+
+    fn render(&mut self, app: &App) {
+        // The frame is drawn partway between the last two ticks, so that motion is smooth at any refresh rate
+        if let Some(mut world_app) = self.world.try_fetch_mut::<App>() {
+            world_app.tick_alpha = app.tick_alpha;
+        }
+        // The window can be resized, or go fullscreen, at any time
+        self.world.write_resource::<OpenGlResource>().camera.aspect = app.aspect();
+        let tick_camera = interpolate_camera(&self.world, app.tick_alpha);
+        self.render_dispatcher.dispatch_seq(&mut self.world);
+        take_photos(&self.world, app);
+        self.ui_render_dispatcher.dispatch_seq(&mut self.world);
+        restore_camera(&self.world, tick_camera);
+    }
+}
+
+impl Island {
+    /// Creates a new island on terrain from `generate_terrain`. Has to be called on the thread with the GL context.
+    pub fn new(terrain: GeneratedTerrain) -> Result<Self, EngineError> {
+        let settings = Settings::load().unwrap_or_default();
+        let mut island = Self::with_graphics_settings(terrain, settings.graphics.clone())?;
+        island.apply_settings(&settings);
+        Ok(island)
+    }
+
+    /// Puts the player's settings into effect. Called on creation, and again after the options menu closes.
+    fn apply_settings(&mut self, settings: &Settings) {
+        self.world.insert(InputMap::new(&settings.controls));
+        // The shadow system resizes the shadow map when it sees the size change
+        self.world.insert(settings.graphics.clone());
+        self.world.insert(settings.view.clone());
+        self.world.insert(settings.gameplay.clone());
+        self.world
+            .write_resource::<OpenGlResource>()
+            .camera
+            .projection_kind = ProjectionKind::Perspective {
+            fov: settings.view.fov,
+        };
+        self.world
+            .write_resource::<AudioResource>()
+            .audio_mgr
+            .set_master_volume(settings.audio.volume);
+    }
+
+    /// Creates a new island, rendered with the given settings rather than the player's
+    pub fn with_graphics_settings(
+        terrain: GeneratedTerrain,
+        graphics_settings: GraphicsSettings,
+    ) -> Result<Self, EngineError> {
+        Self::build(terrain, graphics_settings, AudioManager::new())
+    }
+
+    /// Creates an island to simulate without a window, for tests. Nothing is heard, and unless there's a real GL
+    /// context, GL has to have been loaded with `load_null_gl` first.
+    pub fn headless(terrain: GeneratedTerrain) -> Result<Self, EngineError> {
+        let graphics_settings = GraphicsSettings::from_preset(QualityPreset::Low);
+        Self::build(terrain, graphics_settings, AudioManager::silent())
+    }
+
+    /// Runs the island for some ticks without drawing it, with `app` as the input every tick. Stops early if the island
+    /// wants to change scenes, like when the player dies, and returns what it wanted.
+    pub fn step(&mut self, app: &App, ticks: usize) -> SceneCommand {
+        for _ in 0..ticks {
+            let command = self.update(app);
+            if !matches!(command, SceneCommand::None) {
+                return command;
+            }
+        }
+        SceneCommand::None
+    }
+
+    fn build(
+        terrain: GeneratedTerrain,
+        graphics_settings: GraphicsSettings,
+        mut audio_mgr: AudioManager,
+    ) -> Result<Self, EngineError> {
+        let view_distance = graphics_settings.view_distance;
+
+        // Setup ECS the world
+        let mut world = World::new();
+        let registry = component_registry();
+        registry.register_all(&mut world);
+        world.insert(registry);
+        world.register::<PreviousPositionComponent>();
+        world.register::<MeshComponent>();
+        world.register::<CastsShadowComponent>();
+        world.register::<TreasureCounterComponent>();
+        world.register::<AnimationComponent>();
+        world.register::<DebugHudComponent>();
+        world.register::<DialogComponent>();
+        world.register::<ToastComponent>();
+        world.register::<ParticleEmitterComponent>();
+        world.register::<MinimapComponent>();
+        world.register::<MinimapMarkerComponent>();
+        world.register::<MapViewComponent>();
+        world.register::<HotbarSlotComponent>();
+        world.register::<AmmoReadoutComponent>();
+        world.register::<ViewModelComponent>();
+        world.register::<HeldWeaponComponent>();
+        world.register::<TintComponent>();
+        world.register::<ChestIndicatorComponent>();
+        world.register::<DamageFlashComponent>();
+        world.register::<DamageIndicatorComponent>();
+        world.register::<CompassMarkComponent>();
+        world.register::<HitMarkerComponent>();
+        world.register::<PlayerHealthBarComponent>();
+        world.register::<MobHealthBarComponent>();
+        world.register::<TorchFlameComponent>();
+        world.register::<CoinComponent>();
+        world.register::<LootComponent>();
+        world.register::<InteractableComponent>();
+        world.register::<InteractPromptComponent>();
+        world.register::<LetterboxComponent>();
+        world.register::<TerrainComponent>();
+        world.register::<LightComponent>();
+        world.register::<OxygenMeterComponent>();
+        world.register::<UnderwaterTintComponent>();
+        world.register::<StaminaBarComponent>();
+        world.register::<ParrotComponent>();
+        world.register::<ParentComponent>();
+        world.insert(PersistentIdResource::default());
+
+        // Setup the dispatchers. Systems reading events need the channels in the world first.
+        world.insert(EventChannel::<CollisionEvent>::new());
+        world.insert(EventChannel::<InteractEvent>::new());
+        let mut update_dispatcher_builder = DispatcherBuilder::new();
+        update_dispatcher_builder.add_timed(RecordPreviousSystem, "record previous system", &[]);
+        update_dispatcher_builder.add_timed(InventorySystem::default(), "inventory system", &[]);
+        update_dispatcher_builder.add_timed(ReloadSystem::default(), "reload system", &[]);
+        update_dispatcher_builder.add_timed(PlayerSystem, "player system", &[]);
+        update_dispatcher_builder.add_timed(SwimmingSystem, "swimming system", &[]);
+        update_dispatcher_builder.add_timed(StaminaSystem, "stamina system", &[]);
+        update_dispatcher_builder.add_timed(CinematicSystem, "cinematic system", &[]);
+        update_dispatcher_builder.add_timed(ViewModelSystem::default(), "view model system", &[]);
+        update_dispatcher_builder.add_timed(
+            ChunkStreamingSystem::default(),
+            "chunk streaming system",
+            &[],
+        );
+        update_dispatcher_builder.add_timed(
+            GrassScatterSystem::new(terrain.seed),
+            "grass scatter system",
+            &[],
+        );
+        update_dispatcher_builder.add_timed(PhysicsSystem, "physics system", &[]);
+        // After physics, so that nothing is left overlapping when it's drawn
+        update_dispatcher_builder.add_timed(
+            CollisionDetectionSystem,
+            "collision detection system",
+            &[],
+        );
+        update_dispatcher_builder.add_timed(
+            CollisionResponseSystem::new(&mut world),
+            "collision response system",
+            &[],
+        );
+        update_dispatcher_builder.add_timed(
+            InteractionSystem::default(),
+            "interaction system",
+            &[],
+        );
+        update_dispatcher_builder.add_timed(
+            TreasureSystem::new(&mut world),
+            "treasure system",
+            &[],
+        );
+        update_dispatcher_builder.add_timed(CoinSystem, "coin system", &[]);
+        update_dispatcher_builder.add_timed(LootSystem, "loot system", &[]);
+        update_dispatcher_builder.add_timed(GoalSystem, "goal system", &[]);
+        update_dispatcher_builder.add_timed(TreasureCounterSystem, "treasure counter system", &[]);
+        update_dispatcher_builder.add_timed(AmmoReadoutSystem, "ammo readout system", &[]);
+        update_dispatcher_builder.add_timed(TorchSystem::default(), "torch system", &[]);
+        update_dispatcher_builder.add_timed(StatusSystem, "status system", &[]);
+        update_dispatcher_builder.add_timed(PerceptionSystem, "perception system", &[]);
+        update_dispatcher_builder.add_timed(
+            SpawnerSystem::new(terrain.seed),
+            "spawner system",
+            &[],
+        );
+        update_dispatcher_builder.add_timed(AiSystem::new(terrain.seed), "ai system", &[]);
+        update_dispatcher_builder.add_timed(
+            ContactDamageSystem::new(&mut world),
+            "contact damage system",
+            &[],
+        );
+        update_dispatcher_builder.add_timed(DamageFlashSystem, "damage flash system", &[]);
+        update_dispatcher_builder.add_timed(DamageIndicatorSystem, "damage indicator system", &[]);
+        update_dispatcher_builder.add_timed(LetterboxSystem, "letterbox system", &[]);
+        update_dispatcher_builder.add_timed(
+            CastawaySystem::new(&mut world),
+            "castaway system",
+            &[],
+        );
+        update_dispatcher_builder.add_timed(BoundarySystem::default(), "boundary system", &[]);
+        update_dispatcher_builder.add_timed(MacheteSystem::new(&mut world), "machete system", &[]);
+        update_dispatcher_builder.add_timed(
+            TraderSystem::new(&mut world, terrain.seed),
+            "trader system",
+            &[],
+        );
+        update_dispatcher_builder.add_timed(WeatherSystem, "weather system", &[]);
+        update_dispatcher_builder.add_timed(AmbientSoundSystem, "ambient sound system", &[]);
+        update_dispatcher_builder.add_timed(SonarSystem::default(), "sonar system", &[]);
+        update_dispatcher_builder.add_timed(MinimapSystem, "minimap system", &[]);
+        update_dispatcher_builder.add_timed(MapViewSystem::default(), "map view system", &[]);
+        update_dispatcher_builder.add_timed(ChestIndicatorSystem, "chest indicator system", &[]);
+        update_dispatcher_builder.add_timed(CompassSystem, "compass system", &[]);
+        update_dispatcher_builder.add_timed(ParrotSystem::new(terrain.seed), "parrot system", &[]);
+        // After everything that moves things around, so that children end up with their parents
+        update_dispatcher_builder.add_timed(ParentSystem, "parent system", &[]);
+        update_dispatcher_builder.add_timed(ProjectileSystem, "projectile system", &[]);
+        update_dispatcher_builder.add_timed(DamageSystem::new(&mut world), "damage system", &[]);
+        update_dispatcher_builder.add_timed(
+            KnockbackSystem::new(&mut world),
+            "knockback system",
+            &[],
+        );
+        update_dispatcher_builder.add_timed(SfxSystem::new(&mut world), "sfx system", &[]);
+        update_dispatcher_builder.add_timed(
+            HitMarkerSystem::new(&mut world),
+            "hit marker system",
+            &[],
+        );
+        update_dispatcher_builder.add_timed(
+            ObstacleSystem::new(&mut world),
+            "obstacle system",
+            &[],
+        );
+        update_dispatcher_builder.add_timed(TargetSystem, "target system", &[]);
+        update_dispatcher_builder.add_timed(HealthSystem, "health system", &[]);
+        update_dispatcher_builder.add_timed(
+            MobDeathSystem::new(terrain.seed),
+            "mob death system",
+            &[],
+        );
+        update_dispatcher_builder.add_timed(DeathSplishAnimSystem, "death splish anim system", &[]);
+        update_dispatcher_builder.add_timed(StatsSystem::default(), "stats system", &[]);
+        update_dispatcher_builder.add_timed(HealthBarSystem, "health bar system", &[]);
+        update_dispatcher_builder.add_timed(SwimmingHudSystem, "swimming hud system", &[]);
+        update_dispatcher_builder.add_timed(StaminaBarSystem, "stamina bar system", &[]);
+        update_dispatcher_builder.add_timed(MobAnimationSystem, "mob animation system", &[]);
+        update_dispatcher_builder.add_timed(AnimationSystem, "animation system", &[]);
+        update_dispatcher_builder.add_timed(ParticleSystem, "particle system", &[]);
+        update_dispatcher_builder.add_timed(
+            DiagnosticsSystem::default(),
+            "diagnostics system",
+            &[],
+        );
+        update_dispatcher_builder.add_timed(
+            TerrainOverlaySystem::default(),
+            "terrain overlay system",
+            &[],
+        );
+        initialize_ui_navigation(&mut world, &mut update_dispatcher_builder);
+
+        let mut render_dispatcher_builder = DispatcherBuilder::new();
+        render_dispatcher_builder.add_timed(SkySystem, "sky system", &[]);
+        render_dispatcher_builder.add_gpu_timed(ShadowSystem, "shadow system", &[]);
+        render_dispatcher_builder.add_timed(SkyRenderSystem, "sky render system", &[]);
+        render_dispatcher_builder.add_timed(PointLightSystem, "point light system", &[]);
+        render_dispatcher_builder.add_gpu_timed(Render3dSystem, "render system", &[]);
+        render_dispatcher_builder.add_timed(GrassRenderSystem, "grass render system", &[]);
+        render_dispatcher_builder.add_timed(ParticleRenderSystem, "particle render system", &[]);
+        render_dispatcher_builder.add_timed(WaterRenderSystem, "water render system", &[]);
+        render_dispatcher_builder.add_timed(ViewModelRenderSystem, "view model render system", &[]);
+
+        let mut ui_render_dispatcher_builder = DispatcherBuilder::new();
+        initialize_gui(&mut world, &mut ui_render_dispatcher_builder);
+
+        let GeneratedTerrain {
+            seed,
+            layout,
+            noise,
+            islands,
+            map,
+            moisture,
+            mut rng,
+            spawn_point,
+            stats,
+        } = terrain;
+
+        // Setup the font manager
+        let font_mgr = FontMgr::new()?;
+        let font = font_mgr.load_font("res/HelveticaNeue Medium.ttf", 24)?;
+
+        // Setup the mesh manager
+        let mut mesh_mgr = MeshMgr::new();
+        let prefabs = PrefabResource::new(&mut mesh_mgr, view_distance)?;
+        world.insert(prefabs.clone());
+
+        // Add entities, terrain chunks are built later on by the chunk streaming system
+        world.insert(ChunkResource::new(graphics_settings.chunk_load_radius));
+        let water_mesh = mesh_mgr.add_mesh(create_water_mesh());
+        let sky_mesh = mesh_mgr.add_mesh(create_sky_mesh());
+        world.insert(MeshMgrResource { data: mesh_mgr });
+        world.insert(ParticleResource::new(create_program(
+            include_str!("../../shaders/particle.vert"),
+            include_str!("../../shaders/particle.frag"),
+            PARTICLE_UNIFORMS,
+        )?));
+        world.insert(GrassResource::new(
+            create_program(
+                include_str!("../../shaders/grass.vert"),
+                include_str!("../../shaders/grass.frag"),
+                GRASS_UNIFORMS,
+            )?,
+            GRASS_DISTANCE,
+        ));
+        world.insert(SkyResource::new(
+            create_program(
+                include_str!("../../shaders/sky.vert"),
+                include_str!("../../shaders/sky.frag"),
+                &[MESH_UNIFORMS, SKY_UNIFORMS].concat(),
+            )?,
+            sky_mesh,
+        ));
+        // Inserted once everything is placed, since placing things asks it where the water is
+        let water = WaterResource::new(
+            create_program(
+                include_str!("../../shaders/water.vert"),
+                include_str!("../../shaders/water.frag"),
+                &[MESH_UNIFORMS, WATER_UNIFORMS].concat(),
+            )?,
+            water_mesh,
+            SEA_LEVEL,
+        );
+        world
+            .create_entity()
+            .with(
+                QuadComponent::from_texture(
+                    Texture::from_rgba(1, 1, &[200, 0, 0, 255]),
+                    1,
+                    1,
+                    prefabs.quad_mesh,
+                )
+                .in_layer(UiLayer::Flash, 0),
+            )
+            .with(PositionComponent {
+                pos: nalgebra_glm::zero(),
+            })
+            .with(DamageFlashComponent { seconds_left: 0.0 })
+            .build();
+        for track in [true, false] {
+            world
+                .create_entity()
+                .with(
+                    QuadComponent::from_texture(
+                        bar_texture(),
+                        PLAYER_BAR_WIDTH,
+                        PLAYER_BAR_HEIGHT,
+                        prefabs.quad_mesh,
+                    )
+                    // The bar goes over its track
+                    .in_layer(UiLayer::Hud, if track { 0 } else { 1 }),
+                )
+                .with(PositionComponent {
+                    pos: nalgebra_glm::zero(),
+                })
+                .with(LayoutComponent::new(
+                    Anchor::new(Align::Start, Align::Start),
+                    (PLAYER_BAR_MARGIN, PLAYER_BAR_MARGIN),
+                ))
+                .with(PlayerHealthBarComponent { track })
+                .build();
+        }
+        world
+            .create_entity()
+            .with(QuadComponent::from_text(
+                "+",
+                &font,
+                Color::RGBA(255, 255, 255, 255),
+                prefabs.quad_mesh,
+            ))
+            .with(PositionComponent {
+                pos: nalgebra_glm::zero(),
+            })
+            .build();
+        let mut hit_marker = QuadComponent::from_text(
+            "x",
+            &font,
+            Color::RGBA(255, 255, 255, 255),
+            prefabs.quad_mesh,
+        );
+        hit_marker.tint = nalgebra_glm::vec3(1.0, 0.3, 0.2);
+        hit_marker.opacity = 0.0;
+        world
+            .create_entity()
+            .with(hit_marker.in_layer(UiLayer::Hud, 1))
+            .with(PositionComponent {
+                pos: nalgebra_glm::zero(),
+            })
+            .with(HitMarkerComponent { seconds_left: 0.0 })
+            .build();
+        spawn_damage_indicator(&mut world);
+        spawn_swimming_hud(&mut world);
+        spawn_stamina_bar(&mut world);
+        world
+            .create_entity()
+            .with(QuadComponent::from_text(
+                "Collect all maps to win!",
+                &font,
+                Color::RGBA(255, 255, 255, 255),
+                prefabs.quad_mesh,
+            ))
+            .build();
+        world
+            .create_entity()
+            .with(QuadComponent::from_text(
+                " ",
+                &font,
+                Color::RGBA(255, 255, 255, 255),
+                prefabs.quad_mesh,
+            ))
+            .with(PositionComponent {
+                pos: nalgebra_glm::zero(),
+            })
+            // Kept in the top left corner
+            .with(LayoutComponent::new(
+                Anchor::new(Align::Start, Align::Start),
+                (8, 8),
+            ))
+            .with(DebugHudComponent {})
+            .build();
+        world
+            .create_entity()
+            .with(QuadComponent::from_text(
+                " ",
+                &font,
+                Color::RGBA(255, 255, 255, 255),
+                prefabs.quad_mesh,
+            ))
+            .with(PositionComponent {
+                pos: nalgebra_glm::zero(),
+            })
+            // Centered, a bit above the bottom of the screen
+            .with(LayoutComponent::new(
+                Anchor::new(Align::Center, Align::End),
+                (0, 48),
+            ))
+            .with(DialogComponent {})
+            .build();
+        world
+            .create_entity()
+            .with(QuadComponent::from_text(
+                " ",
+                &font,
+                Color::RGBA(255, 255, 255, 255),
+                prefabs.quad_mesh,
+            ))
+            .with(PositionComponent {
+                pos: nalgebra_glm::zero(),
+            })
+            // Centered, just below the treasure counter
+            .with(LayoutComponent::new(
+                Anchor::new(Align::Center, Align::Start),
+                (0, 112),
+            ))
+            .with(ToastComponent {})
+            .build();
+        update_dispatcher_builder.add_thread_local(DialogSystem {
+            font: font_mgr.load_font("res/HelveticaNeue Medium.ttf", 20)?,
+            text: String::new(),
+        });
+        update_dispatcher_builder.add_thread_local(ToastSystem {
+            font: font_mgr.load_font("res/HelveticaNeue Medium.ttf", 24)?,
+            text: String::new(),
+        });
+        for index in 0..HOTBAR_SLOTS {
+            world
+                .create_entity()
+                .with(QuadComponent::from_text(
+                    " ",
+                    &font,
+                    Color::RGBA(255, 255, 255, 255),
+                    prefabs.quad_mesh,
+                ))
+                .with(PositionComponent {
+                    pos: nalgebra_glm::zero(),
+                })
+                .with(slot_layout(index))
+                .with(HotbarSlotComponent { index })
+                .build();
+        }
+        world
+            .create_entity()
+            .with(QuadComponent::from_text(
+                " ",
+                &font,
+                Color::RGBA(255, 255, 255, 255),
+                prefabs.quad_mesh,
+            ))
+            .with(PositionComponent {
+                pos: nalgebra_glm::zero(),
+            })
+            .with(TextComponent::new("", HUD_FONT))
+            .with(LayoutComponent::new(
+                Anchor::new(Align::End, Align::End),
+                (READOUT_MARGIN, READOUT_MARGIN),
+            ))
+            .with(AmmoReadoutComponent)
+            .build();
+        for top in [true, false] {
+            world
+                .create_entity()
+                .with(
+                    QuadComponent::from_texture(
+                        Texture::from_rgba(1, 1, &[0, 0, 0, 255]),
+                        1,
+                        1,
+                        prefabs.quad_mesh,
+                    )
+                    .in_layer(UiLayer::Cinematic, 0),
+                )
+                .with(PositionComponent {
+                    pos: nalgebra_glm::zero(),
+                })
+                .with(LetterboxComponent { top })
+                .build();
+        }
+        update_dispatcher_builder.add_thread_local(HotbarSystem {
+            font: font_mgr.load_font("res/HelveticaNeue Medium.ttf", 20)?,
+            labels: Default::default(),
+        });
+        update_dispatcher_builder.add_thread_local(DebugHudSystem {
+            font: font_mgr.load_font("res/SourceCodePro.ttf", 16)?,
+            visible: false,
+            toggle_was_down: false,
+            copy_was_down: false,
+            text: String::new(),
+        });
+        update_dispatcher_builder.add_thread_local(TextSystem {
+            fonts: vec![font_mgr.load_font("res/HelveticaNeue Medium.ttf", 24)?],
+        });
+        world.insert(DiagnosticsResource::default());
+        world.insert(ProfileResource::default());
+        ui_render_dispatcher_builder.add_thread_local(DiagnosticsRenderSystem {
+            glyphs: GlyphCache::new(font_mgr.load_font("res/SourceCodePro.ttf", 16)?),
+        });
+        for _ in 0..(MAP_WIDTH * 4) {
+            // Add all the trees
+            let mut attempts = 0;
+            loop {
+                let pos = nalgebra_glm::vec2(
+                    rng.gen::<f32>() * (MAP_WIDTH as f32 - 1.0),
+                    rng.gen::<f32>() * (MAP_WIDTH as f32 - 1.0),
+                );
+                let height = map.get_z_interpolated(pos);
+                let dot_prod = map.get_dot_prod(pos).abs();
+                let variation = rng.gen_range(0.0..1.0);
+                let scale = (15.0 + 70.0 * variation) * UNIT_PER_METER;
+                let underwater = water.is_underwater(nalgebra_glm::vec3(pos.x, pos.y, height));
+                let biome = Biome::classify(height, dot_prod, moisture.get(pos), underwater);
+                // Nothing grows on the seabed
+                if rng.gen::<f32>() < biome.config().tree_chance && !underwater {
+                    spawn_tree(&mut world, nalgebra_glm::vec3(pos.x, pos.y, height), scale);
+                    break;
+                }
+                if attempts > 100 {
+                    break;
+                }
+                attempts += 1;
+            }
+        }
+        for _ in 0..(MAP_WIDTH * 2) {
+            // Add all the bushes
+            let mut attempts = 0;
+            loop {
+                let pos = nalgebra_glm::vec2(
+                    rng.gen::<f32>() * (MAP_WIDTH as f32 - 1.0),
+                    rng.gen::<f32>() * (MAP_WIDTH as f32 - 1.0),
+                );
+                let height = map.get_z_interpolated(pos);
+                let dot_prod = map.get_dot_prod(pos).abs();
+                let variation = rng.gen_range(0.0..1.0);
+                let underwater = water.is_underwater(nalgebra_glm::vec3(pos.x, pos.y, height));
+                let biome = Biome::classify(height, dot_prod, moisture.get(pos), underwater);
+                if rng.gen::<f32>() < biome.config().bush_chance && !underwater {
+                    let scale = (3.5 + 7.0 * variation) * UNIT_PER_METER;
+                    spawn_bush(&mut world, nalgebra_glm::vec3(pos.x, pos.y, height), scale);
+                    break;
+                }
+                if attempts > 100 {
+                    break;
+                }
+                attempts += 1;
+            }
+        }
+        const NUM_TREASURE: usize = MAP_WIDTH / 51;
+        const MAP_ICON_SPACING: i32 = 96; //< Pixels between the map icons along the top of the screen
+        let mut castaway_spawned = false;
+        let mut treasure_sites = vec![];
+        for i in 0..NUM_TREASURE {
+            // Add all the treasure boxes
+            let mut attempts = 0;
+            // An archipelago's chests are dealt out across its islands in turn, so the player has to swim between them
+            let island = i % islands.len();
+            loop {
+                let pos = if islands.len() == 1 {
+                    nalgebra_glm::vec2(
+                        rng.gen::<f32>() * (MAP_WIDTH as f32 - 1.0),
+                        rng.gen::<f32>() * (MAP_WIDTH as f32 - 1.0),
+                    )
+                } else {
+                    // Anywhere the island's shore could have been pushed out to
+                    let reach = islands[island].radius * 2.0;
+                    let offset = nalgebra_glm::vec2(rng.gen::<f32>(), rng.gen::<f32>()) * 2.0
+                        - nalgebra_glm::vec2(1.0, 1.0);
+                    nalgebra_glm::clamp(
+                        &(islands[island].center + offset * reach),
+                        0.0,
+                        MAP_WIDTH as f32 - 1.0,
+                    )
+                };
+                let height = map.get_z_interpolated(pos);
+                if is_treasure_spot(&map, water.level, pos) && Bulge::owner(&islands, pos) == island
+                {
+                    // Add treasure
+                    // The first chests lie out in the open with the tools in them
+                    let chest = ChestComponent {
+                        buried: i >= Tool::ALL.len(),
+                        contents: Tool::ALL.get(i).copied(),
+                    };
+                    let treasure_entity =
+                        spawn_treasure(&mut world, nalgebra_glm::vec3(pos.x, pos.y, height), chest);
+                    treasure_sites.push(nalgebra_glm::vec3(pos.x, pos.y, height));
+                    // Add corresponding map
+                    spawn_treasure_map(
+                        &mut world,
+                        treasure_entity,
+                        (2 * i as i32 - (NUM_TREASURE as i32 - 1)) * MAP_ICON_SPACING / 2,
+                    );
+
+                    // Add skeletons to guard it
+                    const NUM_MOBS: usize = 5;
+                    for _ in 0..NUM_MOBS {
+                        let (x, y) = (
+                            rng.gen::<f32>() - 0.5 + pos.x,
+                            rng.gen::<f32>() - 0.5 + pos.y,
+                        );
+                        // Mobs stand guard facing out from the camp
+                        let facing = (y - pos.y).atan2(x - pos.x);
+                        spawn_mob(
+                            &mut world,
+                            MobKind::Skeleton,
+                            nalgebra_glm::vec3(x, y, height),
+                            facing,
+                        );
+                    }
+
+                    // The first mob camp also has a castaway stuck next to it
+                    if !castaway_spawned {
+                        let castaway_pos = pos + nalgebra_glm::vec2(0.4, 0.4);
+                        let castaway_height = map.get_z_interpolated(castaway_pos);
+                        spawn_castaway(
+                            &mut world,
+                            nalgebra_glm::vec3(castaway_pos.x, castaway_pos.y, castaway_height),
+                            spawn_point,
+                        );
+                        castaway_spawned = true;
+                    }
+                    break;
+                }
+                if attempts > 100 {
+                    break;
+                }
+                attempts += 1;
+            }
+        }
+        dress_treasure_sites(&mut world, &treasure_sites, &map, &water, seed);
+        spawn_bush_walls(&mut world, &map, &moisture, &water, seed);
+
+        // Add the trader, just beside where the player starts, on dry land even if that's right on the shore
+        let beside_spawn = spawn_point.xy() + nalgebra_glm::vec2(0.3, 0.0);
+        let trader_pos =
+            nearest_land(&map, water.level, beside_spawn, 2.0).unwrap_or(spawn_point.xy());
+        spawn_trader(
+            &mut world,
+            nalgebra_glm::vec3(
+                trader_pos.x,
+                trader_pos.y,
+                map.get_z_interpolated(trader_pos),
+            ),
+        );
+
+        // Add a practice range behind the trader, every other target sliding side to side
+        for i in 0..4 {
+            let target_pos = spawn_point.xy() + nalgebra_glm::vec2(0.8, -0.3 + 0.2 * i as f32);
+            let ground = map.get_z_interpolated(target_pos);
+            spawn_target(
+                &mut world,
+                nalgebra_glm::vec3(target_pos.x, target_pos.y, ground + TARGET_SCALE.z),
+                if i % 2 == 1 { 0.05 } else { 0.0 },
+            );
+        }
+
+        // Add the player
+        spawn_player(&mut world, spawn_point);
+        spawn_view_models(&mut world);
+
+        // Add the minimap, with hint circles under a marker for each treasure, and one for the player on top
+        spawn_minimap(&mut world, render_minimap_texture(&map, &moisture, &water));
+        world.insert(terrain_overlay_resource(&moisture));
+        world.insert(terrain_textures_resource(&map, &moisture, water.level));
+        world.insert(NavGridResource::new(&map, water.level));
+        spawn_treasure_counter(&mut world);
+        let treasure_maps: Vec<Entity> = (
+            &world.read_storage::<TreasureMapComponent>(),
+            &world.entities(),
+        )
+            .join()
+            .map(|(_, entity)| entity)
+            .collect();
+        for treasure_map in &treasure_maps {
+            spawn_minimap_marker(&mut world, MinimapMarker::Hint(*treasure_map));
+        }
+        for treasure_map in &treasure_maps {
+            spawn_minimap_marker(&mut world, MinimapMarker::Treasure(*treasure_map));
+        }
+        spawn_compass(&mut world, &treasure_maps);
+        // Add the maps that can be held up, each sketched around its chest
+        for treasure_map in &treasure_maps {
+            let chest = world
+                .read_storage::<TreasureMapComponent>()
+                .get(*treasure_map)
+                .unwrap()
+                .treasure_entity;
+            let chest_pos = world
+                .read_storage::<PositionComponent>()
+                .get(chest)
+                .unwrap()
+                .pos;
+            let texture = render_map_view_texture(&map, water.level, chest_pos.xy());
+            spawn_map_view(&mut world, *treasure_map, texture);
+        }
+        for treasure_map in treasure_maps {
+            spawn_chest_indicator(&mut world, treasure_map);
+        }
+        spawn_minimap_marker(&mut world, MinimapMarker::Player);
+        spawn_interact_prompt(&mut world);
+
+        let summary = WorldSummaryResource::count(&world, seed, stats);
+        println!("{}", summary.terrain_line());
+        println!("{}", summary.entity_line());
+        world.insert(summary);
+
+        // Add resources
+        world.insert(water);
+        world.insert(App::default());
+        for (file_path, variation) in SOUND_VARIATIONS {
+            audio_mgr.set_variation(file_path, *variation);
+        }
+        world.insert(AudioResource { audio_mgr });
+        world.insert(OpenGlResource {
+            camera: Camera::new(
+                spawn_point,
+                nalgebra_glm::vec3(MAP_WIDTH as f32 / 2.0, MAP_WIDTH as f32 / 2.0, 0.5),
+                nalgebra_glm::vec3(0.0, 0.0, 1.0),
+                ProjectionKind::Perspective {
+                    fov: ViewSettings::default().fov,
+                },
+            ),
+            program: create_program(
+                include_str!("../../shaders/3d.vert"),
+                include_str!("../../shaders/3d.frag"),
+                &[MESH_UNIFORMS, LIT_UNIFORMS].concat(),
+            )?,
+        });
+        world.insert(UIResource::new()?);
+        world.insert(PerlinMapResource {
+            map: Arc::new(map),
+            moisture: Arc::new(moisture),
+        });
+        world.insert(SeedResource {
+            seed,
+            layout,
+            noise,
+        });
+        world.insert(GoldResource::default());
+        world.insert(ParrotResource::default());
+        world.insert(DialogResource::default());
+        world.insert(ToastResource::default());
+        world.insert(JournalResource::default());
+        world.insert(NoiseResource::default());
+        world.insert(LightResource::default());
+        world.insert(GoalResource::default());
+        world.insert(StatsResource::default());
+        world.insert(CinematicResource::default());
+        world.insert(WeatherResource::default());
+        world.insert(TimeResource::default());
+        world.insert(InputMap::default());
+        world.insert(ViewSettings::default());
+        world.insert(GameplaySettings::default());
+        let sun_scale = 30.0;
+        world.insert(SunResource::new(
+            Camera::new(
+                nalgebra_glm::vec3(MAP_WIDTH as f32 / -2.0, 0.0, 2.0),
+                nalgebra_glm::vec3(MAP_WIDTH as f32 / 2.0, MAP_WIDTH as f32 / 2.0, 0.5),
+                nalgebra_glm::vec3(0.0, 0.0, 1.0),
+                ProjectionKind::Orthographic {
+                    left: -sun_scale,
+                    right: sun_scale,
+                    bottom: -sun_scale,
+                    top: sun_scale,
+                    near: 0.01,
+                    far: 5000.0,
+                },
+            ),
+            create_program(
+                include_str!("../../shaders/shadow.vert"),
+                include_str!("../../shaders/shadow.frag"),
+                MESH_UNIFORMS,
+            )?,
+            graphics_settings.shadow_size,
+            nalgebra_glm::vec3(0.0, 0.0, 1.0),
+        ));
+        world.insert(graphics_settings);
+
+        Ok(Self {
+            world,
+            update_dispatcher: update_dispatcher_builder.build(),
+            render_dispatcher: render_dispatcher_builder.build(),
+            ui_render_dispatcher: ui_render_dispatcher_builder.build(),
+            snapshot_key_was_down: false,
+            save_key_was_down: false,
+            load_key_was_down: false,
+            profile_key_was_down: false,
+            skip_key_was_down: false,
+            options_key_was_down: false,
+            journal_key_was_down: false,
+            settings_stale: false,
+        })
+    }
+
+    /// What went into the island when it was generated
+    pub(crate) fn summary(&self) -> WorldSummaryResource {
+        (*self.world.read_resource::<WorldSummaryResource>()).clone()
+    }
+
+    /// Flies over the island past each treasure before handing the camera to the player. The player can't be hurt
+    /// until it's over.
+    pub(crate) fn play_intro(&mut self) {
+        let treasures: Vec<nalgebra_glm::Vec3> = {
+            let treasure_maps = self.world.read_storage::<TreasureMapComponent>();
+            let positions = self.world.read_storage::<PositionComponent>();
+            (&treasure_maps)
+                .join()
+                .filter_map(|map| positions.get(map.treasure_entity).map(|p| p.pos))
+                .collect()
+        };
+        let Some((facing, position)) = (
+            &self.world.read_storage::<PlayerComponent>(),
+            &self.world.read_storage::<PositionComponent>(),
+        )
+            .join()
+            .next()
+            .map(|(player, position)| (player.facing, position.pos))
+        else {
+            return;
+        };
+        let eye = position + nalgebra_glm::vec3(0.0, 0.0, PERSON_HEIGHT);
+        let lookat = eye + nalgebra_glm::vec3(facing.cos(), facing.sin(), 0.0);
+        let path = intro_path(&treasures, eye, lookat);
+        for player in (&mut self.world.write_storage::<PlayerComponent>()).join() {
+            player.invulnerable_for = path.duration();
+        }
+        self.world.write_resource::<CinematicResource>().play(path);
+    }
+
+    /// Puts back everything the player changed before saving. The island has to be generated from the save's seed.
+    pub(crate) fn restore(&mut self, save: &SaveGame) {
+        save.apply(&mut self.world);
+        let mut opened = Vec::new();
+        {
+            let treasure_maps = self.world.read_storage::<TreasureMapComponent>();
+            let positions = self.world.read_storage::<PositionComponent>();
+            let mut quads = self.world.write_storage::<QuadComponent>();
+            let prefabs = self.world.read_resource::<PrefabResource>();
+            for (treasure_map, quad) in (&treasure_maps, &mut quads).join() {
+                if treasure_map.found {
+                    quad.texture = prefabs.texture(PrefabTexture::Gold);
+                    opened.extend(positions.get(treasure_map.treasure_entity).map(|p| p.pos));
+                }
+            }
+        }
+        for chest_pos in opened {
+            spawn_chest_lid(&mut self.world, chest_pos, true);
+        }
+    }
+
+    /// Writes every gameplay component in the world to the snapshots directory
+    fn dump_snapshot(&self, tick: usize) {
+        let registry = self.world.read_resource::<ComponentRegistry>();
+        let snapshot = WorldSnapshot::capture(tick, &self.world, &registry);
+
+        let path = format!("snapshots/tick-{}.ron", tick);
+        let result = std::fs::create_dir_all("snapshots")
+            .map_err(|e| e.to_string())
+            .and_then(|_| snapshot.save(&path));
+        match result {
+            Ok(()) => println!("Saved snapshot to {}", path),
+            Err(err) => println!("Couldn't save snapshot: {}", err),
+        }
+    }
+}
+
+/// Whether treasure can be put at a spot: on a gentle slope, a little above the water
+fn is_treasure_spot(map: &PerlinMap, water_level: f32, pos: nalgebra_glm::Vec2) -> bool {
+    let height = map.get_z_interpolated(pos);
+    let dot_prod = map.get_dot_prod(pos).abs();
+    let above_water = height - water_level;
+    (0.0..=0.3).contains(&above_water) && height < 0.75 * dot_prod
+}
+
+/// The closest spot to `pos` that's above the water, looking in steps of a tenth of a tile out to `max_dist` tiles
+fn nearest_land(
+    map: &PerlinMap,
+    water_level: f32,
+    pos: nalgebra_glm::Vec2,
+    max_dist: f32,
+) -> Option<nalgebra_glm::Vec2> {
+    const STEP: f32 = 0.1;
+    let steps = (max_dist / STEP).ceil() as i32;
+    let dist = |p: &nalgebra_glm::Vec2| nalgebra_glm::distance(p, &pos);
+    (-steps..=steps)
+        .flat_map(|y| (-steps..=steps).map(move |x| nalgebra_glm::vec2(x as f32, y as f32) * STEP))
+        .map(|offset| pos + offset)
+        .filter(|p| {
+            dist(p) <= max_dist && !map.oob(*p) && map.get_z_interpolated(*p) >= water_level
+        })
+        .min_by(|a, b| dist(a).total_cmp(&dist(b)))
+}
+
+/// Blocks off some valleys with walls of bushes, which need the machete to get through. Uses its own rng, so that
+/// the rest of the island stays the same for a seed.
+fn spawn_bush_walls(
+    world: &mut World,
+    map: &PerlinMap,
+    moisture: &MoistureMap,
+    water: &WaterResource,
+    seed: u64,
+) {
+    const NUM_WALLS: usize = MAP_WIDTH / 100;
+    const BUSHES_PER_WALL: i32 = 21;
+    const SPACING: f32 = 1.2 * UNIT_PER_METER;
+    const SCALE: f32 = 6.0 * UNIT_PER_METER;
+    let mut rng = StdRng::seed_from_u64(seed ^ 0xb05e_3a11);
+
+    for _ in 0..NUM_WALLS {
+        for _ in 0..1000 {
+            let pos = nalgebra_glm::vec2(
+                rng.gen::<f32>() * (MAP_WIDTH as f32 - 1.0),
+                rng.gen::<f32>() * (MAP_WIDTH as f32 - 1.0),
+            );
+            let height = map.get_z_interpolated(pos);
+            let above_water = -water.depth(nalgebra_glm::vec3(pos.x, pos.y, height));
+            // Valleys are where a lot of water flowed during erosion
+            if !(0.05..=0.3).contains(&above_water) || moisture.get(pos) < 5.0 {
+                continue;
+            }
+
+            // The wall goes across the valley, which is sideways to downhill
+            let downhill = map.get_normal(pos).xy();
+            if nalgebra_glm::length(&downhill) < 0.0001 {
+                continue;
+            }
+            let across = nalgebra_glm::vec2(-downhill.y, downhill.x).normalize();
+            for j in -BUSHES_PER_WALL / 2..=BUSHES_PER_WALL / 2 {
+                let bush_pos = pos + across * (j as f32 * SPACING);
+                if map.oob(bush_pos) {
+                    continue;
+                }
+                let bush_height = map.get_z_interpolated(bush_pos);
+                let bush_pos = nalgebra_glm::vec3(bush_pos.x, bush_pos.y, bush_height);
+                if !water.is_underwater(bush_pos) {
+                    spawn_wall_bush(world, bush_pos, SCALE);
+                }
+            }
+            break;
+        }
+    }
+}
+
+/// Builds the vertex data for a terrain chunk. Only needs the water level, so it can be run off the main thread.
+fn create_mesh(
+    tiles: &PerlinMapResource,
+    water_level: f32,
+    chunk_x: usize,
+    chunk_y: usize,
+) -> (Vec<u32>, Vec<f32>, Vec<f32>, Vec<f32>, Vec<f32>) {
+    let mut indices = Vec::<u32>::new();
+    let mut vertices = Vec::<f32>::new();
+    let mut normals = Vec::<f32>::new();
+    let mut uv = Vec::<f32>::new();
+    let mut colors = Vec::<f32>::new();
+
+    // Baked once per corner, since each is shared by up to six triangles
+    let corners = CHUNK_SIZE + 1;
+    let occlusion: Vec<f32> = (0..corners * corners)
+        .map(|i| {
+            let corner = nalgebra_glm::vec2(
+                (chunk_x + i % corners) as f32,
+                (chunk_y + i / corners) as f32,
+            );
+            tiles.map.occlusion(corner)
+        })
+        .collect();
+
+    let mut i = 0;
+    for y in 0..CHUNK_SIZE {
+        let y = y + chunk_y;
+        for x in 0..CHUNK_SIZE {
+            let x = x + chunk_x;
+            // Left triangle |\
+            let offsets = vec![(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)];
+            add_triangle(
+                tiles,
+                water_level,
+                &mut indices,
+                &mut vertices,
+                &mut normals,
+                &mut uv,
+                &mut colors,
+                &occlusion,
+                x as f32,
+                y as f32,
+                chunk_x as f32,
+                chunk_y as f32,
+                &offsets,
+                &mut i,
+            );
+
+            // Right triangle \|
+            let offsets = vec![(1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+            add_triangle(
+                tiles,
+                water_level,
+                &mut indices,
+                &mut vertices,
+                &mut normals,
+                &mut uv,
+                &mut colors,
+                &occlusion,
+                x as f32,
+                y as f32,
+                chunk_x as f32,
+                chunk_y as f32,
+                &offsets,
+                &mut i,
+            );
+        }
+    }
+
+    (indices, vertices, normals, uv, colors)
+}
+
+fn add_triangle(
+    tiles: &PerlinMapResource,
+    water_level: f32,
+    indices: &mut Vec<u32>,
+    vertices: &mut Vec<f32>,
+    normals: &mut Vec<f32>,
+    uv: &mut Vec<f32>,
+    colors: &mut Vec<f32>,
+    occlusion: &[f32],
+    x: f32,
+    y: f32,
+    chunk_x: f32,
+    chunk_y: f32,
+    offsets: &Vec<(f32, f32)>,
+    i: &mut u32,
+) {
+    let mut sum_z = 0.0;
+    let tri_verts: Vec<nalgebra_glm::Vec3> = offsets
+        .iter()
+        .map(|(xo, yo)| {
+            let z = tiles.map.height(nalgebra_glm::vec2(x + xo, y + yo));
+            let mapval = nalgebra_glm::vec3(x + xo, y + yo, z);
+            sum_z += tiles.map.height(nalgebra_glm::vec2(x + xo, y + yo));
+            let (local_x, local_y) = (x + xo - chunk_x, y + yo - chunk_y);
+            add_vertex(vertices, local_x, local_y, z);
+            let corner = local_x as usize + local_y as usize * (CHUNK_SIZE + 1);
+            add_uv(uv, *xo as f32, *yo as f32, occlusion[corner]);
+            indices.push(*i);
+            *i += 1;
+            mapval
+        })
+        .collect();
+
+    let edge1 = tri_verts[1] - tri_verts[0];
+    let edge2 = tri_verts[2] - tri_verts[0];
+    let normal = nalgebra_glm::cross(&edge1, &edge2).normalize();
+    for _ in 0..3 {
+        normals.push(normal.x);
+        normals.push(normal.y);
+        normals.push(normal.z);
+    }
+    // 0 = steep
+    // 1 = flat
+    let dot_prod = nalgebra_glm::dot(&normal, &nalgebra_glm::vec3(0.0, 0.0, 1.0));
+
+    let avg_z = sum_z / 3.0;
+    let biome = Biome::classify(
+        avg_z,
+        dot_prod,
+        tiles.moisture.get(nalgebra_glm::vec2(x, y)),
+        avg_z < water_level,
+    );
+    for _ in 0..3 {
+        colors.extend(biome.config().color);
+    }
+}
+
+fn add_vertex(vertices: &mut Vec<f32>, x: f32, y: f32, z: f32) {
+    vertices.push(x);
+    vertices.push(y);
+    vertices.push(z);
+}
+
+/// The third texture coordinate is the baked ambient occlusion, which is 0 for everything but the terrain
+fn add_uv(uv: &mut Vec<f32>, x: f32, y: f32, occlusion: f32) {
+    uv.push(x);
+    uv.push(y);
+    uv.push(occlusion);
+}
@@ -0,0 +1,223 @@
+// Finding a way around the island, for mobs that can't go straight to where they want to be. The island is split into a
+// coarse grid of cells that can be walked on or not, from their height and slope, and paths are found over it with A*,
+// then smoothed so that mobs don't zigzag from cell to cell.
+
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use crate::engine::perlin::PerlinMap;
+
+pub(super) const CELL_SIZE: f32 = 0.5; //< Tiles across a cell, 10 meters
+const MIN_NORMAL_Z: f32 = 0.75; //< Ground less upright than this is too steep to walk up
+const MAX_SEARCH: usize = 4000; //< Cells looked at before giving up on a path, so that a hopeless one doesn't hitch
+
+/// Which cells of the island can be walked on
+#[derive(Default)]
+pub(super) struct NavGridResource {
+    width: usize, //< Cells across the grid, and down it
+    walkable: Vec<bool>,
+}
+
+impl NavGridResource {
+    /// Cells are walkable when their middle is out of the water and not too steep
+    pub fn new(map: &PerlinMap, water_level: f32) -> Self {
+        let width = (map.width() as f32 / CELL_SIZE) as usize;
+        let walkable = (0..width * width)
+            .map(|i| {
+                let center = Self::center((i % width, i / width));
+                map.get_z_interpolated(center) >= water_level
+                    && map.get_normal(center).z >= MIN_NORMAL_Z
+            })
+            .collect();
+        Self { width, walkable }
+    }
+
+    fn cell(&self, pos: nalgebra_glm::Vec2) -> Option<(usize, usize)> {
+        let cell = pos / CELL_SIZE;
+        let in_grid = (0.0..self.width as f32).contains(&cell.x)
+            && (0.0..self.width as f32).contains(&cell.y);
+        in_grid.then_some((cell.x as usize, cell.y as usize))
+    }
+
+    fn center(cell: (usize, usize)) -> nalgebra_glm::Vec2 {
+        nalgebra_glm::vec2(cell.0 as f32 + 0.5, cell.1 as f32 + 0.5) * CELL_SIZE
+    }
+
+    fn walkable_cell(&self, cell: (usize, usize)) -> bool {
+        self.walkable[cell.0 + cell.1 * self.width]
+    }
+
+    pub fn is_walkable(&self, pos: nalgebra_glm::Vec2) -> bool {
+        self.cell(pos).is_some_and(|cell| self.walkable_cell(cell))
+    }
+
+    /// Whether walking straight from one spot to another only crosses walkable cells. The cell the walk starts in
+    /// doesn't count, so that a mob that's strayed onto a slope can still walk off it.
+    pub fn line_clear(&self, from: nalgebra_glm::Vec2, to: nalgebra_glm::Vec2) -> bool {
+        let start = self.cell(from);
+        let steps = (nalgebra_glm::distance(&from, &to) / (CELL_SIZE * 0.5)).ceil() as usize;
+        (1..=steps).all(|i| {
+            let pos = nalgebra_glm::lerp(&from, &to, i as f32 / steps as f32);
+            self.cell(pos) == start || self.is_walkable(pos)
+        })
+    }
+
+    /// The spots to walk through in order to get from one place to another, ending at `to`. None if there's no way
+    /// there, or it's too far round to find.
+    pub fn find_path(
+        &self,
+        from: nalgebra_glm::Vec2,
+        to: nalgebra_glm::Vec2,
+    ) -> Option<Vec<nalgebra_glm::Vec2>> {
+        let (start, goal) = (self.cell(from)?, self.cell(to)?);
+        if !self.walkable_cell(goal) {
+            return None;
+        }
+        let index = |cell: (usize, usize)| cell.0 + cell.1 * self.width;
+        // Octile distance, which never overestimates with diagonal steps allowed
+        let estimate = |cell: (usize, usize)| {
+            let dx = cell.0.abs_diff(goal.0) as f32;
+            let dy = cell.1.abs_diff(goal.1) as f32;
+            dx.max(dy) + (std::f32::consts::SQRT_2 - 1.0) * dx.min(dy)
+        };
+        // Costs are kept in thousandths of a cell in the queue, since floats can't be ordered
+        let key = |cost: f32| (cost * 1000.0) as u32;
+
+        let mut cost_to = vec![f32::INFINITY; self.walkable.len()];
+        let mut came_from = vec![usize::MAX; self.walkable.len()];
+        let mut open = BinaryHeap::new();
+        cost_to[index(start)] = 0.0;
+        open.push(Reverse((key(estimate(start)), start)));
+        let mut searched = 0;
+        while let Some(Reverse((_, cell))) = open.pop() {
+            if cell == goal {
+                return Some(self.smooth(
+                    from,
+                    to,
+                    self.trace(&came_from, index(start), index(goal)),
+                ));
+            }
+            searched += 1;
+            if searched > MAX_SEARCH {
+                return None;
+            }
+            for (dx, dy) in [
+                (-1, 0),
+                (1, 0),
+                (0, -1),
+                (0, 1),
+                (-1, -1),
+                (1, -1),
+                (-1, 1),
+                (1, 1),
+            ] {
+                let (Some(x), Some(y)) =
+                    (cell.0.checked_add_signed(dx), cell.1.checked_add_signed(dy))
+                else {
+                    continue;
+                };
+                if x >= self.width || y >= self.width || !self.walkable_cell((x, y)) {
+                    continue;
+                }
+                // No cutting corners past cells that can't be walked on
+                let diagonal = dx != 0 && dy != 0;
+                if diagonal
+                    && (!self.walkable_cell((x, cell.1)) || !self.walkable_cell((cell.0, y)))
+                {
+                    continue;
+                }
+                let step = if diagonal {
+                    std::f32::consts::SQRT_2
+                } else {
+                    1.0
+                };
+                let cost = cost_to[index(cell)] + step;
+                if cost < cost_to[index((x, y))] {
+                    cost_to[index((x, y))] = cost;
+                    came_from[index((x, y))] = index(cell);
+                    open.push(Reverse((key(cost + estimate((x, y))), (x, y))));
+                }
+            }
+        }
+        None
+    }
+
+    /// The middles of the cells from the start to the goal, not counting the start
+    fn trace(&self, came_from: &[usize], start: usize, goal: usize) -> Vec<nalgebra_glm::Vec2> {
+        let mut cells = vec![];
+        let mut at = goal;
+        while at != start {
+            cells.push(Self::center((at % self.width, at / self.width)));
+            at = came_from[at];
+        }
+        cells.reverse();
+        cells
+    }
+
+    /// Skips the cells along a path that can be walked past in a straight line, ending at `to` itself
+    fn smooth(
+        &self,
+        from: nalgebra_glm::Vec2,
+        to: nalgebra_glm::Vec2,
+        mut points: Vec<nalgebra_glm::Vec2>,
+    ) -> Vec<nalgebra_glm::Vec2> {
+        match points.last_mut() {
+            Some(last) => *last = to,
+            None => points.push(to),
+        }
+        let mut smoothed = vec![];
+        let mut at = from;
+        let mut i = 0;
+        while i < points.len() {
+            // The furthest point along that can be walked to straight from here
+            let furthest = (i..points.len())
+                .rev()
+                .find(|j| self.line_clear(at, points[*j]))
+                .unwrap_or(i);
+            at = points[furthest];
+            smoothed.push(at);
+            i = furthest + 1;
+        }
+        smoothed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A grid with a wall down the middle, with a gap at the top
+    fn walled_grid() -> NavGridResource {
+        let width = 20;
+        let walkable = (0..width * width)
+            .map(|i| !(i % width == 10 && i / width < 15))
+            .collect();
+        NavGridResource { width, walkable }
+    }
+
+    #[test]
+    fn paths_go_around_walls() {
+        let grid = walled_grid();
+        let from = NavGridResource::center((5, 2));
+        let to = NavGridResource::center((15, 2));
+        assert!(!grid.line_clear(from, to));
+        let path = grid.find_path(from, to).unwrap();
+        assert_eq!(*path.last().unwrap(), to);
+        // Through the gap, and straight there in a few legs rather than cell by cell
+        assert!(path.iter().any(|p| p.y > 15.0 * CELL_SIZE));
+        assert!(path.len() <= 4);
+        let mut at = from;
+        for point in path {
+            assert!(grid.line_clear(at, point));
+            at = point;
+        }
+    }
+
+    #[test]
+    fn unwalkable_goals_have_no_path() {
+        let grid = walled_grid();
+        let from = NavGridResource::center((5, 2));
+        assert!(grid
+            .find_path(from, NavGridResource::center((10, 2)))
+            .is_none());
+    }
+}
@@ -0,0 +1,250 @@
+// The parrot. Once it's bought from the trader, it rides on the player's shoulder, and every so often flies off
+// squawking toward the nearest treasure that hasn't been found. After a few seconds it turns back, and lands on the
+// player's shoulder again. It stays home while it's turned off in the options.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use specs::{prelude::*, Component};
+
+use crate::engine::{
+    audio::AudioResource,
+    perlin::PerlinMapResource,
+    physics::{ParentComponent, PositionComponent},
+    settings::GameplaySettings,
+    time::TimeResource,
+};
+
+use super::{
+    prefabs::spawn_parrot, PlayerComponent, PrefabResource, TreasureMapComponent, UNIT_PER_METER,
+};
+
+pub(super) const PARROT_PRICE: u32 = 35;
+const SHOULDER_HEIGHT: f32 = 1.45 * UNIT_PER_METER;
+const SHOULDER_WIDTH: f32 = 0.25 * UNIT_PER_METER; //< How far right of the middle of the player the parrot sits
+const PERCH_SECONDS_MIN: f32 = 30.0; //< Shortest time between flights
+const PERCH_SECONDS_MAX: f32 = 60.0;
+const FLY_SECONDS: f32 = 4.0; //< How long the parrot flies toward the treasure before turning back
+const FLY_SPEED: f32 = 12.0 * UNIT_PER_METER; //< Meters per second
+const FLY_HEIGHT: f32 = 4.0 * UNIT_PER_METER; //< Meters above the ground, on the way out
+const SQUAWK_PITCH: f32 = 1.8;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum ParrotState {
+    Perched,
+    Flying,    //< Off toward the treasure
+    Returning, //< Back to the player's shoulder
+}
+
+#[derive(Component)]
+#[storage(HashMapStorage)]
+pub(super) struct ParrotComponent {
+    state: ParrotState,
+    seconds_left: f32, //< Until the parrot flies off, or turns back
+}
+
+impl ParrotComponent {
+    pub fn perched(seconds: f32) -> Self {
+        Self {
+            state: ParrotState::Perched,
+            seconds_left: seconds,
+        }
+    }
+}
+
+/// Whether the player has bought the parrot
+#[derive(Default)]
+pub(super) struct ParrotResource {
+    pub owned: bool,
+}
+
+/// Where the parrot sits, relative to the player's feet, for a player facing `facing`
+pub(super) fn shoulder_offset(facing: f32) -> nalgebra_glm::Vec3 {
+    let right = nalgebra_glm::vec2(facing.sin(), -facing.cos());
+    nalgebra_glm::vec3(right.x, right.y, 0.0) * SHOULDER_WIDTH
+        + nalgebra_glm::vec3(0.0, 0.0, SHOULDER_HEIGHT)
+}
+
+/// Moves `from` toward `to` by up to `step`, and returns whether it got there
+fn fly_toward(from: &mut nalgebra_glm::Vec3, to: nalgebra_glm::Vec3, step: f32) -> bool {
+    let to_target = to - *from;
+    let dist = nalgebra_glm::length(&to_target);
+    if dist <= step {
+        *from = to;
+        return true;
+    }
+    *from += to_target / dist * step;
+    false
+}
+
+/// Brings the parrot along when it's bought and turned on, and sends it off to point out treasure
+pub(super) struct ParrotSystem {
+    rng: StdRng, //< How long the parrot sits between flights
+}
+
+impl ParrotSystem {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed ^ 0x9a77_0077),
+        }
+    }
+
+    fn perch_seconds(&mut self) -> f32 {
+        self.rng.gen_range(PERCH_SECONDS_MIN..PERCH_SECONDS_MAX)
+    }
+}
+
+impl<'a> System<'a> for ParrotSystem {
+    type SystemData = (
+        WriteStorage<'a, ParrotComponent>,
+        WriteStorage<'a, ParentComponent>,
+        WriteStorage<'a, PositionComponent>,
+        ReadStorage<'a, PlayerComponent>,
+        ReadStorage<'a, TreasureMapComponent>,
+        Read<'a, ParrotResource>,
+        Read<'a, GameplaySettings>,
+        Read<'a, PerlinMapResource>,
+        Read<'a, TimeResource>,
+        Read<'a, AudioResource>,
+        Read<'a, PrefabResource>,
+        Read<'a, LazyUpdate>,
+        Entities<'a>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            mut parrots,
+            mut parents,
+            mut positions,
+            players,
+            treasure_maps,
+            bought,
+            settings,
+            tiles,
+            time,
+            audio,
+            prefabs,
+            lazy,
+            entities,
+        ): Self::SystemData,
+    ) {
+        let Some((player, player_position, player_entity)) =
+            (&players, &positions, &entities).join().next()
+        else {
+            return;
+        };
+        let player_pos = player_position.pos;
+        let shoulder = shoulder_offset(player.facing);
+
+        let wanted = bought.owned && settings.parrot;
+        if !wanted {
+            for (_, entity) in (&parrots, &entities).join() {
+                entities.delete(entity).unwrap();
+            }
+            return;
+        }
+        if (&parrots).join().next().is_none() {
+            let seconds = self.perch_seconds();
+            spawn_parrot(
+                &entities,
+                &lazy,
+                &prefabs,
+                player_entity,
+                player_pos + shoulder,
+                shoulder,
+                seconds,
+            );
+            return;
+        }
+
+        let nearest_treasure = (&treasure_maps)
+            .join()
+            .filter(|map| !map.found)
+            .filter_map(|map| positions.get(map.treasure_entity).map(|p| p.pos))
+            .min_by(|a, b| {
+                let a = nalgebra_glm::distance(a, &player_pos);
+                let b = nalgebra_glm::distance(b, &player_pos);
+                a.total_cmp(&b)
+            });
+        let step = FLY_SPEED * time.dt;
+        for (parrot, entity) in (&mut parrots, &entities).join() {
+            parrot.seconds_left -= time.dt;
+            match parrot.state {
+                ParrotState::Perched => {
+                    if let Some(parent) = parents.get_mut(entity) {
+                        parent.offset = shoulder;
+                    }
+                    if parrot.seconds_left > 0.0 {
+                        continue;
+                    }
+                    if nearest_treasure.is_none() {
+                        parrot.seconds_left = self.perch_seconds();
+                        continue;
+                    }
+                    parents.remove(entity);
+                    parrot.state = ParrotState::Flying;
+                    parrot.seconds_left = FLY_SECONDS;
+                    audio
+                        .audio_mgr
+                        .play_sound_pitched("res/pop.ogg".to_string(), 96, SQUAWK_PITCH);
+                }
+                ParrotState::Flying => {
+                    let Some(position) = positions.get_mut(entity) else {
+                        continue;
+                    };
+                    if let Some(treasure) = nearest_treasure {
+                        let ground = tiles.map.get_z_interpolated(treasure.xy());
+                        let target =
+                            nalgebra_glm::vec3(treasure.x, treasure.y, ground + FLY_HEIGHT);
+                        fly_toward(&mut position.pos, target, step);
+                    }
+                    if parrot.seconds_left <= 0.0 || nearest_treasure.is_none() {
+                        parrot.state = ParrotState::Returning;
+                    }
+                }
+                ParrotState::Returning => {
+                    let Some(position) = positions.get_mut(entity) else {
+                        continue;
+                    };
+                    // Faster on the way back, so that it catches up with a running player
+                    if !fly_toward(&mut position.pos, player_pos + shoulder, step * 1.5) {
+                        continue;
+                    }
+                    parents
+                        .insert(
+                            entity,
+                            ParentComponent {
+                                parent: player_entity,
+                                offset: shoulder,
+                            },
+                        )
+                        .unwrap();
+                    parrot.state = ParrotState::Perched;
+                    parrot.seconds_left = self.perch_seconds();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_parrot_sits_on_the_right_shoulder() {
+        // Facing north, right is east
+        let offset = shoulder_offset(std::f32::consts::PI / 2.0);
+        assert!(offset.x > 0.0 && offset.y.abs() < 1e-6);
+        assert_eq!(offset.z, SHOULDER_HEIGHT);
+    }
+
+    #[test]
+    fn flying_stops_at_the_target() {
+        let mut pos = nalgebra_glm::vec3(0.0, 0.0, 0.0);
+        let target = nalgebra_glm::vec3(1.0, 0.0, 0.0);
+        assert!(!fly_toward(&mut pos, target, 0.4));
+        assert!((pos.x - 0.4).abs() < 1e-6);
+        assert!(fly_toward(&mut pos, target, 1.0));
+        assert_eq!(pos, target);
+    }
+}
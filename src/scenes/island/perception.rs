@@ -0,0 +1,289 @@
+// How mobs notice the player. Mobs see in a cone in front of them, as long as the terrain or cover like trees isn't in
+// the way, and hear
+// the player from further away the louder they are. Noticing the player fills a suspicion meter, and mobs only give
+// chase once it's full and they can see the player, so sneaking past at a crouch works. Mobs that lose sight of the
+// player keep after them for a moment, then go to look where they were last seen. Loud noises carry much further than
+// that, and send mobs off to see what made them.
+
+use std::f32::consts::PI;
+
+use serde::{Deserialize, Serialize};
+use specs::{prelude::*, Component};
+
+use crate::engine::{
+    perlin::{PerlinMap, PerlinMapResource},
+    physics::{PositionComponent, VelocityComponent},
+    time::TimeResource,
+};
+
+use super::{
+    collisions::raycast_colliders, ColliderComponent, MobComponent, PlayerComponent, CROUCH_HEIGHT,
+    PERSON_HEIGHT,
+};
+
+const SIGHT_RANGE: f32 = 5.0; //< How far mobs can see a standing player
+const SIGHT_HALF_ANGLE: f32 = 1.05; //< Radians either side of where the mob is facing, about 60 degrees
+const CROUCH_SIGHT_FACTOR: f32 = 0.5; //< Crouching players can only be seen from this fraction of the sight range
+const MOB_EYE_HEIGHT: f32 = 0.15;
+
+// How far away the player can be heard, depending on what they're doing
+const GUNSHOT_HEARING: f32 = 8.0;
+const GUNSHOT_SECONDS: f32 = 0.5; //< How long a gunshot is still ringing out
+const SPRINT_HEARING: f32 = 3.0;
+const WALK_HEARING: f32 = 1.5;
+const CROUCH_WALK_HEARING: f32 = 0.4;
+
+const SIGHT_SUSPICION: f32 = 1.5; //< Suspicion per second while the player is seen up close
+const HEARING_SUSPICION: f32 = 1.0; //< Suspicion per second while the player is heard up close
+const SUSPICION_DECAY: f32 = 0.2; //< Suspicion lost per second while the player is neither seen nor heard
+const TURN_SPEED: f32 = 2.0; //< Radians per second suspicious mobs turn toward the player
+const IDLE_TURN_SPEED: f32 = 0.3; //< Radians per second idle mobs look around
+
+pub(super) const GUNSHOT_NOISE_RADIUS: f32 = 30.0; //< How far away mobs come to investigate a gunshot from
+const INVESTIGATE_ARRIVE_DIST: f32 = 0.2; //< How close a mob has to get to a noise to have investigated it
+const INVESTIGATE_SECONDS: f32 = 20.0; //< Mobs give up on reaching a noise after this long
+const MEMORY_SECONDS: f32 = 3.0; //< How long a chasing mob keeps after the player once it can't see them
+
+#[derive(Component, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub(super) struct PerceptionComponent {
+    pub facing: f32,                               //< Radians, 0 is east
+    pub suspicion: f32,                            //< [0, 1], the mob gives chase once this is full
+    pub aggro: bool, //< Chasing the player. Stays set until suspicion runs out.
+    pub investigating: Option<nalgebra_glm::Vec3>, //< Where a noise the mob is going to check out came from
+    pub investigate_seconds: f32, //< How much longer the mob will keep trying to reach the noise
+    #[serde(default)]
+    pub last_seen: Option<nalgebra_glm::Vec3>, //< Where the player was when the mob last saw them
+    #[serde(default)]
+    pub memory_seconds: f32, //< How much longer the mob keeps chasing without seeing the player
+}
+
+/// Something loud that happened this tick
+pub(super) struct Noise {
+    pub pos: nalgebra_glm::Vec3,
+    pub radius: f32, //< Mobs closer than this go to investigate
+}
+
+/// Noises made since perception last ran. Anything can make a noise, and the perception system hears them all.
+#[derive(Default)]
+pub(super) struct NoiseResource {
+    noises: Vec<Noise>,
+}
+
+impl NoiseResource {
+    pub fn make(&mut self, pos: nalgebra_glm::Vec3, radius: f32) {
+        self.noises.push(Noise { pos, radius });
+    }
+}
+
+impl PerceptionComponent {
+    pub fn new(facing: f32) -> Self {
+        Self {
+            facing,
+            suspicion: 0.0,
+            aggro: false,
+            investigating: None,
+            investigate_seconds: 0.0,
+            last_seen: None,
+            memory_seconds: 0.0,
+        }
+    }
+
+    /// Gives chase once suspicion is full and the player is in sight, and keeps on for a moment after losing sight of
+    /// them. After that, the mob goes to search where the player was last seen.
+    /// - seen: where the player is, if the mob can see them
+    fn track(&mut self, seen: Option<nalgebra_glm::Vec3>, dt: f32) {
+        match seen {
+            Some(player_pos) => {
+                self.last_seen = Some(player_pos);
+                self.memory_seconds = MEMORY_SECONDS;
+            }
+            None => self.memory_seconds -= dt,
+        }
+        if self.suspicion >= 1.0 && seen.is_some() {
+            self.aggro = true;
+        } else if self.aggro && self.memory_seconds <= 0.0 {
+            self.aggro = false;
+            self.investigating = self.last_seen;
+            self.investigate_seconds = INVESTIGATE_SECONDS;
+        } else if self.suspicion <= 0.0 {
+            self.aggro = false;
+        }
+    }
+}
+
+/// Whether nothing on the terrain blocks the straight line between two points
+fn line_of_sight(map: &PerlinMap, from: nalgebra_glm::Vec3, to: nalgebra_glm::Vec3) -> bool {
+    map.raycast(from, to - from).is_none_or(|hit| hit.t >= 1.0)
+}
+
+/// How far away the player can be heard right now
+fn hearing_radius(player: &PlayerComponent, moving: bool, time: &TimeResource) -> f32 {
+    if time.elapsed - player.t_last_shot < GUNSHOT_SECONDS {
+        GUNSHOT_HEARING
+    } else if !moving {
+        0.0
+    } else if player.sprinting {
+        SPRINT_HEARING
+    } else if player.crouching {
+        CROUCH_WALK_HEARING
+    } else {
+        WALK_HEARING
+    }
+}
+
+/// The shortest turn from one angle to another, in [-PI, PI)
+fn angle_diff(from: f32, to: f32) -> f32 {
+    (to - from + PI).rem_euclid(2.0 * PI) - PI
+}
+
+/// Turns `facing` toward `target` by at most `max_turn` radians
+fn turn_toward(facing: f32, target: f32, max_turn: f32) -> f32 {
+    facing + angle_diff(facing, target).clamp(-max_turn, max_turn)
+}
+
+/// Fills and drains each mob's suspicion meter from what it can see and hear of the player, and sends mobs off to
+/// investigate loud noises
+pub(super) struct PerceptionSystem;
+impl<'a> System<'a> for PerceptionSystem {
+    type SystemData = (
+        WriteStorage<'a, PerceptionComponent>,
+        ReadStorage<'a, MobComponent>,
+        ReadStorage<'a, PlayerComponent>,
+        ReadStorage<'a, PositionComponent>,
+        ReadStorage<'a, VelocityComponent>,
+        ReadStorage<'a, ColliderComponent>,
+        Read<'a, PerlinMapResource>,
+        Read<'a, TimeResource>,
+        Write<'a, NoiseResource>,
+        Entities<'a>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            mut perceptions,
+            mobs,
+            players,
+            positions,
+            velocities,
+            colliders,
+            tiles,
+            time,
+            mut noises,
+            entities,
+        ): Self::SystemData,
+    ) {
+        let noises: Vec<Noise> = noises.noises.drain(..).collect();
+        let Some((player, player_position, player_velocity)) =
+            (&players, &positions, &velocities).join().next()
+        else {
+            return;
+        };
+        let moving = nalgebra_glm::length(&player_velocity.vel.xy()) > 0.0001;
+        let hearing = hearing_radius(player, moving, &time);
+        let (sight_range, player_height) = if player.crouching {
+            (SIGHT_RANGE * CROUCH_SIGHT_FACTOR, CROUCH_HEIGHT)
+        } else {
+            (SIGHT_RANGE, PERSON_HEIGHT)
+        };
+        let player_head = player_position.pos + nalgebra_glm::vec3(0.0, 0.0, player_height);
+
+        for (perception, _, position) in (&mut perceptions, &mobs, &positions).join() {
+            let to_player = (player_position.pos - position.pos).xy();
+            let dist = nalgebra_glm::length(&to_player);
+            let to_player_angle = to_player.y.atan2(to_player.x);
+
+            let eye = position.pos + nalgebra_glm::vec3(0.0, 0.0, MOB_EYE_HEIGHT);
+            let in_cone = angle_diff(perception.facing, to_player_angle).abs() <= SIGHT_HALF_ANGLE;
+            // Only things that stay put, like trees and bushes, are cover. Other mobs don't block the view.
+            let seen = dist < sight_range
+                && in_cone
+                && line_of_sight(&tiles.map, eye, player_head)
+                && raycast_colliders(
+                    eye,
+                    player_head - eye,
+                    &colliders,
+                    &positions,
+                    &entities,
+                    |entity| !velocities.contains(entity),
+                )
+                .is_none_or(|(_, hit)| hit.t >= 1.0);
+            let heard = dist < hearing;
+
+            let mut gain = 0.0;
+            if seen {
+                gain += SIGHT_SUSPICION * (1.0 - dist / sight_range);
+            }
+            if heard {
+                gain += HEARING_SUSPICION * (1.0 - dist / hearing);
+            }
+            if gain > 0.0 {
+                perception.suspicion += gain * time.dt;
+            } else {
+                perception.suspicion -= SUSPICION_DECAY * time.dt;
+            }
+            perception.suspicion = perception.suspicion.clamp(0.0, 1.0);
+            perception.track(seen.then_some(player_position.pos), time.dt);
+
+            // The latest noise in earshot wins. Mobs already chasing the player don't get distracted.
+            for noise in &noises {
+                if nalgebra_glm::distance(&noise.pos.xy(), &position.pos.xy()) < noise.radius {
+                    perception.investigating = Some(noise.pos);
+                    perception.investigate_seconds = INVESTIGATE_SECONDS;
+                }
+            }
+            perception.investigate_seconds -= time.dt;
+            let arrived = perception.investigating.is_some_and(|spot| {
+                nalgebra_glm::distance(&spot.xy(), &position.pos.xy()) < INVESTIGATE_ARRIVE_DIST
+            });
+            if perception.aggro || arrived || perception.investigate_seconds <= 0.0 {
+                perception.investigating = None;
+            }
+
+            // Suspicious mobs turn to look at what they noticed, investigating mobs look where they're going, and
+            // the rest look around
+            if gain > 0.0 || perception.aggro {
+                perception.facing =
+                    turn_toward(perception.facing, to_player_angle, TURN_SPEED * time.dt);
+            } else if let Some(spot) = perception.investigating {
+                let to_spot = (spot - position.pos).xy();
+                perception.facing = turn_toward(
+                    perception.facing,
+                    to_spot.y.atan2(to_spot.x),
+                    TURN_SPEED * time.dt,
+                );
+            } else {
+                perception.facing += IDLE_TURN_SPEED * time.dt;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mobs_only_give_chase_in_sight() {
+        let mut perception = PerceptionComponent::new(0.0);
+        perception.suspicion = 1.0;
+        perception.track(None, 0.1);
+        assert!(!perception.aggro);
+        perception.track(Some(nalgebra_glm::vec3(1.0, 2.0, 0.0)), 0.1);
+        assert!(perception.aggro);
+    }
+
+    #[test]
+    fn lost_players_are_searched_for_where_last_seen() {
+        let mut perception = PerceptionComponent::new(0.0);
+        perception.suspicion = 1.0;
+        let last_seen = nalgebra_glm::vec3(1.0, 2.0, 0.0);
+        perception.track(Some(last_seen), 0.1);
+        perception.track(None, MEMORY_SECONDS * 0.5);
+        assert!(perception.aggro);
+        perception.track(None, MEMORY_SECONDS);
+        assert!(!perception.aggro);
+        assert_eq!(perception.investigating, Some(last_seen));
+    }
+}
@@ -0,0 +1,467 @@
+// Saving and loading. Islands are generated from their seed, so a save only holds the seed and what the player has
+// changed since: opened chests, cut bushes, dead mobs, and so on. Loading generates the island again and re-applies
+// the changes.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use serde::{Deserialize, Serialize};
+use specs::{prelude::*, Component};
+
+use crate::engine::{
+    perlin::NoiseKind,
+    physics::{PositionComponent, VelocityComponent},
+    registry::ComponentRegistry,
+};
+
+use super::{
+    inventory::InventoryComponent, parrot::ParrotResource, tools::ChestComponent,
+    weapons::WeaponComponent, DeathSplishAnimComponent, GoldResource, IslandLayout,
+    PlayerComponent, SeedResource, TreasureMapComponent,
+};
+
+pub(super) const SAVE_PATH: &str = "save.ron";
+const SAVED_COMPONENTS: [&str; 2] = ["Health", "Castaway"]; //< Saved whole, by their registered names
+
+/// Names an entity that the player can change, the same way every time an island is generated from a seed
+#[derive(Component, Serialize, Deserialize, Clone, Copy)]
+#[storage(DenseVecStorage)]
+pub(super) struct PersistentIdComponent {
+    pub id: u32,
+}
+
+/// Hands out persistent ids. Entities have to be spawned in the same order for a seed, so that ids line up on load.
+#[derive(Default)]
+pub(super) struct PersistentIdResource {
+    next: u32,
+}
+
+impl PersistentIdResource {
+    pub fn next_id(&mut self) -> PersistentIdComponent {
+        let id = self.next;
+        self.next += 1;
+        PersistentIdComponent { id }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub(crate) struct SaveGame {
+    pub(crate) seed: u64,
+    #[serde(default)]
+    pub(crate) layout: IslandLayout, //< Saves from before archipelagos were all on single islands
+    #[serde(default)]
+    pub(crate) noise: NoiseKind, //< Saves from before gradient noise were all value noise
+    player_pos: nalgebra_glm::Vec3,
+    player_facing: f32,
+    gold: u32,
+    inventory: InventoryComponent,
+    #[serde(default)]
+    weapons: WeaponComponent, //< Ammo left
+    opened_chests: BTreeSet<u32>, //< Persistent ids of chests that have been found
+    removed: BTreeSet<u32>, //< Persistent ids of entities that are gone, like cut bushes and dead mobs
+    positions: BTreeMap<u32, nalgebra_glm::Vec3>, //< Where things that move around have got to
+    #[serde(default)]
+    components: BTreeMap<u32, BTreeMap<String, String>>, //< Persistent id to component name to RON
+    #[serde(default)]
+    chests: BTreeMap<u32, nalgebra_glm::Vec3>, //< Where chests are, since the trader can move them
+    #[serde(default)]
+    hints: BTreeMap<u32, nalgebra_glm::Vec2>, //< Hint circles bought from the trader, by chest
+    #[serde(default)]
+    parrot: bool, //< Whether the parrot has been bought
+}
+
+impl SaveGame {
+    /// Records everything the player has changed in the world
+    pub(super) fn capture(world: &World) -> Self {
+        let ids = world.read_storage::<PersistentIdComponent>();
+        let positions = world.read_storage::<PositionComponent>();
+        let velocities = world.read_storage::<VelocityComponent>();
+        let dying = world.read_storage::<DeathSplishAnimComponent>();
+        let registry = world.read_resource::<ComponentRegistry>();
+        let entities = world.entities();
+        let players = world.read_storage::<PlayerComponent>();
+        let inventories = world.read_storage::<InventoryComponent>();
+        let weapons = world.read_storage::<WeaponComponent>();
+        let treasure_maps = world.read_storage::<TreasureMapComponent>();
+        let chests = world.read_storage::<ChestComponent>();
+
+        let (player, inventory, weapon, player_position) =
+            (&players, &inventories, &weapons, &positions)
+                .join()
+                .next()
+                .unwrap();
+        let opened_chests = (&treasure_maps)
+            .join()
+            .filter(|map| map.found)
+            .filter_map(|map| ids.get(map.treasure_entity))
+            .map(|id| id.id)
+            .collect();
+
+        // Mobs that are mid death animation are as good as gone
+        let alive: BTreeSet<u32> = (&ids, !&dying).join().map(|(id, _)| id.id).collect();
+        let all_ids = 0..world.read_resource::<PersistentIdResource>().next;
+
+        Self {
+            seed: world.read_resource::<SeedResource>().seed,
+            layout: world.read_resource::<SeedResource>().layout,
+            noise: world.read_resource::<SeedResource>().noise,
+            player_pos: player_position.pos,
+            player_facing: player.facing,
+            gold: world.read_resource::<GoldResource>().gold,
+            inventory: inventory.clone(),
+            weapons: weapon.clone(),
+            opened_chests,
+            removed: all_ids.filter(|id| !alive.contains(id)).collect(),
+            positions: (&ids, &positions, &velocities, !&dying)
+                .join()
+                .map(|(id, position, _, _)| (id.id, position.pos))
+                .collect(),
+            components: (&ids, &entities, !&dying)
+                .join()
+                .map(|(id, entity, _)| {
+                    let components: BTreeMap<String, String> = SAVED_COMPONENTS
+                        .iter()
+                        .filter_map(|name| {
+                            let value = registry.write(world, entity, name).unwrap()?;
+                            Some((name.to_string(), value))
+                        })
+                        .collect();
+                    (id.id, components)
+                })
+                .filter(|(_, components)| !components.is_empty())
+                .collect(),
+            chests: (&ids, &positions, &chests)
+                .join()
+                .map(|(id, position, _)| (id.id, position.pos))
+                .collect(),
+            hints: (&treasure_maps)
+                .join()
+                .filter_map(|map| Some((ids.get(map.treasure_entity)?.id, map.hint?)))
+                .collect(),
+            parrot: world.read_resource::<ParrotResource>().owned,
+        }
+    }
+
+    /// Re-applies the player's changes to a freshly generated island with the same seed
+    pub(super) fn apply(&self, world: &mut World) {
+        {
+            let ids = world.read_storage::<PersistentIdComponent>();
+            let entities = world.entities();
+            let by_id: HashMap<u32, Entity> =
+                (&ids, &entities).join().map(|(id, e)| (id.id, e)).collect();
+
+            let registry = world.read_resource::<ComponentRegistry>();
+            let mut positions = world.write_storage::<PositionComponent>();
+            let mut treasure_maps = world.write_storage::<TreasureMapComponent>();
+            let mut players = world.write_storage::<PlayerComponent>();
+            let mut inventories = world.write_storage::<InventoryComponent>();
+            let mut weapons = world.write_storage::<WeaponComponent>();
+
+            for id in &self.removed {
+                if let Some(entity) = by_id.get(id) {
+                    entities.delete(*entity).unwrap();
+                }
+            }
+            for map in (&mut treasure_maps).join() {
+                let chest_id = ids.get(map.treasure_entity).map(|id| id.id);
+                map.found = chest_id.is_some_and(|id| self.opened_chests.contains(&id));
+                map.hint = chest_id.and_then(|id| self.hints.get(&id).copied());
+            }
+            for (id, pos) in self.positions.iter().chain(&self.chests) {
+                if let Some(position) = by_id.get(id).and_then(|e| positions.get_mut(*e)) {
+                    position.pos = *pos;
+                }
+            }
+            for (id, components) in &self.components {
+                let Some(entity) = by_id.get(id) else {
+                    continue;
+                };
+                for (name, value) in components {
+                    if let Err(err) = registry.read(world, *entity, name, value) {
+                        println!("Couldn't load {} {}: {}", name, id, err);
+                    }
+                }
+            }
+            for (player, inventory, weapon, position) in
+                (&mut players, &mut inventories, &mut weapons, &mut positions).join()
+            {
+                player.facing = self.player_facing;
+                *inventory = self.inventory.clone();
+                *weapon = self.weapons.clone();
+                position.pos = self.player_pos;
+            }
+        }
+
+        world.write_resource::<GoldResource>().gold = self.gold;
+        world.write_resource::<ParrotResource>().owned = self.parrot;
+        world.maintain();
+    }
+
+    pub(super) fn load(path: &str) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        ron::from_str(&contents).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub(super) fn save(&self, path: &str) -> Result<(), String> {
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| e.to_string())?;
+        std::fs::write(path, contents).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenes::island::{
+        castaway::{CastawayComponent, CastawayState},
+        component_registry,
+        inventory::Item,
+        mobs::MobKind,
+        tools::{BlockingComponent, Tool},
+        weapons::Weapon,
+        HealthComponent, MobComponent,
+    };
+
+    /// Builds the same small island every time, without anything that needs OpenGL
+    fn generate_world() -> World {
+        let mut world = World::new();
+        let registry = component_registry();
+        registry.register_all(&mut world);
+        world.insert(registry);
+        world.insert(PersistentIdResource::default());
+        world.insert(SeedResource {
+            seed: 1234,
+            layout: IslandLayout::Single,
+            noise: NoiseKind::Value,
+        });
+        world.insert(GoldResource::default());
+        world.insert(ParrotResource::default());
+
+        let at = |x: f32, y: f32| PositionComponent {
+            pos: nalgebra_glm::vec3(x, y, 1.0),
+        };
+        let still = || VelocityComponent {
+            vel: nalgebra_glm::zero(),
+        };
+        for i in 0..2 {
+            let id = world.write_resource::<PersistentIdResource>().next_id();
+            let chest = world
+                .create_entity()
+                .with(id)
+                .with(at(10.0 * i as f32, 5.0))
+                .with(ChestComponent {
+                    buried: i > 0,
+                    contents: None,
+                })
+                .build();
+            world
+                .create_entity()
+                .with(TreasureMapComponent {
+                    treasure_entity: chest,
+                    found: false,
+                    hint: None,
+                })
+                .build();
+        }
+        for i in 0..3 {
+            let id = world.write_resource::<PersistentIdResource>().next_id();
+            world
+                .create_entity()
+                .with(id)
+                .with(at(20.0, i as f32))
+                .with(BlockingComponent)
+                .build();
+        }
+        for i in 0..2 {
+            let id = world.write_resource::<PersistentIdResource>().next_id();
+            world
+                .create_entity()
+                .with(id)
+                .with(at(30.0, i as f32))
+                .with(still())
+                .with(MobComponent {
+                    kind: MobKind::Skeleton,
+                })
+                .with(HealthComponent { health: 1.0 })
+                .build();
+        }
+        let id = world.write_resource::<PersistentIdResource>().next_id();
+        world
+            .create_entity()
+            .with(id)
+            .with(at(40.0, 0.0))
+            .with(still())
+            .with(CastawayComponent {
+                state: CastawayState::Trapped,
+                home: nalgebra_glm::vec3(0.0, 0.0, 1.0),
+            })
+            .build();
+        world
+            .create_entity()
+            .with(PlayerComponent {
+                feet_on_ground: true,
+                crouching: false,
+                sprinting: false,
+                torch_raised: false,
+                facing: 0.0,
+                pitch: 0.0,
+                t_last_shot: 0.0,
+                t_last_walk_played: 0.0,
+                invulnerable_for: 0.0,
+            })
+            .with(InventoryComponent::new())
+            .with(WeaponComponent::default())
+            .with(at(0.0, 0.0))
+            .with(still())
+            .build();
+        world
+    }
+
+    fn entity_with_id(world: &World, id: u32) -> Option<Entity> {
+        let ids = world.read_storage::<PersistentIdComponent>();
+        (&ids, &world.entities())
+            .join()
+            .find(|(i, _)| i.id == id)
+            .map(|(_, e)| e)
+    }
+
+    /// Plays a little: opens a chest, buys a hint for the other one and has it moved, cuts a bush, kills a mob, hurts
+    /// another, frees the castaway, and fires the blunderbuss
+    fn play(world: &mut World) {
+        for (i, map) in (&mut world.write_storage::<TreasureMapComponent>())
+            .join()
+            .enumerate()
+        {
+            if i == 0 {
+                map.found = true;
+            } else {
+                map.hint = Some(nalgebra_glm::vec2(6.0, 4.0));
+            }
+        }
+        let moved_chest = entity_with_id(world, 1).unwrap();
+        world
+            .write_storage::<PositionComponent>()
+            .get_mut(moved_chest)
+            .unwrap()
+            .pos = nalgebra_glm::vec3(8.0, 6.0, 1.1);
+        let bush = entity_with_id(world, 3).unwrap();
+        let dead_mob = entity_with_id(world, 5).unwrap();
+        let hurt_mob = entity_with_id(world, 6).unwrap();
+        let castaway = entity_with_id(world, 7).unwrap();
+        world.delete_entity(bush).unwrap();
+        world
+            .write_storage::<DeathSplishAnimComponent>()
+            .insert(
+                dead_mob,
+                DeathSplishAnimComponent {
+                    timeline: 0.5,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        world
+            .write_storage::<HealthComponent>()
+            .get_mut(hurt_mob)
+            .unwrap()
+            .health = 0.4;
+        world
+            .write_storage::<PositionComponent>()
+            .get_mut(hurt_mob)
+            .unwrap()
+            .pos = nalgebra_glm::vec3(31.0, 2.5, 1.2);
+        world
+            .write_storage::<CastawayComponent>()
+            .get_mut(castaway)
+            .unwrap()
+            .state = CastawayState::Following;
+        for (_, position) in (
+            &world.read_storage::<PlayerComponent>(),
+            &mut world.write_storage::<PositionComponent>(),
+        )
+            .join()
+        {
+            position.pos = nalgebra_glm::vec3(12.0, 3.0, 1.5);
+        }
+        world.write_resource::<GoldResource>().gold = 75;
+        world.write_resource::<ParrotResource>().owned = true;
+        for inventory in (&mut world.write_storage::<InventoryComponent>()).join() {
+            inventory.give(Item::Tool(Tool::Machete));
+            inventory.active = inventory.items.len() - 1;
+        }
+        for weapon in (&mut world.write_storage::<WeaponComponent>()).join() {
+            weapon.ammo[Weapon::Blunderbuss as usize].loaded = 1;
+        }
+        world.maintain();
+    }
+
+    #[test]
+    fn save_and_reload_restores_world() {
+        let mut played = generate_world();
+        play(&mut played);
+        let saved = SaveGame::capture(&played);
+
+        // Round trip through the save file format
+        let contents =
+            ron::ser::to_string_pretty(&saved, ron::ser::PrettyConfig::default()).unwrap();
+        let loaded: SaveGame = ron::from_str(&contents).unwrap();
+        assert_eq!(saved, loaded);
+
+        let mut reloaded = generate_world();
+        loaded.apply(&mut reloaded);
+        assert_eq!(SaveGame::capture(&reloaded), saved);
+
+        // Spot check the entities themselves, not just what the save captures
+        assert!(entity_with_id(&reloaded, 3).is_none(), "cut bush came back");
+        assert!(entity_with_id(&reloaded, 5).is_none(), "dead mob came back");
+        let hurt_mob = entity_with_id(&reloaded, 6).unwrap();
+        assert_eq!(
+            reloaded
+                .read_storage::<HealthComponent>()
+                .get(hurt_mob)
+                .unwrap()
+                .health,
+            0.4
+        );
+        let found: Vec<bool> = (&reloaded.read_storage::<TreasureMapComponent>())
+            .join()
+            .map(|map| map.found)
+            .collect();
+        assert_eq!(found, vec![true, false]);
+        let hints: Vec<_> = (&reloaded.read_storage::<TreasureMapComponent>())
+            .join()
+            .map(|map| map.hint)
+            .collect();
+        assert_eq!(hints, vec![None, Some(nalgebra_glm::vec2(6.0, 4.0))]);
+        let moved_chest = entity_with_id(&reloaded, 1).unwrap();
+        assert_eq!(
+            reloaded
+                .read_storage::<PositionComponent>()
+                .get(moved_chest)
+                .unwrap()
+                .pos,
+            nalgebra_glm::vec3(8.0, 6.0, 1.1)
+        );
+        let castaway = entity_with_id(&reloaded, 7).unwrap();
+        assert_eq!(
+            reloaded
+                .read_storage::<CastawayComponent>()
+                .get(castaway)
+                .unwrap()
+                .state,
+            CastawayState::Following
+        );
+        assert_eq!(reloaded.read_resource::<GoldResource>().gold, 75);
+        assert!(reloaded.read_resource::<ParrotResource>().owned);
+        let inventory = (&reloaded.read_storage::<InventoryComponent>())
+            .join()
+            .next()
+            .unwrap()
+            .clone();
+        assert!(inventory.holding(Item::Tool(Tool::Machete)));
+        let weapon = (&reloaded.read_storage::<WeaponComponent>())
+            .join()
+            .next()
+            .unwrap()
+            .clone();
+        assert_eq!(weapon.ammo(Weapon::Blunderbuss).loaded, 1);
+    }
+}
@@ -0,0 +1,1031 @@
+// Functions that build the island's entities, so that each kind of entity is only put together in one place
+
+use specs::{prelude::*, Entity};
+
+use crate::engine::{
+    animation::AnimationComponent,
+    collision::Shape,
+    error::EngineError,
+    objects::Texture,
+    physics::{ParentComponent, PositionComponent, VelocityComponent},
+    render3d::{
+        create_capsule_mesh, Mesh, MeshComponent, MeshMgr, RenderLayer, TerrainComponent,
+        TintComponent, ViewModelComponent,
+    },
+    shadow_map::CastsShadowComponent,
+    text::{
+        Align, Anchor, BillboardComponent, LayoutComponent, QuadComponent, TextComponent, UiLayer,
+    },
+};
+
+use super::{
+    ai::AiComponent,
+    animations::{CHEST_CLIPS, COIN_CLIPS, LID_CLIPS, LOOT_CLIPS, MOB_CLIPS},
+    castaway::{CastawayComponent, CastawayState, TALK_DIST},
+    coins::CoinComponent,
+    compass::{
+        CompassMarkComponent, CompassTarget, CARDINALS, STRIP_HEIGHT, STRIP_TOP, STRIP_WIDTH,
+    },
+    damage::DamageIndicatorComponent,
+    goal::TreasureCounterComponent,
+    health_bars::{bar_texture, PLAYER_BAR_MARGIN, PLAYER_BAR_WIDTH},
+    indicators::ChestIndicatorComponent,
+    interaction::{InteractAction, InteractPromptComponent, InteractableComponent},
+    inventory::InventoryComponent,
+    loot::{Loot, LootComponent},
+    map_view::{MapViewComponent, MAP_VIEW_SIZE},
+    minimap::{
+        hint_size, render_arrow_texture, render_ring_texture, MinimapComponent, MinimapMarker,
+        MinimapMarkerComponent, ARROW_SIZE, MINIMAP_SIZE,
+    },
+    mobs::MobKind,
+    parrot::ParrotComponent,
+    perception::PerceptionComponent,
+    persistence::{PersistentIdComponent, PersistentIdResource},
+    range::{target_shape, TargetComponent, TARGET_SCALE},
+    sites::{Prop, PropMesh, SitePropComponent},
+    stamina::{StaminaBarComponent, StaminaComponent, STAMINA_BAR_HEIGHT, STAMINA_BAR_TOP},
+    status::StatusComponent,
+    swimming::{
+        OxygenMeterComponent, SwimmerComponent, UnderwaterTintComponent, OXYGEN_METER_HEIGHT,
+        OXYGEN_METER_TOP,
+    },
+    tools::{BlockingComponent, ChestComponent, TraderComponent, CHEST_REACH, TRADE_DIST},
+    view_model::{meters, HeldWeaponComponent, ARM_COLOR, ARM_OFFSET, ARM_SIZE, WEAPON_OFFSET},
+    weapons::WeaponComponent,
+    ColliderComponent, HealthComponent, MobComponent, PlayerComponent, TreasureMapComponent,
+    BUSH_DATA, CHEST_DATA, CONE_DATA, CUBE_DATA, HUD_FONT, MOB_DATA, PERSON_HEIGHT, QUAD_DATA,
+    UNIT_PER_METER,
+};
+
+const PLAYER_RADIUS: f32 = 0.03;
+
+/// Every picture the prefabs put on things, including the mobs'
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(super) enum PrefabTexture {
+    Grass,
+    Earth,
+    Tree,
+    Chest,
+    Gold,
+    Map,
+    Bullet,
+    Ghost,
+}
+
+impl PrefabTexture {
+    /// In the order of the variants, so that each one's texture is at its index
+    const ALL: [PrefabTexture; 8] = [
+        PrefabTexture::Grass,
+        PrefabTexture::Earth,
+        PrefabTexture::Tree,
+        PrefabTexture::Chest,
+        PrefabTexture::Gold,
+        PrefabTexture::Map,
+        PrefabTexture::Bullet,
+        PrefabTexture::Ghost,
+    ];
+
+    fn path(self) -> &'static str {
+        match self {
+            PrefabTexture::Grass => "res/grass.png",
+            PrefabTexture::Earth => "res/earth.png",
+            PrefabTexture::Tree => "res/tree.png",
+            PrefabTexture::Chest => "res/chest.png",
+            PrefabTexture::Gold => "res/gold.png",
+            PrefabTexture::Map => "res/map.png",
+            PrefabTexture::Bullet => "res/bullet.png",
+            PrefabTexture::Ghost => "res/ghost.png",
+        }
+    }
+}
+
+/// Mesh ids, textures and render settings shared by the prefabs. Must be inserted into the world before spawning
+/// anything.
+#[derive(Default, Clone)]
+pub(super) struct PrefabResource {
+    pub quad_mesh: usize,
+    pub cube_mesh: usize,
+    pub mob_mesh: usize,
+    pub tree_mesh: usize,
+    pub bush_mesh: usize,
+    pub chest_mesh: usize,
+    pub player_mesh: usize, //< A capsule the size of the player, only drawn for its shadow
+    pub view_distance: f32, //< Terrain and trees are drawn this far, props are drawn half as far
+    textures: Vec<Texture>, //< Loaded once, indexed by PrefabTexture, and shared by everything they're put on
+}
+
+impl PrefabResource {
+    /// Loads the meshes the prefabs need into the mesh manager, and the textures they put on them
+    pub fn new(mesh_mgr: &mut MeshMgr, view_distance: f32) -> Result<Self, EngineError> {
+        let textures = PrefabTexture::ALL
+            .iter()
+            .map(|texture| Texture::from_png(texture.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let white = nalgebra_glm::vec3(1.0, 1.0, 1.0);
+        Ok(Self {
+            quad_mesh: mesh_mgr.add_mesh(Mesh::from_obj(QUAD_DATA, white)?),
+            cube_mesh: mesh_mgr.add_mesh(Mesh::from_obj(CUBE_DATA, white)?),
+            mob_mesh: mesh_mgr.add_mesh(Mesh::from_obj(MOB_DATA, white)?),
+            tree_mesh: mesh_mgr.add_mesh(Mesh::from_obj(CONE_DATA, white)?),
+            bush_mesh: mesh_mgr.add_mesh(Mesh::from_obj(BUSH_DATA, white)?),
+            chest_mesh: mesh_mgr.add_mesh(Mesh::from_obj(CHEST_DATA, white)?),
+            player_mesh: mesh_mgr.add_mesh(create_capsule_mesh(PLAYER_RADIUS, PERSON_HEIGHT)),
+            view_distance,
+            textures,
+        })
+    }
+
+    /// One of the prefabs' textures, shared rather than loaded again
+    pub fn texture(&self, texture: PrefabTexture) -> Texture {
+        self.textures[texture as usize].clone()
+    }
+}
+
+/// A plain colored texture, for things that don't need a picture on them
+pub(super) fn solid_texture(color: [u8; 3]) -> Texture {
+    Texture::from_rgba(1, 1, &[color[0], color[1], color[2], 255])
+}
+
+fn prefabs(world: &World) -> PrefabResource {
+    (*world.read_resource::<PrefabResource>()).clone()
+}
+
+/// The next persistent id, for entities that saves keep track of
+fn persistent_id(world: &World) -> PersistentIdComponent {
+    world.write_resource::<PersistentIdResource>().next_id()
+}
+
+/// A chunk of terrain. The mesh is built by the caller, since every chunk is different. Chunks are streamed in while the
+/// game runs, so this is spawned lazily.
+pub(super) fn spawn_terrain_chunk(
+    entities: &Entities,
+    lazy: &LazyUpdate,
+    prefabs: &PrefabResource,
+    mesh_id: usize,
+    pos: nalgebra_glm::Vec3,
+) -> Entity {
+    lazy.create_entity(entities)
+        .with(MeshComponent {
+            mesh_id,
+            scale: nalgebra_glm::vec3(1.0, 1.0, 1.0),
+            texture: prefabs.texture(PrefabTexture::Grass),
+            render_dist: Some(prefabs.view_distance),
+            shadow_only: false,
+            layer: RenderLayer::Opaque,
+            order: 0,
+        })
+        .with(PositionComponent { pos })
+        .with(CastsShadowComponent {})
+        .with(TerrainComponent)
+        .build()
+}
+
+/// The parrot, perched on its parent's shoulder
+pub(super) fn spawn_parrot(
+    entities: &Entities,
+    lazy: &LazyUpdate,
+    prefabs: &PrefabResource,
+    parent: Entity,
+    pos: nalgebra_glm::Vec3,
+    offset: nalgebra_glm::Vec3,
+    perch_seconds: f32,
+) -> Entity {
+    lazy.create_entity(entities)
+        .with(MeshComponent {
+            mesh_id: prefabs.mob_mesh,
+            scale: nalgebra_glm::vec3(0.2, 0.2, 0.08),
+            texture: prefabs.texture(PrefabTexture::Earth),
+            render_dist: None,
+            shadow_only: false,
+            layer: RenderLayer::Opaque,
+            order: 0,
+        })
+        .with(TintComponent {
+            color: nalgebra_glm::vec3(0.3, 1.0, 0.35),
+        })
+        .with(PositionComponent { pos })
+        .with(CastsShadowComponent {})
+        .with(ParentComponent { parent, offset })
+        .with(ParrotComponent::perched(perch_seconds))
+        .build()
+}
+
+pub(super) fn spawn_tree(world: &mut World, pos: nalgebra_glm::Vec3, scale: f32) -> Entity {
+    let prefabs = prefabs(world);
+    world
+        .create_entity()
+        .with(MeshComponent {
+            mesh_id: prefabs.tree_mesh,
+            scale: nalgebra_glm::vec3(scale, scale, scale),
+            texture: prefabs.texture(PrefabTexture::Tree),
+            render_dist: Some(prefabs.view_distance),
+            shadow_only: false,
+            layer: RenderLayer::Opaque,
+            order: 0,
+        })
+        .with(PositionComponent { pos })
+        .with(CastsShadowComponent {})
+        .with(ColliderComponent {
+            shape: Shape::Cylinder {
+                radius: 0.06 * scale,
+                height: scale,
+            },
+        })
+        .build()
+}
+
+/// A bush in a wall, which only the machete gets through
+pub(super) fn spawn_wall_bush(world: &mut World, pos: nalgebra_glm::Vec3, scale: f32) -> Entity {
+    let prefabs = prefabs(world);
+    let id = persistent_id(world);
+    world
+        .create_entity()
+        .with(MeshComponent {
+            mesh_id: prefabs.bush_mesh,
+            scale: nalgebra_glm::vec3(scale, scale, scale),
+            texture: prefabs.texture(PrefabTexture::Tree),
+            render_dist: Some(prefabs.view_distance / 2.0),
+            shadow_only: false,
+            layer: RenderLayer::Opaque,
+            order: 0,
+        })
+        .with(PositionComponent { pos })
+        .with(CastsShadowComponent {})
+        .with(ColliderComponent {
+            shape: Shape::Cylinder {
+                radius: 0.1 * scale,
+                height: scale,
+            },
+        })
+        .with(BlockingComponent)
+        .with(id)
+        .build()
+}
+
+pub(super) fn spawn_bush(world: &mut World, pos: nalgebra_glm::Vec3, scale: f32) -> Entity {
+    let prefabs = prefabs(world);
+    world
+        .create_entity()
+        .with(MeshComponent {
+            mesh_id: prefabs.bush_mesh,
+            scale: nalgebra_glm::vec3(scale, scale, scale),
+            texture: prefabs.texture(PrefabTexture::Tree),
+            render_dist: Some(prefabs.view_distance / 2.0),
+            shadow_only: false,
+            layer: RenderLayer::Opaque,
+            order: 0,
+        })
+        .with(PositionComponent { pos })
+        .with(CastsShadowComponent {})
+        .build()
+}
+
+/// A treasure chest. Buried chests are sunk so that only the lid pokes out. Returns the chest, which treasure maps
+/// point to.
+pub(super) fn spawn_treasure(
+    world: &mut World,
+    pos: nalgebra_glm::Vec3,
+    chest: ChestComponent,
+) -> Entity {
+    let prefabs = prefabs(world);
+    let id = persistent_id(world);
+    let sink = if chest.buried { 0.03 } else { 0.0 };
+    let prompt = if chest.buried { "dig" } else { "open" };
+    world
+        .create_entity()
+        .with(MeshComponent {
+            mesh_id: prefabs.chest_mesh,
+            scale: nalgebra_glm::vec3(0.05, 0.05, 0.05),
+            texture: prefabs.texture(PrefabTexture::Chest),
+            render_dist: Some(prefabs.view_distance / 2.0),
+            shadow_only: false,
+            layer: RenderLayer::Opaque,
+            order: 0,
+        })
+        .with(PositionComponent {
+            pos: pos - nalgebra_glm::vec3(0.0, 0.0, sink),
+        })
+        .with(CastsShadowComponent {})
+        .with(AnimationComponent::new(&CHEST_CLIPS))
+        .with(InteractableComponent {
+            radius: CHEST_REACH,
+            prompt,
+            action: InteractAction::OpenChest,
+        })
+        .with(chest)
+        .with(id)
+        .build()
+}
+
+/// The lid of a chest that's been opened, swinging open on top of it. Lids that are `already_open` start swung back.
+pub(super) fn spawn_chest_lid(
+    world: &mut World,
+    chest_pos: nalgebra_glm::Vec3,
+    already_open: bool,
+) -> Entity {
+    let prefabs = prefabs(world);
+    let mut animation = AnimationComponent::new(&LID_CLIPS);
+    animation.play("lid-open");
+    if already_open {
+        animation.time = LID_CLIPS[0].duration();
+    }
+    world
+        .create_entity()
+        .with(MeshComponent {
+            mesh_id: prefabs.cube_mesh,
+            scale: nalgebra_glm::vec3(0.055, 0.078, 0.006),
+            texture: prefabs.texture(PrefabTexture::Chest),
+            render_dist: Some(prefabs.view_distance / 2.0),
+            shadow_only: false,
+            layer: RenderLayer::Opaque,
+            order: 0,
+        })
+        .with(PositionComponent {
+            pos: chest_pos + nalgebra_glm::vec3(0.0, 0.0, 0.108),
+        })
+        .with(CastsShadowComponent {})
+        .with(animation)
+        .build()
+}
+
+/// The `i`th gold coin spilling out of a chest that's just been opened
+pub(super) fn spawn_coin(world: &mut World, chest_pos: nalgebra_glm::Vec3, i: u32) -> Entity {
+    let prefabs = prefabs(world);
+    let mut animation = AnimationComponent::new(&COIN_CLIPS);
+    animation.play("spin");
+    world
+        .create_entity()
+        .with(MeshComponent {
+            mesh_id: prefabs.cube_mesh,
+            scale: nalgebra_glm::vec3(0.0015, 0.006, 0.006),
+            texture: prefabs.texture(PrefabTexture::Gold),
+            render_dist: Some(prefabs.view_distance / 2.0),
+            shadow_only: false,
+            layer: RenderLayer::Opaque,
+            order: 0,
+        })
+        .with(PositionComponent {
+            pos: chest_pos + nalgebra_glm::vec3(0.0, 0.0, 0.1),
+        })
+        .with(animation)
+        .with(CoinComponent::spilled(i))
+        .build()
+}
+
+/// The `i`th of `count` pickups thrown out of a defeated mob: a gold nugget, or a bundle of rounds
+pub(super) fn spawn_loot(
+    world: &mut World,
+    mob_pos: nalgebra_glm::Vec3,
+    loot: Loot,
+    i: usize,
+    count: usize,
+) -> Entity {
+    let prefabs = prefabs(world);
+    let mut animation = AnimationComponent::new(&LOOT_CLIPS);
+    animation.play("bob");
+    let (texture, scale) = match loot {
+        Loot::Gold(_) => (PrefabTexture::Gold, nalgebra_glm::vec3(0.004, 0.004, 0.003)),
+        Loot::Rounds(..) => (
+            PrefabTexture::Bullet,
+            nalgebra_glm::vec3(0.005, 0.003, 0.003),
+        ),
+    };
+    world
+        .create_entity()
+        .with(MeshComponent {
+            mesh_id: prefabs.cube_mesh,
+            scale,
+            texture: prefabs.texture(texture),
+            render_dist: Some(prefabs.view_distance / 2.0),
+            shadow_only: false,
+            layer: RenderLayer::Opaque,
+            order: 0,
+        })
+        .with(PositionComponent {
+            pos: mob_pos + nalgebra_glm::vec3(0.0, 0.0, 0.05),
+        })
+        .with(animation)
+        .with(LootComponent::thrown(loot, i, count))
+        .build()
+}
+
+/// The map icon at the top of the screen for a treasure chest.
+/// - offset_x: pixels right of the middle of the screen, where the icon goes along the top
+pub(super) fn spawn_treasure_map(
+    world: &mut World,
+    treasure_entity: Entity,
+    offset_x: i32,
+) -> Entity {
+    const TOP_OFFSET: i32 = STRIP_TOP + STRIP_HEIGHT + 8; //< Pixels down from the top of the screen, under the compass
+    let prefabs = prefabs(world);
+    world
+        .create_entity()
+        .with(QuadComponent::from_texture(
+            prefabs.texture(PrefabTexture::Map),
+            32,
+            32,
+            prefabs.quad_mesh,
+        ))
+        .with(PositionComponent {
+            pos: nalgebra_glm::zero(),
+        })
+        .with(LayoutComponent::new(
+            Anchor::new(Align::Center, Align::Start),
+            (offset_x, TOP_OFFSET),
+        ))
+        .with(TreasureMapComponent {
+            treasure_entity,
+            found: false,
+            hint: None,
+        })
+        .build()
+}
+
+/// The count of treasure and gold found, under the map icons. The text system fills it in.
+pub(super) fn spawn_treasure_counter(world: &mut World) -> Entity {
+    const TOP_OFFSET: i32 = 78; //< Pixels down from the top of the screen, clear of the map icons
+    let prefabs = prefabs(world);
+    world
+        .create_entity()
+        .with(QuadComponent::from_texture(
+            Texture::from_rgba(1, 1, &[0, 0, 0, 0]),
+            1,
+            1,
+            prefabs.quad_mesh,
+        ))
+        .with(PositionComponent {
+            pos: nalgebra_glm::zero(),
+        })
+        .with(TextComponent::new("", HUD_FONT))
+        .with(LayoutComponent::new(
+            Anchor::new(Align::Center, Align::Start),
+            (0, TOP_OFFSET),
+        ))
+        .with(TreasureCounterComponent)
+        .build()
+}
+
+/// The compass strip across the top of the screen, with the cardinal directions and a mark for each treasure map's
+/// chest. The compass system slides the marks along it.
+pub(super) fn spawn_compass(world: &mut World, treasure_maps: &[Entity]) {
+    const MARK_SIZE: i32 = 16;
+    let prefabs = prefabs(world);
+    let anchor = Anchor::new(Align::Center, Align::Start);
+    world
+        .create_entity()
+        .with(QuadComponent::from_texture(
+            Texture::from_rgba(1, 1, &[0, 0, 0, 110]),
+            STRIP_WIDTH,
+            STRIP_HEIGHT,
+            prefabs.quad_mesh,
+        ))
+        .with(PositionComponent {
+            pos: nalgebra_glm::zero(),
+        })
+        .with(LayoutComponent::new(anchor, (0, STRIP_TOP)))
+        .build();
+    for (letter, bearing) in CARDINALS {
+        let mut text = TextComponent::new(letter, HUD_FONT);
+        if letter == "N" {
+            text.color = sdl2::pixels::Color::RGBA(255, 90, 70, 255);
+        }
+        world
+            .create_entity()
+            .with(
+                QuadComponent::from_texture(
+                    Texture::from_rgba(1, 1, &[0, 0, 0, 0]),
+                    1,
+                    1,
+                    prefabs.quad_mesh,
+                )
+                .in_layer(UiLayer::Hud, 1),
+            )
+            .with(PositionComponent {
+                pos: nalgebra_glm::zero(),
+            })
+            .with(text)
+            .with(LayoutComponent::new(anchor, (0, STRIP_TOP + 2)))
+            .with(CompassMarkComponent {
+                target: CompassTarget::Bearing(bearing),
+            })
+            .build();
+    }
+    for treasure_map in treasure_maps {
+        world
+            .create_entity()
+            .with(
+                QuadComponent::from_texture(
+                    prefabs.texture(PrefabTexture::Gold),
+                    MARK_SIZE,
+                    MARK_SIZE,
+                    prefabs.quad_mesh,
+                )
+                .in_layer(UiLayer::Hud, 1),
+            )
+            .with(PositionComponent {
+                pos: nalgebra_glm::zero(),
+            })
+            .with(LayoutComponent::new(
+                anchor,
+                (0, STRIP_TOP + (STRIP_HEIGHT - MARK_SIZE) / 2),
+            ))
+            .with(CompassMarkComponent {
+                target: CompassTarget::Treasure(*treasure_map),
+            })
+            .build();
+    }
+}
+
+/// An arrow at the edge of the screen, pointing towards a treasure map's chest once it's tracked
+pub(super) fn spawn_chest_indicator(world: &mut World, treasure_map: Entity) -> Entity {
+    let prefabs = prefabs(world);
+    let mut quad = QuadComponent::from_texture(
+        render_arrow_texture(),
+        2 * ARROW_SIZE,
+        2 * ARROW_SIZE,
+        prefabs.quad_mesh,
+    );
+    quad.tint = nalgebra_glm::vec3(1.0, 0.85, 0.3);
+    quad.opacity = 0.0;
+    world
+        .create_entity()
+        .with(quad)
+        .with(PositionComponent {
+            pos: nalgebra_glm::zero(),
+        })
+        .with(ChestIndicatorComponent { treasure_map })
+        .build()
+}
+
+/// The arrow pointing to where the player was last hurt from, hidden until then
+pub(super) fn spawn_damage_indicator(world: &mut World) -> Entity {
+    let prefabs = prefabs(world);
+    let mut quad = QuadComponent::from_texture(
+        render_arrow_texture(),
+        2 * ARROW_SIZE,
+        2 * ARROW_SIZE,
+        prefabs.quad_mesh,
+    );
+    quad.tint = nalgebra_glm::vec3(0.9, 0.1, 0.1);
+    quad.opacity = 0.0;
+    world
+        .create_entity()
+        .with(quad)
+        .with(PositionComponent {
+            pos: nalgebra_glm::zero(),
+        })
+        .with(DamageIndicatorComponent {
+            from: nalgebra_glm::zero(),
+            seconds_left: 0.0,
+        })
+        .build()
+}
+
+/// The oxygen meter and its track under the health bar, and the tint over the screen underwater
+pub(super) fn spawn_swimming_hud(world: &mut World) {
+    let prefabs = prefabs(world);
+    for track in [true, false] {
+        let mut quad = QuadComponent::from_texture(
+            bar_texture(),
+            PLAYER_BAR_WIDTH,
+            OXYGEN_METER_HEIGHT,
+            prefabs.quad_mesh,
+        );
+        quad.tint = if track {
+            nalgebra_glm::vec3(0.1, 0.1, 0.1)
+        } else {
+            nalgebra_glm::vec3(0.4, 0.75, 1.0)
+        };
+        quad.opacity = 0.0;
+        world
+            .create_entity()
+            .with(quad.in_layer(UiLayer::Hud, if track { 0 } else { 1 }))
+            .with(PositionComponent {
+                pos: nalgebra_glm::zero(),
+            })
+            .with(LayoutComponent::new(
+                Anchor::new(Align::Start, Align::Start),
+                (PLAYER_BAR_MARGIN, OXYGEN_METER_TOP),
+            ))
+            .with(OxygenMeterComponent { track })
+            .build();
+    }
+    let mut tint = QuadComponent::from_texture(
+        Texture::from_rgba(1, 1, &[20, 70, 110, 255]),
+        1,
+        1,
+        prefabs.quad_mesh,
+    );
+    tint.opacity = 0.0;
+    world
+        .create_entity()
+        .with(tint.in_layer(UiLayer::Flash, 0))
+        .with(PositionComponent {
+            pos: nalgebra_glm::zero(),
+        })
+        .with(UnderwaterTintComponent)
+        .build();
+}
+
+/// The stamina bar and its track, under the oxygen meter
+pub(super) fn spawn_stamina_bar(world: &mut World) {
+    let prefabs = prefabs(world);
+    for track in [true, false] {
+        let mut quad = QuadComponent::from_texture(
+            bar_texture(),
+            PLAYER_BAR_WIDTH,
+            STAMINA_BAR_HEIGHT,
+            prefabs.quad_mesh,
+        );
+        if track {
+            quad.tint = nalgebra_glm::vec3(0.1, 0.1, 0.1);
+        }
+        quad.opacity = 0.0;
+        world
+            .create_entity()
+            .with(quad.in_layer(UiLayer::Hud, if track { 0 } else { 1 }))
+            .with(PositionComponent {
+                pos: nalgebra_glm::zero(),
+            })
+            .with(LayoutComponent::new(
+                Anchor::new(Align::Start, Align::Start),
+                (PLAYER_BAR_MARGIN, STAMINA_BAR_TOP),
+            ))
+            .with(StaminaBarComponent { track })
+            .build();
+    }
+}
+
+/// A mob guarding a camp, which is part of the island and so is saved
+pub(super) fn spawn_mob(
+    world: &mut World,
+    kind: MobKind,
+    pos: nalgebra_glm::Vec3,
+    facing: f32,
+) -> Entity {
+    let id = persistent_id(world);
+    let mob = spawn_roaming_mob(world, kind, pos, facing);
+    world
+        .write_storage::<PersistentIdComponent>()
+        .insert(mob, id)
+        .unwrap();
+    mob
+}
+
+/// A mob that comes and goes with the spawner, and isn't saved
+pub(super) fn spawn_roaming_mob(
+    world: &mut World,
+    kind: MobKind,
+    pos: nalgebra_glm::Vec3,
+    facing: f32,
+) -> Entity {
+    let prefabs = prefabs(world);
+    let stats = kind.stats();
+    world
+        .create_entity()
+        .with(MeshComponent {
+            mesh_id: prefabs.mob_mesh,
+            scale: nalgebra_glm::make_vec3(&stats.scale),
+            texture: prefabs.texture(stats.texture),
+            render_dist: Some(prefabs.view_distance / 2.0),
+            shadow_only: false,
+            layer: stats.layer,
+            order: 0,
+        })
+        .with(PositionComponent { pos })
+        .with(VelocityComponent {
+            vel: nalgebra_glm::zero(),
+        })
+        .with(CastsShadowComponent {})
+        .with(MobComponent { kind })
+        .with(ColliderComponent {
+            shape: Shape::Cylinder {
+                radius: stats.radius,
+                height: stats.height,
+            },
+        })
+        .with(HealthComponent { health: 1.0 })
+        .with(PerceptionComponent::new(facing))
+        .with(AiComponent::new(kind.ai_params()))
+        .with(StatusComponent::default())
+        .with(AnimationComponent::new(&MOB_CLIPS))
+        .build()
+}
+
+pub(super) fn spawn_player(world: &mut World, pos: nalgebra_glm::Vec3) -> Entity {
+    let prefabs = prefabs(world);
+    world
+        .create_entity()
+        // The camera is inside the player, so their body is only there for its shadow. It's round, so it doesn't have
+        // to turn with the player, only squash down when they crouch.
+        .with(MeshComponent {
+            mesh_id: prefabs.player_mesh,
+            scale: nalgebra_glm::vec3(1.0, 1.0, 1.0),
+            texture: prefabs.texture(PrefabTexture::Tree),
+            render_dist: None,
+            shadow_only: true,
+            layer: RenderLayer::Opaque,
+            order: 0,
+        })
+        .with(CastsShadowComponent {})
+        .with(PlayerComponent {
+            feet_on_ground: true,
+            crouching: false,
+            sprinting: false,
+            torch_raised: false,
+            facing: 3.14,
+            pitch: 0.0,
+            t_last_shot: 0.0,
+            t_last_walk_played: 0.0,
+            invulnerable_for: 0.0,
+        })
+        .with(InventoryComponent::new())
+        .with(WeaponComponent::default())
+        .with(PositionComponent { pos })
+        .with(VelocityComponent {
+            vel: nalgebra_glm::zero(),
+        })
+        .with(ColliderComponent {
+            shape: Shape::Cylinder {
+                radius: PLAYER_RADIUS,
+                height: PERSON_HEIGHT,
+            },
+        })
+        .with(HealthComponent { health: 1.0 })
+        .with(SwimmerComponent::default())
+        .with(StaminaComponent::default())
+        .build()
+}
+
+/// The weapon and arm held up in front of the camera. They only take on a look once a weapon is in hand.
+pub(super) fn spawn_view_models(world: &mut World) {
+    let prefabs = prefabs(world);
+    world
+        .create_entity()
+        .with(ViewModelComponent {
+            mesh_id: prefabs.cube_mesh,
+            scale: nalgebra_glm::zero(),
+            texture: solid_texture([0, 0, 0]),
+            offset: meters(WEAPON_OFFSET),
+            sway: nalgebra_glm::zero(),
+            kick: 0.0,
+            visible: false,
+        })
+        .with(HeldWeaponComponent)
+        .build();
+    world
+        .create_entity()
+        .with(ViewModelComponent {
+            mesh_id: prefabs.cube_mesh,
+            scale: meters(ARM_SIZE),
+            texture: solid_texture(ARM_COLOR),
+            offset: meters(ARM_OFFSET),
+            sway: nalgebra_glm::zero(),
+            kick: 0.0,
+            visible: false,
+        })
+        .build();
+}
+
+/// Dressing around a treasure site
+pub(super) fn spawn_site_prop(world: &mut World, prop: &Prop, pos: nalgebra_glm::Vec3) -> Entity {
+    let prefabs = prefabs(world);
+    let mesh_id = match prop.mesh {
+        PropMesh::Cube => prefabs.cube_mesh,
+        PropMesh::Cone => prefabs.tree_mesh,
+        PropMesh::Bush => prefabs.bush_mesh,
+    };
+    let builder = world
+        .create_entity()
+        .with(MeshComponent {
+            mesh_id,
+            scale: nalgebra_glm::make_vec3(&prop.scale) * UNIT_PER_METER,
+            texture: solid_texture(prop.color),
+            render_dist: Some(prefabs.view_distance / 2.0),
+            shadow_only: false,
+            layer: RenderLayer::Opaque,
+            order: 0,
+        })
+        .with(PositionComponent {
+            pos: pos + nalgebra_glm::vec3(0.0, 0.0, prop.lift * UNIT_PER_METER),
+        })
+        .with(CastsShadowComponent {})
+        .with(SitePropComponent);
+    if prop.radius > 0.0 {
+        builder
+            .with(ColliderComponent {
+                shape: Shape::Cylinder {
+                    radius: prop.radius * UNIT_PER_METER,
+                    height: (prop.lift + prop.scale[2]) * UNIT_PER_METER,
+                },
+            })
+            .build()
+    } else {
+        builder.build()
+    }
+}
+
+/// A castaway waiting to be brought back home
+pub(super) fn spawn_castaway(
+    world: &mut World,
+    pos: nalgebra_glm::Vec3,
+    home: nalgebra_glm::Vec3,
+) -> Entity {
+    let prefabs = prefabs(world);
+    let id = persistent_id(world);
+    world
+        .create_entity()
+        .with(MeshComponent {
+            mesh_id: prefabs.mob_mesh,
+            scale: nalgebra_glm::vec3(1.0, 1.0, 1.0),
+            texture: prefabs.texture(PrefabTexture::Earth),
+            render_dist: Some(prefabs.view_distance / 2.0),
+            shadow_only: false,
+            layer: RenderLayer::Opaque,
+            order: 0,
+        })
+        .with(PositionComponent { pos })
+        .with(VelocityComponent {
+            vel: nalgebra_glm::zero(),
+        })
+        .with(CastsShadowComponent {})
+        .with(CastawayComponent {
+            state: CastawayState::Trapped,
+            home,
+        })
+        .with(InteractableComponent {
+            radius: TALK_DIST,
+            prompt: "talk",
+            action: InteractAction::Talk,
+        })
+        .with(ColliderComponent {
+            shape: Shape::Cylinder {
+                radius: 0.03,
+                height: PERSON_HEIGHT,
+            },
+        })
+        .with(id)
+        .build()
+}
+
+/// The trader, who sells tools for gold
+pub(super) fn spawn_trader(world: &mut World, pos: nalgebra_glm::Vec3) -> Entity {
+    let prefabs = prefabs(world);
+    world
+        .create_entity()
+        .with(MeshComponent {
+            mesh_id: prefabs.mob_mesh,
+            scale: nalgebra_glm::vec3(1.0, 1.0, 1.0),
+            texture: prefabs.texture(PrefabTexture::Gold),
+            render_dist: Some(prefabs.view_distance / 2.0),
+            shadow_only: false,
+            layer: RenderLayer::Opaque,
+            order: 0,
+        })
+        .with(PositionComponent { pos })
+        .with(CastsShadowComponent {})
+        .with(TraderComponent {})
+        .with(InteractableComponent {
+            radius: TRADE_DIST,
+            prompt: "trade",
+            action: InteractAction::Talk,
+        })
+        .with(ColliderComponent {
+            shape: Shape::Cylinder {
+                radius: 0.03,
+                height: PERSON_HEIGHT,
+            },
+        })
+        .build()
+}
+
+/// A target on the practice range. Targets with some sway slide that far to each side of `pos` and back.
+pub(super) fn spawn_target(world: &mut World, pos: nalgebra_glm::Vec3, sway: f32) -> Entity {
+    let prefabs = prefabs(world);
+    world
+        .create_entity()
+        .with(MeshComponent {
+            mesh_id: prefabs.cube_mesh,
+            scale: TARGET_SCALE,
+            texture: prefabs.texture(PrefabTexture::Chest),
+            render_dist: Some(prefabs.view_distance / 2.0),
+            shadow_only: false,
+            layer: RenderLayer::Opaque,
+            order: 0,
+        })
+        .with(PositionComponent { pos })
+        .with(CastsShadowComponent {})
+        .with(ColliderComponent {
+            shape: target_shape(),
+        })
+        .with(HealthComponent { health: 1.0 })
+        .with(TargetComponent {
+            home: pos,
+            sway,
+            reset_in: 0.0,
+            last_health: 1.0,
+        })
+        .build()
+}
+
+/// The minimap, the minimap system keeps it in the corner of the screen
+pub(super) fn spawn_minimap(world: &mut World, texture: Texture) -> Entity {
+    let prefabs = prefabs(world);
+    world
+        .create_entity()
+        .with(QuadComponent::from_texture(
+            texture,
+            MINIMAP_SIZE,
+            MINIMAP_SIZE,
+            prefabs.quad_mesh,
+        ))
+        .with(PositionComponent {
+            pos: nalgebra_glm::zero(),
+        })
+        .with(MinimapComponent {})
+        .build()
+}
+
+/// The large map for a treasure map, put away until it's held up
+pub(super) fn spawn_map_view(world: &mut World, treasure_map: Entity, texture: Texture) -> Entity {
+    let prefabs = prefabs(world);
+    let mut quad =
+        QuadComponent::from_texture(texture, MAP_VIEW_SIZE, MAP_VIEW_SIZE, prefabs.quad_mesh);
+    quad.opacity = 0.0;
+    world
+        .create_entity()
+        .with(quad.in_layer(UiLayer::Hud, 2))
+        .with(PositionComponent {
+            pos: nalgebra_glm::zero(),
+        })
+        .with(MapViewComponent { treasure_map })
+        .build()
+}
+
+/// The prompt over whatever the player would interact with. The interaction system fills it in and moves it around.
+pub(super) fn spawn_interact_prompt(world: &mut World) -> Entity {
+    let prefabs = prefabs(world);
+    let mut quad = QuadComponent::from_texture(
+        Texture::from_rgba(1, 1, &[0, 0, 0, 0]),
+        1,
+        1,
+        prefabs.quad_mesh,
+    );
+    quad.opacity = 0.0;
+    world
+        .create_entity()
+        .with(quad)
+        .with(PositionComponent {
+            pos: nalgebra_glm::zero(),
+        })
+        .with(TextComponent::new("", HUD_FONT))
+        .with(BillboardComponent {
+            anchor: nalgebra_glm::zero(),
+        })
+        .with(InteractPromptComponent)
+        .build()
+}
+
+/// A marker on the minimap, drawn over it
+pub(super) fn spawn_minimap_marker(world: &mut World, marker: MinimapMarker) -> Entity {
+    let prefabs = prefabs(world);
+    let mut quad = match marker {
+        MinimapMarker::Player => {
+            let mut quad = QuadComponent::from_texture(
+                render_arrow_texture(),
+                ARROW_SIZE,
+                ARROW_SIZE,
+                prefabs.quad_mesh,
+            );
+            quad.tint = nalgebra_glm::vec3(1.0, 0.3, 0.2);
+            quad
+        }
+        MinimapMarker::Hint(_) => {
+            let size = hint_size();
+            let mut quad = QuadComponent::from_texture(
+                render_ring_texture(size),
+                size,
+                size,
+                prefabs.quad_mesh,
+            );
+            quad.tint = nalgebra_glm::vec3(1.0, 0.85, 0.3);
+            quad
+        }
+        MinimapMarker::Treasure(_) => QuadComponent::from_texture(
+            prefabs.texture(PrefabTexture::Gold),
+            12,
+            12,
+            prefabs.quad_mesh,
+        ),
+    };
+    quad.opacity = 0.0;
+    world
+        .create_entity()
+        .with(quad.in_layer(UiLayer::Hud, 1))
+        .with(PositionComponent {
+            pos: nalgebra_glm::zero(),
+        })
+        .with(MinimapMarkerComponent { marker })
+        .build()
+}
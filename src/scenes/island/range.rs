@@ -0,0 +1,126 @@
+// The practice range at the camp: a few targets to shoot at, without anything shooting back. Targets take hits just
+// like mobs do, fall over once shot down, and stand back up good as new a few seconds after the last hit. Some of
+// them slide from side to side.
+
+use serde::{Deserialize, Serialize};
+use specs::{prelude::*, Component};
+
+use crate::engine::{
+    aabb::AABB,
+    audio::AudioResource,
+    collision::Shape,
+    particles::{spawn_emitter, EmitterPreset},
+    physics::PositionComponent,
+    render3d::MeshComponent,
+    time::TimeResource,
+};
+
+use super::{ColliderComponent, HealthComponent, UNIT_PER_METER};
+
+pub(super) const TARGET_SCALE: nalgebra_glm::Vec3 = nalgebra_glm::Vec3::new(0.03, 0.01, 0.06); //< Of the cube mesh
+const RESET_SECONDS: f32 = 3.0; //< How long after the last hit a target is healed, and stood back up
+const SWAY_SPEED: f32 = 1.5; //< Radians per second
+
+// Splinters knocked off a target when it's hit
+const TARGET_HIT_PARTICLES: EmitterPreset = EmitterPreset {
+    burst: 8,
+    rate: 0.0,
+    emit_seconds: 0.0,
+    lifetime: 0.4,
+    speed: 2.0 * UNIT_PER_METER,
+    direction: [0.0, 0.0, 1.0],
+    spread: 1.0,
+    gravity: 9.8 * UNIT_PER_METER,
+    drag: 3.0,
+    start_size: 0.1 * UNIT_PER_METER,
+    end_size: 0.05 * UNIT_PER_METER,
+    start_color: [0.6, 0.4, 0.2, 1.0],
+    end_color: [0.6, 0.4, 0.2, 0.0],
+};
+
+#[derive(Component, Serialize, Deserialize)]
+#[storage(HashMapStorage)]
+pub(super) struct TargetComponent {
+    pub home: nalgebra_glm::Vec3, //< Where the target stands, or the middle of where it slides
+    pub sway: f32,                //< How far the target slides each way, 0 stands still
+    pub reset_in: f32,            //< Seconds until the target is healed, 0 when it's unhurt
+    pub last_health: f32,         //< Health last tick, to notice hits
+}
+
+/// The box a standing target can be hit in, around its center
+pub(super) fn target_shape() -> Shape {
+    Shape::Aabb(AABB::from_min_max(-TARGET_SCALE, TARGET_SCALE))
+}
+
+/// Slides targets, shows hits, and knocks down and stands up targets
+pub(super) struct TargetSystem;
+impl<'a> System<'a> for TargetSystem {
+    type SystemData = (
+        WriteStorage<'a, TargetComponent>,
+        WriteStorage<'a, HealthComponent>,
+        WriteStorage<'a, PositionComponent>,
+        WriteStorage<'a, MeshComponent>,
+        WriteStorage<'a, ColliderComponent>,
+        Read<'a, TimeResource>,
+        Read<'a, AudioResource>,
+        Read<'a, LazyUpdate>,
+        Entities<'a>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            mut targets,
+            mut healths,
+            mut positions,
+            mut meshes,
+            mut colliders,
+            time,
+            audio,
+            lazy,
+            entities,
+        ): Self::SystemData,
+    ) {
+        for (target, health, position, mesh, entity) in (
+            &mut targets,
+            &mut healths,
+            &mut positions,
+            &mut meshes,
+            &entities,
+        )
+            .join()
+        {
+            let slide = target.sway * (time.elapsed * SWAY_SPEED).sin();
+            position.pos = target.home + nalgebra_glm::vec3(slide, 0.0, 0.0);
+
+            if health.health < target.last_health {
+                spawn_emitter(&entities, &lazy, position.pos, TARGET_HIT_PARTICLES);
+                target.reset_in = RESET_SECONDS;
+            }
+            if health.health <= 0.0 && colliders.contains(entity) {
+                // Falls flat, and can't be hit again until it stands back up
+                mesh.scale.z = TARGET_SCALE.z * 0.1;
+                colliders.remove(entity);
+                audio.audio_mgr.play_sound("res/pop.ogg".to_string(), 128);
+            }
+
+            if target.reset_in > 0.0 {
+                target.reset_in -= time.dt;
+                if target.reset_in <= 0.0 {
+                    target.reset_in = 0.0;
+                    health.health = 1.0;
+                    mesh.scale = TARGET_SCALE;
+                    colliders
+                        .insert(
+                            entity,
+                            ColliderComponent {
+                                shape: target_shape(),
+                            },
+                        )
+                        .unwrap();
+                }
+            }
+            target.last_health = health.health;
+        }
+    }
+}
@@ -0,0 +1,153 @@
+// Grass and flowers scattered over the ground near the camera. The ground is split into cells a tile across, and each
+// cell's tufts are placed the same way every time from its coordinates, thicker or thinner with the biome under them.
+// Cells are scattered as the camera comes close, and thrown away once it leaves.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use specs::prelude::*;
+
+use crate::engine::{
+    grass::{GrassResource, GRASS_INSTANCE_STRIDE},
+    perlin::PerlinMapResource,
+    render3d::OpenGlResource,
+    water::WaterResource,
+};
+
+use super::{biome::Biome, MAP_WIDTH, UNIT_PER_METER};
+
+pub(super) const GRASS_DISTANCE: f32 = 50.0 * UNIT_PER_METER; //< Tufts shrink away to nothing by this far out
+const CELL_SIZE: f32 = 1.0; //< Tiles
+const MAX_GRASS_DENSITY: f32 = 2.0; //< Tufts per square meter in the thickest biome
+const TUFT_HEIGHT: std::ops::Range<f32> = (0.25 * UNIT_PER_METER)..(0.6 * UNIT_PER_METER);
+const FLOWER_HEIGHT: f32 = 0.45 * UNIT_PER_METER;
+const MAX_CELLS_PER_TICK: usize = 4; //< So that moving quickly doesn't hitch the game
+const FLOWER_COLORS: [[f32; 3]; 4] = [
+    [0.95, 0.9, 0.3],
+    [0.9, 0.35, 0.45],
+    [0.95, 0.95, 0.95],
+    [0.6, 0.45, 0.9],
+];
+
+/// Seeds a cell's tufts from the island's seed and the cell's coordinates. Each coordinate gets its own half, so that
+/// negative ones don't spill over into the other.
+fn cell_seed(island_seed: u64, cell: (i32, i32)) -> u64 {
+    let coordinates = (cell.0 as u32 as u64) << 32 | cell.1 as u32 as u64;
+    island_seed ^ coordinates ^ 0x6a55_0000
+}
+
+/// The tufts in a cell, as grass instance data, in a random order
+fn scatter_cell(
+    tiles: &PerlinMapResource,
+    water_level: f32,
+    island_seed: u64,
+    cell: (i32, i32),
+) -> Vec<f32> {
+    let mut rng = StdRng::seed_from_u64(cell_seed(island_seed, cell));
+    let cell_meters = CELL_SIZE / UNIT_PER_METER;
+    let tries = (cell_meters * cell_meters * MAX_GRASS_DENSITY) as usize;
+    let corner = nalgebra_glm::vec2(cell.0 as f32, cell.1 as f32) * CELL_SIZE;
+
+    let mut instances = Vec::new();
+    for _ in 0..tries {
+        // Rolled whether or not a tuft ends up here, so that every spot rolls the same on every visit
+        let offset = nalgebra_glm::vec2(rng.gen::<f32>(), rng.gen::<f32>()) * CELL_SIZE;
+        let keep: f32 = rng.gen();
+        let height: f32 = rng.gen_range(TUFT_HEIGHT);
+        let shade: f32 = rng.gen_range(1.2..1.8);
+        let flower_roll: f32 = rng.gen();
+        let flower_color = FLOWER_COLORS[rng.gen_range(0..FLOWER_COLORS.len())];
+
+        let pos = corner + offset;
+        let z = tiles.map.get_z_interpolated(pos);
+        let slope = tiles.map.get_normal(pos).z;
+        let biome = Biome::classify(z, slope, tiles.moisture.get(pos), z < water_level);
+        let config = biome.config();
+        if z < water_level || keep * MAX_GRASS_DENSITY >= config.grass_density {
+            continue;
+        }
+        instances.extend([pos.x, pos.y, z]);
+        if flower_roll < config.flower_chance {
+            instances.extend([FLOWER_HEIGHT]);
+            instances.extend(flower_color);
+            instances.extend([1.0]);
+        } else {
+            instances.extend([height]);
+            instances.extend(config.color.map(|c| c * shade));
+            instances.extend([0.0]);
+        }
+    }
+    debug_assert_eq!(instances.len() % GRASS_INSTANCE_STRIDE, 0);
+    instances
+}
+
+/// Distance from the camera to the nearest point of a cell, ignoring height
+fn cell_distance(camera_pos: nalgebra_glm::Vec2, cell: (i32, i32)) -> f32 {
+    let min = nalgebra_glm::vec2(cell.0 as f32, cell.1 as f32) * CELL_SIZE;
+    let max = min + nalgebra_glm::vec2(CELL_SIZE, CELL_SIZE);
+    let nearest = nalgebra_glm::clamp_vec(&camera_pos, &min, &max);
+    nalgebra_glm::distance(&camera_pos, &nearest)
+}
+
+/// Scatters grass over the cells around the camera, and drops the ones it has left
+pub(super) struct GrassScatterSystem {
+    seed: u64, //< The island's, so that each island's grass is its own
+}
+
+impl GrassScatterSystem {
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+}
+impl<'a> System<'a> for GrassScatterSystem {
+    type SystemData = (
+        Write<'a, GrassResource>,
+        Read<'a, PerlinMapResource>,
+        Read<'a, WaterResource>,
+        Read<'a, OpenGlResource>,
+    );
+
+    fn run(&mut self, (mut grass, tiles, water, opengl): Self::SystemData) {
+        let camera_pos = opengl.camera.position.xy();
+        grass
+            .patches
+            .retain(|cell, _| cell_distance(camera_pos, *cell) <= GRASS_DISTANCE);
+
+        let cells = (MAP_WIDTH as f32 / CELL_SIZE) as i32 - 1;
+        let reach = (GRASS_DISTANCE / CELL_SIZE).ceil() as i32;
+        let center = (
+            (camera_pos.x / CELL_SIZE).floor() as i32,
+            (camera_pos.y / CELL_SIZE).floor() as i32,
+        );
+        let mut missing: Vec<(i32, i32)> = (center.1 - reach..=center.1 + reach)
+            .flat_map(|y| (center.0 - reach..=center.0 + reach).map(move |x| (x, y)))
+            .filter(|(x, y)| (0..cells).contains(x) && (0..cells).contains(y))
+            .filter(|cell| !grass.patches.contains_key(cell))
+            .filter(|cell| cell_distance(camera_pos, *cell) <= GRASS_DISTANCE)
+            .collect();
+        missing.sort_by(|a, b| {
+            cell_distance(camera_pos, *a).total_cmp(&cell_distance(camera_pos, *b))
+        });
+        for cell in missing.into_iter().take(MAX_CELLS_PER_TICK) {
+            let instances = scatter_cell(&tiles, water.level, self.seed, cell);
+            grass.add_patch(cell, &instances);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cells_below_zero_get_their_own_seeds() {
+        assert_ne!(cell_seed(1, (1, -1)), cell_seed(1, (2, -1)));
+        assert_ne!(cell_seed(1, (-1, 3)), cell_seed(1, (-1, 4)));
+        assert_ne!(cell_seed(1, (5, 5)), cell_seed(2, (5, 5)));
+    }
+
+    #[test]
+    fn cells_are_measured_from_their_nearest_edge() {
+        let camera = nalgebra_glm::vec2(1.5, 1.5);
+        assert_eq!(cell_distance(camera, (1, 1)), 0.0);
+        assert_eq!(cell_distance(camera, (3, 1)), 1.5);
+    }
+}
@@ -0,0 +1,241 @@
+// Simulated playthroughs. A headless island is stepped with scripted input, and checked on how the game responded:
+// walking, standing on the ground, and digging up treasure. Run with `--simulate` or `cargo test`, which need no
+// window or GPU, so that gameplay can be checked on machines that can't render.
+
+use std::sync::{atomic::AtomicUsize, Arc};
+
+use sdl2::keyboard::Scancode;
+use specs::prelude::*;
+
+use crate::{
+    engine::{
+        headless::load_null_gl,
+        perlin::{NoiseParams, PerlinMapResource},
+        physics::{PositionComponent, VelocityComponent},
+    },
+    App, SceneCommand,
+};
+
+use super::{
+    generate_terrain, ChestComponent, InventoryComponent, Island, IslandLayout, Item,
+    PlayerComponent, Tool, TreasureMapComponent, UNIT_PER_METER,
+};
+
+const SIMULATION_SEEDS: [u64; 3] = [1, 42, 20240601];
+const SETTLE_TICKS: usize = 60; //< Ticks to run before a scenario starts, so that the player has landed
+
+/// A scripted bit of play, which says what went wrong if the game didn't respond as it should have
+struct Scenario {
+    name: &'static str,
+    run: fn(&mut Island) -> Result<(), String>,
+}
+
+const SCENARIOS: [Scenario; 3] = [
+    Scenario {
+        name: "stands_on_ground",
+        run: stands_on_ground,
+    },
+    Scenario {
+        name: "walks_forward",
+        run: walks_forward,
+    },
+    Scenario {
+        name: "opens_chest",
+        run: opens_chest,
+    },
+];
+
+/// Runs every scenario on every seed, each on a fresh island. Returns an error listing the scenarios that failed.
+pub(crate) fn run_simulation_tests() -> Result<(), String> {
+    let mut failures = vec![];
+    for seed in SIMULATION_SEEDS {
+        for scenario in &SCENARIOS {
+            let name = format!("seed{}_{}", seed, scenario.name);
+            match run_scenario(seed, scenario) {
+                Ok(()) => println!("{}: ok", name),
+                Err(err) => failures.push(format!("{}: {}", name, err)),
+            }
+        }
+    }
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(failures.join("\n"))
+    }
+}
+
+/// Runs a scenario on a fresh island
+fn run_scenario(seed: u64, scenario: &Scenario) -> Result<(), String> {
+    load_null_gl();
+    let terrain = generate_terrain(
+        Some(seed),
+        IslandLayout::Single,
+        &NoiseParams::default(),
+        Arc::new(AtomicUsize::new(0)),
+    );
+    let mut island = Island::headless(terrain)?;
+    (scenario.run)(&mut island)
+}
+
+/// An app with nothing pressed
+fn idle_app() -> App {
+    App {
+        running: true,
+        ..Default::default()
+    }
+}
+
+/// An app with some keys held down
+fn app_holding(keys: &[Scancode]) -> App {
+    let mut app = idle_app();
+    for key in keys {
+        app.keys[*key as usize] = true;
+    }
+    app
+}
+
+/// Steps the island, failing if it wanted to leave, like when the player died
+fn step(island: &mut Island, app: &App, ticks: usize) -> Result<(), String> {
+    match island.step(app, ticks) {
+        SceneCommand::None => Ok(()),
+        _ => Err("the island ended early".to_string()),
+    }
+}
+
+fn player_position(island: &Island) -> nalgebra_glm::Vec3 {
+    let world = &island.world;
+    let players = world.read_storage::<PlayerComponent>();
+    let positions = world.read_storage::<PositionComponent>();
+    (&players, &positions).join().next().unwrap().1.pos
+}
+
+fn ground_height(island: &Island, pos: nalgebra_glm::Vec2) -> f32 {
+    island
+        .world
+        .read_resource::<PerlinMapResource>()
+        .map
+        .get_z_interpolated(pos)
+}
+
+/// Puts the player at a spot, standing still and looking a certain way along the ground
+fn place_player(island: &mut Island, pos: nalgebra_glm::Vec3, facing: f32) {
+    let world = &mut island.world;
+    for (player, position, velocity) in (
+        &mut world.write_storage::<PlayerComponent>(),
+        &mut world.write_storage::<PositionComponent>(),
+        &mut world.write_storage::<VelocityComponent>(),
+    )
+        .join()
+    {
+        player.facing = facing;
+        player.pitch = 0.0;
+        position.pos = pos;
+        velocity.vel = nalgebra_glm::zero();
+    }
+}
+
+/// The player lands on the ground at the spawn point, rather than falling through it or floating over it
+fn stands_on_ground(island: &mut Island) -> Result<(), String> {
+    step(island, &idle_app(), SETTLE_TICKS)?;
+    let pos = player_position(island);
+    let ground = ground_height(island, pos.xy());
+    if (pos.z - ground).abs() > 0.25 * UNIT_PER_METER {
+        return Err(format!(
+            "feet at {}, but the ground is at {}",
+            pos.z, ground
+        ));
+    }
+    Ok(())
+}
+
+/// Holding forward moves the player the way they're facing
+fn walks_forward(island: &mut Island) -> Result<(), String> {
+    step(island, &idle_app(), SETTLE_TICKS)?;
+    let start = player_position(island);
+    place_player(island, start, 0.0);
+    step(island, &app_holding(&[Scancode::W]), SETTLE_TICKS)?;
+    let moved = player_position(island) - start;
+    // Facing 0 is along +x. Trees or the shore might stop the player early, but not before they've moved at all.
+    if moved.x < 0.5 * UNIT_PER_METER {
+        return Err(format!("only moved {:?}", moved));
+    }
+    Ok(())
+}
+
+/// Walking up to a chest with the shovel out and pressing the interact key digs it up and opens it
+fn opens_chest(island: &mut Island) -> Result<(), String> {
+    step(island, &idle_app(), SETTLE_TICKS)?;
+    let (chest, chest_pos) = {
+        let world = &island.world;
+        let maps = world.read_storage::<TreasureMapComponent>();
+        let chests = world.read_storage::<ChestComponent>();
+        let positions = world.read_storage::<PositionComponent>();
+        let chest = maps
+            .join()
+            .map(|map| map.treasure_entity)
+            .find(|&entity| chests.contains(entity))
+            .ok_or("there are no chests")?;
+        (chest, positions.get(chest).unwrap().pos)
+    };
+    {
+        let players = island.world.read_storage::<PlayerComponent>();
+        let mut inventories = island.world.write_storage::<InventoryComponent>();
+        let (_, inventory) = (&players, &mut inventories).join().next().unwrap();
+        inventory.give(Item::Tool(Tool::Shovel));
+        inventory.active = inventory
+            .items
+            .iter()
+            .position(|item| *item == Item::Tool(Tool::Shovel))
+            .unwrap();
+    }
+
+    // Just to the west of the chest, facing east at it
+    let stand_xy = chest_pos.xy() - nalgebra_glm::vec2(UNIT_PER_METER, 0.0);
+    let stand_pos = nalgebra_glm::vec3(stand_xy.x, stand_xy.y, ground_height(island, stand_xy));
+    place_player(island, stand_pos, 0.0);
+    step(island, &idle_app(), 1)?;
+    step(island, &app_holding(&[Scancode::E]), 1)?;
+    step(island, &idle_app(), 1)?;
+
+    let maps = island.world.read_storage::<TreasureMapComponent>();
+    let opened = maps
+        .join()
+        .any(|map| map.treasure_entity == chest && map.found);
+    if !opened {
+        return Err(format!("the chest at {:?} wasn't opened", chest_pos));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs the scenario with the given name on every seed
+    fn simulate(name: &str) {
+        let scenario = SCENARIOS
+            .iter()
+            .find(|scenario| scenario.name == name)
+            .unwrap();
+        for seed in SIMULATION_SEEDS {
+            if let Err(err) = run_scenario(seed, scenario) {
+                panic!("seed {}: {}", seed, err);
+            }
+        }
+    }
+
+    #[test]
+    fn simulation_stands_on_ground() {
+        simulate("stands_on_ground");
+    }
+
+    #[test]
+    fn simulation_walks_forward() {
+        simulate("walks_forward");
+    }
+
+    #[test]
+    fn simulation_opens_chest() {
+        simulate("opens_chest");
+    }
+}
@@ -0,0 +1,367 @@
+// Dressing for the treasure sites, so that each one feels like its own place. After the chests are placed, every site
+// gets a theme: props scattered around the chest, a tint on the mobs guarding it, and a sound that plays nearby.
+
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use specs::{prelude::*, Component};
+
+use crate::engine::{
+    audio::AudioResource,
+    perlin::{PerlinMap, PerlinMapResource},
+    physics::PositionComponent,
+    render3d::{OpenGlResource, TintComponent},
+    time::TimeResource,
+    water::WaterResource,
+};
+
+use super::{
+    persistence::PersistentIdComponent, prefabs::spawn_site_prop, MobComponent, UNIT_PER_METER,
+};
+
+const SITE_RADIUS: f32 = 15.0 * UNIT_PER_METER; //< How far props are scattered from the chest
+const CLEAR_RADIUS: f32 = 4.0 * UNIT_PER_METER; //< Kept free of props around the chest, so it can be got at
+const GUARD_RADIUS: f32 = 20.0 * UNIT_PER_METER; //< Mobs this close to the chest are its guards, and get the tint
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize)]
+pub(super) enum SiteTheme {
+    Graveyard,
+    BonePile,
+    RuinedCamp,
+}
+
+#[derive(Clone, Copy)]
+pub(super) enum PropMesh {
+    Cube,
+    Cone,
+    Bush,
+}
+
+/// Something scattered around a site
+pub(super) struct Prop {
+    pub mesh: PropMesh,
+    pub scale: [f32; 3], //< Of the mesh, in meters
+    pub lift: f32,       //< Meters above the ground
+    pub color: [u8; 3],
+    pub radius: f32, //< Of the collider, in meters. Props without one can be walked and shot through.
+}
+
+/// How a theme dresses a site
+pub(super) struct ThemeStats {
+    props: &'static [Prop], //< Picked from at random
+    prop_count: usize,
+    pub mob_tint: [f32; 3],
+    pub sound: &'static str,
+    pub volume: f32, //< At the site itself, fading out to nothing at the edge of hearing
+    pub sound_period: f32, //< Seconds between plays
+}
+
+const GRAVESTONE: Prop = Prop {
+    mesh: PropMesh::Cube,
+    scale: [0.1, 0.35, 0.45],
+    lift: 0.2,
+    color: [125, 125, 130],
+    radius: 0.35,
+};
+const DEAD_BUSH: Prop = Prop {
+    mesh: PropMesh::Bush,
+    scale: [2.5, 2.5, 2.0],
+    lift: 0.0,
+    color: [70, 55, 40],
+    radius: 0.0,
+};
+const BONE: Prop = Prop {
+    mesh: PropMesh::Cube,
+    scale: [0.4, 0.05, 0.05],
+    lift: 0.05,
+    color: [230, 222, 195],
+    radius: 0.0,
+};
+const SKULL: Prop = Prop {
+    mesh: PropMesh::Cube,
+    scale: [0.12, 0.1, 0.1],
+    lift: 0.1,
+    color: [235, 228, 205],
+    radius: 0.0,
+};
+const TENT: Prop = Prop {
+    mesh: PropMesh::Cone,
+    scale: [2.0, 2.0, 1.2],
+    lift: 0.0,
+    color: [165, 145, 105],
+    radius: 1.4,
+};
+const CRATE: Prop = Prop {
+    mesh: PropMesh::Cube,
+    scale: [0.4, 0.4, 0.4],
+    lift: 0.4,
+    color: [110, 75, 40],
+    radius: 0.5,
+};
+const LOG: Prop = Prop {
+    mesh: PropMesh::Cube,
+    scale: [0.9, 0.15, 0.15],
+    lift: 0.15,
+    color: [45, 35, 30],
+    radius: 0.0,
+};
+
+const GRAVEYARD: ThemeStats = ThemeStats {
+    props: &[GRAVESTONE, GRAVESTONE, GRAVESTONE, DEAD_BUSH],
+    prop_count: 14,
+    mob_tint: [0.7, 0.9, 1.0],
+    sound: "res/dead.ogg",
+    volume: 40.0,
+    sound_period: 9.0,
+};
+const BONE_PILE: ThemeStats = ThemeStats {
+    props: &[BONE, BONE, BONE, SKULL],
+    prop_count: 30,
+    mob_tint: [1.0, 0.95, 0.75],
+    sound: "res/hit.ogg",
+    volume: 30.0,
+    sound_period: 5.0,
+};
+const RUINED_CAMP: ThemeStats = ThemeStats {
+    props: &[TENT, CRATE, CRATE, LOG],
+    prop_count: 8,
+    mob_tint: [1.0, 0.7, 0.6],
+    sound: "res/walk.ogg",
+    volume: 25.0,
+    sound_period: 3.0,
+};
+
+impl SiteTheme {
+    pub const ALL: [SiteTheme; 3] = [
+        SiteTheme::Graveyard,
+        SiteTheme::BonePile,
+        SiteTheme::RuinedCamp,
+    ];
+
+    pub fn stats(&self) -> &'static ThemeStats {
+        match self {
+            SiteTheme::Graveyard => &GRAVEYARD,
+            SiteTheme::BonePile => &BONE_PILE,
+            SiteTheme::RuinedCamp => &RUINED_CAMP,
+        }
+    }
+}
+
+/// Dressing scattered around a treasure site. Only there for looks, and the cover it gives.
+#[derive(Component, Default, Serialize, Deserialize)]
+#[storage(NullStorage)]
+pub(super) struct SitePropComponent;
+
+/// Plays a sound every so often, louder the closer the camera is
+#[derive(Component, Serialize)]
+#[storage(HashMapStorage)]
+pub(super) struct AmbientSoundComponent {
+    pub sound: &'static str,
+    pub volume: f32,
+    pub period: f32, //< Seconds between plays
+    pub seconds_left: f32,
+}
+
+/// Picks a theme for each treasure site and dresses it. Runs after the chests and their guards are placed. Themes are
+/// dealt out in turn from a shuffled deck, so that neighbouring sites don't all look the same.
+pub(super) fn dress_treasure_sites(
+    world: &mut World,
+    sites: &[nalgebra_glm::Vec3],
+    map: &PerlinMap,
+    water: &WaterResource,
+    seed: u64,
+) {
+    let mut rng = StdRng::seed_from_u64(seed ^ 0x517e_d2e5);
+    let mut deck = SiteTheme::ALL;
+    deck.shuffle(&mut rng);
+
+    for (i, site) in sites.iter().enumerate() {
+        let stats = deck[i % deck.len()].stats();
+        for _ in 0..stats.prop_count {
+            let prop = stats.props.choose(&mut rng).unwrap();
+            let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+            let dist = rng.gen_range(CLEAR_RADIUS..SITE_RADIUS);
+            let pos = site.xy() + nalgebra_glm::vec2(angle.cos(), angle.sin()) * dist;
+            if map.oob(pos) {
+                continue;
+            }
+            let pos = nalgebra_glm::vec3(pos.x, pos.y, map.get_z_interpolated(pos));
+            if !water.is_underwater(pos) {
+                spawn_site_prop(world, prop, pos);
+            }
+        }
+
+        let guards: Vec<Entity> = (
+            &world.read_storage::<MobComponent>(),
+            &world.read_storage::<PositionComponent>(),
+            &world.entities(),
+        )
+            .join()
+            .filter(|(_, position, _)| nalgebra_glm::distance(&position.pos, site) < GUARD_RADIUS)
+            .map(|(_, _, entity)| entity)
+            .collect();
+        for guard in guards {
+            world
+                .write_storage::<TintComponent>()
+                .insert(
+                    guard,
+                    TintComponent {
+                        color: nalgebra_glm::make_vec3(&stats.mob_tint),
+                    },
+                )
+                .unwrap();
+        }
+
+        world
+            .create_entity()
+            .with(PositionComponent { pos: *site })
+            .with(AmbientSoundComponent {
+                sound: stats.sound,
+                volume: stats.volume,
+                period: stats.sound_period,
+                // Staggered, so that sites don't all go off together
+                seconds_left: rng.gen_range(0.0..stats.sound_period),
+            })
+            .build();
+    }
+}
+
+/// Moves a treasure site's props, guards and sound along with its chest, each keeping its height off the ground.
+/// Anything that would end up in the sea or off the island is removed instead.
+pub(super) fn move_treasure_site(world: &World, from: nalgebra_glm::Vec3, to: nalgebra_glm::Vec3) {
+    let tiles = world.read_resource::<PerlinMapResource>();
+    let water = world.read_resource::<WaterResource>();
+    let entities = world.entities();
+    let props = world.read_storage::<SitePropComponent>();
+    let mobs = world.read_storage::<MobComponent>();
+    let ids = world.read_storage::<PersistentIdComponent>();
+    let sounds = world.read_storage::<AmbientSoundComponent>();
+    let mut positions = world.write_storage::<PositionComponent>();
+    let offset = to.xy() - from.xy();
+    for (entity, position) in (&entities, &mut positions).join() {
+        let dist = nalgebra_glm::distance(&position.pos.xy(), &from.xy());
+        // Only the camp's own mobs are saved, the spawner's just happen to be passing
+        let guard = mobs.contains(entity) && ids.contains(entity);
+        let part_of_site = (props.contains(entity) && dist < SITE_RADIUS)
+            || (guard && dist < GUARD_RADIUS)
+            || (sounds.contains(entity) && dist < CLEAR_RADIUS);
+        if !part_of_site {
+            continue;
+        }
+        let pos = position.pos.xy() + offset;
+        if tiles.map.oob(pos) {
+            entities.delete(entity).unwrap();
+            continue;
+        }
+        let ground = tiles.map.get_z_interpolated(pos);
+        if water.is_underwater(nalgebra_glm::vec3(pos.x, pos.y, ground)) {
+            entities.delete(entity).unwrap();
+            continue;
+        }
+        let lift = position.pos.z - tiles.map.get_z_interpolated(position.pos.xy());
+        position.pos = nalgebra_glm::vec3(pos.x, pos.y, ground + lift);
+    }
+}
+
+/// Plays ambient sounds as they come due, faded by how far away the camera is
+pub(super) struct AmbientSoundSystem;
+impl<'a> System<'a> for AmbientSoundSystem {
+    type SystemData = (
+        WriteStorage<'a, AmbientSoundComponent>,
+        ReadStorage<'a, PositionComponent>,
+        Read<'a, AudioResource>,
+        Read<'a, OpenGlResource>,
+        Read<'a, TimeResource>,
+    );
+
+    fn run(&mut self, (mut ambients, positions, audio, opengl, time): Self::SystemData) {
+        const HEARING_DIST: f32 = 40.0 * UNIT_PER_METER;
+        for (ambient, position) in (&mut ambients, &positions).join() {
+            ambient.seconds_left -= time.real_dt;
+            if ambient.seconds_left > 0.0 {
+                continue;
+            }
+            ambient.seconds_left += ambient.period;
+            let dist = nalgebra_glm::distance(&position.pos, &opengl.camera.position);
+            let falloff = (1.0 - dist / HEARING_DIST).max(0.0).powi(2);
+            if falloff > 0.0 {
+                audio
+                    .audio_mgr
+                    .play_sound(ambient.sound.to_string(), (ambient.volume * falloff) as i32);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{super::mobs::MobKind, *};
+
+    #[test]
+    fn sites_move_with_their_chest() {
+        let mut world = World::new();
+        world.register::<PositionComponent>();
+        world.register::<SitePropComponent>();
+        world.register::<MobComponent>();
+        world.register::<PersistentIdComponent>();
+        world.register::<AmbientSoundComponent>();
+        let map = Arc::new(PerlinMap::new(32, 0.1, 7, 4.0));
+        let (from, to) = (
+            nalgebra_glm::vec2(10.0, 10.0),
+            nalgebra_glm::vec2(20.0, 12.0),
+        );
+        let on_ground = |p: nalgebra_glm::Vec2, lift: f32| {
+            nalgebra_glm::vec3(p.x, p.y, map.get_z_interpolated(p) + lift)
+        };
+        let near = from + nalgebra_glm::vec2(0.3, 0.0);
+        let prop = world
+            .create_entity()
+            .with(PositionComponent {
+                pos: on_ground(near, 0.01),
+            })
+            .with(SitePropComponent)
+            .build();
+        let guard = world
+            .create_entity()
+            .with(PositionComponent {
+                pos: on_ground(near, 0.0),
+            })
+            .with(MobComponent {
+                kind: MobKind::Skeleton,
+            })
+            .with(PersistentIdComponent { id: 1 })
+            .build();
+        let passing = world
+            .create_entity()
+            .with(PositionComponent {
+                pos: on_ground(near, 0.0),
+            })
+            .with(MobComponent {
+                kind: MobKind::Ghost,
+            })
+            .build();
+        world.insert(PerlinMapResource {
+            map: map.clone(),
+            ..Default::default()
+        });
+        world.insert(WaterResource {
+            level: -1.0,
+            ..Default::default()
+        });
+
+        move_treasure_site(&world, on_ground(from, 0.0), on_ground(to, 0.0));
+
+        let positions = world.read_storage::<PositionComponent>();
+        let moved = to + nalgebra_glm::vec2(0.3, 0.0);
+        assert!(
+            nalgebra_glm::distance(&positions.get(prop).unwrap().pos, &on_ground(moved, 0.01))
+                < 1e-4
+        );
+        assert!(
+            nalgebra_glm::distance(&positions.get(guard).unwrap().pos, &on_ground(moved, 0.0))
+                < 1e-4
+        );
+        assert_eq!(positions.get(passing).unwrap().pos, on_ground(near, 0.0));
+    }
+}
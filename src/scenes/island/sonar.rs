@@ -0,0 +1,55 @@
+// The treasure sonar, which pings faster and louder the closer the nearest unfound chest is
+
+use specs::prelude::*;
+
+use crate::engine::{audio::AudioResource, physics::PositionComponent, time::TimeResource};
+
+use super::{weather::WeatherResource, PlayerComponent, TreasureMapComponent, UNIT_PER_METER};
+
+const SONAR_RANGE: f32 = 40.0 * UNIT_PER_METER; //< Chests further than this aren't picked up in clear weather
+const FOG_RANGE_BOOST: f32 = 1.5; //< Extra range, as a fraction of the normal range, in the thickest fog
+const MIN_PING_SECONDS: f32 = 0.32; //< Time between pings right next to a chest
+const MAX_PING_SECONDS: f32 = 2.4; //< Time between pings at the edge of the range
+
+#[derive(Default)]
+pub(super) struct SonarSystem {
+    next_ping: f32, //< Game time of the next ping, in seconds
+}
+impl<'a> System<'a> for SonarSystem {
+    type SystemData = (
+        ReadStorage<'a, TreasureMapComponent>,
+        ReadStorage<'a, PlayerComponent>,
+        ReadStorage<'a, PositionComponent>,
+        Read<'a, TimeResource>,
+        Read<'a, WeatherResource>,
+        Read<'a, AudioResource>,
+    );
+
+    fn run(&mut self, (treasure_maps, players, positions, time, weather, audio): Self::SystemData) {
+        if time.elapsed < self.next_ping {
+            return;
+        }
+
+        let player_pos = (&players, &positions).join().next().unwrap().1.pos;
+        let nearest = (&treasure_maps)
+            .join()
+            .filter(|map| !map.found)
+            .filter_map(|map| positions.get(map.treasure_entity))
+            .map(|position| nalgebra_glm::distance(&position.pos, &player_pos))
+            .min_by(|a, b| a.total_cmp(b));
+        let range = SONAR_RANGE * (1.0 + FOG_RANGE_BOOST * weather.fog);
+        let Some(dist) = nearest.filter(|&dist| dist < range) else {
+            return;
+        };
+
+        // Fog makes the ping louder, and it isn't muffled like everything else
+        let closeness = 1.0 - dist / range;
+        let volume = (16.0 + 48.0 * closeness) * (1.0 + weather.fog);
+        audio
+            .audio_mgr
+            .play_sound_unmuffled("res/pop.ogg".to_string(), volume as i32);
+        self.next_ping = time.elapsed
+            + MIN_PING_SECONDS
+            + (MAX_PING_SECONDS - MIN_PING_SECONDS) * (1.0 - closeness);
+    }
+}
@@ -0,0 +1,149 @@
+// Keeps the island populated. Mobs are spawned out of sight around the player until there are enough of them nearby,
+// more of them and more often at night, and ones the player has left far behind are removed again. Camp guards are
+// never removed, since they're part of the island.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use specs::prelude::*;
+
+use crate::engine::{
+    perlin::PerlinMapResource, physics::PositionComponent, time::TimeResource, water::WaterResource,
+};
+
+use super::{
+    biome::Biome, is_night, mobs::MobKind, persistence::PersistentIdComponent,
+    prefabs::spawn_roaming_mob, DeathSplishAnimComponent, MobComponent, PlayerComponent,
+    UNIT_PER_METER,
+};
+
+const POPULATION_RADIUS: f32 = 40.0 * UNIT_PER_METER; //< Mobs within this of the player count toward the population
+const MIN_SPAWN_DIST: f32 = 25.0 * UNIT_PER_METER;
+const MAX_SPAWN_DIST: f32 = 40.0 * UNIT_PER_METER;
+const DESPAWN_DIST: f32 = 60.0 * UNIT_PER_METER;
+const SPAWN_ATTEMPTS: usize = 8; //< Places tried before giving up on a spawn until next time
+const MAX_ENTITIES: usize = 4000; //< No spawning past this many entities, to keep the frame rate up
+
+/// How many mobs the spawner keeps around the player, and how often it spawns one
+struct SpawnRate {
+    population: usize,
+    interval: f32, //< Seconds between spawns
+}
+
+const DAY_RATE: SpawnRate = SpawnRate {
+    population: 6,
+    interval: 5.0,
+};
+const NIGHT_RATE: SpawnRate = SpawnRate {
+    population: 14,
+    interval: 2.0,
+};
+
+pub(super) struct SpawnerSystem {
+    rng: StdRng, //< Picks where mobs are spawned
+    seconds_since_spawn: f32,
+}
+
+impl SpawnerSystem {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed ^ 0x5fa_3e55),
+            seconds_since_spawn: 0.0,
+        }
+    }
+
+    /// Somewhere out of sight of the player that a mob can get around from, and the kind of mob that lives there
+    fn spawn_point(
+        &mut self,
+        player_pos: nalgebra_glm::Vec3,
+        tiles: &PerlinMapResource,
+        water: &WaterResource,
+    ) -> Option<(nalgebra_glm::Vec3, MobKind)> {
+        for _ in 0..SPAWN_ATTEMPTS {
+            let angle = self
+                .rng
+                .gen_range(-std::f32::consts::PI..std::f32::consts::PI);
+            let dist = self.rng.gen_range(MIN_SPAWN_DIST..MAX_SPAWN_DIST);
+            let xy = player_pos.xy() + nalgebra_glm::vec2(angle.cos(), angle.sin()) * dist;
+            if tiles.map.oob(xy) {
+                continue;
+            }
+            let pos = nalgebra_glm::vec3(xy.x, xy.y, tiles.map.get_z_interpolated(xy));
+            if water.is_underwater(pos) {
+                continue;
+            }
+            let biome = Biome::classify(
+                pos.z,
+                tiles.map.get_dot_prod(xy).abs(),
+                tiles.moisture.get(xy),
+                water.is_underwater(pos),
+            );
+            let Some(kind) = biome.config().mob else {
+                continue;
+            };
+            if water.depth(pos) <= kind.ai_params().max_water_depth {
+                return Some((pos, kind));
+            }
+        }
+        None
+    }
+}
+
+impl<'a> System<'a> for SpawnerSystem {
+    type SystemData = (
+        ReadStorage<'a, PlayerComponent>,
+        ReadStorage<'a, MobComponent>,
+        ReadStorage<'a, DeathSplishAnimComponent>,
+        ReadStorage<'a, PersistentIdComponent>,
+        ReadStorage<'a, PositionComponent>,
+        Read<'a, PerlinMapResource>,
+        Read<'a, WaterResource>,
+        Read<'a, TimeResource>,
+        Read<'a, LazyUpdate>,
+        Entities<'a>,
+    );
+
+    fn run(
+        &mut self,
+        (players, mobs, dying, ids, positions, tiles, water, time, lazy, entities): Self::SystemData,
+    ) {
+        let Some((_, player_position)) = (&players, &positions).join().next() else {
+            return;
+        };
+        let player_pos = player_position.pos;
+
+        // Spawned mobs the player has left far behind aren't coming back
+        for (_, _, position, entity) in (&mobs, !&ids, &positions, &entities).join() {
+            if nalgebra_glm::distance(&position.pos.xy(), &player_pos.xy()) > DESPAWN_DIST {
+                entities.delete(entity).unwrap();
+            }
+        }
+
+        let rate = if is_night(time.elapsed) {
+            &NIGHT_RATE
+        } else {
+            &DAY_RATE
+        };
+        self.seconds_since_spawn += time.dt;
+        if self.seconds_since_spawn < rate.interval {
+            return;
+        }
+        self.seconds_since_spawn = 0.0;
+
+        let population = (&mobs, !&dying, &positions)
+            .join()
+            .filter(|(_, _, position)| {
+                nalgebra_glm::distance(&position.pos.xy(), &player_pos.xy()) <= POPULATION_RADIUS
+            })
+            .count();
+        if population >= rate.population || (&entities).join().count() >= MAX_ENTITIES {
+            return;
+        }
+        if let Some((pos, kind)) = self.spawn_point(player_pos, &tiles, &water) {
+            let facing = self
+                .rng
+                .gen_range(-std::f32::consts::PI..std::f32::consts::PI);
+            lazy.exec_mut(move |world| {
+                spawn_roaming_mob(world, kind, pos, facing);
+            });
+        }
+    }
+}
@@ -0,0 +1,170 @@
+// The textures blended over the terrain. Each tile gets weights for sand, grass and rock from its height, slope and
+// moisture, along the same lines biomes are picked by but fading from one to the next. The textures themselves are
+// made from noise when the island is, so that they repeat without seams.
+
+use crate::engine::{
+    objects::Texture,
+    perlin::{MoistureMap, PerlinMap},
+    render3d::TerrainTexturesResource,
+};
+
+use super::MAP_WIDTH;
+
+const TEXTURE_SIZE: usize = 128;
+const NOISE_PERIOD: usize = 16; //< Noise cells across a texture, so that it wraps around
+const REPEATS: f32 = 4.0; //< Times the textures repeat across a tile, about every 5 meters
+const BEACH_FADE: f32 = 0.15; //< Height over which beaches fade into grass
+const CLIFF_SLOPE: std::ops::Range<f32> = 0.8..0.9; //< Ground turns to rock as it gets steeper than this
+const PEAK_HEIGHT: std::ops::Range<f32> = 3.5..4.5; //< Everything turns to rock going up through these heights
+const DRY_MOISTURE: f32 = 3.0; //< Drier than this, grass thins out to sandy soil
+
+/// How much sand, grass and rock a spot has, adding up to 1.
+/// - slope: dot product of the ground's normal with up, 1 is flat and 0 is a cliff
+/// - underwater: whether the spot is below the water, see `WaterResource::is_underwater`
+fn splat_weights(height: f32, slope: f32, moisture: f32, underwater: bool) -> [f32; 3] {
+    let rock = smoothstep(CLIFF_SLOPE.end, CLIFF_SLOPE.start, slope).max(smoothstep(
+        PEAK_HEIGHT.start,
+        PEAK_HEIGHT.end,
+        height,
+    ));
+    let beach = if underwater {
+        1.0
+    } else {
+        1.0 - smoothstep(-BEACH_FADE, BEACH_FADE, height - 0.9 * slope)
+    };
+    let dry = 0.5 * (1.0 - moisture / DRY_MOISTURE).clamp(0.0, 1.0);
+    let sand = (1.0 - rock) * beach.max(dry);
+    [sand, 1.0 - rock - sand, rock]
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// The weights for every tile, for the terrain shader to look up
+fn render_weights_texture(map: &PerlinMap, moisture: &MoistureMap, water_level: f32) -> Texture {
+    let mut pixels = Vec::with_capacity(MAP_WIDTH * MAP_WIDTH * 4);
+    // Like the moisture overlay, the first row is y = 0, so that tiles line up with texture coordinates
+    for y in 0..MAP_WIDTH {
+        for x in 0..MAP_WIDTH {
+            let p = nalgebra_glm::vec2(x as f32, y as f32);
+            let height = map.height(p);
+            let weights = splat_weights(
+                height,
+                map.get_normal(p).z,
+                moisture.get(p),
+                height < water_level,
+            );
+            pixels.extend(weights.map(|w| (w * 255.0).round() as u8));
+            pixels.push(255);
+        }
+    }
+    Texture::from_rgba(MAP_WIDTH as i32, MAP_WIDTH as i32, &pixels)
+}
+
+/// Value noise that wraps around every `NOISE_PERIOD` cells, in [0, 1]
+fn tiling_noise(x: f32, y: f32, octave: u32) -> f32 {
+    let hash = |cx: usize, cy: usize| {
+        let (cx, cy) = (cx % NOISE_PERIOD, cy % NOISE_PERIOD);
+        let mut h = (cx as u32).wrapping_mul(374_761_393)
+            ^ (cy as u32).wrapping_mul(668_265_263)
+            ^ octave.wrapping_mul(2_246_822_519);
+        h = (h ^ (h >> 13)).wrapping_mul(1_274_126_177);
+        (h ^ (h >> 16)) as f32 / u32::MAX as f32
+    };
+    let (cx, cy) = (x.floor() as usize, y.floor() as usize);
+    let (tx, ty) = (
+        smoothstep(0.0, 1.0, x.fract()),
+        smoothstep(0.0, 1.0, y.fract()),
+    );
+    let top = hash(cx, cy) + tx * (hash(cx + 1, cy) - hash(cx, cy));
+    let bottom = hash(cx, cy + 1) + tx * (hash(cx + 1, cy + 1) - hash(cx, cy + 1));
+    top + ty * (bottom - top)
+}
+
+/// A few octaves of tiling noise, in [0, 1]
+fn tiling_fbm(u: f32, v: f32) -> f32 {
+    let mut sum = 0.0;
+    let mut weight = 0.5;
+    let mut total = 0.0;
+    for octave in 0..3 {
+        let scale = (NOISE_PERIOD << octave) as f32;
+        sum += weight * tiling_noise(u * scale, v * scale, octave);
+        total += weight;
+        weight *= 0.5;
+    }
+    sum / total
+}
+
+/// A repeating texture colored by `color` from noise over the texture, for `u` and `v` in [0, 1)
+fn render_noise_texture(color: impl Fn(f32, f32, f32) -> [f32; 3]) -> Texture {
+    let mut pixels = Vec::with_capacity(TEXTURE_SIZE * TEXTURE_SIZE * 4);
+    for y in 0..TEXTURE_SIZE {
+        for x in 0..TEXTURE_SIZE {
+            let (u, v) = (
+                x as f32 / TEXTURE_SIZE as f32,
+                y as f32 / TEXTURE_SIZE as f32,
+            );
+            let rgb = color(u, v, tiling_fbm(u, v));
+            pixels.extend(rgb.map(|c| (c.clamp(0.0, 1.0) * 255.0) as u8));
+            pixels.push(255);
+        }
+    }
+    Texture::from_rgba(TEXTURE_SIZE as i32, TEXTURE_SIZE as i32, &pixels).repeating()
+}
+
+/// The terrain textures for an island
+pub(super) fn terrain_textures_resource(
+    map: &PerlinMap,
+    moisture: &MoistureMap,
+    water_level: f32,
+) -> TerrainTexturesResource {
+    TerrainTexturesResource {
+        weights: Some(render_weights_texture(map, moisture, water_level)),
+        sand: render_noise_texture(|_, _, n| {
+            let shade = 0.9 + 0.2 * n;
+            [0.86 * shade, 0.74 * shade, 0.62 * shade]
+        }),
+        // Blades and bare patches, grey so that the biome's color shows through
+        grass: render_noise_texture(|u, v, n| {
+            let fine = tiling_noise(u * 64.0, v * 64.0, 7);
+            let shade = 0.35 + 0.2 * n + 0.15 * fine;
+            [shade, shade * 1.05, shade * 0.9]
+        }),
+        // Cracks run along where the noise crosses the middle
+        rock: render_noise_texture(|_, _, n| {
+            let crack = smoothstep(0.0, 0.04, (n - 0.5).abs());
+            let shade = (0.75 + 0.5 * n) * (0.6 + 0.4 * crack);
+            [0.5 * shade, 0.45 * shade, 0.4 * shade]
+        }),
+        repeats: REPEATS,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weights_add_up_to_one() {
+        for (height, slope, moisture) in [(0.1, 1.0, 0.0), (2.0, 0.85, 10.0), (5.0, 0.5, 100.0)] {
+            let weights = splat_weights(height, slope, moisture, false);
+            assert!((weights.iter().sum::<f32>() - 1.0).abs() < 1e-6);
+            assert!(weights.iter().all(|w| *w >= 0.0));
+        }
+    }
+
+    #[test]
+    fn beaches_are_sand_and_cliffs_are_rock() {
+        assert!(splat_weights(0.0, 1.0, 30.0, false)[0] > 0.9);
+        assert!(splat_weights(2.0, 1.0, 30.0, false)[1] > 0.9);
+        assert!(splat_weights(2.0, 0.5, 30.0, false)[2] > 0.9);
+    }
+
+    #[test]
+    fn noise_wraps_around() {
+        let period = NOISE_PERIOD as f32;
+        assert!((tiling_noise(0.3, 2.7, 0) - tiling_noise(0.3 + period, 2.7, 0)).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,161 @@
+// Stamina. Sprinting, jumping and swimming tire the player out, and they get their breath back walking, and faster
+// standing still. Running out leaves them winded, unable to sprint or jump and swimming slowly, until they've
+// recovered a good part of it.
+
+use serde::{Deserialize, Serialize};
+use specs::{prelude::*, Component};
+
+use crate::engine::{
+    physics::{PositionComponent, VelocityComponent},
+    text::QuadComponent,
+    time::{per_second, TimeResource},
+    water::WaterResource,
+};
+
+use super::{
+    health_bars::PLAYER_BAR_WIDTH,
+    swimming::{OXYGEN_METER_HEIGHT, OXYGEN_METER_TOP},
+    PlayerComponent, UNIT_PER_METER,
+};
+
+const SPRINT_DRAIN: f32 = 0.12; //< Stamina per second, so a full bar lasts about eight seconds of sprinting
+const SWIM_DRAIN: f32 = 0.04; //< Stamina per second, while in the water
+pub(super) const JUMP_COST: f32 = 0.08;
+pub(super) const WINDED_SWIM_SPEED: f32 = 0.6; //< Of the usual swimming speed
+const WALK_REGEN: f32 = 0.1; //< Stamina per second, while walking
+const REST_REGEN: f32 = 0.25; //< Stamina per second, while standing still
+const RECOVERED: f32 = 0.3; //< How much stamina a winded player needs back before they can sprint or jump again
+const REST_SPEED: f32 = 0.2 * UNIT_PER_METER; //< Meters per second, slower than this is standing still
+pub(super) const STAMINA_BAR_TOP: i32 = OXYGEN_METER_TOP + OXYGEN_METER_HEIGHT + 4; //< Just under the oxygen meter
+pub(super) const STAMINA_BAR_HEIGHT: i32 = 4;
+const STAMINA_COLOR: nalgebra_glm::Vec3 = nalgebra_glm::Vec3::new(0.95, 0.8, 0.25);
+const WINDED_COLOR: nalgebra_glm::Vec3 = nalgebra_glm::Vec3::new(0.6, 0.45, 0.3);
+
+#[derive(Component, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub(super) struct StaminaComponent {
+    pub stamina: f32, //< [0, 1]
+    pub winded: bool, //< Ran out, and hasn't recovered yet
+}
+
+impl Default for StaminaComponent {
+    fn default() -> Self {
+        Self {
+            stamina: 1.0,
+            winded: false,
+        }
+    }
+}
+
+impl StaminaComponent {
+    /// Whether the player has the legs to sprint or jump
+    pub fn fresh(&self) -> bool {
+        !self.winded
+    }
+
+    /// Uses up some stamina, leaving the player winded if it runs out
+    pub fn spend(&mut self, amount: f32) {
+        self.stamina = (self.stamina - amount).max(0.0);
+        if self.stamina == 0.0 {
+            self.winded = true;
+        }
+    }
+
+    /// Gives back some stamina
+    pub fn recover(&mut self, amount: f32) {
+        self.stamina = (self.stamina + amount).min(1.0);
+        if self.stamina >= RECOVERED {
+            self.winded = false;
+        }
+    }
+}
+
+/// The stamina bar under the health bar, or the dark track behind it. Only shown while the player is tired.
+#[derive(Component)]
+#[storage(HashMapStorage)]
+pub(super) struct StaminaBarComponent {
+    pub track: bool,
+}
+
+/// Tires the player out while they sprint or swim, and lets them recover otherwise. Jumps are paid for as they're made.
+pub(super) struct StaminaSystem;
+impl<'a> System<'a> for StaminaSystem {
+    type SystemData = (
+        WriteStorage<'a, StaminaComponent>,
+        ReadStorage<'a, PlayerComponent>,
+        ReadStorage<'a, PositionComponent>,
+        ReadStorage<'a, VelocityComponent>,
+        Read<'a, WaterResource>,
+        Read<'a, TimeResource>,
+    );
+
+    fn run(
+        &mut self,
+        (mut staminas, players, positions, velocities, water, time): Self::SystemData,
+    ) {
+        for (stamina, player, position, velocity) in
+            (&mut staminas, &players, &positions, &velocities).join()
+        {
+            let speed = per_second(nalgebra_glm::length(&velocity.vel.xy()));
+            if water.is_underwater(position.pos) {
+                stamina.spend(SWIM_DRAIN * time.dt);
+            } else if player.sprinting {
+                stamina.spend(SPRINT_DRAIN * time.dt);
+            } else if speed < REST_SPEED {
+                stamina.recover(REST_REGEN * time.dt);
+            } else {
+                stamina.recover(WALK_REGEN * time.dt);
+            }
+        }
+    }
+}
+
+/// Keeps the stamina bar in sync with the player's stamina
+pub(super) struct StaminaBarSystem;
+impl<'a> System<'a> for StaminaBarSystem {
+    type SystemData = (
+        ReadStorage<'a, StaminaBarComponent>,
+        ReadStorage<'a, StaminaComponent>,
+        ReadStorage<'a, PlayerComponent>,
+        WriteStorage<'a, QuadComponent>,
+    );
+
+    fn run(&mut self, (bars, staminas, players, mut quads): Self::SystemData) {
+        let Some((stamina, _)) = (&staminas, &players).join().next() else {
+            return;
+        };
+        let shown = if stamina.stamina < 1.0 { 1.0 } else { 0.0 };
+        for (bar, quad) in (&bars, &mut quads).join() {
+            if bar.track {
+                quad.opacity = 0.6 * shown;
+            } else {
+                quad.width = (PLAYER_BAR_WIDTH as f32 * stamina.stamina).round() as i32;
+                quad.tint = if stamina.winded {
+                    WINDED_COLOR
+                } else {
+                    STAMINA_COLOR
+                };
+                quad.opacity = shown;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn running_out_leaves_the_player_winded_until_they_recover() {
+        let mut stamina = StaminaComponent::default();
+        stamina.spend(0.6);
+        assert!(stamina.fresh());
+        stamina.spend(0.6);
+        assert_eq!(stamina.stamina, 0.0);
+        assert!(!stamina.fresh());
+        stamina.recover(RECOVERED / 2.0);
+        assert!(!stamina.fresh());
+        stamina.recover(RECOVERED);
+        assert!(stamina.fresh());
+    }
+}
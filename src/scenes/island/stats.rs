@@ -0,0 +1,135 @@
+// Stats kept over a run, and the score worked out from them at the end, win or lose, for the summary screen
+
+use specs::prelude::*;
+
+use super::{HealthComponent, PlayerComponent};
+
+const POINTS_PER_TREASURE: f32 = 1000.0;
+const POINTS_PER_GOLD: f32 = 5.0;
+const POINTS_PER_MOB: f32 = 100.0;
+const POINTS_FOR_ACCURACY: f32 = 1000.0; //< For landing every attack
+const POINTS_PER_HEALTH_LOST: f32 = 500.0; //< Taken off for each full health bar of damage
+const POINTS_FOR_WINNING: f32 = 2000.0;
+const PAR_SECONDS: f32 = 1800.0; //< A point for every second a win comes in under this
+const RANKS: [(u32, &str); 4] = [(10000, "S"), (7500, "A"), (5000, "B"), (2500, "C")];
+
+#[derive(Default)]
+pub(super) struct StatsResource {
+    pub shots_fired: u32,
+    pub attacks: u32,        //< Bullets and pellets fired, and melee swings
+    pub attacks_landed: u32, //< Attacks that hit something
+    pub mobs_defeated: u32,
+    pub gold_earned: u32,  //< Before anything was spent at the trader
+    pub damage_taken: f32, //< In full health bars
+}
+
+impl StatsResource {
+    /// The fraction of attacks that hit something, if any were made
+    pub fn accuracy(&self) -> Option<f32> {
+        (self.attacks > 0).then(|| self.attacks_landed as f32 / self.attacks as f32)
+    }
+}
+
+/// How the run went, shown on the summary screen
+#[derive(Clone, Debug)]
+pub(crate) struct GameSummary {
+    pub won: bool,    //< Or died
+    pub seconds: f32, //< Game time taken
+    pub maps_found: usize,
+    pub shots_fired: u32,
+    pub accuracy: Option<f32>,
+    pub mobs_defeated: u32,
+    pub gold_earned: u32,
+    pub damage_taken: f32,
+}
+
+impl GameSummary {
+    pub(super) fn new(won: bool, seconds: f32, maps_found: usize, stats: &StatsResource) -> Self {
+        Self {
+            won,
+            seconds,
+            maps_found,
+            shots_fired: stats.shots_fired,
+            accuracy: stats.accuracy(),
+            mobs_defeated: stats.mobs_defeated,
+            gold_earned: stats.gold_earned,
+            damage_taken: stats.damage_taken,
+        }
+    }
+
+    pub fn score(&self) -> u32 {
+        let mut score = self.maps_found as f32 * POINTS_PER_TREASURE
+            + self.gold_earned as f32 * POINTS_PER_GOLD
+            + self.mobs_defeated as f32 * POINTS_PER_MOB
+            + self.accuracy.unwrap_or(0.0) * POINTS_FOR_ACCURACY
+            - self.damage_taken * POINTS_PER_HEALTH_LOST;
+        if self.won {
+            score += POINTS_FOR_WINNING + (PAR_SECONDS - self.seconds).max(0.0);
+        }
+        score.max(0.0).round() as u32
+    }
+
+    pub fn rank(&self) -> &'static str {
+        let score = self.score();
+        RANKS
+            .iter()
+            .find(|(points, _)| score >= *points)
+            .map_or("D", |(_, rank)| rank)
+    }
+}
+
+/// Adds up the damage the player takes, from whatever it comes from
+#[derive(Default)]
+pub(super) struct StatsSystem {
+    last_health: Option<f32>,
+}
+impl<'a> System<'a> for StatsSystem {
+    type SystemData = (
+        ReadStorage<'a, PlayerComponent>,
+        ReadStorage<'a, HealthComponent>,
+        Write<'a, StatsResource>,
+    );
+
+    fn run(&mut self, (players, healths, mut stats): Self::SystemData) {
+        let Some((_, health)) = (&players, &healths).join().next() else {
+            return;
+        };
+        if let Some(last_health) = self.last_health {
+            stats.damage_taken += (last_health - health.health).max(0.0);
+        }
+        self.last_health = Some(health.health);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(won: bool, seconds: f32, maps_found: usize) -> GameSummary {
+        let stats = StatsResource {
+            shots_fired: 20,
+            attacks: 40,
+            attacks_landed: 10,
+            mobs_defeated: 5,
+            gold_earned: 100,
+            damage_taken: 1.5,
+        };
+        GameSummary::new(won, seconds, maps_found, &stats)
+    }
+
+    #[test]
+    fn winning_quickly_scores_more() {
+        let quick = summary(true, 600.0, 7);
+        let slow = summary(true, 3600.0, 7);
+        assert_eq!(quick.accuracy, Some(0.25));
+        assert!(quick.score() > slow.score());
+        assert!(summary(false, 600.0, 7).score() < slow.score());
+    }
+
+    #[test]
+    fn ranks_follow_the_score() {
+        assert_eq!(summary(true, 600.0, 7).rank(), "S");
+        assert_eq!(summary(false, 600.0, 2).rank(), "C");
+        assert_eq!(summary(false, 60.0, 0).rank(), "D");
+    }
+}
@@ -0,0 +1,66 @@
+// Status effects: temporary changes to how a mob or the player fares, that wear off on their own
+
+use serde::{Deserialize, Serialize};
+use specs::{prelude::*, Component};
+
+use crate::engine::time::TimeResource;
+
+const WEAKENED_DAMAGE_TAKEN: f32 = 2.0; //< Damage taken multiplier while weakened
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub(super) enum StatusKind {
+    Weakened, //< Takes extra damage, like ghosts in torchlight
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub(super) struct StatusEffect {
+    pub kind: StatusKind,
+    pub seconds_left: f32,
+}
+
+#[derive(Component, Default, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub(super) struct StatusComponent {
+    pub effects: Vec<StatusEffect>,
+}
+
+impl StatusComponent {
+    /// Starts an effect, or tops it back up if it's already on
+    pub fn apply(&mut self, kind: StatusKind, seconds: f32) {
+        match self.effects.iter_mut().find(|effect| effect.kind == kind) {
+            Some(effect) => effect.seconds_left = effect.seconds_left.max(seconds),
+            None => self.effects.push(StatusEffect {
+                kind,
+                seconds_left: seconds,
+            }),
+        }
+    }
+
+    pub fn has(&self, kind: StatusKind) -> bool {
+        self.effects.iter().any(|effect| effect.kind == kind)
+    }
+
+    /// How much more damage than usual hits do
+    pub fn damage_taken_multiplier(&self) -> f32 {
+        if self.has(StatusKind::Weakened) {
+            WEAKENED_DAMAGE_TAKEN
+        } else {
+            1.0
+        }
+    }
+}
+
+/// Wears effects off
+pub(super) struct StatusSystem;
+impl<'a> System<'a> for StatusSystem {
+    type SystemData = (WriteStorage<'a, StatusComponent>, Read<'a, TimeResource>);
+
+    fn run(&mut self, (mut statuses, time): Self::SystemData) {
+        for status in (&mut statuses).join() {
+            for effect in &mut status.effects {
+                effect.seconds_left -= time.dt;
+            }
+            status.effects.retain(|effect| effect.seconds_left > 0.0);
+        }
+    }
+}
@@ -0,0 +1,63 @@
+// What went into an island, counted once it's been placed. Printed to the log and shown on the loading screen, for
+// balancing and for spotting seeds that came out badly.
+
+use specs::prelude::*;
+
+use crate::engine::render3d::MeshComponent;
+
+use super::{
+    prefabs::PrefabResource, sites::SitePropComponent, worldgen::TerrainStats, ChestComponent,
+    MobComponent,
+};
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct WorldSummaryResource {
+    pub seed: u64,
+    pub land_area: f32, //< Square kilometers
+    pub coastline: f32, //< Kilometers
+    pub trees: usize,
+    pub bushes: usize, //< Including the ones in walls
+    pub chests: usize,
+    pub mobs: usize,
+}
+
+impl WorldSummaryResource {
+    /// Counts what's on the island so far
+    pub fn count(world: &World, seed: u64, stats: TerrainStats) -> Self {
+        let prefabs = world.read_resource::<PrefabResource>();
+        let meshes = world.read_storage::<MeshComponent>();
+        // Treasure site props share meshes with the trees and bushes, but aren't counted with them
+        let props = world.read_storage::<SitePropComponent>();
+        let with_mesh = |mesh_id| {
+            (&meshes, !&props)
+                .join()
+                .filter(|(mesh, _)| mesh.mesh_id == mesh_id)
+                .count()
+        };
+        Self {
+            seed,
+            land_area: stats.land_area,
+            coastline: stats.coastline,
+            trees: with_mesh(prefabs.tree_mesh),
+            bushes: with_mesh(prefabs.bush_mesh),
+            chests: world.read_storage::<ChestComponent>().join().count(),
+            mobs: world.read_storage::<MobComponent>().join().count(),
+        }
+    }
+
+    /// The size of the island, in a line
+    pub fn terrain_line(&self) -> String {
+        format!(
+            "Seed {}: {:.1}km² of land, {:.1}km of coastline",
+            self.seed, self.land_area, self.coastline
+        )
+    }
+
+    /// What's on the island, in a line
+    pub fn entity_line(&self) -> String {
+        format!(
+            "{} trees, {} bushes, {} chests, {} mobs",
+            self.trees, self.bushes, self.chests, self.mobs
+        )
+    }
+}
@@ -0,0 +1,202 @@
+// Swimming. The water carries most of a swimmer's weight, and pushes them up until they float with their head above
+// the surface, bobbing up and down. With their head under, the player holds their breath, and once it runs out they
+// start to drown. The screen turns blue and sounds are muffled while they're under.
+
+use std::f32::consts::PI;
+
+use serde::{Deserialize, Serialize};
+use specs::{prelude::*, Component};
+
+use crate::{
+    engine::{
+        audio::AudioResource,
+        physics::{PositionComponent, VelocityComponent},
+        render3d::OpenGlResource,
+        text::QuadComponent,
+        time::{accel_per_tick, TimeResource},
+        water::WaterResource,
+    },
+    App,
+};
+
+use super::{
+    health_bars::{PLAYER_BAR_HEIGHT, PLAYER_BAR_MARGIN, PLAYER_BAR_WIDTH},
+    HealthComponent, PlayerComponent, ToastResource, GRAVITY, PERSON_HEIGHT, UNIT_PER_METER,
+};
+
+const WATER_GRAVITY: f32 = 0.3; //< How much of gravity is left under the water
+const FLOAT_FRACTION: f32 = 0.85; //< How much of a swimmer is under the surface while they float
+const WATER_DRAG: f32 = 3.0; //< Per second, how quickly bobbing up and down settles
+const BOB_HEIGHT: f32 = 0.08 * UNIT_PER_METER; //< How far the surface a swimmer floats at rises and falls
+const BOB_SECONDS: f32 = 2.5; //< Seconds per bob
+pub(super) const OXYGEN_SECONDS: f32 = 20.0; //< How long the player can hold their breath
+const OXYGEN_REFILL: f32 = 5.0; //< Seconds of breath got back per second with their head above water
+const DROWN_DAMAGE: f32 = 0.08; //< Health per second, once the player is out of breath
+const TINT_OPACITY: f32 = 0.35;
+pub(super) const OXYGEN_METER_TOP: i32 = PLAYER_BAR_MARGIN + PLAYER_BAR_HEIGHT + 6; //< Just under the health bar
+pub(super) const OXYGEN_METER_HEIGHT: i32 = 6;
+
+/// Something that floats in water, and has to hold its breath under it
+#[derive(Component, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub(super) struct SwimmerComponent {
+    pub oxygen: f32, //< Seconds of breath left
+}
+
+impl Default for SwimmerComponent {
+    fn default() -> Self {
+        Self {
+            oxygen: OXYGEN_SECONDS,
+        }
+    }
+}
+
+/// The oxygen meter under the health bar, or the dark track behind it. Only shown while the player is short of breath.
+#[derive(Component)]
+#[storage(HashMapStorage)]
+pub(super) struct OxygenMeterComponent {
+    pub track: bool,
+}
+
+/// A blue quad covering the whole screen while the player's head is underwater
+#[derive(Component)]
+#[storage(HashMapStorage)]
+pub(super) struct UnderwaterTintComponent;
+
+/// How hard the water pushes up on a swimmer with `submerged` of their height under the surface, per second squared.
+/// Up to where they float, it carries all but a little of their weight, and a little past it, all of it.
+fn buoyancy(submerged: f32) -> f32 {
+    let submerged = submerged.clamp(0.0, 1.0);
+    let carried = GRAVITY * (1.0 - WATER_GRAVITY) * (submerged / FLOAT_FRACTION).min(1.0);
+    let pushed = GRAVITY * WATER_GRAVITY * submerged / FLOAT_FRACTION;
+    carried + pushed
+}
+
+/// Floats swimmers, and runs the player's breath down while their head is under
+pub(super) struct SwimmingSystem;
+impl<'a> System<'a> for SwimmingSystem {
+    type SystemData = (
+        WriteStorage<'a, SwimmerComponent>,
+        ReadStorage<'a, PlayerComponent>,
+        WriteStorage<'a, HealthComponent>,
+        ReadStorage<'a, PositionComponent>,
+        WriteStorage<'a, VelocityComponent>,
+        Read<'a, WaterResource>,
+        Read<'a, OpenGlResource>,
+        Read<'a, TimeResource>,
+        Write<'a, AudioResource>,
+        Write<'a, ToastResource>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            mut swimmers,
+            players,
+            mut healths,
+            positions,
+            mut velocities,
+            water,
+            open_gl,
+            time,
+            mut audio,
+            mut toast,
+        ): Self::SystemData,
+    ) {
+        let bob = BOB_HEIGHT * (2.0 * PI * time.elapsed / BOB_SECONDS).sin();
+        for (position, velocity, _) in (&positions, &mut velocities, &swimmers).join() {
+            let submerged = (water.depth(position.pos) + bob) / PERSON_HEIGHT;
+            if submerged <= 0.0 {
+                continue;
+            }
+            velocity.vel.z += accel_per_tick(buoyancy(submerged)) * time.rate();
+            velocity.vel.z *= (1.0 - WATER_DRAG * time.dt).max(0.0);
+        }
+
+        // The camera is the player's head
+        let head_under = water.is_underwater(open_gl.camera.position);
+        audio.audio_mgr.set_underwater(head_under);
+        for (swimmer, health, _) in (&mut swimmers, &mut healths, &players).join() {
+            if !head_under {
+                swimmer.oxygen = (swimmer.oxygen + OXYGEN_REFILL * time.dt).min(OXYGEN_SECONDS);
+                continue;
+            }
+            let had_oxygen = swimmer.oxygen > 0.0;
+            swimmer.oxygen = (swimmer.oxygen - time.dt).max(0.0);
+            if swimmer.oxygen > 0.0 {
+                continue;
+            }
+            if had_oxygen {
+                toast.show("You're out of breath, get to the surface!");
+            }
+            health.health -= DROWN_DAMAGE * time.dt;
+        }
+    }
+}
+
+/// Keeps the oxygen meter in sync with the player's breath, and the underwater tint over the screen
+pub(super) struct SwimmingHudSystem;
+impl<'a> System<'a> for SwimmingHudSystem {
+    type SystemData = (
+        ReadStorage<'a, OxygenMeterComponent>,
+        ReadStorage<'a, UnderwaterTintComponent>,
+        ReadStorage<'a, SwimmerComponent>,
+        ReadStorage<'a, PlayerComponent>,
+        WriteStorage<'a, QuadComponent>,
+        WriteStorage<'a, PositionComponent>,
+        Read<'a, WaterResource>,
+        Read<'a, OpenGlResource>,
+        Read<'a, App>,
+    );
+
+    fn run(
+        &mut self,
+        (meters, tints, swimmers, players, mut quads, mut positions, water, open_gl, app): Self::SystemData,
+    ) {
+        let Some((swimmer, _)) = (&swimmers, &players).join().next() else {
+            return;
+        };
+        let oxygen = swimmer.oxygen / OXYGEN_SECONDS;
+        let shown = if oxygen < 1.0 { 1.0 } else { 0.0 };
+        for (meter, quad) in (&meters, &mut quads).join() {
+            if meter.track {
+                quad.opacity = 0.6 * shown;
+            } else {
+                quad.width = (PLAYER_BAR_WIDTH as f32 * oxygen).round() as i32;
+                quad.opacity = shown;
+            }
+        }
+
+        let head_under = water.is_underwater(open_gl.camera.position);
+        for (_, quad, position) in (&tints, &mut quads, &mut positions).join() {
+            quad.opacity = if head_under { TINT_OPACITY } else { 0.0 };
+            quad.width = app.screen_width;
+            quad.height = app.screen_height;
+            position.pos = nalgebra_glm::zero();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dry_swimmers_are_not_held_up() {
+        assert_eq!(buoyancy(0.0), 0.0);
+        assert_eq!(buoyancy(-1.0), 0.0);
+    }
+
+    #[test]
+    fn swimmers_float_partly_under() {
+        assert!((buoyancy(FLOAT_FRACTION) - GRAVITY).abs() < 1e-6);
+        assert!(buoyancy(FLOAT_FRACTION - 0.1) < GRAVITY);
+    }
+
+    #[test]
+    fn sunk_swimmers_rise_gently() {
+        let rise = buoyancy(1.0) - GRAVITY;
+        assert!(0.0 < rise && rise < GRAVITY * WATER_GRAVITY);
+        assert_eq!(buoyancy(2.0), buoyancy(1.0));
+    }
+}
@@ -0,0 +1,63 @@
+// Debug views drawn over the terrain, cycled through with F4: the tile grid with chunk borders, a slope heat map, and
+// the moisture left by erosion. For tuning where things are scattered, and how the island erodes.
+
+use sdl2::keyboard::Scancode;
+use specs::prelude::*;
+
+use crate::{
+    engine::{
+        objects::Texture,
+        perlin::MoistureMap,
+        render3d::{TerrainOverlay, TerrainOverlayResource},
+    },
+    App,
+};
+
+use super::{ToastResource, CHUNK_SIZE, MAP_WIDTH};
+
+const MOISTURE_SCALE: f32 = 100.0; //< Moisture shown as soaking wet, well past swamp
+
+/// The moisture map as a texture, for the moisture overlay to look up by tile
+fn render_moisture_texture(moisture: &MoistureMap) -> Texture {
+    let mut pixels = Vec::with_capacity(MAP_WIDTH * MAP_WIDTH * 4);
+    // Unlike the minimap, the first row is y = 0, so that tiles line up with texture coordinates
+    for y in 0..MAP_WIDTH {
+        for x in 0..MAP_WIDTH {
+            let value = moisture.get(nalgebra_glm::vec2(x as f32, y as f32)) / MOISTURE_SCALE;
+            pixels.extend([(value.clamp(0.0, 1.0) * 255.0) as u8, 0, 0, 255]);
+        }
+    }
+    Texture::from_rgba(MAP_WIDTH as i32, MAP_WIDTH as i32, &pixels)
+}
+
+/// The overlay resource for an island, with the overlay off to begin with
+pub(super) fn terrain_overlay_resource(moisture: &MoistureMap) -> TerrainOverlayResource {
+    TerrainOverlayResource {
+        overlay: TerrainOverlay::Off,
+        moisture: Some(render_moisture_texture(moisture)),
+        map_width: MAP_WIDTH as f32,
+        chunk_size: CHUNK_SIZE as f32,
+    }
+}
+
+/// Steps through the overlays with F4
+#[derive(Default)]
+pub(super) struct TerrainOverlaySystem {
+    toggle_was_down: bool,
+}
+impl<'a> System<'a> for TerrainOverlaySystem {
+    type SystemData = (
+        Write<'a, TerrainOverlayResource>,
+        Write<'a, ToastResource>,
+        Read<'a, App>,
+    );
+
+    fn run(&mut self, (mut overlay, mut toast, app): Self::SystemData) {
+        let toggle_down = app.keys[Scancode::F4 as usize];
+        if toggle_down && !self.toggle_was_down {
+            overlay.overlay = overlay.overlay.next();
+            toast.show(&format!("Terrain overlay: {:?}", overlay.overlay));
+        }
+        self.toggle_was_down = toggle_down;
+    }
+}
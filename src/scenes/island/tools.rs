@@ -0,0 +1,503 @@
+// Tools that gate progress: the machete cuts through bush walls, and the shovel digs up buried chests. The first
+// chests aren't buried and have the tools in them, otherwise they can be bought from the trader. Tools go in the
+// player's inventory, and only work while they're in hand. Once the tools are sold, the trader sells hints at where
+// the treasure is instead. The trader also sells a parrot, which flies off toward treasure now and then.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use specs::{
+    prelude::*,
+    shrev::{EventChannel, ReaderId},
+    Component,
+};
+
+use crate::{
+    engine::{
+        audio::AudioResource,
+        input::{Action, InputMap},
+        particles::{spawn_emitter, EmitterPreset},
+        perlin::PerlinMapResource,
+        physics::PositionComponent,
+        settings::GameplaySettings,
+        water::WaterResource,
+    },
+    App,
+};
+
+use super::{
+    interaction::{interact_reader, InteractAction, InteractEvent},
+    inventory::{InventoryComponent, Item},
+    is_treasure_spot,
+    map_view::redraw_map_view,
+    minimap::HINT_RADIUS,
+    parrot::{ParrotResource, PARROT_PRICE},
+    sites::move_treasure_site,
+    weapons::WeaponComponent,
+    DialogResource, GoldResource, PlayerComponent, TreasureMapComponent, UNIT_PER_METER,
+};
+
+const CUT_DIST: f32 = 2.5 * UNIT_PER_METER; //< How close the player has to be to cut a bush
+pub(super) const TRADE_DIST: f32 = 3.0 * UNIT_PER_METER; //< How close the player has to be to trade
+pub(super) const CHEST_REACH: f32 = 3.0 * UNIT_PER_METER; //< How close the player has to be to open a chest
+const HINT_PRICE: u32 = 15;
+const REROLL_PRICE: u32 = 30;
+const REROLL_MIN_DIST: f32 = 150.0 * UNIT_PER_METER; //< Treasure closer than this isn't worth moving
+const REROLL_NEAR_DIST: f32 = 40.0 * UNIT_PER_METER; //< Moved treasure ends up between this and the far distance
+const REROLL_FAR_DIST: f32 = 100.0 * UNIT_PER_METER;
+const REROLL_ATTEMPTS: usize = 100;
+
+// Leaves flying off a bush that was cut down
+const BUSH_CUT_PARTICLES: EmitterPreset = EmitterPreset {
+    burst: 20,
+    rate: 0.0,
+    emit_seconds: 0.0,
+    lifetime: 0.64,
+    speed: 2.5 * UNIT_PER_METER,
+    direction: [0.0, 0.0, 1.0],
+    spread: 0.9,
+    gravity: 11.7 * UNIT_PER_METER,
+    drag: 5.2,
+    start_size: 0.2 * UNIT_PER_METER,
+    end_size: 0.1 * UNIT_PER_METER,
+    start_color: [0.25, 0.5, 0.2, 1.0],
+    end_color: [0.25, 0.5, 0.2, 0.0],
+};
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub(super) enum Tool {
+    Machete,
+    Shovel,
+}
+
+impl Tool {
+    /// Tools in the order the trader offers them
+    pub const ALL: [Tool; 2] = [Tool::Machete, Tool::Shovel];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Tool::Machete => "machete",
+            Tool::Shovel => "shovel",
+        }
+    }
+
+    /// How much gold the trader wants for the tool
+    pub fn price(&self) -> u32 {
+        match self {
+            Tool::Machete => 25,
+            Tool::Shovel => 40,
+        }
+    }
+}
+
+/// A treasure chest
+#[derive(Component, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub(super) struct ChestComponent {
+    pub buried: bool,           //< Buried chests need the shovel to dig up
+    pub contents: Option<Tool>, //< Given to the player when the chest is found
+}
+
+/// A bush in a wall that the player can't get through without the machete
+#[derive(Component, Default, Serialize, Deserialize)]
+#[storage(NullStorage)]
+pub(super) struct BlockingComponent;
+
+#[derive(Component, Serialize, Deserialize)]
+#[storage(HashMapStorage)]
+pub(super) struct TraderComponent {}
+
+/// Cuts down the closest blocking bush with E, if the player has the machete in hand. Pressing E at something that can
+/// be interacted with, like the trader, does that instead.
+pub(super) struct MacheteSystem {
+    reader: ReaderId<InteractEvent>,
+    cut_was_down: bool,
+}
+
+impl MacheteSystem {
+    pub fn new(world: &mut World) -> Self {
+        Self {
+            reader: interact_reader(world),
+            cut_was_down: false,
+        }
+    }
+}
+
+impl<'a> System<'a> for MacheteSystem {
+    type SystemData = (
+        ReadStorage<'a, BlockingComponent>,
+        ReadStorage<'a, PlayerComponent>,
+        ReadStorage<'a, InventoryComponent>,
+        ReadStorage<'a, PositionComponent>,
+        Read<'a, App>,
+        Read<'a, InputMap>,
+        Read<'a, AudioResource>,
+        Write<'a, DialogResource>,
+        Read<'a, EventChannel<InteractEvent>>,
+        Read<'a, LazyUpdate>,
+        Entities<'a>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            blockings,
+            players,
+            inventories,
+            positions,
+            app,
+            input,
+            audio,
+            mut dialog,
+            events,
+            lazy,
+            entities,
+        ): Self::SystemData,
+    ) {
+        // The interaction system runs first, so this is whatever the same press was used for
+        let interacted = events.read(&mut self.reader).count() > 0;
+        let cut_down = input.held(&app, Action::Interact);
+        let cut_pressed = cut_down && !self.cut_was_down;
+        self.cut_was_down = cut_down;
+        if !cut_pressed || interacted {
+            return;
+        }
+
+        let (_, inventory, player_position) =
+            (&players, &inventories, &positions).join().next().unwrap();
+        let player_pos = player_position.pos;
+        let closest = (&blockings, &positions, &entities)
+            .join()
+            .map(|(_, position, entity)| {
+                let dist = nalgebra_glm::distance(&position.pos.xy(), &player_pos.xy());
+                (dist, position.pos, entity)
+            })
+            .filter(|(dist, _, _)| *dist < CUT_DIST)
+            .min_by(|a, b| a.0.total_cmp(&b.0));
+        let Some((_, bush_pos, bush_entity)) = closest else {
+            return;
+        };
+
+        let machete = Item::Tool(Tool::Machete);
+        if inventory.holding(machete) {
+            spawn_emitter(&entities, &lazy, bush_pos, BUSH_CUT_PARTICLES);
+            audio.audio_mgr.play_sound("res/hit.ogg".to_string(), 128);
+            entities.delete(bush_entity).unwrap();
+        } else if inventory.has(machete) {
+            dialog.say("I should get my machete out.");
+        } else {
+            dialog.say("These bushes are too thick to get through. I need a machete.");
+        }
+    }
+}
+
+/// What came of asking the trader to move the furthest treasure closer
+enum Reroll {
+    Moved {
+        treasure_map: Entity,
+        from: nalgebra_glm::Vec3,
+        to: nalgebra_glm::Vec3,
+    },
+    AllClose, //< Nothing left is far enough off to be worth moving
+    NoSpot,   //< Nowhere near the player would do for it
+}
+
+/// Sells tools for gold when the player talks to the trader with E. Once every tool is sold, E buys a hint circle on
+/// the minimap around the nearest treasure, and R buys moving the furthest treasure somewhere closer. B buys a box of
+/// rounds for the weapon in hand, and P buys the parrot.
+pub(super) struct TraderSystem {
+    rng: StdRng, //< Places hint circles and moved treasure
+    reader: ReaderId<InteractEvent>,
+    reroll_was_down: bool,
+    buy_ammo_was_down: bool,
+    parrot_was_down: bool,
+}
+
+impl TraderSystem {
+    pub fn new(world: &mut World, seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed ^ 0x7ade_5eed),
+            reader: interact_reader(world),
+            reroll_was_down: false,
+            buy_ammo_was_down: false,
+            parrot_was_down: false,
+        }
+    }
+
+    /// Draws a circle on the minimap somewhere around the nearest treasure that hasn't got one yet
+    fn sell_hint(
+        &mut self,
+        player_pos: nalgebra_glm::Vec3,
+        treasure_maps: &mut WriteStorage<TreasureMapComponent>,
+        positions: &WriteStorage<PositionComponent>,
+    ) -> bool {
+        let nearest = (treasure_maps)
+            .join()
+            .filter(|map| !map.found && map.hint.is_none())
+            .filter_map(|map| {
+                let treasure_pos = positions.get(map.treasure_entity)?.pos;
+                Some((
+                    nalgebra_glm::distance(&treasure_pos, &player_pos),
+                    treasure_pos,
+                    map,
+                ))
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0));
+        let Some((_, treasure_pos, map)) = nearest else {
+            return false;
+        };
+        // Off center, so that the treasure isn't just in the middle of the circle
+        let angle = self
+            .rng
+            .gen_range(-std::f32::consts::PI..std::f32::consts::PI);
+        let offset = self.rng.gen_range(0.0..0.7) * HINT_RADIUS;
+        map.hint = Some(treasure_pos.xy() + nalgebra_glm::vec2(angle.cos(), angle.sin()) * offset);
+        true
+    }
+
+    /// Moves the furthest treasure to somewhere closer to the player
+    fn reroll_treasure(
+        &mut self,
+        player_pos: nalgebra_glm::Vec3,
+        treasure_maps: &mut WriteStorage<TreasureMapComponent>,
+        positions: &mut WriteStorage<PositionComponent>,
+        tiles: &PerlinMapResource,
+        water: &WaterResource,
+        entities: &Entities,
+    ) -> Reroll {
+        let furthest = (treasure_maps, entities)
+            .join()
+            .filter(|(map, _)| !map.found)
+            .filter_map(|(map, entity)| {
+                let treasure_pos = positions.get(map.treasure_entity)?.pos;
+                Some((treasure_pos, map, entity))
+            })
+            .max_by(|a, b| {
+                let dist = |pos| nalgebra_glm::distance(pos, &player_pos);
+                dist(&a.0).total_cmp(&dist(&b.0))
+            });
+        let Some((from, map, treasure_map)) = furthest else {
+            return Reroll::AllClose;
+        };
+        if nalgebra_glm::distance(&from, &player_pos) < REROLL_MIN_DIST {
+            return Reroll::AllClose;
+        }
+
+        for _ in 0..REROLL_ATTEMPTS {
+            let angle = self
+                .rng
+                .gen_range(-std::f32::consts::PI..std::f32::consts::PI);
+            let new_dist = self.rng.gen_range(REROLL_NEAR_DIST..REROLL_FAR_DIST);
+            let pos = player_pos.xy() + nalgebra_glm::vec2(angle.cos(), angle.sin()) * new_dist;
+            if tiles.map.oob(pos) || !is_treasure_spot(&tiles.map, water.level, pos) {
+                continue;
+            }
+            let to = nalgebra_glm::vec3(pos.x, pos.y, tiles.map.get_z_interpolated(pos));
+            positions.get_mut(map.treasure_entity).unwrap().pos = to;
+            // The old hint is no good anymore
+            map.hint = None;
+            return Reroll::Moved {
+                treasure_map,
+                from,
+                to,
+            };
+        }
+        Reroll::NoSpot
+    }
+}
+
+impl<'a> System<'a> for TraderSystem {
+    type SystemData = (
+        ReadStorage<'a, TraderComponent>,
+        ReadStorage<'a, PlayerComponent>,
+        WriteStorage<'a, InventoryComponent>,
+        WriteStorage<'a, WeaponComponent>,
+        WriteStorage<'a, TreasureMapComponent>,
+        WriteStorage<'a, PositionComponent>,
+        Read<'a, PerlinMapResource>,
+        Read<'a, WaterResource>,
+        Read<'a, App>,
+        Read<'a, InputMap>,
+        Read<'a, AudioResource>,
+        Write<'a, GoldResource>,
+        Write<'a, DialogResource>,
+        Write<'a, ParrotResource>,
+        Read<'a, GameplaySettings>,
+        Read<'a, EventChannel<InteractEvent>>,
+        Read<'a, LazyUpdate>,
+        Entities<'a>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            traders,
+            players,
+            mut inventories,
+            mut weapons,
+            mut treasure_maps,
+            mut positions,
+            tiles,
+            water,
+            app,
+            input,
+            audio,
+            mut gold,
+            mut dialog,
+            mut parrot,
+            settings,
+            events,
+            lazy,
+            entities,
+        ): Self::SystemData,
+    ) {
+        let talk_pressed = events
+            .read(&mut self.reader)
+            .any(|event| event.action == InteractAction::Talk && traders.contains(event.entity));
+        let reroll_down = input.held(&app, Action::RerollTreasure);
+        let reroll_pressed = reroll_down && !self.reroll_was_down;
+        self.reroll_was_down = reroll_down;
+        let buy_ammo_down = input.held(&app, Action::BuyAmmo);
+        let buy_ammo_pressed = buy_ammo_down && !self.buy_ammo_was_down;
+        self.buy_ammo_was_down = buy_ammo_down;
+        let parrot_down = input.held(&app, Action::BuyParrot);
+        let parrot_pressed = parrot_down && !self.parrot_was_down;
+        self.parrot_was_down = parrot_down;
+        if !talk_pressed && !reroll_pressed && !buy_ammo_pressed && !parrot_pressed {
+            return;
+        }
+
+        let (_, inventory, weapon, player_position) =
+            (&players, &mut inventories, &mut weapons, &positions)
+                .join()
+                .next()
+                .unwrap();
+        let player_pos = player_position.pos;
+        let near_trader = (&traders, &positions)
+            .join()
+            .any(|(_, position)| nalgebra_glm::distance(&position.pos, &player_pos) < TRADE_DIST);
+        if !near_trader {
+            return;
+        }
+
+        let unsold_tool = Tool::ALL
+            .into_iter()
+            .find(|tool| !inventory.has(Item::Tool(*tool)));
+        if buy_ammo_pressed {
+            let Some(held) = inventory.held_weapon() else {
+                dialog.say("Show me which weapon you need rounds for.");
+                return;
+            };
+            let stats = held.stats();
+            if gold.gold < stats.box_price {
+                dialog.say(&format!(
+                    "Rounds for a {} are {} gold a box.",
+                    stats.name, stats.box_price
+                ));
+                return;
+            }
+            if weapon.give_rounds(held, stats.box_rounds) == 0 {
+                dialog.say(&format!(
+                    "You can't carry any more for that {}.",
+                    stats.name
+                ));
+                return;
+            }
+            gold.gold -= stats.box_price;
+            audio.audio_mgr.play_sound("res/pop.ogg".to_string(), 128);
+            dialog.say(&format!(
+                "A box of rounds for your {}, that's {} gold.",
+                stats.name, stats.box_price
+            ));
+            return;
+        }
+        if parrot_pressed {
+            if !settings.parrot {
+                dialog.say("No birds for you, you said so yourself.");
+            } else if parrot.owned {
+                dialog.say("You've already got the only parrot I have.");
+            } else if gold.gold < PARROT_PRICE {
+                dialog.say(&format!(
+                    "This parrot can smell gold. Yours for {} gold.",
+                    PARROT_PRICE
+                ));
+            } else {
+                gold.gold -= PARROT_PRICE;
+                parrot.owned = true;
+                audio.audio_mgr.play_sound("res/pop.ogg".to_string(), 128);
+                dialog.say("Keep an eye on where it flies off to. It knows.");
+            }
+            return;
+        }
+        if reroll_pressed {
+            if gold.gold < REROLL_PRICE {
+                dialog.say(&format!(
+                    "For {} gold, I could tell you about some treasure a lot closer by.",
+                    REROLL_PRICE
+                ));
+                return;
+            }
+            match self.reroll_treasure(
+                player_pos,
+                &mut treasure_maps,
+                &mut positions,
+                &tiles,
+                &water,
+                &entities,
+            ) {
+                Reroll::Moved {
+                    treasure_map,
+                    from,
+                    to,
+                } => {
+                    gold.gold -= REROLL_PRICE;
+                    audio.audio_mgr.play_sound("res/pop.ogg".to_string(), 128);
+                    dialog.say(&format!(
+                        "Forget that far off chest. There's one about {:.0} meters from here.",
+                        nalgebra_glm::distance(&to, &player_pos) / UNIT_PER_METER
+                    ));
+                    // The chest's map and camp go with it
+                    lazy.exec_mut(move |world| {
+                        redraw_map_view(world, treasure_map, to.xy());
+                        move_treasure_site(world, from, to);
+                    });
+                }
+                Reroll::AllClose => dialog.say("All the treasure left is close enough already."),
+                Reroll::NoSpot => {
+                    dialog.say("I can't think of anywhere closer for it. Keep your gold.")
+                }
+            }
+            return;
+        }
+
+        match unsold_tool {
+            None if gold.gold >= HINT_PRICE => {
+                if self.sell_hint(player_pos, &mut treasure_maps, &positions) {
+                    gold.gold -= HINT_PRICE;
+                    audio.audio_mgr.play_sound("res/pop.ogg".to_string(), 128);
+                    dialog.say("It's somewhere in the circle I drew on your map.");
+                } else {
+                    dialog.say("I've got nothing left to sell you. Good luck out there!");
+                }
+            }
+            None => dialog.say(&format!(
+                "For {} gold, I'll mark about where some treasure is on your map.",
+                HINT_PRICE
+            )),
+            Some(tool) if gold.gold >= tool.price() => {
+                gold.gold -= tool.price();
+                inventory.give(Item::Tool(tool));
+                audio.audio_mgr.play_sound("res/pop.ogg".to_string(), 128);
+                dialog.say(&format!(
+                    "One {}, that's {} gold. Pleasure!",
+                    tool.name(),
+                    tool.price()
+                ));
+            }
+            Some(tool) => dialog.say(&format!(
+                "A {} will cost you {} gold. Come back when you have it.",
+                tool.name(),
+                tool.price()
+            )),
+        }
+    }
+}
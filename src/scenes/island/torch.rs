@@ -0,0 +1,171 @@
+// The torch, held up in the off hand next to the gun with F. It lights up the ground around the player, its light
+// weakens ghosts close by, and at night they won't come into it, but the gun is slower to fire with only one hand on
+// it.
+
+use specs::{prelude::*, Component};
+
+use crate::{
+    engine::{
+        input::{Action, InputMap},
+        particles::{EmitterPreset, ParticleEmitterComponent},
+        physics::PositionComponent,
+        render3d::{LightComponent, OpenGlResource},
+    },
+    App,
+};
+
+use super::{
+    status::{StatusComponent, StatusKind},
+    MobComponent, PlayerComponent, UNIT_PER_METER,
+};
+
+const LIGHT_RADIUS: f32 = 5.0 * UNIT_PER_METER; //< Ghosts won't come this close to the torch at night
+const WEAKEN_RADIUS: f32 = 8.0 * UNIT_PER_METER; //< Ghosts this close to the torch are weakened
+const WEAKEN_SECONDS: f32 = 0.5; //< How long ghosts stay weakened after leaving the light
+const GLOW_RADIUS: f32 = 12.0 * UNIT_PER_METER; //< How far the torch lights up the ground
+const GLOW_COLOR: nalgebra_glm::Vec3 = nalgebra_glm::Vec3::new(1.0, 0.6, 0.25);
+pub(super) const TORCH_SHOT_PERIOD_SCALE: f32 = 1.6; //< How much longer the gun takes between shots one-handed
+
+// Flames licking up off the torch
+const TORCH_FLAME: EmitterPreset = EmitterPreset {
+    burst: 0,
+    rate: 40.0,
+    emit_seconds: f32::INFINITY,
+    lifetime: 0.3,
+    speed: 0.4 * UNIT_PER_METER,
+    direction: [0.0, 0.0, 1.0],
+    spread: 0.3,
+    gravity: 0.0,
+    drag: 2.0,
+    start_size: 0.15 * UNIT_PER_METER,
+    end_size: 0.02 * UNIT_PER_METER,
+    start_color: [1.0, 0.7, 0.2, 1.0],
+    end_color: [1.0, 0.2, 0.0, 0.0],
+};
+
+/// Something giving off light that ghosts keep out of at night
+#[derive(Clone, Copy, Debug)]
+pub(super) struct Light {
+    pub pos: nalgebra_glm::Vec3,
+    pub radius: f32,
+}
+
+/// Every light on the island this tick
+#[derive(Default)]
+pub(super) struct LightResource {
+    pub lights: Vec<Light>,
+}
+
+impl LightResource {
+    /// The light a spot is in, if any
+    pub fn lit_by(&self, pos: nalgebra_glm::Vec2) -> Option<&Light> {
+        self.lights
+            .iter()
+            .find(|light| nalgebra_glm::distance(&light.pos.xy(), &pos) < light.radius)
+    }
+}
+
+/// The flame over the player's torch
+#[derive(Component, Default)]
+#[storage(NullStorage)]
+pub(super) struct TorchFlameComponent;
+
+/// Raises and lowers the torch, keeps its flame in hand, and weakens ghosts in its light
+#[derive(Default)]
+pub(super) struct TorchSystem {
+    toggle_was_down: bool,
+}
+impl<'a> System<'a> for TorchSystem {
+    type SystemData = (
+        WriteStorage<'a, PlayerComponent>,
+        ReadStorage<'a, MobComponent>,
+        WriteStorage<'a, StatusComponent>,
+        WriteStorage<'a, TorchFlameComponent>,
+        WriteStorage<'a, PositionComponent>,
+        WriteStorage<'a, ParticleEmitterComponent>,
+        WriteStorage<'a, LightComponent>,
+        Read<'a, OpenGlResource>,
+        Write<'a, LightResource>,
+        Read<'a, App>,
+        Read<'a, InputMap>,
+        Entities<'a>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            mut players,
+            mobs,
+            mut statuses,
+            mut flames,
+            mut positions,
+            mut emitters,
+            mut glows,
+            opengl,
+            mut lights,
+            app,
+            input,
+            entities,
+        ): Self::SystemData,
+    ) {
+        let toggle_down = input.held(&app, Action::Torch);
+        let toggle_pressed = toggle_down && !self.toggle_was_down;
+        self.toggle_was_down = toggle_down;
+
+        let Some((player, _)) = (&mut players, &positions).join().next() else {
+            return;
+        };
+        if toggle_pressed {
+            player.torch_raised = !player.torch_raised;
+        }
+        let torch_raised = player.torch_raised;
+
+        // Held out in front, off to the left of the gun
+        let forward = (opengl.camera.lookat - opengl.camera.position).normalize();
+        let left = nalgebra_glm::cross(&opengl.camera.up, &forward).normalize();
+        let torch_pos = opengl.camera.position + forward * 0.04 + left * 0.015
+            - nalgebra_glm::vec3(0.0, 0.0, 0.01);
+
+        let flame = (&flames, &entities).join().map(|(_, e)| e).next();
+        match (torch_raised, flame) {
+            (true, None) => {
+                let flame = entities.create();
+                flames.insert(flame, TorchFlameComponent).unwrap();
+                positions
+                    .insert(flame, PositionComponent { pos: torch_pos })
+                    .unwrap();
+                emitters
+                    .insert(flame, ParticleEmitterComponent::new(TORCH_FLAME))
+                    .unwrap();
+                glows
+                    .insert(
+                        flame,
+                        LightComponent {
+                            color: GLOW_COLOR,
+                            radius: GLOW_RADIUS,
+                        },
+                    )
+                    .unwrap();
+            }
+            (true, Some(flame)) => positions.get_mut(flame).unwrap().pos = torch_pos,
+            (false, Some(flame)) => entities.delete(flame).unwrap(),
+            (false, None) => {}
+        }
+
+        lights.lights.clear();
+        if !torch_raised {
+            return;
+        }
+        lights.lights.push(Light {
+            pos: torch_pos,
+            radius: LIGHT_RADIUS,
+        });
+        for (mob, status, position) in (&mobs, &mut statuses, &positions).join() {
+            if mob.kind.stats().weak_to_light
+                && nalgebra_glm::distance(&position.pos.xy(), &torch_pos.xy()) < WEAKEN_RADIUS
+            {
+                status.apply(StatusKind::Weakened, WEAKEN_SECONDS);
+            }
+        }
+    }
+}
@@ -0,0 +1,112 @@
+// The weapon in the player's hand, and the arm holding it. Bobs along while the player walks, and kicks up when fired.
+
+use std::f32::consts::PI;
+
+use specs::{prelude::*, Component};
+
+use crate::engine::{
+    physics::VelocityComponent,
+    render3d::ViewModelComponent,
+    time::{per_second, TimeResource},
+};
+
+use super::{
+    cinematic::CinematicResource,
+    inventory::InventoryComponent,
+    prefabs::solid_texture,
+    weapons::{Weapon, WeaponComponent},
+    PlayerComponent, UNIT_PER_METER,
+};
+
+pub(super) const WEAPON_OFFSET: [f32; 3] = [0.6, -0.18, -0.2]; //< Forward, left, and up from the eye, in meters
+pub(super) const ARM_OFFSET: [f32; 3] = [0.45, -0.22, -0.3];
+pub(super) const ARM_SIZE: [f32; 3] = [0.2, 0.04, 0.04]; //< Half extents, in meters
+pub(super) const ARM_COLOR: [u8; 3] = [222, 170, 130];
+
+const STEP_LENGTH: f32 = 0.75; //< Meters walked per step, one bob down and up
+const BOB_HEIGHT: f32 = 0.015; //< Meters
+const BOB_WIDTH: f32 = 0.01; //< Meters side to side, over two steps
+const BOB_EASE: f32 = 8.0; //< How quickly the bob fades in and out when starting and stopping, per second
+const RECOIL_SECONDS: f32 = 0.15; //< How long the kick takes to settle
+const RECOIL_PUSH: f32 = 0.1; //< Meters the weapon is pushed back per radian of kick
+
+/// The view model showing the weapon in hand, as opposed to the arm
+#[derive(Component, Default)]
+#[storage(NullStorage)]
+pub(super) struct HeldWeaponComponent;
+
+/// Converts a size or offset in meters to world units
+pub(super) fn meters(v: [f32; 3]) -> nalgebra_glm::Vec3 {
+    nalgebra_glm::vec3(v[0], v[1], v[2]) * UNIT_PER_METER
+}
+
+/// Shows the view models while a weapon is in hand, swapping in its look, and moves them along with the player
+#[derive(Default)]
+pub(super) struct ViewModelSystem {
+    bob_phase: f32,  //< Radians, half a turn per step
+    bob_amount: f32, //< In [0, 1], eased towards 1 while walking on the ground
+    shown: Option<Weapon>,
+}
+impl<'a> System<'a> for ViewModelSystem {
+    type SystemData = (
+        ReadStorage<'a, PlayerComponent>,
+        ReadStorage<'a, InventoryComponent>,
+        ReadStorage<'a, WeaponComponent>,
+        ReadStorage<'a, VelocityComponent>,
+        ReadStorage<'a, HeldWeaponComponent>,
+        WriteStorage<'a, ViewModelComponent>,
+        Read<'a, CinematicResource>,
+        Read<'a, TimeResource>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            players,
+            inventories,
+            weapons,
+            velocities,
+            held_weapons,
+            mut view_models,
+            cinematic,
+            time,
+        ): Self::SystemData,
+    ) {
+        let Some((player, inventory, weapon, velocity)) =
+            (&players, &inventories, &weapons, &velocities)
+                .join()
+                .next()
+        else {
+            return;
+        };
+        let held = inventory.held_weapon();
+
+        let speed = per_second(nalgebra_glm::length(&velocity.vel.xy())) / UNIT_PER_METER;
+        let walking = player.feet_on_ground && speed > 0.1;
+        self.bob_phase = (self.bob_phase + time.dt * speed / STEP_LENGTH * PI) % (2.0 * PI);
+        let ease = (time.dt * BOB_EASE).min(1.0);
+        self.bob_amount += (if walking { 1.0 } else { 0.0 } - self.bob_amount) * ease;
+
+        let kick = held.map_or(0.0, |held| {
+            let since_fired = time.elapsed - weapon.t_last_fired;
+            held.stats().recoil * (1.0 - since_fired / RECOIL_SECONDS).max(0.0)
+        });
+        let sway = nalgebra_glm::vec3(
+            -kick * RECOIL_PUSH,
+            self.bob_phase.cos() * BOB_WIDTH * self.bob_amount,
+            -self.bob_phase.sin().abs() * BOB_HEIGHT * self.bob_amount,
+        ) * UNIT_PER_METER;
+
+        let changed = held != self.shown;
+        self.shown = held;
+        for (view_model, held_weapon) in (&mut view_models, held_weapons.maybe()).join() {
+            view_model.visible = held.is_some() && !cinematic.playing();
+            view_model.sway = sway;
+            view_model.kick = kick;
+            if let (Some(held), Some(_), true) = (held, held_weapon, changed) {
+                view_model.scale = meters(held.stats().held_size);
+                view_model.texture = solid_texture(held.stats().held_color);
+            }
+        }
+    }
+}
@@ -0,0 +1,354 @@
+// The player's weapons. Each one fires its own projectiles at its own rate, holds so many rounds before it has to be
+// reloaded, and sounds different. Whichever is in the active hotbar slot is the one that fires. Spare rounds are
+// scarce: a few turn up in each chest, and the trader sells more, and once a weapon is out the player has to club
+// things with it instead.
+
+use serde::{Deserialize, Serialize};
+use specs::{prelude::*, Component};
+
+use crate::{
+    engine::{
+        audio::AudioResource,
+        input::{Action, InputMap},
+        text::{QuadComponent, TextComponent},
+        time::TimeResource,
+    },
+    App,
+};
+
+use super::{inventory::InventoryComponent, PlayerComponent};
+
+pub(super) const READOUT_MARGIN: i32 = 12; //< Gap between the ammo readout and the bottom right corner, in pixels
+const RELOAD_SOUND: &str = "res/walk.ogg";
+const RELOAD_VOLUME: i32 = 90;
+
+pub(super) const MELEE_PERIOD: f32 = 0.5; //< Seconds between swings
+pub(super) const MELEE_DAMAGE: f32 = 0.15;
+pub(super) const MELEE_RANGE: f32 = 1.5; //< Meters
+pub(super) const MELEE_SECONDS: f32 = 0.1; //< How long a swing takes to reach its full range
+pub(super) const MELEE_RADIUS: f32 = 0.3; //< Of the swing, in meters
+pub(super) const MELEE_SOUND: &str = "res/jump.ogg";
+pub(super) const MELEE_VOLUME: i32 = 50;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub(super) enum Weapon {
+    Musket,
+    Blunderbuss,   //< A handful of pellets in a cone, for up close
+    ThrowingKnife, //< Slow and quiet, doesn't draw mobs in
+}
+
+/// How a weapon fires
+pub(super) struct WeaponStats {
+    pub name: &'static str,
+    pub period: f32,    //< Seconds between shots
+    pub speed: f32,     //< Meters per second
+    pub pellets: usize, //< Projectiles per shot
+    pub spread: f32,    //< Radians between the middle pellet and the ring of the rest
+    pub damage: f32,    //< Health each projectile takes from a ghost, tougher things lose less
+    pub magazine: u32,  //< Rounds that can be fired before reloading
+    pub reserve: u32,   //< Most rounds that can be carried to reload with
+    pub start_reserve: u32,
+    pub chest_rounds: u32, //< Spare rounds found in each chest
+    pub box_rounds: u32,   //< Spare rounds the trader sells at a time
+    pub box_price: u32,
+    pub reload_seconds: f32,
+    pub sound: &'static str,
+    pub volume: i32,
+    pub size: f32, //< Of the projectile, in world units
+    pub tracer: bool,
+    pub loud: bool,          //< Whether mobs hear it and come to investigate
+    pub held_size: [f32; 3], //< Half the length, width, and height of it in hand, in meters
+    pub held_color: [u8; 3],
+    pub recoil: f32, //< Radians the muzzle kicks up by when fired
+}
+
+const MUSKET: WeaponStats = WeaponStats {
+    name: "musket",
+    period: 0.12,
+    speed: 74.0,
+    pellets: 1,
+    spread: 0.0,
+    damage: 0.1,
+    magazine: 12,
+    reserve: 120,
+    start_reserve: 36,
+    chest_rounds: 24,
+    box_rounds: 24,
+    box_price: 10,
+    reload_seconds: 1.5,
+    sound: "res/pop.ogg",
+    volume: 128,
+    size: 0.01,
+    tracer: true,
+    loud: true,
+    held_size: [0.3, 0.025, 0.03],
+    held_color: [92, 58, 32],
+    recoil: 0.12,
+};
+const BLUNDERBUSS: WeaponStats = WeaponStats {
+    name: "blunderbuss",
+    period: 0.6,
+    speed: 50.0,
+    pellets: 8,
+    spread: 0.08,
+    damage: 0.05,
+    magazine: 2,
+    reserve: 24,
+    start_reserve: 6,
+    chest_rounds: 4,
+    box_rounds: 6,
+    box_price: 12,
+    reload_seconds: 2.2,
+    sound: "res/ground.ogg",
+    volume: 128,
+    size: 0.008,
+    tracer: true,
+    loud: true,
+    held_size: [0.22, 0.04, 0.04],
+    held_color: [120, 90, 40],
+    recoil: 0.35,
+};
+const THROWING_KNIFE: WeaponStats = WeaponStats {
+    name: "knife",
+    period: 0.4,
+    speed: 30.0,
+    pellets: 1,
+    spread: 0.0,
+    damage: 0.3,
+    magazine: 1,
+    reserve: 9,
+    start_reserve: 3,
+    chest_rounds: 2,
+    box_rounds: 3,
+    box_price: 8,
+    reload_seconds: 0.5,
+    sound: "res/jump.ogg",
+    volume: 60,
+    size: 0.02,
+    tracer: false,
+    loud: false,
+    held_size: [0.12, 0.008, 0.02],
+    held_color: [180, 180, 190],
+    recoil: 0.05,
+};
+
+impl Weapon {
+    /// Weapons in the order they sit in the hotbar
+    pub const ALL: [Weapon; 3] = [Weapon::Musket, Weapon::Blunderbuss, Weapon::ThrowingKnife];
+
+    pub fn stats(&self) -> &'static WeaponStats {
+        match self {
+            Weapon::Musket => &MUSKET,
+            Weapon::Blunderbuss => &BLUNDERBUSS,
+            Weapon::ThrowingKnife => &THROWING_KNIFE,
+        }
+    }
+
+    /// Which way each pellet of a shot flies, as (right, up) offsets from straight ahead in radians. The middle pellet
+    /// goes straight, and the rest are spaced evenly around it, so every shot has the same pattern.
+    pub fn pellet_offsets(&self) -> Vec<(f32, f32)> {
+        let stats = self.stats();
+        let ring = stats.pellets.saturating_sub(1);
+        (0..stats.pellets)
+            .map(|i| {
+                if i == 0 {
+                    return (0.0, 0.0);
+                }
+                let angle = (i - 1) as f32 / ring as f32 * std::f32::consts::TAU;
+                (angle.cos() * stats.spread, angle.sin() * stats.spread)
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub(super) struct Ammo {
+    pub loaded: u32,
+    pub reserve: u32,
+}
+
+/// The player's ammo for each weapon, and the reload in progress
+#[derive(Component, Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[storage(HashMapStorage)]
+pub(super) struct WeaponComponent {
+    pub ammo: [Ammo; 3], //< In the order of `Weapon::ALL`
+    pub reloading: Option<Weapon>,
+    pub reload_left: f32, //< Seconds until the reload is done
+    #[serde(skip)]
+    pub t_last_fired: f32, //< Seconds of game time
+}
+
+impl Default for WeaponComponent {
+    /// Everything loaded, with a few spare rounds to start with
+    fn default() -> Self {
+        Self {
+            ammo: Weapon::ALL.map(|weapon| Ammo {
+                loaded: weapon.stats().magazine,
+                reserve: weapon.stats().start_reserve,
+            }),
+            reloading: None,
+            reload_left: 0.0,
+            t_last_fired: 0.0,
+        }
+    }
+}
+
+impl WeaponComponent {
+    pub fn ammo(&self, weapon: Weapon) -> Ammo {
+        self.ammo[weapon as usize]
+    }
+
+    /// Whether the weapon can fire right now, with shots `period` seconds apart
+    pub fn ready(&self, weapon: Weapon, period: f32, elapsed: f32) -> bool {
+        self.reloading.is_none()
+            && self.ammo(weapon).loaded > 0
+            && elapsed - self.t_last_fired > period
+    }
+
+    pub fn fire(&mut self, weapon: Weapon, elapsed: f32) {
+        self.ammo[weapon as usize].loaded -= 1;
+        self.t_last_fired = elapsed;
+    }
+
+    /// Starts reloading the weapon, if it isn't full and there are rounds to load it with
+    pub fn reload(&mut self, weapon: Weapon) {
+        let ammo = self.ammo(weapon);
+        if self.reloading.is_none() && ammo.loaded < weapon.stats().magazine && ammo.reserve > 0 {
+            self.reloading = Some(weapon);
+            self.reload_left = weapon.stats().reload_seconds;
+        }
+    }
+
+    /// Whether the weapon has no rounds left at all, loaded or spare, so it can only be swung
+    pub fn out_of_ammo(&self, weapon: Weapon) -> bool {
+        self.ammo(weapon)
+            == Ammo {
+                loaded: 0,
+                reserve: 0,
+            }
+    }
+
+    /// Whether a swing can be made right now
+    pub fn ready_to_swing(&self, elapsed: f32) -> bool {
+        elapsed - self.t_last_fired > MELEE_PERIOD
+    }
+
+    pub fn swing(&mut self, elapsed: f32) {
+        self.t_last_fired = elapsed;
+    }
+
+    /// Adds spare rounds for the weapon, up to as many as can be carried, and returns how many were taken
+    pub fn give_rounds(&mut self, weapon: Weapon, rounds: u32) -> u32 {
+        let ammo = &mut self.ammo[weapon as usize];
+        let taken = rounds.min(weapon.stats().reserve - ammo.reserve);
+        ammo.reserve += taken;
+        taken
+    }
+
+    /// Adds the stash of spare rounds found in a chest, for every weapon
+    pub fn find_stash(&mut self) {
+        for weapon in Weapon::ALL {
+            self.give_rounds(weapon, weapon.stats().chest_rounds);
+        }
+    }
+}
+
+/// Shows how many rounds are left in the weapon in hand
+#[derive(Component, Default)]
+#[storage(NullStorage)]
+pub(super) struct AmmoReadoutComponent;
+
+/// Reloads when asked to, or by itself once the weapon in hand is empty. Putting the weapon away stops the reload.
+#[derive(Default)]
+pub(super) struct ReloadSystem {
+    reload_was_down: bool,
+}
+impl<'a> System<'a> for ReloadSystem {
+    type SystemData = (
+        ReadStorage<'a, PlayerComponent>,
+        ReadStorage<'a, InventoryComponent>,
+        WriteStorage<'a, WeaponComponent>,
+        Read<'a, App>,
+        Read<'a, InputMap>,
+        Read<'a, AudioResource>,
+        Read<'a, TimeResource>,
+    );
+
+    fn run(
+        &mut self,
+        (players, inventories, mut weapons, app, input, audio, time): Self::SystemData,
+    ) {
+        let reload_down = input.held(&app, Action::Reload);
+        let reload_pressed = reload_down && !self.reload_was_down;
+        self.reload_was_down = reload_down;
+
+        for (_, inventory, weapon) in (&players, &inventories, &mut weapons).join() {
+            let held = inventory.held_weapon();
+            if weapon.reloading.is_some() && weapon.reloading != held {
+                weapon.reloading = None;
+            }
+            if let Some(held) = held {
+                let was_reloading = weapon.reloading.is_some();
+                if reload_pressed || weapon.ammo(held).loaded == 0 {
+                    weapon.reload(held);
+                }
+                if weapon.reloading.is_some() && !was_reloading {
+                    audio
+                        .audio_mgr
+                        .play_sound(RELOAD_SOUND.to_string(), RELOAD_VOLUME);
+                }
+            }
+
+            let Some(reloading) = weapon.reloading else {
+                continue;
+            };
+            weapon.reload_left -= time.dt;
+            if weapon.reload_left <= 0.0 {
+                let ammo = &mut weapon.ammo[reloading as usize];
+                let rounds = (reloading.stats().magazine - ammo.loaded).min(ammo.reserve);
+                ammo.loaded += rounds;
+                ammo.reserve -= rounds;
+                weapon.reloading = None;
+            }
+        }
+    }
+}
+
+/// Puts the rounds left in the weapon in hand in the bottom right corner of the screen
+pub(super) struct AmmoReadoutSystem;
+impl<'a> System<'a> for AmmoReadoutSystem {
+    type SystemData = (
+        ReadStorage<'a, AmmoReadoutComponent>,
+        ReadStorage<'a, PlayerComponent>,
+        ReadStorage<'a, InventoryComponent>,
+        ReadStorage<'a, WeaponComponent>,
+        WriteStorage<'a, TextComponent>,
+        WriteStorage<'a, QuadComponent>,
+    );
+
+    fn run(
+        &mut self,
+        (readouts, players, inventories, weapons, mut texts, mut quads): Self::SystemData,
+    ) {
+        let Some((_, inventory, weapon)) = (&players, &inventories, &weapons).join().next() else {
+            return;
+        };
+        let held = inventory.held_weapon();
+        let text = match held {
+            Some(held) if weapon.reloading == Some(held) => "reloading".to_string(),
+            Some(held) if weapon.out_of_ammo(held) => "out of ammo".to_string(),
+            Some(held) => {
+                let ammo = weapon.ammo(held);
+                format!("{} / {}", ammo.loaded, ammo.reserve)
+            }
+            None => String::new(),
+        };
+
+        for (_, readout_text, quad) in (&readouts, &mut texts, &mut quads).join() {
+            if !text.is_empty() {
+                readout_text.set_text(&text);
+            }
+            quad.opacity = if held.is_some() { 1.0 } else { 0.0 };
+        }
+    }
+}
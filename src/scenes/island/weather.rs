@@ -0,0 +1,77 @@
+// Weather. Some mornings a heavy fog rolls in, which makes treasure maps harder to read, muffles sounds, and makes the
+// sonar reach further.
+
+use rand::{Rng, SeedableRng};
+use specs::prelude::*;
+
+use crate::engine::{audio::AudioResource, time::TimeResource};
+
+use super::{clock_time, model_time, SeedResource, ToastResource};
+
+const FOG_HOURS: std::ops::Range<u32> = 5..10; //< When fog can hang around, on a 24 hour clock
+const FOG_CHANCE: f32 = 0.4; //< Chance each morning is foggy
+const FOG_DENSITY: f32 = 1.5; //< Fog thickness at its heaviest, per unit of distance
+const FOG_FADE_SECONDS: f32 = 20.0; //< How long fog takes to roll in or lift
+
+#[derive(Clone, Copy, Default, PartialEq)]
+pub(super) enum Weather {
+    #[default]
+    Clear,
+    Fog,
+}
+
+#[derive(Default)]
+pub(super) struct WeatherResource {
+    pub weather: Weather,
+    pub fog: f32, //< [0, 1], eases toward 1 while it's foggy, and back to 0 once it lifts
+}
+
+impl WeatherResource {
+    /// How thick the fog is, per unit of distance
+    pub fn fog_density(&self) -> f32 {
+        self.fog * FOG_DENSITY
+    }
+}
+
+/// Decides each morning whether it's foggy, and fades the fog in and out
+pub(super) struct WeatherSystem;
+impl<'a> System<'a> for WeatherSystem {
+    type SystemData = (
+        Read<'a, TimeResource>,
+        Read<'a, SeedResource>,
+        Write<'a, WeatherResource>,
+        Write<'a, AudioResource>,
+        Write<'a, ToastResource>,
+    );
+
+    fn run(&mut self, (time, seed, mut weather, mut audio, mut toast): Self::SystemData) {
+        let model_t = model_time(time.elapsed);
+        let (hour, _) = clock_time(model_t);
+        // Days start at midnight, and every day of a seed gets the same weather
+        let day = ((model_t + std::f32::consts::PI) / (2.0 * std::f32::consts::PI)) as u64;
+        let foggy_day =
+            rand::rngs::StdRng::seed_from_u64(seed.seed ^ day).gen::<f32>() < FOG_CHANCE;
+
+        let new_weather = if foggy_day && FOG_HOURS.contains(&hour) {
+            Weather::Fog
+        } else {
+            Weather::Clear
+        };
+        if new_weather != weather.weather {
+            match new_weather {
+                Weather::Fog => toast.show("A thick morning fog rolls in..."),
+                Weather::Clear => toast.show("The fog lifts"),
+            }
+            weather.weather = new_weather;
+        }
+
+        let target = if weather.weather == Weather::Fog {
+            1.0
+        } else {
+            0.0
+        };
+        let step = time.dt / FOG_FADE_SECONDS;
+        weather.fog += (target - weather.fog).clamp(-step, step);
+        audio.audio_mgr.set_muffle(weather.fog);
+    }
+}
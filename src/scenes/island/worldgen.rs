@@ -0,0 +1,308 @@
+// Island terrain generation. This doesn't touch OpenGL, so that it can run on a background thread while the loading
+// screen is shown. Seeds that come out unplayable, like hardly any island at all, are swapped for another seed made
+// from them, so that a seed still always gives the same island. An archipelago is a few smaller islands instead, with
+// channels between them, and the treasure spread across all of them.
+
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::engine::{
+    perlin::{Bulge, ErosionParams, MoistureMap, NoiseKind, NoiseParams, PerlinMap},
+    water::SEA_LEVEL,
+};
+
+use super::{is_treasure_spot, MAP_WIDTH, UNIT_PER_METER};
+
+const MIN_LAND_AREA: f32 = 12.0; //< Square kilometers, about half a usual island
+const MIN_TREASURE_SITES: usize = 500; //< Spots a chest could go that the player can walk to, usually ~3000
+const TREASURE_SITE_SPACING: usize = 2; //< Treasure sites are only looked for every this many tiles
+const MAX_REROLLS: usize = 5; //< Gives up on finding a better island after this many tries, and keeps the last
+const ARCHIPELAGO_ISLANDS: usize = 4; //< Islands an archipelago tries to fit, fewer if they don't fit
+const ARCHIPELAGO_RADII: (f32, f32) = (0.12, 0.16); //< Range of island radii, as a fraction of the map's width
+const ARCHIPELAGO_SPACING: f32 = 1.5; //< Islands are this many times their radii apart, since noise pushes shores out
+
+/// How the land is laid out across the map
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub(crate) enum IslandLayout {
+    #[default]
+    Single, //< One big island in the middle
+    Archipelago, //< A few smaller islands, with channels between them to swim across
+}
+
+/// The generated terrain, and the rng to keep placing things with, so that a seed always gives the same island
+pub(crate) struct GeneratedTerrain {
+    pub seed: u64,
+    pub layout: IslandLayout,
+    pub noise: NoiseKind,
+    pub islands: Vec<Bulge>, //< The player starts on the first
+    pub map: PerlinMap,
+    pub moisture: MoistureMap,
+    pub rng: StdRng,
+    pub spawn_point: nalgebra_glm::Vec3,
+    pub stats: TerrainStats,
+}
+
+/// How big the island came out
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct TerrainStats {
+    pub land_area: f32, //< Square kilometers above sea level
+    pub coastline: f32, //< Kilometers of land bordering the sea, going along tile edges
+}
+
+impl TerrainStats {
+    fn measure(map: &PerlinMap) -> Self {
+        let land = |x, y| is_land(map, x, y);
+        let tile_km = 1.0 / UNIT_PER_METER / 1000.0;
+        let mut land_tiles = 0;
+        let mut coast_edges = 0;
+        for y in 0..MAP_WIDTH {
+            for x in 0..MAP_WIDTH {
+                if !land(x, y) {
+                    continue;
+                }
+                land_tiles += 1;
+                // Edges of the map count as sea
+                let neighbors = [
+                    x > 0 && land(x - 1, y),
+                    x + 1 < MAP_WIDTH && land(x + 1, y),
+                    y > 0 && land(x, y - 1),
+                    y + 1 < MAP_WIDTH && land(x, y + 1),
+                ];
+                coast_edges += neighbors.iter().filter(|is_land| !**is_land).count();
+            }
+        }
+        Self {
+            land_area: land_tiles as f32 * tile_km * tile_km,
+            coastline: coast_edges as f32 * tile_km,
+        }
+    }
+}
+
+/// Generates the terrain for an island. If no seed is given, a random one is picked. Seeds giving an unplayable island
+/// are swapped for another made from them, and the terrain's seed is the one that was kept.
+/// - noise: what the heights start out as, before the islands are raised out of them and eroded
+/// - progress: set to the percent of erosion done, for the loading screen
+pub(crate) fn generate_terrain(
+    seed: Option<u64>,
+    layout: IslandLayout,
+    noise: &NoiseParams,
+    progress: Arc<AtomicUsize>,
+) -> GeneratedTerrain {
+    let mut seed = seed.unwrap_or_else(|| StdRng::from_entropy().gen());
+    let mut rerolls = 0;
+    loop {
+        let terrain = generate_terrain_from_seed(seed, layout, noise, &progress);
+        let Err(reason) = validate(&terrain) else {
+            return terrain;
+        };
+        if rerolls >= MAX_REROLLS {
+            println!(
+                "Seed {} {}, keeping it after {} tries",
+                seed,
+                reason,
+                rerolls + 1
+            );
+            return terrain;
+        }
+        let next_seed = StdRng::seed_from_u64(seed ^ 0x5eed_ca5e).gen();
+        println!("Seed {} {}, trying seed {}", seed, reason, next_seed);
+        seed = next_seed;
+        rerolls += 1;
+    }
+}
+
+/// Checks that an island is worth playing on: that there's enough of it, that the player doesn't start in the sea,
+/// and that there's room for the treasure somewhere the player can walk to. An archipelago's islands also have to be
+/// apart, and each needs room for its share of the treasure. Returns why it isn't, if it isn't.
+fn validate(terrain: &GeneratedTerrain) -> Result<(), String> {
+    if terrain.stats.land_area < MIN_LAND_AREA {
+        return Err(format!(
+            "only has {:.2}km² of land",
+            terrain.stats.land_area
+        ));
+    }
+    let map = &terrain.map;
+    let spawn_tile = terrain.spawn_point.xy().map(|x| x.round() as usize);
+    if terrain.spawn_point.z < SEA_LEVEL || !is_land(map, spawn_tile.x, spawn_tile.y) {
+        return Err("starts the player in the sea".to_string());
+    }
+
+    // Flood the land from the spawn point, and from the middle of every other island, to find everywhere the player
+    // can walk to on each
+    let mut island_of = vec![None; MAP_WIDTH * MAP_WIDTH];
+    for (island, bulge) in terrain.islands.iter().enumerate() {
+        let start = if island == 0 {
+            spawn_tile
+        } else {
+            bulge.center.map(|x| x.round() as usize)
+        };
+        if !is_land(map, start.x, start.y) {
+            return Err(format!("has island {} sunk under the sea", island));
+        }
+        if let Some(other) = island_of[start.x + start.y * MAP_WIDTH] {
+            return Err(format!("has islands {} and {} joined up", other, island));
+        }
+        let mut frontier = vec![(start.x, start.y)];
+        island_of[start.x + start.y * MAP_WIDTH] = Some(island);
+        while let Some((x, y)) = frontier.pop() {
+            let neighbors = [
+                (x.wrapping_sub(1), y),
+                (x + 1, y),
+                (x, y.wrapping_sub(1)),
+                (x, y + 1),
+            ];
+            for (nx, ny) in neighbors {
+                if nx < MAP_WIDTH
+                    && ny < MAP_WIDTH
+                    && island_of[nx + ny * MAP_WIDTH].is_none()
+                    && is_land(map, nx, ny)
+                {
+                    island_of[nx + ny * MAP_WIDTH] = Some(island);
+                    frontier.push((nx, ny));
+                }
+            }
+        }
+    }
+
+    let mut sites = vec![0; terrain.islands.len()];
+    for y in (0..MAP_WIDTH).step_by(TREASURE_SITE_SPACING) {
+        for x in (0..MAP_WIDTH).step_by(TREASURE_SITE_SPACING) {
+            let Some(island) = island_of[x + y * MAP_WIDTH] else {
+                continue;
+            };
+            if is_treasure_spot(map, SEA_LEVEL, nalgebra_glm::vec2(x as f32, y as f32)) {
+                sites[island] += 1;
+            }
+        }
+    }
+    let min_sites = MIN_TREASURE_SITES / terrain.islands.len();
+    if let Some((island, count)) = sites
+        .iter()
+        .enumerate()
+        .find(|(_, &count)| count < min_sites)
+    {
+        return Err(format!(
+            "only has {} reachable treasure sites on island {}",
+            count, island
+        ));
+    }
+    Ok(())
+}
+
+/// Picks where the islands of an archipelago go, and how big they are. They're kept apart from each other and from
+/// the edges of the map, so that the noise doesn't join them up or cut them off.
+fn place_archipelago(rng: &mut StdRng) -> Vec<Bulge> {
+    let width = MAP_WIDTH as f32;
+    let mut islands: Vec<Bulge> = vec![];
+    for _ in 0..ARCHIPELAGO_ISLANDS {
+        for _ in 0..100 {
+            let radius = width * rng.gen_range(ARCHIPELAGO_RADII.0..ARCHIPELAGO_RADII.1);
+            let margin = radius * ARCHIPELAGO_SPACING;
+            let center = nalgebra_glm::vec2(
+                rng.gen_range(margin..width - margin),
+                rng.gen_range(margin..width - margin),
+            );
+            let apart = islands.iter().all(|other| {
+                nalgebra_glm::distance(&center, &other.center)
+                    > (radius + other.radius) * ARCHIPELAGO_SPACING
+            });
+            if apart {
+                islands.push(Bulge { center, radius });
+                break;
+            }
+        }
+    }
+    islands
+}
+
+fn is_land(map: &PerlinMap, x: usize, y: usize) -> bool {
+    map.height(nalgebra_glm::vec2(x as f32, y as f32)) >= SEA_LEVEL
+}
+
+fn generate_terrain_from_seed(
+    seed: u64,
+    layout: IslandLayout,
+    noise: &NoiseParams,
+    progress: &AtomicUsize,
+) -> GeneratedTerrain {
+    println!("Setting up island...");
+    println!("Seed: {}", seed);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut map = PerlinMap::with_noise(MAP_WIDTH, rng.gen(), noise);
+
+    println!("Creating bulge...");
+    map.normalize();
+    let islands = match layout {
+        IslandLayout::Single => vec![map.center_bulge()],
+        IslandLayout::Archipelago => place_archipelago(&mut rng),
+    };
+    map.create_bulges(&islands);
+
+    println!("Eroding...");
+    let start = Instant::now();
+    let moisture = map.erode(&ErosionParams::default(), rng.gen(), |percent| {
+        progress.store(percent, Ordering::Relaxed)
+    });
+    progress.store(100, Ordering::Relaxed);
+    println!("Erode time: {:?}", start.elapsed());
+
+    // The player starts on the first island, on the first land north of its middle
+    let start = islands[0].center.map(f32::round);
+    let height = map.get_z_interpolated(start);
+    let mut spawn_point = nalgebra_glm::vec3(start.x, start.y, height);
+    for y in 0..(MAP_WIDTH as f32 - start.y) as usize {
+        let height = map.get_z_interpolated(nalgebra_glm::vec2(start.x, start.y + y as f32));
+        if height >= SEA_LEVEL {
+            spawn_point = nalgebra_glm::vec3(start.x, start.y + y as f32, height);
+            break;
+        }
+    }
+
+    let stats = TerrainStats::measure(&map);
+    GeneratedTerrain {
+        seed,
+        layout,
+        noise: noise.kind,
+        islands,
+        map,
+        moisture,
+        rng,
+        spawn_point,
+        stats,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn archipelago_islands_are_apart_and_on_the_map() {
+        for seed in 0..20 {
+            let islands = place_archipelago(&mut StdRng::seed_from_u64(seed));
+            assert!(
+                islands.len() >= 2,
+                "seed {} only fit {} islands",
+                seed,
+                islands.len()
+            );
+            for (i, island) in islands.iter().enumerate() {
+                let margin = island.radius * ARCHIPELAGO_SPACING;
+                assert!(island.center.min() >= margin);
+                assert!(island.center.max() <= MAP_WIDTH as f32 - margin);
+                for other in &islands[i + 1..] {
+                    let apart = nalgebra_glm::distance(&island.center, &other.center);
+                    assert!(apart > (island.radius + other.radius) * ARCHIPELAGO_SPACING);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,200 @@
+// The photo journal, opened over the island with J or left on the d-pad. Shows every treasure ever found, on any
+// island, as a grid of photos that scrolls with the mouse wheel, the arrow keys or the d-pad.
+
+use sdl2::{controller::Button, keyboard::Scancode, pixels::Color};
+use specs::{prelude::*, Component, Dispatcher};
+
+use crate::{
+    engine::{
+        error::EngineError,
+        input::{Action, InputMap},
+        objects::Texture,
+        physics::PositionComponent,
+        render3d::{Mesh, MeshMgr, MeshMgrResource},
+        settings::Settings,
+        text::{
+            initialize_gui, Align, Anchor, FontMgr, LayoutComponent, QuadComponent, UIResource,
+        },
+        time::TICK_SECONDS,
+        ui_nav::{initialize_ui_navigation, UiFocusResource},
+    },
+    App, Scene, SceneCommand,
+};
+
+use super::island::{Journal, JOURNAL_PATH, QUAD_DATA};
+
+const COLUMNS: usize = 3;
+const COLUMN_SPACING: i32 = 280; //< Pixels between the centers of neighbouring photos
+const ROW_SPACING: i32 = 210; //< Pixels from the top of one photo to the top of the one below
+const TOP_MARGIN: i32 = 120; //< Pixels above the first row, for the title
+const SCROLL_SPEED: f32 = 600.0; //< Pixels per second, with the keys held
+const WHEEL_STEP: f32 = 60.0; //< Pixels per notch of the mouse wheel
+
+/// Something that scrolls with the journal
+#[derive(Component)]
+#[storage(VecStorage)]
+struct ScrollComponent {
+    top: i32, //< Pixels down from the top of the journal, before scrolling
+}
+
+pub struct JournalScene {
+    world: World,
+    update_dispatcher: Dispatcher<'static, 'static>,
+    ui_render_dispatcher: Dispatcher<'static, 'static>,
+    scroll: f32, //< Pixels scrolled down
+    content_height: i32,
+    close_was_down: bool,
+}
+
+impl JournalScene {
+    pub fn new() -> Result<Self, EngineError> {
+        let settings = Settings::load().unwrap_or_default();
+        let journal = Journal::load(JOURNAL_PATH).unwrap_or_else(|err| {
+            println!("Couldn't read the journal: {}", err);
+            Journal::default()
+        });
+
+        let mut world = World::new();
+        world.register::<PositionComponent>();
+        world.register::<ScrollComponent>();
+        let mut update_dispatcher_builder = DispatcherBuilder::new();
+        initialize_ui_navigation(&mut world, &mut update_dispatcher_builder);
+        let mut ui_render_dispatcher_builder = DispatcherBuilder::new();
+        initialize_gui(&mut world, &mut ui_render_dispatcher_builder);
+        world.insert(InputMap::new(&settings.controls));
+
+        let font_mgr = FontMgr::new()?;
+        let title_font = font_mgr.load_font("res/HelveticaNeue Medium.ttf", 48)?;
+        let font = font_mgr.load_font("res/HelveticaNeue Medium.ttf", 20)?;
+
+        let mut mesh_mgr = MeshMgr::new();
+        let quad_mesh = mesh_mgr.add_mesh(Mesh::from_obj(
+            QUAD_DATA,
+            nalgebra_glm::vec3(1.0, 1.0, 1.0),
+        )?);
+        world.insert(MeshMgrResource { data: mesh_mgr });
+        world.insert(UIResource::new()?);
+
+        let white = Color::RGBA(255, 255, 255, 255);
+        let grey = Color::RGBA(180, 180, 180, 255);
+        let title = if journal.entries.is_empty() {
+            "No treasure found yet"
+        } else {
+            "Journal"
+        };
+        let mut add = |quad: QuadComponent, x: i32, top: i32| {
+            world
+                .create_entity()
+                .with(quad)
+                .with(PositionComponent {
+                    pos: nalgebra_glm::zero(),
+                })
+                .with(LayoutComponent::new(
+                    Anchor::new(Align::Center, Align::Start),
+                    (x, top),
+                ))
+                .with(ScrollComponent { top })
+                .build();
+        };
+        add(
+            QuadComponent::from_text(title, &title_font, white, quad_mesh),
+            0,
+            32,
+        );
+        for (i, entry) in journal.entries.iter().enumerate() {
+            let x = ((i % COLUMNS) as i32 - (COLUMNS as i32 - 1) / 2) * COLUMN_SPACING;
+            let top = TOP_MARGIN + (i / COLUMNS) as i32 * ROW_SPACING;
+            let mut caption_top = top;
+            match load_photo(&entry.photo) {
+                Ok((texture, width, height)) => {
+                    add(
+                        QuadComponent::from_texture(texture, width, height, quad_mesh),
+                        x,
+                        top,
+                    );
+                    caption_top += height + 8;
+                }
+                Err(err) => println!("Couldn't load a journal photo: {}", err),
+            }
+            let when = format!("Day {}, {:02}:{:02}", entry.day, entry.hours, entry.minutes);
+            add(
+                QuadComponent::from_text(&when, &font, white, quad_mesh),
+                x,
+                caption_top,
+            );
+            add(
+                QuadComponent::from_text(&entry.location, &font, grey, quad_mesh),
+                x,
+                caption_top + 26,
+            );
+        }
+        let rows = journal.entries.len().div_ceil(COLUMNS) as i32;
+
+        Ok(Self {
+            world,
+            update_dispatcher: update_dispatcher_builder.build(),
+            ui_render_dispatcher: ui_render_dispatcher_builder.build(),
+            scroll: 0.0,
+            content_height: TOP_MARGIN + rows * ROW_SPACING,
+            // The key that opened the journal is still down
+            close_was_down: true,
+        })
+    }
+}
+
+fn load_photo(path: &str) -> Result<(Texture, i32, i32), String> {
+    let photo = image::open(path)
+        .map_err(|e| format!("{}: {}", path, e))?
+        .to_rgba8();
+    let (width, height) = (photo.width() as i32, photo.height() as i32);
+    Ok((
+        Texture::from_rgba(width, height, photo.as_raw()),
+        width,
+        height,
+    ))
+}
+
+impl Scene for JournalScene {
+    fn update(&mut self, app: &App) -> SceneCommand {
+        self.world.insert((*app).clone());
+        self.update_dispatcher.dispatch_seq(&self.world);
+
+        let close_down = self
+            .world
+            .read_resource::<InputMap>()
+            .held(app, Action::Journal);
+        let close = (close_down && !self.close_was_down)
+            || self.world.read_resource::<UiFocusResource>().back;
+        self.close_was_down = close_down;
+        if close {
+            return SceneCommand::Pop;
+        }
+
+        let up = app.keys[Scancode::Up as usize] || app.button(Button::DPadUp);
+        let down = app.keys[Scancode::Down as usize] || app.button(Button::DPadDown);
+        let held = (down as i32 - up as i32) as f32 * SCROLL_SPEED * TICK_SECONDS;
+        let max_scroll = (self.content_height - app.screen_height).max(0) as f32;
+        self.scroll = (self.scroll + held - app.mouse_wheel * WHEEL_STEP).clamp(0.0, max_scroll);
+
+        let (scrolls, mut layouts) = self
+            .world
+            .system_data::<(ReadStorage<ScrollComponent>, WriteStorage<LayoutComponent>)>();
+        for (scroll, layout) in (&scrolls, &mut layouts).join() {
+            layout.offset.1 = scroll.top - self.scroll as i32;
+        }
+        SceneCommand::None
+    }
+
+    fn render(&mut self, app: &App) {
+        unsafe {
+            gl::Viewport(0, 0, app.screen_width, app.screen_height);
+            gl::ClearColor(0.05, 0.1, 0.15, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        self.ui_render_dispatcher.dispatch_seq(&self.world);
+    }
+
+    fn frees_cursor(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,194 @@
+// The loading screen, shown while the island is generated on a background thread. Once it's ready, a summary of what
+// went into the island is shown for a moment before it's entered.
+
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+};
+
+use sdl2::{pixels::Color, ttf::Font};
+use specs::{prelude::*, Dispatcher};
+
+use crate::{
+    engine::{
+        error::EngineError,
+        perlin::{NoiseKind, NoiseParams},
+        physics::PositionComponent,
+        render3d::{Mesh, MeshMgr, MeshMgrResource},
+        text::{initialize_gui, FontMgr, QuadComponent, UIResource},
+        time::TICK_SECONDS,
+    },
+    App, Scene, SceneCommand,
+};
+
+use super::island::{
+    generate_terrain, GeneratedTerrain, Island, IslandLayout, SaveGame, QUAD_DATA,
+};
+
+const SUMMARY_SECONDS: f32 = 2.0; //< How long the island's summary is shown before going in
+
+pub struct LoadingScene {
+    world: World,
+    ui_render_dispatcher: Dispatcher<'static, 'static>,
+    font: Font<'static, 'static>,
+    progress_text: Entity,
+    summary_text: Entity,   //< Blank until the island is ready
+    island: Option<Island>, //< Held while its summary is shown
+    summary_seconds_left: f32,
+    progress: Arc<AtomicUsize>, //< Percent of erosion done, written by the worldgen thread
+    shown_progress: Option<usize>,
+    worldgen_thread: Option<JoinHandle<GeneratedTerrain>>,
+    save: Option<SaveGame>, //< Restored on top of the island once it's generated
+}
+
+impl LoadingScene {
+    /// Starts generating an island in the background. If no seed is given, a random one is picked.
+    pub fn new(
+        seed: Option<u64>,
+        layout: IslandLayout,
+        noise: NoiseKind,
+    ) -> Result<Self, EngineError> {
+        let noise = NoiseParams {
+            kind: noise,
+            ..Default::default()
+        };
+        let progress = Arc::new(AtomicUsize::new(0));
+        let worldgen_progress = progress.clone();
+        let worldgen_thread = std::thread::Builder::new()
+            .name("worldgen".to_string())
+            .spawn(move || generate_terrain(seed, layout, &noise, worldgen_progress))
+            .unwrap();
+
+        let mut world = World::new();
+        world.register::<PositionComponent>();
+        let mut ui_render_dispatcher_builder = DispatcherBuilder::new();
+        initialize_gui(&mut world, &mut ui_render_dispatcher_builder);
+
+        let font = FontMgr::new()?.load_font("res/HelveticaNeue Medium.ttf", 24)?;
+
+        let mut mesh_mgr = MeshMgr::new();
+        let quad_mesh = mesh_mgr.add_mesh(Mesh::from_obj(
+            QUAD_DATA,
+            nalgebra_glm::vec3(1.0, 1.0, 1.0),
+        )?);
+        world.insert(MeshMgrResource { data: mesh_mgr });
+        world.insert(UIResource::new()?);
+
+        let progress_text = world
+            .create_entity()
+            .with(QuadComponent::from_text(
+                "Generating island...",
+                &font,
+                Color::RGBA(255, 255, 255, 255),
+                quad_mesh,
+            ))
+            .with(PositionComponent {
+                pos: nalgebra_glm::vec3(0.0, 0.0, 0.0),
+            })
+            .build();
+        let summary_text = world
+            .create_entity()
+            .with(QuadComponent::from_text(
+                " ",
+                &font,
+                Color::RGBA(255, 255, 255, 255),
+                quad_mesh,
+            ))
+            .with(PositionComponent {
+                pos: nalgebra_glm::vec3(0.0, -0.1, 0.0),
+            })
+            .build();
+
+        Ok(Self {
+            world,
+            ui_render_dispatcher: ui_render_dispatcher_builder.build(),
+            font,
+            progress_text,
+            summary_text,
+            island: None,
+            summary_seconds_left: SUMMARY_SECONDS,
+            progress,
+            shown_progress: None,
+            worldgen_thread: Some(worldgen_thread),
+            save: None,
+        })
+    }
+
+    /// Generates the island a save was made on, and then restores the save
+    pub fn from_save(save: SaveGame) -> Result<Self, EngineError> {
+        let (seed, layout, noise) = (save.seed, save.layout, save.noise);
+        Ok(Self {
+            save: Some(save),
+            ..Self::new(Some(seed), layout, noise)?
+        })
+    }
+}
+
+impl Scene for LoadingScene {
+    fn update(&mut self, app: &App) -> SceneCommand {
+        self.world.insert((*app).clone());
+
+        if self
+            .worldgen_thread
+            .as_ref()
+            .is_some_and(|thread| thread.is_finished())
+        {
+            let terrain = self.worldgen_thread.take().unwrap().join().unwrap();
+            let mut island = match Island::new(terrain) {
+                Ok(island) => island,
+                Err(err) => return SceneCommand::Fail(err),
+            };
+            match &self.save {
+                Some(save) => island.restore(save),
+                None => island.play_intro(),
+            }
+            let summary = island.summary();
+            let mut quads = self.world.write_storage::<QuadComponent>();
+            let white = Color::RGBA(255, 255, 255, 255);
+            quads.get_mut(self.progress_text).unwrap().set_text(
+                &summary.terrain_line(),
+                &self.font,
+                white,
+            );
+            quads.get_mut(self.summary_text).unwrap().set_text(
+                &summary.entity_line(),
+                &self.font,
+                white,
+            );
+            self.island = Some(island);
+        }
+        if self.island.is_some() {
+            self.summary_seconds_left -= TICK_SECONDS;
+            if self.summary_seconds_left <= 0.0 {
+                return SceneCommand::Replace(Box::new(self.island.take().unwrap()));
+            }
+            return SceneCommand::None;
+        }
+
+        // Only re-render the text when the percent changes, since rendering text makes a new texture
+        let progress = self.progress.load(Ordering::Relaxed);
+        if self.shown_progress != Some(progress) {
+            self.shown_progress = Some(progress);
+            let mut quads = self.world.write_storage::<QuadComponent>();
+            quads.get_mut(self.progress_text).unwrap().set_text(
+                &format!("Generating island... {}%", progress),
+                &self.font,
+                Color::RGBA(255, 255, 255, 255),
+            );
+        }
+
+        SceneCommand::None
+    }
+
+    fn render(&mut self, app: &App) {
+        unsafe {
+            gl::Viewport(0, 0, app.screen_width, app.screen_height);
+            gl::ClearColor(0.05, 0.1, 0.2, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        self.ui_render_dispatcher.dispatch_seq(&self.world);
+    }
+}
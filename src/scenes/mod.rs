@@ -1 +1,5 @@
 pub(crate) mod island;
+pub(crate) mod journal;
+pub(crate) mod loading;
+pub(crate) mod options;
+pub(crate) mod summary;
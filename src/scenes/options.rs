@@ -0,0 +1,322 @@
+// The options menu, opened over the island with Tab or Back. Selecting an option steps it through a few choices, and
+// the settings file is saved on the way back out, for the island to pick up again.
+
+use sdl2::{pixels::Color, ttf::Font};
+use specs::{prelude::*, Component, Dispatcher};
+
+use crate::{
+    engine::{
+        error::EngineError,
+        input::{Action, InputMap},
+        physics::PositionComponent,
+        render3d::{Mesh, MeshMgr, MeshMgrResource},
+        settings::{GraphicsSettings, QualityPreset, Settings},
+        text::{initialize_gui, FontMgr, QuadComponent, UIResource},
+        ui_button::{initialize_ui_buttons, ButtonComponent},
+        ui_nav::{initialize_ui_navigation, FocusableComponent, UiFocusResource},
+    },
+    App, Scene, SceneCommand,
+};
+
+use super::island::QUAD_DATA;
+
+const FOV_CHOICES: [f32; 6] = [0.7, 0.8, 0.9, 1.0, 1.1, 1.2]; //< Radians
+const SENSITIVITY_CHOICES: [f32; 6] = [0.005, 0.0075, 0.01, 0.0125, 0.015, 0.02];
+const VOLUME_CHOICES: [i32; 5] = [0, 32, 64, 96, 128];
+const QUALITY_CHOICES: [QualityPreset; 3] = [
+    QualityPreset::Low,
+    QualityPreset::Medium,
+    QualityPreset::High,
+];
+const SHADOW_SIZE_CHOICES: [i32; 4] = [512, 1024, 2048, 4096];
+const GRASS_DENSITY_CHOICES: [f32; 5] = [0.0, 0.25, 0.5, 0.75, 1.0];
+const DEFAULT_SENSITIVITY: f32 = 0.01; //< Shown as 1x
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum OptionRow {
+    Fov,
+    Sensitivity,
+    Volume,
+    Quality, //< Overrides the preset picked on first run, resetting the graphics options below it
+    ShadowSize,
+    GrassDensity,
+    Parrot,
+    Back,
+}
+
+impl OptionRow {
+    const ALL: [OptionRow; 8] = [
+        OptionRow::Fov,
+        OptionRow::Sensitivity,
+        OptionRow::Volume,
+        OptionRow::Quality,
+        OptionRow::ShadowSize,
+        OptionRow::GrassDensity,
+        OptionRow::Parrot,
+        OptionRow::Back,
+    ];
+
+    fn label(&self, settings: &Settings) -> String {
+        match self {
+            OptionRow::Fov => format!(
+                "Field of view: {:.0} degrees",
+                settings.view.fov.to_degrees()
+            ),
+            OptionRow::Sensitivity => format!(
+                "Mouse sensitivity: {:.2}x",
+                settings.view.mouse_sensitivity / DEFAULT_SENSITIVITY
+            ),
+            OptionRow::Volume => format!("Volume: {}%", settings.audio.volume * 100 / 128),
+            OptionRow::Quality => format!("Graphics quality: {:?}", settings.graphics.preset),
+            OptionRow::ShadowSize => format!("Shadow detail: {}", settings.graphics.shadow_size),
+            OptionRow::GrassDensity => match settings.graphics.grass_density {
+                density if density <= 0.0 => "Grass: off".to_string(),
+                density => format!("Grass: {:.0}%", density * 100.0),
+            },
+            OptionRow::Parrot => format!(
+                "Parrot companion: {}",
+                if settings.gameplay.parrot {
+                    "on"
+                } else {
+                    "off"
+                }
+            ),
+            OptionRow::Back => "Back".to_string(),
+        }
+    }
+
+    /// Steps the option on to its next choice
+    fn step(&self, settings: &mut Settings) {
+        match self {
+            OptionRow::Fov => settings.view.fov = next_choice(&FOV_CHOICES, settings.view.fov),
+            OptionRow::Sensitivity => {
+                settings.view.mouse_sensitivity =
+                    next_choice(&SENSITIVITY_CHOICES, settings.view.mouse_sensitivity)
+            }
+            OptionRow::Volume => {
+                settings.audio.volume = next_choice(&VOLUME_CHOICES, settings.audio.volume)
+            }
+            OptionRow::Quality => {
+                let preset = next_choice(&QUALITY_CHOICES, settings.graphics.preset);
+                settings.graphics = GraphicsSettings::from_preset(preset);
+            }
+            OptionRow::ShadowSize => {
+                settings.graphics.shadow_size =
+                    next_choice(&SHADOW_SIZE_CHOICES, settings.graphics.shadow_size)
+            }
+            OptionRow::GrassDensity => {
+                settings.graphics.grass_density =
+                    next_choice(&GRASS_DENSITY_CHOICES, settings.graphics.grass_density)
+            }
+            OptionRow::Parrot => settings.gameplay.parrot = !settings.gameplay.parrot,
+            OptionRow::Back => {}
+        }
+    }
+}
+
+/// The choice after `current`, wrapping around. Values that aren't one of the choices, like ones typed into the
+/// settings file, go back to the first choice.
+fn next_choice<T: Copy + PartialEq>(choices: &[T], current: T) -> T {
+    match choices.iter().position(|choice| *choice == current) {
+        Some(i) => choices[(i + 1) % choices.len()],
+        None => choices[0],
+    }
+}
+
+#[derive(Component)]
+#[storage(VecStorage)]
+struct OptionRowComponent {
+    row: OptionRow,
+}
+
+pub struct OptionsScene {
+    world: World,
+    update_dispatcher: Dispatcher<'static, 'static>,
+    ui_render_dispatcher: Dispatcher<'static, 'static>,
+    settings: Settings,
+    font: Font<'static, 'static>,
+    close_was_down: bool,
+}
+
+impl OptionsScene {
+    pub fn new() -> Result<Self, EngineError> {
+        let settings = Settings::load().unwrap_or_default();
+
+        let mut world = World::new();
+        world.register::<PositionComponent>();
+        world.register::<OptionRowComponent>();
+        let mut update_dispatcher_builder = DispatcherBuilder::new();
+        initialize_ui_navigation(&mut world, &mut update_dispatcher_builder);
+        initialize_ui_buttons(&mut world, &mut update_dispatcher_builder);
+        let mut ui_render_dispatcher_builder = DispatcherBuilder::new();
+        initialize_gui(&mut world, &mut ui_render_dispatcher_builder);
+        world.insert(InputMap::new(&settings.controls));
+
+        let font_mgr = FontMgr::new()?;
+        let title_font = font_mgr.load_font("res/HelveticaNeue Medium.ttf", 48)?;
+        let font = font_mgr.load_font("res/HelveticaNeue Medium.ttf", 24)?;
+
+        let mut mesh_mgr = MeshMgr::new();
+        let quad_mesh = mesh_mgr.add_mesh(Mesh::from_obj(
+            QUAD_DATA,
+            nalgebra_glm::vec3(1.0, 1.0, 1.0),
+        )?);
+        world.insert(MeshMgrResource { data: mesh_mgr });
+        world.insert(UIResource::new()?);
+
+        let white = Color::RGBA(255, 255, 255, 255);
+        world
+            .create_entity()
+            .with(QuadComponent::from_text(
+                "Options",
+                &title_font,
+                white,
+                quad_mesh,
+            ))
+            .with(PositionComponent {
+                pos: nalgebra_glm::vec3(0.0, 0.55, 0.0),
+            })
+            .build();
+        for (i, row) in OptionRow::ALL.into_iter().enumerate() {
+            // Back sits a little apart from the options
+            let y = 0.35 - i as f32 * 0.12 - if row == OptionRow::Back { 0.1 } else { 0.0 };
+            world
+                .create_entity()
+                .with(QuadComponent::from_text(
+                    &row.label(&settings),
+                    &font,
+                    white,
+                    quad_mesh,
+                ))
+                .with(PositionComponent {
+                    pos: nalgebra_glm::vec3(0.0, y, 0.0),
+                })
+                .with(FocusableComponent::new(i as i32))
+                .with(ButtonComponent::default())
+                .with(OptionRowComponent { row })
+                .build();
+        }
+        world
+            .create_entity()
+            .with(QuadComponent::from_text(
+                "Click, Enter or A to change, Backspace or B to go back",
+                &font,
+                Color::RGBA(180, 180, 180, 255),
+                quad_mesh,
+            ))
+            .with(PositionComponent {
+                pos: nalgebra_glm::vec3(0.0, -0.7, 0.0),
+            })
+            .build();
+
+        Ok(Self {
+            world,
+            update_dispatcher: update_dispatcher_builder.build(),
+            ui_render_dispatcher: ui_render_dispatcher_builder.build(),
+            settings,
+            font,
+            // The key that opened the menu is still down
+            close_was_down: true,
+        })
+    }
+}
+
+impl Scene for OptionsScene {
+    fn update(&mut self, app: &App) -> SceneCommand {
+        self.world.insert((*app).clone());
+        self.update_dispatcher.dispatch_seq(&self.world);
+
+        let close_down = self
+            .world
+            .read_resource::<InputMap>()
+            .held(app, Action::Options);
+        let mut close = (close_down && !self.close_was_down)
+            || self.world.read_resource::<UiFocusResource>().back;
+        self.close_was_down = close_down;
+
+        let (rows, focusables, mut quads) = self.world.system_data::<(
+            ReadStorage<OptionRowComponent>,
+            ReadStorage<FocusableComponent>,
+            WriteStorage<QuadComponent>,
+        )>();
+        let mut stepped = false;
+        for (option, focusable) in (&rows, &focusables).join() {
+            if !focusable.selected {
+                continue;
+            }
+            if option.row == OptionRow::Back {
+                close = true;
+                continue;
+            }
+            option.row.step(&mut self.settings);
+            stepped = true;
+        }
+        // Every label, since a preset changes the options under it too
+        if stepped {
+            for (option, quad) in (&rows, &mut quads).join() {
+                quad.set_text(
+                    &option.row.label(&self.settings),
+                    &self.font,
+                    Color::RGBA(255, 255, 255, 255),
+                );
+            }
+        }
+
+        if close {
+            if let Err(err) = self.settings.save() {
+                println!("Couldn't save settings: {}", err);
+            }
+            return SceneCommand::Pop;
+        }
+        SceneCommand::None
+    }
+
+    fn render(&mut self, app: &App) {
+        unsafe {
+            gl::Viewport(0, 0, app.screen_width, app.screen_height);
+            gl::ClearColor(0.05, 0.1, 0.15, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        self.ui_render_dispatcher.dispatch_seq(&self.world);
+    }
+
+    fn frees_cursor(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn choices_wrap_around() {
+        assert_eq!(next_choice(&VOLUME_CHOICES, 96), 128);
+        assert_eq!(next_choice(&VOLUME_CHOICES, 128), 0);
+    }
+
+    #[test]
+    fn quality_presets_reset_the_graphics_options() {
+        // Changed from the preset's by hand
+        let mut settings = Settings {
+            graphics: GraphicsSettings {
+                shadow_size: 4096,
+                ..GraphicsSettings::from_preset(QualityPreset::Medium)
+            },
+            ..Default::default()
+        };
+        OptionRow::Quality.step(&mut settings);
+        assert_eq!(settings.graphics.preset, QualityPreset::High);
+        assert_eq!(
+            settings.graphics.shadow_size,
+            GraphicsSettings::from_preset(QualityPreset::High).shadow_size
+        );
+        OptionRow::Quality.step(&mut settings);
+        assert_eq!(settings.graphics.preset, QualityPreset::Low);
+    }
+
+    #[test]
+    fn unknown_values_go_back_to_the_first_choice() {
+        assert_eq!(next_choice(&SHADOW_SIZE_CHOICES, 3000), 512);
+    }
+}
@@ -0,0 +1,209 @@
+// The summary screen, shown at the end of a run, once every treasure map on the island has been found or the player
+// has run out of health. Goes on to a new island, or after a death, back to the same one.
+
+use sdl2::{controller::Button, keyboard::Scancode, pixels::Color};
+use specs::{prelude::*, Dispatcher};
+
+use crate::{
+    engine::{
+        error::EngineError,
+        perlin::NoiseKind,
+        physics::PositionComponent,
+        render3d::{Mesh, MeshMgr, MeshMgrResource},
+        text::{initialize_gui, FontMgr, QuadComponent, UIResource},
+        ui_button::{initialize_ui_buttons, ButtonComponent},
+    },
+    App, Scene, SceneCommand,
+};
+
+use super::{
+    island::{GameSummary, IslandLayout, QUAD_DATA},
+    loading::LoadingScene,
+};
+
+pub struct SummaryScene {
+    world: World,
+    update_dispatcher: Dispatcher<'static, 'static>,
+    ui_render_dispatcher: Dispatcher<'static, 'static>,
+    won: bool,
+    seed: Option<u64>,    //< The island to go back to on retry, after a death
+    layout: IslandLayout, //< Kept for the next island too
+    noise: NoiseKind,     //< Kept for the next island too
+    new_island_button: Entity,
+    retry_button: Option<Entity>,
+    new_island_was_down: bool,
+    retry_was_down: bool,
+}
+
+impl SummaryScene {
+    pub fn new(
+        summary: GameSummary,
+        seed: Option<u64>,
+        layout: IslandLayout,
+        noise: NoiseKind,
+    ) -> Result<Self, EngineError> {
+        let mut world = World::new();
+        world.register::<PositionComponent>();
+        let mut update_dispatcher_builder = DispatcherBuilder::new();
+        initialize_ui_buttons(&mut world, &mut update_dispatcher_builder);
+        let mut ui_render_dispatcher_builder = DispatcherBuilder::new();
+        initialize_gui(&mut world, &mut ui_render_dispatcher_builder);
+
+        let font_mgr = FontMgr::new()?;
+        let title_font = font_mgr.load_font("res/HelveticaNeue Medium.ttf", 48)?;
+        let font = font_mgr.load_font("res/HelveticaNeue Medium.ttf", 24)?;
+
+        let mut mesh_mgr = MeshMgr::new();
+        let quad_mesh = mesh_mgr.add_mesh(Mesh::from_obj(
+            QUAD_DATA,
+            nalgebra_glm::vec3(1.0, 1.0, 1.0),
+        )?);
+        world.insert(MeshMgrResource { data: mesh_mgr });
+        world.insert(UIResource::new()?);
+
+        let title = if summary.won {
+            "You found all the treasure!"
+        } else {
+            "You died"
+        };
+        let minutes = (summary.seconds / 60.0) as u32;
+        let seconds = summary.seconds as u32 % 60;
+        let accuracy = match summary.accuracy {
+            Some(accuracy) => format!("{:.0}%", accuracy * 100.0),
+            None => "-".to_string(),
+        };
+        let lines = [
+            (title.to_string(), &title_font, 0.4),
+            (format!("Time: {}:{:02}", minutes, seconds), &font, 0.2),
+            (
+                format!("Treasure found: {}", summary.maps_found),
+                &font,
+                0.12,
+            ),
+            (
+                format!("Accuracy: {} of {} shots", accuracy, summary.shots_fired),
+                &font,
+                0.04,
+            ),
+            (
+                format!("Mobs defeated: {}", summary.mobs_defeated),
+                &font,
+                -0.04,
+            ),
+            (
+                format!("Gold earned: {}", summary.gold_earned),
+                &font,
+                -0.12,
+            ),
+            (
+                format!("Damage taken: {:.0}%", summary.damage_taken * 100.0),
+                &font,
+                -0.2,
+            ),
+            (
+                format!("Score: {}   Rank: {}", summary.score(), summary.rank()),
+                &title_font,
+                -0.32,
+            ),
+        ];
+        for (text, font, y) in lines {
+            world
+                .create_entity()
+                .with(QuadComponent::from_text(
+                    &text,
+                    font,
+                    Color::RGBA(255, 255, 255, 255),
+                    quad_mesh,
+                ))
+                .with(PositionComponent {
+                    pos: nalgebra_glm::vec3(0.0, y, 0.0),
+                })
+                .build();
+        }
+
+        // The prompts at the bottom can be clicked
+        let mut button = |text: &str, y: f32| {
+            world
+                .create_entity()
+                .with(QuadComponent::from_text(
+                    text,
+                    &font,
+                    Color::RGBA(255, 255, 255, 255),
+                    quad_mesh,
+                ))
+                .with(PositionComponent {
+                    pos: nalgebra_glm::vec3(0.0, y, 0.0),
+                })
+                .with(ButtonComponent::default())
+                .build()
+        };
+        let new_island_button = button("Click or press Enter or A to sail to a new island", -0.46);
+        let retry_button =
+            seed.map(|_| button("Click or press R or X to try this island again", -0.54));
+
+        Ok(Self {
+            world,
+            update_dispatcher: update_dispatcher_builder.build(),
+            ui_render_dispatcher: ui_render_dispatcher_builder.build(),
+            won: summary.won,
+            seed,
+            layout,
+            noise,
+            new_island_button,
+            retry_button,
+            // So that a key held down when the run ended doesn't skip straight past the screen
+            new_island_was_down: true,
+            retry_was_down: true,
+        })
+    }
+
+    fn clicked(&self, button: Option<Entity>) -> bool {
+        let buttons = self.world.read_storage::<ButtonComponent>();
+        button
+            .and_then(|button| buttons.get(button))
+            .is_some_and(|button| button.clicked)
+    }
+}
+
+impl Scene for SummaryScene {
+    fn update(&mut self, app: &App) -> SceneCommand {
+        self.world.insert((*app).clone());
+        self.update_dispatcher.dispatch_seq(&self.world);
+
+        let new_island_down = app.keys[Scancode::Return as usize] || app.button(Button::A);
+        let new_island_pressed = new_island_down && !self.new_island_was_down;
+        self.new_island_was_down = new_island_down;
+        if new_island_pressed || self.clicked(Some(self.new_island_button)) {
+            return SceneCommand::reset(LoadingScene::new(None, self.layout, self.noise));
+        }
+
+        let retry_down = app.keys[Scancode::R as usize] || app.button(Button::X);
+        let retry_pressed = retry_down && !self.retry_was_down;
+        self.retry_was_down = retry_down;
+        if let Some(seed) = self.seed {
+            if retry_pressed || self.clicked(self.retry_button) {
+                // Start over on the same island, from scratch
+                return SceneCommand::reset(LoadingScene::new(Some(seed), self.layout, self.noise));
+            }
+        }
+
+        SceneCommand::None
+    }
+
+    fn render(&mut self, app: &App) {
+        unsafe {
+            gl::Viewport(0, 0, app.screen_width, app.screen_height);
+            if self.won {
+                gl::ClearColor(0.05, 0.2, 0.25, 1.0);
+            } else {
+                gl::ClearColor(0.25, 0.02, 0.02, 1.0);
+            }
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        self.ui_render_dispatcher.dispatch_seq(&self.world);
+    }
+
+    fn frees_cursor(&self) -> bool {
+        true
+    }
+}
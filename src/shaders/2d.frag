@@ -1,6 +1,8 @@
 #version 330 core
 
 uniform float u_opacity;
+uniform vec3 u_tint;
+uniform float u_rotation; // Counter-clockwise, in radians
 uniform sampler2D texture0;
 
 in vec3 texCoord;
@@ -9,8 +11,13 @@ out vec4 Color;
 
 void main()
 {
-    vec4 texture_color = texture(texture0, texCoord.xy);
+    // Rotate around the middle of the quad. The texture's v axis points down, so this turns the image counter-clockwise
+    vec2 centered = texCoord.xy - 0.5;
+    float c = cos(u_rotation);
+    float s = sin(u_rotation);
+    vec2 uv = vec2(c * centered.x - s * centered.y, s * centered.x + c * centered.y) + 0.5;
+    vec4 texture_color = texture(texture0, uv);
     float texture_alpha = texture_color.w * u_opacity;
 
-    Color = vec4(texture_color.xyz, texture_alpha);
+    Color = vec4(texture_color.xyz * u_tint, texture_alpha);
 }
\ No newline at end of file
@@ -5,27 +5,40 @@ in vec3 color;
 in vec3 Normal_cameraspace;
 in vec3 LightDirection_cameraspace;
 in vec4 light_space_pos; // For shadow mapping
+in float view_distance; // For fog
+in vec3 world_pos; // For caustics
+in float occlusion; // Baked into the terrain, 0 for everything else
 
 out vec4 Color;
 
 uniform sampler2D texture0;
 uniform sampler2D shadow_map;
-
-vec2 poissonDisk[9] = vec2[](
-  vec2( -1.0,  1.0 ),
-  vec2(  0.0,  1.0 ),
-  vec2(  1.0,  1.0 ),
-  vec2( -1.0,  0.0 ),
-  vec2(  0.0,  0.0 ),
-  vec2(  1.0,  0.0 ),
-  vec2( -1.0, -1.0 ),
-  vec2(  0.0, -1.0 ),
-  vec2(  1.0, -1.0 )
-);
-
-// x x x
-// x   x
-// x x x
+uniform int u_pcf_radius; // Percentage-closer filtering kernel radius, 0 is hard shadows
+uniform vec3 u_fog_color;
+uniform float u_fog_density; // 0 is no fog
+uniform float u_fog_start; // Haze starts thickening this far from the camera
+uniform float u_fog_end; // and hides everything past here, the furthest anything is drawn
+uniform float u_cull_dist; // How far this mesh is drawn, 0 for always
+uniform float u_water_level;
+uniform float u_caustics_strength; // Follows the sun, 0 at night
+uniform vec3 u_moon_dir;
+uniform vec3 u_moon_color; // 0 while the moon is down
+uniform float u_time;
+uniform vec3 u_tint; // Multiplied with the material color
+uniform int u_overlay; // Debug view drawn over terrain: 0 none, 1 grid, 2 slope, 3 moisture
+uniform sampler2D u_moisture; // For the moisture overlay, looked up by tile
+uniform float u_map_width;
+uniform float u_chunk_size;
+uniform int u_splat; // 1 for terrain, which blends textures by weights instead of using texture0
+uniform sampler2D u_splat_weights; // Sand, grass and rock, looked up by tile
+uniform sampler2D u_sand;
+uniform sampler2D u_grass; // Grey, tinted by the vertex color
+uniform sampler2D u_rock;
+uniform float u_splat_repeats; // Times the textures repeat across a tile
+uniform int u_light_count; // Point lights, like torches, nearest the camera first
+uniform vec3 u_light_pos[8];
+uniform vec3 u_light_color[8];
+uniform float u_light_radius[8]; // Where each light fades out to nothing
 
 float calc_shadow_factor()
 {
@@ -36,24 +49,103 @@ float calc_shadow_factor()
     float z = 0.5 * proj_coords.z + 0.5;
 
     float bias = 0.0000;
-    float visibility = 1.0;
-
-    for (int i=0; i<9; i++){
-        float depth = texture(shadow_map, uv_coords + poissonDisk[i] / 7000.0).x;
+    vec2 texel_size = 1.0 / vec2(textureSize(shadow_map, 0));
 
-        if (depth + bias < z) {
-            visibility -= 0.111;
+    // Average the depth test over a (2r+1)x(2r+1) square of neighboring texels
+    float lit = 0.0;
+    float samples = 0.0;
+    for (int x = -u_pcf_radius; x <= u_pcf_radius; x++) {
+        for (int y = -u_pcf_radius; y <= u_pcf_radius; y++) {
+            float depth = texture(shadow_map, uv_coords + vec2(x, y) * texel_size).x;
+            if (depth + bias >= z) {
+                lit += 1.0;
+            }
+            samples += 1.0;
         }
     }
 
-    return visibility;
+    return lit / samples;
+}
+
+// Bright wavy lines of light focused by the waves above. Returns [0, 1].
+float caustics(vec2 p)
+{
+    // About one cell per meter
+    p *= 20.0;
+    float t = 0.8 * u_time;
+    float c = 0.0;
+    for (int i = 0; i < 3; i++) {
+        p += 0.7 * vec2(sin(1.7 * p.y + t), cos(1.3 * p.x - 0.9 * t));
+        c += abs(sin(p.x) * sin(p.y));
+    }
+    return pow(1.0 - c / 3.0, 5.0);
+}
+
+// Light from the point lights reaching this fragment, unshadowed
+vec3 point_lights(vec3 n)
+{
+    vec3 light = vec3(0.0);
+    for (int i = 0; i < u_light_count; i++) {
+        vec3 to_light = u_light_pos[i] - world_pos;
+        float dist = length(to_light);
+        float falloff = 1.0 - clamp(dist / u_light_radius[i], 0.0, 1.0);
+        float cos_theta = clamp(dot(n, to_light / max(dist, 0.0001)), 0.0, 1.0);
+        light += u_light_color[i] * cos_theta * falloff * falloff;
+    }
+    return light;
+}
+
+// The terrain's color, sand, grass and rock blended by how much of each the ground has
+vec3 splat_color()
+{
+    vec3 weights = texture(u_splat_weights, (world_pos.xy + 0.5) / u_map_width).rgb;
+    weights /= max(weights.r + weights.g + weights.b, 0.0001);
+    vec2 uv = texCoord.xy * u_splat_repeats;
+    return weights.r * texture(u_sand, uv).rgb
+        + weights.g * 2.0 * color * texture(u_grass, uv).rgb
+        + weights.b * texture(u_rock, uv).rgb;
+}
+
+// How close p is to a grid line every `spacing`, 1 on the line fading to 0 about `width` pixels away
+float grid_line(vec2 p, float spacing, float width)
+{
+    vec2 cell = p / spacing;
+    vec2 to_line = abs(fract(cell - 0.5) - 0.5) / fwidth(cell);
+    return 1.0 - clamp(min(to_line.x, to_line.y) / width, 0.0, 1.0);
+}
+
+// Paints the debug overlay over the terrain's lit color
+vec3 debug_overlay(vec3 lit_color)
+{
+    if (u_overlay == 1) {
+        vec3 color = mix(lit_color, vec3(0.05), 0.6 * grid_line(world_pos.xy, 1.0, 1.0));
+        return mix(color, vec3(1.0, 0.2, 0.1), grid_line(world_pos.xy, u_chunk_size, 2.0));
+    }
+    if (u_overlay == 2) {
+        // The face's own normal, so the heat map shows the slope of each triangle
+        vec3 face_normal = normalize(cross(dFdx(world_pos), dFdy(world_pos)));
+        float slope = clamp(2.0 * (1.0 - abs(face_normal.z)), 0.0, 1.0);
+        vec3 heat = slope < 0.5
+            ? mix(vec3(0.1, 0.3, 1.0), vec3(0.1, 0.9, 0.2), 2.0 * slope)
+            : mix(vec3(0.1, 0.9, 0.2), vec3(1.0, 0.1, 0.1), 2.0 * slope - 1.0);
+        return mix(lit_color, heat, 0.7);
+    }
+    if (u_overlay == 3) {
+        float moisture = texture(u_moisture, (world_pos.xy + 0.5) / u_map_width).r;
+        vec3 wetness = mix(vec3(0.9, 0.8, 0.5), vec3(0.0, 0.3, 1.0), moisture);
+        return mix(lit_color, wetness, 0.7);
+    }
+    return lit_color;
 }
 
 void main()
 {
     vec4 texture_color = texture(texture0, texCoord.xy) * vec4(color, 1.0);
     float texture_alpha = texture_color.w;
-    vec3 material_color = texture_color.xyz;
+    vec3 material_color = texture_color.xyz * u_tint;
+    if (u_splat == 1) {
+        material_color = splat_color() * u_tint;
+    }
     vec3 ambient_color = vec3(0.8, 0.9, 1.0);
 
     vec3 LightColor = vec3(1.0, 1.0, 1.0);
@@ -70,5 +162,37 @@ void main()
 
     float shadow_factor = calc_shadow_factor();
 
-    Color = vec4(0.2 * ambient_color * material_color + shadow_factor * material_color * LightColor * cosTheta, texture_alpha);
+    // Valleys and hollows see less of the sky, so get less of its light, and a little less of the sun's
+    float ambient_visibility = 1.0 - clamp(4.0 * occlusion, 0.0, 0.85);
+    float direct_visibility = mix(1.0, ambient_visibility, 0.4);
+
+    vec3 lit_color = 0.2 * ambient_visibility * ambient_color * material_color
+        + direct_visibility * shadow_factor * material_color * LightColor * cosTheta;
+
+    // Moonlight, the shadow map follows the moon at night
+    float moon_cos_theta = clamp(dot(n, normalize(u_moon_dir)), 0, 1);
+    lit_color += shadow_factor * material_color * u_moon_color * moon_cos_theta;
+
+    // Torches and the like
+    lit_color += material_color * point_lights(n);
+
+    // Caustics on things under the water, strongest in the shallows
+    float depth = u_water_level - world_pos.z;
+    if (depth > 0.0 && u_caustics_strength > 0.0) {
+        float depth_fade = smoothstep(0.0, 0.005, depth) * exp(-20.0 * depth);
+        lit_color += 1.5 * u_caustics_strength * depth_fade * shadow_factor * caustics(world_pos.xy) * material_color;
+    }
+
+    lit_color = debug_overlay(lit_color);
+
+    // Exponential fog, things fade into the fog color the further away they are
+    float fog_factor = 1.0 - exp(-u_fog_density * view_distance);
+    // Distance haze, so that the edge of the world fades out instead of ending
+    fog_factor = max(fog_factor, smoothstep(u_fog_start, u_fog_end, view_distance));
+    // Things drawn less far than the terrain fade out before they're culled, instead of popping
+    if (u_cull_dist > 0.0) {
+        fog_factor = max(fog_factor, smoothstep(0.75 * u_cull_dist, u_cull_dist, view_distance));
+    }
+
+    Color = vec4(mix(lit_color, u_fog_color, fog_factor), texture_alpha);
 }
\ No newline at end of file
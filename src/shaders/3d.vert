@@ -17,6 +17,9 @@ out vec3 color;
 out vec3 Normal_cameraspace;
 out vec3 LightDirection_cameraspace;
 out vec4 light_space_pos; // For shadow mapping
+out float view_distance; // For fog
+out vec3 world_pos; // For caustics
+out float occlusion; // Baked into the terrain, 0 for everything else
 
 void main()
 {
@@ -36,6 +39,9 @@ void main()
 
     gl_Position = uv;
     texCoord = texture_coord;
+    occlusion = texture_coord.z;
     color = Color;
     light_space_pos = light_mvp * vec4(Position, 1.0); // For shadow mapping
+    view_distance = length((u_view_matrix * u_model_matrix * vec4(Position, 1.0)).xyz);
+    world_pos = (u_model_matrix * vec4(Position, 1.0)).xyz;
 }
\ No newline at end of file
@@ -0,0 +1,18 @@
+#version 330 core
+
+in vec3 world_pos;
+
+out vec4 Color;
+
+// Roughly as much work per fragment as lighting with a 5x5 shadow kernel
+void main()
+{
+    vec3 n = normalize(cross(dFdx(world_pos), dFdy(world_pos)));
+    float lit = 0.0;
+    for (int x = -2; x <= 2; x++) {
+        for (int y = -2; y <= 2; y++) {
+            lit += max(dot(n, normalize(vec3(x, y, 3.0))), 0.0);
+        }
+    }
+    Color = vec4(vec3(lit / 25.0), 1.0);
+}
@@ -0,0 +1,18 @@
+#version 330 core
+
+uniform mat4 u_model_matrix;
+uniform mat4 u_view_matrix;
+uniform mat4 u_proj_matrix;
+
+layout (location = 0) in vec3 Position;
+
+out vec3 world_pos;
+
+void main()
+{
+    vec4 world = u_model_matrix * vec4(Position, 1.0);
+    // Bumpy surface, so that there's plenty of overdraw
+    world.z += 0.05 * sin(40.0 * world.x) * cos(40.0 * world.y);
+    gl_Position = u_proj_matrix * u_view_matrix * world;
+    world_pos = world.xyz;
+}
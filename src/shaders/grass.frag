@@ -0,0 +1,45 @@
+#version 330 core
+
+in vec2 corner; // x is [-0.5, 0.5] across, y is [0, 1] up
+in vec3 color;
+in float flower;
+in float view_distance;
+
+out vec4 Color;
+
+uniform vec3 u_sun_dir;
+uniform vec3 u_sun_color;
+uniform vec3 u_moon_color;
+uniform vec3 u_fog_color;
+uniform float u_fog_density;
+
+// Whether a blade leaning from `base` covers the spot, blades narrow to a point at the top
+bool blade(float base, float lean, float top)
+{
+    float x = base + lean * corner.y;
+    float half_width = 0.09 * (1.0 - corner.y / top);
+    return corner.y < top && abs(corner.x - x) < half_width;
+}
+
+void main()
+{
+    // Alpha cutout, a few blades to a tuft, and a round head on flowers
+    bool covered = blade(-0.2, -0.2, 0.8) || blade(0.0, 0.05, 1.0) || blade(0.2, 0.2, 0.7);
+    vec3 material_color = color;
+    if (flower > 0.5) {
+        bool head = length(corner - vec2(0.05, 0.9)) < 0.12;
+        covered = head || blade(0.0, 0.05, 0.9);
+        material_color = head ? color : vec3(0.25, 0.4, 0.15);
+    }
+    if (!covered) {
+        discard;
+    }
+
+    // Darker at the roots, where the tuft shades itself
+    material_color *= mix(0.55, 1.0, corner.y);
+    vec3 light = vec3(0.2) + u_sun_color * clamp(u_sun_dir.z, 0.0, 1.0) + u_moon_color;
+    vec3 lit_color = material_color * light;
+
+    float fog_factor = 1.0 - exp(-u_fog_density * view_distance);
+    Color = vec4(mix(lit_color, u_fog_color, fog_factor), 1.0);
+}
@@ -0,0 +1,47 @@
+#version 330 core
+
+uniform vec2 u_resolution;
+uniform mat4 u_view_matrix;
+uniform mat4 u_proj_matrix;
+uniform vec3 u_camera_pos;
+uniform float u_fade_distance; // Tufts shrink away to nothing by this far from the camera
+uniform float u_time;
+
+layout (location = 0) in vec2 Corner;
+layout (location = 1) in vec4 PositionHeight; // Per tuft
+layout (location = 2) in vec4 ColorFlower; // Per tuft, w is 1 for flowers
+
+out vec2 corner;
+out vec3 color;
+out float flower;
+out float view_distance; // For fog
+
+void main()
+{
+    // Turned about the up axis to face the camera, so tufts stay standing
+    vec3 right = normalize(vec3(u_view_matrix[0][0], u_view_matrix[1][0], 0.0) + vec3(1e-5, 0.0, 0.0));
+    float dist = length(PositionHeight.xyz - u_camera_pos);
+    float height = PositionHeight.w * (1.0 - smoothstep(0.7 * u_fade_distance, u_fade_distance, dist));
+
+    // The wind pushes the tops of tufts back and forth, each a little out of step with its neighbors
+    float phase = dot(PositionHeight.xy, vec2(40.0, 27.0));
+    float gust = sin(1.7 * u_time + phase) + 0.4 * sin(3.1 * u_time + 1.3 * phase);
+    float sway = Corner.y * Corner.y * height * 0.3 * gust;
+
+    vec3 world = PositionHeight.xyz
+        + Corner.x * height * right
+        + vec3(sway, 0.4 * sway, Corner.y * height);
+    vec4 uv = u_proj_matrix * u_view_matrix * vec4(world, 1.0);
+
+    if (u_resolution.x > u_resolution.y) {
+        uv.x *= u_resolution.y / u_resolution.x;
+    } else {
+        uv.y *= u_resolution.x / u_resolution.y;
+    }
+
+    gl_Position = uv;
+    corner = Corner;
+    color = ColorFlower.rgb;
+    flower = ColorFlower.w;
+    view_distance = length((u_view_matrix * vec4(world, 1.0)).xyz);
+}
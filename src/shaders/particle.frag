@@ -0,0 +1,16 @@
+#version 330 core
+
+in vec2 corner;
+in vec4 color;
+
+out vec4 Color;
+
+void main()
+{
+    // Soft round puff instead of a square
+    float falloff = 1.0 - smoothstep(0.3, 0.5, length(corner));
+    if (falloff <= 0.0) {
+        discard;
+    }
+    Color = vec4(color.rgb, color.a * falloff);
+}
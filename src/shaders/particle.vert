@@ -0,0 +1,31 @@
+#version 330 core
+
+uniform vec2 u_resolution;
+uniform mat4 u_view_matrix;
+uniform mat4 u_proj_matrix;
+
+layout (location = 0) in vec2 Corner;
+layout (location = 1) in vec4 PositionSize; // Per particle
+layout (location = 2) in vec4 ParticleColor; // Per particle
+
+out vec2 corner;
+out vec4 color;
+
+void main()
+{
+    // Stretch the quad along the camera's right and up vectors, so it always faces the camera
+    vec3 right = vec3(u_view_matrix[0][0], u_view_matrix[1][0], u_view_matrix[2][0]);
+    vec3 up = vec3(u_view_matrix[0][1], u_view_matrix[1][1], u_view_matrix[2][1]);
+    vec3 world = PositionSize.xyz + PositionSize.w * (Corner.x * right + Corner.y * up);
+    vec4 uv = u_proj_matrix * u_view_matrix * vec4(world, 1.0);
+
+    if (u_resolution.x > u_resolution.y) {
+        uv.x *= u_resolution.y / u_resolution.x;
+    } else {
+        uv.y *= u_resolution.x / u_resolution.y;
+    }
+
+    gl_Position = uv;
+    corner = Corner;
+    color = ParticleColor;
+}
@@ -0,0 +1,65 @@
+#version 330 core
+
+uniform vec3 u_camera_pos;
+uniform vec3 u_zenith_color;
+uniform vec3 u_horizon_color;
+uniform vec3 u_sun_dir;
+uniform vec3 u_sun_color;
+uniform vec3 u_moon_dir;
+uniform vec3 u_moon_color;
+uniform float u_star_brightness;
+uniform float u_star_rotation;
+
+in vec3 world_pos;
+
+out vec4 Color;
+
+float hash(vec3 p)
+{
+    return fract(sin(dot(p, vec3(127.1, 311.7, 74.7))) * 43758.5453);
+}
+
+// Sparse points of light, fixed to the sky as it turns
+float stars(vec3 dir)
+{
+    float c = cos(u_star_rotation);
+    float s = sin(u_star_rotation);
+    dir = vec3(dir.x, c * dir.y - s * dir.z, s * dir.y + c * dir.z);
+
+    vec3 cell = floor(dir * 150.0);
+    float h = hash(cell);
+    if (h < 0.985) {
+        return 0.0;
+    }
+    // Fade each star towards the edge of its cell, so they look round
+    vec3 center = cell + 0.5 + 0.3 * (vec3(hash(cell + 1.0), hash(cell + 2.0), hash(cell + 3.0)) - 0.5);
+    float d = length(dir * 150.0 - center);
+    return (h - 0.985) / 0.015 * smoothstep(0.35, 0.0, d);
+}
+
+void main()
+{
+    vec3 dir = normalize(world_pos - u_camera_pos);
+
+    // Horizon gradient, the area below the horizon is only ever seen over the sea
+    float up = clamp(dir.z, 0.0, 1.0);
+    vec3 color = mix(u_horizon_color, u_zenith_color, pow(up, 0.5));
+
+    // Sun disc with a soft glow around it
+    vec3 sun = normalize(u_sun_dir);
+    float sun_dot = max(dot(dir, sun), 0.0);
+    float disc = smoothstep(0.9990, 0.9995, sun_dot);
+    float glow = 0.25 * pow(sun_dot, 64.0);
+    color += u_sun_color * (disc + glow);
+
+    // Moon disc, a little larger and much softer than the sun
+    float moon_dot = max(dot(dir, normalize(u_moon_dir)), 0.0);
+    float moon_disc = smoothstep(0.9985, 0.9992, moon_dot);
+    float moon_glow = 0.1 * pow(moon_dot, 32.0);
+    color += u_moon_color * (3.0 * moon_disc + moon_glow);
+
+    // Stars fade in at night, and are hidden near the horizon by the haze
+    color += vec3(u_star_brightness * stars(dir) * smoothstep(0.0, 0.2, dir.z));
+
+    Color = vec4(color, 1.0);
+}
@@ -0,0 +1,25 @@
+#version 330 core
+
+uniform vec2 u_resolution;
+uniform mat4 u_model_matrix;
+uniform mat4 u_view_matrix;
+uniform mat4 u_proj_matrix;
+
+layout (location = 0) in vec3 Position;
+
+out vec3 world_pos;
+
+void main()
+{
+    vec4 world = u_model_matrix * vec4(Position, 1.0);
+    vec4 uv = u_proj_matrix * u_view_matrix * world;
+
+    if (u_resolution.x > u_resolution.y) {
+        uv.x *= u_resolution.y / u_resolution.x;
+    } else {
+        uv.y *= u_resolution.x / u_resolution.y;
+    }
+
+    gl_Position = uv;
+    world_pos = world.xyz;
+}
@@ -0,0 +1,47 @@
+#version 330 core
+
+uniform vec3 u_camera_pos;
+uniform vec3 u_water_color;
+uniform vec3 u_sky_color;
+uniform vec3 u_sun_dir;
+uniform int u_underwater;
+
+in vec3 world_pos;
+in vec3 normal;
+
+out vec4 Color;
+
+void main()
+{
+    vec3 n = normalize(normal);
+    if (u_underwater != 0) {
+        n = -n;
+    }
+    vec3 to_eye = normalize(u_camera_pos - world_pos);
+
+    // Schlick's approximation, water reflects more the closer to the horizon it's viewed
+    float fresnel = 0.02 + 0.98 * pow(1.0 - clamp(dot(n, to_eye), 0.0, 1.0), 5.0);
+
+    // Planar reflection of the sky, brighter towards the horizon
+    vec3 reflected = reflect(-to_eye, n);
+    vec3 sky_reflection = mix(u_sky_color * 1.15, u_sky_color, clamp(reflected.z, 0.0, 1.0));
+
+    // Sun glint, only while the sun is up
+    float sun_up = clamp(u_sun_dir.z * 4.0, 0.0, 1.0);
+    float glint = sun_up * pow(max(dot(reflected, normalize(u_sun_dir)), 0.0), 200.0);
+
+    // Refraction: the terrain below shows through where the surface isn't reflecting much
+    vec3 color;
+    float alpha;
+    if (u_underwater != 0) {
+        // From below, total internal reflection makes the surface look like more water at glancing angles
+        color = mix(u_sky_color, u_water_color, fresnel);
+        alpha = mix(0.5, 0.95, fresnel);
+    } else {
+        float daylight = clamp(dot(u_sky_color, vec3(0.333)) * 1.5, 0.05, 1.0);
+        color = mix(u_water_color * daylight, sky_reflection, fresnel) + vec3(glint);
+        alpha = mix(0.7, 1.0, fresnel);
+    }
+
+    Color = vec4(color, alpha);
+}
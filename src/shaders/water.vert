@@ -0,0 +1,53 @@
+#version 330 core
+
+uniform vec2 u_resolution;
+uniform mat4 u_model_matrix;
+uniform mat4 u_view_matrix;
+uniform mat4 u_proj_matrix;
+uniform vec3 u_camera_pos;
+uniform float u_time;
+uniform float u_wave_amplitude;
+uniform float u_wave_length;
+uniform float u_wave_speed;
+
+layout (location = 0) in vec3 Position;
+
+out vec3 world_pos;
+out vec3 normal;
+
+// Adds a directional sine wave to the height, and its slope to the gradient
+void add_wave(vec2 p, vec2 dir, float amplitude, float wave_length, inout float height, inout vec2 gradient)
+{
+    float k = 6.2832 / wave_length;
+    float phase = k * (dot(dir, p) - u_wave_speed * u_time);
+    height += amplitude * sin(phase);
+    gradient += amplitude * k * cos(phase) * dir;
+}
+
+void main()
+{
+    vec4 world = u_model_matrix * vec4(Position, 1.0);
+
+    // Fade the waves out in the distance, where the grid is too coarse to show them
+    float fade = clamp(1.0 - length(world.xy - u_camera_pos.xy) / 20.0, 0.0, 1.0);
+
+    float height = 0.0;
+    vec2 gradient = vec2(0.0);
+    add_wave(world.xy, normalize(vec2(1.0, 0.3)), u_wave_amplitude, u_wave_length, height, gradient);
+    add_wave(world.xy, normalize(vec2(-0.4, 1.0)), 0.6 * u_wave_amplitude, 0.63 * u_wave_length, height, gradient);
+    add_wave(world.xy, normalize(vec2(0.7, -0.8)), 0.3 * u_wave_amplitude, 0.37 * u_wave_length, height, gradient);
+    world.z += fade * height;
+    gradient *= fade;
+
+    vec4 uv = u_proj_matrix * u_view_matrix * world;
+
+    if (u_resolution.x > u_resolution.y) {
+        uv.x *= u_resolution.y / u_resolution.x;
+    } else {
+        uv.y *= u_resolution.x / u_resolution.y;
+    }
+
+    gl_Position = uv;
+    world_pos = world.xyz;
+    normal = normalize(vec3(-gradient, 1.0));
+}